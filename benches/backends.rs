@@ -0,0 +1,57 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use chip_8_rs::backend::{ExecutionBackend, Interpreter};
+use chip_8_rs::blocks::BlockTranslator;
+use chip_8_rs::cached::CachedInterpreter;
+use chip_8_rs::{splash, Chip8};
+
+// Frames run per iteration, at an uncapped fast-forward speed.
+const FRAMES: usize = 60;
+const CYCLES_PER_FRAME: usize = 1000;
+
+// A busy loop of arithmetic, drawing whenever V0 wraps around:
+// LD V0, 0; LD V1, 0; ADD V0, 1; ADD V1, V0; XOR V2, V0; LD I, 0x300;
+// SE V0, 0; JP 0x204; DRW V0, V1, 5; JP 0x204
+const BUSY_LOOP: &[u8] = &[
+    0x60, 0x00, 0x61, 0x00, 0x70, 0x01, 0x81, 0x04, 0x82, 0x03, 0xA3, 0x00, 0x30, 0x00, 0x12, 0x04,
+    0xD0, 0x15, 0x12, 0x04,
+];
+
+type NewBackend = fn() -> Box<dyn ExecutionBackend>;
+
+fn bench_rom(c: &mut Criterion, name: &str, rom: &[u8]) {
+    let backends: [(&str, NewBackend); 3] = [
+        ("interpreter", || Box::new(Interpreter)),
+        ("cached", || Box::new(CachedInterpreter::new())),
+        ("blocks", || Box::new(BlockTranslator::new())),
+    ];
+    let mut group = c.benchmark_group(name);
+    for (backend_name, backend) in backends {
+        group.bench_function(backend_name, |b| {
+            b.iter_batched(
+                || {
+                    let mut chip8 = Chip8::with_backend(backend());
+                    chip8.set_seed(0);
+                    chip8.load_rom(rom).unwrap();
+                    chip8
+                },
+                |mut chip8| {
+                    for _ in 0..FRAMES {
+                        chip8.run_frame(CYCLES_PER_FRAME);
+                    }
+                    chip8
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn backends(c: &mut Criterion) {
+    bench_rom(c, "busy_loop", BUSY_LOOP);
+    bench_rom(c, "splash", splash::ROM);
+}
+
+criterion_group!(benches, backends);
+criterion_main!(benches);
@@ -0,0 +1,49 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+use chip_8_rs::backend::{ExecutionBackend, Interpreter};
+use chip_8_rs::bench::{CYCLES_PER_FRAME, WORKLOADS};
+use chip_8_rs::blocks::BlockTranslator;
+use chip_8_rs::cached::CachedInterpreter;
+use chip_8_rs::Chip8;
+
+// Frames run per iteration.
+const FRAMES: usize = 10;
+
+type NewBackend = fn() -> Box<dyn ExecutionBackend>;
+
+// Instructions per second of every workload of `chip8 bench`, with and
+// without the cache of decoded instructions.
+fn workloads(c: &mut Criterion) {
+    let backends: [(&str, NewBackend); 3] = [
+        ("interpreter", || Box::new(Interpreter)),
+        ("cached", || Box::new(CachedInterpreter::new())),
+        ("blocks", || Box::new(BlockTranslator::new())),
+    ];
+    for workload in WORKLOADS {
+        let rom = workload.rom();
+        let mut group = c.benchmark_group(workload.name);
+        group.throughput(Throughput::Elements((FRAMES * CYCLES_PER_FRAME) as u64));
+        for (backend_name, backend) in backends {
+            group.bench_function(backend_name, |b| {
+                b.iter_batched(
+                    || {
+                        let mut chip8 = Chip8::with_backend(backend());
+                        chip8.load_rom(&rom).unwrap();
+                        chip8
+                    },
+                    |mut chip8| {
+                        for _ in 0..FRAMES {
+                            chip8.run_frame(CYCLES_PER_FRAME);
+                        }
+                        chip8
+                    },
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, workloads);
+criterion_main!(benches);
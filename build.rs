@@ -0,0 +1,28 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// The assembler has no dependencies on the rest of the crate, so the build
+// script compiles it too.
+#[allow(dead_code)]
+#[path = "src/asm.rs"]
+mod asm;
+
+// ROMs of the `corpus` module, assembled from `tests/roms/<name>.s` into
+// `$OUT_DIR/roms/<name>.ch8`.
+const CORPUS: [&str; 5] = ["bcd", "counter", "quirks", "selfmod", "timer"];
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/asm.rs");
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("roms");
+    fs::create_dir_all(&out).unwrap();
+    for name in CORPUS {
+        let source = format!("tests/roms/{}.s", name);
+        let assembly = asm::assemble_file(Path::new(&source))
+            .unwrap_or_else(|e| panic!("Failed to assemble {}: {}", source, e));
+        for file in &assembly.files {
+            println!("cargo:rerun-if-changed={}", file);
+        }
+        fs::write(out.join(format!("{}.ch8", name)), &assembly.rom).unwrap();
+    }
+}
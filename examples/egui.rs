@@ -0,0 +1,435 @@
+//! A minimal egui frontend driving the emulator through an `EmulatorHandle`,
+//! so the GUI thread never waits for the machine:
+//!
+//! ```sh
+//! cargo run --example egui --features egui -- game.ch8
+//! ```
+//!
+//! The keypad is on 1234/QWER/ASDF/ZXCV, Space pauses and F11 resets. F2
+//! shows an oscilloscope of the buzzer, the beep or the XO-CHIP audio
+//! pattern the program loaded. F3 opens a sprite editor on the sprites found
+//! in the ROM, or on new ones, which copies them as assembler `db` lines or
+//! writes them into memory to preview them in the running program. F4
+//! shows the keypad, with the keys held lit, outlined while the program
+//! waits for a key in Fx0A.
+
+use std::time::{Duration, Instant};
+use std::{env, fs, process};
+
+use chip_8_rs::audio::{AudioConfig, Mixer, Voice};
+use chip_8_rs::handle::{Command, EmulatorHandle, Event, HandleConfig};
+use chip_8_rs::keyboard::{KeyMap, KeypadView};
+use chip_8_rs::pacing::FramePacer;
+use chip_8_rs::palette::Palette;
+use chip_8_rs::scope::Oscilloscope;
+use chip_8_rs::sprites::{self, Sprite};
+use chip_8_rs::{splash, Chip8};
+use eframe::egui;
+
+// Window pixels per CHIP-8 pixel at the start.
+const SCALE: f32 = 10.0;
+
+// Samples shown by the oscilloscope at a time, about 12ms.
+const SCOPE_WINDOW: usize = 512;
+
+// Height of the oscilloscope panel, in window pixels.
+const SCOPE_HEIGHT: f32 = 64.0;
+
+// Window pixels per sprite pixel in the sprite editor.
+const CELL: f32 = 16.0;
+
+// Window pixels per key of the keypad panel, and between keys.
+const KEY_SIZE: f32 = 28.0;
+const KEY_SPACING: f32 = 4.0;
+
+struct App {
+    handle: EmulatorHandle,
+    keymap: KeyMap,
+    palette: Palette,
+    screen: Option<egui::TextureHandle>,
+    paused: bool,
+    beeping: bool,
+    status: String,
+
+    // The buzzer rendered as the worker plays it, for the oscilloscope
+    mixer: Mixer,
+    scope: Oscilloscope,
+    pacer: FramePacer,
+    rendered: Instant,
+    show_scope: bool,
+
+    // The keypad as the worker last reported it
+    keypad: KeypadView,
+    show_keypad: bool,
+
+    editor: SpriteEditor,
+}
+
+// The sprite editor window, on one sprite at a time.
+struct SpriteEditor {
+    open: bool,
+    found: Vec<Sprite>,
+    sprite: Sprite,
+
+    // Height of new 8 pixel wide sprites
+    height: usize,
+
+    // Write every edit into memory as it is made
+    live: bool,
+}
+
+impl App {
+    fn new(context: &egui::Context, rom: Vec<u8>) -> App {
+        let waker = context.clone();
+        let handle =
+            EmulatorHandle::spawn_with_waker(HandleConfig::default(), Chip8::new, move || {
+                waker.request_repaint()
+            });
+        let editor = SpriteEditor::new(sprites::scan(&rom, 0x200));
+        handle.send(Command::Load(rom));
+        App {
+            handle,
+            keymap: KeyMap::standard(),
+            palette: Palette::default(),
+            screen: None,
+            paused: false,
+            beeping: false,
+            status: String::new(),
+            mixer: Mixer::new(AudioConfig::default()),
+            scope: Oscilloscope::new(SCOPE_WINDOW),
+            pacer: FramePacer::new(60),
+            rendered: Instant::now(),
+            show_scope: false,
+            keypad: KeypadView::new([false; 16], false),
+            show_keypad: false,
+            editor,
+        }
+    }
+
+    fn handle_events(&mut self, context: &egui::Context) {
+        for event in self.handle.events() {
+            match event {
+                Event::Frame { display, .. } => {
+                    let image = egui::ColorImage::from_rgba_unmultiplied(
+                        [display.width(), display.height()],
+                        &self.palette.rgba(&display),
+                    );
+                    match &mut self.screen {
+                        Some(screen) => screen.set(image, egui::TextureOptions::NEAREST),
+                        None => {
+                            self.screen = Some(context.load_texture(
+                                "screen",
+                                image,
+                                egui::TextureOptions::NEAREST,
+                            ))
+                        }
+                    }
+                }
+                Event::Beep(on) => self.beeping = on,
+                Event::Voice(voice) => self.mixer.set_voice(voice),
+                Event::Keypad(keypad) => self.keypad = keypad,
+                Event::Error(message) => self.status = message,
+                Event::Exited => self.status = "The program exited".to_string(),
+            }
+        }
+    }
+
+    fn handle_keys(&mut self, context: &egui::Context) {
+        let keys: Vec<(egui::Key, bool)> = context.input(|input| {
+            input
+                .events
+                .iter()
+                .filter_map(|event| match *event {
+                    egui::Event::Key {
+                        key,
+                        pressed,
+                        repeat: false,
+                        ..
+                    } => Some((key, pressed)),
+                    _ => None,
+                })
+                .collect()
+        });
+        for (key, pressed) in keys {
+            match (key, pressed) {
+                (egui::Key::Space, true) => {
+                    self.paused = !self.paused;
+                    self.handle.send(if self.paused {
+                        Command::Pause
+                    } else {
+                        Command::Resume
+                    });
+                }
+                (egui::Key::F2, true) => self.show_scope = !self.show_scope,
+                (egui::Key::F3, true) => self.editor.open = !self.editor.open,
+                (egui::Key::F4, true) => self.show_keypad = !self.show_keypad,
+                (egui::Key::F11, true) => {
+                    self.status.clear();
+                    self.handle.send(Command::Reset);
+                }
+                _ => {
+                    if let Some(binding) = self.keymap.lookup(key.name()) {
+                        if binding.pad == 0 {
+                            self.handle.send(Command::Key(binding.key, pressed));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Feed the oscilloscope the buzzer of the frames since the last
+    // repaint.
+    fn render_audio(&mut self, context: &egui::Context) {
+        let now = Instant::now();
+        let mut samples = Vec::new();
+        for _ in 0..self.pacer.advance(now - self.rendered) {
+            samples.clear();
+            self.mixer.render_frame(self.beeping, &mut samples);
+            self.scope.feed(&samples);
+        }
+        self.rendered = now;
+        // Keep the trace moving until the beep faded out.
+        if self.show_scope && self.scope.trace().iter().any(|&sample| sample != 0.0) {
+            context.request_repaint_after(Duration::from_secs(1) / 60);
+        }
+    }
+
+    fn show_scope(&self, ui: &mut egui::Ui) {
+        ui.label(match self.mixer.voice() {
+            Voice::Beep => format!("Beep at {}Hz", self.mixer.config().pitch),
+            Voice::Pattern { pitch, .. } => format!("Audio pattern at pitch {}", pitch),
+        });
+        let size = egui::vec2(ui.available_width(), SCOPE_HEIGHT);
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, egui::Color32::BLACK);
+        painter.hline(
+            rect.x_range(),
+            rect.center().y,
+            egui::Stroke::new(1.0, egui::Color32::from_gray(0x30)),
+        );
+        // Full volume spans the panel.
+        let volume = self.mixer.config().volume.max(f32::EPSILON);
+        let points = self
+            .scope
+            .trace()
+            .iter()
+            .enumerate()
+            .map(|(i, sample)| {
+                egui::pos2(
+                    rect.left() + rect.width() * i as f32 / SCOPE_WINDOW as f32,
+                    rect.center().y - sample / volume * rect.height() / 2.0,
+                )
+            })
+            .collect();
+        painter.add(egui::Shape::line(
+            points,
+            egui::Stroke::new(1.0, egui::Color32::from_rgb(0x33, 0xFF, 0x66)),
+        ));
+    }
+
+    // Draw the 4x4 keypad in its original layout, the keys held lit and
+    // every key outlined while the program waits for one.
+    fn show_keypad(&self, ui: &mut egui::Ui) {
+        let side = 4.0 * KEY_SIZE + 3.0 * KEY_SPACING;
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(side, side), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        let waiting = egui::Color32::from_rgb(0xFF, 0xCC, 0x00);
+        for cell in self.keypad.cells() {
+            let min = rect.min
+                + egui::vec2(cell.column as f32, cell.row as f32) * (KEY_SIZE + KEY_SPACING);
+            let key = egui::Rect::from_min_size(min, egui::vec2(KEY_SIZE, KEY_SIZE));
+            let (fill, text) = if cell.pressed {
+                (egui::Color32::WHITE, egui::Color32::BLACK)
+            } else {
+                (
+                    egui::Color32::from_gray(0x30),
+                    egui::Color32::from_gray(0xC0),
+                )
+            };
+            painter.rect_filled(key, 4.0, fill);
+            if self.keypad.waiting {
+                painter.rect_stroke(
+                    key,
+                    4.0,
+                    egui::Stroke::new(2.0, waiting),
+                    egui::StrokeKind::Inside,
+                );
+            }
+            painter.text(
+                key.center(),
+                egui::Align2::CENTER_CENTER,
+                format!("{:X}", cell.key),
+                egui::FontId::monospace(16.0),
+                text,
+            );
+        }
+        if self.keypad.waiting {
+            ui.colored_label(waiting, "Waiting for a key (Fx0A)");
+        }
+    }
+}
+
+impl SpriteEditor {
+    fn new(found: Vec<Sprite>) -> SpriteEditor {
+        let sprite = match found.first() {
+            Some(sprite) => sprite.clone(),
+            None => Sprite::blank(0x200, 8, 8),
+        };
+        SpriteEditor {
+            open: false,
+            found,
+            sprite,
+            height: 8,
+            live: true,
+        }
+    }
+
+    fn show(&mut self, context: &egui::Context, handle: &EmulatorHandle) {
+        let mut open = self.open;
+        egui::Window::new("Sprites")
+            .open(&mut open)
+            .resizable(false)
+            .show(context, |ui| {
+                egui::ComboBox::from_id_salt("found")
+                    .selected_text(describe(&self.sprite))
+                    .show_ui(ui, |ui| {
+                        for sprite in &self.found {
+                            let selected = *sprite == self.sprite;
+                            if ui.selectable_label(selected, describe(sprite)).clicked() {
+                                self.sprite = sprite.clone();
+                            }
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    if ui.button("New 8xN").clicked() {
+                        self.sprite = Sprite::blank(self.sprite.address, 8, self.height);
+                    }
+                    ui.add(egui::DragValue::new(&mut self.height).range(1..=15));
+                    if ui.button("New 16x16").clicked() {
+                        self.sprite = Sprite::blank(self.sprite.address, 16, 16);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Address");
+                    ui.add(
+                        egui::DragValue::new(&mut self.sprite.address).hexadecimal(3, false, true),
+                    );
+                });
+                let edited = self.edit(ui);
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.live, "Live");
+                    if ui.button("Write").clicked() || (edited && self.live) {
+                        handle.send(Command::Poke(self.sprite.address, self.sprite.data.clone()));
+                    }
+                    if ui.button("Copy db").clicked() {
+                        let label = format!("sprite_{:03x}", self.sprite.address);
+                        ui.ctx().copy_text(self.sprite.to_db(&label));
+                    }
+                });
+            });
+        self.open = open;
+    }
+
+    // Draw the pixel grid, toggling the pixel clicked. Returns whether one
+    // was.
+    fn edit(&mut self, ui: &mut egui::Ui) -> bool {
+        let (width, height) = (self.sprite.width, self.sprite.height());
+        let size = egui::vec2(width as f32, height as f32) * CELL;
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
+        let clicked = response
+            .interact_pointer_pos()
+            .filter(|_| response.clicked())
+            .map(|position| (position - rect.min) / CELL)
+            .map(|cell| (cell.x as usize, cell.y as usize))
+            .filter(|&(x, y)| x < width && y < height);
+        if let Some((x, y)) = clicked {
+            self.sprite.toggle_pixel(x, y);
+        }
+
+        let painter = ui.painter_at(rect);
+        for y in 0..height {
+            for x in 0..width {
+                let min = rect.min + egui::vec2(x as f32, y as f32) * CELL;
+                let cell = egui::Rect::from_min_size(min, egui::vec2(CELL - 1.0, CELL - 1.0));
+                let color = if self.sprite.pixel(x, y) {
+                    egui::Color32::WHITE
+                } else {
+                    egui::Color32::from_gray(0x20)
+                };
+                painter.rect_filled(cell, 0.0, color);
+            }
+        }
+        clicked.is_some()
+    }
+}
+
+// How sprites are listed, e.g. `0x2A0 8x5`.
+fn describe(sprite: &Sprite) -> String {
+    format!(
+        "0x{:03X} {}x{}",
+        sprite.address,
+        sprite.width,
+        sprite.height()
+    )
+}
+
+impl eframe::App for App {
+    fn update(&mut self, context: &egui::Context, _frame: &mut eframe::Frame) {
+        self.handle_events(context);
+        self.handle_keys(context);
+        self.render_audio(context);
+        egui::TopBottomPanel::bottom("status").show(context, |ui| {
+            let mut status = self.status.clone();
+            if self.paused {
+                status = format!("Paused {}", status);
+            }
+            if self.beeping {
+                status = format!("Beep {}", status);
+            }
+            ui.label(status);
+        });
+        if self.show_scope {
+            egui::TopBottomPanel::bottom("scope").show(context, |ui| self.show_scope(ui));
+        }
+        if self.show_keypad {
+            egui::SidePanel::right("keypad")
+                .resizable(false)
+                .show(context, |ui| self.show_keypad(ui));
+        }
+        self.editor.show(context, &self.handle);
+        egui::CentralPanel::default()
+            .frame(egui::Frame::NONE)
+            .show(context, |ui| {
+                if let Some(screen) = &self.screen {
+                    ui.add(egui::Image::new(screen).fit_to_exact_size(ui.available_size()));
+                }
+            });
+    }
+}
+
+fn main() {
+    let rom = match env::args().nth(1) {
+        Some(path) => fs::read(&path).unwrap_or_else(|e| {
+            eprintln!("Failed to read {}: {}", path, e);
+            process::exit(1);
+        }),
+        None => splash::ROM.to_vec(),
+    };
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("CHIP-8")
+            .with_inner_size([64.0 * SCALE, 32.0 * SCALE + 24.0]),
+        ..Default::default()
+    };
+    let result = eframe::run_native(
+        "CHIP-8",
+        options,
+        Box::new(|creation| Ok(Box::new(App::new(&creation.egui_ctx, rom)))),
+    );
+    if let Err(e) = result {
+        eprintln!("Failed to play: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,52 @@
+#![no_main]
+
+//! Runs arbitrary bytes as a program, on a machine configured by the first
+//! two of them, and checks that it neither panics nor leaves its memory or
+//! stack. `tests/properties.rs` checks the same with proptest on every
+//! `cargo test`; run this one with `cargo +nightly fuzz run cpu`.
+
+use chip_8_rs::backend::{ExecutionBackend, Interpreter};
+use chip_8_rs::blocks::BlockTranslator;
+use chip_8_rs::cached::CachedInterpreter;
+use chip_8_rs::cartridge::Variant;
+use chip_8_rs::{Chip8, EmulationMode, Quirks, Register};
+use libfuzzer_sys::fuzz_target;
+
+// Frames run per input, each of `CYCLES` instructions.
+const FRAMES: u32 = 60;
+const CYCLES: usize = 16;
+
+fuzz_target!(|data: &[u8]| {
+    let [config, keys, rom @ ..] = data else {
+        return;
+    };
+    let backend: Box<dyn ExecutionBackend> = match config & 0x3 {
+        0 => Box::new(CachedInterpreter::new()),
+        1 => Box::new(BlockTranslator::new()),
+        _ => Box::new(Interpreter),
+    };
+    let mut chip8 = Chip8::with_backend(backend);
+    chip8.set_variant(match config >> 2 & 0x3 {
+        0 => Variant::SuperChip,
+        1 => Variant::XoChip,
+        _ => Variant::Chip8,
+    });
+    chip8.set_quirks(Quirks {
+        vf_reset: config & 0x10 != 0,
+        memory: config & 0x20 != 0,
+        shifting: config & 0x40 != 0,
+        jumping: config & 0x80 != 0,
+    });
+    if keys & 0x80 != 0 {
+        chip8.set_mode(EmulationMode::Strict);
+    }
+    if chip8.load_rom(rom).is_err() {
+        return;
+    }
+    for frame in 0..FRAMES {
+        chip8.set_keys((*keys as u16).rotate_left(frame));
+        chip8.run_frame(CYCLES);
+        assert!(chip8.register(Register::PC) as usize <= chip8.memory().len());
+        assert!(chip8.register(Register::SP) <= 16);
+    }
+});
@@ -0,0 +1,926 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+/// # Assembler
+///
+/// Turns CHIP-8 assembly in the usual mnemonics, SUPER-CHIP and XO-CHIP
+/// ones included, into a ROM loaded at 0x200. `chip8 asm` writes the ROM
+/// out, `chip8 build` runs it as well.
+///
+/// ```text
+/// %include "sprites.s"        ; paste another file here
+/// %define SPEED 2             ; compile-time constant
+///
+/// %macro move reg, delta      ; macros take comma separated parameters
+///     add reg, delta
+/// %endmacro
+///
+/// start:
+///     ld v0, SPEED * 4 + 1    ; operands are expressions
+///     move v0, 0xFF
+///     ld i, ball
+///     drw v0, v1, ball_end - ball
+/// %%here: jp %%here           ; %% labels are local to a macro expansion
+///
+/// ball:   db 0b11000000, 0b11000000
+/// ball_end:
+/// ```
+///
+/// Mnemonics, registers and directives are case-insensitive, labels and
+/// constants are not. Comments start with `;`. Expressions are made of
+/// numbers (decimal, `0x` hexadecimal, `0b` binary), labels, constants,
+/// parentheses and the operators `+ - * / % & | ^ << >> ~`, with C
+/// precedence. Besides the instructions, `db` emits bytes (or strings),
+/// `dw` emits big-endian words and `org` moves on to a later address.
+///
+/// The XO-CHIP instructions are written `scu n`, `plane n`, `audio`,
+/// `pitch vx`, `ld [i], vx-vy` and `ld vx-vy, [i]` for register ranges,
+/// and `ld i, long addr`, which takes four bytes for its 16-bit address.
+///
+/// `%include` paths are relative to the including file. Macros are expanded
+/// by substituting their parameters wherever they appear as a whole word,
+/// and `%%name` becomes a name unique to the expansion.
+///
+/// ## Linking
+///
+/// A project may also be split into several inputs given to `link`, which
+/// places them one after the other in a single ROM. Labels, constants and
+/// macros are shared by all inputs, so code can jump to labels of any file
+/// and use data from any file; macros must be defined before they are used.
+/// Data inputs, such as sprite sheets produced by a sprite editor, are
+/// copied as they are under a label named after the file: `sprites/ship.bin`
+/// becomes `ship`, followed by a `ship_end` label for computing its size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assembly {
+    pub rom: Vec<u8>,
+
+    // Address of every label
+    pub labels: BTreeMap<String, u16>,
+
+    // Every file read, inputs and includes, e.g. to watch for changes
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+/// One of the inputs linked into a ROM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    Source { name: String, text: String },
+    Data { name: String, bytes: Vec<u8> },
+}
+
+// Address the ROM is loaded at.
+pub const START: u16 = 0x200;
+
+// Deepest nesting of includes and macro expansions, to stop runaway
+// recursion.
+const MAX_DEPTH: usize = 32;
+
+// Assemble `source`, named `name` in error messages, using `include` to
+// read included files by path. Include paths are relative to the directory
+// of `name`.
+pub fn assemble(
+    source: &str,
+    name: &str,
+    include: impl FnMut(&str) -> Result<String, String>,
+) -> Result<Assembly, AsmError> {
+    let input = Input::Source {
+        name: name.to_string(),
+        text: source.to_string(),
+    };
+    link(&[input], include)
+}
+
+// Assemble and link several inputs into one ROM, in order.
+pub fn link(
+    inputs: &[Input],
+    mut include: impl FnMut(&str) -> Result<String, String>,
+) -> Result<Assembly, AsmError> {
+    let mut preprocessor = Preprocessor {
+        include: &mut include,
+        macros: HashMap::new(),
+        expansions: 0,
+        lines: Vec::new(),
+        files: Vec::new(),
+    };
+    for input in inputs {
+        match input {
+            Input::Source { name, text } => {
+                let dir = Path::new(name).parent().unwrap_or(Path::new(""));
+                preprocessor.file(text, name, dir, 0)?;
+            }
+            Input::Data { name, bytes } => preprocessor.data(name, bytes),
+        }
+    }
+    let lines = std::mem::take(&mut preprocessor.lines);
+
+    let mut symbols = Symbols::default();
+    let statements = layout(&lines, &mut symbols)?;
+    let rom = encode(&statements, &symbols)?;
+    Ok(Assembly {
+        rom,
+        labels: symbols.labels,
+        files: preprocessor.files,
+    })
+}
+
+// Assemble a file, resolving includes relative to the including file.
+pub fn assemble_file(path: &Path) -> Result<Assembly, AsmError> {
+    link_files(&[path])
+}
+
+// Link files into one ROM. Files ending in `.s` or `.asm` are assembled,
+// anything else is taken as data.
+pub fn link_files(paths: &[&Path]) -> Result<Assembly, AsmError> {
+    let mut inputs = Vec::new();
+    for path in paths {
+        let name = path.display().to_string();
+        let error = |e: std::io::Error| AsmError {
+            file: name.clone(),
+            line: 0,
+            message: e.to_string(),
+        };
+        let source = path
+            .extension()
+            .is_some_and(|ext| ext == "s" || ext == "asm");
+        inputs.push(if source {
+            let text = std::fs::read_to_string(path).map_err(error)?;
+            Input::Source { name, text }
+        } else {
+            let bytes = std::fs::read(path).map_err(error)?;
+            Input::Data { name, bytes }
+        });
+    }
+    link(&inputs, |path| {
+        std::fs::read_to_string(path).map_err(|e| e.to_string())
+    })
+}
+
+// A source line after includes and macros were expanded.
+#[derive(Debug, Clone)]
+struct Line {
+    file: String,
+    number: usize,
+    text: String,
+}
+
+#[derive(Debug, Clone)]
+struct Macro {
+    parameters: Vec<String>,
+    body: Vec<String>,
+}
+
+struct Preprocessor<'a> {
+    include: &'a mut dyn FnMut(&str) -> Result<String, String>,
+    macros: HashMap<String, Macro>,
+    expansions: usize,
+    lines: Vec<Line>,
+    files: Vec<String>,
+}
+
+impl Preprocessor<'_> {
+    // Expand a file located in `dir`, relative to the main source.
+    fn file(&mut self, source: &str, name: &str, dir: &Path, depth: usize) -> Result<(), AsmError> {
+        self.files.push(name.to_string());
+        let mut lines = source.lines().enumerate();
+        while let Some((index, text)) = lines.next() {
+            let number = index + 1;
+            let error = |message: String| AsmError {
+                file: name.to_string(),
+                line: number,
+                message,
+            };
+            let text = strip_comment(text).trim();
+            let (keyword, rest) = split_word(text);
+            match keyword.to_ascii_lowercase().as_str() {
+                "%macro" => {
+                    let (macro_name, parameters) = split_word(rest);
+                    if !is_identifier(macro_name) {
+                        return Err(error(format!("invalid macro name `{}`", macro_name)));
+                    }
+                    let parameters = split_operands(parameters)
+                        .into_iter()
+                        .map(str::to_string)
+                        .collect();
+                    let mut body = Vec::new();
+                    loop {
+                        let Some((_, line)) = lines.next() else {
+                            return Err(error(format!("`{}` lacks %endmacro", macro_name)));
+                        };
+                        let line = strip_comment(line).trim();
+                        if line.eq_ignore_ascii_case("%endmacro") {
+                            break;
+                        }
+                        body.push(line.to_string());
+                    }
+                    self.macros
+                        .insert(macro_name.to_string(), Macro { parameters, body });
+                }
+                "%endmacro" => return Err(error("%endmacro without %macro".to_string())),
+                "%include" => {
+                    if depth == MAX_DEPTH {
+                        return Err(error("includes nested too deeply".to_string()));
+                    }
+                    let path = rest
+                        .strip_prefix('"')
+                        .and_then(|rest| rest.strip_suffix('"'))
+                        .ok_or_else(|| error("expected %include \"path\"".to_string()))?;
+                    let path = dir.join(path);
+                    let name = path.to_string_lossy();
+                    let source = (self.include)(&name)
+                        .map_err(|e| error(format!("cannot include {}: {}", name, e)))?;
+                    let dir = path.parent().unwrap_or(Path::new(""));
+                    self.file(&source, &name, dir, depth + 1)?;
+                }
+                _ => self.line(text, name, number, depth)?,
+            }
+        }
+        Ok(())
+    }
+
+    // Emit a data input between labels named after its file.
+    fn data(&mut self, name: &str, bytes: &[u8]) {
+        self.files.push(name.to_string());
+        let stem = Path::new(name)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_default();
+        let mut label: String = stem
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        if !label.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            label.insert(0, '_');
+        }
+        let line = |number: usize, text: String| Line {
+            file: name.to_string(),
+            number,
+            text,
+        };
+        self.lines.push(line(0, format!("{}:", label)));
+        for (row, chunk) in bytes.chunks(16).enumerate() {
+            let values: Vec<String> = chunk.iter().map(|b| format!("0x{:02X}", b)).collect();
+            self.lines
+                .push(line(row + 1, format!("db {}", values.join(", "))));
+        }
+        self.lines.push(line(0, format!("{}_end:", label)));
+    }
+
+    // Expand a line which is not a preprocessor block.
+    fn line(
+        &mut self,
+        text: &str,
+        file: &str,
+        number: usize,
+        depth: usize,
+    ) -> Result<(), AsmError> {
+        let (labels, rest) = split_labels(text);
+        let (keyword, operands) = split_word(rest);
+        let Some(definition) = self.macros.get(keyword).cloned() else {
+            self.lines.push(Line {
+                file: file.to_string(),
+                number,
+                text: text.to_string(),
+            });
+            return Ok(());
+        };
+
+        let error = |message: String| AsmError {
+            file: file.to_string(),
+            line: number,
+            message,
+        };
+        if depth == MAX_DEPTH {
+            return Err(error("macros nested too deeply".to_string()));
+        }
+        let arguments = split_operands(operands);
+        if arguments.len() != definition.parameters.len() {
+            return Err(error(format!(
+                "macro `{}` takes {} arguments, got {}",
+                keyword,
+                definition.parameters.len(),
+                arguments.len()
+            )));
+        }
+        if !labels.is_empty() {
+            self.lines.push(Line {
+                file: file.to_string(),
+                number,
+                text: labels.to_string(),
+            });
+        }
+        self.expansions += 1;
+        let suffix = format!("__{}", self.expansions);
+        for body in &definition.body {
+            let mut expanded = substitute(body, &definition.parameters, &arguments);
+            expanded = expand_local_labels(&expanded, &suffix);
+            self.line(&expanded, file, number, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+// Replace whole-word occurrences of the parameters by the arguments.
+fn substitute(text: &str, parameters: &[String], arguments: &[&str]) -> String {
+    let mut result = String::new();
+    let mut word = String::new();
+    let flush = |word: &mut String, result: &mut String| {
+        match parameters.iter().position(|p| p == word) {
+            Some(i) => result.push_str(arguments[i]),
+            None => result.push_str(word),
+        }
+        word.clear();
+    };
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut result);
+            result.push(c);
+        }
+    }
+    flush(&mut word, &mut result);
+    result
+}
+
+// Turn `%%name` into `name__N`.
+fn expand_local_labels(text: &str, suffix: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(at) = rest.find("%%") {
+        result.push_str(&rest[..at]);
+        rest = &rest[at + 2..];
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+            .unwrap_or(rest.len());
+        result.push_str(&rest[..end]);
+        result.push_str(suffix);
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[derive(Debug, Default)]
+struct Symbols {
+    labels: BTreeMap<String, u16>,
+    constants: HashMap<String, Expr>,
+}
+
+// A line reduced to what it emits, at its address.
+#[derive(Debug)]
+struct Statement<'a> {
+    line: &'a Line,
+    address: u16,
+    keyword: String,
+    operands: Vec<&'a str>,
+}
+
+// First pass: define labels and constants, and give every statement its
+// address.
+fn layout<'a>(lines: &'a [Line], symbols: &mut Symbols) -> Result<Vec<Statement<'a>>, AsmError> {
+    let mut statements = Vec::new();
+    let mut address = START as i64;
+    for line in lines {
+        let error = |message: String| AsmError {
+            file: line.file.clone(),
+            line: line.number,
+            message,
+        };
+        let (labels, rest) = split_labels(&line.text);
+        for label in labels.split(':').map(str::trim).filter(|l| !l.is_empty()) {
+            if !is_identifier(label) || is_reserved(label) {
+                return Err(error(format!("invalid label `{}`", label)));
+            }
+            if symbols.labels.contains_key(label) || symbols.constants.contains_key(label) {
+                return Err(error(format!("`{}` is defined twice", label)));
+            }
+            symbols.labels.insert(label.to_string(), address as u16);
+        }
+
+        let (keyword, operands) = split_word(rest);
+        if keyword.is_empty() {
+            continue;
+        }
+        let keyword = keyword.to_ascii_lowercase();
+        let operands = split_operands(operands);
+        let size = match keyword.as_str() {
+            "%define" => {
+                let (name, value) = split_word(rest[keyword.len()..].trim());
+                if !is_identifier(name) || is_reserved(name) {
+                    return Err(error(format!("invalid constant name `{}`", name)));
+                }
+                if symbols.labels.contains_key(name) || symbols.constants.contains_key(name) {
+                    return Err(error(format!("`{}` is defined twice", name)));
+                }
+                let value = Expr::parse(value).map_err(error)?;
+                symbols.constants.insert(name.to_string(), value);
+                continue;
+            }
+            "org" => {
+                let [target] = operands[..] else {
+                    return Err(error("org takes one address".to_string()));
+                };
+                let target = Expr::parse(target)
+                    .and_then(|e| e.eval(symbols, 0))
+                    .map_err(error)?;
+                if target < address {
+                    return Err(error(format!(
+                        "org 0x{:X} goes back from 0x{:X}",
+                        target, address
+                    )));
+                }
+                target - address
+            }
+            "db" => operands
+                .iter()
+                .map(|o| string_literal(o).map_or(1, |s| s.len()))
+                .sum::<usize>() as i64,
+            "dw" => 2 * operands.len() as i64,
+            "ld" if long_address(&operands).is_some() => 4,
+            _ => 2,
+        };
+        statements.push(Statement {
+            line,
+            address: address as u16,
+            keyword,
+            operands,
+        });
+        address += size;
+        if address > 0x1000 {
+            return Err(error("program does not fit below 0x1000".to_string()));
+        }
+    }
+    Ok(statements)
+}
+
+// Second pass: emit the bytes of every statement.
+fn encode(statements: &[Statement], symbols: &Symbols) -> Result<Vec<u8>, AsmError> {
+    let mut rom = Vec::new();
+    for statement in statements {
+        let error = |message: String| AsmError {
+            file: statement.line.file.clone(),
+            line: statement.line.number,
+            message,
+        };
+        // `org` may have skipped ahead.
+        rom.resize((statement.address - START) as usize, 0);
+        match statement.keyword.as_str() {
+            "org" => {}
+            "db" => {
+                for operand in &statement.operands {
+                    match string_literal(operand) {
+                        Some(text) => rom.extend(text.bytes()),
+                        None => {
+                            rom.push(value(operand, symbols, Width::Byte).map_err(error)? as u8)
+                        }
+                    }
+                }
+            }
+            "dw" => {
+                for operand in &statement.operands {
+                    let word = value(operand, symbols, Width::Word).map_err(error)?;
+                    rom.extend(word.to_be_bytes());
+                }
+            }
+            "ld" if long_address(&statement.operands).is_some() => {
+                let text = long_address(&statement.operands).unwrap_or_default();
+                let address = value(text, symbols, Width::Word).map_err(error)?;
+                rom.extend([0xF0, 0x00]);
+                rom.extend(address.to_be_bytes());
+            }
+            _ => {
+                let opcode =
+                    instruction(&statement.keyword, &statement.operands, symbols).map_err(error)?;
+                rom.extend(opcode.to_be_bytes());
+            }
+        }
+    }
+    Ok(rom)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    V(u16),
+    // Vx-Vy, a register range
+    Range(u16, u16),
+    I,
+    IndirectI,
+    DT,
+    ST,
+    K,
+    F,
+    HF,
+    B,
+    R,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Width {
+    Nibble,
+    Byte,
+    Address,
+    Word,
+}
+
+fn operand(text: &str) -> Option<Operand> {
+    let upper = text.to_ascii_uppercase();
+    Some(match upper.as_str() {
+        "I" => Operand::I,
+        "[I]" => Operand::IndirectI,
+        "DT" => Operand::DT,
+        "ST" => Operand::ST,
+        "K" => Operand::K,
+        "F" => Operand::F,
+        "HF" => Operand::HF,
+        "B" => Operand::B,
+        "R" => Operand::R,
+        _ => match upper.split_once('-') {
+            Some((first, last)) => match (operand(first.trim())?, operand(last.trim())?) {
+                (Operand::V(x), Operand::V(y)) => Operand::Range(x, y),
+                _ => return None,
+            },
+            None => {
+                let digit = upper.strip_prefix('V')?;
+                if digit.len() != 1 {
+                    return None;
+                }
+                Operand::V(u16::from_str_radix(digit, 16).ok()?)
+            }
+        },
+    })
+}
+
+// The address operand of `ld i, long addr`.
+fn long_address<'a>(operands: &[&'a str]) -> Option<&'a str> {
+    let [register, address] = operands else {
+        return None;
+    };
+    let (keyword, address) = split_word(address);
+    let long = keyword.eq_ignore_ascii_case("long") && !address.is_empty();
+    (operand(register) == Some(Operand::I) && long).then_some(address)
+}
+
+fn is_reserved(name: &str) -> bool {
+    operand(name).is_some()
+}
+
+// Evaluate an expression operand and check that it fits.
+fn value(text: &str, symbols: &Symbols, width: Width) -> Result<u16, String> {
+    let value = Expr::parse(text)?.eval(symbols, 0)?;
+    let (min, max) = match width {
+        Width::Nibble => (0, 0xF),
+        Width::Byte => (-0x80, 0xFF),
+        Width::Address => (0, 0xFFF),
+        Width::Word => (-0x8000, 0xFFFF),
+    };
+    if value < min || value > max {
+        return Err(format!("{} does not fit in {:?}", value, width).to_lowercase());
+    }
+    Ok(value as u16 & if min < 0 { max as u16 } else { u16::MAX })
+}
+
+// Encode an instruction.
+fn instruction(mnemonic: &str, operands: &[&str], symbols: &Symbols) -> Result<u16, String> {
+    use Operand::*;
+    let parsed: Vec<Option<Operand>> = operands.iter().map(|o| operand(o)).collect();
+    let x = |i: usize| match parsed[i] {
+        Some(V(x)) => x << 8,
+        _ => 0,
+    };
+    let y = |i: usize| match parsed[i] {
+        Some(V(y)) => y << 4,
+        _ => 0,
+    };
+    let addr = |i: usize| value(operands[i], symbols, Width::Address);
+    let byte = |i: usize| value(operands[i], symbols, Width::Byte);
+    let nibble = |i: usize| value(operands[i], symbols, Width::Nibble);
+
+    Ok(match (mnemonic, &parsed[..]) {
+        ("scd", [None]) => 0x00C0 | nibble(0)?,
+        ("scu", [None]) => 0x00D0 | nibble(0)?,
+        ("cls", []) => 0x00E0,
+        ("ret", []) => 0x00EE,
+        ("scr", []) => 0x00FB,
+        ("scl", []) => 0x00FC,
+        ("exit", []) => 0x00FD,
+        ("low", []) => 0x00FE,
+        ("high", []) => 0x00FF,
+        ("sys", [None]) => addr(0)?,
+        ("jp", [None]) => 0x1000 | addr(0)?,
+        ("jp", [Some(V(0)), None]) => 0xB000 | addr(1)?,
+        ("call", [None]) => 0x2000 | addr(0)?,
+        ("se", [Some(V(_)), None]) => 0x3000 | x(0) | byte(1)?,
+        ("sne", [Some(V(_)), None]) => 0x4000 | x(0) | byte(1)?,
+        ("se", [Some(V(_)), Some(V(_))]) => 0x5000 | x(0) | y(1),
+        ("ld", [Some(IndirectI), Some(Range(x, y))]) => 0x5002 | x << 8 | y << 4,
+        ("ld", [Some(Range(x, y)), Some(IndirectI)]) => 0x5003 | x << 8 | y << 4,
+        ("sne", [Some(V(_)), Some(V(_))]) => 0x9000 | x(0) | y(1),
+        ("ld", [Some(V(_)), None]) => 0x6000 | x(0) | byte(1)?,
+        ("add", [Some(V(_)), None]) => 0x7000 | x(0) | byte(1)?,
+        ("ld", [Some(V(_)), Some(V(_))]) => 0x8000 | x(0) | y(1),
+        ("or", [Some(V(_)), Some(V(_))]) => 0x8001 | x(0) | y(1),
+        ("and", [Some(V(_)), Some(V(_))]) => 0x8002 | x(0) | y(1),
+        ("xor", [Some(V(_)), Some(V(_))]) => 0x8003 | x(0) | y(1),
+        ("add", [Some(V(_)), Some(V(_))]) => 0x8004 | x(0) | y(1),
+        ("sub", [Some(V(_)), Some(V(_))]) => 0x8005 | x(0) | y(1),
+        ("shr", [Some(V(_))]) => 0x8006 | x(0),
+        ("shr", [Some(V(_)), Some(V(_))]) => 0x8006 | x(0) | y(1),
+        ("subn", [Some(V(_)), Some(V(_))]) => 0x8007 | x(0) | y(1),
+        ("shl", [Some(V(_))]) => 0x800E | x(0),
+        ("shl", [Some(V(_)), Some(V(_))]) => 0x800E | x(0) | y(1),
+        ("ld", [Some(I), None]) => 0xA000 | addr(1)?,
+        ("rnd", [Some(V(_)), None]) => 0xC000 | x(0) | byte(1)?,
+        ("drw", [Some(V(_)), Some(V(_)), None]) => 0xD000 | x(0) | y(1) | nibble(2)?,
+        ("skp", [Some(V(_))]) => 0xE09E | x(0),
+        ("sknp", [Some(V(_))]) => 0xE0A1 | x(0),
+        ("plane", [None]) => 0xF001 | nibble(0)? << 8,
+        ("audio", []) => 0xF002,
+        ("ld", [Some(V(_)), Some(DT)]) => 0xF007 | x(0),
+        ("ld", [Some(V(_)), Some(K)]) => 0xF00A | x(0),
+        ("ld", [Some(DT), Some(V(_))]) => 0xF015 | x(1),
+        ("ld", [Some(ST), Some(V(_))]) => 0xF018 | x(1),
+        ("add", [Some(I), Some(V(_))]) => 0xF01E | x(1),
+        ("ld", [Some(F), Some(V(_))]) => 0xF029 | x(1),
+        ("ld", [Some(HF), Some(V(_))]) => 0xF030 | x(1),
+        ("ld", [Some(B), Some(V(_))]) => 0xF033 | x(1),
+        ("pitch", [Some(V(_))]) => 0xF03A | x(0),
+        ("ld", [Some(IndirectI), Some(V(_))]) => 0xF055 | x(1),
+        ("ld", [Some(V(_)), Some(IndirectI)]) => 0xF065 | x(0),
+        ("ld", [Some(R), Some(V(_))]) => 0xF075 | x(1),
+        ("ld", [Some(V(_)), Some(R)]) => 0xF085 | x(0),
+        _ if is_instruction(mnemonic) => {
+            return Err(format!("invalid operands for `{}`", mnemonic));
+        }
+        _ => return Err(format!("unknown instruction `{}`", mnemonic)),
+    })
+}
+
+fn is_instruction(mnemonic: &str) -> bool {
+    matches!(
+        mnemonic,
+        "scd"
+            | "scu"
+            | "cls"
+            | "ret"
+            | "scr"
+            | "scl"
+            | "exit"
+            | "low"
+            | "high"
+            | "sys"
+            | "jp"
+            | "call"
+            | "se"
+            | "sne"
+            | "ld"
+            | "add"
+            | "or"
+            | "and"
+            | "xor"
+            | "sub"
+            | "shr"
+            | "subn"
+            | "shl"
+            | "rnd"
+            | "drw"
+            | "skp"
+            | "sknp"
+            | "plane"
+            | "audio"
+            | "pitch"
+    )
+}
+
+/// A compile-time expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Number(i64),
+    Symbol(String),
+    Negate(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+}
+
+// Binary operators from loosest to tightest; `<` and `>` stand for the
+// shifts.
+const PRECEDENCE: [&str; 6] = ["|", "^", "&", "<>", "+-", "*/%"];
+
+impl Expr {
+    fn parse(text: &str) -> Result<Expr, String> {
+        let tokens = tokenize(text)?;
+        let mut position = 0;
+        let expr = parse_binary(&tokens, &mut position, 0)?;
+        match tokens.get(position) {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected `{}` in `{}`", token, text)),
+        }
+    }
+
+    // Evaluate, resolving labels and constants. `depth` guards against
+    // constants defined in terms of themselves.
+    fn eval(&self, symbols: &Symbols, depth: usize) -> Result<i64, String> {
+        if depth > MAX_DEPTH {
+            return Err("constant defined in terms of itself".to_string());
+        }
+        Ok(match self {
+            Expr::Number(n) => *n,
+            Expr::Symbol(name) => match symbols.labels.get(name) {
+                Some(&address) => address as i64,
+                None => symbols
+                    .constants
+                    .get(name)
+                    .ok_or_else(|| format!("undefined symbol `{}`", name))?
+                    .eval(symbols, depth + 1)?,
+            },
+            Expr::Negate(e) => e.eval(symbols, depth)?.wrapping_neg(),
+            Expr::Not(e) => !e.eval(symbols, depth)?,
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (a.eval(symbols, depth)?, b.eval(symbols, depth)?);
+                match op {
+                    '|' => a | b,
+                    '^' => a ^ b,
+                    '&' => a & b,
+                    '<' => a.checked_shl(b as u32).unwrap_or(0),
+                    '>' => a.checked_shr(b as u32).unwrap_or(0),
+                    '+' => a.wrapping_add(b),
+                    '-' => a.wrapping_sub(b),
+                    '*' => a.wrapping_mul(b),
+                    '/' => a.checked_div(b).ok_or("division by zero")?,
+                    '%' => a.checked_rem(b).ok_or("division by zero")?,
+                    _ => unreachable!("unknown operator {}", op),
+                }
+            }
+        })
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(word);
+        } else if c == '<' || c == '>' {
+            chars.next();
+            if chars.next() != Some(c) {
+                return Err(format!("expected `{}{}`", c, c));
+            }
+            tokens.push(c.to_string());
+        } else if "|^&+-*/%~()".contains(c) {
+            tokens.push(c.to_string());
+            chars.next();
+        } else {
+            return Err(format!("unexpected `{}` in `{}`", c, text));
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_binary(tokens: &[String], position: &mut usize, level: usize) -> Result<Expr, String> {
+    if level == PRECEDENCE.len() {
+        return parse_unary(tokens, position);
+    }
+    let mut lhs = parse_binary(tokens, position, level + 1)?;
+    while let Some(op) = tokens.get(*position).and_then(|t| t.chars().next()) {
+        if tokens[*position].len() != 1 || !PRECEDENCE[level].contains(op) {
+            break;
+        }
+        *position += 1;
+        let rhs = parse_binary(tokens, position, level + 1)?;
+        lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+    }
+    Ok(lhs)
+}
+
+fn parse_unary(tokens: &[String], position: &mut usize) -> Result<Expr, String> {
+    let token = tokens.get(*position).ok_or("expected a value")?;
+    *position += 1;
+    match token.as_str() {
+        "-" => Ok(Expr::Negate(Box::new(parse_unary(tokens, position)?))),
+        "~" => Ok(Expr::Not(Box::new(parse_unary(tokens, position)?))),
+        "(" => {
+            let expr = parse_binary(tokens, position, 0)?;
+            if tokens.get(*position).map(String::as_str) != Some(")") {
+                return Err("expected `)`".to_string());
+            }
+            *position += 1;
+            Ok(expr)
+        }
+        _ if token.starts_with(|c: char| c.is_ascii_digit()) => {
+            let lower = token.to_ascii_lowercase();
+            let number = if let Some(hex) = lower.strip_prefix("0x") {
+                i64::from_str_radix(hex, 16)
+            } else if let Some(binary) = lower.strip_prefix("0b") {
+                i64::from_str_radix(binary, 2)
+            } else {
+                lower.parse()
+            };
+            number
+                .map(Expr::Number)
+                .map_err(|_| format!("invalid number `{}`", token))
+        }
+        _ if is_identifier(token) && !is_reserved(token) => Ok(Expr::Symbol(token.clone())),
+        _ => Err(format!("unexpected `{}`", token)),
+    }
+}
+
+fn is_identifier(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '.')
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+// Remove a `;` comment, leaving semicolons inside strings alone.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+// Split off the first whitespace-separated word.
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    match text.find(char::is_whitespace) {
+        Some(at) => (&text[..at], text[at..].trim()),
+        None => (text, ""),
+    }
+}
+
+// Split the leading `label:` definitions from the rest of a line.
+fn split_labels(text: &str) -> (&str, &str) {
+    let mut end = 0;
+    let mut rest = text.trim_start();
+    loop {
+        let name_end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '%'))
+            .unwrap_or(rest.len());
+        if name_end == 0 || !rest[name_end..].starts_with(':') {
+            break;
+        }
+        let consumed = text.len() - rest.len() + name_end + 1;
+        end = consumed;
+        rest = text[end..].trim_start();
+    }
+    (&text[..end], text[end..].trim())
+}
+
+// Split operands on commas outside of parentheses and strings.
+fn split_operands(text: &str) -> Vec<&str> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Vec::new();
+    }
+    let mut operands = Vec::new();
+    let (mut depth, mut quoted, mut start) = (0, false, 0);
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                operands.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    operands.push(text[start..].trim());
+    operands
+}
+
+fn string_literal(text: &str) -> Option<&str> {
+    text.strip_prefix('"')?.strip_suffix('"')
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
@@ -0,0 +1,137 @@
+use std::fs;
+use std::path::Path;
+
+use crate::cpu::Chip8;
+use crate::movie::Movie;
+use crate::recording::InputRecording;
+
+/// # Attract Mode
+///
+/// Like the attract mode of arcade cabinets: once nobody has touched the keys
+/// for a while, the machine is reset and a recorded run is played back in a
+/// loop, until a user presses a key again. Useful for kiosks and demos.
+///
+/// A recording is attached to a ROM by saving it next to the ROM with a
+/// `.demo` extension, e.g. `pong.demo` for `pong.ch8`, or taken from a movie,
+/// which also brings the seed the demo restarts with.
+///
+/// Frontends call `before_frame` once per frame, before running it, which
+/// acts on the machine and returns the `AttractAction` it took; `update`
+/// only tells the action.
+#[derive(Debug, Clone)]
+pub struct AttractMode {
+    recording: InputRecording,
+
+    // Idle frames before the demo starts
+    idle_limit: u64,
+
+    // Idle frames so far
+    idle: u64,
+
+    // Next frame of the recording while the demo plays
+    position: Option<usize>,
+
+    // Seed of the random number generator on every restart, from a movie
+    seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttractAction {
+    // The user is in control, nothing to do
+    Idle,
+
+    // Reset the machine, then run the frame with these keys held
+    Restart(u16),
+
+    // Run the frame with these keys held
+    Play(u16),
+
+    // The user took over, reset the machine and hand control back
+    Stop,
+}
+
+impl AttractMode {
+    // The demo starts after `idle_seconds` of 60Hz frames without input.
+    pub fn new(recording: InputRecording, idle_seconds: u64) -> Self {
+        Self {
+            recording,
+            idle_limit: idle_seconds * 60,
+            idle: 0,
+            position: None,
+            seed: None,
+        }
+    }
+
+    // Play the inputs of a movie, from the seed it was recorded with.
+    pub fn from_movie(movie: Movie, idle_seconds: u64) -> Self {
+        Self {
+            seed: Some(movie.seed),
+            ..Self::new(movie.recording, idle_seconds)
+        }
+    }
+
+    // Load the recording attached to a ROM, if there is a readable one.
+    pub fn attached(rom_path: &Path, idle_seconds: u64) -> Option<Self> {
+        let text = fs::read_to_string(rom_path.with_extension("demo")).ok()?;
+        Some(Self::new(text.parse().ok()?, idle_seconds))
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.position.is_some()
+    }
+
+    // Advance by one frame. `user_input` tells whether any key was pressed by
+    // the user since the previous frame.
+    pub fn update(&mut self, user_input: bool) -> AttractAction {
+        if user_input {
+            self.idle = 0;
+            return match self.position.take() {
+                Some(_) => AttractAction::Stop,
+                None => AttractAction::Idle,
+            };
+        }
+
+        match self.position {
+            None if self.recording.is_empty() => AttractAction::Idle,
+            None => {
+                self.idle += 1;
+                if self.idle < self.idle_limit {
+                    return AttractAction::Idle;
+                }
+                self.position = Some(1);
+                AttractAction::Restart(self.recording.keys(0))
+            }
+            Some(position) if position >= self.recording.len() => {
+                self.position = Some(1);
+                AttractAction::Restart(self.recording.keys(0))
+            }
+            Some(position) => {
+                self.position = Some(position + 1);
+                AttractAction::Play(self.recording.keys(position))
+            }
+        }
+    }
+
+    // Advance by one frame like `update`, and act on the machine: it is
+    // reset, and reseeded for movies, when the demo starts over, and reset
+    // with every key released when the user takes over.
+    pub fn before_frame(&mut self, chip8: &mut Chip8, user_input: bool) -> AttractAction {
+        let action = self.update(user_input);
+        match action {
+            AttractAction::Idle => {}
+            AttractAction::Restart(keys) => {
+                chip8.reset(true);
+                if let Some(seed) = self.seed {
+                    chip8.set_seed(seed);
+                }
+                chip8.set_keys(keys);
+            }
+            AttractAction::Play(keys) => chip8.set_keys(keys),
+            AttractAction::Stop => {
+                chip8.reset(true);
+                chip8.set_keys(0);
+            }
+        }
+        action
+    }
+}
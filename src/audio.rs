@@ -0,0 +1,393 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::host::AudioBackend;
+
+/// # Audio
+///
+/// The CHIP-8 has a single buzzer which sounds while the sound timer is
+/// non-zero. Sound is produced in three stages, so that every output device
+/// behaves the same way:
+///
+/// - `Mixer` renders one frame of samples at a time from the machine state
+/// - `SampleQueue` buffers the samples between the emulation loop and the
+///   device, and is kept short enough to stay within the target latency
+/// - a `Buzzer` hands the samples to an actual output (a sound device, a
+///   WAV file, ...)
+///
+/// Audio devices disagree on sensible defaults, so the sample rate, the
+/// buffer size requested from the device and the target latency are all
+/// configurable through `AudioConfig`. A too small buffer or latency makes
+/// the beep crackle, a too large one makes it lag behind the picture.
+///
+/// Switching a wave on or off abruptly makes an audible pop, so the mixer
+/// fades the buzzer in and out over a short attack and release time. The
+/// beep is a square wave by default, as on the original hardware, or a
+/// softer sine or triangle wave.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioConfig {
+    // Samples per second
+    pub sample_rate: u32,
+
+    // Samples per device buffer
+    pub buffer_size: u32,
+
+    // Maximum audio queued ahead of the device, in milliseconds
+    pub latency_ms: u32,
+
+    // Frequency and shape of the beep
+    pub pitch: f32,
+    pub waveform: Waveform,
+
+    // Amplitude of the beep, from 0.0 to 1.0
+    pub volume: f32,
+
+    // Fade in time when the buzzer starts, in milliseconds
+    pub attack_ms: u32,
+
+    // Fade out time when the buzzer stops, in milliseconds
+    pub release_ms: u32,
+}
+
+impl AudioConfig {
+    // Check that the settings describe a usable output.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.sample_rate == 0 || self.buffer_size == 0 {
+            return Err("sample rate and buffer size must be positive".to_string());
+        }
+        if self.latency_samples() < self.buffer_size as usize {
+            return Err(format!(
+                "a latency of {}ms cannot hold a buffer of {} samples",
+                self.latency_ms, self.buffer_size
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.volume) {
+            return Err("volume must be between 0.0 and 1.0".to_string());
+        }
+        Ok(())
+    }
+
+    // The target latency expressed in samples.
+    pub fn latency_samples(&self) -> usize {
+        self.samples_for(self.latency_ms)
+    }
+
+    fn samples_for(&self, ms: u32) -> usize {
+        (self.sample_rate as u64 * ms as u64 / 1000) as usize
+    }
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig {
+            sample_rate: 44100,
+            buffer_size: 512,
+            latency_ms: 50,
+            pitch: 440.0,
+            waveform: Waveform::Square,
+            volume: 0.25,
+            attack_ms: 2,
+            release_ms: 5,
+        }
+    }
+}
+
+/// An output for the samples produced by the mixer. Every frontend plugs
+/// in its own: an SDL audio queue, a cpal stream, Web Audio, a WAV file.
+pub trait Buzzer {
+    // Short name used in logs and reports.
+    fn name(&self) -> &'static str;
+
+    // Play a buffer of mono samples in the range -1.0 to 1.0.
+    fn write(&mut self, samples: &[f32]);
+}
+
+/// Shape of the beep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Waveform {
+    #[default]
+    Square,
+    Sine,
+    Triangle,
+}
+
+/// What the mixer plays while the buzzer sounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Voice {
+    // The classic square wave beep, at the configured pitch
+    Beep,
+
+    // An XO-CHIP audio pattern: 128 one-bit samples played in a loop at a
+    // rate of 4000 * 2^((pitch - 64) / 48) bits per second
+    Pattern { bits: [u8; 16], pitch: u8 },
+}
+
+/// Renders the buzzer, one frame at a time.
+#[derive(Debug, Clone)]
+pub struct Mixer {
+    config: AudioConfig,
+    voice: Voice,
+
+    // Frames rendered per second, following the timer rate
+    frame_rate: u32,
+
+    // Position within the current wave period, from 0.0 to 1.0
+    phase: f32,
+
+    // Current gain of the envelope, from 0.0 (silent) to 1.0
+    level: f32,
+
+    // Fractional samples carried over, so frames average to the exact rate
+    remainder: u32,
+}
+
+impl Mixer {
+    pub fn new(config: AudioConfig) -> Mixer {
+        Mixer {
+            config,
+            voice: Voice::Beep,
+            frame_rate: 60,
+            phase: 0.0,
+            level: 0.0,
+            remainder: 0,
+        }
+    }
+
+    pub fn config(&self) -> &AudioConfig {
+        &self.config
+    }
+
+    // Match the rate at which `render_frame` gets called, 50 or 60Hz.
+    pub fn set_frame_rate(&mut self, frame_rate: u32) {
+        self.frame_rate = frame_rate.max(1);
+    }
+
+    pub fn voice(&self) -> Voice {
+        self.voice
+    }
+
+    pub fn set_voice(&mut self, voice: Voice) {
+        self.voice = voice;
+    }
+
+    // Change the frequency of the beep, in Hz.
+    pub fn set_pitch(&mut self, pitch: f32) {
+        self.config.pitch = pitch;
+    }
+
+    // Append the samples of one frame to `out`.
+    pub fn render_frame(&mut self, sounding: bool, out: &mut Vec<f32>) {
+        let total = self.config.sample_rate + self.remainder;
+        let count = total / self.frame_rate;
+        self.remainder = total % self.frame_rate;
+
+        let step = self.frequency() / self.config.sample_rate as f32;
+        let (target, ramp) = if sounding {
+            (1.0, self.config.attack_ms)
+        } else {
+            (0.0, self.config.release_ms)
+        };
+        let delta = 1.0 / self.config.samples_for(ramp).max(1) as f32;
+        for _ in 0..count {
+            self.level = if self.level < target {
+                (self.level + delta).min(target)
+            } else {
+                (self.level - delta).max(target)
+            };
+            out.push(self.wave() * self.config.volume * self.level);
+            self.phase = (self.phase + step).fract();
+        }
+    }
+
+    // Number of wave periods per second for the current voice.
+    fn frequency(&self) -> f32 {
+        match self.voice {
+            Voice::Beep => self.config.pitch,
+            Voice::Pattern { pitch, .. } => {
+                4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0) / 128.0
+            }
+        }
+    }
+
+    // Value of the wave at the current phase, from -1.0 to 1.0.
+    fn wave(&self) -> f32 {
+        let phase = self.phase;
+        match (self.voice, self.config.waveform) {
+            (Voice::Beep, Waveform::Square) => square(phase < 0.5),
+            (Voice::Beep, Waveform::Sine) => (phase * std::f32::consts::TAU).sin(),
+            (Voice::Beep, Waveform::Triangle) => 1.0 - 4.0 * (phase - 0.5).abs(),
+            // Patterns are one-bit samples, whatever the waveform
+            (Voice::Pattern { bits, .. }, _) => {
+                let bit = (phase * 128.0) as usize % 128;
+                square(bits[bit / 8] & (0x80 >> (bit % 8)) != 0)
+            }
+        }
+    }
+}
+
+fn square(high: bool) -> f32 {
+    if high {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "square" => Ok(Waveform::Square),
+            "sine" => Ok(Waveform::Sine),
+            "triangle" => Ok(Waveform::Triangle),
+            _ => Err(format!("unknown waveform `{}`", s)),
+        }
+    }
+}
+
+/// The `AudioBackend` of frontends with sound: renders every frame with a
+/// `Mixer` and plays the samples through a `Buzzer`.
+pub struct Speaker {
+    mixer: Mixer,
+    buzzer: Box<dyn Buzzer>,
+    samples: Vec<f32>,
+}
+
+impl Speaker {
+    pub fn new(mixer: Mixer, buzzer: Box<dyn Buzzer>) -> Speaker {
+        Speaker {
+            mixer,
+            buzzer,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn mixer_mut(&mut self) -> &mut Mixer {
+        &mut self.mixer
+    }
+}
+
+impl AudioBackend for Speaker {
+    fn play(&mut self, sounding: bool, voice: Voice) {
+        self.samples.clear();
+        self.mixer.set_voice(voice);
+        self.mixer.render_frame(sounding, &mut self.samples);
+        self.buzzer.write(&self.samples);
+    }
+}
+
+impl fmt::Debug for Speaker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Speaker")
+            .field("mixer", &self.mixer)
+            .field("buzzer", &self.buzzer.name())
+            .finish()
+    }
+}
+
+/// Buffers samples between the emulation loop and the output device.
+///
+/// Pushing more than the target latency drops the oldest samples, so audio
+/// never drifts behind the picture; pulling more than is queued pads with
+/// silence and counts an underrun.
+#[derive(Debug, Clone)]
+pub struct SampleQueue {
+    samples: VecDeque<f32>,
+    capacity: usize,
+    underruns: u64,
+}
+
+impl SampleQueue {
+    pub fn new(config: &AudioConfig) -> SampleQueue {
+        let capacity = config.latency_samples().max(config.buffer_size as usize);
+        SampleQueue {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+            underruns: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    // Number of pulls which ran out of samples.
+    pub fn underruns(&self) -> u64 {
+        self.underruns
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        self.samples.extend(samples);
+        let excess = self.samples.len().saturating_sub(self.capacity);
+        self.samples.drain(..excess);
+    }
+
+    // Fill `out` with queued samples.
+    pub fn pull(&mut self, out: &mut [f32]) {
+        let available = self.samples.len().min(out.len());
+        for (slot, sample) in out.iter_mut().zip(self.samples.drain(..available)) {
+            *slot = sample;
+        }
+        if available < out.len() {
+            out[available..].fill(0.0);
+            self.underruns += 1;
+        }
+    }
+}
+
+/// A buzzer recording everything it plays, to be saved as a WAV file.
+#[derive(Debug, Clone, Default)]
+pub struct WavRecorder {
+    sample_rate: u32,
+    samples: Vec<f32>,
+}
+
+impl WavRecorder {
+    pub fn new(config: &AudioConfig) -> WavRecorder {
+        WavRecorder {
+            sample_rate: config.sample_rate,
+            samples: Vec::new(),
+        }
+    }
+
+    // Encode the recording as a 16-bit mono WAV file.
+    pub fn to_wav(&self) -> Vec<u8> {
+        let data_len = (self.samples.len() * 2) as u32;
+        let mut wav = Vec::with_capacity(44 + data_len as usize);
+        wav.extend(b"RIFF");
+        wav.extend((36 + data_len).to_le_bytes());
+        wav.extend(b"WAVEfmt ");
+        wav.extend(16u32.to_le_bytes());
+        // PCM, one channel
+        wav.extend(1u16.to_le_bytes());
+        wav.extend(1u16.to_le_bytes());
+        wav.extend(self.sample_rate.to_le_bytes());
+        wav.extend((self.sample_rate * 2).to_le_bytes());
+        // Block alignment and bits per sample
+        wav.extend(2u16.to_le_bytes());
+        wav.extend(16u16.to_le_bytes());
+        wav.extend(b"data");
+        wav.extend(data_len.to_le_bytes());
+        for sample in &self.samples {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            wav.extend(value.to_le_bytes());
+        }
+        wav
+    }
+}
+
+impl Buzzer for WavRecorder {
+    fn name(&self) -> &'static str {
+        "wav"
+    }
+
+    fn write(&mut self, samples: &[f32]) {
+        self.samples.extend_from_slice(samples);
+    }
+}
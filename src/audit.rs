@@ -0,0 +1,114 @@
+use std::fmt;
+
+use crate::registers::Register;
+
+/// # Audit Mode
+///
+/// For forensic analysis of how a ROM ends up in a bad state, the machine
+/// can report every memory read and write and every register change as a
+/// structured `AuditEvent` to an `AuditSink`. Any `FnMut(&AuditEvent)`
+/// closure is a sink, and `AuditEvent`'s `Display` produces one JSON object
+/// per event, ready to be written as a JSON Lines log.
+///
+/// Events carry the address of the instruction causing them. Register
+/// events cover V0-VF, I, SP and the timers; PC is left out since it
+/// changes on every instruction, and the timers' 60Hz countdown is not
+/// reported either, only changes made by instructions.
+///
+/// Auditing is expensive, so it is off unless a sink is installed with
+/// `Chip8::set_audit_sink`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditEvent {
+    Read {
+        pc: u16,
+        address: usize,
+        value: u8,
+    },
+    Write {
+        pc: u16,
+        address: usize,
+        old: u8,
+        new: u8,
+    },
+    Register {
+        pc: u16,
+        register: Register,
+        old: u16,
+        new: u16,
+    },
+}
+
+pub trait AuditSink {
+    fn record(&mut self, event: &AuditEvent);
+}
+
+impl<F: FnMut(&AuditEvent)> AuditSink for F {
+    fn record(&mut self, event: &AuditEvent) {
+        self(event)
+    }
+}
+
+// Registers compared before and after each instruction.
+pub(crate) const AUDITED_REGISTERS: [Register; 20] = [
+    Register::V(0x0),
+    Register::V(0x1),
+    Register::V(0x2),
+    Register::V(0x3),
+    Register::V(0x4),
+    Register::V(0x5),
+    Register::V(0x6),
+    Register::V(0x7),
+    Register::V(0x8),
+    Register::V(0x9),
+    Register::V(0xA),
+    Register::V(0xB),
+    Register::V(0xC),
+    Register::V(0xD),
+    Register::V(0xE),
+    Register::V(0xF),
+    Register::I,
+    Register::SP,
+    Register::DT,
+    Register::ST,
+];
+
+impl fmt::Display for AuditEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditEvent::Read { pc, address, value } => write!(
+                f,
+                r#"{{"event":"read","pc":{},"address":{},"value":{}}}"#,
+                pc, address, value
+            ),
+            AuditEvent::Write {
+                pc,
+                address,
+                old,
+                new,
+            } => write!(
+                f,
+                r#"{{"event":"write","pc":{},"address":{},"old":{},"new":{}}}"#,
+                pc, address, old, new
+            ),
+            AuditEvent::Register {
+                pc,
+                register,
+                old,
+                new,
+            } => write!(
+                f,
+                r#"{{"event":"register","pc":{},"register":"{}","old":{},"new":{}}}"#,
+                pc, register, old, new
+            ),
+        }
+    }
+}
+
+// Holder for the installed sink, so the machine can stay `Debug`.
+pub(crate) struct Auditor(pub(crate) Box<dyn AuditSink>);
+
+impl fmt::Debug for Auditor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Auditor")
+    }
+}
@@ -0,0 +1,54 @@
+use std::fmt;
+
+use crate::cpu::Chip8;
+
+/// # Execution Backends
+///
+/// How instructions get executed is pluggable, so faster cores (cached
+/// decoding, translated blocks, ...) can be experimented with without
+/// destabilizing the default path. A backend is picked when the machine is
+/// created with `Chip8::with_backend`; `Chip8::new` uses the plain
+/// `Interpreter`.
+///
+/// Backends drive the machine through its public API: `fetch` reads the
+/// opcode at PC and advances PC past it, `execute` runs a single opcode.
+/// Whatever a backend does, the observable machine state after each `step`
+/// or `run` must be the same as with the plain interpreter executing the same
+/// number of instructions.
+pub trait ExecutionBackend: fmt::Debug {
+    // Short name used in logs and reports.
+    fn name(&self) -> &'static str;
+
+    // Execute the instruction at PC.
+    fn step(&mut self, chip8: &mut Chip8);
+
+    // Execute up to `budget` instructions and return how many were executed,
+    // stopping early if the machine halts. Backends that run several
+    // instructions at once override this.
+    fn run(&mut self, chip8: &mut Chip8, budget: usize) -> usize {
+        for executed in 0..budget {
+            if chip8.is_halted() {
+                return executed;
+            }
+            self.step(chip8);
+        }
+        budget
+    }
+}
+
+/// Fetches, decodes and executes one instruction at a time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Interpreter;
+
+impl ExecutionBackend for Interpreter {
+    fn name(&self) -> &'static str {
+        "interpreter"
+    }
+
+    fn step(&mut self, chip8: &mut Chip8) {
+        // Faults are recorded on the machine, which reports them from `step`.
+        if let Some(opcode) = chip8.fetch() {
+            let _ = chip8.execute(opcode);
+        }
+    }
+}
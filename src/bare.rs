@@ -0,0 +1,528 @@
+use core::time::Duration;
+
+use crate::display::{HEIGHT, HIRES_HEIGHT, HIRES_WIDTH, WIDTH};
+use crate::fault::{Access, Chip8Error, Fault};
+use crate::font::FontSet;
+use crate::instruction::{decode, Instruction};
+use crate::memory::{self, MemoryError};
+use crate::quirks::Quirks;
+use crate::random::RandomSource;
+use crate::timers::Timers;
+
+/// # Bare Machine
+///
+/// `Chip8` allocates its memory, screen and instrumentation on the heap and
+/// reads the clock and the operating system's entropy. `Machine` is the
+/// CHIP-8 and SUPER-CHIP core alone, for microcontrollers, and builds
+/// without the `std` feature: 4KB of memory, the screen and the stack are
+/// fixed size arrays inside the struct, about 5.3KB altogether, so it can
+/// live in a `static` or on the stack.
+///
+/// Everything the machine cannot know itself comes from the host. Cxkk
+/// draws from the `RandomSource` it was created with, e.g. a `SplitMix64`
+/// seeded from an ADC or a hardware RNG peripheral. The timers only run
+/// when told: `run_frame` ticks them once after its instructions, hosts
+/// stepping on their own call `tick_timers` at 60Hz, or pass the time a
+/// hardware timer measured to `advance_timers`. Keys are reported with
+/// `set_key`.
+///
+/// Any fault halts the machine, there is nobody to report it to while it
+/// carries on. XO-CHIP instructions are invalid opcodes; its 64KB of
+/// memory would not fit most boards.
+#[derive(Debug, Clone)]
+pub struct Machine<R> {
+    memory: [u8; memory::SIZE],
+    v_registers: [u8; 16],
+    i_register: u16,
+    program_counter: u16,
+
+    // Return addresses, `stack_pointer` of them in use
+    stack: [u16; 16],
+    stack_pointer: u8,
+
+    // SUPER-CHIP RPL user flags, Fx75 and Fx85
+    rpl_flags: [u8; 16],
+    timers: Timers,
+    keys: [bool; 16],
+
+    // Fx0A: whether the program waits for a key, and the key held down
+    // since, which it gets once released
+    waiting: bool,
+    latched: Option<u8>,
+    screen: Framebuffer,
+    quirks: Quirks,
+    rng: R,
+    fault: Option<Fault>,
+    exited: bool,
+}
+
+/// The screen of a `Machine`, one `u128` per row with the leftmost pixel
+/// in the highest bit. In the 64x32 mode rows take the upper 64 bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Framebuffer {
+    rows: [u128; HIRES_HEIGHT],
+    hires: bool,
+}
+
+impl<R: RandomSource> Machine<R> {
+    pub fn new(rng: R) -> Machine<R> {
+        let mut machine = Machine {
+            memory: [0; memory::SIZE],
+            v_registers: [0; 16],
+            i_register: 0,
+            program_counter: memory::INTERPRETER_END as u16,
+            stack: [0; 16],
+            stack_pointer: 0,
+            rpl_flags: [0; 16],
+            timers: Timers::new(),
+            keys: [false; 16],
+            waiting: false,
+            latched: None,
+            screen: Framebuffer::new(),
+            quirks: Quirks::default(),
+            rng,
+            fault: None,
+            exited: false,
+        };
+        machine.set_font(FontSet::default());
+        machine
+    }
+
+    // Copy a program to 0x200, where execution starts.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), MemoryError> {
+        let start = memory::INTERPRETER_END;
+        let program = self
+            .memory
+            .get_mut(start..start + rom.len())
+            .ok_or(MemoryError::OutOfBounds(memory::SIZE))?;
+        program.copy_from_slice(rom);
+        self.program_counter = start as u16;
+        Ok(())
+    }
+
+    // Replace the digits in the interpreter area with those of `font`.
+    pub fn set_font(&mut self, font: FontSet) {
+        let fonts = [
+            (memory::FONT_ADDRESS, font.small().as_flattened()),
+            (memory::BIG_FONT_ADDRESS, font.big().as_flattened()),
+        ];
+        for (address, digits) in fonts {
+            self.memory[address..address + digits.len()].copy_from_slice(digits);
+        }
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        if let Some(held) = self.keys.get_mut(key as usize) {
+            *held = pressed;
+        }
+    }
+
+    pub fn screen(&self) -> &Framebuffer {
+        &self.screen
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn v_registers(&self) -> &[u8; 16] {
+        &self.v_registers
+    }
+
+    pub fn i_register(&self) -> u16 {
+        self.i_register
+    }
+
+    pub fn program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    pub fn timers(&self) -> &Timers {
+        &self.timers
+    }
+
+    // Whether the buzzer sounds.
+    pub fn is_beeping(&self) -> bool {
+        self.timers.is_beeping()
+    }
+
+    // Whether the program is blocked in Fx0A until a key is pressed and
+    // released.
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.waiting
+    }
+
+    // Whether the program ended with 00FD.
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    // The fault the machine halted on, if any.
+    pub fn fault(&self) -> Option<Fault> {
+        self.fault
+    }
+
+    // Count both timers down by one, at 60Hz.
+    pub fn tick_timers(&mut self) {
+        self.timers.tick();
+    }
+
+    // Tick the timers for `elapsed` time, returning the number of ticks.
+    pub fn advance_timers(&mut self, elapsed: Duration) -> u32 {
+        self.timers.advance(elapsed)
+    }
+
+    // Run up to `cycles` instructions and tick the timers once.
+    pub fn run_frame(&mut self, cycles: usize) -> Result<(), Fault> {
+        let result = (0..cycles).try_for_each(|_| self.step());
+        self.tick_timers();
+        result
+    }
+
+    // Fetch and execute one instruction. Once halted on a fault, the fault
+    // is returned again; after 00FD nothing runs anymore.
+    pub fn step(&mut self) -> Result<(), Fault> {
+        if let Some(fault) = self.fault {
+            return Err(fault);
+        }
+        if self.exited {
+            return Ok(());
+        }
+        let result = self.fetch().and_then(|opcode| {
+            self.program_counter = self.program_counter.wrapping_add(2);
+            self.execute(opcode)
+        });
+        result.map_err(|kind| {
+            let fault = Fault {
+                kind,
+                program_counter: self.program_counter,
+            };
+            self.fault = Some(fault);
+            fault
+        })
+    }
+
+    fn fetch(&self) -> Result<u16, Chip8Error> {
+        let pc = self.program_counter as usize;
+        match self.memory.get(pc..pc + 2) {
+            Some(&[high, low]) => Ok(u16::from_be_bytes([high, low])),
+            _ => Err(Chip8Error::MemoryOutOfBounds {
+                access: Access::Fetch,
+                address: pc,
+            }),
+        }
+    }
+
+    fn read(&self, address: usize) -> Result<u8, Chip8Error> {
+        self.memory
+            .get(address)
+            .copied()
+            .ok_or(Chip8Error::MemoryOutOfBounds {
+                access: Access::Read,
+                address,
+            })
+    }
+
+    // The interpreter area is write-protected, as on `Chip8` by default.
+    fn write(&mut self, address: usize, value: u8) -> Result<(), Chip8Error> {
+        if address < memory::INTERPRETER_END {
+            return Err(Chip8Error::WriteProtected(address));
+        }
+        let byte = self
+            .memory
+            .get_mut(address)
+            .ok_or(Chip8Error::MemoryOutOfBounds {
+                access: Access::Write,
+                address,
+            })?;
+        *byte = value;
+        Ok(())
+    }
+
+    fn execute(&mut self, opcode: u16) -> Result<(), Chip8Error> {
+        use Instruction::*;
+
+        let instruction = decode(opcode)
+            .ok()
+            .filter(|instruction| !instruction.is_xo_chip())
+            .ok_or(Chip8Error::InvalidOpcode(opcode))?;
+        let v = &mut self.v_registers;
+        let is_pressed = |key: u8| self.keys.get(key as usize) == Some(&true);
+        let mut skip = false;
+        match instruction {
+            // 0nnn - SYS addr, machine code routines are not emulated.
+            Sys { .. } => {}
+            ScrollDown { n } => self.screen.scroll_down(n as usize),
+            Clear => self.screen.clear(),
+            Return => {
+                if self.stack_pointer == 0 {
+                    return Err(Chip8Error::StackUnderflow);
+                }
+                self.stack_pointer -= 1;
+                self.program_counter = self.stack[self.stack_pointer as usize];
+            }
+            ScrollRight => self.screen.scroll_right(),
+            ScrollLeft => self.screen.scroll_left(),
+            Exit => self.exited = true,
+            LowResolution => self.screen.set_hires(false),
+            HighResolution => self.screen.set_hires(true),
+            Jump { addr } => self.program_counter = addr,
+            Call { addr } => {
+                if self.stack_pointer as usize == self.stack.len() {
+                    return Err(Chip8Error::StackOverflow);
+                }
+                self.stack[self.stack_pointer as usize] = self.program_counter;
+                self.stack_pointer += 1;
+                self.program_counter = addr;
+            }
+            SkipIfEqual { x, byte } => skip = v[x as usize] == byte,
+            SkipIfNotEqual { x, byte } => skip = v[x as usize] != byte,
+            SkipIfRegistersEqual { x, y } => skip = v[x as usize] == v[y as usize],
+            Load { x, byte } => v[x as usize] = byte,
+            AddByte { x, byte } => v[x as usize] = v[x as usize].wrapping_add(byte),
+            Move { x, y } => v[x as usize] = v[y as usize],
+            Or { x, y } | And { x, y } | Xor { x, y } => {
+                let (vx, vy) = (v[x as usize], v[y as usize]);
+                v[x as usize] = match instruction {
+                    Or { .. } => vx | vy,
+                    And { .. } => vx & vy,
+                    _ => vx ^ vy,
+                };
+                if self.quirks.vf_reset {
+                    v[0xF] = 0;
+                }
+            }
+            // The flag is written last, so that it wins when x is F.
+            Add { x, y } => {
+                let (sum, carry) = v[x as usize].overflowing_add(v[y as usize]);
+                v[x as usize] = sum;
+                v[0xF] = carry as u8;
+            }
+            Sub { x, y } | SubN { x, y } => {
+                let (from, by) = match instruction {
+                    Sub { .. } => (v[x as usize], v[y as usize]),
+                    _ => (v[y as usize], v[x as usize]),
+                };
+                let (difference, borrow) = from.overflowing_sub(by);
+                v[x as usize] = difference;
+                v[0xF] = !borrow as u8;
+            }
+            ShiftRight { x, y } | ShiftLeft { x, y } => {
+                let value = v[if self.quirks.shifting { x } else { y } as usize];
+                let (shifted, flag) = match instruction {
+                    ShiftRight { .. } => (value >> 1, value & 0x1),
+                    _ => (value << 1, value >> 7),
+                };
+                v[x as usize] = shifted;
+                v[0xF] = flag;
+            }
+            SkipIfRegistersNotEqual { x, y } => skip = v[x as usize] != v[y as usize],
+            LoadI { addr } => self.i_register = addr,
+            JumpWithOffset { addr } => {
+                let x = if self.quirks.jumping { addr >> 8 } else { 0 };
+                let target = addr as usize + v[x as usize] as usize;
+                self.program_counter = (target % memory::SIZE) as u16;
+            }
+            Random { x, byte } => v[x as usize] = self.rng.next_byte() & byte,
+            Draw { x, y, n } => self.draw(x, y, n)?,
+            SkipIfKeyPressed { x } => skip = is_pressed(v[x as usize]),
+            SkipIfKeyNotPressed { x } => skip = !is_pressed(v[x as usize]),
+            LoadDelayTimer { x } => v[x as usize] = self.timers.delay,
+            WaitForKey { x } => match self.latched {
+                Some(key) if !self.keys[key as usize] => {
+                    v[x as usize] = key;
+                    self.waiting = false;
+                    self.latched = None;
+                }
+                latched => {
+                    self.waiting = true;
+                    self.latched = latched.or_else(|| (0..16).find(|&key| self.keys[key as usize]));
+                    self.program_counter -= 2;
+                }
+            },
+            SetDelayTimer { x } => self.timers.delay = v[x as usize],
+            SetSoundTimer { x } => self.timers.sound = v[x as usize],
+            AddToI { x } => self.i_register = self.i_register.wrapping_add(v[x as usize] as u16),
+            LoadDigit { x } => {
+                let digit = (v[x as usize] & 0x0F) as usize;
+                self.i_register = (memory::FONT_ADDRESS + digit * 5) as u16;
+            }
+            LoadBigDigit { x } => {
+                let digit = (v[x as usize] & 0x0F) as usize;
+                self.i_register = (memory::BIG_FONT_ADDRESS + digit * 10) as u16;
+            }
+            StoreBcd { x } => {
+                let (value, i) = (v[x as usize], self.i_register as usize);
+                for (offset, digit) in [value / 100, value / 10 % 10, value % 10]
+                    .into_iter()
+                    .enumerate()
+                {
+                    self.write(i + offset, digit)?;
+                }
+            }
+            StoreRegisters { x } => {
+                for register in 0..=x as usize {
+                    let value = self.v_registers[register];
+                    self.write(self.i_register as usize + register, value)?;
+                }
+                if self.quirks.memory {
+                    self.i_register = self.i_register.wrapping_add(x as u16 + 1);
+                }
+            }
+            LoadRegisters { x } => {
+                for register in 0..=x as usize {
+                    self.v_registers[register] = self.read(self.i_register as usize + register)?;
+                }
+                if self.quirks.memory {
+                    self.i_register = self.i_register.wrapping_add(x as u16 + 1);
+                }
+            }
+            StoreRplFlags { x } => {
+                let count = x as usize + 1;
+                self.rpl_flags[..count].copy_from_slice(&v[..count]);
+            }
+            LoadRplFlags { x } => {
+                let count = x as usize + 1;
+                v[..count].copy_from_slice(&self.rpl_flags[..count]);
+            }
+            ScrollUp { .. }
+            | StoreRegisterRange { .. }
+            | LoadRegisterRange { .. }
+            | LoadILong
+            | SelectPlanes { .. }
+            | LoadAudioPattern
+            | SetPitch { .. } => unreachable!("XO-CHIP instructions are rejected above"),
+        }
+        if skip {
+            self.program_counter = self.program_counter.wrapping_add(2);
+        }
+        Ok(())
+    }
+
+    // Dxyn - DRW Vx, Vy, nibble
+    // Display n-byte sprite starting at memory location I at (Vx, Vy), set
+    // VF = collision. Dxy0 displays a 16x16 sprite of 32 bytes.
+    fn draw(&mut self, x: u8, y: u8, n: u8) -> Result<(), Chip8Error> {
+        let (x, y) = (
+            self.v_registers[x as usize] as usize,
+            self.v_registers[y as usize] as usize,
+        );
+        let mut sprite = [0; 32];
+        let len = if n == 0 { sprite.len() } else { n as usize };
+        for (offset, byte) in sprite[..len].iter_mut().enumerate() {
+            *byte = self.read(self.i_register as usize + offset)?;
+        }
+        let collision = if n == 0 {
+            let rows = sprite
+                .chunks(2)
+                .map(|row| (u16::from_be_bytes([row[0], row[1]]) as u128) << 112);
+            self.screen.draw(x, y, rows)
+        } else {
+            let rows = sprite[..len].iter().map(|&byte| (byte as u128) << 120);
+            self.screen.draw(x, y, rows)
+        };
+        self.v_registers[0xF] = collision as u8;
+        Ok(())
+    }
+}
+
+impl Framebuffer {
+    fn new() -> Framebuffer {
+        Framebuffer {
+            rows: [0; HIRES_HEIGHT],
+            hires: false,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        if self.hires {
+            HIRES_WIDTH
+        } else {
+            WIDTH
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        if self.hires {
+            HIRES_HEIGHT
+        } else {
+            HEIGHT
+        }
+    }
+
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    // The rows of the current resolution, e.g. to copy into the buffer of
+    // a display controller.
+    pub fn rows(&self) -> &[u128] {
+        &self.rows[..self.height()]
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        let row = self.rows[y % self.height()];
+        row >> (127 - x % self.width()) & 1 == 1
+    }
+
+    fn clear(&mut self) {
+        self.rows = [0; HIRES_HEIGHT];
+    }
+
+    // 00FE - LOW, 00FF - HIGH
+    // Switch resolution, clearing the screen.
+    fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.clear();
+    }
+
+    // 00Cn - SCD n
+    fn scroll_down(&mut self, n: usize) {
+        let (height, n) = (self.height(), n.min(self.height()));
+        self.rows.copy_within(..height - n, n);
+        self.rows[..n].fill(0);
+    }
+
+    // 00FB - SCR, keeping 64x32 rows in their upper half.
+    fn scroll_right(&mut self) {
+        let mask = u128::MAX << (128 - self.width());
+        for row in &mut self.rows {
+            *row = *row >> 4 & mask;
+        }
+    }
+
+    // 00FC - SCL
+    fn scroll_left(&mut self) {
+        for row in &mut self.rows {
+            *row <<= 4;
+        }
+    }
+
+    // XOR rows of a sprite, the leftmost pixel in the highest bit, onto the
+    // screen at (x, y), wrapping around the edges like `Display` does.
+    // Returns whether any lit pixel was switched off.
+    fn draw(&mut self, x: usize, y: usize, rows: impl Iterator<Item = u128>) -> bool {
+        let (width, height) = (self.width(), self.height());
+        let shift = (x % width) as u32;
+        let mut collision = false;
+        for (offset, bits) in rows.enumerate() {
+            let bits = if self.hires {
+                bits.rotate_right(shift)
+            } else {
+                (((bits >> 64) as u64).rotate_right(shift) as u128) << 64
+            };
+            let row = &mut self.rows[(y + offset) % height];
+            collision |= *row & bits != 0;
+            *row ^= bits;
+        }
+        collision
+    }
+}
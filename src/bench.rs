@@ -0,0 +1,142 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::cpu::Chip8;
+
+/// # Benchmark
+///
+/// Measures how many instructions per second the emulator achieves on this
+/// machine, on small programs which each stress one part of it: `draw`
+/// draws sprites all over the screen, `math` runs the arithmetic and logic
+/// of 8xyN, and `memory` copies blocks of memory with Fx65 and Fx55. Run
+/// under each backend, they compare the plain interpreter with the cache of
+/// decoded instructions and the block translator.
+///
+/// `chip8 bench` prints the results as a table, and the criterion suite in
+/// `benches/workloads.rs` runs the same programs to catch regressions.
+/// Frames run `CYCLES_PER_FRAME` instructions, far more than real time
+/// would, so the time measured is spent emulating rather than waiting.
+#[derive(Debug, Clone, Copy)]
+pub struct Workload {
+    pub name: &'static str,
+    program: &'static [u16],
+}
+
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    pub workload: &'static str,
+    pub backend: &'static str,
+    pub instructions: u64,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    // Every workload under every backend, workloads first
+    pub measurements: Vec<Measurement>,
+}
+
+// Instructions per frame of the workloads.
+pub const CYCLES_PER_FRAME: usize = 10_000;
+
+pub const WORKLOADS: &[Workload] = &[
+    Workload {
+        name: "draw",
+        // LD V0, 0; LD V1, 0; LD V3, 0x0F; LD F, V2; DRW V0, V1, 5;
+        // ADD V0, 5; ADD V1, 3; ADD V2, 1; AND V2, V3; JP 0x206
+        program: &[
+            0x6000, 0x6100, 0x630F, 0xF229, 0xD015, 0x7005, 0x7103, 0x7201, 0x8232, 0x1206,
+        ],
+    },
+    Workload {
+        name: "math",
+        // LD V0, 1; LD V1, 3; ADD V0, V1; SUB V1, V0; SHL V2, V0;
+        // XOR V3, V2; OR V4, V1; SHR V4, V4; ADD V5, 7; AND V5, V0;
+        // SE V5, 0; ADD V6, 1; JP 0x204
+        program: &[
+            0x6001, 0x6103, 0x8014, 0x8105, 0x820E, 0x8323, 0x8411, 0x8446, 0x7507, 0x8502, 0x3500,
+            0x7601, 0x1204,
+        ],
+    },
+    Workload {
+        name: "memory",
+        // LD I, 0x300; ADD I, VF; LD VE, [I]; LD I, 0x400; ADD I, VF;
+        // LD [I], VE; ADD VF, 15; JP 0x200
+        program: &[
+            0xA300, 0xFF1E, 0xFE65, 0xA400, 0xFF1E, 0xFE55, 0x7F0F, 0x1200,
+        ],
+    },
+];
+
+impl Workload {
+    pub fn rom(&self) -> Vec<u8> {
+        self.program
+            .iter()
+            .flat_map(|op| op.to_be_bytes())
+            .collect()
+    }
+
+    // Load the workload into `chip8` and time `frames` frames of it.
+    pub fn measure(&self, mut chip8: Chip8, frames: u64) -> Measurement {
+        chip8.set_seed(0);
+        chip8
+            .load_rom(&self.rom())
+            .expect("the workloads fit in memory");
+        let instructions = chip8.telemetry().instructions;
+        let started = Instant::now();
+        for _ in 0..frames {
+            chip8.run_frame(CYCLES_PER_FRAME);
+        }
+        Measurement {
+            workload: self.name,
+            backend: chip8.backend_name(),
+            instructions: chip8.telemetry().instructions - instructions,
+            elapsed: started.elapsed(),
+        }
+    }
+}
+
+impl Measurement {
+    // Instructions per second, 0 if no time could be measured.
+    pub fn ips(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.instructions as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+// Measure every workload for `frames` frames under each of `backends`,
+// with machines made by `new_machine` from a backend name.
+pub fn run(backends: &[&str], frames: u64, new_machine: impl Fn(&str) -> Chip8) -> Report {
+    let mut measurements = Vec::new();
+    for workload in WORKLOADS {
+        for backend in backends {
+            measurements.push(workload.measure(new_machine(backend), frames));
+        }
+    }
+    Report { measurements }
+}
+
+// One row of millions of instructions per second per workload, one column
+// per backend.
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let columns = self.measurements.len() / WORKLOADS.len().max(1);
+        let width = WORKLOADS.iter().map(|w| w.name.len()).max().unwrap_or(0);
+        write!(f, "{:w$}", "", w = width)?;
+        for measurement in self.measurements.iter().take(columns) {
+            write!(f, " {:>16}", measurement.backend)?;
+        }
+        for row in self.measurements.chunks(columns.max(1)) {
+            write!(f, "\n{:w$}", row[0].workload, w = width)?;
+            for measurement in row {
+                let mips = format!("{:.1} MIPS", measurement.ips() / 1e6);
+                write!(f, " {:>16}", mips)?;
+            }
+        }
+        Ok(())
+    }
+}
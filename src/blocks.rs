@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::backend::{ExecutionBackend, Interpreter};
+use crate::cpu::Chip8;
+use crate::instruction::{decode, Instruction};
+
+/// # Block Translation
+///
+/// `BlockTranslator` interprets code normally while counting how often each
+/// address is executed. Once an address gets hot, the straight-line run of
+/// instructions starting there (up to and including the first instruction
+/// that can change the flow of control) is decoded once into a chain of
+/// closures. Later visits run the chain directly, skipping fetch and decode.
+///
+/// Simple register instructions (6xkk, 7xkk, 8xy0, Annn) are translated to
+/// closures working on the register file; everything else is decoded once
+/// at translation and executed from the decoded instruction.
+///
+/// Writes into memory covered by a translated block throw the block away,
+/// and a chain stops right after an instruction that modified its own
+/// block, so self-modifying programs keep working. While the access map, the
+/// profile, audit mode or tracing is enabled everything is interpreted, so
+/// they stay exact.
+pub struct BlockTranslator {
+    counts: HashMap<u16, u32>,
+    blocks: HashMap<u16, Rc<Block>>,
+}
+
+// Executions of an address before its block gets translated.
+const HOT_THRESHOLD: u32 = 16;
+
+// Longest block translated, in instructions.
+const MAX_BLOCK_LENGTH: usize = 64;
+
+type Op = Box<dyn Fn(&mut Chip8)>;
+
+struct Block {
+    start: u16,
+    ops: Vec<Op>,
+}
+
+impl Block {
+    // Address one past the last instruction of the block, which is past
+    // the end of memory for blocks ending at 0xFFFE on XO-CHIP.
+    fn end(&self) -> usize {
+        self.start as usize + 2 * self.ops.len()
+    }
+
+    fn overlaps(&self, (low, high): (usize, usize)) -> bool {
+        (self.start as usize) <= high && low < self.end()
+    }
+}
+
+impl BlockTranslator {
+    pub fn new() -> BlockTranslator {
+        BlockTranslator {
+            counts: HashMap::new(),
+            blocks: HashMap::new(),
+        }
+    }
+
+    // Number of blocks currently translated.
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    // Drop every block overlapping memory written since the last check.
+    // Returns the written range, if any.
+    fn invalidate(&mut self, chip8: &mut Chip8) -> Option<(usize, usize)> {
+        let range = chip8.take_written_range()?;
+        self.blocks.retain(|_, block| !block.overlaps(range));
+        Some(range)
+    }
+
+    // Interpret the instruction at PC, translating its block once it is hot.
+    fn interpret(&mut self, chip8: &mut Chip8) {
+        let pc = chip8.program_counter;
+        Interpreter.step(chip8);
+        self.invalidate(chip8);
+        if chip8.is_instrumented() {
+            return;
+        }
+        let count = self.counts.entry(pc).or_insert(0);
+        *count += 1;
+        if *count >= HOT_THRESHOLD {
+            self.counts.remove(&pc);
+            if let Some(block) = translate(chip8, pc) {
+                self.blocks.insert(pc, Rc::new(block));
+            }
+        }
+    }
+
+    // Run up to `budget` instructions of a translated block, returning how
+    // many were executed.
+    fn run_block(&mut self, chip8: &mut Chip8, block: &Block, budget: usize) -> usize {
+        let mut executed = 0;
+        for op in block.ops.iter().take(budget) {
+            executed += 1;
+            chip8.program_counter = block.start.wrapping_add(2 * executed as u16);
+            op(chip8);
+            if let Some(range) = self.invalidate(chip8) {
+                if block.overlaps(range) {
+                    break;
+                }
+            }
+            if chip8.is_halted() {
+                break;
+            }
+        }
+        executed
+    }
+}
+
+impl Default for BlockTranslator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for BlockTranslator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockTranslator")
+            .field("blocks", &self.blocks.len())
+            .finish()
+    }
+}
+
+impl ExecutionBackend for BlockTranslator {
+    fn name(&self) -> &'static str {
+        "blocks"
+    }
+
+    fn step(&mut self, chip8: &mut Chip8) {
+        self.run(chip8, 1);
+    }
+
+    fn run(&mut self, chip8: &mut Chip8, budget: usize) -> usize {
+        // Memory may have been written from outside since, e.g. by a poke.
+        self.invalidate(chip8);
+        let mut executed = 0;
+        while executed < budget && !chip8.is_halted() {
+            let block = match self.blocks.get(&chip8.program_counter) {
+                Some(block) if !chip8.is_instrumented() => Rc::clone(block),
+                _ => {
+                    self.interpret(chip8);
+                    executed += 1;
+                    continue;
+                }
+            };
+            executed += self.run_block(chip8, &block, budget - executed);
+        }
+        executed
+    }
+}
+
+// Decode the block starting at `start`, or `None` if there is nothing to
+// translate there.
+fn translate(chip8: &Chip8, start: u16) -> Option<Block> {
+    let mut ops = Vec::new();
+    let mut addr = start as usize;
+    while ops.len() < MAX_BLOCK_LENGTH {
+        let (Some(high), Some(low)) = (chip8.peek(addr), chip8.peek(addr + 1)) else {
+            break;
+        };
+        let opcode = u16::from_be_bytes([high, low]);
+        ops.push(compile(opcode));
+        addr += 2;
+        if ends_block(opcode) {
+            break;
+        }
+    }
+    if ops.is_empty() {
+        None
+    } else {
+        Some(Block { start, ops })
+    }
+}
+
+// Whether the instruction may continue anywhere but the next address.
+// Opcodes which do not decode end the block too, as they may fault.
+fn ends_block(opcode: u16) -> bool {
+    decode(opcode).map_or(true, Instruction::is_branch)
+}
+
+// Turn one opcode into a closure, with PC already pointing past it.
+fn compile(opcode: u16) -> Op {
+    let instruction = decode(opcode);
+    match instruction {
+        Ok(Instruction::Load { x, byte }) => Box::new(move |chip8: &mut Chip8| {
+            chip8.telemetry.instructions += 1;
+            chip8.v_registers[x as usize] = byte;
+        }),
+        Ok(Instruction::AddByte { x, byte }) => Box::new(move |chip8: &mut Chip8| {
+            chip8.telemetry.instructions += 1;
+            let x = x as usize;
+            chip8.v_registers[x] = chip8.v_registers[x].wrapping_add(byte);
+        }),
+        Ok(Instruction::Move { x, y }) => Box::new(move |chip8: &mut Chip8| {
+            chip8.telemetry.instructions += 1;
+            chip8.v_registers[x as usize] = chip8.v_registers[y as usize];
+        }),
+        Ok(Instruction::LoadI { addr }) => Box::new(move |chip8: &mut Chip8| {
+            chip8.telemetry.instructions += 1;
+            chip8.i_register = addr;
+        }),
+        // Blocks only run on machines which are not instrumented, so nothing
+        // `execute` would feed is missed.
+        _ => {
+            let instruction = instruction.ok();
+            Box::new(move |chip8: &mut Chip8| chip8.execute_decoded(opcode, instruction))
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// # ROM Browser
+///
+/// The ROMs in a directory, for switching games from inside a frontend
+/// instead of relaunching it from the command line. Files with one of the
+/// `EXTENSIONS` of CHIP-8 programs and cartridges are listed by name;
+/// subdirectories are not searched. The directory is read again whenever
+/// the list is shown, so ROMs saved in the meantime show up.
+///
+/// F4 shows and hides the list. While it is shown, the keys it uses do not
+/// reach the keypad:
+///
+/// - Up and Down move the selection, PageUp and PageDown by a page, Home
+///   and End to the first and the last ROM
+/// - a letter or digit selects the next ROM whose name starts with it
+/// - Return picks the selected ROM, which `handle` hands to the frontend
+///   to load, and hides the list
+#[derive(Debug, Clone)]
+pub struct RomBrowser {
+    dir: PathBuf,
+    roms: Vec<PathBuf>,
+    selected: usize,
+    page_size: usize,
+    shown: bool,
+
+    // Why the directory could not be read the last time
+    error: Option<String>,
+}
+
+/// What became of a key given to `RomBrowser::handle`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BrowserAction {
+    // Not a key of the browser, e.g. one for the keypad
+    Ignored,
+    Handled,
+
+    // The ROM picked with Return
+    Load(PathBuf),
+}
+
+pub const TOGGLE_KEY: &str = "F4";
+
+pub const EXTENSIONS: [&str; 6] = ["ch8", "c8", "sc8", "xo8", "c8x", "c8b"];
+
+impl RomBrowser {
+    // A browser over `dir`, showing `page_size` ROMs at a time.
+    pub fn new(dir: &Path, page_size: usize) -> RomBrowser {
+        RomBrowser {
+            dir: dir.to_path_buf(),
+            roms: Vec::new(),
+            selected: 0,
+            page_size: page_size.max(1),
+            shown: false,
+            error: None,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn roms(&self) -> &[PathBuf] {
+        &self.roms
+    }
+
+    pub fn selected(&self) -> Option<&Path> {
+        self.roms.get(self.selected).map(PathBuf::as_path)
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.shown
+    }
+
+    // Read the directory again, keeping the selected ROM if it is still
+    // there.
+    pub fn refresh(&mut self) {
+        let selected = self.selected().map(Path::to_path_buf);
+        match fs::read_dir(&self.dir) {
+            Ok(entries) => {
+                self.roms = entries
+                    .filter_map(|entry| Some(entry.ok()?.path()))
+                    .filter(|path| path.is_file() && is_rom(path))
+                    .collect();
+                self.roms.sort_by_key(|path| sort_key(path));
+                self.error = None;
+            }
+            Err(e) => {
+                self.roms.clear();
+                self.error = Some(format!("Cannot read {}: {}", self.dir.display(), e));
+            }
+        }
+        self.selected = 0;
+        if let Some(path) = selected {
+            self.select(&path);
+        }
+    }
+
+    // Select the ROM at `path`, e.g. the one running, if it is listed.
+    pub fn select(&mut self, path: &Path) {
+        let name = path.file_name();
+        if let Some(index) = self.roms.iter().position(|rom| rom.file_name() == name) {
+            self.selected = index;
+        }
+    }
+
+    // Show or hide the list, reading the directory when it is shown.
+    pub fn toggle(&mut self) {
+        self.shown = !self.shown;
+        if self.shown {
+            self.refresh();
+        }
+    }
+
+    // Act on a host key, named as for `KeyMap`.
+    pub fn handle(&mut self, key: &str) -> BrowserAction {
+        if key == TOGGLE_KEY {
+            self.toggle();
+            return BrowserAction::Handled;
+        }
+        if !self.shown {
+            return BrowserAction::Ignored;
+        }
+        let last = self.roms.len().saturating_sub(1);
+        let page = self.page_size;
+        match key.to_ascii_lowercase().as_str() {
+            "up" => self.selected = self.selected.saturating_sub(1),
+            "down" => self.selected = (self.selected + 1).min(last),
+            "pageup" => self.selected = self.selected.saturating_sub(page),
+            "pagedown" => self.selected = (self.selected + page).min(last),
+            "home" => self.selected = 0,
+            "end" => self.selected = last,
+            "return" => {
+                let Some(rom) = self.selected().map(Path::to_path_buf) else {
+                    return BrowserAction::Handled;
+                };
+                self.shown = false;
+                return BrowserAction::Load(rom);
+            }
+            name => {
+                let mut chars = name.chars();
+                let (Some(initial), None) = (chars.next(), chars.next()) else {
+                    return BrowserAction::Ignored;
+                };
+                if !initial.is_ascii_alphanumeric() {
+                    return BrowserAction::Ignored;
+                }
+                self.jump_to_initial(initial);
+            }
+        }
+        BrowserAction::Handled
+    }
+
+    // Select the next ROM after the selected one starting with `initial`,
+    // wrapping around.
+    fn jump_to_initial(&mut self, initial: char) {
+        let count = self.roms.len();
+        self.selected = (1..=count)
+            .map(|step| (self.selected + step) % count)
+            .find(|&index| sort_key(&self.roms[index]).starts_with(initial))
+            .unwrap_or(self.selected);
+    }
+
+    // Text lines of the list: the directory with the position of the
+    // selection, then the page of ROM names holding it, the selected one
+    // marked with `>`.
+    pub fn render(&self) -> Vec<String> {
+        if let Some(error) = &self.error {
+            return vec![error.clone()];
+        }
+        if self.roms.is_empty() {
+            return vec![format!("No ROMs in {}", self.dir.display())];
+        }
+        let start = self.selected / self.page_size * self.page_size;
+        let end = (start + self.page_size).min(self.roms.len());
+        let mut lines = vec![format!(
+            "{} ({}/{})",
+            self.dir.display(),
+            self.selected + 1,
+            self.roms.len()
+        )];
+        for (index, rom) in self.roms[start..end].iter().enumerate() {
+            let marker = if start + index == self.selected {
+                '>'
+            } else {
+                ' '
+            };
+            let name = rom.file_name().unwrap_or_default().to_string_lossy();
+            lines.push(format!("{} {}", marker, name));
+        }
+        lines
+    }
+}
+
+// Whether the file has the extension of a ROM, in any case.
+pub fn is_rom(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+// ROMs are sorted by name regardless of case.
+fn sort_key(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase()
+}
@@ -0,0 +1,117 @@
+use crate::cartridge::{Cartridge, CartridgeError, Reader, Variant};
+
+const MAGIC: &[u8; 3] = b"CBF";
+const VERSION: u8 = 0;
+
+// Variants this emulator can run, in order of preference.
+const SUPPORTED: &[Variant] = &[Variant::XoChip, Variant::SuperChip, Variant::Chip8];
+
+pub fn is_c8b(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// # CHIP-8 Binary Format
+///
+/// A `.c8b` file bundles several builds of the same program, one per target
+/// platform, together with a table of optional properties. The loader picks
+/// the code segment for the most capable variant this emulator supports and
+/// turns it into a `Cartridge`. All multi-byte values are stored
+/// most-significant-byte first.
+///
+/// ```text
+/// +--------+------------------------------------------------------+
+/// | Offset | Content                                              |
+/// +--------+------------------------------------------------------+
+/// | 0      | Magic "CBF"                                          |
+/// | 3      | Format version (0)                                   |
+/// | 4      | Offset of the properties table (0 for none)          |
+/// | 6      | Bytecode table, 5 bytes per entry, ended by a 0 byte |
+/// |        |   platform (1 byte), offset (2 bytes), size (2 bytes)|
+/// +--------+------------------------------------------------------+
+/// ```
+///
+/// Platforms are 0x01 CHIP-8, 0x02 SUPER-CHIP and 0x03 XO-CHIP; entries for
+/// other platforms are skipped.
+///
+/// The properties table is a list of `key, length, data` records ended by a
+/// 0xFF key. Unknown keys are skipped.
+///
+/// - 0x00 - Designer (UTF-8)
+/// - 0x01 - Description (UTF-8)
+/// - 0x02 - Program name (UTF-8)
+/// - 0x04 - Tickrate (2 bytes)
+/// - 0x05 - Background and foreground colors (6 bytes, RGB)
+pub fn parse(bytes: &[u8]) -> Result<Cartridge, CartridgeError> {
+    if !is_c8b(bytes) {
+        return Err(CartridgeError::BadMagic);
+    }
+    let mut reader = Reader::new(bytes, MAGIC.len());
+    let version = reader.byte()?;
+    if version != VERSION {
+        return Err(CartridgeError::UnsupportedVersion(version));
+    }
+    let properties = reader.word()? as usize;
+
+    let mut segments = Vec::new();
+    loop {
+        let platform = reader.byte()?;
+        if platform == 0 {
+            break;
+        }
+        let offset = reader.word()? as usize;
+        let size = reader.word()? as usize;
+        if let Some(variant) = variant(platform) {
+            segments.push((variant, offset, size));
+        }
+    }
+
+    let (variant, offset, size) = SUPPORTED
+        .iter()
+        .find_map(|v| segments.iter().find(|s| s.0 == *v))
+        .copied()
+        .ok_or(CartridgeError::NoSupportedSegment)?;
+    let rom = Reader::new(bytes, offset).take(size)?;
+
+    let mut cartridge = Cartridge::from_rom(rom);
+    cartridge.variant = variant;
+    if properties != 0 {
+        apply_properties(&mut cartridge, Reader::new(bytes, properties))?;
+    }
+    Ok(cartridge)
+}
+
+fn variant(platform: u8) -> Option<Variant> {
+    match platform {
+        0x01 => Some(Variant::Chip8),
+        0x02 => Some(Variant::SuperChip),
+        0x03 => Some(Variant::XoChip),
+        _ => None,
+    }
+}
+
+fn apply_properties(
+    cartridge: &mut Cartridge,
+    mut reader: Reader<'_>,
+) -> Result<(), CartridgeError> {
+    loop {
+        let key = reader.byte()?;
+        if key == 0xFF {
+            return Ok(());
+        }
+        let len = reader.byte()? as usize;
+        let data = reader.take(len)?;
+        match (key, data) {
+            (0x00, _) => cartridge.author = text(data)?,
+            (0x02, _) => cartridge.title = text(data)?,
+            (0x04, &[high, low]) => cartridge.tickrate = Some(u16::from_be_bytes([high, low])),
+            (0x05, &[br, bg, bb, fr, fg, fb]) => {
+                cartridge.palette = Some([[br, bg, bb], [fr, fg, fb]]);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn text(data: &[u8]) -> Result<String, CartridgeError> {
+    String::from_utf8(data.to_vec()).map_err(|_| CartridgeError::InvalidText)
+}
@@ -0,0 +1,101 @@
+use crate::backend::{ExecutionBackend, Interpreter};
+use crate::cpu::Chip8;
+use crate::instruction::{decode, Instruction};
+
+/// # Cached Interpreter
+///
+/// `CachedInterpreter` interprets one instruction at a time like the plain
+/// `Interpreter`, but decodes each address only once: the decoded
+/// instruction is kept in a cache indexed by address, and later visits skip
+/// fetching and decoding it. Unlike the `BlockTranslator` there is no
+/// warm-up, which suits runs that go through a lot of code at high speed
+/// (fast-forward, fuzzing, headless batch runs).
+///
+/// Writes to memory drop the cached instructions covering the written
+/// bytes before the next instruction runs, so self-modifying programs keep
+/// working, and so does resizing memory, which drops them all. While the access map, the profile, audit mode or tracing is
+/// enabled everything goes through the plain interpreter, so they stay
+/// exact.
+#[derive(Debug, Clone, Default)]
+pub struct CachedInterpreter {
+    // Decoded instruction per address, none until it is first executed
+    cache: Vec<Option<Decoded>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Decoded {
+    opcode: u16,
+
+    // None if the opcode decodes to no instruction
+    instruction: Option<Instruction>,
+}
+
+impl CachedInterpreter {
+    pub fn new() -> CachedInterpreter {
+        CachedInterpreter::default()
+    }
+
+    // Number of addresses currently cached.
+    pub fn cached_count(&self) -> usize {
+        self.cache.iter().flatten().count()
+    }
+
+    // Drop the instructions overlapping memory written since the last
+    // check; an instruction starting a byte before a write covers it too.
+    // A change of memory size, e.g. from XO-CHIP's 64KB back to 4KB with
+    // the variant, drops everything.
+    fn invalidate(&mut self, chip8: &mut Chip8) {
+        let size = chip8.memory().len();
+        if self.cache.len() != size {
+            self.cache.clear();
+            self.cache.resize(size, None);
+        }
+        let Some((low, high)) = chip8.take_written_range() else {
+            return;
+        };
+        let end = (high + 1).min(self.cache.len());
+        let start = low.saturating_sub(1).min(end);
+        self.cache[start..end].fill(None);
+    }
+
+    // The instruction at `pc`, decoded now if it was not cached yet. None if
+    // it does not fit in memory.
+    fn lookup(&mut self, chip8: &Chip8, pc: usize) -> Option<Decoded> {
+        if let Some(decoded) = self.cache.get(pc).copied().flatten() {
+            return Some(decoded);
+        }
+        let opcode = u16::from_be_bytes([chip8.peek(pc)?, chip8.peek(pc + 1)?]);
+        let decoded = Decoded {
+            opcode,
+            instruction: decode(opcode).ok(),
+        };
+        self.cache[pc] = Some(decoded);
+        Some(decoded)
+    }
+}
+
+impl ExecutionBackend for CachedInterpreter {
+    fn name(&self) -> &'static str {
+        "cached"
+    }
+
+    fn step(&mut self, chip8: &mut Chip8) {
+        self.invalidate(chip8);
+        if chip8.is_instrumented() {
+            Interpreter.step(chip8);
+            return;
+        }
+        let pc = chip8.program_counter as usize;
+        match self.lookup(chip8, pc) {
+            Some(Decoded {
+                opcode,
+                instruction,
+            }) => {
+                chip8.program_counter = chip8.program_counter.wrapping_add(2);
+                chip8.execute_decoded(opcode, instruction);
+            }
+            // The interpreter raises the fault for fetching past the end.
+            None => Interpreter.step(chip8),
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::display::Display;
+use crate::palette::Palette;
+use crate::png::{self, Frame};
+
+/// # Screenshots and Recordings
+///
+/// `screenshot` encodes the screen as a PNG in the colors of a palette,
+/// every pixel `scale` pixels wide. A `Recorder` collects the screen after
+/// every emulated frame into an animated PNG playing at the emulated frame
+/// rate; runs of identical frames are kept once and shown for longer, so a
+/// mostly still screen makes a small file. When a recording switches
+/// resolution, every frame is scaled up to the largest one.
+///
+/// The frontends bind both to hotkeys through `Captures`: F12 saves a
+/// screenshot, F10 starts a recording and saves it when pressed again, or
+/// when the frontend closes. Given the path of the ROM, they are written
+/// next to it as `<rom>.shot<n>.png` and `<rom>.rec<n>.png`, with the first
+/// n not taken yet; otherwise into the working directory.
+pub fn screenshot(display: &Display, palette: &Palette, scale: u32) -> Vec<u8> {
+    let scale = scale.max(1) as usize;
+    let (width, height) = (display.width() * scale, display.height() * scale);
+    let indices = resize(display.colors(), display.width(), width, height);
+    png::encode_indexed(width as u32, height as u32, &palette.colors, &indices)
+}
+
+// Nearest neighbour scaling of `width` wide pixels to the given size.
+fn resize(pixels: &[u8], width: usize, to_width: usize, to_height: usize) -> Vec<u8> {
+    let height = pixels.len() / width;
+    (0..to_height)
+        .flat_map(|y| {
+            let row = y * height / to_height * width;
+            (0..to_width).map(move |x| pixels[row + x * width / to_width])
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    palette: Palette,
+    scale: u32,
+    frame_rate: u32,
+    frames: Vec<Recorded>,
+}
+
+// A screen and the number of frames in a row it was shown for.
+#[derive(Debug, Clone)]
+struct Recorded {
+    width: usize,
+    colors: Vec<u8>,
+    count: u32,
+}
+
+impl Recorder {
+    pub fn new(palette: Palette, scale: u32, frame_rate: u32) -> Recorder {
+        Recorder {
+            palette,
+            scale: scale.max(1),
+            frame_rate: frame_rate.clamp(1, u16::MAX as u32),
+            frames: Vec::new(),
+        }
+    }
+
+    // Add the screen at the end of a frame.
+    pub fn capture(&mut self, display: &Display) {
+        if let Some(last) = self.frames.last_mut() {
+            if last.width == display.width() && last.colors == display.colors() {
+                last.count += 1;
+                return;
+            }
+        }
+        self.frames.push(Recorded {
+            width: display.width(),
+            colors: display.colors().to_vec(),
+            count: 1,
+        });
+    }
+
+    // Frames captured so far.
+    pub fn len(&self) -> u64 {
+        self.frames.iter().map(|frame| frame.count as u64).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    // The recording as an animated PNG, none if nothing was captured.
+    pub fn encode(&self) -> Option<Vec<u8>> {
+        let scale = self.scale as usize;
+        let width = self.frames.iter().map(|frame| frame.width).max()? * scale;
+        let height = self
+            .frames
+            .iter()
+            .map(|frame| frame.colors.len() / frame.width)
+            .max()?
+            * scale;
+        let mut frames = Vec::new();
+        for recorded in &self.frames {
+            let indices = resize(&recorded.colors, recorded.width, width, height);
+            // Delays are 16-bit, long stills take several frames.
+            let mut count = recorded.count;
+            while count > 0 {
+                let delay = count.min(u16::MAX as u32);
+                frames.push(Frame {
+                    indices: indices.clone(),
+                    delay: (delay as u16, self.frame_rate as u16),
+                });
+                count -= delay;
+            }
+        }
+        Some(png::encode_apng(
+            width as u32,
+            height as u32,
+            &self.palette.colors,
+            &frames,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureHotkey {
+    Screenshot,
+    Record,
+}
+
+impl CaptureHotkey {
+    // The hotkey bound to a host key, named as for `KeyMap`.
+    pub fn from_name(name: &str) -> Option<CaptureHotkey> {
+        match name {
+            "F12" => Some(CaptureHotkey::Screenshot),
+            "F10" => Some(CaptureHotkey::Record),
+            _ => None,
+        }
+    }
+}
+
+/// Screenshots and recordings saved by a frontend's hotkeys, which calls
+/// `after_frame` after running each frame.
+#[derive(Debug, Clone)]
+pub struct Captures {
+    rom_path: Option<PathBuf>,
+    palette: Palette,
+    scale: u32,
+    frame_rate: u32,
+    recorder: Option<Recorder>,
+}
+
+impl Captures {
+    pub fn new(rom_path: Option<&Path>, palette: Palette, scale: u32, frame_rate: u32) -> Captures {
+        Captures {
+            rom_path: rom_path.map(Path::to_path_buf),
+            palette,
+            scale,
+            frame_rate,
+            recorder: None,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    // Act on a hotkey, returning a message for the status line.
+    pub fn handle(&mut self, display: &Display, hotkey: CaptureHotkey) -> String {
+        match hotkey {
+            CaptureHotkey::Screenshot => {
+                let path = self.next_path("shot");
+                match fs::write(&path, screenshot(display, &self.palette, self.scale)) {
+                    Ok(()) => format!("Saved {}", path.display()),
+                    Err(e) => format!("Failed to save {}: {}", path.display(), e),
+                }
+            }
+            CaptureHotkey::Record if self.recorder.is_none() => {
+                self.recorder = Some(Recorder::new(self.palette, self.scale, self.frame_rate));
+                "Recording".to_string()
+            }
+            CaptureHotkey::Record => self.finish().unwrap_or_default(),
+        }
+    }
+
+    pub fn after_frame(&mut self, display: &Display) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.capture(display);
+        }
+    }
+
+    // Stop recording and save the recording, if one is running. Returns a
+    // message for the status line.
+    pub fn finish(&mut self) -> Option<String> {
+        let recorder = self.recorder.take()?;
+        let Some(apng) = recorder.encode() else {
+            return Some("Nothing recorded".to_string());
+        };
+        let path = self.next_path("rec");
+        Some(match fs::write(&path, apng) {
+            Ok(()) => format!("Saved {} ({} frames)", path.display(), recorder.len()),
+            Err(e) => format!("Failed to save {}: {}", path.display(), e),
+        })
+    }
+
+    // The first `<rom>.<kind><n>.png` file not taken yet.
+    fn next_path(&self, kind: &str) -> PathBuf {
+        let base = self
+            .rom_path
+            .as_deref()
+            .filter(|path| path.file_name().is_some())
+            .unwrap_or(Path::new("chip8"));
+        (1..)
+            .map(|n| {
+                let mut name = base.file_name().unwrap_or_default().to_os_string();
+                name.push(format!(".{}{}.png", kind, n));
+                base.with_file_name(name)
+            })
+            .find(|path| !path.exists())
+            .expect("some file name is free")
+    }
+}
@@ -0,0 +1,300 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::c8b;
+use crate::display::Resolution;
+use crate::quirks::{Preset, Quirks};
+
+/// # Cartridge
+///
+/// A `.c8x` cartridge wraps a ROM together with the settings it needs to run
+/// correctly, so that games can ship with their own configuration instead of
+/// relying on users to pick the right one. All multi-byte values are stored
+/// most-significant-byte first.
+///
+/// ```text
+/// +--------+----------------------------------------------------+
+/// | Offset | Content                                            |
+/// +--------+----------------------------------------------------+
+/// | 0      | Magic "C8X"                                        |
+/// | 3      | Format version (2)                                 |
+/// | 4      | Target variant (0 CHIP-8, 1 SUPER-CHIP, 2 XO-CHIP, |
+/// |        | 3 Hires CHIP-8)                                    |
+/// | 5      | Tickrate, instructions per frame (0 for default)   |
+/// | 7      | Palette, background and foreground RGB             |
+/// |        | (all zero for default)                             |
+/// | 13     | Timer rate (0 60Hz, 1 50Hz)                        |
+/// | 14     | Title length n, followed by n bytes of UTF-8       |
+/// | 15+n   | Author length m, followed by m bytes of UTF-8      |
+/// | 16+n+m | ROM bytes                                          |
+/// +--------+----------------------------------------------------+
+/// ```
+///
+/// Version 1 cartridges lack the timer rate byte and run at 60Hz.
+///
+/// `.c8b` binaries are recognised by their own magic and converted into a
+/// cartridge, see `c8b`. Plain ROMs are accepted as well and produce a cartridge without any
+/// settings, so callers can always go through `Cartridge::load`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cartridge {
+    pub variant: Variant,
+
+    // Instructions executed per frame
+    pub tickrate: Option<u16>,
+
+    // Background and foreground colors
+    pub palette: Option<[[u8; 3]; 2]>,
+
+    // Frequency of the timers and frames
+    pub timer_rate: TimerRate,
+
+    pub title: String,
+    pub author: String,
+    pub rom: Vec<u8>,
+}
+
+/// The machine a program targets. SUPER-CHIP instructions are available
+/// on every variant; XO-CHIP additionally extends memory to 64KB and adds
+/// a second drawing plane, long `I` loads, scrolling up, register ranges
+/// and an audio pattern buffer, see `Chip8::set_variant`.
+///
+/// Hires CHIP-8 is CHIP-8 on a 64x64 screen, as run by the patched COSMAC
+/// VIP interpreters of 1978. Its programs start with `1260`, a jump into
+/// the patch, which is redirected to the program itself at 0x2C0, and
+/// clear the screen with `0230`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Variant {
+    #[default]
+    Chip8,
+    SuperChip,
+    XoChip,
+    HiresChip8,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 4] = [
+        Variant::Chip8,
+        Variant::SuperChip,
+        Variant::XoChip,
+        Variant::HiresChip8,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Chip8 => "chip8",
+            Variant::SuperChip => "schip",
+            Variant::XoChip => "xochip",
+            Variant::HiresChip8 => "hires",
+        }
+    }
+
+    // The quirks of the interpreter the variant is named after.
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Variant::Chip8 | Variant::HiresChip8 => Preset::CosmacVip,
+            Variant::SuperChip => Preset::SuperChip,
+            Variant::XoChip => Preset::XoChip,
+        }
+        .quirks()
+    }
+
+    // The low resolution of the machine, see `Resolution`.
+    pub fn resolution(self) -> Resolution {
+        match self {
+            Variant::HiresChip8 => Resolution::R64x64,
+            _ => Resolution::R64x32,
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Variant::ALL
+            .into_iter()
+            .find(|variant| variant.name() == s)
+            .ok_or_else(|| format!("unknown variant `{}`", s))
+    }
+}
+
+/// Rate at which the delay and sound timers count down, which is also the
+/// frame rate. The COSMAC VIP derived it from the 60Hz NTSC video signal,
+/// machines sold for 50Hz PAL television ran their timers slower.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimerRate {
+    #[default]
+    Ntsc,
+    Pal,
+}
+
+impl TimerRate {
+    pub fn hz(self) -> u32 {
+        match self {
+            TimerRate::Ntsc => 60,
+            TimerRate::Pal => 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CartridgeError {
+    UnsupportedVersion(u8),
+    UnknownVariant(u8),
+    UnknownTimerRate(u8),
+    Truncated,
+    InvalidText,
+    NoSupportedSegment,
+    BadMagic,
+}
+
+const MAGIC: &[u8; 3] = b"C8X";
+const VERSION: u8 = 2;
+
+impl Cartridge {
+    // Wrap a plain ROM in a cartridge without any settings.
+    pub fn from_rom(rom: &[u8]) -> Self {
+        Self {
+            variant: Variant::Chip8,
+            tickrate: None,
+            palette: None,
+            timer_rate: TimerRate::Ntsc,
+            title: String::new(),
+            author: String::new(),
+            rom: rom.to_vec(),
+        }
+    }
+
+    // Parse a `.c8x` container, or wrap the bytes as a plain ROM if they do
+    // not start with the cartridge magic.
+    pub fn load(bytes: &[u8]) -> Result<Self, CartridgeError> {
+        if c8b::is_c8b(bytes) {
+            return c8b::parse(bytes);
+        }
+        if !bytes.starts_with(MAGIC) {
+            return Ok(Self::from_rom(bytes));
+        }
+
+        let mut reader = Reader::new(bytes, MAGIC.len());
+        let version = reader.byte()?;
+        if !(1..=VERSION).contains(&version) {
+            return Err(CartridgeError::UnsupportedVersion(version));
+        }
+        let variant = match reader.byte()? {
+            0 => Variant::Chip8,
+            1 => Variant::SuperChip,
+            2 => Variant::XoChip,
+            3 => Variant::HiresChip8,
+            v => return Err(CartridgeError::UnknownVariant(v)),
+        };
+        let tickrate = reader.word()?;
+        let colors = reader.take(6)?;
+        let palette = [
+            [colors[0], colors[1], colors[2]],
+            [colors[3], colors[4], colors[5]],
+        ];
+        let timer_rate = match version {
+            1 => TimerRate::Ntsc,
+            _ => match reader.byte()? {
+                0 => TimerRate::Ntsc,
+                1 => TimerRate::Pal,
+                r => return Err(CartridgeError::UnknownTimerRate(r)),
+            },
+        };
+        let title = reader.text()?;
+        let author = reader.text()?;
+
+        Ok(Self {
+            variant,
+            tickrate: (tickrate != 0).then_some(tickrate),
+            palette: (palette != [[0; 3]; 2]).then_some(palette),
+            timer_rate,
+            title,
+            author,
+            rom: bytes[reader.position..].to_vec(),
+        })
+    }
+
+    // Encode the cartridge as a `.c8x` container. Title and author are
+    // truncated to 255 bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.push(self.variant as u8);
+        bytes.extend(self.tickrate.unwrap_or(0).to_be_bytes());
+        for color in self.palette.unwrap_or_default() {
+            bytes.extend(color);
+        }
+        bytes.push(self.timer_rate as u8);
+        for text in [&self.title, &self.author] {
+            let text = truncate(text, u8::MAX as usize);
+            bytes.push(text.len() as u8);
+            bytes.extend(text.as_bytes());
+        }
+        bytes.extend(&self.rom);
+        bytes
+    }
+}
+
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    pub(crate) position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8], position: usize) -> Self {
+        Self { bytes, position }
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8], CartridgeError> {
+        let slice = self
+            .bytes
+            .get(self.position..self.position + len)
+            .ok_or(CartridgeError::Truncated)?;
+        self.position += len;
+        Ok(slice)
+    }
+
+    pub(crate) fn byte(&mut self) -> Result<u8, CartridgeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn word(&mut self) -> Result<u16, CartridgeError> {
+        Ok(u16::from_be_bytes([self.byte()?, self.byte()?]))
+    }
+
+    pub(crate) fn text(&mut self) -> Result<String, CartridgeError> {
+        let len = self.byte()? as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| CartridgeError::InvalidText)
+    }
+}
+
+// Cut a string to at most `max` bytes without splitting a character.
+fn truncate(text: &str, max: usize) -> &str {
+    let mut end = text.len().min(max);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedVersion(v) => write!(f, "unsupported cartridge version {}", v),
+            Self::UnknownVariant(v) => write!(f, "unknown target variant {}", v),
+            Self::UnknownTimerRate(r) => write!(f, "unknown timer rate {}", r),
+            Self::Truncated => write!(f, "cartridge header is truncated"),
+            Self::InvalidText => write!(f, "cartridge title or author is not valid UTF-8"),
+            Self::NoSupportedSegment => write!(f, "no code segment for a supported variant"),
+            Self::BadMagic => write!(f, "not a .c8b binary"),
+        }
+    }
+}
+
+impl std::error::Error for CartridgeError {}
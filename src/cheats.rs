@@ -0,0 +1,302 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use toml::Spanned;
+
+use crate::config::{self, Strings};
+use crate::cpu::Chip8;
+
+/// # Cheats
+///
+/// Memory patches in the manner of the Game Genie: each code writes a value
+/// to an address, e.g. `2F3:03`, or with a compare value `2F3:03?05` only
+/// where the byte there is still 05, so a code made for one version of a
+/// game leaves other versions alone. Addresses and values are hexadecimal.
+///
+/// A cheat is a named group of codes, of one of two kinds. Patches are
+/// written once the ROM is loaded and again on every reset, e.g. to start
+/// on a later level or to remove a check. Freezes are written again after
+/// every frame (whenever the timers tick), holding a value such as a lives
+/// counter in place whatever the program does to it. Cheats are written
+/// with `Chip8::poke`, which the program's checks and audit do not see.
+///
+/// Cheats for a ROM are kept next to it, in a file named after it with a
+/// `.cheats.toml` extension, e.g. `pong.cheats.toml` for `pong.ch8`:
+///
+/// ```toml
+/// # Written whenever the ROM is loaded or reset
+/// [patch]
+/// "Start on level 5" = "2F4:05"
+///
+/// # Written again after every frame
+/// [freeze]
+/// "Infinite lives" = "2F3:03"
+/// "Full ammo" = ["2F5:09", "2F6:09?00"]
+/// ```
+///
+/// Cheats start enabled, and are turned on and off by name while the
+/// machine runs, see `Chip8::enable_cheat`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cheat {
+    pub name: String,
+    pub kind: CheatKind,
+    pub codes: Vec<CheatCode>,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheatKind {
+    // Written on loads and resets
+    Patch,
+
+    // Written after every frame
+    Freeze,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheatCode {
+    pub address: u16,
+    pub value: u8,
+
+    // The byte expected at the address, which is left alone otherwise
+    pub compare: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheatError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Cheats {
+    pub fn new() -> Cheats {
+        Cheats::default()
+    }
+
+    // Where the cheats of the ROM at `rom_path` are kept.
+    pub fn path_for(rom_path: &Path) -> PathBuf {
+        rom_path.with_extension("cheats.toml")
+    }
+
+    // Add a cheat, replacing the one of the same name if there is one.
+    pub fn add(&mut self, cheat: Cheat) {
+        match self.cheats.iter_mut().find(|c| c.name == cheat.name) {
+            Some(existing) => *existing = cheat,
+            None => self.cheats.push(cheat),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Cheat> {
+        self.cheats.iter().find(|cheat| cheat.name == name)
+    }
+
+    // Turn a cheat on or off, returning whether there is one of that name.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.cheats.iter_mut().find(|cheat| cheat.name == name) {
+            Some(cheat) => {
+                cheat.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cheat> {
+        self.cheats.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cheats.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    // Write the codes of the enabled cheats of one kind.
+    pub fn apply(&self, kind: CheatKind, chip8: &mut Chip8) {
+        for cheat in &self.cheats {
+            if cheat.enabled && cheat.kind == kind {
+                cheat.apply(chip8);
+            }
+        }
+    }
+}
+
+impl Cheat {
+    // An enabled cheat.
+    pub fn new(name: impl Into<String>, kind: CheatKind, codes: Vec<CheatCode>) -> Cheat {
+        Cheat {
+            name: name.into(),
+            kind,
+            codes,
+            enabled: true,
+        }
+    }
+
+    // Write the codes, whether the cheat is enabled or not.
+    pub fn apply(&self, chip8: &mut Chip8) {
+        for code in &self.codes {
+            code.apply(chip8);
+        }
+    }
+}
+
+impl CheatCode {
+    // Write the value, unless the compare value does not match. Returns
+    // whether it was written; addresses past the end of memory never are.
+    pub fn apply(&self, chip8: &mut Chip8) -> bool {
+        let address = self.address as usize;
+        match (self.compare, chip8.peek(address)) {
+            (_, None) => false,
+            (Some(compare), Some(byte)) if compare != byte => false,
+            _ => chip8.poke(address, self.value),
+        }
+    }
+}
+
+impl FromStr for Cheats {
+    type Err = CheatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let file: CheatFile = config::from_toml(s).map_err(|e| CheatError {
+            line: e.line,
+            message: e.message,
+        })?;
+        let mut cheats = Cheats::new();
+        let tables = [
+            (CheatKind::Patch, file.patch),
+            (CheatKind::Freeze, file.freeze),
+        ];
+        for (kind, CheatTable(entries)) in tables {
+            for (name, codes) in entries {
+                let line = s[..codes.span().start].matches('\n').count() + 1;
+                let error = |message: String| CheatError { line, message };
+                if cheats.get(&name).is_some() {
+                    return Err(error(format!("cheat `{}` defined twice", name)));
+                }
+                let codes = codes
+                    .into_inner()
+                    .into_vec()
+                    .iter()
+                    .map(|code| code.parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(error)?;
+                cheats.add(Cheat::new(name, kind, codes));
+            }
+        }
+        Ok(cheats)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CheatFile {
+    #[serde(default)]
+    patch: CheatTable,
+    #[serde(default)]
+    freeze: CheatTable,
+}
+
+// The codes of a table by cheat name, in the order of the file.
+#[derive(Default)]
+struct CheatTable(Vec<(String, Spanned<Strings>)>);
+
+impl<'de> Deserialize<'de> for CheatTable {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Entries;
+
+        impl<'de> Visitor<'de> for Entries {
+            type Value = CheatTable;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a table of cheats")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<CheatTable, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(CheatTable(entries))
+            }
+        }
+
+        deserializer.deserialize_map(Entries)
+    }
+}
+
+impl FromStr for CheatCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected ADDRESS:VALUE[?COMPARE], got `{}`", s);
+        let hex = |digits: &str| {
+            let digits = digits.trim();
+            let digits = digits
+                .strip_prefix("0x")
+                .or_else(|| digits.strip_prefix("0X"))
+                .unwrap_or(digits);
+            u16::from_str_radix(digits, 16).map_err(|_| invalid())
+        };
+        let byte =
+            |digits: &str| hex(digits).and_then(|value| u8::try_from(value).map_err(|_| invalid()));
+        let (address, value) = s.split_once(':').ok_or_else(invalid)?;
+        let (value, compare) = match value.split_once('?') {
+            Some((value, compare)) => (value, Some(byte(compare)?)),
+            None => (value, None),
+        };
+        Ok(CheatCode {
+            address: hex(address)?,
+            value: byte(value)?,
+            compare,
+        })
+    }
+}
+
+impl fmt::Display for CheatCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:03X}:{:02X}", self.address, self.value)?;
+        if let Some(compare) = self.compare {
+            write!(f, "?{:02X}", compare)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CheatKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            CheatKind::Patch => "patch",
+            CheatKind::Freeze => "freeze",
+        })
+    }
+}
+
+// As listed by the debugger, e.g. `on  freeze Infinite lives: 2F3:03`.
+impl fmt::Display for Cheat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.enabled { "on " } else { "off" };
+        write!(f, "{} {:6} {}:", state, self.kind, self.name)?;
+        for code in &self.codes {
+            write!(f, " {}", code)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CheatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for CheatError {}
@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde::de::{self, DeserializeOwned};
+use serde::{Deserialize, Deserializer};
+
+use crate::keyboard::KeyMap;
+use crate::palette::{self, Palette};
+use crate::quirks::Quirks;
+
+/// # Configuration File
+///
+/// Settings the command line would otherwise need on every run, kept in
+/// `~/.config/chip8-rs/config.toml` (or under `$XDG_CONFIG_HOME`). Flags
+/// given on the command line override them, and settings carried by a
+/// cartridge override the colors.
+///
+/// ```toml
+/// # Quirks as for --quirks, and instructions per second as for --ips
+/// quirks = "schip,shifting=off"
+/// ips = 1000
+/// scale = 12
+///
+/// # Where the ROM browser of the window looks for ROMs, as for --rom-dir
+/// rom_dir = "/home/me/roms"
+///
+/// # A ROM database to look ROMs up in, as for --rom-db
+/// rom_db = "/home/me/roms/programs.json"
+///
+/// # A palette as for --palette, with single colors changed
+/// [colors]
+/// palette = "amber"
+/// background = "#102010"
+/// foreground = "#80FF80"
+///
+/// # Host keys for keypad keys, replacing their standard keys
+/// [keys]
+/// 5 = "Up"
+/// 8 = ["Down", "S"]
+/// ```
+///
+/// The colors are `background`, `foreground`, and for XO-CHIP `plane2` and
+/// `both`, see `Palette`.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    pub quirks: Option<Quirks>,
+    pub ips: Option<u32>,
+    pub scale: Option<u32>,
+    pub rom_dir: Option<PathBuf>,
+    pub rom_db: Option<PathBuf>,
+
+    pub palette: Option<Palette>,
+
+    // Host keys bound to keypad keys, instead of the standard ones
+    pub keys: BTreeMap<u8, Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Config {
+    // Where the configuration file is looked for, none without a home
+    // directory.
+    pub fn default_path() -> Option<PathBuf> {
+        let base = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(base.join("chip8-rs").join("config.toml"))
+    }
+
+    // Rebind the remapped keys in `keymap`, leaving the other keys alone.
+    pub fn apply_keys(&self, keymap: &mut KeyMap) {
+        for (&key, hosts) in &self.keys {
+            keymap.unbind_key(0, key);
+            for host in hosts {
+                keymap.bind(host, 0, key);
+            }
+        }
+    }
+}
+
+impl FromStr for Config {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let file: ConfigFile = from_toml(s)?;
+        let mut palette = file.colors.palette;
+        let colors = [
+            file.colors.background,
+            file.colors.foreground,
+            file.colors.plane2,
+            file.colors.both,
+        ];
+        for (index, color) in colors.into_iter().enumerate() {
+            if let Some(color) = color {
+                palette.get_or_insert_with(Palette::default).colors[index] = color;
+            }
+        }
+        Ok(Config {
+            quirks: file.quirks,
+            ips: file.ips,
+            scale: file.scale,
+            rom_dir: file.rom_dir,
+            rom_db: file.rom_db,
+            palette,
+            keys: file
+                .keys
+                .into_iter()
+                .map(|(KeypadKey(key), hosts)| (key, hosts.into_vec()))
+                .collect(),
+        })
+    }
+}
+
+// The file as written, before the colors are merged into one palette.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default, deserialize_with = "parsed")]
+    quirks: Option<Quirks>,
+    ips: Option<u32>,
+    scale: Option<u32>,
+    rom_dir: Option<PathBuf>,
+    rom_db: Option<PathBuf>,
+    #[serde(default)]
+    colors: Colors,
+    #[serde(default)]
+    keys: BTreeMap<KeypadKey, Strings>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Colors {
+    #[serde(default, deserialize_with = "parsed")]
+    palette: Option<Palette>,
+    #[serde(default, deserialize_with = "color")]
+    background: Option<[u8; 3]>,
+    #[serde(default, deserialize_with = "color")]
+    foreground: Option<[u8; 3]>,
+    #[serde(default, deserialize_with = "color")]
+    plane2: Option<[u8; 3]>,
+    #[serde(default, deserialize_with = "color")]
+    both: Option<[u8; 3]>,
+}
+
+// A keypad key named by its hexadecimal digit.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct KeypadKey(u8);
+
+impl<'de> Deserialize<'de> for KeypadKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        u8::from_str_radix(&name, 16)
+            .ok()
+            .filter(|&key| key < 16)
+            .map(KeypadKey)
+            .ok_or_else(|| de::Error::custom(format!("unknown keypad key `{}`", name)))
+    }
+}
+
+// A string, or an array of strings.
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "a string or an array of strings")]
+pub(crate) enum Strings {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Strings {
+    pub(crate) fn into_vec(self) -> Vec<String> {
+        match self {
+            Strings::One(value) => vec![value],
+            Strings::Many(values) => values,
+        }
+    }
+}
+
+// A string setting parsed with `FromStr`, e.g. the quirks.
+fn parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err = String>,
+{
+    let value = String::deserialize(deserializer)?;
+    value.parse().map(Some).map_err(de::Error::custom)
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<[u8; 3]>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    palette::parse_color(&value)
+        .map(Some)
+        .map_err(de::Error::custom)
+}
+
+// Deserialize a TOML document, with the line of the first error.
+pub(crate) fn from_toml<T: DeserializeOwned>(text: &str) -> Result<T, ConfigError> {
+    toml::from_str(text).map_err(|e| ConfigError {
+        line: e
+            .span()
+            .map_or(1, |span| text[..span.start].matches('\n').count() + 1),
+        message: e.message().to_string(),
+    })
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
@@ -0,0 +1,46 @@
+use crate::splash;
+
+/// # Test ROM Corpus
+///
+/// Small ROMs bundled with the crate (behind the `test-roms` feature) so that
+/// tests and tools can exercise the real fetch, decode and draw paths without
+/// network access or external files. All of them were written for this
+/// project, in `tests/roms/<name>.s`, which `build.rs` assembles; the
+/// scenarios in `tests/scenarios` describe what each one does.
+#[derive(Debug, Clone, Copy)]
+pub struct TestRom {
+    pub name: &'static str,
+    pub rom: &'static [u8],
+}
+
+pub const ROMS: &[TestRom] = &[
+    TestRom {
+        name: "bcd",
+        rom: include_bytes!(concat!(env!("OUT_DIR"), "/roms/bcd.ch8")),
+    },
+    TestRom {
+        name: "counter",
+        rom: include_bytes!(concat!(env!("OUT_DIR"), "/roms/counter.ch8")),
+    },
+    TestRom {
+        name: "quirks",
+        rom: include_bytes!(concat!(env!("OUT_DIR"), "/roms/quirks.ch8")),
+    },
+    TestRom {
+        name: "selfmod",
+        rom: include_bytes!(concat!(env!("OUT_DIR"), "/roms/selfmod.ch8")),
+    },
+    TestRom {
+        name: "splash",
+        rom: splash::ROM,
+    },
+    TestRom {
+        name: "timer",
+        rom: include_bytes!(concat!(env!("OUT_DIR"), "/roms/timer.ch8")),
+    },
+];
+
+// Look up a bundled ROM by name.
+pub fn find(name: &str) -> Option<&'static TestRom> {
+    ROMS.iter().find(|rom| rom.name == name)
+}
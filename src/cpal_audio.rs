@@ -0,0 +1,85 @@
+use std::sync::{Arc, Mutex};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{BufferSize, OutputCallbackInfo, SampleRate, Stream, StreamConfig};
+
+use crate::audio::{AudioConfig, Buzzer, SampleQueue};
+
+/// # Native Audio
+///
+/// A `Buzzer` for desktop builds, playing through the default output device
+/// of the system's audio API (ALSA, CoreAudio, WASAPI) with cpal. The
+/// samples rendered by the shared `Mixer` go through a `SampleQueue` which
+/// the device callback pulls from, so the latency stays bounded and the
+/// device plays silence when the emulation falls behind.
+///
+/// The buzzer is mono, and copied to every channel of devices which only
+/// offer stereo or more.
+pub struct CpalBuzzer {
+    queue: Arc<Mutex<SampleQueue>>,
+
+    // Plays for as long as it is kept alive
+    _stream: Stream,
+}
+
+impl CpalBuzzer {
+    pub fn open(config: &AudioConfig) -> Result<CpalBuzzer, String> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or("no audio output device")?;
+        let channels = device
+            .default_output_config()
+            .map_err(|e| e.to_string())?
+            .channels();
+        let stream_config = StreamConfig {
+            channels,
+            sample_rate: SampleRate(config.sample_rate),
+            buffer_size: BufferSize::Fixed(config.buffer_size),
+        };
+
+        let queue = Arc::new(Mutex::new(SampleQueue::new(config)));
+        let shared = Arc::clone(&queue);
+        let mut mono = Vec::new();
+        let stream = device
+            .build_output_stream(
+                &stream_config,
+                move |out: &mut [f32], _: &OutputCallbackInfo| {
+                    mono.resize(out.len() / channels as usize, 0.0);
+                    match shared.lock() {
+                        Ok(mut queue) => queue.pull(&mut mono),
+                        Err(_) => mono.fill(0.0),
+                    }
+                    for (frame, &sample) in out.chunks_mut(channels as usize).zip(&mono) {
+                        frame.fill(sample);
+                    }
+                },
+                // The stream plays silence after an error, there is nothing
+                // to recover.
+                |_| {},
+                None,
+            )
+            .map_err(|e| e.to_string())?;
+        stream.play().map_err(|e| e.to_string())?;
+        Ok(CpalBuzzer {
+            queue,
+            _stream: stream,
+        })
+    }
+
+    // Number of device buffers which ran out of samples.
+    pub fn underruns(&self) -> u64 {
+        self.queue.lock().map_or(0, |queue| queue.underruns())
+    }
+}
+
+impl Buzzer for CpalBuzzer {
+    fn name(&self) -> &'static str {
+        "cpal"
+    }
+
+    fn write(&mut self, samples: &[f32]) {
+        if let Ok(mut queue) = self.queue.lock() {
+            queue.push(samples);
+        }
+    }
+}
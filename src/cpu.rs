@@ -1,3 +1,33 @@
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::audio::Voice;
+use crate::audit::{AuditEvent, AuditSink, Auditor, AUDITED_REGISTERS};
+use crate::backend::{ExecutionBackend, Interpreter};
+use crate::capture;
+use crate::cartridge::Variant;
+use crate::cheats::{Cheat, CheatKind, Cheats};
+use crate::display::{Display, Resolution};
+use crate::fault::{Access, Check, Checks, Chip8Error, EmulationMode, Fault, FaultPolicy};
+use crate::font::FontSet;
+use crate::heatmap::AccessMap;
+use crate::instruction::{decode, Instruction};
+use crate::keyboard::Keypad;
+use crate::memory::{self, MemoryError};
+use crate::palette::Palette;
+use crate::profile::{Profile, Stopwatch};
+use crate::quirks::Quirks;
+use crate::random::{RandomSource, SplitMix64};
+use crate::registers::{Register, RegisterError};
+use crate::rewind::{Rewind, RewindConfig};
+use crate::rom::{LoadAddress, LoadError};
+use crate::savestate::{MachineState, SaveStateError, Thumbnail};
+use crate::telemetry::Telemetry;
+use crate::timers::Timers;
+use crate::trace::{History, TraceSink, Tracer};
+
 /// # Chip-8 CPU
 ///
 /// ## Registers
@@ -16,12 +46,15 @@
 ///
 /// There are also some "pseudo-registers" which are not accessible from Chip-8
 /// programs. The program counter (PC) should be 16-bit, and is used to store
-/// the currently executing address. The stack pointer (SP) can be 8-bit, it is
-/// used to point to the topmost level of the stack.
+/// the currently executing address. The stack pointer (SP) can be 8-bit, it
+/// counts the addresses on the stack, so it points one past the topmost level.
 ///
 /// The stack is an array of 16 16-bit values, used to store the address that
 /// the interpreter should return to when finished with a subroutine.
-/// Chip-8 allows for up to 16 levels of nested subroutines.
+/// Chip-8 allows for up to 16 levels of nested subroutines. Calling a 17th
+/// raises `Chip8Error::StackOverflow`, returning with an empty stack
+/// `Chip8Error::StackUnderflow`; under the `Continue` policy the call or
+/// return is skipped and the stack stays as it was.
 ///
 /// ## Instructions
 ///
@@ -29,14 +62,25 @@
 /// instructions, including math, graphics, and flow control functions.
 ///
 /// Super Chip-48 added an additional 10 instructions, for a total of 46.
+/// SUPER-CHIP 1.1 added another 5, all of which are implemented: 00Cn,
+/// 00FB and 00FC scroll the screen, 00FE and 00FF switch between 64x32 and
+/// 128x64 pixels, 00FD exits the interpreter, Dxy0 draws 16x16 sprites,
+/// Fx30 points I at a large digit, and Fx75 and Fx85 save and restore
+/// registers to the HP-48 RPL user flags.
+///
+/// The XO-CHIP variant (see `set_variant`) adds 00Dn to scroll up, 5xy2
+/// and 5xy3 to save and load a range of registers, F000 nnnn to load a
+/// 16-bit address into I, Fn01 to select drawing planes, F002 to load an
+/// audio pattern and Fx3A to set its pitch. F000 is followed by its
+/// address, so skips jump over all four bytes of it.
 ///
 /// All instructions are 2 bytes long and are stored most-significant-byte first.
 /// In memory, the first byte of each instruction should be located at an even
 /// addresses. If a program includes sprite data, it should be padded so any
 /// instructions following it will be properly situated in RAM.
 ///
-/// This document does not yet contain descriptions of the Super Chip-48 instruc-
-/// tions. They are listed at [here](http://devernay.free.fr/hacks/chip8/C8TECH10.HTM#3.1).
+/// The Super Chip-48 instructions are listed
+/// [here](http://devernay.free.fr/hacks/chip8/C8TECH10.HTM#3.1).
 ///
 /// In these listings, the following variables are used:
 ///
@@ -45,103 +89,1183 @@
 /// - x - A 4-bit value, the lower 4 bits of the high byte of the instruction
 /// - y - A 4-bit value, the upper 4 bits of the low byte of the instruction
 /// - kk or byte - An 8-bit value, the lowest 8 bits of the instruction
-use rand::Rng;
-
-mod memory;
-
 #[derive(Debug)]
-struct Chip8 {
+pub struct Chip8 {
     // General purpose 8-bit registers (V0 to VF)
-    v_registers: [u8; 16],
+    pub(crate) v_registers: [u8; 16],
 
     // 16-bit register I (used for memory addresses)
-    i_register: u16,
+    pub(crate) i_register: u16,
 
     // Delay and sound timers (60Hz)
-    delay_timer: u8,
-    sound_timer: u8,
+    timers: Timers,
 
     // Program counter (PC) - 16-bit
-    program_counter: u16,
+    pub(crate) program_counter: u16,
 
-    // Stack pointer (SP) - 8-bit
+    // Stack pointer (SP) - 8-bit, the number of addresses on the stack
     stack_pointer: u8,
 
     // Stack (16 16-bit values)
     stack: [u16; 16],
 
+    // HP-48 RPL user flags, saved and restored by Fx75 and Fx85
+    rpl_flags: [u8; 16],
+
     // Memory
     memory: memory::Memory,
+
+    // The program last loaded, copied in again by `reset`
+    rom: Vec<u8>,
+
+    // Keypad state, and the program waiting for a key
+    keypad: Keypad,
+
+    // Random number generator used by Cxkk
+    rng: Box<dyn RandomSource>,
+
+    // Runtime statistics
+    pub(crate) telemetry: Telemetry,
+
+    // Optional read/write/execute shadow map of memory
+    access_map: Option<Box<AccessMap>>,
+
+    // Optional per-address execution profile
+    profile: Option<Box<Profile>>,
+
+    // Optional history of save states for rewinding
+    rewind: Option<Box<Rewind>>,
+
+    // Strategy used to execute instructions, only `None` while it runs
+    backend: Option<Box<dyn ExecutionBackend>>,
+
+    // Lowest and highest address written since the last `take_written_range`
+    written: Option<(usize, usize)>,
+
+    // What to do on out-of-spec behavior, and the last fault that happened
+    checks: Checks,
+    fault: Option<Fault>,
+    halted: bool,
+
+    // Set by `pause`, stopping instructions and timers until `resume`
+    paused: bool,
+
+    // Receiver of audit events, only set in audit mode
+    audit: Option<Auditor>,
+
+    // Receiver of executed instructions and history of the last ones, only
+    // set when tracing
+    trace: Option<Box<Tracer>>,
+
+    // Behavior of the instructions interpreters disagree on
+    quirks: Quirks,
+
+    display: Display,
+
+    // Where programs are loaded and start
+    load_address: LoadAddress,
+
+    // Machine the program targets, enabling the XO-CHIP extensions
+    variant: Variant,
+
+    // XO-CHIP audio pattern loaded by F002, and its pitch set by Fx3A
+    audio_pattern: Option<[u8; 16]>,
+    pitch: u8,
+
+    // Patches written on loads and resets, and values frozen every frame
+    cheats: Cheats,
+}
+
+/// What became of the instruction run by `Chip8::step`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepResult {
+    // The instruction ran, or the machine is blocked in Fx0A waiting for a
+    // key stroke
+    Normal,
+    WaitingForKey,
+
+    // The instruction raised a fault, or the machine has halted on one
+    // before
+    Error(Fault),
+
+    // The program exited with 00FD
+    Exited,
+
+    // The machine is paused, see `Chip8::pause`, and nothing ran
+    Paused,
+}
+
+/// Frontend hotkeys restarting the program: F11 resets the machine, see
+/// `Chip8::reset`, Home only restarts the program, see `Chip8::soft_reset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetHotkey {
+    Reset,
+    SoftReset,
+}
+
+impl ResetHotkey {
+    // The hotkey bound to a host key, named as for `KeyMap`.
+    pub fn from_name(name: &str) -> Option<ResetHotkey> {
+        match name {
+            "F11" => Some(ResetHotkey::Reset),
+            "Home" => Some(ResetHotkey::SoftReset),
+            _ => None,
+        }
+    }
+
+    // Reset `chip8`, returning a message for the status line.
+    pub fn apply(self, chip8: &mut Chip8) -> String {
+        match self {
+            ResetHotkey::Reset => {
+                chip8.reset(true);
+                "Reset".to_string()
+            }
+            ResetHotkey::SoftReset => {
+                chip8.soft_reset();
+                "Soft reset".to_string()
+            }
+        }
+    }
 }
 
+// The jump Hires CHIP-8 programs start with, into the interpreter patch at
+// 0x260, and the jump past it to the program at 0x2C0 it is replaced with.
+const HIRES_BOOT: [u8; 2] = [0x12, 0x60];
+const HIRES_START: [u8; 2] = [0x12, 0xC0];
+
+// Pitch of XO-CHIP audio patterns until Fx3A sets one, 4000 bits per second.
+const DEFAULT_PITCH: u8 = 64;
+
+// Snapshots carry a 16x8 thumbnail of the 64x32 screen, 32x16 in high
+// resolution.
+const THUMBNAIL_SCALE: usize = 4;
+
 impl Chip8 {
-    fn new() -> Chip8 {
+    pub fn new() -> Chip8 {
+        Self::with_backend(Box::new(Interpreter))
+    }
+
+    // Create a machine executing instructions through the given backend.
+    pub fn with_backend(backend: Box<dyn ExecutionBackend>) -> Chip8 {
         Chip8 {
             v_registers: [0; 16],
             i_register: 0,
-            delay_timer: 0,
-            sound_timer: 0,
+            timers: Timers::new(),
             program_counter: 0,
             stack_pointer: 0,
             stack: [0; 16],
+            rpl_flags: [0; 16],
             memory: memory::Memory::new(),
+            rom: Vec::new(),
+            keypad: Keypad::new(),
+            rng: Box::new(SplitMix64::from_entropy()),
+            telemetry: Telemetry::new(),
+            access_map: None,
+            profile: None,
+            rewind: None,
+            backend: Some(backend),
+            written: None,
+            checks: Checks::default(),
+            fault: None,
+            halted: false,
+            paused: false,
+            audit: None,
+            trace: None,
+            quirks: Quirks::default(),
+            display: Display::new(),
+            load_address: LoadAddress::default(),
+            variant: Variant::default(),
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            cheats: Cheats::new(),
+        }
+    }
+
+    // Create a machine running the ROM file at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Chip8, LoadError> {
+        let rom = fs::read(path)?;
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom)?;
+        Ok(chip8)
+    }
+
+    // Copy a program into memory starting at the load address and point PC
+    // at it. ROMs running past the end of memory are rejected, leaving the
+    // machine untouched.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), LoadError> {
+        self.replace_rom(rom)?;
+        self.program_counter = self.load_address.address();
+        Ok(())
+    }
+
+    // Swap the program in memory for another, e.g. a rebuild of it, leaving
+    // registers, the screen and the rest of memory as they are; resets
+    // load the new one. The rewind history, which would bring the old one
+    // back, is cleared. ROMs running past the end of memory are rejected,
+    // leaving the machine untouched.
+    pub fn replace_rom(&mut self, rom: &[u8]) -> Result<(), LoadError> {
+        self.load_address.check(rom, self.memory.size())?;
+        let mut rom = rom.to_vec();
+        // Hires CHIP-8 programs jump into the interpreter patch loaded
+        // with them, which is machine code, and on to the program.
+        if self.variant == Variant::HiresChip8
+            && self.load_address == LoadAddress::Standard
+            && rom.starts_with(&HIRES_BOOT)
+        {
+            rom[..2].copy_from_slice(&HIRES_START);
+        }
+        let start = self.load_address.address();
+        self.memory
+            .load(start as usize, &rom)
+            .expect("the load address checked the ROM fits");
+        if !rom.is_empty() {
+            self.mark_written(start as usize, start as usize + rom.len() - 1);
+        }
+        if let Some(rewind) = &mut self.rewind {
+            rewind.clear();
+        }
+        self.rom = rom;
+        self.apply_cheats(CheatKind::Patch);
+        Ok(())
+    }
+
+    // Start over as if the machine was switched off and on again: memory
+    // is cleared, the font put back and, with `keep_rom`, the program last
+    // loaded copied in again, undoing whatever it wrote over itself. The
+    // rest is as for `soft_reset`. Without the ROM, memory is ready for
+    // `load_rom`. Patch cheats are written into the program again.
+    //
+    // A ROM which no longer fits, because the variant or the load address
+    // changed since it was loaded, is dropped, and the machine restarts
+    // with an out of bounds write fault at the load address.
+    pub fn reset(&mut self, keep_rom: bool) {
+        let mut memory = memory::Memory::with_size(self.memory.size());
+        memory.set_write_protect(self.memory.is_write_protected());
+        memory.set_font(self.memory.font());
+        self.memory = memory;
+        self.mark_written(0, self.memory.size() - 1);
+        if !keep_rom {
+            self.rom.clear();
+        }
+        let start = self.load_address.address() as usize;
+        let fits = self
+            .load_address
+            .check(&self.rom, self.memory.size())
+            .is_ok();
+        if fits {
+            self.memory
+                .load(start, &self.rom)
+                .expect("the load address checked the ROM fits");
+        } else {
+            self.rom.clear();
+        }
+        if keep_rom {
+            self.apply_cheats(CheatKind::Patch);
+        }
+        self.rpl_flags = [0; 16];
+        self.soft_reset();
+        if !fits {
+            self.raise(Chip8Error::MemoryOutOfBounds {
+                access: Access::Write,
+                address: self.memory.size(),
+            });
+        }
+    }
+
+    // Restart the program where it stands in memory, like the reset switch
+    // of the COSMAC VIP: registers, stack, timers, the screen and any fault
+    // are cleared, the font is put back in case the program overwrote it,
+    // and PC points at the load address again. Held keys stay held; the
+    // pause, quirks, sinks and the rewind history are kept.
+    pub fn soft_reset(&mut self) {
+        self.v_registers = [0; 16];
+        self.i_register = 0;
+        self.stack = [0; 16];
+        self.stack_pointer = 0;
+        self.timers = Timers::with_rate(self.timers.rate());
+        self.keypad.restore(self.keypad.keys());
+        self.display = Display::with_resolution(self.display.lores());
+        self.audio_pattern = None;
+        self.pitch = DEFAULT_PITCH;
+        self.fault = None;
+        self.halted = false;
+        self.set_font(self.memory.font());
+        self.program_counter = self.load_address.address();
+    }
+
+    // Where `load_rom` puts programs, 0x200 unless set otherwise.
+    pub fn set_load_address(&mut self, load_address: LoadAddress) {
+        self.load_address = load_address;
+    }
+
+    pub fn load_address(&self) -> LoadAddress {
+        self.load_address
+    }
+
+    // Whether programs may write to the interpreter area below 0x200, which
+    // they may not by default.
+    pub fn set_write_protect(&mut self, write_protect: bool) {
+        self.memory.set_write_protect(write_protect);
+    }
+
+    // Replace the hexadecimal digits drawn by Fx29 and Fx30 with those of
+    // another machine, see `FontSet`.
+    pub fn set_font(&mut self, font: FontSet) {
+        self.memory.set_font(font);
+        self.mark_written(memory::FONT_ADDRESS, memory::INTERPRETER_END - 1);
+    }
+
+    pub fn font(&self) -> FontSet {
+        self.memory.font()
+    }
+
+    // Select the machine the program targets, before loading it. XO-CHIP
+    // grows memory to 64KB and enables its instructions, the other
+    // variants run the CHIP-8 and SUPER-CHIP instructions in 4KB. The
+    // screen switches to the variant's low resolution.
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+        self.display.set_resolution(variant.resolution());
+        self.memory.resize(match variant {
+            Variant::XoChip => memory::XO_CHIP_SIZE,
+            _ => memory::SIZE,
+        });
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    // Select the low resolution programs start in and 00FE returns to,
+    // after the variant, which sets its own. Clears the screen.
+    pub fn set_resolution(&mut self, resolution: Resolution) {
+        self.display.set_resolution(resolution);
+    }
+
+    fn is_xo_chip(&self) -> bool {
+        self.variant == Variant::XoChip
+    }
+
+    // Execute the next instruction through the backend: fetch the opcode at
+    // PC, advance PC past it, then execute it.
+    pub fn step(&mut self) -> StepResult {
+        if self.paused {
+            return StepResult::Paused;
+        }
+        let errors = self.telemetry.errors;
+        if !self.halted {
+            if let Some(mut backend) = self.backend.take() {
+                backend.step(self);
+                self.backend = Some(backend);
+            }
+        }
+        match self.fault {
+            Some(fault) if self.halted || self.telemetry.errors > errors => {
+                StepResult::Error(fault)
+            }
+            _ if self.halted => StepResult::Exited,
+            _ if self.keypad.waiting() => StepResult::WaitingForKey,
+            _ => StepResult::Normal,
         }
     }
 
-    fn execute(&mut self, opcode: u16) {
-        match opcode & 0xF000 {
-            0x0000 => {}
-            0x1000 => self.jump_to(opcode & 0x0FFF),
-            0x2000 => self.call_subroutine(opcode & 0x0FFF),
-            0x3000 => {
-                let x = opcode & 0x0F00 >> 8;
-                let byte = opcode & 0x00FF;
-                self.skip_if_equal(x as u8, byte as u8);
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.as_ref().map_or("", |b| b.name())
+    }
+
+    // Fetch the big-endian opcode at PC and advance PC past it.
+    pub fn fetch(&mut self) -> Option<u16> {
+        let pc = self.program_counter as usize;
+        match self.memory.read_word(pc) {
+            Ok(opcode) => {
+                if let Some(map) = &mut self.access_map {
+                    map.record_execute(pc);
+                    map.record_execute(pc + 1);
+                }
+                self.program_counter = self.program_counter.wrapping_add(2);
+                Some(opcode)
             }
-            0x4000 => {
-                let x = opcode & 0x0F00 >> 8;
-                let byte = opcode & 0x00FF;
-                self.skip_if_not_equal(x as u8, byte as u8);
+            Err(_) => {
+                self.raise(Chip8Error::MemoryOutOfBounds {
+                    access: Access::Fetch,
+                    address: pc,
+                });
+                None
             }
-            0x5000 => {
-                let x = opcode & 0x0F00 >> 8;
-                let y = opcode & 0x00F0 >> 4;
-                self.skip_if_registers_equal(x as u8, y as u8);
+        }
+    }
+
+    // Execute a frame worth of instructions, then tick the timers once.
+    pub fn run_frame(&mut self, cycles: usize) {
+        if self.halted || self.paused {
+            return;
+        }
+        if let Some(mut backend) = self.backend.take() {
+            let mut remaining = cycles;
+            while remaining > 0 {
+                let executed = backend.run(self, remaining);
+                if executed == 0 {
+                    break;
+                }
+                remaining -= executed.min(remaining);
             }
-            0x6000 => {
-                let x = opcode & 0x0F00 >> 8;
-                let byte = opcode & 0x00FF;
-                self.load_to_register(x as u8, byte as u8);
+            self.backend = Some(backend);
+        }
+        self.tick_timers();
+    }
+
+    // Switch between strict and permissive emulation, keeping the per-check
+    // overrides.
+    pub fn set_mode(&mut self, mode: EmulationMode) {
+        self.checks.mode = mode;
+    }
+
+    // Override the policy for one kind of fault.
+    pub fn set_check(&mut self, check: Check, policy: FaultPolicy) {
+        self.checks.set(check, policy);
+    }
+
+    pub fn checks(&self) -> &Checks {
+        &self.checks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    // The last fault, if any.
+    pub fn fault(&self) -> Option<Fault> {
+        self.fault
+    }
+
+    // Whether the machine stopped on a fault, see `FaultPolicy::Halt`, or
+    // because the program exited.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    // Stop running instructions and ticking the timers, until `resume`.
+    // Frontends keep presenting the same frame meanwhile.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // Continue after a pause or a halt, forgetting the fault.
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.halted = false;
+        self.fault = None;
+    }
+
+    // Run whole frames until the program draws its first sprite, or until
+    // `max_frames` have passed, and return the number of frames run. Used to
+    // skip long init and title delays.
+    pub fn run_until_first_draw(&mut self, cycles: usize, max_frames: u64) -> u64 {
+        let draws = self.telemetry.draw_calls;
+        let mut frames = 0;
+        while frames < max_frames && self.telemetry.draw_calls == draws {
+            self.run_frame(cycles);
+            frames += 1;
+        }
+        frames
+    }
+
+    // Decrement the delay and sound timers, called once per frame (60Hz, or
+    // 50Hz with PAL timing). Frozen cheat values are written again.
+    pub fn tick_timers(&mut self) {
+        if self.paused {
+            return;
+        }
+        self.timers.tick();
+        self.telemetry.frames += 1;
+        self.apply_cheats(CheatKind::Freeze);
+        self.record_rewind(1);
+    }
+
+    // Count the timers down by the wall time that passed, for hosts which
+    // run instructions at their own pace instead of calling `run_frame`.
+    // Returns the number of ticks.
+    pub fn advance_timers(&mut self, elapsed: Duration) -> u32 {
+        if self.paused {
+            return 0;
+        }
+        let ticks = self.timers.advance(elapsed);
+        self.telemetry.frames += ticks as u64;
+        if ticks > 0 {
+            self.apply_cheats(CheatKind::Freeze);
+            self.record_rewind(ticks);
+        }
+        ticks
+    }
+
+    pub fn timers(&self) -> &Timers {
+        &self.timers
+    }
+
+    // Rate at which `advance_timers` ticks, 60Hz unless set otherwise.
+    pub fn set_timer_rate(&mut self, rate: u32) {
+        self.timers.set_rate(rate);
+    }
+
+    // Reseed the random number generator so that Cxkk becomes reproducible.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Box::new(SplitMix64::new(seed));
+    }
+
+    // Replace the random number generator, e.g. with a `FixedRandom` in
+    // tests.
+    pub fn set_random_source(&mut self, source: Box<dyn RandomSource>) {
+        self.rng = source;
+    }
+
+    // Update the state of one of the 16 keys.
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        if pressed {
+            self.keypad.press(key);
+        } else {
+            self.keypad.release(key);
+        }
+    }
+
+    // Update all 16 keys at once from a mask, bit k for key k.
+    pub fn set_keys(&mut self, mask: u16) {
+        for key in 0..16 {
+            let pressed = mask & (1 << key) != 0;
+            if pressed != self.keypad.is_pressed(key) {
+                self.set_key(key, pressed);
             }
-            0x7000 => {
-                let x = opcode & 0x0F00 >> 8;
-                let byte = opcode & 0x00FF;
-                self.add_to_register(x as u8, byte as u8);
+        }
+    }
+
+    // Current state of the 16 keys, indexed by key value.
+    pub fn keys(&self) -> [bool; 16] {
+        self.keypad.keys()
+    }
+
+    pub fn keypad(&self) -> &Keypad {
+        &self.keypad
+    }
+
+    // Whether the buzzer sounds, i.e. the sound timer is running.
+    pub fn sound_active(&self) -> bool {
+        self.timers.is_beeping()
+    }
+
+    // What the buzzer plays: the XO-CHIP audio pattern once the program
+    // loaded one, the beep otherwise.
+    pub fn voice(&self) -> Voice {
+        match self.audio_pattern {
+            Some(bits) => Voice::Pattern {
+                bits,
+                pitch: self.pitch,
+            },
+            None => Voice::Beep,
+        }
+    }
+
+    // FNV-1a hash of the machine state (registers, timers, stack, memory and
+    // screen).
+    pub fn state_hash(&self) -> u64 {
+        let mut hash: u64 = 0xCBF2_9CE4_8422_2325;
+        let mut feed = |bytes: &[u8]| {
+            for &byte in bytes {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
             }
-            0x8000 => self.logitcal_op(opcode),
-            0x9000 => {
-                let x = opcode & 0x0F00 >> 8;
-                let y = opcode & 0x00F0 >> 4;
-                self.skip_if_registers_not_equal(x as u8, y as u8);
+        };
+        feed(&self.v_registers);
+        feed(&self.i_register.to_be_bytes());
+        feed(&[self.timers.delay, self.timers.sound, self.stack_pointer]);
+        feed(&self.rpl_flags);
+        feed(&self.program_counter.to_be_bytes());
+        for entry in self.stack {
+            feed(&entry.to_be_bytes());
+        }
+        feed(self.memory.as_slice());
+        feed(&[self.display.resolution().id(), self.display.planes()]);
+        feed(self.display.colors());
+        feed(&self.audio_pattern.unwrap_or_default());
+        feed(&[self.pitch]);
+        hash
+    }
+
+    pub fn display(&self) -> &Display {
+        &self.display
+    }
+
+    // The screen's pixels in row-major order, in the current resolution;
+    // see `display()` for the size and the XO-CHIP colors.
+    pub fn framebuffer(&self) -> &[bool] {
+        self.display.pixels()
+    }
+
+    // The screen as a PNG, white on black at its own resolution; see
+    // `capture::screenshot` for other colors and sizes.
+    pub fn screenshot(&self) -> Vec<u8> {
+        capture::screenshot(&self.display, &Palette::default(), 1)
+    }
+
+    // The whole memory, without side effects, for debugging tools.
+    pub fn memory(&self) -> &[u8] {
+        self.memory.as_slice()
+    }
+
+    // The bytes of `range` that exist, without side effects, for debugging
+    // tools. Addresses past the end of memory are cut off.
+    pub fn memory_slice(&self, range: Range<usize>) -> &[u8] {
+        let memory = self.memory.as_slice();
+        let end = range.end.min(memory.len());
+        &memory[range.start.min(end)..end]
+    }
+
+    // Read a byte of memory without side effects, for debugging tools.
+    pub fn peek(&self, addr: usize) -> Option<u8> {
+        self.memory.read(addr).ok()
+    }
+
+    // Overwrite a byte of memory from outside the program, e.g. from an
+    // editor or a cheat. Unlike writes by instructions this raises no fault
+    // and is not audited, and the interpreter area is writable whatever
+    // the write protection. Returns whether the address exists.
+    pub fn poke(&mut self, addr: usize, value: u8) -> bool {
+        if self.memory.load(addr, &[value]).is_err() {
+            return false;
+        }
+        self.mark_written(addr, addr);
+        true
+    }
+
+    // Cheats written on loads, resets and frames, see `Cheats`. New ones
+    // are first written by the next of these.
+    pub fn set_cheats(&mut self, cheats: Cheats) {
+        self.cheats = cheats;
+    }
+
+    pub fn cheats(&self) -> &Cheats {
+        &self.cheats
+    }
+
+    // Add a cheat, or replace the one of the same name, writing its codes
+    // right away if it is enabled.
+    pub fn add_cheat(&mut self, cheat: Cheat) {
+        if cheat.enabled {
+            cheat.apply(self);
+        }
+        self.cheats.add(cheat);
+    }
+
+    // Turn a cheat on or off by name, writing its codes right away when it
+    // is turned on. Returns whether there is a cheat of that name.
+    pub fn enable_cheat(&mut self, name: &str, enabled: bool) -> bool {
+        if !self.cheats.set_enabled(name, enabled) {
+            return false;
+        }
+        if let (true, Some(cheat)) = (enabled, self.cheats.get(name).cloned()) {
+            cheat.apply(self);
+        }
+        true
+    }
+
+    fn apply_cheats(&mut self, kind: CheatKind) {
+        if self.cheats.is_empty() {
+            return;
+        }
+        let cheats = std::mem::take(&mut self.cheats);
+        cheats.apply(kind, self);
+        self.cheats = cheats;
+    }
+
+    // Extend the range reported by `take_written_range`.
+    fn mark_written(&mut self, low: usize, high: usize) {
+        self.written = Some(match self.written {
+            Some((first, last)) => (first.min(low), last.max(high)),
+            None => (low, high),
+        });
+    }
+
+    // Current value of a register.
+    pub fn register(&self, register: Register) -> u16 {
+        match register {
+            Register::V(x) => self.v_registers[(x & 0x0F) as usize] as u16,
+            Register::I => self.i_register,
+            Register::PC => self.program_counter,
+            Register::SP => self.stack_pointer as u16,
+            Register::DT => self.timers.delay as u16,
+            Register::ST => self.timers.sound as u16,
+        }
+    }
+
+    // Overwrite a register, e.g. from a debugger. Values that do not fit in
+    // the register, or addresses past the end of memory, are rejected and
+    // leave the machine untouched.
+    pub fn set_register(&mut self, register: Register, value: u16) -> Result<(), RegisterError> {
+        register.validate(value, self.memory.size())?;
+        match register {
+            Register::V(x) => self.v_registers[(x & 0x0F) as usize] = value as u8,
+            Register::I => self.i_register = value,
+            Register::PC => self.program_counter = value,
+            Register::SP => self.stack_pointer = value as u8,
+            Register::DT => self.timers.delay = value as u8,
+            Register::ST => self.timers.sound = value as u8,
+        }
+        Ok(())
+    }
+
+    // Capture the machine state, e.g. for save states or rewinding.
+    pub fn snapshot(&self) -> MachineState {
+        MachineState {
+            v_registers: self.v_registers,
+            i_register: self.i_register,
+            delay_timer: self.timers.delay,
+            sound_timer: self.timers.sound,
+            program_counter: self.program_counter,
+            stack_pointer: self.stack_pointer,
+            stack: self.stack,
+            rpl_flags: self.rpl_flags,
+            keys: self.keypad.keys(),
+            memory: self.memory.as_slice().to_vec(),
+            variant: self.variant,
+            resolution: self.display.resolution(),
+            planes: self.display.planes(),
+            framebuffer: self.display.plane(1),
+            second_plane: self.display.plane(2),
+            audio_pattern: self.audio_pattern,
+            pitch: self.pitch,
+            rng: self.rng.state(),
+            thumbnail: Some(Thumbnail::downscale(
+                self.display.pixels(),
+                self.display.width(),
+                self.display.height(),
+                THUMBNAIL_SCALE,
+            )),
+        }
+    }
+
+    pub fn restore(&mut self, state: &MachineState) {
+        self.v_registers = state.v_registers;
+        self.i_register = state.i_register;
+        self.timers.delay = state.delay_timer;
+        self.timers.sound = state.sound_timer;
+        self.program_counter = state.program_counter;
+        self.stack_pointer = state.stack_pointer.min(self.stack.len() as u8);
+        self.stack = state.stack;
+        self.rpl_flags = state.rpl_flags;
+        self.keypad.restore(state.keys);
+        self.set_variant(state.variant);
+        self.memory.restore(&state.memory);
+        self.display.set_resolution(state.resolution);
+        self.display.select_planes(state.planes);
+        self.display
+            .restore(&state.framebuffer, &state.second_plane);
+        self.audio_pattern = state.audio_pattern;
+        self.pitch = state.pitch;
+        if let Some(rng) = state.rng {
+            self.rng.restore(rng);
+        }
+        self.written = Some((0, state.memory.len().saturating_sub(1)));
+    }
+
+    pub fn save_state(&self) -> Vec<u8> {
+        self.snapshot().encode()
+    }
+
+    // Restore a save state, migrating it from older format versions. The
+    // machine is left untouched if the state cannot be loaded.
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), SaveStateError> {
+        let state = MachineState::decode(bytes)?;
+        self.restore(&state);
+        Ok(())
+    }
+
+    // Runtime statistics collected since the machine was created.
+    pub fn telemetry(&self) -> &Telemetry {
+        &self.telemetry
+    }
+
+    // Start recording memory accesses into a shadow map. Accesses made before
+    // the map is enabled, such as loading the ROM, are not recorded.
+    pub fn enable_access_map(&mut self) {
+        self.access_map.get_or_insert_with(Default::default);
+    }
+
+    pub fn access_map(&self) -> Option<&AccessMap> {
+        self.access_map.as_deref()
+    }
+
+    // Record a history of save states at the end of frames (whenever the
+    // timers tick), for `rewind`. Enabling it again starts a new history.
+    pub fn enable_rewind(&mut self, config: RewindConfig) {
+        self.rewind = Some(Box::new(Rewind::new(config)));
+    }
+
+    pub fn disable_rewind(&mut self) {
+        self.rewind = None;
+    }
+
+    // Frames of history available to `rewind`.
+    pub fn rewind_frames(&self) -> u32 {
+        self.rewind.as_ref().map_or(0, |rewind| rewind.frames())
+    }
+
+    // Go back at least `frames` frames in time, or as far as the history
+    // goes, returning how many frames the machine went back. Returns 0
+    // without history, see `enable_rewind`.
+    pub fn rewind(&mut self, frames: u32) -> u32 {
+        let Some((state, back)) = self
+            .rewind
+            .as_mut()
+            .and_then(|rewind| rewind.rewind(frames))
+        else {
+            return 0;
+        };
+        match self.load_state(&state) {
+            Ok(()) => back,
+            Err(_) => 0,
+        }
+    }
+
+    fn record_rewind(&mut self, frames: u32) {
+        let due = match &mut self.rewind {
+            Some(rewind) => rewind.advance(frames),
+            None => return,
+        };
+        if due {
+            let state = self.save_state();
+            if let Some(rewind) = &mut self.rewind {
+                rewind.record(&state);
             }
-            0xa000 => self.load_i(opcode & 0x0FFF),
-            0xb000 => self.jump_with_offset(opcode & 0x0FFF),
-            0xc000 => {
-                let x = opcode & 0x0F00 >> 8;
-                let byte = opcode & 0x00FF;
-                self.random_and(x as u8, byte as u8);
+        }
+    }
+
+    pub fn enable_profile(&mut self) {
+        self.profile.get_or_insert_with(Default::default);
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_deref()
+    }
+
+    // Enter audit mode, reporting every memory access and register change
+    // to `sink`.
+    pub fn set_audit_sink(&mut self, sink: Box<dyn AuditSink>) {
+        self.audit = Some(Auditor(sink));
+    }
+
+    pub fn clear_audit_sink(&mut self) {
+        self.audit = None;
+    }
+
+    // Report every executed instruction to `sink`, with the memory accesses
+    // and register changes it made.
+    pub fn set_trace_sink(&mut self, sink: Box<dyn TraceSink>) {
+        self.trace
+            .get_or_insert_with(|| Box::new(Tracer::new()))
+            .sink = Some(sink);
+    }
+
+    pub fn clear_trace_sink(&mut self) {
+        if let Some(tracer) = &mut self.trace {
+            tracer.sink = None;
+        }
+        self.trace.take_if(|tracer| tracer.is_idle());
+    }
+
+    // Keep the last `len` instructions run, see `history`.
+    pub fn enable_history(&mut self, len: usize) {
+        self.trace
+            .get_or_insert_with(|| Box::new(Tracer::new()))
+            .history = Some(History::new(len));
+    }
+
+    pub fn disable_history(&mut self) {
+        if let Some(tracer) = &mut self.trace {
+            tracer.history = None;
+        }
+        self.trace.take_if(|tracer| tracer.is_idle());
+    }
+
+    pub fn history(&self) -> Option<&History> {
+        self.trace.as_ref()?.history.as_ref()
+    }
+
+    // Whether every instruction has to go through `execute`, so that the
+    // access map, the profile, the audit log or the trace see it.
+    pub(crate) fn is_instrumented(&self) -> bool {
+        self.access_map.is_some()
+            || self.profile.is_some()
+            || self.audit.is_some()
+            || self.trace.is_some()
+    }
+
+    // Whether memory accesses and register changes are reported, to the
+    // audit sink or the trace sink.
+    fn is_auditing(&self) -> bool {
+        self.audit.is_some() || self.trace.as_ref().is_some_and(|t| t.sink.is_some())
+    }
+
+    fn audit(&mut self, event: AuditEvent) {
+        if let Some(Auditor(sink)) = &mut self.audit {
+            sink.record(&event);
+        }
+        if let Some(tracer) = self.trace.as_mut().filter(|t| t.sink.is_some()) {
+            tracer.events.push(event);
+        }
+    }
+
+    // Address of the instruction being executed.
+    fn instruction_address(&self) -> u16 {
+        self.program_counter.wrapping_sub(2)
+    }
+
+    // Read a byte from memory, recording the access in the shadow map.
+    fn read_memory(&mut self, addr: usize) -> Option<u8> {
+        let value = self.memory.read(addr).ok();
+        match (&mut self.access_map, value) {
+            (_, None) => {
+                self.raise(Chip8Error::MemoryOutOfBounds {
+                    access: Access::Read,
+                    address: addr,
+                });
             }
-            0xd000 => {
-                let x = opcode & 0x0F00 >> 8;
-                let y = opcode & 0x00F0 >> 4;
-                let nibble = opcode & 0x000F;
-                self.draw(x as u8, y as u8, nibble as u8);
+            (Some(map), Some(_)) => map.record_read(addr),
+            (None, Some(_)) => {}
+        }
+        if let (Some(value), true) = (value, self.is_auditing()) {
+            self.audit(AuditEvent::Read {
+                pc: self.instruction_address(),
+                address: addr,
+                value,
+            });
+        }
+        value
+    }
+
+    // Write a byte to memory, counting failed writes as errors. Returns
+    // whether execution continues, so that instructions writing several
+    // bytes stop at the first fault which halts the machine.
+    fn write_memory(&mut self, addr: usize, value: u8) -> bool {
+        let old = self.memory.read(addr).ok();
+        if let Err(error) = self.memory.write(addr, value) {
+            return self.raise(match error {
+                MemoryError::Protected(address) => Chip8Error::WriteProtected(address),
+                MemoryError::OutOfBounds(address) => Chip8Error::MemoryOutOfBounds {
+                    access: Access::Write,
+                    address,
+                },
+            });
+        }
+        self.mark_written(addr, addr);
+        if let Some(map) = &mut self.access_map {
+            map.record_write(addr);
+        }
+        if let (Some(old), true) = (old, self.is_auditing()) {
+            self.audit(AuditEvent::Write {
+                pc: self.instruction_address(),
+                address: addr,
+                old,
+                new: value,
+            });
+        }
+        true
+    }
+
+    // Record a fault and apply the policy for its check. Returns whether
+    // execution continues.
+    fn raise(&mut self, kind: Chip8Error) -> bool {
+        self.telemetry.errors += 1;
+        self.fault = Some(Fault {
+            kind,
+            program_counter: self.program_counter,
+        });
+        if self.checks.policy(kind.check()) == FaultPolicy::Halt {
+            self.halted = true;
+        }
+        !self.halted
+    }
+
+    // Return and clear the range of memory written since the last call, so
+    // backends caching decoded code can notice self-modifying programs.
+    pub(crate) fn take_written_range(&mut self) -> Option<(usize, usize)> {
+        self.written.take()
+    }
+
+    // Execute an opcode, with PC already pointing past it. Returns the fault
+    // the opcode raised, if any; whether the machine carries on after it
+    // depends on the policy for its check, see `Checks`.
+    pub fn execute(&mut self, opcode: u16) -> Result<(), Fault> {
+        let errors = self.telemetry.errors;
+        let stopwatch = self.profile.as_mut().map(|profile| {
+            profile.record(self.program_counter.wrapping_sub(2), opcode);
+            Stopwatch::start()
+        });
+        let pc = self.instruction_address();
+        if !self.is_auditing() {
+            self.execute_opcode(opcode);
+        } else {
+            let before = AUDITED_REGISTERS.map(|register| self.register(register));
+            self.execute_opcode(opcode);
+            for (register, old) in AUDITED_REGISTERS.into_iter().zip(before) {
+                let new = self.register(register);
+                if new != old {
+                    self.audit(AuditEvent::Register {
+                        pc,
+                        register,
+                        old,
+                        new,
+                    });
+                }
             }
-            0xe000 => self.skip_with_key_status(opcode),
-            0xf000 => self.fx_inst(opcode),
+        }
+        if let (Some(profile), Some(stopwatch)) = (&mut self.profile, stopwatch) {
+            profile.record_time(opcode, stopwatch.elapsed());
+        }
+        if let Some(tracer) = &mut self.trace {
+            tracer.finish(pc, opcode);
+        }
+        match self.fault {
+            Some(fault) if self.telemetry.errors > errors => Err(fault),
+            _ => Ok(()),
+        }
+    }
+
+    fn execute_opcode(&mut self, opcode: u16) {
+        self.execute_decoded(opcode, decode(opcode).ok());
+    }
+
+    // Execute an opcode decoded beforehand, with PC already pointing past
+    // it; `instruction` is none if the opcode decodes to nothing. Unlike
+    // `execute`, nothing but the telemetry sees the instruction, so callers
+    // leave instrumented machines to `execute`.
+    pub(crate) fn execute_decoded(&mut self, opcode: u16, instruction: Option<Instruction>) {
+        use Instruction::*;
+
+        self.telemetry.instructions += 1;
+        let instruction =
+            instruction.filter(|instruction| !instruction.is_xo_chip() || self.is_xo_chip());
+        let Some(instruction) = instruction else {
+            self.raise(Chip8Error::InvalidOpcode(opcode));
+            return;
+        };
+        match instruction {
+            // 0230 - CLS, a routine of the Hires CHIP-8 interpreter patch.
+            Sys { addr: 0x230 } if self.variant == Variant::HiresChip8 => self.clear_screen(),
+            // 0nnn - SYS addr, machine code routines are not emulated.
+            Sys { .. } => {}
+            ScrollDown { n } => self.scroll_down(n),
+            ScrollUp { n } => self.scroll_up(n),
+            Clear => self.clear_screen(),
+            Return => self.return_from_subroutine(),
+            ScrollRight => self.scroll_right(),
+            ScrollLeft => self.scroll_left(),
+            Exit => self.exit(),
+            LowResolution => self.switch_resolution(false),
+            HighResolution => self.switch_resolution(true),
+            Jump { addr } => self.jump_to(addr),
+            Call { addr } => self.call_subroutine(addr),
+            SkipIfEqual { x, byte } => self.skip_if_equal(x, byte),
+            SkipIfNotEqual { x, byte } => self.skip_if_not_equal(x, byte),
+            SkipIfRegistersEqual { x, y } => self.skip_if_registers_equal(x, y),
+            StoreRegisterRange { x, y } => self.store_register_range(x, y),
+            LoadRegisterRange { x, y } => self.load_register_range(x, y),
+            Load { x, byte } => self.load_to_register(x, byte),
+            AddByte { x, byte } => self.add_to_register(x, byte),
+            Move { x, y } => self.load_from_to(x, y),
+            Or { x, y } => self.or(x, y),
+            And { x, y } => self.and(x, y),
+            Xor { x, y } => self.xor(x, y),
+            Add { x, y } => self.add(x, y),
+            Sub { x, y } => self.sub(x, y),
+            ShiftRight { x, y } => self.shr(x, y),
+            SubN { x, y } => self.subn(x, y),
+            ShiftLeft { x, y } => self.shl(x, y),
+            SkipIfRegistersNotEqual { x, y } => self.skip_if_registers_not_equal(x, y),
+            LoadI { addr } => self.load_i(addr),
+            JumpWithOffset { addr } => self.jump_with_offset(addr),
+            Random { x, byte } => self.random_and(x, byte),
+            Draw { x, y, n } => self.draw(x, y, n),
+            SkipIfKeyPressed { x } => self.skip_if_key_pressed(x),
+            SkipIfKeyNotPressed { x } => self.skip_if_key_not_pressed(x),
+            LoadILong => self.load_i_long(),
+            SelectPlanes { n } => self.display.select_planes(n),
+            LoadAudioPattern => self.load_audio_pattern(),
+            LoadDelayTimer { x } => self.load_delay_timer(x),
+            WaitForKey { x } => self.wait_for_key_press(x),
+            SetDelayTimer { x } => self.set_delay_timer(x),
+            SetSoundTimer { x } => self.set_sound_timer(x),
+            AddToI { x } => self.add_to_i_register(x),
+            LoadDigit { x } => self.set_i_register(x),
+            LoadBigDigit { x } => self.set_i_big_digit(x),
+            StoreBcd { x } => self.store_bcd(x),
+            SetPitch { x } => self.set_pitch(x),
+            StoreRegisters { x } => self.store_registers(x),
+            LoadRegisters { x } => self.load_registers(x),
+            StoreRplFlags { x } => self.store_rpl_flags(x),
+            LoadRplFlags { x } => self.load_rpl_flags(x),
         }
     }
 
+    // 00Cn - SCD nibble
+    // Scroll the display down by n pixels.
+    fn scroll_down(&mut self, n: u8) {
+        self.display.scroll(0, n as isize);
+    }
+
+    // 00Dn - SCU nibble
+    // Scroll the display up by n pixels.
+    fn scroll_up(&mut self, n: u8) {
+        self.display.scroll(0, -(n as isize));
+    }
+
+    // 00E0 - CLS
+    // Clear the display.
+    fn clear_screen(&mut self) {
+        self.display.clear();
+    }
+
+    // 00EE - RET
+    // Return from a subroutine to the address on top of the stack.
+    fn return_from_subroutine(&mut self) {
+        if self.stack_pointer == 0 {
+            self.raise(Chip8Error::StackUnderflow);
+            return;
+        }
+        self.stack_pointer -= 1;
+        self.program_counter = self.stack[self.stack_pointer as usize];
+    }
+
+    // 00FB - SCR
+    // Scroll the display right by 4 pixels.
+    fn scroll_right(&mut self) {
+        self.display.scroll(4, 0);
+    }
+
+    // 00FC - SCL
+    // Scroll the display left by 4 pixels.
+    fn scroll_left(&mut self) {
+        self.display.scroll(-4, 0);
+    }
+
+    // 00FD - EXIT
+    // Exit the interpreter, halting the machine without a fault.
+    fn exit(&mut self) {
+        self.fault = None;
+        self.halted = true;
+    }
+
+    // 00FE - LOW, 00FF - HIGH
+    // Switch to the low resolution or 128x64 pixels, clearing the display.
+    fn switch_resolution(&mut self, hires: bool) {
+        self.display.set_hires(hires);
+    }
+
     // 1nnn - JP addr
     // Jump to location nnn.
     fn jump_to(&mut self, addr: u16) {
@@ -149,18 +1273,31 @@ impl Chip8 {
     }
 
     // 2nnn - CALL addr
-    // Call subroutine at nnn.
+    // Call subroutine at nnn, pushing the address of the instruction after
+    // the call, where PC already points.
     fn call_subroutine(&mut self, addr: u16) {
-        self.stack_pointer = (self.stack_pointer + 1) % 16;
+        if self.stack_pointer as usize >= self.stack.len() {
+            self.raise(Chip8Error::StackOverflow);
+            return;
+        }
         self.stack[self.stack_pointer as usize] = self.program_counter;
+        self.stack_pointer += 1;
         self.program_counter = addr;
     }
 
+    // Skip the next instruction, which takes four bytes if it is an XO-CHIP
+    // F000 nnnn.
+    fn skip(&mut self) {
+        let next = self.program_counter as usize;
+        let long = self.is_xo_chip() && self.memory.read_word(next) == Ok(0xF000);
+        self.program_counter = self.program_counter.wrapping_add(if long { 4 } else { 2 });
+    }
+
     // 3xkk - SE Vx, byte
     // Skip next instaruction if Vx == kk.
     fn skip_if_equal(&mut self, x: u8, byte: u8) {
         if self.v_registers[x as usize] == byte {
-            self.program_counter += 2;
+            self.skip();
         }
     }
 
@@ -168,7 +1305,7 @@ impl Chip8 {
     // Skip next instaruction if Vx != kk.
     fn skip_if_not_equal(&mut self, x: u8, byte: u8) {
         if self.v_registers[x as usize] != byte {
-            self.program_counter += 2;
+            self.skip();
         }
     }
 
@@ -176,7 +1313,32 @@ impl Chip8 {
     // Skip next instaruction if Vx == Vy.
     fn skip_if_registers_equal(&mut self, x: u8, y: u8) {
         if self.v_registers[x as usize] == self.v_registers[y as usize] {
-            self.program_counter += 2;
+            self.skip();
+        }
+    }
+
+    // 5xy2 - LD [I], Vx-Vy
+    // Store registers Vx through Vy, in either order, in memory starting at
+    // location I. I is left unchanged.
+    fn store_register_range(&mut self, x: u8, y: u8) {
+        for (offset, register) in register_range(x, y).enumerate() {
+            let value = self.v_registers[register];
+            if !self.write_memory(self.i_register as usize + offset, value) {
+                return;
+            }
+        }
+    }
+
+    // 5xy3 - LD Vx-Vy, [I]
+    // Read registers Vx through Vy, in either order, from memory starting at
+    // location I. I is left unchanged.
+    fn load_register_range(&mut self, x: u8, y: u8) {
+        for (offset, register) in register_range(x, y).enumerate() {
+            match self.read_memory(self.i_register as usize + offset) {
+                Some(value) => self.v_registers[register] = value,
+                None if self.halted => return,
+                None => {}
+            }
         }
     }
 
@@ -192,25 +1354,6 @@ impl Chip8 {
         self.v_registers[x as usize] = self.v_registers[x as usize].wrapping_add(byte);
     }
 
-    // Logitcal operations
-    fn logitcal_op(&mut self, opcode: u16) {
-        let x = opcode & 0x0F00 >> 8;
-        let y = opcode & 0x00F0 >> 4;
-        let c = opcode & 0x000F;
-        match c {
-            0x0 => self.load_from_to(x, y),
-            0x1 => self.or(x, y),
-            0x2 => self.and(x, y),
-            0x3 => self.xor(x, y),
-            0x4 => self.add(x, y),
-            0x5 => self.sub(x, y),
-            0x6 => self.shr(x, y),
-            0x7 => self.subn(x, y),
-            0xe => self.shl(x, y),
-            _ => panic!("Unknown opcode: {}", opcode),
-        }
-    }
-
     // 8xy0 - LD Vx, Vy
     // Set Vx = Vy.
     fn load_from_to(&mut self, x: u8, y: u8) {
@@ -221,223 +1364,272 @@ impl Chip8 {
     // Set Vx = Vx OR Vy.
     fn or(&mut self, x: u8, y: u8) {
         self.v_registers[x as usize] |= self.v_registers[y as usize];
+        self.reset_vf();
     }
 
     // 8xy2 - AND Vx, Vy
     // Set Vx = Vx AND Vy.
     fn and(&mut self, x: u8, y: u8) {
         self.v_registers[x as usize] &= self.v_registers[y as usize];
+        self.reset_vf();
     }
 
     // 8xy3 - XOR Vx, Vy
     // Set Vx = Vx XOR Vy.
     fn xor(&mut self, x: u8, y: u8) {
         self.v_registers[x as usize] ^= self.v_registers[y as usize];
+        self.reset_vf();
+    }
+
+    // The COSMAC VIP logic instructions clobber VF.
+    fn reset_vf(&mut self) {
+        if self.quirks.vf_reset {
+            self.v_registers[0xF] = 0;
+        }
     }
 
     // 8xy4 - ADD Vx, Vy
     // Set Vx = Vx + Vy, set VF = carry.
+    // The flag is written last, so that it wins when x is F.
     fn add(&mut self, x: u8, y: u8) {
-        self.v_registers[0xF] =
-            if self.v_registers[x as usize] > (0xFF - self.v_registers[y as usize]) {
-                1
-            } else {
-                0
-            };
-        self.v_registers[x as usize] =
-            self.v_registers[x as usize].wrapping_add(self.v_registers[y as usize]);
+        let (sum, carry) =
+            self.v_registers[x as usize].overflowing_add(self.v_registers[y as usize]);
+        self.v_registers[x as usize] = sum;
+        self.v_registers[0xF] = carry as u8;
     }
 
     // 8xy5 - SUB Vx, Vy
     // Set Vx = Vx - Vy, set VF = NOT borrow.
     fn sub(&mut self, x: u8, y: u8) {
-        self.v_registers[0xF] = if self.v_registers[x as usize] > self.v_registers[y as usize] {
-            1
-        } else {
-            0
-        };
-        self.v_registers[x as usize] =
-            self.v_registers[x as usize].wrapping_sub(self.v_registers[y as usize]);
+        let (vx, vy) = (self.v_registers[x as usize], self.v_registers[y as usize]);
+        self.subtract_into(x, vx, vy);
     }
 
     // 8xy6 - SHR Vx {, Vy}
-    // Set Vx = Vx SHR 1.
+    // Set Vx = Vx SHR 1, or Vx = Vy SHR 1 without the shifting quirk.
     fn shr(&mut self, x: u8, y: u8) {
-        self.v_registers[0xF] = self.v_registers[x as usize] & 0x1;
-        self.v_registers[x as usize] >>= 1;
+        let value = self.shift_source(x, y);
+        self.v_registers[x as usize] = value >> 1;
+        self.v_registers[0xF] = value & 0x1;
     }
 
     // 8xy7 - SUBN Vx, Vy
     // Set Vx = Vy - Vx, set VF = NOT borrow.
     fn subn(&mut self, x: u8, y: u8) {
-        self.v_registers[0xF] = if self.v_registers[y as usize] > self.v_registers[x as usize] {
-            1
-        } else {
-            0
-        };
-        self.v_registers[x as usize] =
-            self.v_registers[y as usize].wrapping_sub(self.v_registers[x as usize]);
+        let (vx, vy) = (self.v_registers[x as usize], self.v_registers[y as usize]);
+        self.subtract_into(x, vy, vx);
     }
 
     // 8xyE - SHL Vx {, Vy}
-    // Set Vx = Vx SHL 1.
+    // Set Vx = Vx SHL 1, or Vx = Vy SHL 1 without the shifting quirk.
     fn shl(&mut self, x: u8, y: u8) {
-        self.v_registers[0xF] = (self.v_registers[x as usize] & 0x80) >> 7;
-        self.v_registers[x as usize] <<= 1;
+        let value = self.shift_source(x, y);
+        self.v_registers[x as usize] = value << 1;
+        self.v_registers[0xF] = value >> 7;
+    }
+
+    // Set Vx = from - by and VF = NOT borrow, which is 1 when both are
+    // equal. The flag is written last, so that it wins when x is F.
+    fn subtract_into(&mut self, x: u8, from: u8, by: u8) {
+        let (difference, borrow) = from.overflowing_sub(by);
+        self.v_registers[x as usize] = difference;
+        self.v_registers[0xF] = !borrow as u8;
+    }
+
+    // The register 8xy6 and 8xyE shift, Vx with the shifting quirk and Vy
+    // without.
+    fn shift_source(&self, x: u8, y: u8) -> u8 {
+        let source = if self.quirks.shifting { x } else { y };
+        self.v_registers[source as usize]
     }
 
     // 9xy0 - SNE Vx, Vy
     // Skip next instruction if Vx != Vy.
     fn skip_if_registers_not_equal(&mut self, x: u8, y: u8) {
         if self.v_registers[x as usize] != self.v_registers[y as usize] {
-            self.program_counter += 2;
+            self.skip();
         }
     }
 
     // Annn - LD I, addr
     // Set I = nnn.
     fn load_i(&mut self, nnn: u16) {
-        self.i_registers = nnn;
+        self.i_register = nnn;
     }
 
     // Bnnn - JP V0, addr
-    // Jump to location nnn + V0.
+    // Jump to location nnn + V0, or nnn + Vx with the jumping quirk. Targets
+    // past the end of memory wrap around to its start.
     fn jump_with_offset(&mut self, nnn: u16) {
-        self.program_counter = nnn + self.v_registers[0] as u16;
+        let x = if self.quirks.jumping { nnn >> 8 } else { 0 };
+        let target = nnn as usize + self.v_registers[x as usize] as usize;
+        self.program_counter = (target % self.memory.size()) as u16;
     }
 
     // Cxkk - RND Vx, byte
     // Set Vx = random byte AND kk.
     fn random_and(&mut self, x: u8, kk: u8) {
-        let mut rng = rand::thread_rng();
-        let byte: u8 = rng.gen();
+        let byte = self.rng.next_byte();
         self.v_registers[x as usize] = byte & kk;
     }
 
     // Dxyn - DRW Vx, Vy, nibble
     // Display n-byte sprite starting at memory location I at (Vx, Vy), set VF = collision.
+    // Dxy0 displays a 16x16 sprite of 32 bytes, two per row. With both XO-CHIP
+    // planes selected, the sprite for the second plane follows the first.
     fn draw(&mut self, x: u8, y: u8, nibble: u8) {
-        let mut sprite: [u8; 16] = [0; 16];
-        for i in 0..(nibble & 0x0F) {
-            if let Some(v) = self.memory.access((self.i_registers + i as u16) as usize) {
-                sprite[i as usize] = *v;
-            } else {
-                panic!(
-                    "Invalid memory address: 0x{:X}.",
-                    self.i_registers + i as u16
-                );
+        self.telemetry.draw_calls += 1;
+        let mut sprite = [0; 64];
+        let planes = self.display.planes().count_ones() as usize;
+        let len = match nibble & 0x0F {
+            0 => 32,
+            n => n as usize,
+        } * planes;
+        for (i, byte) in sprite.iter_mut().take(len).enumerate() {
+            // Invalid addresses are recorded as faults, the rows read as 0
+            // unless the fault halts the machine, which draws nothing.
+            match self.read_memory(self.i_register as usize + i) {
+                Some(v) => *byte = v,
+                None if self.halted => return,
+                None => {}
             }
         }
-        // TODO: Implement collision detection.
-    }
-
-    // Skip next instruction if key pressed or not.
-    fn skip_with_key_status(&mut self, opcode: u16) {
-        let x = opcode & 0x0F00 >> 8;
-        let low_byte = opcode & 0x00FF;
-        match low_byte {
-            0x9E => {}
-            0xA1 => {}
-            _ => panic!("Invalid opcode: 0x{:X}.", opcode),
-        }
+        let (x, y) = (
+            self.v_registers[x as usize] as usize,
+            self.v_registers[y as usize] as usize,
+        );
+        let collision = if nibble & 0x0F == 0 {
+            self.display.draw_sprite_16(x, y, &sprite)
+        } else {
+            self.display.draw_sprite(x, y, &sprite[..len])
+        };
+        self.v_registers[0xF] = collision as u8;
+        self.telemetry.collisions += collision as u64;
     }
 
     // Ex9E - SKP Vx
     // Skip next instruction if key with the value of Vx is pressed.
     fn skip_if_key_pressed(&mut self, x: u8) {
-        // TODO: Implement key press detection.
+        if self.keypad.is_pressed(self.v_registers[x as usize]) {
+            self.skip();
+        }
     }
 
     // ExA1 - SKNP Vx
     // Skip next instruction if key with the value of Vx is not pressed.
     fn skip_if_key_not_pressed(&mut self, x: u8) {
-        // TODO: Implement key press detection.
+        if !self.keypad.is_pressed(self.v_registers[x as usize]) {
+            self.skip();
+        }
+    }
+
+    // F000 nnnn - LD I, long nnnn
+    // Set I = the 16-bit address following the instruction, and skip it.
+    fn load_i_long(&mut self) {
+        let pc = self.program_counter as usize;
+        if let (Some(high), Some(low)) = (self.read_memory(pc), self.read_memory(pc + 1)) {
+            self.i_register = u16::from_be_bytes([high, low]);
+        }
+        self.program_counter = self.program_counter.wrapping_add(2);
     }
 
-    // Fx** instructions
-    fn fx_inst(&mut self, opcode: u16) {
-        let x = opcode & 0x0F00 >> 8;
-        let low_byte = opcode & 0x00FF;
-        match low_byte {
-            0x07 => self.load_delay_timer(x),
-            0x0A => self.wait_for_key_press(x),
-            0x15 => self.set_delay_timer(x),
-            0x18 => self.set_sound_timer(x),
-            0x1E => self.add_to_i_register(x),
-            0x29 => self.set_i_register(x),
-            0x33 => self.store_bcd(x),
-            0x55 => self.store_registers(x),
-            0x65 => self.load_registers(x),
-            _ => panic!("Invalid opcode: 0x{:X}.", opcode),
+    // F002 - AUDIO
+    // Load the 16-byte audio pattern starting at memory location I.
+    fn load_audio_pattern(&mut self) {
+        let mut pattern = [0; 16];
+        for (i, byte) in pattern.iter_mut().enumerate() {
+            match self.read_memory(self.i_register as usize + i) {
+                Some(value) => *byte = value,
+                None if self.halted => return,
+                None => {}
+            }
         }
+        self.audio_pattern = Some(pattern);
+    }
+
+    // Fx3A - PITCH Vx
+    // Set the playback rate of the audio pattern to 4000 * 2^((Vx - 64) / 48) bits per second.
+    fn set_pitch(&mut self, x: u8) {
+        self.pitch = self.v_registers[x as usize];
     }
 
     // Fx07 - LD Vx, DT
     // Set Vx = delay timer value.
     fn load_delay_timer(&mut self, x: u8) {
-        self.v_registers[x as usize] = self.delay_timer;
+        self.v_registers[x as usize] = self.timers.delay;
     }
 
     // Fx0A - LD Vx, K
     // Wait for a key press, store the value of the key in Vx.
+    // The key is stored once released, until then the instruction repeats.
     fn wait_for_key_press(&mut self, x: u8) {
-        // TODO: Implement key press detection.
+        match self.keypad.wait_for_key() {
+            Some(key) => self.v_registers[x as usize] = key,
+            None => self.program_counter = self.instruction_address(),
+        }
     }
 
     // Fx15 - LD DT, Vx
     // Set delay timer = Vx.
     fn set_delay_timer(&mut self, x: u8) {
-        self.delay_timer = self.v_registers[x as usize];
+        self.timers.delay = self.v_registers[x as usize];
     }
 
     // Fx18 - LD ST, Vx
     // Set sound timer = Vx.
     fn set_sound_timer(&mut self, x: u8) {
-        self.sound_timer = self.v_registers[x as usize];
+        if self.timers.sound == 0 && self.v_registers[x as usize] > 0 {
+            self.telemetry.sound_activations += 1;
+        }
+        self.timers.sound = self.v_registers[x as usize];
     }
 
     // Fx1E - ADD I, Vx
     // Set I = I + Vx.
     fn add_to_i_register(&mut self, x: u8) {
-        if let Some(value) = self.memory.access(self.i_registers as usize) {
-            self.memory.assign(
-                self.i_registers as usize,
-                value.wrapping_add(self.v_registers[x as usize] as u8),
-            );
-        } else {
-            eprintln!("Invalid memory address: 0x{:X}.", self.i_registers);
-        }
+        self.i_register = self
+            .i_register
+            .wrapping_add(self.v_registers[x as usize] as u16);
     }
 
     // Fx29 - LD F, Vx
     // Set I = location of sprite for digit Vx.
     fn set_i_register(&mut self, x: u8) {
-        // TODO: Implement sprite loading.
+        let digit = (self.v_registers[x as usize] & 0x0F) as usize;
+        self.i_register = (memory::FONT_ADDRESS + digit * 5) as u16;
+    }
+
+    // Fx30 - LD HF, Vx
+    // Set I = location of the 8x10 sprite for digit Vx.
+    fn set_i_big_digit(&mut self, x: u8) {
+        let digit = (self.v_registers[x as usize] & 0x0F) as usize;
+        self.i_register = (memory::BIG_FONT_ADDRESS + digit * 10) as u16;
     }
 
     // Fx33 - LD B, Vx
     // Store BCD representation of Vx in memory locations I, I+1, and I+2.
     fn store_bcd(&mut self, x: u8) {
-        self.memory.assign(
-            self.i_registers as usize,
-            self.v_registers[x as usize] / 100,
-        );
-        self.memory.assign(
-            (self.i_registers + 1) as usize,
-            (self.v_registers[x as usize] / 10) % 10,
-        );
-        self.memory.assign(
-            (self.i_registers + 2) as usize,
-            self.v_registers[x as usize] % 10,
-        );
+        let value = self.v_registers[x as usize];
+        for (offset, digit) in [value / 100, value / 10 % 10, value % 10]
+            .into_iter()
+            .enumerate()
+        {
+            if !self.write_memory(self.i_register as usize + offset, digit) {
+                return;
+            }
+        }
     }
 
     // Fx55 - LD [I], Vx
     // Store registers V0 through Vx in memory starting at location I.
     fn store_registers(&mut self, x: u8) {
         for i in 0..=x as usize {
-            self.memory
-                .assign(self.i_registers as usize + i, self.v_registers[i]);
+            if !self.write_memory(self.i_register as usize + i, self.v_registers[i]) {
+                return;
+            }
+        }
+        if self.quirks.memory {
+            self.i_register = self.i_register.wrapping_add(x as u16 + 1);
         }
     }
 
@@ -445,9 +1637,44 @@ impl Chip8 {
     // Read registers V0 through Vx from memory starting at location I.
     fn load_registers(&mut self, x: u8) {
         for i in 0..=x as usize {
-            if let Some(value) = self.memory.access(self.i_registers as usize + i) {
-                self.v_registers[i] = value;
+            match self.read_memory(self.i_register as usize + i) {
+                Some(value) => self.v_registers[i] = value,
+                None if self.halted => return,
+                None => {}
             }
         }
+        if self.quirks.memory {
+            self.i_register = self.i_register.wrapping_add(x as u16 + 1);
+        }
+    }
+
+    // Fx75 - LD R, Vx
+    // Store registers V0 through Vx in the RPL user flags.
+    fn store_rpl_flags(&mut self, x: u8) {
+        let count = x as usize + 1;
+        self.rpl_flags[..count].copy_from_slice(&self.v_registers[..count]);
+    }
+
+    // Fx85 - LD Vx, R
+    // Read registers V0 through Vx from the RPL user flags.
+    fn load_rpl_flags(&mut self, x: u8) {
+        let count = x as usize + 1;
+        self.v_registers[..count].copy_from_slice(&self.rpl_flags[..count]);
+    }
+}
+
+// Registers from Vx to Vy, counting down if y is below x.
+fn register_range(x: u8, y: u8) -> Box<dyn Iterator<Item = usize>> {
+    let (x, y) = (x as usize, y as usize);
+    if x <= y {
+        Box::new(x..=y)
+    } else {
+        Box::new((y..=x).rev())
+    }
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
     }
 }
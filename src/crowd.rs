@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+/// # Crowd Input
+///
+/// "Twitch plays CHIP-8": many remote clients vote for a key and every frame
+/// the key with the most votes is held down. Each client has one vote per
+/// frame, a later vote replacing an earlier one. Ties go to the lowest key,
+/// so the outcome only depends on the votes and not on their arrival order,
+/// and the resulting masks can be recorded and replayed like any other input.
+#[derive(Debug, Clone, Default)]
+pub struct CrowdInput {
+    // Vote of every client for the current frame
+    votes: BTreeMap<u64, u8>,
+}
+
+impl CrowdInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn vote(&mut self, client: u64, key: u8) {
+        self.votes.insert(client, key & 0x0F);
+    }
+
+    pub fn vote_count(&self) -> usize {
+        self.votes.len()
+    }
+
+    // Close the voting for this frame and return the keypad mask to apply,
+    // with only the winning key held (or none without votes).
+    pub fn tally(&mut self) -> u16 {
+        let mut counts = [0usize; 16];
+        for &key in self.votes.values() {
+            counts[key as usize] += 1;
+        }
+        self.votes.clear();
+
+        let (key, &count) = counts
+            .iter()
+            .enumerate()
+            .max_by(|(a, x), (b, y)| x.cmp(y).then(b.cmp(a)))
+            .unwrap_or((0, &0));
+        if count == 0 {
+            0
+        } else {
+            1 << key
+        }
+    }
+}
+
+#[cfg(feature = "net")]
+pub use server::VoteServer;
+
+#[cfg(feature = "net")]
+mod server {
+    use std::io::{self, BufRead, BufReader, ErrorKind};
+    use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+
+    use super::CrowdInput;
+
+    /// Collects votes over TCP. Clients send one hexadecimal key per line;
+    /// every connection is a separate voter. The server never blocks, the host
+    /// calls `poll` once per frame before `CrowdInput::tally`.
+    pub struct VoteServer {
+        listener: TcpListener,
+        clients: Vec<Client>,
+        next_id: u64,
+    }
+
+    struct Client {
+        id: u64,
+        reader: BufReader<TcpStream>,
+
+        // Partial line received so far
+        line: String,
+    }
+
+    impl VoteServer {
+        pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+            let listener = TcpListener::bind(addr)?;
+            listener.set_nonblocking(true)?;
+            Ok(Self {
+                listener,
+                clients: Vec::new(),
+                next_id: 0,
+            })
+        }
+
+        // The address the server listens on, e.g. the port picked when
+        // bound to port 0.
+        pub fn local_addr(&self) -> io::Result<SocketAddr> {
+            self.listener.local_addr()
+        }
+
+        pub fn client_count(&self) -> usize {
+            self.clients.len()
+        }
+
+        // Accept new clients and feed every complete line into the votes.
+        pub fn poll(&mut self, crowd: &mut CrowdInput) -> io::Result<()> {
+            loop {
+                match self.listener.accept() {
+                    Ok((stream, _)) => {
+                        stream.set_nonblocking(true)?;
+                        self.clients.push(Client {
+                            id: self.next_id,
+                            reader: BufReader::new(stream),
+                            line: String::new(),
+                        });
+                        self.next_id += 1;
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                    Err(e) => return Err(e),
+                }
+            }
+
+            self.clients.retain_mut(|client| loop {
+                match client.reader.read_line(&mut client.line) {
+                    // Connection closed
+                    Ok(0) => return false,
+                    Ok(_) => {
+                        if let Ok(key) = u8::from_str_radix(client.line.trim(), 16) {
+                            if key < 16 {
+                                crowd.vote(client.id, key);
+                            }
+                        }
+                        client.line.clear();
+                    }
+                    // Keep the partial line until the rest arrives
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => return true,
+                    Err(_) => return false,
+                }
+            });
+            Ok(())
+        }
+    }
+}
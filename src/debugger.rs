@@ -0,0 +1,684 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::{self, Write};
+use std::rc::Rc;
+
+use crate::audit::AuditEvent;
+use crate::cheats::{Cheat, CheatCode, CheatKind};
+use crate::cpu::{Chip8, StepResult};
+use crate::disasm;
+use crate::fault::Fault;
+use crate::heatmap::AccessMap;
+use crate::instruction::{decode, Instruction};
+use crate::ramsearch::{RamSearch, Width};
+use crate::registers::Register;
+use crate::savestate::MachineState;
+use crate::watch::{Expr, ParseError, WatchList};
+
+/// # Debugger
+///
+/// Wraps a `Chip8` to run it under control: breakpoints stop execution
+/// before the instruction at an address runs, optionally only while a
+/// watch expression is true (non-zero), watchpoints stop it after an
+/// instruction wrote to a memory address or changed a register. Execution
+/// goes one instruction at a time (`step`), over whole subroutine calls
+/// (`step_over`) or on until something stops it (`resume`). The timers
+/// tick after every `cycles_per_frame` instructions, as with `run_frame`.
+///
+/// Watchpoints are fed by the machine's audit events, so the debugger
+/// takes over the audit sink while any are set. After every instruction,
+/// a `StepHook` sees the whole machine and may stop execution as well.
+/// Watch expressions added with `display` are shown whenever a command
+/// stops.
+///
+/// `command` runs one line of the prompt of `chip8 debug`:
+///
+/// ```text
+/// b 0x200         break before the instruction at 0x200 (d deletes it)
+/// b 0x31C if V4 == 0x1F
+///                 break there only while the condition holds
+/// w 0x300         stop after writes to 0x300; w V3 after V3 changes
+/// p [I+2]         print the value of a watch expression
+/// display V4      show V4 after every stop, undisplay 1 removes it again
+/// s [n]           step n instructions, 1 by default
+/// n               step over a CALL
+/// c [frames]      continue, for at most 600 frames by default
+/// regs            show the registers
+/// x/16 I          show 16 bytes starting at I, addresses are watch
+///                 expressions
+/// key 5 down      press (or release with up) a key
+/// snap            remember the machine state, restore goes back to it
+/// cheats          list the cheats, see `Cheats`
+/// cheat off Infinite lives
+///                 turn a cheat off (or on again with on)
+/// cheat 2F3:03    freeze 2F3 at 03 from now on
+/// heatmap         show which bytes of the 4KB were read, written or
+///                 executed since the debugger started, as a 64x64 grid
+///                 of characters, see `AccessMap::to_text`
+/// search 8        search memory for a byte (16 for a word), see `RamSearch`
+/// search decreased
+///                 keep the addresses whose value decreased, listed as
+///                 cheat codes; search alone lists them again
+/// ```
+#[derive(Debug)]
+pub struct Debugger {
+    chip8: Chip8,
+    cycles_per_frame: usize,
+
+    // Instructions executed since the timers last ticked
+    cycles: usize,
+
+    // Breakpoints and their conditions, if any
+    breakpoints: BTreeMap<u16, Option<Expr>>,
+    memory_watchpoints: BTreeSet<usize>,
+    register_watchpoints: HashSet<Register>,
+
+    // Audit events of the last instruction, while watchpoints are set
+    events: Rc<RefCell<Vec<AuditEvent>>>,
+
+    snapshot: Option<MachineState>,
+    search: Option<RamSearch>,
+
+    // Expressions shown after every stop, see `display`
+    watches: WatchList,
+
+    hook: Option<Hook>,
+}
+
+/// Called after every instruction the debugger executes, with the machine
+/// as the instruction left it. Returning true stops execution with
+/// `Stop::Hook`, e.g. once a condition spanning several instructions is
+/// met.
+pub trait StepHook {
+    fn after_step(&mut self, chip8: &Chip8) -> bool;
+}
+
+impl<F: FnMut(&Chip8) -> bool> StepHook for F {
+    fn after_step(&mut self, chip8: &Chip8) -> bool {
+        self(chip8)
+    }
+}
+
+struct Hook(Box<dyn StepHook>);
+
+impl fmt::Debug for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Hook")
+    }
+}
+
+/// Why execution stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    Breakpoint(u16),
+    Watchpoint(AuditEvent),
+    Fault(Fault),
+    Exited,
+    WaitingForKey,
+
+    // The `StepHook` asked to stop, with PC after the instruction
+    Hook(u16),
+}
+
+// Frames `c` runs for at most, so a program that never stops does not
+// hang the prompt.
+const CONTINUE_FRAMES: u64 = 600;
+
+// Bytes shown per line by `x`.
+const BYTES_PER_LINE: usize = 16;
+
+// Candidates listed by `search`, there are thousands before the first
+// condition.
+const SEARCH_RESULTS: usize = 16;
+
+impl Debugger {
+    pub fn new(mut chip8: Chip8, cycles_per_frame: usize) -> Debugger {
+        // For `heatmap`
+        chip8.enable_access_map();
+        Debugger {
+            chip8,
+            cycles_per_frame: cycles_per_frame.max(1),
+            cycles: 0,
+            breakpoints: BTreeMap::new(),
+            memory_watchpoints: BTreeSet::new(),
+            register_watchpoints: HashSet::new(),
+            events: Rc::new(RefCell::new(Vec::new())),
+            snapshot: None,
+            search: None,
+            watches: WatchList::new(),
+            hook: None,
+        }
+    }
+
+    pub fn chip8(&self) -> &Chip8 {
+        &self.chip8
+    }
+
+    pub fn chip8_mut(&mut self) -> &mut Chip8 {
+        &mut self.chip8
+    }
+
+    pub fn into_inner(mut self) -> Chip8 {
+        self.chip8.clear_audit_sink();
+        self.chip8
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.breakpoints.insert(address, None);
+    }
+
+    // Break at the address only while `condition` evaluates to non-zero.
+    // Conditions without a value, e.g. reading outside of memory, do not
+    // break. Replaces any breakpoint at the address.
+    pub fn add_conditional_breakpoint(&mut self, address: u16, condition: Expr) {
+        self.breakpoints.insert(address, Some(condition));
+    }
+
+    // Returns whether there was a breakpoint at the address.
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool {
+        self.breakpoints.remove(&address).is_some()
+    }
+
+    // Remove every breakpoint, e.g. once the program they were set in
+    // changed.
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.keys().copied()
+    }
+
+    // The condition of the breakpoint at the address, if it has one.
+    pub fn condition(&self, address: u16) -> Option<&Expr> {
+        self.breakpoints.get(&address)?.as_ref()
+    }
+
+    // Whether execution stops before the instruction at `pc`.
+    fn breaks_at(&self, pc: u16) -> bool {
+        match self.breakpoints.get(&pc) {
+            Some(Some(condition)) => condition.eval(&self.chip8).is_some_and(|value| value != 0),
+            Some(None) => true,
+            None => false,
+        }
+    }
+
+    // Show an expression after every stop.
+    pub fn display(&mut self, source: &str) -> Result<(), ParseError> {
+        self.watches.add(source)
+    }
+
+    // Remove the expression at `index`, from 0 in the order they were
+    // added.
+    pub fn undisplay(&mut self, index: usize) {
+        self.watches.remove(index);
+    }
+
+    pub fn watches(&self) -> &WatchList {
+        &self.watches
+    }
+
+    pub fn set_step_hook(&mut self, hook: Box<dyn StepHook>) {
+        self.hook = Some(Hook(hook));
+    }
+
+    pub fn clear_step_hook(&mut self) {
+        self.hook = None;
+    }
+
+    pub fn watch_memory(&mut self, address: usize) {
+        self.memory_watchpoints.insert(address);
+        self.install_sink();
+    }
+
+    pub fn watch_register(&mut self, register: Register) {
+        self.register_watchpoints.insert(register);
+        self.install_sink();
+    }
+
+    fn install_sink(&mut self) {
+        let events = Rc::clone(&self.events);
+        self.chip8
+            .set_audit_sink(Box::new(move |event: &AuditEvent| {
+                events.borrow_mut().push(*event)
+            }));
+    }
+
+    // Execute one instruction, ticking the timers at the end of a frame.
+    pub fn step(&mut self) -> Option<Stop> {
+        self.events.borrow_mut().clear();
+        let result = self.chip8.step();
+        self.cycles += 1;
+        if self.cycles == self.cycles_per_frame {
+            self.cycles = 0;
+            self.chip8.tick_timers();
+        }
+        match result {
+            StepResult::Error(fault) => return Some(Stop::Fault(fault)),
+            StepResult::Exited => return Some(Stop::Exited),
+            _ => {}
+        }
+        if let Some(Hook(hook)) = &mut self.hook {
+            if hook.after_step(&self.chip8) {
+                return Some(Stop::Hook(self.chip8.register(Register::PC)));
+            }
+        }
+        let watched = self
+            .events
+            .borrow()
+            .iter()
+            .copied()
+            .find(|event| match *event {
+                AuditEvent::Write { address, .. } => self.memory_watchpoints.contains(&address),
+                AuditEvent::Register { register, .. } => {
+                    self.register_watchpoints.contains(&register)
+                }
+                AuditEvent::Read { .. } => false,
+            });
+        if let Some(event) = watched {
+            return Some(Stop::Watchpoint(event));
+        }
+        (result == StepResult::WaitingForKey).then_some(Stop::WaitingForKey)
+    }
+
+    // Execute the instruction at PC, running a called subroutine until it
+    // returns.
+    pub fn step_over(&mut self, max_frames: u64) -> Option<Stop> {
+        let pc = self.chip8.register(Register::PC);
+        let is_call = self
+            .chip8
+            .peek(pc as usize)
+            .zip(self.chip8.peek(pc as usize + 1))
+            .is_some_and(|(high, low)| {
+                matches!(
+                    decode(u16::from_be_bytes([high, low])),
+                    Ok(Instruction::Call { .. })
+                )
+            });
+        if !is_call {
+            return self.step();
+        }
+        let depth = self.chip8.register(Register::SP);
+        let returned = |chip8: &Chip8| {
+            chip8.register(Register::PC) == pc + 2 && chip8.register(Register::SP) == depth
+        };
+        self.run_until(max_frames, returned)
+    }
+
+    // Execute instructions until a breakpoint, watchpoint, fault or exit
+    // stops them, or `max_frames` frames ran. The instruction at PC runs
+    // even if there is a breakpoint on it, so execution can go on from one.
+    pub fn resume(&mut self, max_frames: u64) -> Option<Stop> {
+        self.run_until(max_frames, |_| false)
+    }
+
+    fn run_until(&mut self, max_frames: u64, done: impl Fn(&Chip8) -> bool) -> Option<Stop> {
+        let budget = max_frames as usize * self.cycles_per_frame;
+        for executed in 0..budget {
+            let pc = self.chip8.register(Register::PC);
+            if executed > 0 && self.breaks_at(pc) {
+                return Some(Stop::Breakpoint(pc));
+            }
+            match self.step() {
+                // Waiting for a key only stops single steps.
+                Some(Stop::WaitingForKey) => {}
+                Some(stop) => return Some(stop),
+                None => {}
+            }
+            if done(&self.chip8) {
+                return None;
+            }
+        }
+        None
+    }
+
+    pub fn snapshot(&self) -> MachineState {
+        self.chip8.snapshot()
+    }
+
+    pub fn restore(&mut self, state: &MachineState) {
+        self.chip8.restore(state);
+        self.cycles = 0;
+    }
+
+    // Run one line of the prompt, returning what to print.
+    pub fn command(&mut self, line: &str) -> Result<String, String> {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return Ok(String::new());
+        };
+        let rest: Vec<&str> = words.collect();
+        let rest = rest.join(" ");
+        let (command, count) = match command.split_once('/') {
+            Some((command, count)) => (command, Some(count)),
+            None => (command, None),
+        };
+        match command {
+            "b" => match rest.split_once(" if ") {
+                Some((address, condition)) => {
+                    let address = self.address(address)?;
+                    let expr = Expr::parse(condition).map_err(|e| e.to_string())?;
+                    self.add_conditional_breakpoint(address as u16, expr);
+                    Ok(format!(
+                        "breakpoint at 0x{:03X} if {}",
+                        address,
+                        condition.trim()
+                    ))
+                }
+                None => {
+                    let address = self.address(&rest)?;
+                    self.add_breakpoint(address as u16);
+                    Ok(format!("breakpoint at 0x{:03X}", address))
+                }
+            },
+            "d" => {
+                let address = self.address(&rest)?;
+                match self.remove_breakpoint(address as u16) {
+                    true => Ok(format!("deleted breakpoint at 0x{:03X}", address)),
+                    false => Err(format!("no breakpoint at 0x{:03X}", address)),
+                }
+            }
+            "w" => match rest.parse::<Register>() {
+                Ok(register) => {
+                    self.watch_register(register);
+                    Ok(format!("watching {}", register))
+                }
+                Err(_) => {
+                    let address = self.address(&rest)?;
+                    self.watch_memory(address);
+                    Ok(format!("watching 0x{:03X}", address))
+                }
+            },
+            "s" => {
+                let steps = if rest.is_empty() { 1 } else { number(&rest)? };
+                let mut stop = None;
+                for _ in 0..steps {
+                    stop = self.step();
+                    if stop.is_some() {
+                        break;
+                    }
+                }
+                Ok(self.report(stop))
+            }
+            "n" => {
+                let stop = self.step_over(CONTINUE_FRAMES);
+                Ok(self.report(stop))
+            }
+            "c" => {
+                let frames = if rest.is_empty() {
+                    CONTINUE_FRAMES
+                } else {
+                    number(&rest)? as u64
+                };
+                let stop = self.resume(frames);
+                Ok(self.report(stop))
+            }
+            "p" => {
+                let value = self.evaluate(&rest)?;
+                Ok(format!("{} = 0x{:X}", rest.trim(), value))
+            }
+            "display" => {
+                self.display(&rest).map_err(|e| e.to_string())?;
+                Ok(self.watch_values())
+            }
+            "undisplay" => {
+                let index = number(&rest)?;
+                if index == 0 || index > self.watches.len() {
+                    return Err(format!("no expression {}", index));
+                }
+                self.undisplay(index - 1);
+                Ok(String::new())
+            }
+            "regs" => Ok(self.registers()),
+            "x" => {
+                let len = count.map_or(Ok(BYTES_PER_LINE), number)?;
+                let address = self.address(&rest)?;
+                Ok(self.dump(address, len))
+            }
+            "key" => {
+                let (key, state) = rest.split_once(' ').unwrap_or((&rest, ""));
+                let key = number(key).ok().filter(|&k| k < 16);
+                match (key, state) {
+                    (Some(key), "down") => self.chip8.set_key(key as u8, true),
+                    (Some(key), "up") => self.chip8.set_key(key as u8, false),
+                    _ => return Err("key expects a key from 0 to 15 and down or up".to_string()),
+                }
+                Ok(String::new())
+            }
+            "snap" => {
+                self.snapshot = Some(self.snapshot());
+                Ok("saved".to_string())
+            }
+            "restore" => {
+                let state = self.snapshot.clone().ok_or("nothing saved yet")?;
+                self.restore(&state);
+                Ok(self.location())
+            }
+            "cheats" => Ok(self
+                .chip8
+                .cheats()
+                .iter()
+                .map(Cheat::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")),
+            "cheat" => self.cheat(&rest),
+            "search" => self.search(&rest),
+            "heatmap" => Ok(self
+                .chip8
+                .access_map()
+                .map(AccessMap::to_text)
+                .unwrap_or_default()),
+            _ => Err(format!("unknown command `{}`", command)),
+        }
+    }
+
+    // Turn a cheat on or off by name, or freeze memory with new codes,
+    // named after them.
+    fn cheat(&mut self, rest: &str) -> Result<String, String> {
+        let toggle = match rest.split_once(' ') {
+            Some(("on", name)) => Some((name, true)),
+            Some(("off", name)) => Some((name, false)),
+            _ => None,
+        };
+        if let Some((name, enabled)) = toggle {
+            return match self.chip8.enable_cheat(name, enabled) {
+                true => Ok(self
+                    .chip8
+                    .cheats()
+                    .get(name)
+                    .map(Cheat::to_string)
+                    .unwrap_or_default()),
+                false => Err(format!("no cheat `{}`", name)),
+            };
+        }
+        let codes = rest
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<CheatCode>, _>>()?;
+        if codes.is_empty() {
+            return Err("cheat expects codes, or on or off and a name".to_string());
+        }
+        let cheat = Cheat::new(rest, CheatKind::Freeze, codes);
+        let listed = cheat.to_string();
+        self.chip8.add_cheat(cheat);
+        Ok(listed)
+    }
+
+    // Start a RAM search for bytes or words, narrow it down with a
+    // condition, or list the candidates left as cheat codes holding their
+    // current value, ready for `cheat`.
+    fn search(&mut self, rest: &str) -> Result<String, String> {
+        let memory = self.chip8.memory();
+        let width = match rest {
+            "8" => Some(Width::Byte),
+            "16" => Some(Width::Word),
+            _ => None,
+        };
+        if let Some(width) = width {
+            self.search = Some(RamSearch::with_width(memory, width));
+        }
+        let search = self
+            .search
+            .as_mut()
+            .ok_or("no search, start one with search 8 or search 16")?;
+        if width.is_none() && !rest.is_empty() {
+            search.filter(memory, rest.parse()?);
+        }
+        let results = search.results();
+        let plural = if results.len() == 1 { "" } else { "s" };
+        let mut out = format!("{} candidate{}", results.len(), plural);
+        for (address, value) in results.into_iter().take(SEARCH_RESULTS) {
+            let bytes = value.to_be_bytes();
+            let bytes = &bytes[2 - search.width().bytes()..];
+            out.push('\n');
+            for (offset, byte) in bytes.iter().enumerate() {
+                let separator = if offset == 0 { "" } else { " " };
+                write!(out, "{}{:03X}:{:02X}", separator, address + offset, byte).unwrap();
+            }
+        }
+        Ok(out)
+    }
+
+    // Evaluate an address given as a watch expression.
+    fn address(&self, source: &str) -> Result<usize, String> {
+        self.evaluate(source).map(usize::from)
+    }
+
+    fn evaluate(&self, source: &str) -> Result<u16, String> {
+        let expr = Expr::parse(source).map_err(|e| e.to_string())?;
+        expr.eval(&self.chip8)
+            .ok_or_else(|| format!("`{}` has no value", source))
+    }
+
+    // Describe why execution stopped, followed by the next instruction and
+    // the watch expressions.
+    fn report(&self, stop: Option<Stop>) -> String {
+        let mut out = match stop {
+            Some(stop) => format!("{}\n{}", stop, self.location()),
+            None => self.location(),
+        };
+        if !self.watches.is_empty() {
+            out.push('\n');
+            out.push_str(&self.watch_values());
+        }
+        out
+    }
+
+    // The watch expressions, numbered from 1 for `undisplay`.
+    fn watch_values(&self) -> String {
+        let lines: Vec<String> = self
+            .watches
+            .evaluate(&self.chip8)
+            .into_iter()
+            .enumerate()
+            .map(|(index, (source, value))| match value {
+                Some(v) => format!("{}: {} = 0x{:X}", index + 1, source, v),
+                None => format!("{}: {} = ?", index + 1, source),
+            })
+            .collect();
+        lines.join("\n")
+    }
+
+    // The next instruction, disassembled.
+    fn location(&self) -> String {
+        let pc = self.chip8.register(Register::PC);
+        let opcode = self
+            .chip8
+            .peek(pc as usize)
+            .zip(self.chip8.peek(pc as usize + 1))
+            .map(|(high, low)| u16::from_be_bytes([high, low]));
+        match opcode {
+            Some(opcode) => {
+                let text = decode(opcode)
+                    .map(|instruction| disasm::mnemonic(instruction, &BTreeMap::new()))
+                    .unwrap_or_else(|_| "???".to_string());
+                format!("0x{:03X}: {:04X}  {}", pc, opcode, text)
+            }
+            None => format!("0x{:03X}: outside of memory", pc),
+        }
+    }
+
+    fn registers(&self) -> String {
+        let mut out = String::new();
+        for x in 0..16 {
+            let separator = match x {
+                0 => "",
+                8 => "\n",
+                _ => " ",
+            };
+            let value = self.chip8.register(Register::V(x));
+            let _ = write!(out, "{}V{:X}={:02X}", separator, x, value);
+        }
+        let _ = write!(
+            out,
+            "\nI={:03X} PC={:03X} SP={:X} DT={:02X} ST={:02X}",
+            self.chip8.register(Register::I),
+            self.chip8.register(Register::PC),
+            self.chip8.register(Register::SP),
+            self.chip8.register(Register::DT),
+            self.chip8.register(Register::ST),
+        );
+        out
+    }
+
+    fn dump(&self, address: usize, len: usize) -> String {
+        let lines: Vec<String> = (address..address + len)
+            .step_by(BYTES_PER_LINE)
+            .map(|start| {
+                let end = (start + BYTES_PER_LINE).min(address + len);
+                let bytes: Vec<String> = (start..end)
+                    .map(|a| match self.chip8.peek(a) {
+                        Some(byte) => format!("{:02X}", byte),
+                        None => "--".to_string(),
+                    })
+                    .collect();
+                format!("0x{:03X}: {}", start, bytes.join(" "))
+            })
+            .collect();
+        lines.join("\n")
+    }
+}
+
+fn number(text: &str) -> Result<usize, String> {
+    let text = text.trim();
+    let parsed = match text.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| format!("invalid number `{}`", text))
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stop::Breakpoint(pc) => write!(f, "breakpoint at 0x{:03X}", pc),
+            Stop::Watchpoint(AuditEvent::Write {
+                pc,
+                address,
+                old,
+                new,
+            }) => write!(
+                f,
+                "0x{:03X} changed from 0x{:02X} to 0x{:02X} at 0x{:03X}",
+                address, old, new, pc
+            ),
+            Stop::Watchpoint(AuditEvent::Register {
+                pc,
+                register,
+                old,
+                new,
+            }) => write!(
+                f,
+                "{} changed from 0x{:02X} to 0x{:02X} at 0x{:03X}",
+                register, old, new, pc
+            ),
+            Stop::Watchpoint(AuditEvent::Read { pc, address, .. }) => {
+                write!(f, "0x{:03X} read at 0x{:03X}", address, pc)
+            }
+            Stop::Fault(fault) => write!(f, "{}", fault),
+            Stop::Exited => write!(f, "program exited"),
+            Stop::WaitingForKey => write!(f, "waiting for a key"),
+            Stop::Hook(pc) => write!(f, "stopped by the step hook at 0x{:03X}", pc),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use crate::cpu::Chip8;
+use crate::registers::Register;
+
+/// # Determinism Check
+///
+/// Replays, movies and netplay all rely on the machine being a pure function
+/// of the ROM, the RNG seed and the keypad input of each frame. The check runs
+/// two machines in lockstep from identical starting conditions and compares
+/// their state hashes after every frame, so an accidental source of
+/// nondeterminism is reported at the first frame where it shows up.
+///
+/// Inputs are given per frame as a 16-bit mask, where bit k set means key k
+/// is held down during that frame. Frames past the end of the inputs run with
+/// every key released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence {
+    // Index of the first frame whose state hashes differ
+    pub frame: u64,
+
+    // State hashes of the two runs after that frame
+    pub first: u64,
+    pub second: u64,
+}
+
+// Run two machines set up alike, on the same inputs for the given number of
+// frames, failing on the first frame where they disagree: booted the same
+// way to check that runs repeat, or with different backends to compare
+// them.
+pub fn compare_runs(
+    mut first: Chip8,
+    mut second: Chip8,
+    inputs: &[u16],
+    frames: u64,
+    cycles_per_frame: usize,
+) -> Result<(), Divergence> {
+    for frame in 0..frames {
+        let keys = inputs.get(frame as usize).copied().unwrap_or(0);
+        for chip8 in [&mut first, &mut second] {
+            chip8.set_keys(keys);
+            chip8.run_frame(cycles_per_frame);
+        }
+        let (a, b) = (first.state_hash(), second.state_hash());
+        if a != b {
+            return Err(Divergence {
+                frame,
+                first: a,
+                second: b,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The first instruction after which two machines running the same ROM
+/// with different quirks disagree, see `find_quirk_divergence`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuirkDivergence {
+    pub frame: u64,
+
+    // Index of the instruction within the frame
+    pub instruction: usize,
+
+    // The instruction both machines executed last
+    pub program_counter: u16,
+    pub opcode: u16,
+}
+
+// Run two machines booted alike but for their quirks in lockstep on the
+// same inputs, comparing the state hashes after every instruction, and
+// report the first instruction after which they differ. That instruction
+// is the one depending on a quirk, see `Quirks::affecting`.
+pub fn find_quirk_divergence(
+    mut first: Chip8,
+    mut second: Chip8,
+    inputs: &[u16],
+    frames: u64,
+    cycles_per_frame: usize,
+) -> Option<QuirkDivergence> {
+    for frame in 0..frames {
+        let keys = inputs.get(frame as usize).copied().unwrap_or(0);
+        first.set_keys(keys);
+        second.set_keys(keys);
+        for instruction in 0..cycles_per_frame {
+            let pc = first.register(Register::PC);
+            let opcode = u16::from_be_bytes([
+                first.peek(pc as usize).unwrap_or(0),
+                first.peek(pc as usize + 1).unwrap_or(0),
+            ]);
+            first.step();
+            second.step();
+            if first.state_hash() != second.state_hash() {
+                return Some(QuirkDivergence {
+                    frame,
+                    instruction,
+                    program_counter: pc,
+                    opcode,
+                });
+            }
+        }
+        first.tick_timers();
+        second.tick_timers();
+    }
+    None
+}
@@ -0,0 +1,427 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::rc::Rc;
+
+use crate::audit::AuditEvent;
+use crate::cpu::Chip8;
+use crate::instruction::{self, Instruction};
+use crate::sprites;
+
+/// # Disassembler
+///
+/// Turns a ROM back into source in the assembler's syntax, SUPER-CHIP and
+/// XO-CHIP instructions included. Code is found by following the control
+/// flow from 0x200 through jumps, calls and skips; bytes no instruction
+/// leads to, and bytes known to be data, become `db` lines. Every line
+/// shows the address and raw bytes as a comment. Targets of jumps, calls
+/// and `LD I` get generated symbol names (`loc_2A6`, `sub_2A0`,
+/// `sprite_300`, `data_3F0`) used in place of the raw addresses, so the
+/// listing reassembles into the same ROM.
+///
+/// A static listing only knows the sprites found by `sprites::scan`. For
+/// reverse engineering, a `Trace` recorded while running the ROM adds what
+/// actually happened: how often every instruction executed, which addresses
+/// it read and wrote, and which bytes were drawn as sprites. The annotated
+/// listing shows those as comments, with a preview of every sprite row.
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    // Executions per instruction address
+    executions: BTreeMap<u16, u64>,
+
+    // Addresses read and written by each instruction
+    reads: BTreeMap<u16, BTreeSet<usize>>,
+    writes: BTreeMap<u16, BTreeSet<usize>>,
+}
+
+const START: u16 = 0x200;
+
+// Data bytes per `db` line, outside of sprites.
+const DATA_PER_LINE: usize = 8;
+
+// Column of the comments in the listing.
+const COMMENT_COLUMN: usize = 24;
+
+impl Trace {
+    // Run `frames` frames, recording every instruction and memory access.
+    pub fn record(chip8: &mut Chip8, frames: u64, cycles_per_frame: usize) -> Trace {
+        let trace = Rc::new(RefCell::new(Trace::default()));
+        let sink = Rc::clone(&trace);
+        chip8.set_audit_sink(Box::new(move |event: &AuditEvent| {
+            sink.borrow_mut().add(event)
+        }));
+        chip8.enable_profile();
+        for _ in 0..frames {
+            chip8.run_frame(cycles_per_frame);
+        }
+        chip8.clear_audit_sink();
+
+        let mut trace = trace.take();
+        if let Some(profile) = chip8.profile() {
+            trace.executions = profile.hot_spots().into_iter().collect();
+        }
+        trace
+    }
+
+    fn add(&mut self, event: &AuditEvent) {
+        match *event {
+            AuditEvent::Read { pc, address, .. } => {
+                self.reads.entry(pc).or_default().insert(address);
+            }
+            AuditEvent::Write { pc, address, .. } => {
+                self.writes.entry(pc).or_default().insert(address);
+            }
+            AuditEvent::Register { .. } => {}
+        }
+    }
+
+    // Number of executions of the instruction at `pc`.
+    pub fn executions(&self, pc: u16) -> u64 {
+        self.executions.get(&pc).copied().unwrap_or(0)
+    }
+
+    // Bytes read by DRW instructions, given the ROM to tell them apart.
+    fn drawn(&self, rom: &[u8]) -> BTreeSet<usize> {
+        self.reads
+            .iter()
+            .filter(|(&pc, _)| {
+                opcode(rom, pc).is_some_and(|op| {
+                    matches!(instruction::decode(op), Ok(Instruction::Draw { .. }))
+                })
+            })
+            .flat_map(|(_, addresses)| addresses.iter().copied())
+            .collect()
+    }
+
+    // Bytes accessed as data by any instruction.
+    fn accessed(&self) -> BTreeSet<usize> {
+        self.reads
+            .values()
+            .chain(self.writes.values())
+            .flatten()
+            .copied()
+            .collect()
+    }
+}
+
+fn opcode(rom: &[u8], address: u16) -> Option<u16> {
+    let offset = address.checked_sub(START)? as usize;
+    Some(u16::from_be_bytes([
+        *rom.get(offset)?,
+        *rom.get(offset + 1)?,
+    ]))
+}
+
+// An instruction in the assembler's syntax, with addresses replaced by
+// their symbol when there is one. `ld i, long` is followed by the address
+// in the next word, which the caller appends.
+pub fn mnemonic(instruction: Instruction, symbols: &BTreeMap<u16, String>) -> String {
+    use Instruction::*;
+
+    let name = |addr: u16| {
+        symbols
+            .get(&addr)
+            .cloned()
+            .unwrap_or_else(|| format!("0x{:03X}", addr))
+    };
+    match instruction {
+        Sys { addr } => format!("sys {}", name(addr)),
+        ScrollDown { n } => format!("scd {}", n),
+        ScrollUp { n } => format!("scu {}", n),
+        Clear => "cls".to_string(),
+        Return => "ret".to_string(),
+        ScrollRight => "scr".to_string(),
+        ScrollLeft => "scl".to_string(),
+        Exit => "exit".to_string(),
+        LowResolution => "low".to_string(),
+        HighResolution => "high".to_string(),
+        Jump { addr } => format!("jp {}", name(addr)),
+        Call { addr } => format!("call {}", name(addr)),
+        SkipIfEqual { x, byte } => format!("se v{:x}, 0x{:02X}", x, byte),
+        SkipIfNotEqual { x, byte } => format!("sne v{:x}, 0x{:02X}", x, byte),
+        SkipIfRegistersEqual { x, y } => format!("se v{:x}, v{:x}", x, y),
+        StoreRegisterRange { x, y } => format!("ld [i], v{:x}-v{:x}", x, y),
+        LoadRegisterRange { x, y } => format!("ld v{:x}-v{:x}, [i]", x, y),
+        Load { x, byte } => format!("ld v{:x}, 0x{:02X}", x, byte),
+        AddByte { x, byte } => format!("add v{:x}, 0x{:02X}", x, byte),
+        Move { x, y } => format!("ld v{:x}, v{:x}", x, y),
+        Or { x, y } => format!("or v{:x}, v{:x}", x, y),
+        And { x, y } => format!("and v{:x}, v{:x}", x, y),
+        Xor { x, y } => format!("xor v{:x}, v{:x}", x, y),
+        Add { x, y } => format!("add v{:x}, v{:x}", x, y),
+        Sub { x, y } => format!("sub v{:x}, v{:x}", x, y),
+        ShiftRight { x, y } => format!("shr v{:x}, v{:x}", x, y),
+        SubN { x, y } => format!("subn v{:x}, v{:x}", x, y),
+        ShiftLeft { x, y } => format!("shl v{:x}, v{:x}", x, y),
+        SkipIfRegistersNotEqual { x, y } => format!("sne v{:x}, v{:x}", x, y),
+        LoadI { addr } => format!("ld i, {}", name(addr)),
+        JumpWithOffset { addr } => format!("jp v0, {}", name(addr)),
+        Random { x, byte } => format!("rnd v{:x}, 0x{:02X}", x, byte),
+        Draw { x, y, n } => format!("drw v{:x}, v{:x}, {}", x, y, n),
+        SkipIfKeyPressed { x } => format!("skp v{:x}", x),
+        SkipIfKeyNotPressed { x } => format!("sknp v{:x}", x),
+        LoadILong => "ld i, long".to_string(),
+        SelectPlanes { n } => format!("plane {}", n),
+        LoadAudioPattern => "audio".to_string(),
+        LoadDelayTimer { x } => format!("ld v{:x}, dt", x),
+        WaitForKey { x } => format!("ld v{:x}, k", x),
+        SetDelayTimer { x } => format!("ld dt, v{:x}", x),
+        SetSoundTimer { x } => format!("ld st, v{:x}", x),
+        AddToI { x } => format!("add i, v{:x}", x),
+        LoadDigit { x } => format!("ld f, v{:x}", x),
+        LoadBigDigit { x } => format!("ld hf, v{:x}", x),
+        StoreBcd { x } => format!("ld b, v{:x}", x),
+        SetPitch { x } => format!("pitch v{:x}", x),
+        StoreRegisters { x } => format!("ld [i], v{:x}", x),
+        LoadRegisters { x } => format!("ld v{:x}, [i]", x),
+        StoreRplFlags { x } => format!("ld r, v{:x}", x),
+        LoadRplFlags { x } => format!("ld v{:x}, r", x),
+    }
+}
+
+// The instruction at `address` and its size in bytes, four for F000 with
+// its address.
+fn instruction_at(rom: &[u8], address: usize) -> Option<(Instruction, usize)> {
+    let instruction = instruction::decode(opcode(rom, address as u16)?).ok()?;
+    match instruction {
+        Instruction::LoadILong => {
+            opcode(rom, address as u16 + 2)?;
+            Some((instruction, 4))
+        }
+        _ => Some((instruction, 2)),
+    }
+}
+
+// Addresses of the instructions reachable from the start of the ROM,
+// following jumps, calls and both ways out of skips. `jp v0` only leads
+// to its base address, as the offset is not known statically.
+fn reachable(rom: &[u8]) -> BTreeSet<usize> {
+    use Instruction::*;
+
+    let mut found = BTreeSet::new();
+    let mut pending = vec![START as usize];
+    while let Some(address) = pending.pop() {
+        if found.contains(&address) {
+            continue;
+        }
+        let Some((instruction, size)) = instruction_at(rom, address) else {
+            continue;
+        };
+        found.insert(address);
+        let next = address + size;
+        match instruction {
+            Return | Exit => {}
+            Jump { addr } | JumpWithOffset { addr } => pending.push(addr as usize),
+            Call { addr } => pending.extend([addr as usize, next]),
+            SkipIfEqual { .. }
+            | SkipIfNotEqual { .. }
+            | SkipIfRegistersEqual { .. }
+            | SkipIfRegistersNotEqual { .. }
+            | SkipIfKeyPressed { .. }
+            | SkipIfKeyNotPressed { .. } => {
+                let skipped = instruction_at(rom, next).map_or(2, |(_, size)| size);
+                pending.extend([next, next + skipped]);
+            }
+            _ => pending.push(next),
+        }
+    }
+    found
+}
+
+// Generate symbols for the addresses within the ROM that the instructions
+// at `code` refer to.
+fn symbols(rom: &[u8], code: &BTreeSet<usize>, sprites: &BTreeSet<usize>) -> BTreeMap<u16, String> {
+    let end = START as usize + rom.len();
+    let mut symbols: BTreeMap<u16, String> = BTreeMap::new();
+    for &address in code {
+        let Some((instruction, _)) = instruction_at(rom, address) else {
+            continue;
+        };
+        let (prefix, target) = match instruction {
+            Instruction::Jump { addr } | Instruction::JumpWithOffset { addr } => ("loc", addr),
+            Instruction::Call { addr } => ("sub", addr),
+            Instruction::LoadI { addr } => ("data", addr),
+            Instruction::LoadILong => match opcode(rom, address as u16 + 2) {
+                Some(addr) => ("data", addr),
+                None => continue,
+            },
+            _ => continue,
+        };
+        if !(START as usize..end).contains(&(target as usize)) {
+            continue;
+        }
+        let prefix = match prefix {
+            "data" if sprites.contains(&(target as usize)) => "sprite",
+            prefix => prefix,
+        };
+        // Calls name a subroutine even if it is also jumped to.
+        let name = format!("{}_{:03X}", prefix, target);
+        match symbols.get(&target) {
+            Some(existing) if !existing.starts_with("loc") || prefix != "sub" => {}
+            _ => {
+                symbols.insert(target, name);
+            }
+        }
+    }
+    symbols
+}
+
+// Disassemble the ROM, annotated with what happened in `trace` if given.
+pub fn listing(rom: &[u8], trace: Option<&Trace>) -> String {
+    let mut sprites: BTreeSet<usize> = sprites::scan(rom, START)
+        .iter()
+        .flat_map(|s| s.address as usize..s.address as usize + s.data.len())
+        .collect();
+    let mut data = BTreeSet::new();
+    if let Some(trace) = trace {
+        sprites.extend(trace.drawn(rom));
+        data.extend(trace.accessed());
+    }
+    // Sprites are shown one row per line, other data packed.
+    let sprite_width = sprite_widths(rom, &sprites);
+    data.extend(sprites.iter().copied());
+    // Instructions reachable from the start or run in the trace are code.
+    let mut code = reachable(rom);
+    if let Some(trace) = trace {
+        code.extend(trace.executions.keys().map(|&pc| pc as usize));
+    }
+    let symbols = symbols(rom, &code, &sprites);
+    // Executed bytes are code, whatever else they look like.
+    let executed = |address: usize| {
+        trace.is_some_and(|t| {
+            t.executions(address as u16) > 0 || t.executions(address as u16 - 1) > 0
+        })
+    };
+    let is_data = |address: usize| data.contains(&address) && !executed(address);
+    let instruction = |address: usize| {
+        let (instruction, size) =
+            instruction_at(rom, address).filter(|_| code.contains(&address))?;
+        // An instruction must not hide data or a symbol in its other bytes.
+        let hides = |a: usize| a > address && symbols.contains_key(&(a as u16));
+        let clear = (address..address + size).all(|a| !is_data(a) && !hides(a));
+        clear.then_some((instruction, size))
+    };
+
+    let mut out = String::new();
+    let mut address = START as usize;
+    let end = START as usize + rom.len();
+    while address < end {
+        if let Some(name) = symbols.get(&(address as u16)) {
+            let _ = writeln!(out, "{}:", name);
+        }
+        match instruction(address) {
+            Some((instruction, size)) => {
+                let mut text = mnemonic(instruction, &symbols);
+                let bytes = &rom[address - START as usize..address - START as usize + size];
+                let mut comment = format!("{:03X}:", address);
+                for word in bytes.chunks(2) {
+                    let _ = write!(comment, " {:02X}{:02X}", word[0], word[1]);
+                }
+                if instruction == Instruction::LoadILong {
+                    let target = u16::from_be_bytes([bytes[2], bytes[3]]);
+                    let name = symbols.get(&target).cloned();
+                    text += &format!(" {}", name.unwrap_or_else(|| format!("0x{:04X}", target)));
+                }
+                if let Some(trace) = trace {
+                    annotate(&mut comment, trace, address as u16);
+                }
+                line(&mut out, &text, &comment);
+                address += size;
+            }
+            _ if sprites.contains(&address) => {
+                let width = sprite_width.get(&address).copied().unwrap_or(1);
+                let row: Vec<u8> = (address..(address + width).min(end))
+                    .map(|a| rom[a - START as usize])
+                    .collect();
+                let bytes: Vec<String> = row.iter().map(|b| format!("0b{:08b}", b)).collect();
+                let preview: String = row.iter().map(|&b| pixels(b)).collect();
+                let comment = format!("{:03X}: {}", address, preview);
+                line(&mut out, &format!("db {}", bytes.join(", ")), &comment);
+                address += row.len();
+            }
+            _ => {
+                // Pack data until the next symbol, sprite or instruction.
+                let start = address;
+                address += 1;
+                while address < end
+                    && address - start < DATA_PER_LINE
+                    && !symbols.contains_key(&(address as u16))
+                    && !sprites.contains(&address)
+                    && instruction(address).is_none()
+                {
+                    address += 1;
+                }
+                let bytes: Vec<String> = rom[start - START as usize..address - START as usize]
+                    .iter()
+                    .map(|b| format!("0x{:02X}", b))
+                    .collect();
+                line(
+                    &mut out,
+                    &format!("db {}", bytes.join(", ")),
+                    &format!("{:03X}", start),
+                );
+            }
+        }
+    }
+    out
+}
+
+// Bytes per row of the sprites drawn with 16 pixel wide DRW Vx, Vy, 0, by
+// address of each row.
+fn sprite_widths(rom: &[u8], sprites: &BTreeSet<usize>) -> BTreeMap<usize, usize> {
+    let mut widths = BTreeMap::new();
+    for sprite in sprites::scan(rom, START).iter().filter(|s| s.width == 16) {
+        for row in 0..sprite.height() {
+            widths.insert(sprite.address as usize + row * 2, 2);
+        }
+    }
+    widths.retain(|address, _| sprites.contains(address));
+    widths
+}
+
+fn annotate(comment: &mut String, trace: &Trace, pc: u16) {
+    match trace.executions(pc) {
+        0 => comment.push_str("  never run"),
+        n => {
+            let _ = write!(comment, "  x{}", n);
+        }
+    }
+    if let Some(reads) = trace.reads.get(&pc) {
+        let _ = write!(comment, "  reads {}", ranges(reads));
+    }
+    if let Some(writes) = trace.writes.get(&pc) {
+        let _ = write!(comment, "  writes {}", ranges(writes));
+    }
+}
+
+// Addresses as a list of ranges, e.g. `0x300-0x304, 0x310`.
+fn ranges(addresses: &BTreeSet<usize>) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &address in addresses {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == address => *end = address,
+            _ => ranges.push((address, address)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                format!("0x{:03X}", start)
+            } else {
+                format!("0x{:03X}-0x{:03X}", start, end)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn pixels(byte: u8) -> String {
+    (0..8)
+        .map(|bit| if byte & (0x80 >> bit) != 0 { '#' } else { '.' })
+        .collect()
+}
+
+fn line(out: &mut String, text: &str, comment: &str) {
+    let _ = writeln!(out, "    {:w$}; {}", text, comment, w = COMMENT_COLUMN - 4);
+}
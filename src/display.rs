@@ -1,3 +1,10 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 /// # Display
 ///
 /// ## Modes
@@ -7,6 +14,11 @@
 /// - 64x64
 /// - 128x64
 ///
+/// Programs run in one of the low resolutions, 64x32 unless the machine
+/// has a larger screen: the Hires CHIP-8 interpreters drew 64x64 (see
+/// `Variant::HiresChip8`), others 64x48. SUPER-CHIP and XO-CHIP programs
+/// switch to 128x64 with 00FF and back to the low resolution with 00FE.
+///
 /// ## Sprites
 ///
 /// Chip-8 draws graphics on screen through the use of sprites. A sprite is a
@@ -16,3 +28,346 @@
 /// Programs may also refer to a group of sprites representing the hexadecimal
 /// digits 0 through F. These sprites are 5 bytes long, or 8x5 pixels. The data
 /// should be stored in the interpreter area of Chip-8 memory (0x000 to 0x1FF).
+///
+/// SUPER-CHIP adds 16x16 sprites, two bytes per row, drawn by `Dxy0`, and
+/// large 8x10 digits for `Fx30`.
+///
+/// ## Framebuffer
+///
+/// `Display` holds the monochrome screen in its current resolution.
+/// Switching resolution clears the screen. Sprites are XORed onto it, so drawing a
+/// sprite twice erases it again; a pixel switched off that way is a
+/// collision, which `Dxyn` reports in VF. Coordinates wrap around the edges
+/// of the screen, both the starting position and the sprite pixels running
+/// past an edge.
+///
+/// Scrolling (00Cn, 00FB, 00FC and 00Dn) moves the picture by pixels of
+/// the current resolution, pixels scrolled in are off.
+///
+/// ## Planes
+///
+/// XO-CHIP draws on two bit planes, which combine into four colors. `Fn01`
+/// selects the planes that sprites, clearing and scrolling act on, the
+/// first one by default. With both planes selected, a sprite holds the rows
+/// for the first plane followed by those for the second. `pixels` lights
+/// the pixels lit on either plane, `colors` tells them apart.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Display {
+    // Color of every pixel in row-major order, bit 0 for the first plane
+    // and bit 1 for the second
+    colors: Vec<u8>,
+
+    // Pixels lit on any plane
+    pixels: Vec<bool>,
+
+    // Planes acted on, bit 0 for the first plane
+    planes: u8,
+
+    // The current resolution, and the low one 00FE returns to
+    resolution: Resolution,
+    lores: Resolution,
+}
+
+/// Sizes of the screen, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Resolution {
+    #[default]
+    R64x32,
+    R64x48,
+    R64x64,
+    R128x64,
+}
+
+pub const WIDTH: usize = 64;
+pub const HEIGHT: usize = 32;
+
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+
+impl Resolution {
+    pub const ALL: [Resolution; 4] = [
+        Resolution::R64x32,
+        Resolution::R64x48,
+        Resolution::R64x64,
+        Resolution::R128x64,
+    ];
+
+    pub fn width(self) -> usize {
+        match self {
+            Resolution::R128x64 => HIRES_WIDTH,
+            _ => WIDTH,
+        }
+    }
+
+    pub fn height(self) -> usize {
+        match self {
+            Resolution::R64x32 => HEIGHT,
+            Resolution::R64x48 => 48,
+            Resolution::R64x64 | Resolution::R128x64 => 64,
+        }
+    }
+
+    // Number of the resolution in save states and state hashes, where
+    // 64x32 and 128x64 came first.
+    #[cfg(feature = "std")]
+    pub(crate) fn id(self) -> u8 {
+        match self {
+            Resolution::R64x32 => 0,
+            Resolution::R128x64 => 1,
+            Resolution::R64x48 => 2,
+            Resolution::R64x64 => 3,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn from_id(id: u8) -> Option<Resolution> {
+        Resolution::ALL
+            .into_iter()
+            .find(|resolution| resolution.id() == id)
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width(), self.height())
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for Resolution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Resolution::ALL
+            .into_iter()
+            .find(|resolution| resolution.to_string() == s)
+            .ok_or_else(|| format!("unknown resolution `{}`", s))
+    }
+}
+
+// Mask of all the planes.
+#[cfg(feature = "std")]
+const ALL_PLANES: u8 = 0b11;
+
+#[cfg(feature = "std")]
+impl Display {
+    pub fn new() -> Display {
+        Display::with_resolution(Resolution::default())
+    }
+
+    // A blank screen in a low resolution.
+    pub fn with_resolution(resolution: Resolution) -> Display {
+        let mut display = Display {
+            colors: Vec::new(),
+            pixels: Vec::new(),
+            planes: 1,
+            resolution,
+            lores: Resolution::default(),
+        };
+        display.set_resolution(resolution);
+        display
+    }
+
+    pub fn width(&self) -> usize {
+        self.resolution.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.resolution.height()
+    }
+
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    // The resolution 00FE returns to.
+    pub fn lores(&self) -> Resolution {
+        self.lores
+    }
+
+    pub fn is_hires(&self) -> bool {
+        self.resolution == Resolution::R128x64
+    }
+
+    // Switch resolution, clearing every plane. Any but 128x64 also becomes
+    // the low resolution.
+    pub fn set_resolution(&mut self, resolution: Resolution) {
+        self.resolution = resolution;
+        if resolution != Resolution::R128x64 {
+            self.lores = resolution;
+        }
+        let size = self.width() * self.height();
+        self.colors = vec![0; size];
+        self.pixels = vec![false; size];
+    }
+
+    // 00FE - LOW, 00FF - HIGH
+    // Switch between the low resolution and 128x64, clearing every plane.
+    pub fn set_hires(&mut self, hires: bool) {
+        self.set_resolution(if hires {
+            Resolution::R128x64
+        } else {
+            self.lores
+        });
+    }
+
+    // Fn01 - PLANE n
+    // Select the planes to act on, bit 0 for the first plane.
+    pub fn select_planes(&mut self, planes: u8) {
+        self.planes = planes & ALL_PLANES;
+    }
+
+    pub fn planes(&self) -> u8 {
+        self.planes
+    }
+
+    // 00E0 - CLS
+    // Clear the selected planes.
+    pub fn clear(&mut self) {
+        let keep = !self.planes;
+        for (color, lit) in self.colors.iter_mut().zip(&mut self.pixels) {
+            *color &= keep;
+            *lit = *color != 0;
+        }
+    }
+
+    // XOR a sprite, one byte per row, onto the selected planes at (x, y).
+    // Returns whether any lit pixel was switched off.
+    pub fn draw_sprite(&mut self, x: usize, y: usize, sprite: &[u8]) -> bool {
+        self.draw_planes(sprite, 1, |display, plane, sprite| {
+            let rows = sprite.iter().map(|&byte| (byte as u16) << 8);
+            display.draw_rows(plane, x, y, rows)
+        })
+    }
+
+    // XOR a 16 pixel wide sprite, as drawn by Dxy0, two bytes per row.
+    pub fn draw_sprite_16(&mut self, x: usize, y: usize, sprite: &[u8]) -> bool {
+        self.draw_planes(sprite, 2, |display, plane, sprite| {
+            let rows = sprite
+                .chunks(2)
+                .map(|row| u16::from_be_bytes([row[0], row.get(1).copied().unwrap_or(0)]));
+            display.draw_rows(plane, x, y, rows)
+        })
+    }
+
+    // Split a sprite into equal parts of whole rows, one per selected plane,
+    // and draw each part on its plane.
+    fn draw_planes(
+        &mut self,
+        sprite: &[u8],
+        row_size: usize,
+        mut draw: impl FnMut(&mut Self, u8, &[u8]) -> bool,
+    ) -> bool {
+        let planes: Vec<u8> = [1, 2]
+            .into_iter()
+            .filter(|plane| self.planes & plane != 0)
+            .collect();
+        if planes.is_empty() || sprite.is_empty() {
+            return false;
+        }
+        let rows = sprite.len().div_ceil(row_size).div_ceil(planes.len());
+        let mut collision = false;
+        for (plane, part) in planes.into_iter().zip(sprite.chunks(rows * row_size)) {
+            collision |= draw(self, plane, part);
+        }
+        collision
+    }
+
+    // XOR rows of up to 16 pixels, the leftmost in the highest bit, onto
+    // one plane.
+    fn draw_rows(
+        &mut self,
+        plane: u8,
+        x: usize,
+        y: usize,
+        rows: impl Iterator<Item = u16>,
+    ) -> bool {
+        let (width, height) = (self.width(), self.height());
+        let mut collision = false;
+        for (row, bits) in rows.enumerate() {
+            for column in 0..16 {
+                if bits & (0x8000 >> column) == 0 {
+                    continue;
+                }
+                let index = (y + row) % height * width + (x + column) % width;
+                collision |= self.colors[index] & plane != 0;
+                self.colors[index] ^= plane;
+                self.pixels[index] = self.colors[index] != 0;
+            }
+        }
+        collision
+    }
+
+    // 00Cn - SCD n, 00Dn - SCU n, 00FB - SCR, 00FC - SCL
+    // Move the selected planes `dx` pixels right and `dy` pixels down,
+    // negative values moving them left and up.
+    pub fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = (self.width() as isize, self.height() as isize);
+        let planes = self.planes;
+        let old = self.colors.clone();
+        for y in 0..height {
+            for x in 0..width {
+                let (from_x, from_y) = (x - dx, y - dy);
+                let moved = if (0..width).contains(&from_x) && (0..height).contains(&from_y) {
+                    old[(from_y * width + from_x) as usize] & planes
+                } else {
+                    0
+                };
+                let index = (y * width + x) as usize;
+                self.colors[index] = self.colors[index] & !planes | moved;
+                self.pixels[index] = self.colors[index] != 0;
+            }
+        }
+    }
+
+    // Whether the pixel is lit on any plane.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.pixels[y % self.height() * self.width() + x % self.width()]
+    }
+
+    // Pixels lit on any plane in row-major order, `width()` by `height()`.
+    pub fn pixels(&self) -> &[bool] {
+        &self.pixels
+    }
+
+    // Colors from 0 to 3 in row-major order, bit 0 for the first plane.
+    pub fn colors(&self) -> &[u8] {
+        &self.colors
+    }
+
+    // The pixels of one plane, 1 for the first and 2 for the second.
+    pub fn plane(&self, plane: u8) -> Vec<bool> {
+        self.colors
+            .iter()
+            .map(|&color| color & plane != 0)
+            .collect()
+    }
+
+    // Replace the whole screen, e.g. when restoring a save state, from the
+    // pixels of the first and the second plane. Missing pixels are cleared.
+    pub fn restore(&mut self, first: &[bool], second: &[bool]) {
+        for (i, color) in self.colors.iter_mut().enumerate() {
+            let lit = |plane: &[bool]| plane.get(i).copied().unwrap_or(false) as u8;
+            *color = lit(first) | lit(second) << 1;
+            self.pixels[i] = *color != 0;
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for Display {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Pack up to 8 pixels into a byte, the leftmost in the highest bit.
+#[cfg(feature = "std")]
+pub(crate) fn pack(pixels: &[bool]) -> u8 {
+    pixels
+        .iter()
+        .enumerate()
+        .fold(0, |byte, (i, &on)| byte | (on as u8) << (7 - i))
+}
@@ -0,0 +1,170 @@
+use core::fmt;
+
+/// # Faults
+///
+/// A misbehaving ROM may point I or PC outside of the program memory,
+/// overwrite the write-protected interpreter area, call subroutines deeper
+/// than the stack allows, return with an empty stack or execute opcodes
+/// which do not exist. Rather than bringing down the host
+/// application, the machine records such behavior as a `Fault` (the
+/// `Chip8Error` and where it happened), returns it from `Chip8::step` and
+/// `Chip8::execute`, counts it in the telemetry and then
+/// follows the `FaultPolicy` for that kind of `Check`: either carry on like
+/// lenient interpreters do (invalid reads return nothing, invalid writes and
+/// unknown opcodes are ignored, a call with a full stack or a return with
+/// an empty one is skipped and the stack stays as it was), or halt until
+/// the host calls `Chip8::resume`.
+///
+/// The policies come from a global `EmulationMode`, strict (halt on
+/// anything out of spec) or permissive (always continue), with per-check
+/// overrides on top, see `Checks`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fault {
+    pub kind: Chip8Error,
+
+    // Program counter when the fault happened, already past the faulting
+    // instruction unless the fault is a failed fetch
+    pub program_counter: u16,
+}
+
+/// Everything that can go wrong while running a program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Error {
+    InvalidOpcode(u16),
+    MemoryOutOfBounds { access: Access, address: usize },
+
+    // A write to the interpreter area while it is write-protected
+    WriteProtected(usize),
+    StackOverflow,
+    StackUnderflow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Fetch,
+}
+
+/// The classes of out-of-spec behavior which can be configured separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    Memory,
+    Stack,
+    Opcode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FaultPolicy {
+    #[default]
+    Continue,
+    Halt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmulationMode {
+    Strict,
+    #[default]
+    Permissive,
+}
+
+/// The policy for every check: the mode's default unless overridden.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Checks {
+    pub mode: EmulationMode,
+    overrides: [Option<FaultPolicy>; 3],
+}
+
+impl Chip8Error {
+    pub fn check(&self) -> Check {
+        match self {
+            Chip8Error::InvalidOpcode(_) => Check::Opcode,
+            Chip8Error::MemoryOutOfBounds { .. } | Chip8Error::WriteProtected(_) => Check::Memory,
+            Chip8Error::StackOverflow | Chip8Error::StackUnderflow => Check::Stack,
+        }
+    }
+}
+
+impl Checks {
+    pub fn new(mode: EmulationMode) -> Checks {
+        Checks {
+            mode,
+            overrides: [None; 3],
+        }
+    }
+
+    // Use `policy` for `check` regardless of the mode.
+    pub fn set(&mut self, check: Check, policy: FaultPolicy) {
+        self.overrides[check as usize] = Some(policy);
+    }
+
+    // Go back to the mode's default for `check`.
+    pub fn reset(&mut self, check: Check) {
+        self.overrides[check as usize] = None;
+    }
+
+    pub fn policy(&self, check: Check) -> FaultPolicy {
+        self.overrides[check as usize].unwrap_or(match self.mode {
+            EmulationMode::Strict => FaultPolicy::Halt,
+            EmulationMode::Permissive => FaultPolicy::Continue,
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::str::FromStr for Check {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "memory" => Ok(Check::Memory),
+            "stack" => Ok(Check::Stack),
+            "opcode" => Ok(Check::Opcode),
+            _ => Err(format!("unknown check `{}`", s)),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::str::FromStr for FaultPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "continue" => Ok(FaultPolicy::Continue),
+            "halt" => Ok(FaultPolicy::Halt),
+            _ => Err(format!("unknown fault policy `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Chip8Error::InvalidOpcode(opcode) => write!(f, "unknown opcode 0x{:04X}", opcode),
+            Chip8Error::MemoryOutOfBounds { access, address } => {
+                let access = match access {
+                    Access::Read => "read from",
+                    Access::Write => "write to",
+                    Access::Fetch => "fetch from",
+                };
+                write!(f, "invalid {} 0x{:04X}", access, address)
+            }
+            Chip8Error::WriteProtected(address) => {
+                write!(f, "write to protected 0x{:04X}", address)
+            }
+            Chip8Error::StackOverflow => write!(f, "stack overflow"),
+            Chip8Error::StackUnderflow => write!(f, "stack underflow"),
+        }
+    }
+}
+
+impl core::error::Error for Chip8Error {}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (PC 0x{:03X})", self.kind, self.program_counter)
+    }
+}
+
+impl core::error::Error for Fault {}
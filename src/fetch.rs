@@ -0,0 +1,170 @@
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::{env, fmt, fs};
+
+use crate::rominfo;
+
+/// # ROM Fetching
+///
+/// ROMs can be run straight from a URL, which is handy for trying game jam
+/// entries. Downloads are cached under `$XDG_CACHE_HOME/chip8-rs` (or
+/// `~/.cache/chip8-rs`) in a file named after the hash of the URL, so a ROM is
+/// only downloaded once. The SHA-1 of the ROM is kept next to it, and a
+/// cached ROM which no longer matches it, e.g. after an interrupted write, is
+/// downloaded again.
+///
+/// Only http and https URLs are fetched, and downloads which are empty or
+/// larger than any program are refused rather than cut short. ROM databases
+/// are fetched the same way, with `fetch_rom_db`.
+pub fn fetch_rom(url: &str) -> Result<Vec<u8>, FetchError> {
+    fetch(url, MAX_ROM_SIZE)
+}
+
+// Download a ROM database such as the community one, see `RomDatabase`,
+// cached the same way as ROMs.
+pub fn fetch_rom_db(url: &str) -> Result<Vec<u8>, FetchError> {
+    fetch(url, MAX_DATABASE_SIZE)
+}
+
+fn fetch(url: &str, limit: u64) -> Result<Vec<u8>, FetchError> {
+    check_url(url)?;
+    let cache = RomCache::open()?;
+    if let Some(bytes) = cache.get(url) {
+        return Ok(bytes);
+    }
+
+    let response = ureq::get(url)
+        .call()
+        .map_err(|e| FetchError::Http(e.to_string()))?;
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(limit + 1)
+        .read_to_end(&mut bytes)?;
+    check_size(&bytes, limit)?;
+    cache.put(url, &bytes)?;
+    Ok(bytes)
+}
+
+// Check that the URL is one to download from.
+pub fn check_url(url: &str) -> Result<(), FetchError> {
+    let host = url
+        .strip_prefix("http://")
+        .or_else(|| url.strip_prefix("https://"))
+        .map(|rest| rest.split(['/', '?', '#']).next().unwrap_or(""));
+    match host {
+        Some(host) if !host.is_empty() => Ok(()),
+        _ => Err(FetchError::InvalidUrl(url.to_string())),
+    }
+}
+
+// Check that a download can be a ROM. Downloads are read up to a byte past
+// the limit, so that larger ones show.
+pub fn check_download(rom: &[u8]) -> Result<(), FetchError> {
+    check_size(rom, MAX_ROM_SIZE)
+}
+
+fn check_size(bytes: &[u8], limit: u64) -> Result<(), FetchError> {
+    if bytes.is_empty() {
+        return Err(FetchError::Empty);
+    }
+    if bytes.len() as u64 > limit {
+        return Err(FetchError::TooLarge(limit));
+    }
+    Ok(())
+}
+
+/// ROMs downloaded before, by URL.
+#[derive(Debug, Clone)]
+pub struct RomCache {
+    dir: PathBuf,
+}
+
+impl RomCache {
+    // The cache in the user's cache directory.
+    pub fn open() -> Result<RomCache, FetchError> {
+        Ok(RomCache::new(cache_dir()?))
+    }
+
+    pub fn new(dir: impl Into<PathBuf>) -> RomCache {
+        RomCache { dir: dir.into() }
+    }
+
+    // Where the ROM downloaded from `url` is kept. Its SHA-1 is kept next
+    // to it, with a `.sha1` extension.
+    pub fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.ch8", fnv1a(url.as_bytes())))
+    }
+
+    // The ROM downloaded from `url`, unless there is none or it does not
+    // match its hash.
+    pub fn get(&self, url: &str) -> Option<Vec<u8>> {
+        let path = self.path(url);
+        let rom = fs::read(&path).ok()?;
+        let hash = fs::read_to_string(path.with_extension("sha1")).ok()?;
+        (hash.trim() == rominfo::sha1_hex(&rom)).then_some(rom)
+    }
+
+    // Keep the ROM downloaded from `url`, replacing any kept before.
+    pub fn put(&self, url: &str, rom: &[u8]) -> io::Result<()> {
+        let path = self.path(url);
+        fs::create_dir_all(&self.dir)?;
+        fs::write(&path, rom)?;
+        fs::write(path.with_extension("sha1"), rominfo::sha1_hex(rom))
+    }
+}
+
+#[derive(Debug)]
+pub enum FetchError {
+    InvalidUrl(String),
+    Http(String),
+    Empty,
+
+    // Past the limit for the kind of download, in bytes
+    TooLarge(u64),
+    Io(io::Error),
+    NoCacheDir,
+}
+
+// Upper bound for downloads, large enough for a 64KB XO-CHIP program
+// wrapped in a container.
+pub const MAX_ROM_SIZE: u64 = 0x20000;
+
+// Upper bound for ROM database downloads, several times the size of the
+// community database.
+pub const MAX_DATABASE_SIZE: u64 = 0x1000000;
+
+fn cache_dir() -> Result<PathBuf, FetchError> {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .ok_or(FetchError::NoCacheDir)?;
+    Ok(base.join("chip8-rs"))
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+impl From<io::Error> for FetchError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidUrl(url) => write!(f, "not an http or https URL: {}", url),
+            Self::Http(e) => write!(f, "download failed: {}", e),
+            Self::Empty => write!(f, "the download is empty"),
+            Self::TooLarge(limit) => write!(f, "the download is larger than {} bytes", limit),
+            Self::Io(e) => write!(f, "cache error: {}", e),
+            Self::NoCacheDir => write!(f, "no cache directory, set XDG_CACHE_HOME or HOME"),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
@@ -0,0 +1,141 @@
+/// # Flicker Limiter
+///
+/// An accessibility stage for the display pipeline. CHIP-8 has no double
+/// buffering and draws by XOR, so games erase and redraw sprites every frame
+/// and some invert the whole screen; shown as is, that strobes, which can
+/// trigger photosensitive seizures. The limiter turns the machine's frames
+/// into what is presented with two measures:
+///
+/// - Persistence: a pixel stays lit for a few frames after being switched
+///   off, which hides the erase half of erase-redraw flicker.
+/// - Flash guard: a change of the mean screen luminance of at least
+///   `flash_threshold` counts as a flash, and a flash reversing the previous
+///   one within `min_flash_interval` frames is held back, keeping the
+///   previous picture. At 60Hz, the default interval allows at most three
+///   flashes per second, the WCAG guideline.
+///
+/// Frames are pixels in row-major order, presented as intensities from 0 to
+/// 255. Frontends should keep the limiter on by default and offer it as an
+/// accessibility setting.
+#[derive(Debug, Clone)]
+pub struct FlickerLimiter {
+    config: FlickerConfig,
+
+    // Presented intensities
+    shown: Vec<u8>,
+
+    // Frames since each pixel was last lit, saturating
+    unlit_for: Vec<u8>,
+
+    // Frame count, and the frame and direction of the last flash
+    frame: u64,
+    last_flash: Option<(u64, bool)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlickerConfig {
+    pub enabled: bool,
+
+    // Frames a pixel stays lit after being switched off
+    pub persistence: u8,
+
+    // Change of the mean luminance, as a fraction of full brightness, which
+    // counts as a flash
+    pub flash_threshold: f32,
+
+    // Fewest frames between a flash and one in the opposite direction
+    pub min_flash_interval: u64,
+}
+
+impl Default for FlickerConfig {
+    fn default() -> Self {
+        FlickerConfig {
+            enabled: true,
+            persistence: 2,
+            flash_threshold: 0.1,
+            min_flash_interval: 20,
+        }
+    }
+}
+
+impl FlickerLimiter {
+    pub fn new(config: FlickerConfig) -> FlickerLimiter {
+        FlickerLimiter {
+            config,
+            shown: Vec::new(),
+            unlit_for: Vec::new(),
+            frame: 0,
+            last_flash: None,
+        }
+    }
+
+    pub fn config(&self) -> FlickerConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: FlickerConfig) {
+        self.config = config;
+    }
+
+    // Take the machine's next frame and return the one to present. A frame
+    // of another size than the previous one starts over, e.g. on a switch
+    // to high resolution.
+    pub fn present(&mut self, pixels: &[bool]) -> &[u8] {
+        self.frame += 1;
+        if self.shown.len() != pixels.len() {
+            self.shown = pixels.iter().map(|&on| intensity(on)).collect();
+            self.unlit_for = pixels
+                .iter()
+                .map(|&on| if on { 0 } else { u8::MAX })
+                .collect();
+            self.last_flash = None;
+            return &self.shown;
+        }
+        if !self.config.enabled {
+            for (shown, &on) in self.shown.iter_mut().zip(pixels) {
+                *shown = intensity(on);
+            }
+            return &self.shown;
+        }
+
+        for (unlit_for, &on) in self.unlit_for.iter_mut().zip(pixels) {
+            *unlit_for = if on { 0 } else { unlit_for.saturating_add(1) };
+        }
+        let persistence = self.config.persistence;
+        let candidate: Vec<u8> = self
+            .unlit_for
+            .iter()
+            .map(|&unlit_for| intensity(unlit_for <= persistence))
+            .collect();
+
+        let change = mean(&candidate) - mean(&self.shown);
+        if change.abs() >= self.config.flash_threshold {
+            let brighter = change > 0.0;
+            let reverses = self.last_flash.is_some_and(|(frame, direction)| {
+                direction != brighter && self.frame - frame < self.config.min_flash_interval
+            });
+            if reverses {
+                return &self.shown;
+            }
+            self.last_flash = Some((self.frame, brighter));
+        }
+        self.shown = candidate;
+        &self.shown
+    }
+}
+
+fn intensity(on: bool) -> u8 {
+    if on {
+        u8::MAX
+    } else {
+        0
+    }
+}
+
+// Mean luminance as a fraction of full brightness.
+fn mean(frame: &[u8]) -> f32 {
+    if frame.is_empty() {
+        return 0.0;
+    }
+    frame.iter().map(|&v| v as f32).sum::<f32>() / (frame.len() as f32 * 255.0)
+}
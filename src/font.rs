@@ -0,0 +1,203 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::str::FromStr;
+
+/// # Font Sets
+///
+/// Every interpreter kept a 4x5 sprite for each hexadecimal digit in its
+/// own memory, for Fx29 to point I at, and SUPER-CHIP added 8x10 digits
+/// for Fx30. The shapes were never specified, so each machine drew them its
+/// own way, and ROMs which print scores or build their graphics out of
+/// digits look different on each of them. The font set picks whose digits
+/// the interpreter area holds:
+///
+/// - `Modern`, the default: the digits most interpreters use today, with
+///   the 8x10 font extended to A through F as in Octo
+/// - `CosmacVip`: the original COSMAC VIP interpreter's digits
+/// - `Eti660`: the narrower digits of the ETI 660
+/// - `Dream6800`: the DREAM 6800's, three pixels wide
+/// - `SuperChip`: the SUPER-CHIP 1.1 8x10 digits, which are rounder than
+///   the modern ones; SUPER-CHIP only had big digits for 0 to 9, so A to F
+///   keep their modern shape
+///
+/// Only `SuperChip` differs from `Modern` in its 8x10 digits, the others
+/// change the 4x5 digits alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FontSet {
+    #[default]
+    Modern,
+    CosmacVip,
+    Eti660,
+    Dream6800,
+    SuperChip,
+}
+
+impl FontSet {
+    pub const ALL: [FontSet; 5] = [
+        FontSet::Modern,
+        FontSet::CosmacVip,
+        FontSet::Eti660,
+        FontSet::Dream6800,
+        FontSet::SuperChip,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FontSet::Modern => "modern",
+            FontSet::CosmacVip => "vip",
+            FontSet::Eti660 => "eti660",
+            FontSet::Dream6800 => "dream6800",
+            FontSet::SuperChip => "schip",
+        }
+    }
+
+    // The 4x5 digits 0 through F, drawn with Fx29.
+    pub fn small(self) -> &'static [[u8; 5]; 16] {
+        match self {
+            FontSet::Modern | FontSet::SuperChip => &MODERN,
+            FontSet::CosmacVip => &COSMAC_VIP,
+            FontSet::Eti660 => &ETI_660,
+            FontSet::Dream6800 => &DREAM_6800,
+        }
+    }
+
+    // The 8x10 digits 0 through F, drawn with Fx30.
+    pub fn big(self) -> &'static [[u8; 10]; 16] {
+        match self {
+            FontSet::SuperChip => &SUPER_CHIP_BIG,
+            _ => &MODERN_BIG,
+        }
+    }
+}
+
+impl fmt::Display for FontSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for FontSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FontSet::ALL
+            .into_iter()
+            .find(|font| font.name() == s)
+            .ok_or_else(|| format!("unknown font set `{}`", s))
+    }
+}
+
+const MODERN: [[u8; 5]; 16] = [
+    [0xF0, 0x90, 0x90, 0x90, 0xF0], // 0
+    [0x20, 0x60, 0x20, 0x20, 0x70], // 1
+    [0xF0, 0x10, 0xF0, 0x80, 0xF0], // 2
+    [0xF0, 0x10, 0xF0, 0x10, 0xF0], // 3
+    [0x90, 0x90, 0xF0, 0x10, 0x10], // 4
+    [0xF0, 0x80, 0xF0, 0x10, 0xF0], // 5
+    [0xF0, 0x80, 0xF0, 0x90, 0xF0], // 6
+    [0xF0, 0x10, 0x20, 0x40, 0x40], // 7
+    [0xF0, 0x90, 0xF0, 0x90, 0xF0], // 8
+    [0xF0, 0x90, 0xF0, 0x10, 0xF0], // 9
+    [0xF0, 0x90, 0xF0, 0x90, 0x90], // A
+    [0xE0, 0x90, 0xE0, 0x90, 0xE0], // B
+    [0xF0, 0x80, 0x80, 0x80, 0xF0], // C
+    [0xE0, 0x90, 0x90, 0x90, 0xE0], // D
+    [0xF0, 0x80, 0xF0, 0x80, 0xF0], // E
+    [0xF0, 0x80, 0xF0, 0x80, 0x80], // F
+];
+
+const COSMAC_VIP: [[u8; 5]; 16] = [
+    [0xF0, 0x90, 0x90, 0x90, 0xF0], // 0
+    [0x60, 0x20, 0x20, 0x20, 0x70], // 1
+    [0xF0, 0x10, 0xF0, 0x80, 0xF0], // 2
+    [0xF0, 0x10, 0xF0, 0x10, 0xF0], // 3
+    [0xA0, 0xA0, 0xF0, 0x20, 0x20], // 4
+    [0xF0, 0x80, 0xF0, 0x10, 0xF0], // 5
+    [0xF0, 0x80, 0xF0, 0x90, 0xF0], // 6
+    [0xF0, 0x10, 0x10, 0x10, 0x10], // 7
+    [0xF0, 0x90, 0xF0, 0x90, 0xF0], // 8
+    [0xF0, 0x90, 0xF0, 0x10, 0xF0], // 9
+    [0xF0, 0x90, 0xF0, 0x90, 0x90], // A
+    [0xF0, 0x50, 0x70, 0x50, 0xF0], // B
+    [0xF0, 0x80, 0x80, 0x80, 0xF0], // C
+    [0xF0, 0x50, 0x50, 0x50, 0xF0], // D
+    [0xF0, 0x80, 0xF0, 0x80, 0xF0], // E
+    [0xF0, 0x80, 0xF0, 0x80, 0x80], // F
+];
+
+const ETI_660: [[u8; 5]; 16] = [
+    [0xE0, 0xA0, 0xA0, 0xA0, 0xE0], // 0
+    [0x20, 0x20, 0x20, 0x20, 0x20], // 1
+    [0xE0, 0x20, 0xE0, 0x80, 0xE0], // 2
+    [0xE0, 0x20, 0xE0, 0x20, 0xE0], // 3
+    [0xA0, 0xA0, 0xE0, 0x20, 0x20], // 4
+    [0xE0, 0x80, 0xE0, 0x20, 0xE0], // 5
+    [0xE0, 0x80, 0xE0, 0xA0, 0xE0], // 6
+    [0xE0, 0x20, 0x20, 0x20, 0x20], // 7
+    [0xE0, 0xA0, 0xE0, 0xA0, 0xE0], // 8
+    [0xE0, 0xA0, 0xE0, 0x20, 0xE0], // 9
+    [0xE0, 0xA0, 0xE0, 0xA0, 0xA0], // A
+    [0x80, 0x80, 0xE0, 0xA0, 0xE0], // B
+    [0xE0, 0x80, 0x80, 0x80, 0xE0], // C
+    [0x20, 0x20, 0xE0, 0xA0, 0xE0], // D
+    [0xE0, 0x80, 0xE0, 0x80, 0xE0], // E
+    [0xE0, 0x80, 0xC0, 0x80, 0x80], // F
+];
+
+const DREAM_6800: [[u8; 5]; 16] = [
+    [0xE0, 0xA0, 0xA0, 0xA0, 0xE0], // 0
+    [0x40, 0x40, 0x40, 0x40, 0x40], // 1
+    [0xE0, 0x20, 0xE0, 0x80, 0xE0], // 2
+    [0xE0, 0x20, 0xE0, 0x20, 0xE0], // 3
+    [0x80, 0xA0, 0xA0, 0xE0, 0x20], // 4
+    [0xE0, 0x80, 0xE0, 0x20, 0xE0], // 5
+    [0xE0, 0x80, 0xE0, 0xA0, 0xE0], // 6
+    [0xE0, 0x20, 0x20, 0x20, 0x20], // 7
+    [0xE0, 0xA0, 0xE0, 0xA0, 0xE0], // 8
+    [0xE0, 0xA0, 0xE0, 0x20, 0xE0], // 9
+    [0xE0, 0xA0, 0xE0, 0xA0, 0xA0], // A
+    [0xC0, 0xA0, 0xE0, 0xA0, 0xC0], // B
+    [0xE0, 0x80, 0x80, 0x80, 0xE0], // C
+    [0xC0, 0xA0, 0xA0, 0xA0, 0xC0], // D
+    [0xE0, 0x80, 0xE0, 0x80, 0xE0], // E
+    [0xE0, 0x80, 0xC0, 0x80, 0x80], // F
+];
+
+const MODERN_BIG: [[u8; 10]; 16] = [
+    [0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF], // 0
+    [0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF], // 1
+    [0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF], // 2
+    [0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF], // 3
+    [0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03], // 4
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF], // 5
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF], // 6
+    [0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18], // 7
+    [0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF], // 8
+    [0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF], // 9
+    [0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3], // A
+    [0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC], // B
+    [0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C], // C
+    [0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC], // D
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF], // E
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0], // F
+];
+
+const SUPER_CHIP_BIG: [[u8; 10]; 16] = [
+    [0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C], // 0
+    [0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C], // 1
+    [0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF], // 2
+    [0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C], // 3
+    [0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06], // 4
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C], // 5
+    [0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C], // 6
+    [0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60], // 7
+    [0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C], // 8
+    [0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C], // 9
+    [0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3], // A
+    [0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC], // B
+    [0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C], // C
+    [0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC], // D
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF], // E
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0], // F
+];
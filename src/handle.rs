@@ -0,0 +1,312 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::audio::Voice;
+use crate::cpu::Chip8;
+use crate::display::Display;
+use crate::keyboard::KeypadView;
+use crate::pacing::{FramePacer, Speed};
+
+/// # Emulator Handle
+///
+/// Runs a `Chip8` on a worker thread of its own, for hosts which must not
+/// block: GUI event loops such as winit and egui, or async runtimes. The
+/// host sends `Command`s and receives `Event`s over channels, and neither
+/// side ever waits for the other. The core does not block either: a
+/// program waiting for a key in Fx0A leaves the machine parked in
+/// `StepResult::WaitingForKey`, and it runs on once a key event arrives.
+///
+/// The worker paces frames at `frame_rate` with a `FramePacer`, and sends
+/// `Event::Frame` with a copy of the screen whenever frames changed it, for
+/// the host to draw (e.g. with `Palette::rgba` or a `Phosphor`) when it
+/// next repaints. Hosts which sleep until something happens pass a waker to
+/// `spawn_with_waker`, such as egui's `request_repaint`, which the worker
+/// calls after sending events. Async hosts poll `try_event` from a timer.
+/// `Event::Beep` and `Event::Voice` say when and what the buzzer plays, for
+/// the host to render with its own `Mixer`, and `Event::Keypad` which keys
+/// are held and whether the program waits for one, for a keypad widget.
+///
+/// `Chip8` holds boxed backends and sinks which stay on one thread, so the
+/// machine is built on the worker by the closure given to `spawn`.
+/// Dropping the handle stops the worker. See `examples/egui.rs` for a
+/// frontend built on it.
+pub struct EmulatorHandle {
+    // None once the handle is being dropped, which stops the worker
+    commands: Option<Sender<Command>>,
+    events: Receiver<Event>,
+    worker: Option<JoinHandle<()>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HandleConfig {
+    pub cycles_per_frame: usize,
+
+    // Timer and frame rate, in Hz
+    pub frame_rate: u32,
+}
+
+impl Default for HandleConfig {
+    fn default() -> Self {
+        HandleConfig {
+            cycles_per_frame: 12,
+            frame_rate: 60,
+        }
+    }
+}
+
+/// What the host asks of the worker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    // Reset the machine and load a ROM into it, see `Chip8::reset`
+    Load(Vec<u8>),
+
+    // Reset the machine, keeping the ROM
+    Reset,
+
+    Pause,
+    Resume,
+    SetSpeed(Speed),
+
+    // Press (true) or release a keypad key
+    Key(u8, bool),
+
+    // Write bytes into memory from an address on, as cheats do, e.g. a
+    // sprite being edited for a live preview. Bytes past the end of memory
+    // are dropped.
+    Poke(u16, Vec<u8>),
+}
+
+/// What the worker tells the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    // Frames changed the screen; `frame` counts the frames run so far
+    Frame { frame: u64, display: Display },
+
+    // The buzzer started (true) or stopped
+    Beep(bool),
+
+    // The buzzer plays something else, e.g. the program loaded an XO-CHIP
+    // audio pattern or changed its pitch
+    Voice(Voice),
+
+    // Keys were pressed or released, or the program started or stopped
+    // waiting for one in Fx0A
+    Keypad(KeypadView),
+
+    // The program faulted, or a ROM could not be loaded
+    Error(String),
+
+    // The program exited with 00FD
+    Exited,
+}
+
+// How long the worker waits for commands between checks for due frames.
+const TICK: Duration = Duration::from_millis(1);
+
+impl EmulatorHandle {
+    // Start a worker running the machine `boot` builds.
+    pub fn spawn(
+        config: HandleConfig,
+        boot: impl FnOnce() -> Chip8 + Send + 'static,
+    ) -> EmulatorHandle {
+        EmulatorHandle::spawn_with_waker(config, boot, || {})
+    }
+
+    // Start a worker as for `spawn`, calling `wake` whenever it sent events.
+    pub fn spawn_with_waker(
+        config: HandleConfig,
+        boot: impl FnOnce() -> Chip8 + Send + 'static,
+        wake: impl Fn() + Send + 'static,
+    ) -> EmulatorHandle {
+        let (commands, inbox) = mpsc::channel();
+        let (outbox, events) = mpsc::channel();
+        let worker = thread::spawn(move || {
+            let mut worker = Worker {
+                chip8: boot(),
+                config,
+                events: outbox,
+                frame: 0,
+                shown: None,
+                keypad: KeypadView::new([false; 16], false),
+                sounding: false,
+                voice: Voice::Beep,
+                exited: false,
+            };
+            worker.run(inbox, wake);
+        });
+        EmulatorHandle {
+            commands: Some(commands),
+            events,
+            worker: Some(worker),
+        }
+    }
+
+    // Commands sent after the worker stopped, e.g. by a panic, are dropped.
+    pub fn send(&self, command: Command) {
+        if let Some(commands) = &self.commands {
+            let _ = commands.send(command);
+        }
+    }
+
+    // The next event, if one is waiting.
+    pub fn try_event(&self) -> Option<Event> {
+        self.events.try_recv().ok()
+    }
+
+    // The next event, waiting at most `timeout` for it.
+    pub fn wait_event(&self, timeout: Duration) -> Option<Event> {
+        self.events.recv_timeout(timeout).ok()
+    }
+
+    // Every event waiting, without blocking.
+    pub fn events(&self) -> impl Iterator<Item = Event> + '_ {
+        self.events.try_iter()
+    }
+}
+
+impl Drop for EmulatorHandle {
+    fn drop(&mut self) {
+        self.commands = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+// The machine on the worker thread, and what the host last heard of it.
+struct Worker {
+    chip8: Chip8,
+    config: HandleConfig,
+    events: Sender<Event>,
+
+    // Frames run, and the screen and keypad last sent
+    frame: u64,
+    shown: Option<Display>,
+    keypad: KeypadView,
+
+    sounding: bool,
+    voice: Voice,
+    exited: bool,
+}
+
+impl Worker {
+    // Run until the handle is dropped.
+    fn run(&mut self, inbox: Receiver<Command>, wake: impl Fn()) {
+        let mut pacer = FramePacer::new(self.config.frame_rate);
+        let mut last = Instant::now();
+        loop {
+            let mut changed = false;
+            match inbox.recv_timeout(TICK) {
+                Ok(command) => changed |= self.apply(command, &mut pacer),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+            while let Ok(command) = inbox.try_recv() {
+                changed |= self.apply(command, &mut pacer);
+            }
+
+            let now = Instant::now();
+            let frames = pacer.advance(now - last);
+            last = now;
+            for _ in 0..frames {
+                changed |= self.run_frame();
+            }
+            if frames > 0 && self.shown.as_ref() != Some(self.chip8.display()) {
+                let display = self.chip8.display().clone();
+                self.shown = Some(display.clone());
+                self.send(Event::Frame {
+                    frame: self.frame,
+                    display,
+                });
+                changed = true;
+            }
+            let keypad = self.chip8.keypad().view();
+            if keypad != self.keypad {
+                self.keypad = keypad;
+                self.send(Event::Keypad(keypad));
+                changed = true;
+            }
+            if changed {
+                wake();
+            }
+        }
+    }
+
+    // Act on a command, returning whether events were sent.
+    fn apply(&mut self, command: Command, pacer: &mut FramePacer) -> bool {
+        match command {
+            Command::Load(rom) => {
+                // Checked first, so a ROM which does not fit leaves the
+                // machine running.
+                if let Err(e) = self.chip8.replace_rom(&rom) {
+                    self.send(Event::Error(e.to_string()));
+                    return true;
+                }
+                return self.restart();
+            }
+            Command::Reset => return self.restart(),
+            Command::Pause => self.chip8.pause(),
+            Command::Resume => self.chip8.resume(),
+            Command::SetSpeed(speed) => pacer.set_speed(speed),
+            Command::Key(key, pressed) => self.chip8.set_key(key, pressed),
+            Command::Poke(address, bytes) => {
+                for (i, byte) in bytes.into_iter().enumerate() {
+                    self.chip8.poke(address as usize + i, byte);
+                }
+            }
+        }
+        false
+    }
+
+    // Reset the machine, returning whether events were sent: resets drop
+    // a ROM which no longer fits the machine, with a fault.
+    fn restart(&mut self) -> bool {
+        self.chip8.reset(true);
+        self.exited = false;
+        // The next frame is sent whatever it shows.
+        self.shown = None;
+        match self.chip8.fault() {
+            Some(fault) => {
+                self.send(Event::Error(fault.to_string()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Run a frame, returning whether events were sent.
+    fn run_frame(&mut self) -> bool {
+        let errors = self.chip8.telemetry().errors;
+        self.chip8.run_frame(self.config.cycles_per_frame);
+        self.frame += 1;
+        let mut sent = false;
+        if self.chip8.sound_active() != self.sounding {
+            self.sounding = !self.sounding;
+            self.send(Event::Beep(self.sounding));
+            sent = true;
+        }
+        if self.chip8.voice() != self.voice {
+            self.voice = self.chip8.voice();
+            self.send(Event::Voice(self.voice));
+            sent = true;
+        }
+        if self.chip8.telemetry().errors > errors {
+            if let Some(fault) = self.chip8.fault() {
+                self.send(Event::Error(fault.to_string()));
+                sent = true;
+            }
+        }
+        if self.chip8.is_halted() && self.chip8.fault().is_none() && !self.exited {
+            self.exited = true;
+            self.send(Event::Exited);
+            sent = true;
+        }
+        sent
+    }
+
+    // Events the host no longer listens for are dropped.
+    fn send(&self, event: Event) {
+        let _ = self.events.send(event);
+    }
+}
@@ -0,0 +1,119 @@
+/// # Memory Access Heatmap
+///
+/// A shadow map counting how often every byte of the 4KB address space was
+/// read, written or executed. Since 4096 = 64 * 64, the map renders naturally
+/// as a 64x64 grid with one cell per byte, row-major from 0x000 at the top
+/// left to 0xFFF at the bottom right.
+///
+/// Each kind of access drives one color channel: red for writes, green for
+/// reads and blue for executes. Counts are scaled logarithmically against the
+/// busiest address so that rarely touched bytes still show up, and untouched
+/// bytes stay black.
+///
+/// For text, such as the `heatmap` command of the debugger, `to_text` draws
+/// one character per byte by the kinds of access instead: `x` executed, `X`
+/// executed and written (self-modifying code), `w` written, `r` only read
+/// and `.` untouched, each row led by the address it starts at.
+#[derive(Debug, Clone)]
+pub struct AccessMap {
+    reads: Vec<u32>,
+    writes: Vec<u32>,
+    executes: Vec<u32>,
+}
+
+pub const GRID_SIZE: usize = 64;
+
+const SIZE: usize = GRID_SIZE * GRID_SIZE;
+
+impl AccessMap {
+    pub fn new() -> Self {
+        Self {
+            reads: vec![0; SIZE],
+            writes: vec![0; SIZE],
+            executes: vec![0; SIZE],
+        }
+    }
+
+    pub fn record_read(&mut self, addr: usize) {
+        Self::bump(&mut self.reads, addr);
+    }
+
+    pub fn record_write(&mut self, addr: usize) {
+        Self::bump(&mut self.writes, addr);
+    }
+
+    pub fn record_execute(&mut self, addr: usize) {
+        Self::bump(&mut self.executes, addr);
+    }
+
+    pub fn reads(&self, addr: usize) -> u32 {
+        self.reads.get(addr).copied().unwrap_or(0)
+    }
+
+    pub fn writes(&self, addr: usize) -> u32 {
+        self.writes.get(addr).copied().unwrap_or(0)
+    }
+
+    pub fn executes(&self, addr: usize) -> u32 {
+        self.executes.get(addr).copied().unwrap_or(0)
+    }
+
+    // Render the map as 64x64 RGB pixels, three bytes per pixel.
+    pub fn to_rgb(&self) -> Vec<u8> {
+        let scales = [&self.writes, &self.reads, &self.executes]
+            .map(|counts| (counts.iter().copied().max().unwrap_or(0) as f64).ln_1p());
+
+        let mut rgb = Vec::with_capacity(SIZE * 3);
+        for addr in 0..SIZE {
+            let counts = [self.writes[addr], self.reads[addr], self.executes[addr]];
+            for (count, scale) in counts.into_iter().zip(scales) {
+                rgb.push(intensity(count, scale));
+            }
+        }
+        rgb
+    }
+
+    // Render the map as 64 lines of 64 characters, see above.
+    pub fn to_text(&self) -> String {
+        let mut text = String::with_capacity(GRID_SIZE * (GRID_SIZE + 7));
+        for row in 0..GRID_SIZE {
+            text.push_str(&format!("0x{:03X} ", row * GRID_SIZE));
+            for addr in row * GRID_SIZE..(row + 1) * GRID_SIZE {
+                let (read, written, executed) = (
+                    self.reads[addr] > 0,
+                    self.writes[addr] > 0,
+                    self.executes[addr] > 0,
+                );
+                text.push(match (executed, written, read) {
+                    (true, true, _) => 'X',
+                    (true, false, _) => 'x',
+                    (false, true, _) => 'w',
+                    (false, false, true) => 'r',
+                    (false, false, false) => '.',
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    fn bump(counts: &mut [u32], addr: usize) {
+        if let Some(count) = counts.get_mut(addr) {
+            *count = count.saturating_add(1);
+        }
+    }
+}
+
+// Map a count onto 64..=255, keeping zero black so any access is visible.
+fn intensity(count: u32, scale: f64) -> u8 {
+    if count == 0 || scale == 0.0 {
+        return 0;
+    }
+    (64.0 + 191.0 * (count as f64).ln_1p() / scale) as u8
+}
+
+impl Default for AccessMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
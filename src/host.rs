@@ -0,0 +1,169 @@
+use crate::audio::Voice;
+use crate::cpu::Chip8;
+use crate::display::Display;
+use crate::recording::InputRecording;
+
+/// # Host Backends
+///
+/// What the machine needs from its host, split into three traits: a
+/// `DisplayBackend` shows the screen, an `AudioBackend` follows the buzzer
+/// and an `InputBackend` provides the keypad state. `run_frame` drives a
+/// machine through them, one 60Hz frame at a time.
+///
+/// `Headless` implements all three in memory, capturing the last frame and
+/// every time the buzzer switches on or off, so tests, fuzzers and server
+/// side tools can run ROMs without any native dependency. The frontends
+/// implement the traits they can: the SDL window draws through a
+/// `DisplayBackend`, and every frontend with sound plays it through a
+/// `Speaker`. Frontends that run several frames per refresh, or handle
+/// hotkeys between them, call the backends themselves instead of going
+/// through `run_frame`.
+pub trait DisplayBackend {
+    // Show the screen as it is at the end of a frame.
+    fn present(&mut self, display: &Display);
+}
+
+pub trait AudioBackend {
+    // Follow the buzzer after a frame, sounding `voice` or silent.
+    fn play(&mut self, sounding: bool, voice: Voice);
+}
+
+pub trait InputBackend {
+    // The keypad state for the next frame, bit n set for key n held down.
+    fn keys(&mut self) -> u16;
+}
+
+// Run one frame on the machine, with the keypad set from `input`, then
+// pass the screen and the buzzer on to the other backends.
+pub fn run_frame<D, A, I>(
+    chip8: &mut Chip8,
+    cycles_per_frame: usize,
+    display: &mut D,
+    audio: &mut A,
+    input: &mut I,
+) where
+    D: DisplayBackend + ?Sized,
+    A: AudioBackend + ?Sized,
+    I: InputBackend + ?Sized,
+{
+    chip8.set_keys(input.keys());
+    chip8.run_frame(cycles_per_frame);
+    audio.play(chip8.sound_active(), chip8.voice());
+    display.present(chip8.display());
+}
+
+/// The buzzer switching on or off, at the end of the given frame (from 0).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BeepEvent {
+    pub frame: u64,
+    pub sounding: bool,
+}
+
+/// Backends keeping everything in memory. The keys follow the scripted
+/// `InputRecording` if given, and whatever `set_keys` set once it runs out.
+#[derive(Debug, Clone, Default)]
+pub struct Headless {
+    frames: u64,
+    framebuffer: Vec<bool>,
+    width: usize,
+    height: usize,
+    sounding: bool,
+    beeps: Vec<BeepEvent>,
+    script: InputRecording,
+    keys: u16,
+    polled: usize,
+}
+
+impl Headless {
+    pub fn new() -> Headless {
+        Headless::default()
+    }
+
+    // Press the keys recorded for each frame, in order.
+    pub fn with_input(script: InputRecording) -> Headless {
+        Headless {
+            script,
+            ..Headless::default()
+        }
+    }
+
+    // Hold down the keys in the mask, from the end of the script on.
+    pub fn set_keys(&mut self, mask: u16) {
+        self.keys = mask;
+    }
+
+    // Run `frames` frames through these backends, as `run_frame` would.
+    pub fn run(&mut self, chip8: &mut Chip8, frames: u64, cycles_per_frame: usize) {
+        for _ in 0..frames {
+            chip8.set_keys(self.keys());
+            chip8.run_frame(cycles_per_frame);
+            self.play(chip8.sound_active(), chip8.voice());
+            self.present(chip8.display());
+        }
+    }
+
+    // Frames presented so far.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    // The last screen presented, `width` by `height` pixels in row-major
+    // order.
+    pub fn framebuffer(&self) -> &[bool] {
+        &self.framebuffer
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        x < self.width && self.framebuffer.get(y * self.width + x) == Some(&true)
+    }
+
+    pub fn is_sounding(&self) -> bool {
+        self.sounding
+    }
+
+    pub fn beeps(&self) -> &[BeepEvent] {
+        &self.beeps
+    }
+}
+
+impl DisplayBackend for Headless {
+    fn present(&mut self, display: &Display) {
+        self.framebuffer.clear();
+        self.framebuffer.extend_from_slice(display.pixels());
+        self.width = display.width();
+        self.height = display.height();
+        self.frames += 1;
+    }
+}
+
+impl AudioBackend for Headless {
+    fn play(&mut self, sounding: bool, _voice: Voice) {
+        if sounding != self.sounding {
+            self.sounding = sounding;
+            self.beeps.push(BeepEvent {
+                frame: self.frames,
+                sounding,
+            });
+        }
+    }
+}
+
+impl InputBackend for Headless {
+    fn keys(&mut self) -> u16 {
+        let frame = self.polled;
+        self.polled += 1;
+        if frame < self.script.len() {
+            self.script.keys(frame)
+        } else {
+            self.keys
+        }
+    }
+}
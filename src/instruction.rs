@@ -0,0 +1,368 @@
+use core::fmt;
+
+/// # Instructions
+///
+/// Every opcode the machine knows, decoded from its 16-bit encoding into
+/// the operands it takes, so that the CPU, the block translator and the
+/// tools around them never pick opcodes apart with bit masks themselves.
+/// `x` and `y` name the registers Vx and Vy, `byte` an 8-bit immediate,
+/// `addr` a 12-bit address and `n` a 4-bit immediate.
+///
+/// `decode` knows the CHIP-8, SUPER-CHIP and XO-CHIP instructions alike;
+/// whether the machine executes one depends on its variant. F000 is
+/// followed by the 16-bit address it loads, which is not part of the
+/// opcode. `encode` turns an instruction back into its opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    // 0nnn - SYS addr
+    Sys { addr: u16 },
+    // 00Cn - SCD n
+    ScrollDown { n: u8 },
+    // 00Dn - SCU n
+    ScrollUp { n: u8 },
+    // 00E0 - CLS
+    Clear,
+    // 00EE - RET
+    Return,
+    // 00FB - SCR
+    ScrollRight,
+    // 00FC - SCL
+    ScrollLeft,
+    // 00FD - EXIT
+    Exit,
+    // 00FE - LOW
+    LowResolution,
+    // 00FF - HIGH
+    HighResolution,
+    // 1nnn - JP addr
+    Jump { addr: u16 },
+    // 2nnn - CALL addr
+    Call { addr: u16 },
+    // 3xkk - SE Vx, byte
+    SkipIfEqual { x: u8, byte: u8 },
+    // 4xkk - SNE Vx, byte
+    SkipIfNotEqual { x: u8, byte: u8 },
+    // 5xy0 - SE Vx, Vy
+    SkipIfRegistersEqual { x: u8, y: u8 },
+    // 5xy2 - LD [I], Vx-Vy
+    StoreRegisterRange { x: u8, y: u8 },
+    // 5xy3 - LD Vx-Vy, [I]
+    LoadRegisterRange { x: u8, y: u8 },
+    // 6xkk - LD Vx, byte
+    Load { x: u8, byte: u8 },
+    // 7xkk - ADD Vx, byte
+    AddByte { x: u8, byte: u8 },
+    // 8xy0 - LD Vx, Vy
+    Move { x: u8, y: u8 },
+    // 8xy1 - OR Vx, Vy
+    Or { x: u8, y: u8 },
+    // 8xy2 - AND Vx, Vy
+    And { x: u8, y: u8 },
+    // 8xy3 - XOR Vx, Vy
+    Xor { x: u8, y: u8 },
+    // 8xy4 - ADD Vx, Vy
+    Add { x: u8, y: u8 },
+    // 8xy5 - SUB Vx, Vy
+    Sub { x: u8, y: u8 },
+    // 8xy6 - SHR Vx {, Vy}
+    ShiftRight { x: u8, y: u8 },
+    // 8xy7 - SUBN Vx, Vy
+    SubN { x: u8, y: u8 },
+    // 8xyE - SHL Vx {, Vy}
+    ShiftLeft { x: u8, y: u8 },
+    // 9xy0 - SNE Vx, Vy
+    SkipIfRegistersNotEqual { x: u8, y: u8 },
+    // Annn - LD I, addr
+    LoadI { addr: u16 },
+    // Bnnn - JP V0, addr
+    JumpWithOffset { addr: u16 },
+    // Cxkk - RND Vx, byte
+    Random { x: u8, byte: u8 },
+    // Dxyn - DRW Vx, Vy, n
+    Draw { x: u8, y: u8, n: u8 },
+    // Ex9E - SKP Vx
+    SkipIfKeyPressed { x: u8 },
+    // ExA1 - SKNP Vx
+    SkipIfKeyNotPressed { x: u8 },
+    // F000 nnnn - LD I, long nnnn
+    LoadILong,
+    // Fn01 - PLANE n
+    SelectPlanes { n: u8 },
+    // F002 - AUDIO
+    LoadAudioPattern,
+    // Fx07 - LD Vx, DT
+    LoadDelayTimer { x: u8 },
+    // Fx0A - LD Vx, K
+    WaitForKey { x: u8 },
+    // Fx15 - LD DT, Vx
+    SetDelayTimer { x: u8 },
+    // Fx18 - LD ST, Vx
+    SetSoundTimer { x: u8 },
+    // Fx1E - ADD I, Vx
+    AddToI { x: u8 },
+    // Fx29 - LD F, Vx
+    LoadDigit { x: u8 },
+    // Fx30 - LD HF, Vx
+    LoadBigDigit { x: u8 },
+    // Fx33 - LD B, Vx
+    StoreBcd { x: u8 },
+    // Fx3A - PITCH Vx
+    SetPitch { x: u8 },
+    // Fx55 - LD [I], Vx
+    StoreRegisters { x: u8 },
+    // Fx65 - LD Vx, [I]
+    LoadRegisters { x: u8 },
+    // Fx75 - LD R, Vx
+    StoreRplFlags { x: u8 },
+    // Fx85 - LD Vx, R
+    LoadRplFlags { x: u8 },
+}
+
+/// An opcode that is no instruction of any variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeError(pub u16);
+
+pub fn decode(opcode: u16) -> Result<Instruction, DecodeError> {
+    use Instruction::*;
+
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let n = (opcode & 0x000F) as u8;
+    let byte = (opcode & 0x00FF) as u8;
+    let addr = opcode & 0x0FFF;
+    Ok(match opcode & 0xF000 {
+        0x0000 => match opcode {
+            0x00C0..=0x00CF => ScrollDown { n },
+            0x00D0..=0x00DF => ScrollUp { n },
+            0x00E0 => Clear,
+            0x00EE => Return,
+            0x00FB => ScrollRight,
+            0x00FC => ScrollLeft,
+            0x00FD => Exit,
+            0x00FE => LowResolution,
+            0x00FF => HighResolution,
+            _ => Sys { addr },
+        },
+        0x1000 => Jump { addr },
+        0x2000 => Call { addr },
+        0x3000 => SkipIfEqual { x, byte },
+        0x4000 => SkipIfNotEqual { x, byte },
+        0x5000 => match n {
+            0x0 => SkipIfRegistersEqual { x, y },
+            0x2 => StoreRegisterRange { x, y },
+            0x3 => LoadRegisterRange { x, y },
+            _ => return Err(DecodeError(opcode)),
+        },
+        0x6000 => Load { x, byte },
+        0x7000 => AddByte { x, byte },
+        0x8000 => match n {
+            0x0 => Move { x, y },
+            0x1 => Or { x, y },
+            0x2 => And { x, y },
+            0x3 => Xor { x, y },
+            0x4 => Add { x, y },
+            0x5 => Sub { x, y },
+            0x6 => ShiftRight { x, y },
+            0x7 => SubN { x, y },
+            0xE => ShiftLeft { x, y },
+            _ => return Err(DecodeError(opcode)),
+        },
+        0x9000 if n == 0 => SkipIfRegistersNotEqual { x, y },
+        0xA000 => LoadI { addr },
+        0xB000 => JumpWithOffset { addr },
+        0xC000 => Random { x, byte },
+        0xD000 => Draw { x, y, n },
+        0xE000 => match byte {
+            0x9E => SkipIfKeyPressed { x },
+            0xA1 => SkipIfKeyNotPressed { x },
+            _ => return Err(DecodeError(opcode)),
+        },
+        0xF000 => match byte {
+            0x00 if x == 0 => LoadILong,
+            0x01 => SelectPlanes { n: x },
+            0x02 if x == 0 => LoadAudioPattern,
+            0x07 => LoadDelayTimer { x },
+            0x0A => WaitForKey { x },
+            0x15 => SetDelayTimer { x },
+            0x18 => SetSoundTimer { x },
+            0x1E => AddToI { x },
+            0x29 => LoadDigit { x },
+            0x30 => LoadBigDigit { x },
+            0x33 => StoreBcd { x },
+            0x3A => SetPitch { x },
+            0x55 => StoreRegisters { x },
+            0x65 => LoadRegisters { x },
+            0x75 => StoreRplFlags { x },
+            0x85 => LoadRplFlags { x },
+            _ => return Err(DecodeError(opcode)),
+        },
+        _ => return Err(DecodeError(opcode)),
+    })
+}
+
+impl Instruction {
+    pub fn encode(self) -> u16 {
+        use Instruction::*;
+
+        let xy = |high: u16, x: u8, y: u8, low: u16| high | (x as u16) << 8 | (y as u16) << 4 | low;
+        let xkk = |high: u16, x: u8, byte: u8| high | (x as u16) << 8 | byte as u16;
+        match self {
+            Sys { addr } => addr & 0x0FFF,
+            ScrollDown { n } => 0x00C0 | n as u16,
+            ScrollUp { n } => 0x00D0 | n as u16,
+            Clear => 0x00E0,
+            Return => 0x00EE,
+            ScrollRight => 0x00FB,
+            ScrollLeft => 0x00FC,
+            Exit => 0x00FD,
+            LowResolution => 0x00FE,
+            HighResolution => 0x00FF,
+            Jump { addr } => 0x1000 | addr & 0x0FFF,
+            Call { addr } => 0x2000 | addr & 0x0FFF,
+            SkipIfEqual { x, byte } => xkk(0x3000, x, byte),
+            SkipIfNotEqual { x, byte } => xkk(0x4000, x, byte),
+            SkipIfRegistersEqual { x, y } => xy(0x5000, x, y, 0x0),
+            StoreRegisterRange { x, y } => xy(0x5000, x, y, 0x2),
+            LoadRegisterRange { x, y } => xy(0x5000, x, y, 0x3),
+            Load { x, byte } => xkk(0x6000, x, byte),
+            AddByte { x, byte } => xkk(0x7000, x, byte),
+            Move { x, y } => xy(0x8000, x, y, 0x0),
+            Or { x, y } => xy(0x8000, x, y, 0x1),
+            And { x, y } => xy(0x8000, x, y, 0x2),
+            Xor { x, y } => xy(0x8000, x, y, 0x3),
+            Add { x, y } => xy(0x8000, x, y, 0x4),
+            Sub { x, y } => xy(0x8000, x, y, 0x5),
+            ShiftRight { x, y } => xy(0x8000, x, y, 0x6),
+            SubN { x, y } => xy(0x8000, x, y, 0x7),
+            ShiftLeft { x, y } => xy(0x8000, x, y, 0xE),
+            SkipIfRegistersNotEqual { x, y } => xy(0x9000, x, y, 0x0),
+            LoadI { addr } => 0xA000 | addr & 0x0FFF,
+            JumpWithOffset { addr } => 0xB000 | addr & 0x0FFF,
+            Random { x, byte } => xkk(0xC000, x, byte),
+            Draw { x, y, n } => xy(0xD000, x, y, n as u16),
+            SkipIfKeyPressed { x } => xkk(0xE000, x, 0x9E),
+            SkipIfKeyNotPressed { x } => xkk(0xE000, x, 0xA1),
+            LoadILong => 0xF000,
+            SelectPlanes { n } => xkk(0xF000, n, 0x01),
+            LoadAudioPattern => 0xF002,
+            LoadDelayTimer { x } => xkk(0xF000, x, 0x07),
+            WaitForKey { x } => xkk(0xF000, x, 0x0A),
+            SetDelayTimer { x } => xkk(0xF000, x, 0x15),
+            SetSoundTimer { x } => xkk(0xF000, x, 0x18),
+            AddToI { x } => xkk(0xF000, x, 0x1E),
+            LoadDigit { x } => xkk(0xF000, x, 0x29),
+            LoadBigDigit { x } => xkk(0xF000, x, 0x30),
+            StoreBcd { x } => xkk(0xF000, x, 0x33),
+            SetPitch { x } => xkk(0xF000, x, 0x3A),
+            StoreRegisters { x } => xkk(0xF000, x, 0x55),
+            LoadRegisters { x } => xkk(0xF000, x, 0x65),
+            StoreRplFlags { x } => xkk(0xF000, x, 0x75),
+            LoadRplFlags { x } => xkk(0xF000, x, 0x85),
+        }
+    }
+
+    // Whether the instruction belongs to the XO-CHIP extensions.
+    pub fn is_xo_chip(self) -> bool {
+        use Instruction::*;
+
+        matches!(
+            self,
+            ScrollUp { .. }
+                | StoreRegisterRange { .. }
+                | LoadRegisterRange { .. }
+                | LoadILong
+                | SelectPlanes { .. }
+                | LoadAudioPattern
+                | SetPitch { .. }
+        )
+    }
+
+    // Whether execution may continue anywhere but at the next instruction.
+    pub fn is_branch(self) -> bool {
+        use Instruction::*;
+
+        matches!(
+            self,
+            Return
+                | Jump { .. }
+                | Call { .. }
+                | JumpWithOffset { .. }
+                | SkipIfEqual { .. }
+                | SkipIfNotEqual { .. }
+                | SkipIfRegistersEqual { .. }
+                | SkipIfRegistersNotEqual { .. }
+                | SkipIfKeyPressed { .. }
+                | SkipIfKeyNotPressed { .. }
+                | WaitForKey { .. }
+                | LoadILong
+        )
+    }
+
+    // The opcode pattern of the instruction, the same for all operands,
+    // e.g. `8xy4` for ADD Vx, Vy.
+    pub fn pattern(self) -> &'static str {
+        use Instruction::*;
+
+        match self {
+            Sys { .. } => "0nnn",
+            ScrollDown { .. } => "00Cn",
+            ScrollUp { .. } => "00Dn",
+            Clear => "00E0",
+            Return => "00EE",
+            ScrollRight => "00FB",
+            ScrollLeft => "00FC",
+            Exit => "00FD",
+            LowResolution => "00FE",
+            HighResolution => "00FF",
+            Jump { .. } => "1nnn",
+            Call { .. } => "2nnn",
+            SkipIfEqual { .. } => "3xkk",
+            SkipIfNotEqual { .. } => "4xkk",
+            SkipIfRegistersEqual { .. } => "5xy0",
+            StoreRegisterRange { .. } => "5xy2",
+            LoadRegisterRange { .. } => "5xy3",
+            Load { .. } => "6xkk",
+            AddByte { .. } => "7xkk",
+            Move { .. } => "8xy0",
+            Or { .. } => "8xy1",
+            And { .. } => "8xy2",
+            Xor { .. } => "8xy3",
+            Add { .. } => "8xy4",
+            Sub { .. } => "8xy5",
+            ShiftRight { .. } => "8xy6",
+            SubN { .. } => "8xy7",
+            ShiftLeft { .. } => "8xyE",
+            SkipIfRegistersNotEqual { .. } => "9xy0",
+            LoadI { .. } => "Annn",
+            JumpWithOffset { .. } => "Bnnn",
+            Random { .. } => "Cxkk",
+            Draw { .. } => "Dxyn",
+            SkipIfKeyPressed { .. } => "Ex9E",
+            SkipIfKeyNotPressed { .. } => "ExA1",
+            LoadILong => "F000",
+            SelectPlanes { .. } => "Fn01",
+            LoadAudioPattern => "F002",
+            LoadDelayTimer { .. } => "Fx07",
+            WaitForKey { .. } => "Fx0A",
+            SetDelayTimer { .. } => "Fx15",
+            SetSoundTimer { .. } => "Fx18",
+            AddToI { .. } => "Fx1E",
+            LoadDigit { .. } => "Fx29",
+            LoadBigDigit { .. } => "Fx30",
+            StoreBcd { .. } => "Fx33",
+            SetPitch { .. } => "Fx3A",
+            StoreRegisters { .. } => "Fx55",
+            LoadRegisters { .. } => "Fx65",
+            StoreRplFlags { .. } => "Fx75",
+            LoadRplFlags { .. } => "Fx85",
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown opcode 0x{:04X}", self.0)
+    }
+}
+
+impl core::error::Error for DecodeError {}
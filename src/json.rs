@@ -0,0 +1,232 @@
+use std::fmt;
+
+// A JSON reader for the files other tools produce, reference traces and ROM
+// databases. Numbers are kept as text for the caller to parse into the type
+// it needs, and objects keep their keys in order, duplicates included.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct JsonError {
+    pub(crate) line: usize,
+    pub(crate) message: String,
+}
+
+// Parse a document holding a single value.
+pub(crate) fn parse(text: &str) -> Result<Json, JsonError> {
+    let mut parser = Parser { text, offset: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.peek().is_some() {
+        return Err(parser.error("expected the end of the document"));
+    }
+    Ok(value)
+}
+
+impl Json {
+    // The value of `key` in an object.
+    pub(crate) fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_u64(&self) -> Option<u64> {
+        match self {
+            Json::Number(text) => text.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.offset..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.offset..];
+        self.offset += rest.len() - rest.trim_start().len();
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        self.skip_whitespace();
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("expected `{}`", expected)));
+        }
+        self.offset += 1;
+        Ok(())
+    }
+
+    // Whether `c` comes next, which it then consumes.
+    fn accept(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        let accepted = self.peek() == Some(c);
+        if accepted {
+            self.offset += 1;
+        }
+        accepted
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('[') => {
+                self.offset += 1;
+                let mut items = Vec::new();
+                if self.accept(']') {
+                    return Ok(Json::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    if !self.accept(',') {
+                        break;
+                    }
+                }
+                self.expect(']')?;
+                Ok(Json::Array(items))
+            }
+            Some('{') => {
+                self.offset += 1;
+                let mut fields = Vec::new();
+                if self.accept('}') {
+                    return Ok(Json::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() != Some('"') {
+                        return Err(self.error("expected a key"));
+                    }
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    fields.push((key, self.value()?));
+                    if !self.accept(',') {
+                        break;
+                    }
+                }
+                self.expect('}')?;
+                Ok(Json::Object(fields))
+            }
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some(c) if c == '-' || c.is_ascii_alphanumeric() => {
+                let rest = &self.text[self.offset..];
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.')))
+                    .unwrap_or(rest.len());
+                let word = &rest[..len];
+                let value = match word {
+                    "null" => Json::Null,
+                    "true" => Json::Bool(true),
+                    "false" => Json::Bool(false),
+                    _ if word.parse::<f64>().is_ok() => Json::Number(word.to_string()),
+                    _ => return Err(self.error(&format!("unexpected `{}`", word))),
+                };
+                self.offset += len;
+                Ok(value)
+            }
+            Some(c) => Err(self.error(&format!("unexpected `{}`", c))),
+            None => Err(self.error("unexpected end of the document")),
+        }
+    }
+
+    // A string, starting at its opening quote.
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.offset += 1;
+        let mut string = String::new();
+        let mut chars = self.text[self.offset..].char_indices();
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.offset += index + 1;
+                    return Ok(string);
+                }
+                '\\' => {
+                    let escaped = match chars.next().map(|(_, c)| c) {
+                        Some(c @ ('"' | '\\' | '/')) => c,
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            // Characters outside of the BMP come as surrogate pairs.
+                            let code = match unit(&mut chars) {
+                                Some(high @ 0xD800..=0xDBFF) => {
+                                    let low = match (chars.next(), chars.next()) {
+                                        (Some((_, '\\')), Some((_, 'u'))) => unit(&mut chars),
+                                        _ => None,
+                                    };
+                                    low.filter(|low| (0xDC00..=0xDFFF).contains(low))
+                                        .map(|low| {
+                                            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                                        })
+                                }
+                                code => code,
+                            };
+                            code.and_then(char::from_u32)
+                                .unwrap_or(char::REPLACEMENT_CHARACTER)
+                        }
+                        _ => {
+                            self.offset += index;
+                            return Err(self.error("invalid escape"));
+                        }
+                    };
+                    string.push(escaped);
+                }
+                c => string.push(c),
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    fn error(&self, message: &str) -> JsonError {
+        JsonError {
+            line: self.text[..self.offset].matches('\n').count() + 1,
+            message: message.to_string(),
+        }
+    }
+}
+
+// The four hexadecimal digits of a `\u` escape.
+fn unit(chars: &mut std::str::CharIndices) -> Option<u32> {
+    let hex: String = chars.take(4).map(|(_, c)| c).collect();
+    u32::from_str_radix(&hex, 16)
+        .ok()
+        .filter(|_| hex.len() == 4)
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
@@ -1,3 +1,7 @@
+use std::collections::BTreeMap;
+
+use crate::cpu::Chip8;
+
 /// # Keyboard
 ///
 /// The computers which originally used the Chip-8 Language had a 16-key hexade-
@@ -7,3 +11,262 @@
 /// | 4 | 5 | 6 | D |
 /// | 7 | 8 | 9 | E |
 /// | A | 0 | B | F |
+pub const LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// # Keypad
+///
+/// The state of the 16 keys, and of a program waiting in `Fx0A - LD Vx, K`.
+/// Like the COSMAC VIP, Fx0A waits for a whole key stroke: the key pressed
+/// first (or already held) while waiting is only reported once it is
+/// released, so a program reading keys in a loop sees each stroke once
+/// instead of the key repeating while held.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Keypad {
+    pressed: [bool; 16],
+
+    // Whether a program is blocked in Fx0A, the key it got pressed and the
+    // key once it was released again
+    waiting: bool,
+    latched: Option<u8>,
+    released: Option<u8>,
+}
+
+impl Keypad {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn press(&mut self, key: u8) {
+        let key = key & 0x0F;
+        self.pressed[key as usize] = true;
+        if self.waiting && self.latched.is_none() {
+            self.latched = Some(key);
+        }
+    }
+
+    pub fn release(&mut self, key: u8) {
+        let key = key & 0x0F;
+        self.pressed[key as usize] = false;
+        if self.waiting && self.latched == Some(key) {
+            self.released = Some(key);
+        }
+    }
+
+    pub fn is_pressed(&self, key: u8) -> bool {
+        self.pressed[(key & 0x0F) as usize]
+    }
+
+    // Current state of the 16 keys, indexed by key value.
+    pub fn keys(&self) -> [bool; 16] {
+        self.pressed
+    }
+
+    // Replace the state of all keys, e.g. when restoring a save state. A
+    // program waiting in Fx0A starts waiting over.
+    pub fn restore(&mut self, keys: [bool; 16]) {
+        *self = Self {
+            pressed: keys,
+            ..Self::default()
+        };
+    }
+
+    // Whether a program is blocked in Fx0A.
+    pub fn waiting(&self) -> bool {
+        self.waiting
+    }
+
+    // The keypad as frontends show it, see `KeypadView`.
+    pub fn view(&self) -> KeypadView {
+        KeypadView::new(self.pressed, self.waiting)
+    }
+
+    // Fx0A, called every time the instruction runs: the key once a stroke
+    // completed, `None` while the program has to keep waiting.
+    pub fn wait_for_key(&mut self) -> Option<u8> {
+        if let Some(key) = self.released.take() {
+            self.waiting = false;
+            self.latched = None;
+            return Some(key);
+        }
+        self.waiting = true;
+        if self.latched.is_none() {
+            self.latched = (0..16).find(|&key| self.pressed[key as usize]);
+        }
+        None
+    }
+}
+
+/// # Keypad View
+///
+/// A frontend-agnostic model of the keypad widget: the 4x4 grid in the
+/// original layout, which keys are held down, and whether the program is
+/// blocked in `Fx0A - LD Vx, K` waiting for a key. Graphical frontends draw
+/// the cells themselves, terminal frontends can use `render`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeypadView {
+    pub keys: [bool; 16],
+    pub waiting: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyCell {
+    pub row: usize,
+    pub column: usize,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+impl KeypadView {
+    pub fn new(keys: [bool; 16], waiting: bool) -> Self {
+        Self { keys, waiting }
+    }
+
+    // Cells in row-major order of the physical layout.
+    pub fn cells(&self) -> impl Iterator<Item = KeyCell> + '_ {
+        LAYOUT.iter().enumerate().flat_map(move |(row, keys)| {
+            keys.iter().enumerate().map(move |(column, &key)| KeyCell {
+                row,
+                column,
+                key,
+                pressed: self.keys[key as usize],
+            })
+        })
+    }
+
+    // Render the keypad as text, with held keys in brackets and a note while
+    // the program waits for a key press.
+    pub fn render(&self) -> String {
+        const BORDER: &str = "+---+---+---+---+\n";
+        let mut text = String::from(BORDER);
+        for row in LAYOUT {
+            text.push('|');
+            for key in row {
+                if self.keys[key as usize] {
+                    text.push_str(&format!("[{:X}]|", key));
+                } else {
+                    text.push_str(&format!(" {:X} |", key));
+                }
+            }
+            text.push('\n');
+            text.push_str(BORDER);
+        }
+        if self.waiting {
+            text.push_str("waiting for a key (Fx0A)\n");
+        }
+        text
+    }
+}
+
+/// # Key Map
+///
+/// Maps host keys, identified by name (`"Q"`, `"Up"`, `"Pad2.A"`), onto keys
+/// of a keypad. Two keypads are supported: pad 0 is the machine's main
+/// keypad, pad 1 is the auxiliary keypad of CHIP-8X. Two-player games on a
+/// single keypad conventionally split its 16 keys between the players, which
+/// is handled by binding a second set of host keys to pad 0.
+///
+/// Host key names are compared case-insensitively.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyMap {
+    bindings: BTreeMap<String, KeyBinding>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub pad: u8,
+    pub key: u8,
+}
+
+// Host keys of the standard mapping, laid out like `LAYOUT`.
+const STANDARD: [[&str; 4]; 4] = [
+    ["1", "2", "3", "4"],
+    ["Q", "W", "E", "R"],
+    ["A", "S", "D", "F"],
+    ["Z", "X", "C", "V"],
+];
+
+// Numeric keypad used for the auxiliary keypad, laid out like `LAYOUT`.
+const NUMPAD: [[&str; 4]; 4] = [
+    ["Keypad7", "Keypad8", "Keypad9", "KeypadDivide"],
+    ["Keypad4", "Keypad5", "Keypad6", "KeypadMultiply"],
+    ["Keypad1", "Keypad2", "Keypad3", "KeypadMinus"],
+    ["Keypad0", "KeypadPeriod", "KeypadEnter", "KeypadPlus"],
+];
+
+impl KeyMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 1234/QWER/ASDF/ZXCV onto the main keypad.
+    pub fn standard() -> Self {
+        let mut map = Self::new();
+        map.bind_grid(&STANDARD, 0);
+        map
+    }
+
+    // The standard mapping plus the arrow keys for the second player of games
+    // which split the keypad, using the C/D up/down convention of Pong.
+    pub fn split() -> Self {
+        let mut map = Self::standard();
+        map.bind("Up", 0, 0xC);
+        map.bind("Down", 0, 0xD);
+        map
+    }
+
+    // The standard mapping plus the numeric keypad as the CHIP-8X
+    // auxiliary keypad.
+    pub fn chip8x() -> Self {
+        let mut map = Self::standard();
+        map.bind_grid(&NUMPAD, 1);
+        map
+    }
+
+    pub fn bind(&mut self, host: &str, pad: u8, key: u8) {
+        self.bindings.insert(
+            host.to_ascii_lowercase(),
+            KeyBinding {
+                pad: pad & 1,
+                key: key & 0x0F,
+            },
+        );
+    }
+
+    pub fn unbind(&mut self, host: &str) {
+        self.bindings.remove(&host.to_ascii_lowercase());
+    }
+
+    // Remove every host key bound to `key` of `pad`.
+    pub fn unbind_key(&mut self, pad: u8, key: u8) {
+        self.bindings
+            .retain(|_, binding| binding.pad != pad || binding.key != key);
+    }
+
+    pub fn lookup(&self, host: &str) -> Option<KeyBinding> {
+        self.bindings.get(&host.to_ascii_lowercase()).copied()
+    }
+
+    // Forward a host key event to the machine. Events for the auxiliary
+    // keypad are returned to the caller, since the core only has the main
+    // keypad.
+    pub fn apply(&self, chip8: &mut Chip8, host: &str, pressed: bool) -> Option<KeyBinding> {
+        let binding = self.lookup(host)?;
+        if binding.pad == 0 {
+            chip8.set_key(binding.key, pressed);
+        }
+        Some(binding)
+    }
+
+    fn bind_grid(&mut self, hosts: &[[&str; 4]; 4], pad: u8) {
+        for (row, keys) in LAYOUT.iter().enumerate() {
+            for (column, &key) in keys.iter().enumerate() {
+                self.bind(hosts[row][column], pad, key);
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! A CHIP-8 emulator core, with the tools built around it: an assembler and
+//! disassembler, save states, rewind, debugging aids and more.
+//!
+//! The core is `Chip8`, which can be embedded in any frontend. The host
+//! loads a ROM, feeds in the keypad state and runs the machine one 60Hz
+//! frame at a time (or one instruction at a time with `step`):
+//!
+//! ```
+//! use chip_8_rs::{Chip8, Register};
+//!
+//! let mut chip8 = Chip8::new();
+//! chip8.set_seed(42);
+//! // LD V0, 0x2A; JP 0x202
+//! chip8.load_rom(&[0x60, 0x2A, 0x12, 0x02]).expect("the ROM fits in memory");
+//! chip8.set_key(0x5, true);
+//! chip8.run_frame(12);
+//! assert_eq!(chip8.register(Register::V(0)), 0x2A);
+//! ```
+//!
+//! `run_frame` ticks the timers after running its instructions; hosts
+//! calling `step` themselves call `tick_timers` at 60Hz, and learn from the
+//! returned `StepResult` whether the program waits for a key or faulted.
+//! Hosts can also implement the backend traits of `host` and leave it to
+//! `host::run_frame` to feed them; `host::Headless` does so in memory.
+//!
+//! Without the default `std` feature the crate is `no_std` and keeps only
+//! what runs without an operating system or an allocator: `bare::Machine`,
+//! a fixed size CHIP-8 and SUPER-CHIP machine for microcontrollers, and
+//! the instruction decoder, fonts, quirks, timers and random sources it is
+//! built from.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+pub mod asm;
+#[cfg(feature = "std")]
+pub mod attract;
+#[cfg(feature = "std")]
+pub mod audio;
+#[cfg(feature = "std")]
+pub mod audit;
+#[cfg(feature = "std")]
+pub mod backend;
+pub mod bare;
+#[cfg(feature = "std")]
+pub mod bench;
+#[cfg(feature = "std")]
+pub mod blocks;
+#[cfg(feature = "std")]
+pub mod browser;
+#[cfg(feature = "std")]
+pub mod c8b;
+#[cfg(feature = "std")]
+pub mod cached;
+#[cfg(feature = "std")]
+pub mod capture;
+#[cfg(feature = "std")]
+pub mod cartridge;
+#[cfg(feature = "std")]
+pub mod cheats;
+#[cfg(feature = "std")]
+pub mod config;
+#[cfg(feature = "test-roms")]
+pub mod corpus;
+#[cfg(feature = "cpal")]
+pub mod cpal_audio;
+#[cfg(feature = "std")]
+pub mod cpu;
+#[cfg(feature = "std")]
+pub mod crowd;
+#[cfg(feature = "std")]
+pub mod debugger;
+#[cfg(feature = "std")]
+pub mod determinism;
+#[cfg(feature = "std")]
+pub mod disasm;
+pub mod display;
+pub mod fault;
+#[cfg(feature = "net")]
+pub mod fetch;
+#[cfg(feature = "std")]
+pub mod flicker;
+pub mod font;
+#[cfg(feature = "std")]
+pub mod handle;
+#[cfg(feature = "std")]
+pub mod heatmap;
+#[cfg(feature = "std")]
+pub mod host;
+pub mod instruction;
+#[cfg(feature = "std")]
+mod json;
+#[cfg(feature = "std")]
+pub mod keyboard;
+#[cfg(feature = "libretro")]
+pub mod libretro;
+pub mod memory;
+#[cfg(feature = "std")]
+pub mod memview;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod movie;
+#[cfg(feature = "std")]
+pub mod pacing;
+#[cfg(feature = "std")]
+pub mod palette;
+#[cfg(feature = "std")]
+pub mod phosphor;
+#[cfg(feature = "std")]
+pub mod png;
+#[cfg(feature = "std")]
+pub mod profile;
+#[cfg(feature = "std")]
+pub mod quicksave;
+pub mod quirks;
+#[cfg(feature = "std")]
+pub mod ramsearch;
+pub mod random;
+#[cfg(feature = "std")]
+pub mod recording;
+#[cfg(feature = "std")]
+pub mod reference;
+#[cfg(feature = "std")]
+pub mod registers;
+#[cfg(feature = "std")]
+pub mod reload;
+#[cfg(feature = "std")]
+pub mod rewind;
+#[cfg(feature = "std")]
+pub mod rollback;
+#[cfg(feature = "std")]
+pub mod rom;
+#[cfg(feature = "std")]
+pub mod rominfo;
+#[cfg(feature = "std")]
+pub mod savestate;
+#[cfg(feature = "std")]
+pub mod scenario;
+#[cfg(feature = "std")]
+pub mod scope;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+#[cfg(feature = "sdl")]
+pub mod sdl;
+#[cfg(feature = "std")]
+pub mod selftest;
+#[cfg(feature = "std")]
+pub mod snapshot;
+#[cfg(feature = "std")]
+pub mod splash;
+#[cfg(feature = "std")]
+pub mod sprites;
+#[cfg(feature = "std")]
+pub mod telemetry;
+pub mod timers;
+#[cfg(feature = "std")]
+pub mod trace;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "web")]
+pub mod wasm;
+#[cfg(feature = "std")]
+pub mod watch;
+#[cfg(feature = "web")]
+pub mod web_audio;
+
+#[cfg(feature = "std")]
+pub use cpu::{Chip8, ResetHotkey, StepResult};
+#[cfg(feature = "std")]
+pub use display::Display;
+pub use fault::{Chip8Error, EmulationMode, Fault};
+pub use font::FontSet;
+pub use instruction::Instruction;
+#[cfg(feature = "std")]
+pub use memory::Memory;
+pub use memory::MemoryError;
+pub use quirks::Quirks;
+#[cfg(feature = "std")]
+pub use registers::Register;
+#[cfg(feature = "std")]
+pub use rom::{LoadAddress, LoadError};
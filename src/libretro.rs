@@ -0,0 +1,495 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, c_uint, c_void};
+use std::ptr;
+use std::slice;
+
+use crate::audio::{AudioConfig, Mixer};
+use crate::cartridge::Cartridge;
+use crate::cpu::Chip8;
+use crate::display::{self, Resolution};
+use crate::palette::Palette;
+
+/// # libretro Core
+///
+/// The emulator as a libretro core, for RetroArch and other libretro
+/// frontends, when the crate is built with `--features libretro` as a
+/// `cdylib` (`cargo rustc --lib --crate-type cdylib`), which then exports
+/// the `retro_*` functions of the libretro API. The API is declared here
+/// by hand, as far as the core uses it.
+///
+/// Games are loaded from memory, as plain ROMs or cartridges (see
+/// `Cartridge`), whose variant, tickrate, palette and timer rate apply.
+/// Every `retro_run` runs one frame at the timer rate, draws the screen in
+/// XRGB8888 at its current resolution, and plays the buzzer as a frame of
+/// stereo samples. Switching between resolutions of other shapes, such as
+/// 64x32 and 64x64, sets the geometry of the frontend anew. Save states go
+/// through `Chip8::save_state`.
+///
+/// The 16 buttons of the RetroPad map onto the 16 keys of the keypad, the
+/// directions onto 2, 4, 6 and 8, which most games use to move:
+///
+/// ```text
+/// Up 2      Down 8    Left 4    Right 6
+/// A 5       B 0       X 1       Y 3
+/// L 7       R 9       L2 A      R2 B
+/// L3 C      R3 D      Select E  Start F
+/// ```
+///
+/// libretro calls a core from one thread, so the core and the callbacks
+/// the frontend registers are kept per thread.
+pub const API_VERSION: c_uint = 1;
+
+const DEVICE_JOYPAD: c_uint = 1;
+const ENVIRONMENT_SET_PIXEL_FORMAT: c_uint = 10;
+const ENVIRONMENT_SET_INPUT_DESCRIPTORS: c_uint = 11;
+const ENVIRONMENT_SET_GEOMETRY: c_uint = 37;
+const PIXEL_FORMAT_XRGB8888: c_uint = 1;
+const REGION_NTSC: c_uint = 0;
+const REGION_PAL: c_uint = 1;
+
+// RetroPad buttons by their libretro ids, with the key each is mapped onto
+// and its description, nul-terminated for the frontend.
+const BUTTONS: [(c_uint, u8, &str); 16] = [
+    (0, 0x0, "B (0)\0"),
+    (1, 0x3, "Y (3)\0"),
+    (2, 0xE, "Select (E)\0"),
+    (3, 0xF, "Start (F)\0"),
+    (4, 0x2, "Up (2)\0"),
+    (5, 0x8, "Down (8)\0"),
+    (6, 0x4, "Left (4)\0"),
+    (7, 0x6, "Right (6)\0"),
+    (8, 0x5, "A (5)\0"),
+    (9, 0x1, "X (1)\0"),
+    (10, 0x7, "L (7)\0"),
+    (11, 0x9, "R (9)\0"),
+    (12, 0xA, "L2 (A)\0"),
+    (13, 0xB, "R2 (B)\0"),
+    (14, 0xC, "L3 (C)\0"),
+    (15, 0xD, "R3 (D)\0"),
+];
+
+// Instructions per frame when the cartridge sets no tickrate, 720 per
+// second at 60Hz.
+const DEFAULT_CYCLES_PER_FRAME: usize = 12;
+
+#[repr(C)]
+pub struct SystemInfo {
+    pub library_name: *const c_char,
+    pub library_version: *const c_char,
+    pub valid_extensions: *const c_char,
+    pub need_fullpath: bool,
+    pub block_extract: bool,
+}
+
+#[repr(C)]
+pub struct GameGeometry {
+    pub base_width: c_uint,
+    pub base_height: c_uint,
+    pub max_width: c_uint,
+    pub max_height: c_uint,
+    pub aspect_ratio: f32,
+}
+
+#[repr(C)]
+pub struct SystemTiming {
+    pub fps: f64,
+    pub sample_rate: f64,
+}
+
+#[repr(C)]
+pub struct SystemAvInfo {
+    pub geometry: GameGeometry,
+    pub timing: SystemTiming,
+}
+
+#[repr(C)]
+pub struct GameInfo {
+    pub path: *const c_char,
+    pub data: *const c_void,
+    pub size: usize,
+    pub meta: *const c_char,
+}
+
+#[repr(C)]
+struct InputDescriptor {
+    port: c_uint,
+    device: c_uint,
+    index: c_uint,
+    id: c_uint,
+    description: *const c_char,
+}
+
+pub type EnvironmentFn = unsafe extern "C" fn(cmd: c_uint, data: *mut c_void) -> bool;
+pub type VideoRefreshFn =
+    unsafe extern "C" fn(data: *const c_void, width: c_uint, height: c_uint, pitch: usize);
+pub type AudioSampleFn = unsafe extern "C" fn(left: i16, right: i16);
+pub type AudioSampleBatchFn = unsafe extern "C" fn(data: *const i16, frames: usize) -> usize;
+pub type InputPollFn = unsafe extern "C" fn();
+pub type InputStateFn =
+    unsafe extern "C" fn(port: c_uint, device: c_uint, index: c_uint, id: c_uint) -> i16;
+
+// The callbacks registered by the frontend.
+#[derive(Clone, Copy, Default)]
+struct Callbacks {
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+}
+
+// The loaded game.
+struct Core {
+    chip8: Chip8,
+    cartridge: Cartridge,
+    cycles_per_frame: usize,
+    palette: Palette,
+    mixer: Mixer,
+    samples: Vec<f32>,
+
+    // Interleaved stereo samples and XRGB8888 pixels of the last frame
+    audio: Vec<i16>,
+    video: Vec<u32>,
+
+    // Resolution of the geometry the frontend was given
+    resolution: Resolution,
+}
+
+thread_local! {
+    static CALLBACKS: RefCell<Callbacks> = RefCell::default();
+    static CORE: RefCell<Option<Core>> = const { RefCell::new(None) };
+}
+
+impl Core {
+    fn new(cartridge: Cartridge) -> Option<Core> {
+        let chip8 = boot(&cartridge)?;
+        let resolution = chip8.display().resolution();
+        let mut mixer = Mixer::new(AudioConfig::default());
+        mixer.set_frame_rate(cartridge.timer_rate.hz());
+        Some(Core {
+            chip8,
+            cycles_per_frame: cartridge
+                .tickrate
+                .map_or(DEFAULT_CYCLES_PER_FRAME, |rate| rate as usize),
+            palette: cartridge
+                .palette
+                .map_or(Palette::default(), |[background, foreground]| {
+                    Palette::new(background, foreground)
+                }),
+            cartridge,
+            mixer,
+            samples: Vec::new(),
+            audio: Vec::new(),
+            video: Vec::new(),
+            resolution,
+        })
+    }
+
+    fn run_frame(&mut self, callbacks: Callbacks) {
+        if let (Some(poll), Some(state)) = (callbacks.input_poll, callbacks.input_state) {
+            // SAFETY: the frontend's callbacks are valid while a game is
+            // loaded.
+            unsafe { poll() };
+            let mut mask = 0;
+            for (id, key, _) in BUTTONS {
+                // SAFETY: as above.
+                if unsafe { state(0, DEVICE_JOYPAD, 0, id) } != 0 {
+                    mask |= 1 << key;
+                }
+            }
+            self.chip8.set_keys(mask);
+        }
+        self.chip8.run_frame(self.cycles_per_frame);
+
+        let display = self.chip8.display();
+        let resolution = display.resolution();
+        if resolution != self.resolution {
+            self.resolution = resolution;
+            if let Some(environment) = callbacks.environment {
+                let mut geometry = geometry(resolution);
+                // SAFETY: the frontend copies the geometry.
+                unsafe { environment(ENVIRONMENT_SET_GEOMETRY, (&raw mut geometry).cast()) };
+            }
+        }
+        self.video.clear();
+        self.video.extend(display.colors().iter().map(|&color| {
+            let [r, g, b] = self.palette.color(color);
+            u32::from_be_bytes([0, r, g, b])
+        }));
+        if let Some(video_refresh) = callbacks.video_refresh {
+            let (width, height) = (display.width(), display.height());
+            // SAFETY: the buffer holds `height` rows of `width` pixels and
+            // outlives the call.
+            unsafe {
+                video_refresh(
+                    self.video.as_ptr().cast(),
+                    width as c_uint,
+                    height as c_uint,
+                    width * 4,
+                )
+            };
+        }
+
+        self.samples.clear();
+        self.mixer.set_voice(self.chip8.voice());
+        self.mixer
+            .render_frame(self.chip8.sound_active(), &mut self.samples);
+        self.audio.clear();
+        for &sample in &self.samples {
+            let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.audio.extend([sample, sample]);
+        }
+        if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+            // SAFETY: the buffer holds `samples.len()` stereo frames and
+            // outlives the call.
+            unsafe { audio_sample_batch(self.audio.as_ptr(), self.samples.len()) };
+        }
+    }
+}
+
+// A machine set up for the cartridge, none if its ROM does not fit.
+fn boot(cartridge: &Cartridge) -> Option<Chip8> {
+    let mut chip8 = Chip8::new();
+    chip8.set_variant(cartridge.variant);
+    chip8.set_timer_rate(cartridge.timer_rate.hz());
+    chip8.load_rom(&cartridge.rom).ok()?;
+    Some(chip8)
+}
+
+// The screen size at `resolution`, within the largest one.
+fn geometry(resolution: Resolution) -> GameGeometry {
+    GameGeometry {
+        base_width: resolution.width() as c_uint,
+        base_height: resolution.height() as c_uint,
+        max_width: display::HIRES_WIDTH as c_uint,
+        max_height: display::HIRES_HEIGHT as c_uint,
+        aspect_ratio: resolution.width() as f32 / resolution.height() as f32,
+    }
+}
+
+fn callbacks() -> Callbacks {
+    CALLBACKS.with(|callbacks| *callbacks.borrow())
+}
+
+fn with_core<T>(default: T, f: impl FnOnce(&mut Core) -> T) -> T {
+    CORE.with(|core| core.borrow_mut().as_mut().map_or(default, f))
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    API_VERSION
+}
+
+// The callbacks set by the `retro_set_*` functions must stay valid until
+// the core is unloaded.
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+    CALLBACKS.with(|callbacks| callbacks.borrow_mut().environment = Some(callback));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+    CALLBACKS.with(|callbacks| callbacks.borrow_mut().video_refresh = Some(callback));
+}
+
+// Single samples are never sent, the core only plays whole frames.
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_callback: AudioSampleFn) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+    CALLBACKS.with(|callbacks| callbacks.borrow_mut().audio_sample_batch = Some(callback));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+    CALLBACKS.with(|callbacks| callbacks.borrow_mut().input_poll = Some(callback));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+    CALLBACKS.with(|callbacks| callbacks.borrow_mut().input_state = Some(callback));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    CORE.with(|core| core.borrow_mut().take());
+}
+
+/// # Safety
+///
+/// `info` must point to a `SystemInfo` to fill in.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut SystemInfo) {
+    let Some(info) = (unsafe { info.as_mut() }) else {
+        return;
+    };
+    *info = SystemInfo {
+        library_name: c"chip-8-rs".as_ptr(),
+        library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast(),
+        valid_extensions: c"ch8|c8|sc8|xo8|c8x|c8b".as_ptr(),
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// # Safety
+///
+/// `info` must point to a `SystemAvInfo` to fill in.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut SystemAvInfo) {
+    let Some(info) = (unsafe { info.as_mut() }) else {
+        return;
+    };
+    let fps = with_core(60, |core| core.cartridge.timer_rate.hz());
+    let resolution = with_core(Resolution::default(), |core| core.resolution);
+    *info = SystemAvInfo {
+        geometry: geometry(resolution),
+        timing: SystemTiming {
+            fps: fps as f64,
+            sample_rate: AudioConfig::default().sample_rate as f64,
+        },
+    };
+}
+
+/// Load the game from memory. Returns whether it could be loaded.
+///
+/// # Safety
+///
+/// `game` must point to a `GameInfo` whose `data` holds `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const GameInfo) -> bool {
+    let Some(game) = (unsafe { game.as_ref() }).filter(|game| !game.data.is_null()) else {
+        return false;
+    };
+    // SAFETY: the frontend passes `size` bytes of game data.
+    let bytes = unsafe { slice::from_raw_parts(game.data.cast::<u8>(), game.size) };
+    let Some(core) = Cartridge::load(bytes).ok().and_then(Core::new) else {
+        return false;
+    };
+    if let Some(environment) = callbacks().environment {
+        let mut format = PIXEL_FORMAT_XRGB8888;
+        let mut descriptors: Vec<InputDescriptor> = BUTTONS
+            .iter()
+            .map(|&(id, _, description)| InputDescriptor {
+                port: 0,
+                device: DEVICE_JOYPAD,
+                index: 0,
+                id,
+                description: description.as_ptr().cast(),
+            })
+            .collect();
+        descriptors.push(InputDescriptor {
+            port: 0,
+            device: 0,
+            index: 0,
+            id: 0,
+            description: ptr::null(),
+        });
+        // SAFETY: both commands take a pointer to the data they set, which
+        // the frontend copies.
+        unsafe {
+            if !environment(ENVIRONMENT_SET_PIXEL_FORMAT, (&raw mut format).cast()) {
+                return false;
+            }
+            environment(
+                ENVIRONMENT_SET_INPUT_DESCRIPTORS,
+                descriptors.as_mut_ptr().cast(),
+            );
+        }
+    }
+    CORE.with(|slot| *slot.borrow_mut() = Some(core));
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const GameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    CORE.with(|core| core.borrow_mut().take());
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    match with_core(60, |core| core.cartridge.timer_rate.hz()) {
+        50 => REGION_PAL,
+        _ => REGION_NTSC,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let callbacks = callbacks();
+    with_core((), |core| core.run_frame(callbacks));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    with_core((), |core| core.chip8.reset(true));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    with_core(0, |core| core.chip8.save_state().len())
+}
+
+/// # Safety
+///
+/// `data` must point to `size` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    with_core(false, |core| {
+        let state = core.chip8.save_state();
+        if data.is_null() || size < state.len() {
+            return false;
+        }
+        // SAFETY: the frontend passes `size` writable bytes.
+        let out = unsafe { slice::from_raw_parts_mut(data.cast::<u8>(), state.len()) };
+        out.copy_from_slice(&state);
+        true
+    })
+}
+
+/// # Safety
+///
+/// `data` must point to `size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    if data.is_null() {
+        return false;
+    }
+    // SAFETY: the frontend passes `size` bytes of a state.
+    let state = unsafe { slice::from_raw_parts(data.cast::<u8>(), size) };
+    with_core(false, |core| core.chip8.load_state(state).is_ok())
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+// Memory is not exposed to the frontend, which would need it to stay at
+// the same address for the whole session.
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+    ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+    0
+}
@@ -1,3 +1,2430 @@
+use std::error::Error;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use std::{fs, process, thread};
+
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand};
+
+#[cfg(any(feature = "sdl", feature = "tui"))]
+use chip_8_rs::attract::AttractMode;
+use chip_8_rs::audio::{
+    AudioConfig, Buzzer, Mixer, SampleQueue, Speaker, Voice, WavRecorder, Waveform,
+};
+use chip_8_rs::audit::AuditEvent;
+use chip_8_rs::backend::{ExecutionBackend, Interpreter};
+use chip_8_rs::blocks::BlockTranslator;
+use chip_8_rs::cached::CachedInterpreter;
+use chip_8_rs::capture::{self, Recorder};
+use chip_8_rs::cartridge::{Cartridge, TimerRate, Variant};
+use chip_8_rs::cheats::{Cheat, CheatKind, Cheats};
+use chip_8_rs::config::Config;
+#[cfg(feature = "cpal")]
+use chip_8_rs::cpal_audio::CpalBuzzer;
+use chip_8_rs::cpu::Chip8;
+#[cfg(feature = "net")]
+use chip_8_rs::crowd::{CrowdInput, VoteServer};
+use chip_8_rs::debugger::Debugger;
+use chip_8_rs::disasm::{self, Trace};
+use chip_8_rs::display::Resolution;
+use chip_8_rs::fault::{Check, EmulationMode, FaultPolicy};
+#[cfg(feature = "net")]
+use chip_8_rs::fetch;
+use chip_8_rs::font::FontSet;
+use chip_8_rs::heatmap::{self, AccessMap};
+use chip_8_rs::host::AudioBackend;
+use chip_8_rs::memview::MemoryView;
+#[cfg(feature = "metrics")]
+use chip_8_rs::metrics::{Kind, Metrics, MetricsServer};
+use chip_8_rs::movie::{self, Movie, MovieSession};
+use chip_8_rs::palette::Palette;
+use chip_8_rs::phosphor::PhosphorConfig;
+use chip_8_rs::quirks::{self, Quirks};
+use chip_8_rs::recording::InputRecording;
+use chip_8_rs::reference::ReferenceTrace;
+use chip_8_rs::registers::Register;
+use chip_8_rs::reload::LiveReload;
+use chip_8_rs::rom::{LoadAddress, LoadError};
+use chip_8_rs::rominfo::{self, RomDatabase};
+#[cfg(feature = "scripting")]
+use chip_8_rs::scripting::Script;
+#[cfg(feature = "sdl")]
+use chip_8_rs::sdl::{self, LoadedRom, SdlBuzzer, SdlConfig};
+use chip_8_rs::trace::{TraceEntry, HISTORY_LEN};
+#[cfg(feature = "tui")]
+use chip_8_rs::tui::{self, TuiConfig};
+use chip_8_rs::watch::WatchList;
+use chip_8_rs::{asm, bench, determinism, png, selftest, snapshot, splash, sprites};
+
+const NOTES: &str = "\
+Every command reads its defaults from ~/.config/chip8-rs/config.toml, or the
+file given with --config; --no-config ignores it. Cheats are read from the
+file next to the ROM named after it, e.g. game.cheats.toml, or the one given
+with --cheats; --no-cheats ignores it.";
+
+/// CHIP-8 emulator, assembler and tools
+#[derive(Parser)]
+#[command(name = "chip8", after_help = NOTES)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    /// Configuration file, instead of the default one
+    #[arg(long, global = true, value_name = "FILE", conflicts_with = "no_config")]
+    config: Option<PathBuf>,
+
+    #[arg(long, global = true)]
+    no_config: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a ROM headlessly and print a telemetry report on exit, or the
+    /// splash screen without a ROM
+    Run(RunArgs),
+    /// Play a ROM in a window
+    Play(PlayArgs),
+    /// Play a ROM in the terminal
+    Tui(TuiArgs),
+    /// Record the keypad of every frame into a movie while playing
+    Record(RecordArgs),
+    /// Assemble and link sources into a ROM, then play it
+    Build(BuildArgs),
+    /// Assemble and link sources into a ROM
+    Asm(AsmArgs),
+    /// Print the state hash after running a ROM
+    Verify(VerifyArgs),
+    /// Check the screen of a test ROM against a hash or snapshot
+    TestRom(TestRomArgs),
+    /// Compare two sets of quirks by running a ROM under both
+    Quirkdiff(QuirkdiffArgs),
+    /// Compare a run against a reference trace, or write one
+    Compare(CompareArgs),
+    /// Export the sprites of a ROM
+    Sprites(SpritesArgs),
+    /// Disassemble a ROM into source that reassembles
+    Disasm(DisasmArgs),
+    /// Export a memory access heatmap of a run as a PNG
+    Heatmap(HeatmapArgs),
+    /// Print expressions over the machine after every frame
+    Watch(WatchArgs),
+    /// Hex dump memory after running a ROM
+    Dump(DumpArgs),
+    /// Run a ROM in the interactive debugger
+    Debug(DebugArgs),
+    /// Play a tone sweep and XO-CHIP patterns through the audio settings
+    Soundtest(SoundtestArgs),
+    /// Print what the cartridge and the ROM database know about a ROM
+    Info(InfoArgs),
+    /// Check this build with the built-in opcode tests
+    Selftest(SelftestArgs),
+    /// Compare the speed of the backends
+    Bench(BenchArgs),
+}
+
+// How the machine running the ROM is set up, for every command running one.
+#[derive(clap::Args)]
+#[command(next_help_heading = "Machine")]
+struct MachineArgs {
+    /// Seed of the random number generator
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    seed: u64,
+
+    /// Instructions per second, instead of the cartridge's tickrate
+    #[arg(long, value_name = "N")]
+    ips: Option<u32>,
+
+    /// Execution backend
+    #[arg(long, value_name = "NAME", default_value = "interpreter", value_parser = BACKENDS)]
+    backend: String,
+
+    /// Skip ahead until the first draw, at most N frames
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    fast_boot: u64,
+
+    /// Halt on anything out of spec
+    #[arg(long, overrides_with = "permissive")]
+    strict: bool,
+
+    /// Carry on after anything out of spec (the default)
+    #[arg(long, overrides_with = "strict")]
+    permissive: bool,
+
+    /// What to do on a fault of one kind, e.g. memory=continue
+    #[arg(long, value_name = "CHECK=POLICY", value_parser = parse_check)]
+    check: Vec<(Check, FaultPolicy)>,
+
+    /// auto, or chip8, schip, xochip or default followed by
+    /// ,<quirk>=on|off overrides
+    #[arg(long, value_name = "SPEC")]
+    quirks: Option<QuirkSetting>,
+
+    /// Run the timers and frames at 50Hz
+    #[arg(long)]
+    pal: bool,
+
+    /// chip8, schip, xochip or hires
+    #[arg(long)]
+    variant: Option<Variant>,
+
+    /// Low resolution instead of the variant's: 64x32, 64x48 or 64x64
+    #[arg(long, value_name = "SIZE")]
+    resolution: Option<Resolution>,
+
+    /// Load the program at 0x600, for ETI 660 ROMs
+    #[arg(long)]
+    eti660: bool,
+
+    /// Let programs write to the interpreter area below 0x200
+    #[arg(long)]
+    unprotected: bool,
+
+    /// modern, vip, eti660, dream6800 or schip
+    #[arg(long)]
+    font: Option<FontSet>,
+
+    /// ROM database extending the bundled one for --from-db, see `info`
+    #[arg(long, value_name = "FILE|URL")]
+    rom_db: Option<PathBuf>,
+
+    /// Fill in the cartridge settings from the ROM database
+    #[arg(long)]
+    from_db: bool,
+
+    /// Cheat file, instead of the one next to the ROM
+    #[arg(long, value_name = "FILE", conflicts_with = "no_cheats")]
+    cheats: Option<PathBuf>,
+
+    #[arg(long)]
+    no_cheats: bool,
+
+    /// Freeze a value, as ADDR:VALUE[?COMPARE]
+    #[arg(long = "cheat", value_name = "CODE", value_parser = parse_cheat)]
+    codes: Vec<Cheat>,
+
+    /// Turn off a cheat by name
+    #[arg(long = "no-cheat", value_name = "NAME")]
+    disabled_cheats: Vec<String>,
+}
+
+// How the screen looks, in the window and the exported images.
+#[derive(clap::Args)]
+#[command(next_help_heading = "Display")]
+struct DisplayArgs {
+    /// A preset, or 2 or 4 colors as #RRGGBB separated by commas
+    #[arg(long)]
+    palette: Option<Palette>,
+
+    /// Pixel size of the window and the exported images
+    #[arg(long, value_name = "N")]
+    scale: Option<u32>,
+}
+
+// What the keypad does while nobody plays.
+#[derive(clap::Args)]
+#[command(next_help_heading = "Input")]
+struct InputArgs {
+    /// Movie to play as a demo after --idle seconds without a key pressed
+    #[arg(long, value_name = "MOVIE")]
+    attract: Option<String>,
+
+    #[arg(long, value_name = "SECONDS", default_value_t = 30)]
+    idle: u64,
+}
+
+// The sound of the buzzer, see `AudioConfig`.
+#[derive(clap::Args)]
+#[command(next_help_heading = "Audio")]
+struct AudioArgs {
+    #[arg(long, value_name = "HZ")]
+    sample_rate: Option<u32>,
+
+    #[arg(long, value_name = "N")]
+    buffer_size: Option<u32>,
+
+    #[arg(long, value_name = "MS")]
+    latency: Option<u32>,
+
+    #[arg(long, value_name = "MS")]
+    attack: Option<u32>,
+
+    #[arg(long, value_name = "MS")]
+    release: Option<u32>,
+
+    #[arg(long, value_name = "HZ")]
+    pitch: Option<u32>,
+
+    #[arg(long, value_name = "PERCENT")]
+    volume: Option<u32>,
+
+    /// square, sine or triangle
+    #[arg(long)]
+    waveform: Option<Waveform>,
+}
+
+#[derive(clap::Args)]
+struct RunArgs {
+    /// The ROM file or URL, the splash screen without one
+    rom: Option<String>,
+
+    /// Movie to replay, instead of running --frames
+    #[arg(requires = "rom")]
+    movie: Option<String>,
+
+    /// Frames to run
+    #[arg(long, value_name = "N", default_value_t = 600)]
+    frames: u64,
+
+    /// Log every memory access and register change as JSON Lines
+    #[arg(long, value_name = "FILE")]
+    audit: Option<String>,
+
+    /// Print every instruction run to stderr
+    #[arg(long)]
+    trace: bool,
+
+    /// Print the busiest opcodes and addresses when the run ends
+    #[arg(long)]
+    profile: bool,
+
+    /// Write the profile as folded stacks
+    #[arg(long, value_name = "FILE")]
+    profile_folded: Option<String>,
+
+    /// Run twice and compare the state hashes of every frame
+    #[arg(long)]
+    verify_determinism: bool,
+
+    /// Record the buzzer into a WAV file
+    #[arg(long = "audio", value_name = "FILE")]
+    wav: Option<String>,
+
+    /// Save the last screen as a PNG
+    #[arg(long, value_name = "FILE")]
+    screenshot: Option<String>,
+
+    /// Save every frame as an animated PNG
+    #[arg(long, value_name = "FILE")]
+    apng: Option<String>,
+
+    /// Serve Prometheus metrics on ADDR
+    #[arg(long, value_name = "ADDR")]
+    metrics: Option<String>,
+
+    /// Take key votes on ADDR, see `VoteServer`
+    #[arg(long, value_name = "ADDR", conflicts_with = "movie")]
+    crowd: Option<String>,
+
+    /// A Rhai script hooked into every frame
+    #[arg(long, value_name = "FILE")]
+    script: Option<String>,
+
+    #[command(flatten)]
+    machine: MachineArgs,
+
+    #[command(flatten)]
+    display: DisplayArgs,
+
+    #[command(flatten)]
+    audio: AudioArgs,
+}
+
+#[derive(clap::Args)]
+struct PlayArgs {
+    /// The ROM file or URL, the splash screen without one
+    rom: Option<String>,
+
+    /// Movie to replay
+    #[arg(requires = "rom")]
+    movie: Option<String>,
+
+    /// Reload the ROM when its file changes
+    #[arg(long)]
+    watch: bool,
+
+    /// Where the ROM browser lists ROMs
+    #[arg(long, value_name = "DIR")]
+    rom_dir: Option<PathBuf>,
+
+    /// Afterglow of the window, as FRAMES[,DECAY]
+    #[arg(long)]
+    phosphor: Option<PhosphorConfig>,
+
+    #[command(flatten)]
+    machine: MachineArgs,
+
+    #[command(flatten)]
+    display: DisplayArgs,
+
+    #[command(flatten)]
+    input: InputArgs,
+
+    #[command(flatten)]
+    audio: AudioArgs,
+}
+
+#[derive(clap::Args)]
+struct TuiArgs {
+    /// The ROM file or URL, the splash screen without one
+    rom: Option<String>,
+
+    /// Movie to replay
+    #[arg(requires = "rom")]
+    movie: Option<String>,
+
+    /// Reload the ROM when its file changes
+    #[arg(long)]
+    watch: bool,
+
+    /// A preset, or 2 or 4 colors as #RRGGBB separated by commas
+    #[arg(long)]
+    palette: Option<Palette>,
+
+    #[command(flatten)]
+    machine: MachineArgs,
+
+    #[command(flatten)]
+    input: InputArgs,
+
+    #[command(flatten)]
+    audio: AudioArgs,
+}
+
+#[derive(clap::Args)]
+struct RecordArgs {
+    /// The ROM file or URL
+    rom: String,
+
+    /// The movie to write
+    #[arg(short, long, value_name = "FILE")]
+    output: String,
+
+    /// Afterglow of the window, as FRAMES[,DECAY]
+    #[arg(long)]
+    phosphor: Option<PhosphorConfig>,
+
+    #[command(flatten)]
+    machine: MachineArgs,
+
+    #[command(flatten)]
+    display: DisplayArgs,
+
+    #[command(flatten)]
+    audio: AudioArgs,
+}
+
+#[derive(clap::Args)]
+struct BuildArgs {
+    /// The sources to link, assembler files and binaries
+    #[arg(required = true)]
+    sources: Vec<String>,
+
+    /// The ROM to write, the first source with a .ch8 extension by default
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<String>,
+
+    /// Reassemble and reload the ROM when a source changes
+    #[arg(long, conflicts_with_all = ["headless", "no_run"])]
+    watch: bool,
+
+    /// Build without running
+    #[arg(long, conflicts_with = "headless")]
+    no_run: bool,
+
+    /// Run headlessly and print a telemetry report, instead of playing
+    #[arg(long)]
+    headless: bool,
+
+    /// Frames to run headlessly
+    #[arg(long, value_name = "N", default_value_t = 600, requires = "headless")]
+    frames: u64,
+
+    /// Afterglow of the window, as FRAMES[,DECAY]
+    #[arg(long)]
+    phosphor: Option<PhosphorConfig>,
+
+    #[command(flatten)]
+    machine: MachineArgs,
+
+    #[command(flatten)]
+    display: DisplayArgs,
+
+    #[command(flatten)]
+    audio: AudioArgs,
+}
+
+#[derive(clap::Args)]
+struct AsmArgs {
+    /// The sources to link, assembler files and binaries
+    #[arg(required = true)]
+    sources: Vec<String>,
+
+    /// The ROM to write, the first source with a .ch8 extension by default
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<String>,
+}
+
+#[derive(clap::Args)]
+struct VerifyArgs {
+    /// The ROM file or URL
+    rom: String,
+
+    /// Frames to run
+    #[arg(long, value_name = "N", default_value_t = 600)]
+    frames: u64,
+
+    #[command(flatten)]
+    machine: MachineArgs,
+}
+
+#[derive(clap::Args)]
+struct TestRomArgs {
+    /// The ROM file or URL
+    rom: String,
+
+    /// State hash or snapshot file to check the screen against
+    #[arg(long, value_name = "HASH|FILE")]
+    expect: Option<String>,
+
+    /// Write the screen as a snapshot file
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<String>,
+
+    /// Frames to run
+    #[arg(long, value_name = "N", default_value_t = 600)]
+    frames: u64,
+
+    #[command(flatten)]
+    machine: MachineArgs,
+}
+
+#[derive(clap::Args)]
+struct QuirkdiffArgs {
+    /// The ROM file or URL
+    rom: String,
+
+    /// Movie to replay, instead of running --frames
+    #[arg(conflicts_with = "inputs")]
+    movie: Option<String>,
+
+    /// Quirks to compare against
+    #[arg(long, value_name = "SPEC")]
+    against: Quirks,
+
+    /// Keypad state of every frame, see `InputRecording`
+    #[arg(long, value_name = "FILE")]
+    inputs: Option<String>,
+
+    /// Frames to run
+    #[arg(long, value_name = "N", default_value_t = 600)]
+    frames: u64,
+
+    #[command(flatten)]
+    machine: MachineArgs,
+}
+
+#[derive(clap::Args)]
+#[command(group = clap::ArgGroup::new("trace").required(true).args(["reference", "output"]))]
+struct CompareArgs {
+    /// The ROM file or URL
+    rom: String,
+
+    /// Trace to compare the run against
+    #[arg(long, value_name = "FILE")]
+    reference: Option<String>,
+
+    /// Write the trace of the run
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<String>,
+
+    /// Frames to run
+    #[arg(long, value_name = "N", default_value_t = 600)]
+    frames: u64,
+
+    #[command(flatten)]
+    machine: MachineArgs,
+}
+
+#[derive(clap::Args)]
+struct SpritesArgs {
+    /// The ROM file or URL
+    rom: String,
+
+    /// Write the sprites to a PBM sprite sheet
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<String>,
+
+    /// Export the sprites as assembly
+    #[arg(long)]
+    asm: bool,
+}
+
+#[derive(clap::Args)]
+struct DisasmArgs {
+    /// The ROM file or URL
+    rom: String,
+
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<String>,
+
+    /// Annotate the disassembly with what a run did
+    #[arg(long)]
+    annotate: bool,
+
+    /// Frames to run when annotating
+    #[arg(long, value_name = "N", default_value_t = 600)]
+    frames: u64,
+
+    #[command(flatten)]
+    machine: MachineArgs,
+}
+
+#[derive(clap::Args)]
+struct HeatmapArgs {
+    /// The ROM file or URL
+    rom: String,
+
+    /// The PNG to write
+    #[arg(short, long, value_name = "FILE")]
+    output: String,
+
+    /// Frames to run
+    #[arg(long, value_name = "N", default_value_t = 600)]
+    frames: u64,
+
+    #[command(flatten)]
+    machine: MachineArgs,
+}
+
+#[derive(clap::Args)]
+struct WatchArgs {
+    /// The ROM file or URL
+    rom: String,
+
+    /// Expression to watch
+    #[arg(short = 'e', long = "expr", value_name = "EXPR", required = true)]
+    expressions: Vec<String>,
+
+    /// Frames to run
+    #[arg(long, value_name = "N", default_value_t = 600)]
+    frames: u64,
+
+    #[command(flatten)]
+    machine: MachineArgs,
+}
+
+#[derive(clap::Args)]
+struct DumpArgs {
+    /// The ROM file or URL
+    rom: String,
+
+    /// Where the dump starts: pc, i or an address
+    #[arg(long, value_name = "WHERE", default_value = "pc")]
+    at: DumpStart,
+
+    /// Pages to dump
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pages: usize,
+
+    /// Frames to run
+    #[arg(long, value_name = "N", default_value_t = 600)]
+    frames: u64,
+
+    #[command(flatten)]
+    machine: MachineArgs,
+}
+
+#[derive(clap::Args)]
+struct DebugArgs {
+    /// The ROM file or URL
+    rom: String,
+
+    /// Reload the ROM when its file changes
+    #[arg(long)]
+    watch: bool,
+
+    /// Keep the breakpoints when the ROM is reloaded
+    #[arg(long, requires = "watch")]
+    keep_breakpoints: bool,
+
+    #[command(flatten)]
+    machine: MachineArgs,
+}
+
+#[derive(clap::Args)]
+struct SoundtestArgs {
+    /// Write the sound test into a WAV file instead of playing it
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<String>,
+
+    /// Sound output
+    #[arg(long, value_name = "NAME", value_parser = ["cpal", "sdl"])]
+    sink: Option<String>,
+
+    #[command(flatten)]
+    audio: AudioArgs,
+}
+
+#[derive(clap::Args)]
+struct InfoArgs {
+    /// The ROM file or URL
+    rom: String,
+
+    /// ROM database extending the bundled one: a programs.json, a clone
+    /// of the community database, a URL, or `community` to download it
+    #[arg(long, value_name = "FILE|URL")]
+    rom_db: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+struct SelftestArgs {
+    /// Execution backend
+    #[arg(long, value_name = "NAME", default_value = "interpreter", value_parser = BACKENDS)]
+    backend: String,
+}
+
+#[derive(clap::Args)]
+struct BenchArgs {
+    /// Frames to run each workload
+    #[arg(long, value_name = "N", default_value_t = 600)]
+    frames: u64,
+}
+
+// `--quirks`: a configuration, or `auto` to detect one by running the ROM.
+#[derive(Clone, Copy)]
+enum QuirkSetting {
+    Auto,
+    Given(Quirks),
+}
+
+impl FromStr for QuirkSetting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(QuirkSetting::Auto),
+            spec => spec.parse().map(QuirkSetting::Given),
+        }
+    }
+}
+
+// `--at`: where `dump` starts.
+#[derive(Clone, Copy)]
+enum DumpStart {
+    Pc,
+    I,
+    Address(usize),
+}
+
+impl FromStr for DumpStart {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pc" => Ok(DumpStart::Pc),
+            "i" => Ok(DumpStart::I),
+            address => parse_address(address).map(DumpStart::Address),
+        }
+    }
+}
+
+// Frames run under each quirk preset by `--quirks auto`.
+const QUIRK_DETECTION_FRAMES: u64 = 120;
+
+// Instructions executed per second when the cartridge sets no tickrate.
+const INSTRUCTIONS_PER_SECOND: usize = 720;
+
+// Each cell of the 64x64 heatmap becomes an 8x8 block in the exported image.
+const HEATMAP_SCALE: u32 = 8;
+
+// The execution backends by name, compared by `bench` with the plain
+// interpreter first.
+const BACKENDS: [&str; 3] = ["interpreter", "cached", "blocks"];
+
+// Opcodes and addresses listed by the `--profile` report.
+const PROFILE_TOP: usize = 10;
+
+// Sound output of `soundtest` without --sink.
+const DEFAULT_SINK: &str = if cfg!(feature = "cpal") {
+    "cpal"
+} else {
+    "sdl"
+};
+
+// Bytes per page of the memory dump.
+const DUMP_PAGE_SIZE: usize = 0x100;
+
+// XO-CHIP patterns played by the sound test, one second each: a square wave
+// at the default pitch, a pulse wave an octave up, and white-ish noise.
+const SOUNDTEST_PATTERNS: [([u8; 16], u8); 3] = [
+    (
+        [
+            0xFF, 0xFF, 0, 0, 0xFF, 0xFF, 0, 0, 0xFF, 0xFF, 0, 0, 0xFF, 0xFF, 0, 0,
+        ],
+        64,
+    ),
+    (
+        [
+            0xF0, 0, 0xF0, 0, 0xF0, 0, 0xF0, 0, 0xF0, 0, 0xF0, 0, 0xF0, 0, 0xF0, 0,
+        ],
+        112,
+    ),
+    (
+        [
+            0x9D, 0x3A, 0x61, 0xE4, 0x2F, 0xB8, 0x47, 0x0C, 0xD3, 0x76, 0xA9, 0x15, 0x5E, 0xC2,
+            0x88, 0x3B,
+        ],
+        64,
+    ),
+];
+
+// The machine and frontend settings of a command, resolved from its
+// arguments, the configuration file and the cartridge. What a command has
+// no arguments for keeps the defaults of `options`.
+struct Options {
+    cartridge: Cartridge,
+    frames: u64,
+    seed: u64,
+    ips: Option<u32>,
+    backend: String,
+    fast_boot: u64,
+    mode: EmulationMode,
+    checks: Vec<(Check, FaultPolicy)>,
+    quirks: Quirks,
+    audit: Option<String>,
+    trace: bool,
+    profile: bool,
+    profile_folded: Option<String>,
+    audio: AudioConfig,
+
+    // --watch: reload the ROM on changes for `play` and `tui`, reassemble
+    // it from `sources` for `build`
+    watch: bool,
+
+    // The sources `build` linked the ROM from
+    #[cfg_attr(not(any(feature = "sdl", feature = "tui")), allow(dead_code))]
+    sources: Vec<PathBuf>,
+    metrics: Option<String>,
+
+    // Where `run` takes key votes, see `VoteServer`
+    crowd: Option<String>,
+
+    // A Rhai script hooked into headless runs
+    script: Option<String>,
+    load_address: LoadAddress,
+
+    // Low resolution instead of the variant's
+    resolution: Option<Resolution>,
+    apng: Option<String>,
+
+    // Let programs write to the interpreter area below 0x200
+    unprotected: bool,
+    font: FontSet,
+    scale: u32,
+
+    // The ROM file, for keeping quick saves next to it and reloading it
+    rom_path: Option<PathBuf>,
+
+    // Where the window's ROM browser lists ROMs
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    rom_dir: Option<PathBuf>,
+
+    // Movie replayed by headless runs
+    movie: Option<String>,
+
+    // The configuration file, for the settings without a flag
+    #[cfg_attr(not(any(feature = "sdl", feature = "tui")), allow(dead_code))]
+    config: Config,
+
+    // Colors from --palette, the cartridge or the configuration file
+    palette: Option<Palette>,
+
+    // From the ROM's cheat file or --cheats, and --cheat
+    cheats: Cheats,
+
+    // Afterglow of the window from --phosphor
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    phosphor: Option<PhosphorConfig>,
+
+    // Movie played as a demo by `play` and `tui` after `idle` seconds
+    // without a key pressed, see `AttractMode`
+    #[cfg_attr(not(any(feature = "sdl", feature = "tui")), allow(dead_code))]
+    attract: Option<String>,
+    #[cfg_attr(not(any(feature = "sdl", feature = "tui")), allow(dead_code))]
+    idle: u64,
+}
+
+impl Options {
+    // Cartridges may carry their own tickrate, unless `--ips` overrides it.
+    // Otherwise the speed stays the same at either timer rate, with more
+    // instructions per PAL frame.
+    fn cycles_per_frame(&self) -> usize {
+        self.cycles_per_frame_of(&self.cartridge)
+    }
+
+    fn cycles_per_frame_of(&self, cartridge: &Cartridge) -> usize {
+        let hz = cartridge.timer_rate.hz() as usize;
+        match (self.ips, cartridge.tickrate) {
+            (Some(ips), _) => ((ips as usize + hz / 2) / hz).max(1),
+            (None, Some(rate)) => rate as usize,
+            (None, None) => (INSTRUCTIONS_PER_SECOND + hz / 2) / hz,
+        }
+    }
+
+    fn frames(mut self, frames: u64) -> Options {
+        self.frames = frames;
+        self
+    }
+
+    // --palette and --scale win over the cartridge and the configuration
+    // file.
+    fn display(mut self, args: &DisplayArgs) -> Options {
+        self.palette = args.palette.or(self.palette);
+        self.scale = args.scale.unwrap_or(self.scale);
+        self
+    }
+
+    fn input(mut self, args: &InputArgs) -> Options {
+        self.attract = args.attract.clone();
+        self.idle = args.idle;
+        self
+    }
+
+    fn audio(mut self, args: &AudioArgs) -> Options {
+        self.audio = audio_config(args);
+        self
+    }
+
+    // What `run` adds to a headless run: a movie, instruments and hooks.
+    fn headless(mut self, args: &RunArgs) -> Options {
+        self.frames = args.frames;
+        self.movie = args.movie.clone();
+        self.audit = args.audit.clone();
+        self.trace = args.trace;
+        self.profile = args.profile;
+        self.profile_folded = args.profile_folded.clone();
+        self.metrics = args.metrics.clone();
+        self.crowd = args.crowd.clone();
+        self.script = args.script.clone();
+        self.apng = args.apng.clone();
+        self
+    }
+}
+
 fn main() {
-    println!("Hello, world!");
+    let cli = Cli::parse();
+    let config = load_config(&cli);
+    match &cli.command {
+        Command::Run(args) => run(config, args),
+        Command::Play(args) => play(config, args),
+        Command::Tui(args) => play_in_terminal(config, args),
+        Command::Record(args) => record(config, args),
+        Command::Build(args) => build(config, args),
+        Command::Asm(args) => asm(args),
+        Command::Verify(args) => verify(config, args),
+        Command::TestRom(args) => test_rom(config, args),
+        Command::Quirkdiff(args) => quirkdiff(config, args),
+        Command::Compare(args) => compare(config, args),
+        Command::Sprites(args) => sprites(args),
+        Command::Disasm(args) => disasm(config, args),
+        Command::Heatmap(args) => heatmap(config, args),
+        Command::Watch(args) => watch(config, args),
+        Command::Dump(args) => dump(config, args),
+        Command::Debug(args) => debug(config, args),
+        Command::Soundtest(args) => soundtest(args),
+        Command::Selftest(args) => run_selftest(args),
+        Command::Bench(args) => run_bench(args),
+        Command::Info(args) => info(&config, args),
+    }
+}
+
+// Run the ROM headlessly and print the telemetry report on exit.
+fn run(config: Config, args: &RunArgs) {
+    let options = options(config, args.rom.as_deref(), &args.machine)
+        .display(&args.display)
+        .audio(&args.audio)
+        .headless(args);
+    if args.verify_determinism {
+        check_determinism(&options);
+    }
+    let cartridge = &options.cartridge;
+    if !cartridge.title.is_empty() {
+        eprintln!("{} by {}", cartridge.title, cartridge.author);
+    }
+    let chip8 = match &args.wav {
+        Some(path) => run_with_audio(&options, path),
+        None => run_headless(&options),
+    };
+    if let Some(path) = &args.screenshot {
+        let palette = options.palette.unwrap_or_default();
+        fs::write(
+            path,
+            capture::screenshot(chip8.display(), &palette, options.scale),
+        )
+        .unwrap_or_else(|e| fail(&format!("Failed to write {}: {}", path, e)));
+    }
+    if let (Some(path), Some(profile)) = (&options.profile_folded, chip8.profile()) {
+        fs::write(path, profile.to_folded())
+            .unwrap_or_else(|e| fail(&format!("Failed to write {}: {}", path, e)));
+    }
+    if let (true, Some(profile)) = (options.profile, chip8.profile()) {
+        eprint!("{}", profile.to_report(PROFILE_TOP));
+    }
+    report(&chip8);
+}
+
+// Print how the run ended, then the telemetry report.
+fn report(chip8: &Chip8) {
+    if let Some(fault) = chip8.fault() {
+        let state = if chip8.is_halted() {
+            "Halted on fault"
+        } else {
+            "Last fault"
+        };
+        eprintln!("{}: {}", state, fault);
+        if let Some(history) = chip8.history().filter(|_| chip8.is_halted()) {
+            eprint!("Last {} instructions:\n{}", history.len(), history);
+        }
+    } else if chip8.is_halted() {
+        eprintln!("Program exited");
+    }
+    println!("{}", chip8.telemetry().to_json());
+}
+
+// Play the ROM in a window, replaying the movie if one was given.
+fn play(config: Config, args: &PlayArgs) {
+    let mut options = options(config, args.rom.as_deref(), &args.machine)
+        .display(&args.display)
+        .input(&args.input)
+        .audio(&args.audio);
+    options.watch = args.watch;
+    options.rom_dir = args.rom_dir.clone().or(options.rom_dir);
+    options.phosphor = args.phosphor;
+    let mut chip8 = boot(&options);
+    let mut movie = args
+        .movie
+        .as_deref()
+        .map(|path| replay(&options, path, &mut chip8));
+    window(&options, &mut chip8, movie.as_mut());
+}
+
+// Play the ROM in the terminal, replaying the movie if one was given.
+fn play_in_terminal(config: Config, args: &TuiArgs) {
+    let mut options = options(config, args.rom.as_deref(), &args.machine)
+        .input(&args.input)
+        .audio(&args.audio);
+    options.watch = args.watch;
+    options.palette = args.palette.or(options.palette);
+    let mut chip8 = boot(&options);
+    let mut movie = args
+        .movie
+        .as_deref()
+        .map(|path| replay(&options, path, &mut chip8));
+    terminal(&options, &mut chip8, movie.as_mut());
+}
+
+// Play the ROM in a window, or in the terminal without the `sdl` feature.
+fn frontend(options: &Options, chip8: &mut Chip8, movie: Option<&mut MovieSession>) {
+    if cfg!(feature = "sdl") {
+        window(options, chip8, movie);
+    } else {
+        terminal(options, chip8, movie);
+    }
+}
+
+// Play the ROM as `frontend` does, recording the keypad into a movie.
+fn record(config: Config, args: &RecordArgs) {
+    let mut options = options(config, Some(&args.rom), &args.machine)
+        .display(&args.display)
+        .audio(&args.audio);
+    options.phosphor = args.phosphor;
+    let movie = Movie::new(
+        &options.cartridge.rom,
+        options.seed,
+        options.cycles_per_frame(),
+    );
+    let mut session = MovieSession::record(movie);
+    let mut chip8 = boot(&options);
+    frontend(&options, &mut chip8, Some(&mut session));
+    let movie = session.into_movie();
+    let path = &args.output;
+    fs::write(path, movie.to_string())
+        .unwrap_or_else(|e| fail(&format!("Failed to write {}: {}", path, e)));
+    eprintln!("Recorded {} frames to {}", movie.frames(), path);
+}
+
+// Read a movie for replaying on a machine booted with its ROM.
+fn replay(options: &Options, path: &str, chip8: &mut Chip8) -> MovieSession {
+    MovieSession::replay(read_movie(options, path), chip8)
+}
+
+// Read a movie, checking that it was recorded with the ROM.
+fn read_movie(options: &Options, path: &str) -> Movie {
+    let text = fs::read_to_string(path)
+        .unwrap_or_else(|e| fail(&format!("Failed to read {}: {}", path, e)));
+    let movie: Movie = text
+        .parse()
+        .unwrap_or_else(|e| fail(&format!("Invalid movie {}: {}", path, e)));
+    if let Err(e) = movie.check_rom(&options.cartridge.rom) {
+        fail(&format!("Cannot replay {}: {}", path, e));
+    }
+    movie
+}
+
+// The demo of `--attract`, checked against the ROM like replayed movies.
+#[cfg(any(feature = "sdl", feature = "tui"))]
+fn attract_mode(options: &Options) -> Option<AttractMode> {
+    let path = options.attract.as_deref()?;
+    Some(AttractMode::from_movie(
+        read_movie(options, path),
+        options.idle,
+    ))
+}
+
+#[cfg(feature = "sdl")]
+fn window(options: &Options, chip8: &mut Chip8, movie: Option<&mut MovieSession>) {
+    let cartridge = &options.cartridge;
+    let mut config = SdlConfig {
+        scale: options.scale,
+        cycles_per_frame: options.cycles_per_frame(),
+        frame_rate: cartridge.timer_rate.hz(),
+        audio: options.audio,
+        rom_path: options.rom_path.clone(),
+        rom_dir: options.rom_dir.clone(),
+        phosphor: options.phosphor,
+        watch: options.watch,
+        sources: options.sources.clone(),
+        attract: attract_mode(options),
+        ..SdlConfig::default()
+    };
+    if let Some(palette) = options.palette {
+        config.palette = palette;
+    }
+    options.config.apply_keys(&mut config.keymap);
+    if let Some(movie) = &movie {
+        config.cycles_per_frame = movie.movie().cycles_per_frame;
+    }
+    // Dropped and picked ROMs run with the same options. Plain ROMs keep
+    // the variant and timer rate of the one on the command line, cartridges
+    // bring their own.
+    let load = |path: &Path| -> Result<LoadedRom, String> {
+        let bytes =
+            fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let mut loaded = Cartridge::load(&bytes)
+            .map_err(|e| format!("Failed to load {}: {}", path.display(), e))?;
+        if loaded == Cartridge::from_rom(&bytes) {
+            loaded.variant = cartridge.variant;
+            loaded.timer_rate = cartridge.timer_rate;
+        }
+        let chip8 = boot_cartridge(options, &loaded)
+            .map_err(|e| format!("Failed to load {}: {}", path.display(), e))?;
+        let mut title = window_title(&loaded);
+        if loaded.title.is_empty() {
+            if let Some(name) = path.file_name() {
+                title = format!("{} - CHIP-8", name.to_string_lossy());
+            }
+        }
+        Ok(LoadedRom {
+            chip8,
+            title,
+            cycles_per_frame: options.cycles_per_frame_of(&loaded),
+            frame_rate: loaded.timer_rate.hz(),
+        })
+    };
+    if let Err(e) = sdl::run(chip8, &window_title(cartridge), &config, movie, &load) {
+        fail(&format!("Failed to play: {}", e));
+    }
+}
+
+#[cfg(feature = "sdl")]
+fn window_title(cartridge: &Cartridge) -> String {
+    if cartridge.title.is_empty() {
+        "CHIP-8".to_string()
+    } else {
+        format!("{} - CHIP-8", cartridge.title)
+    }
+}
+
+#[cfg(not(feature = "sdl"))]
+fn window(_options: &Options, _chip8: &mut Chip8, _movie: Option<&mut MovieSession>) {
+    fail("play: built without the `sdl` feature");
+}
+
+#[cfg(feature = "tui")]
+fn terminal(options: &Options, chip8: &mut Chip8, movie: Option<&mut MovieSession>) {
+    let cartridge = &options.cartridge;
+    let mut config = TuiConfig {
+        cycles_per_frame: options.cycles_per_frame(),
+        frame_rate: cartridge.timer_rate.hz(),
+        audio: options.audio,
+        rom_path: options.rom_path.clone(),
+        watch: options.watch,
+        sources: options.sources.clone(),
+        attract: attract_mode(options),
+        ..TuiConfig::default()
+    };
+    if !cartridge.title.is_empty() {
+        config.title = cartridge.title.clone();
+    }
+    config.palette = options.palette;
+    options.config.apply_keys(&mut config.keymap);
+    if let Some(movie) = &movie {
+        config.cycles_per_frame = movie.movie().cycles_per_frame;
+    }
+    // Without sound output the terminal bell stands in for the buzzer.
+    #[cfg(feature = "cpal")]
+    let buzzer = CpalBuzzer::open(&options.audio)
+        .map(|buzzer| Box::new(buzzer) as Box<dyn Buzzer>)
+        .map_err(|e| eprintln!("No sound: {}", e))
+        .ok();
+    #[cfg(not(feature = "cpal"))]
+    let buzzer = None;
+    if let Err(e) = tui::run(chip8, &config, buzzer, movie) {
+        fail(&format!("Failed to play: {}", e));
+    }
+}
+
+#[cfg(not(feature = "tui"))]
+fn terminal(_options: &Options, _chip8: &mut Chip8, _movie: Option<&mut MovieSession>) {
+    fail("tui: built without the `tui` feature");
+}
+
+// Run the ROM headlessly, recording the buzzer into a WAV file.
+fn run_with_audio(options: &Options, path: &str) -> Chip8 {
+    let mut chip8 = boot(options);
+    let mut output = WavOutput::new(options.audio);
+    output
+        .mixer
+        .set_frame_rate(options.cartridge.timer_rate.hz());
+    let mut monitor = Monitor::new(options);
+    let mut crowd = Crowd::new(options);
+    let mut scripter = Scripter::new(options);
+    let mut recorder = frame_recorder(options);
+    for _ in 0..options.frames {
+        crowd.before_frame(&mut chip8);
+        let started = Instant::now();
+        if !scripter.run_frame(&mut chip8, options.cycles_per_frame()) {
+            break;
+        }
+        output.play(chip8.sound_active(), chip8.voice());
+        monitor.frame(&chip8, started, Some(output.queue.len()));
+        record_frame(&mut recorder, &chip8);
+    }
+    output.save(path);
+    save_recording(options, recorder);
+    chip8
+}
+
+// The audio pipeline, from the mixer to a WAV recording.
+struct WavOutput {
+    mixer: Mixer,
+    queue: SampleQueue,
+    recorder: WavRecorder,
+    frame: Vec<f32>,
+    buffer: Vec<f32>,
+}
+
+impl WavOutput {
+    fn new(config: AudioConfig) -> WavOutput {
+        WavOutput {
+            mixer: Mixer::new(config),
+            queue: SampleQueue::new(&config),
+            recorder: WavRecorder::new(&config),
+            frame: Vec::new(),
+            buffer: vec![0.0; config.buffer_size as usize],
+        }
+    }
+
+    fn save(mut self, path: &str) {
+        let rest = self.queue.len();
+        self.queue.pull(&mut self.buffer[..rest]);
+        self.recorder.write(&self.buffer[..rest]);
+        fs::write(path, self.recorder.to_wav())
+            .unwrap_or_else(|e| fail(&format!("Failed to write {}: {}", path, e)));
+    }
+}
+
+impl AudioBackend for WavOutput {
+    // Render one frame and pass every full buffer on to the recorder.
+    fn play(&mut self, sounding: bool, voice: Voice) {
+        self.frame.clear();
+        self.mixer.set_voice(voice);
+        self.mixer.render_frame(sounding, &mut self.frame);
+        self.queue.push(&self.frame);
+        while self.queue.len() >= self.buffer.len() {
+            self.queue.pull(&mut self.buffer);
+            self.recorder.write(&self.buffer);
+        }
+    }
+}
+
+// Write the ROM assembled from the sources, then play it as `play` or
+// `tui` would, or run it headlessly as `run` would.
+fn build(config: Config, args: &BuildArgs) {
+    let rom = assemble(&args.sources);
+    let path = write_rom(&rom, &args.sources, args.output.as_deref());
+    let mut options = options_for(
+        config,
+        Cartridge::from_rom(&rom),
+        Some(&path),
+        &args.machine,
+    )
+    .frames(args.frames)
+    .display(&args.display)
+    .audio(&args.audio);
+    options.phosphor = args.phosphor;
+    options.watch = args.watch;
+    options.sources = args.sources.iter().map(PathBuf::from).collect();
+    if args.headless {
+        report(&run_headless(&options));
+    } else if !args.no_run {
+        let mut chip8 = boot(&options);
+        frontend(&options, &mut chip8, None);
+    }
+}
+
+// Write the ROM assembled from the sources.
+fn asm(args: &AsmArgs) {
+    write_rom(
+        &assemble(&args.sources),
+        &args.sources,
+        args.output.as_deref(),
+    );
+}
+
+// Link the sources into a ROM.
+fn assemble(sources: &[String]) -> Vec<u8> {
+    let paths: Vec<&Path> = sources.iter().map(Path::new).collect();
+    asm::link_files(&paths)
+        .unwrap_or_else(|e| fail(&e.to_string()))
+        .rom
+}
+
+// Write the ROM to `output`, or next to the first source, returning where
+// it went.
+fn write_rom(rom: &[u8], sources: &[String], output: Option<&str>) -> String {
+    let path = match output {
+        Some(path) => path.to_string(),
+        None => Path::new(&sources[0])
+            .with_extension("ch8")
+            .display()
+            .to_string(),
+    };
+    fs::write(&path, rom).unwrap_or_else(|e| fail(&format!("Failed to write {}: {}", path, e)));
+    eprintln!("Wrote {} ({} bytes)", path, rom.len());
+    path
+}
+
+// Run the ROM headlessly and print the final state hash, so that scripts can
+// compare it against a known good value.
+fn verify(config: Config, args: &VerifyArgs) {
+    let options = options(config, Some(&args.rom), &args.machine).frames(args.frames);
+    let chip8 = run_headless(&options);
+    println!("0x{:016X}", chip8.state_hash());
+}
+
+// Run the ROM headlessly and compare the screen against a snapshot file or
+// its hash, exiting with 1 on a mismatch. Without `--expect`, print them to
+// start from instead.
+fn test_rom(config: Config, args: &TestRomArgs) {
+    let options = options(config, Some(&args.rom), &args.machine).frames(args.frames);
+    let chip8 = run_headless(&options);
+    let display = chip8.display();
+    let found = snapshot::hash(display);
+    if let Some(path) = &args.output {
+        fs::write(path, snapshot::to_text(display))
+            .unwrap_or_else(|e| fail(&format!("Failed to write {}: {}", path, e)));
+    }
+    let Some(expect) = &args.expect else {
+        println!("0x{:016X}", found);
+        print!("{}", snapshot::to_text(display));
+        return;
+    };
+    let matches = match expect.strip_prefix("0x") {
+        Some(hex) => {
+            u64::from_str_radix(hex, 16)
+                .unwrap_or_else(|_| usage_error(&format!("Invalid hash: {}", expect)))
+                == found
+        }
+        None => {
+            let text = fs::read_to_string(expect)
+                .unwrap_or_else(|e| fail(&format!("Failed to read {}: {}", expect, e)));
+            snapshot::matches(display, &text)
+        }
+    };
+    if !matches {
+        eprint!(
+            "Screen 0x{:016X} does not match {}\n{}",
+            found,
+            expect,
+            snapshot::to_text(display)
+        );
+        process::exit(1);
+    }
+    println!("ok 0x{:016X}", found);
+}
+
+// Run the ROM under two quirk configurations and report the first
+// instruction where they diverge.
+fn quirkdiff(config: Config, args: &QuirkdiffArgs) {
+    let options = options(config, Some(&args.rom), &args.machine).frames(args.frames);
+    let against = args.against;
+    let differences = options.quirks.differences(&against);
+    if differences.is_empty() {
+        usage_error("The two quirk configurations are identical");
+    }
+    println!("Comparing quirks: {}", differences.join(", "));
+    let movie = args.movie.as_ref().map(|path| read_movie(&options, path));
+    let [first, second] = [options.quirks, against].map(|quirks| {
+        let mut chip8 = boot(&options);
+        chip8.set_quirks(quirks);
+        if let Some(movie) = &movie {
+            chip8.set_seed(movie.seed);
+        }
+        chip8
+    });
+    // A movie replaces --frames with its own length.
+    let recording = match (&movie, &args.inputs) {
+        (Some(movie), _) => movie.recording.clone(),
+        (None, Some(path)) => read_inputs(path),
+        (None, None) => InputRecording::new(),
+    };
+    let (frames, cycles) = match &movie {
+        Some(movie) => (movie.frames() as u64, movie.cycles_per_frame),
+        None => (options.frames, options.cycles_per_frame()),
+    };
+    let divergence =
+        determinism::find_quirk_divergence(first, second, &recording.frames, frames, cycles);
+    match divergence {
+        Some(d) => {
+            println!(
+                "Diverged at frame {}, instruction {}: PC 0x{:03X} opcode 0x{:04X}",
+                d.frame, d.instruction, d.program_counter, d.opcode
+            );
+            if let Some(quirk) = Quirks::affecting(d.opcode) {
+                println!("The ROM depends on the `{}` quirk.", quirk);
+            }
+        }
+        None => println!("No divergence over {} frames.", frames),
+    }
+}
+
+// Read the keypad state of every frame, see `InputRecording`.
+fn read_inputs(path: &str) -> InputRecording {
+    fs::read_to_string(path)
+        .unwrap_or_else(|e| fail(&format!("Failed to read {}: {}", path, e)))
+        .parse()
+        .unwrap_or_else(|e| fail(&format!("Invalid inputs {}: {}", path, e)))
+}
+
+// Run the ROM along a reference trace and report the first step where they
+// disagree, or record the trace of this run with -o.
+fn compare(config: Config, args: &CompareArgs) {
+    let options = options(config, Some(&args.rom), &args.machine).frames(args.frames);
+    let mut chip8 = boot(&options);
+    let cycles = options.cycles_per_frame();
+    if let Some(output) = &args.output {
+        let trace = ReferenceTrace::record(&mut chip8, options.frames, cycles);
+        fs::write(output, trace.to_json())
+            .unwrap_or_else(|e| fail(&format!("Failed to write {}: {}", output, e)));
+        eprintln!("Wrote {} steps to {}", trace.steps.len(), output);
+        return;
+    }
+    let path = args
+        .reference
+        .as_deref()
+        .expect("clap requires --reference or -o");
+    let text = fs::read_to_string(path)
+        .unwrap_or_else(|e| fail(&format!("Failed to read {}: {}", path, e)));
+    let trace: ReferenceTrace = text
+        .parse()
+        .unwrap_or_else(|e| fail(&format!("{}: {}", path, e)));
+    match trace.compare(&mut chip8, cycles) {
+        Ok(steps) => println!("All {} steps match {}", steps, path),
+        Err(divergence) => {
+            eprintln!("{}", divergence);
+            process::exit(1);
+        }
+    }
+}
+
+// List the sprites found in the ROM, print them as assembler source, or write
+// them to a PBM sprite sheet.
+fn sprites(args: &SpritesArgs) {
+    let found = sprites::scan(&load_rom(&args.rom).rom, 0x200);
+    match &args.output {
+        Some(path) => {
+            fs::write(path, sprites::sheet_pbm(&found, 8))
+                .unwrap_or_else(|e| fail(&format!("Failed to write {}: {}", path, e)));
+            eprintln!("Wrote {} sprites to {}", found.len(), path);
+        }
+        None if args.asm => {
+            for sprite in &found {
+                println!(
+                    "{}",
+                    sprite.to_db(&format!("sprite_{:03x}", sprite.address))
+                );
+            }
+        }
+        None => {
+            for sprite in &found {
+                println!(
+                    "0x{:03X} ({}x{})",
+                    sprite.address,
+                    sprite.width,
+                    sprite.height()
+                );
+                println!("{}", sprite.to_ascii());
+            }
+        }
+    }
+}
+
+// Print the ROM as assembler source, with runtime information from a
+// headless run as comments when annotating.
+fn disasm(config: Config, args: &DisasmArgs) {
+    let options = options(config, Some(&args.rom), &args.machine).frames(args.frames);
+    let trace = args.annotate.then(|| {
+        let mut chip8 = boot(&options);
+        Trace::record(&mut chip8, options.frames, options.cycles_per_frame())
+    });
+    let listing = disasm::listing(&options.cartridge.rom, trace.as_ref());
+    match &args.output {
+        Some(path) => {
+            fs::write(path, listing)
+                .unwrap_or_else(|e| fail(&format!("Failed to write {}: {}", path, e)));
+            eprintln!("Wrote {}", path);
+        }
+        None => print!("{}", listing),
+    }
+}
+
+// Run the ROM headlessly and export its memory access heatmap as a PNG.
+fn heatmap(config: Config, args: &HeatmapArgs) {
+    let options = options(config, Some(&args.rom), &args.machine).frames(args.frames);
+    let path = &args.output;
+    let mut chip8 = boot(&options);
+    chip8.enable_access_map();
+    run_frames(&mut chip8, &options);
+
+    let rgb = chip8
+        .access_map()
+        .map(AccessMap::to_rgb)
+        .unwrap_or_default();
+    let (size, scale) = (heatmap::GRID_SIZE as u32, HEATMAP_SCALE);
+    let image = png::scale_rgb(size, size, &rgb, scale);
+    fs::write(path, png::encode_rgb(size * scale, size * scale, &image))
+        .unwrap_or_else(|e| fail(&format!("Failed to write {}: {}", path, e)));
+}
+
+// Run the ROM headlessly and print the watch expressions after every frame.
+fn watch(config: Config, args: &WatchArgs) {
+    let mut watches = WatchList::new();
+    for source in &args.expressions {
+        watches
+            .add(source)
+            .unwrap_or_else(|e| usage_error(&format!("Invalid expression {:?}: {}", source, e)));
+    }
+
+    let options = options(config, Some(&args.rom), &args.machine).frames(args.frames);
+    let mut chip8 = boot(&options);
+    for frame in 0..options.frames {
+        chip8.run_frame(options.cycles_per_frame());
+        let values: Vec<String> = watches
+            .evaluate(&chip8)
+            .into_iter()
+            .map(|(source, value)| match value {
+                Some(v) => format!("{} = 0x{:X}", source, v),
+                None => format!("{} = ?", source),
+            })
+            .collect();
+        println!("{:>6}: {}", frame, values.join(", "));
+    }
+}
+
+// Run the ROM headlessly, then print a hex dump of memory around PC, I or
+// a given address.
+fn dump(config: Config, args: &DumpArgs) {
+    let options = options(config, Some(&args.rom), &args.machine).frames(args.frames);
+    let chip8 = run_headless(&options);
+    let pc = chip8.register(Register::PC) as usize;
+    let i = chip8.register(Register::I) as usize;
+    let at = match args.at {
+        DumpStart::Pc => pc,
+        DumpStart::I => i,
+        DumpStart::Address(address) => address,
+    };
+
+    let memory = chip8.memory();
+    let mut view = MemoryView::new(memory.len(), DUMP_PAGE_SIZE);
+    view.jump_to(at);
+    for page in 0..args.pages {
+        if page > 0 {
+            if view.page() + 1 == view.page_count() {
+                break;
+            }
+            view.next_page();
+        }
+        print!("{}", view.render(memory, pc, i));
+    }
+}
+
+// Run the ROM under the debugger, reading its commands from stdin until
+// `q` or the end of the input.
+// With --watch, the ROM is reloaded before the next command once its file
+// changed, and `mark` and `unmark` set and clear the point reloads go back
+// to, see `LiveReload`.
+fn debug(config: Config, args: &DebugArgs) {
+    let options = options(config, Some(&args.rom), &args.machine);
+    let mut debugger = Debugger::new(boot(&options), options.cycles_per_frame());
+    let mut watcher = options
+        .rom_path
+        .as_ref()
+        .filter(|_| args.watch)
+        .map(LiveReload::new);
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(chip8) ");
+        let _ = io::stdout().flush();
+        let Some(Ok(line)) = lines.next() else {
+            break;
+        };
+        let line = line.trim();
+        if line == "q" || line == "quit" {
+            break;
+        }
+        if let Some(watcher) = &mut watcher {
+            match watcher.poll(debugger.chip8_mut()) {
+                Some(Ok(size)) => {
+                    if !args.keep_breakpoints {
+                        debugger.clear_breakpoints();
+                    }
+                    println!("Reloaded {} ({} bytes)", watcher.path().display(), size);
+                }
+                Some(Err(message)) => println!("error: {}", message),
+                None => {}
+            }
+            match line {
+                "mark" => {
+                    watcher.set_mark(debugger.chip8());
+                    continue;
+                }
+                "unmark" => {
+                    watcher.clear_mark();
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        match debugger.command(line) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => println!("{}", output),
+            Err(message) => println!("error: {}", message),
+        }
+    }
+}
+
+// Play a tone sweep followed by a few XO-CHIP patterns through the audio
+// pipeline, without a ROM, to check the audio settings: in real time through
+// the sound output, or into a WAV file with -o.
+fn soundtest(args: &SoundtestArgs) {
+    let config = audio_config(&args.audio);
+    let Some(path) = &args.output else {
+        let buzzer = open_buzzer(args.sink.as_deref().unwrap_or(DEFAULT_SINK), &config)
+            .unwrap_or_else(|e| fail(&format!("No sound: {}", e)));
+        eprintln!(
+            "Playing through {} ({}Hz, buffer {}, latency {}ms)",
+            buzzer.name(),
+            config.sample_rate,
+            config.buffer_size,
+            config.latency_ms
+        );
+        let mut speaker = Speaker::new(Mixer::new(config), buzzer);
+        let frame = Duration::from_secs(1) / 60;
+        let mut next = Instant::now();
+        for (pitch, voice) in soundtest_frames() {
+            if let Some(pitch) = pitch {
+                speaker.mixer_mut().set_pitch(pitch);
+            }
+            speaker.play(true, voice);
+            next += frame;
+            thread::sleep(next.saturating_duration_since(Instant::now()));
+        }
+        // Let the device play what is still queued.
+        thread::sleep(Duration::from_millis(config.latency_ms as u64));
+        return;
+    };
+
+    let mut output = WavOutput::new(config);
+    for (pitch, voice) in soundtest_frames() {
+        if let Some(pitch) = pitch {
+            output.mixer.set_pitch(pitch);
+        }
+        output.play(true, voice);
+    }
+    output.save(path);
+    eprintln!(
+        "Wrote {} ({}Hz, buffer {}, latency {}ms)",
+        path, config.sample_rate, config.buffer_size, config.latency_ms
+    );
+}
+
+// The frames of the sound test, with the pitch to switch the beep to: a two
+// second sweep from 110Hz to 1760Hz, four octaves, then the patterns.
+fn soundtest_frames() -> impl Iterator<Item = (Option<f32>, Voice)> {
+    let sweep = (0..120).map(|step| {
+        let pitch = 110.0 * 2f32.powf(step as f32 / 30.0);
+        (Some(pitch), Voice::Beep)
+    });
+    let patterns = SOUNDTEST_PATTERNS
+        .into_iter()
+        .flat_map(|(bits, pitch)| std::iter::repeat_n((None, Voice::Pattern { bits, pitch }), 60));
+    sweep.chain(patterns)
+}
+
+// Open the sound output named `sink`, as built into this binary.
+#[cfg_attr(not(any(feature = "cpal", feature = "sdl")), allow(unused_variables))]
+fn open_buzzer(sink: &str, config: &AudioConfig) -> Result<Box<dyn Buzzer>, String> {
+    match sink {
+        #[cfg(feature = "cpal")]
+        "cpal" => Ok(Box::new(CpalBuzzer::open(config)?)),
+        #[cfg(feature = "sdl")]
+        "sdl" => {
+            let audio = sdl2::init()?.audio()?;
+            Ok(Box::new(SdlBuzzer::open(&audio, config)?))
+        }
+        #[cfg(not(feature = "cpal"))]
+        "cpal" => Err("built without the `cpal` feature".to_string()),
+        #[cfg(not(feature = "sdl"))]
+        "sdl" => Err("built without the `sdl` feature".to_string()),
+        _ => Err(format!("unknown sink `{}`, expected cpal or sdl", sink)),
+    }
+}
+
+// Run the built-in opcode tests under every combination of quirks and print
+// the pass/fail matrix, exiting with an error if any failed.
+fn run_selftest(args: &SelftestArgs) {
+    let report = selftest::run(|| Chip8::with_backend(backend(&args.backend)));
+    println!("{}", report);
+    if report.failed() > 0 {
+        process::exit(1);
+    }
+}
+
+// Time the benchmark workloads under every backend and print the
+// instructions per second achieved on this machine, see `bench`.
+fn run_bench(args: &BenchArgs) {
+    let report = bench::run(&BACKENDS, args.frames, |name| {
+        Chip8::with_backend(backend(name))
+    });
+    println!("{}", report);
+}
+
+// Print what is known about the ROM: the cartridge settings, the hashes
+// movies and ROM databases identify it by, and what the database says.
+fn info(config: &Config, args: &InfoArgs) {
+    let cartridge = &load_rom(&args.rom);
+    let or_unknown = |text: &str| match text {
+        "" => "unknown".to_string(),
+        text => text.to_string(),
+    };
+    let colors = |[background, foreground]: [[u8; 3]; 2]| {
+        format!(
+            "#{:02X}{:02X}{:02X} on #{:02X}{:02X}{:02X}",
+            foreground[0],
+            foreground[1],
+            foreground[2],
+            background[0],
+            background[1],
+            background[2]
+        )
+    };
+    println!("Title:      {}", or_unknown(&cartridge.title));
+    println!("Author:     {}", or_unknown(&cartridge.author));
+    println!("Variant:    {}", cartridge.variant.name());
+    println!("Timer rate: {}Hz", cartridge.timer_rate.hz());
+    match cartridge.tickrate {
+        Some(rate) => println!("Tickrate:   {} instructions per frame", rate),
+        None => println!("Tickrate:   default"),
+    }
+    match cartridge.palette {
+        Some(palette) => println!("Palette:    {}", colors(palette)),
+        None => println!("Palette:    default"),
+    }
+    println!("Size:       {} bytes", cartridge.rom.len());
+    println!("Hash:       {:016X}", movie::rom_hash(&cartridge.rom));
+    println!("SHA-1:      {}", rominfo::sha1_hex(&cartridge.rom));
+
+    let database = rom_database(args.rom_db.as_deref().or(config.rom_db.as_deref()));
+    let Some(found) = database.lookup(&cartridge.rom) else {
+        println!("Not in the ROM database");
+        return;
+    };
+    println!("In the ROM database:");
+    println!("  Title:    {}", or_unknown(&found.title));
+    println!("  Authors:  {}", or_unknown(&found.authors.join(", ")));
+    if let Some(release) = &found.release {
+        println!("  Release:  {}", release);
+    }
+    if let Some(file) = &found.file {
+        println!("  File:     {}", file);
+    }
+    match (&found.platform, found.variant) {
+        (Some(platform), Some(variant)) => {
+            println!("  Platform: {} (variant {})", platform, variant.name())
+        }
+        _ => println!(
+            "  Platform: {} (unsupported)",
+            or_unknown(&found.platforms.join(", "))
+        ),
+    }
+    if let Some(quirks) = found.quirks {
+        println!("  Quirks:   {}", quirks);
+    }
+    if let Some(rate) = found.tickrate {
+        println!("  Tickrate: {} instructions per frame", rate);
+    }
+    if let Some(palette) = found.palette {
+        println!("  Palette:  {}", colors(palette));
+    }
+}
+
+fn backend(name: &str) -> Box<dyn ExecutionBackend> {
+    match name {
+        "interpreter" => Box::new(Interpreter),
+        "blocks" => Box::new(BlockTranslator::new()),
+        "cached" => Box::new(CachedInterpreter::new()),
+        name => unreachable!("unknown backend {}", name),
+    }
+}
+
+fn boot(options: &Options) -> Chip8 {
+    boot_cartridge(options, &options.cartridge)
+        .unwrap_or_else(|e| fail(&format!("Failed to load ROM: {}", e)))
+}
+
+// Boot a machine set up from the options with another cartridge.
+fn boot_cartridge(options: &Options, cartridge: &Cartridge) -> Result<Chip8, LoadError> {
+    let mut chip8 = Chip8::with_backend(backend(&options.backend));
+    chip8.set_seed(options.seed);
+    chip8.set_mode(options.mode);
+    chip8.set_quirks(options.quirks);
+    for &(check, policy) in &options.checks {
+        chip8.set_check(check, policy);
+    }
+    chip8.set_load_address(options.load_address);
+    chip8.set_write_protect(!options.unprotected);
+    chip8.set_font(options.font);
+    chip8.set_variant(cartridge.variant);
+    if let Some(resolution) = options.resolution {
+        chip8.set_resolution(resolution);
+    }
+    chip8.set_timer_rate(cartridge.timer_rate.hz());
+    chip8.set_cheats(options.cheats.clone());
+    chip8.load_rom(&cartridge.rom)?;
+    if options.profile || options.profile_folded.is_some() {
+        chip8.enable_profile();
+    }
+    if let Some(path) = &options.audit {
+        let file = fs::File::create(path)
+            .unwrap_or_else(|e| fail(&format!("Failed to create {}: {}", path, e)));
+        let mut log = BufWriter::new(file);
+        chip8.set_audit_sink(Box::new(move |event: &AuditEvent| {
+            let _ = writeln!(log, "{}", event);
+        }));
+    }
+    if options.trace {
+        chip8.set_trace_sink(Box::new(|entry: &TraceEntry| eprintln!("{}", entry)));
+    }
+    // Keeping a history makes the block translator interpret everything, so
+    // only the interpreter has one to show on a crash.
+    if options.backend == "interpreter" {
+        chip8.enable_history(HISTORY_LEN);
+    }
+    if options.fast_boot > 0 {
+        let cycles = options.cycles_per_frame_of(cartridge);
+        let frames = chip8.run_until_first_draw(cycles, options.fast_boot);
+        eprintln!("Fast boot: skipped {} frames", frames);
+    }
+    Ok(chip8)
+}
+
+fn run_frames(chip8: &mut Chip8, options: &Options) {
+    for _ in 0..options.frames {
+        chip8.run_frame(options.cycles_per_frame());
+    }
+}
+
+fn run_headless(options: &Options) -> Chip8 {
+    let mut chip8 = boot(options);
+    let mut monitor = Monitor::new(options);
+    let mut scripter = Scripter::new(options);
+    let mut recorder = frame_recorder(options);
+    // A movie replaces --frames with its own length.
+    if let Some(path) = &options.movie {
+        let mut movie = replay(options, path, &mut chip8);
+        let cycles = movie.movie().cycles_per_frame;
+        while !movie.is_finished() {
+            let started = Instant::now();
+            movie.before_frame(&mut chip8);
+            if !scripter.run_frame(&mut chip8, cycles) {
+                break;
+            }
+            monitor.frame(&chip8, started, None);
+            record_frame(&mut recorder, &chip8);
+        }
+    } else {
+        let mut crowd = Crowd::new(options);
+        for _ in 0..options.frames {
+            crowd.before_frame(&mut chip8);
+            let started = Instant::now();
+            if !scripter.run_frame(&mut chip8, options.cycles_per_frame()) {
+                break;
+            }
+            monitor.frame(&chip8, started, None);
+            record_frame(&mut recorder, &chip8);
+        }
+    }
+    save_recording(options, recorder);
+    chip8
+}
+
+// Records every frame of a headless run when given `--apng`.
+fn frame_recorder(options: &Options) -> Option<Recorder> {
+    options.apng.as_ref().map(|_| {
+        Recorder::new(
+            options.palette.unwrap_or_default(),
+            options.scale,
+            options.cartridge.timer_rate.hz(),
+        )
+    })
+}
+
+fn record_frame(recorder: &mut Option<Recorder>, chip8: &Chip8) {
+    if let Some(recorder) = recorder {
+        recorder.capture(chip8.display());
+    }
+}
+
+fn save_recording(options: &Options, recorder: Option<Recorder>) {
+    let (Some(path), Some(apng)) = (&options.apng, recorder.and_then(|r| r.encode())) else {
+        return;
+    };
+    fs::write(path, apng).unwrap_or_else(|e| fail(&format!("Failed to write {}: {}", path, e)));
+}
+
+// Serves metrics over HTTP during a run when given `--metrics`. The run is
+// paced in real time then, as a server deployment would be, instead of
+// running as fast as possible.
+#[cfg(feature = "metrics")]
+struct Monitor(Option<MetricsMonitor>);
+
+#[cfg(feature = "metrics")]
+struct MetricsMonitor {
+    server: MetricsServer,
+    metrics: Metrics,
+    frame_duration: Duration,
+    next_frame: Instant,
+}
+
+#[cfg(feature = "metrics")]
+impl Monitor {
+    fn new(options: &Options) -> Monitor {
+        Monitor(options.metrics.as_ref().map(|addr| {
+            let server = MetricsServer::bind(addr.as_str())
+                .unwrap_or_else(|e| fail(&format!("Failed to listen on {}: {}", addr, e)));
+            if let Ok(addr) = server.local_addr() {
+                eprintln!("Serving metrics on http://{}/metrics", addr);
+            }
+            MetricsMonitor {
+                server,
+                metrics: Metrics::new(),
+                frame_duration: Duration::from_secs(1) / options.cartridge.timer_rate.hz(),
+                next_frame: Instant::now(),
+            }
+        }))
+    }
+
+    // Update the metrics after a frame that began at `started`, answer
+    // scrapes, then wait for the next frame.
+    fn frame(&mut self, chip8: &Chip8, started: Instant, queue: Option<usize>) {
+        let Some(monitor) = &mut self.0 else {
+            return;
+        };
+        let metrics = &mut monitor.metrics;
+        metrics.record_telemetry(chip8.telemetry());
+        metrics.set(
+            "chip8_frame_time_seconds",
+            Kind::Gauge,
+            "Time spent emulating the last frame",
+            started.elapsed().as_secs_f64(),
+        );
+        metrics.set(
+            "chip8_halted",
+            Kind::Gauge,
+            "Whether the machine is halted on a fault",
+            chip8.is_halted() as u8 as f64,
+        );
+        if let Some(samples) = queue {
+            metrics.set(
+                "chip8_audio_queue_samples",
+                Kind::Gauge,
+                "Audio samples waiting for the output",
+                samples as f64,
+            );
+        }
+        if let Err(e) = monitor.server.poll(metrics) {
+            eprintln!("Metrics server error: {}", e);
+        }
+        monitor.next_frame += monitor.frame_duration;
+        thread::sleep(monitor.next_frame.saturating_duration_since(Instant::now()));
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+struct Monitor;
+
+#[cfg(not(feature = "metrics"))]
+impl Monitor {
+    fn new(options: &Options) -> Monitor {
+        if options.metrics.is_some() {
+            usage_error("--metrics: built without the `metrics` feature");
+        }
+        Monitor
+    }
+
+    fn frame(&mut self, _chip8: &Chip8, _started: Instant, _queue: Option<usize>) {}
+}
+
+// Holds down the key most voted for through `--crowd` in every frame of a
+// headless run, which then runs in real time for the votes to come in.
+#[cfg(feature = "net")]
+struct Crowd(Option<CrowdVotes>);
+
+#[cfg(feature = "net")]
+struct CrowdVotes {
+    server: VoteServer,
+    votes: CrowdInput,
+    frame_duration: Duration,
+    next_frame: Instant,
+}
+
+#[cfg(feature = "net")]
+impl Crowd {
+    fn new(options: &Options) -> Crowd {
+        Crowd(options.crowd.as_ref().map(|addr| {
+            let server = VoteServer::bind(addr.as_str())
+                .unwrap_or_else(|e| fail(&format!("Failed to listen on {}: {}", addr, e)));
+            if let Ok(addr) = server.local_addr() {
+                eprintln!("Taking key votes on {}", addr);
+            }
+            CrowdVotes {
+                server,
+                votes: CrowdInput::new(),
+                frame_duration: Duration::from_secs(1) / options.cartridge.timer_rate.hz(),
+                next_frame: Instant::now(),
+            }
+        }))
+    }
+
+    // Wait for the frame to be due, then hold the key voted for since the
+    // last one.
+    fn before_frame(&mut self, chip8: &mut Chip8) {
+        let Some(crowd) = &mut self.0 else {
+            return;
+        };
+        thread::sleep(crowd.next_frame.saturating_duration_since(Instant::now()));
+        crowd.next_frame += crowd.frame_duration;
+        if let Err(e) = crowd.server.poll(&mut crowd.votes) {
+            eprintln!("Vote server error: {}", e);
+        }
+        chip8.set_keys(crowd.votes.tally());
+    }
+}
+
+#[cfg(not(feature = "net"))]
+struct Crowd;
+
+#[cfg(not(feature = "net"))]
+impl Crowd {
+    fn new(options: &Options) -> Crowd {
+        if options.crowd.is_some() {
+            usage_error("--crowd: built without the `net` feature");
+        }
+        Crowd
+    }
+
+    fn before_frame(&mut self, _chip8: &mut Chip8) {}
+}
+
+// Runs the `--script` hooks around every frame of a headless run, printing
+// what the script prints to stderr. A failing script ends the program.
+#[cfg(feature = "scripting")]
+struct Scripter(Option<Script>);
+
+#[cfg(feature = "scripting")]
+impl Scripter {
+    fn new(options: &Options) -> Scripter {
+        Scripter(options.script.as_ref().map(|path| {
+            Script::from_file(path).unwrap_or_else(|e| fail(&format!("{}: {}", path, e)))
+        }))
+    }
+
+    // Run a frame, returning false once the script stopped the run.
+    fn run_frame(&mut self, chip8: &mut Chip8, cycles: usize) -> bool {
+        let Some(script) = &mut self.0 else {
+            chip8.run_frame(cycles);
+            return true;
+        };
+        let result = script.run_frame(chip8, cycles);
+        for line in script.take_log() {
+            eprintln!("{}", line);
+        }
+        if let Err(e) = result {
+            fail(&format!("Script error: {}", e));
+        }
+        !script.is_stopped()
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+struct Scripter;
+
+#[cfg(not(feature = "scripting"))]
+impl Scripter {
+    fn new(options: &Options) -> Scripter {
+        if options.script.is_some() {
+            usage_error("--script: built without the `scripting` feature");
+        }
+        Scripter
+    }
+
+    fn run_frame(&mut self, chip8: &mut Chip8, cycles: usize) -> bool {
+        chip8.run_frame(cycles);
+        true
+    }
+}
+
+// Run two machines booted as `run` boots them, replaying the movie if one
+// was given, and fail on the first frame where they disagree.
+fn check_determinism(options: &Options) {
+    let movie = options.movie.as_ref().map(|path| read_movie(options, path));
+    let [first, second] = [(); 2].map(|_| {
+        let mut chip8 = boot(options);
+        if let Some(movie) = &movie {
+            chip8.set_seed(movie.seed);
+        }
+        chip8
+    });
+    // A movie replaces --frames with its own length.
+    let (inputs, frames, cycles) = match &movie {
+        Some(movie) => (
+            &movie.recording.frames[..],
+            movie.frames() as u64,
+            movie.cycles_per_frame,
+        ),
+        None => (&[][..], options.frames, options.cycles_per_frame()),
+    };
+    match determinism::compare_runs(first, second, inputs, frames, cycles) {
+        Ok(()) => eprintln!("Deterministic over {} frames.", frames),
+        Err(d) => {
+            eprintln!(
+                "Nondeterminism detected at frame {}: 0x{:016X} != 0x{:016X}",
+                d.frame, d.first, d.second
+            );
+            process::exit(1);
+        }
+    }
+}
+
+// Resolve the machine arguments against the configuration file and the
+// cartridge of the ROM at `rom`, or the splash screen without one.
+fn options(config: Config, rom: Option<&str>, args: &MachineArgs) -> Options {
+    let cartridge = rom.map_or_else(|| Cartridge::from_rom(splash::ROM), load_rom);
+    options_for(config, cartridge, rom, args)
+}
+
+// Resolve the machine arguments for a cartridge, loaded from `rom` if
+// given.
+fn options_for(
+    config: Config,
+    mut cartridge: Cartridge,
+    rom: Option<&str>,
+    args: &MachineArgs,
+) -> Options {
+    let local_rom = rom.filter(|path| !path.contains("://")).map(PathBuf::from);
+    let mut quirks = match args.quirks {
+        Some(QuirkSetting::Given(quirks)) => quirks,
+        _ => config.quirks.unwrap_or_default(),
+    };
+    if args.from_db {
+        let database = rom_database(args.rom_db.as_deref().or(config.rom_db.as_deref()));
+        apply_rom_info(
+            &database,
+            &mut cartridge,
+            args.quirks.is_none().then_some(&mut quirks),
+        );
+    }
+    if args.pal {
+        cartridge.timer_rate = TimerRate::Pal;
+    }
+    if let Some(variant) = args.variant {
+        cartridge.variant = variant;
+    }
+    let palette = cartridge
+        .palette
+        .map(|[background, foreground]| Palette::new(background, foreground))
+        .or(config.palette);
+    let cheats_path = args.cheats.clone().or_else(|| {
+        local_rom
+            .as_deref()
+            .map(Cheats::path_for)
+            .filter(|path| !args.no_cheats && path.is_file())
+    });
+    let mut cheats = match cheats_path {
+        Some(path) => load_cheats(&path),
+        None => Cheats::new(),
+    };
+    for cheat in &args.codes {
+        cheats.add(cheat.clone());
+    }
+    for name in &args.disabled_cheats {
+        if !cheats.set_enabled(name, false) {
+            usage_error(&format!("--no-cheat: no cheat `{}`", name));
+        }
+    }
+    let mut options = Options {
+        cartridge,
+        frames: 0,
+        seed: args.seed,
+        ips: args.ips.or(config.ips),
+        backend: args.backend.clone(),
+        fast_boot: args.fast_boot,
+        mode: if args.strict {
+            EmulationMode::Strict
+        } else {
+            EmulationMode::Permissive
+        },
+        checks: args.check.clone(),
+        quirks,
+        audit: None,
+        trace: false,
+        profile: false,
+        profile_folded: None,
+        audio: AudioConfig::default(),
+        watch: false,
+        sources: Vec::new(),
+        metrics: None,
+        crowd: None,
+        script: None,
+        load_address: if args.eti660 {
+            LoadAddress::Eti660
+        } else {
+            LoadAddress::default()
+        },
+        resolution: args.resolution,
+        apng: None,
+        unprotected: args.unprotected,
+        font: args.font.unwrap_or_default(),
+        scale: config.scale.unwrap_or(10),
+        rom_path: local_rom,
+        rom_dir: config.rom_dir.clone(),
+        movie: None,
+        config,
+        palette,
+        cheats,
+        phosphor: None,
+        attract: None,
+        idle: 0,
+    };
+    if let Some(QuirkSetting::Auto) = args.quirks {
+        options.quirks = detect_quirks_for(&options);
+    }
+    options
+}
+
+// Read the ROM at `path`, or download it when given a URL.
+fn load_rom(path: &str) -> Cartridge {
+    let rom = read_rom(path).unwrap_or_else(|e| fail(&format!("Failed to read {}: {}", path, e)));
+    Cartridge::load(&rom).unwrap_or_else(|e| fail(&format!("Failed to load {}: {}", path, e)))
+}
+
+fn audio_config(args: &AudioArgs) -> AudioConfig {
+    let mut audio = AudioConfig::default();
+    let settings = [
+        (args.sample_rate, &mut audio.sample_rate),
+        (args.buffer_size, &mut audio.buffer_size),
+        (args.latency, &mut audio.latency_ms),
+        (args.attack, &mut audio.attack_ms),
+        (args.release, &mut audio.release_ms),
+    ];
+    for (value, setting) in settings {
+        if let Some(value) = value {
+            *setting = value;
+        }
+    }
+    if let Some(pitch) = args.pitch {
+        audio.pitch = pitch as f32;
+    }
+    if let Some(volume) = args.volume {
+        audio.volume = volume as f32 / 100.0;
+    }
+    if let Some(waveform) = args.waveform {
+        audio.waveform = waveform;
+    }
+    if let Err(e) = audio.validate() {
+        usage_error(&format!("Invalid audio settings: {}", e));
+    }
+    audio
+}
+
+// The configuration file given with --config, or the default one if there
+// is one, unless --no-config says otherwise.
+fn load_config(cli: &Cli) -> Config {
+    if cli.no_config {
+        return Config::default();
+    }
+    let path = match cli.config.clone().or_else(Config::default_path) {
+        Some(path) if cli.config.is_some() || path.is_file() => path,
+        _ => return Config::default(),
+    };
+    let text = fs::read_to_string(&path)
+        .unwrap_or_else(|e| fail(&format!("Failed to read {}: {}", path.display(), e)));
+    text.parse()
+        .unwrap_or_else(|e| fail(&format!("Invalid {}: {}", path.display(), e)))
+}
+
+fn load_cheats(path: &Path) -> Cheats {
+    let text = fs::read_to_string(path)
+        .unwrap_or_else(|e| fail(&format!("Failed to read {}: {}", path.display(), e)));
+    text.parse()
+        .unwrap_or_else(|e| fail(&format!("Invalid {}: {}", path.display(), e)))
+}
+
+// The bundled ROM database, extended with the one from --rom-db or the
+// configuration file: a file, a clone of the community database, or a URL
+// to download, `community` for the community database's.
+fn rom_database(source: Option<&Path>) -> RomDatabase {
+    let mut database = RomDatabase::bundled();
+    let Some(source) = source else {
+        return database;
+    };
+    let url = match source.to_str() {
+        Some("community") => Some(rominfo::COMMUNITY_URL),
+        Some(url) if url.contains("://") => Some(url),
+        _ => None,
+    };
+    let other = match url {
+        Some(url) => fetch_rom_db(url)
+            .unwrap_or_else(|e| fail(&format!("Failed to download {}: {}", url, e))),
+        None => RomDatabase::load(source)
+            .unwrap_or_else(|e| fail(&format!("Invalid ROM database {}: {}", source.display(), e))),
+    };
+    database.extend(other);
+    database
+}
+
+#[cfg_attr(not(feature = "net"), allow(unused_variables))]
+fn fetch_rom_db(url: &str) -> Result<RomDatabase, Box<dyn Error>> {
+    #[cfg(feature = "net")]
+    return Ok(String::from_utf8(fetch::fetch_rom_db(url)?)?.parse()?);
+    #[cfg(not(feature = "net"))]
+    return Err("built without the `net` feature".into());
+}
+
+// Fill in what the ROM database knows about the ROM where the cartridge
+// does not say, and the quirks unless given on the command line.
+fn apply_rom_info(database: &RomDatabase, cartridge: &mut Cartridge, quirks: Option<&mut Quirks>) {
+    let Some(info) = database.lookup(&cartridge.rom) else {
+        eprintln!(
+            "Not in the ROM database: {}",
+            rominfo::sha1_hex(&cartridge.rom)
+        );
+        return;
+    };
+    eprintln!(
+        "ROM database: {} ({})",
+        info.title,
+        info.platform.as_deref().unwrap_or("unsupported platform")
+    );
+    // Only plain ROMs leave the variant to the database, cartridges name
+    // theirs.
+    if *cartridge == Cartridge::from_rom(&cartridge.rom) {
+        cartridge.variant = info.variant.unwrap_or(cartridge.variant);
+    }
+    cartridge.tickrate = cartridge.tickrate.or(info.tickrate);
+    cartridge.palette = cartridge.palette.or(info.palette);
+    if cartridge.title.is_empty() {
+        cartridge.title = info.title.clone();
+    }
+    if cartridge.author.is_empty() {
+        cartridge.author = info.authors.join(", ");
+    }
+    if let (Some(quirks), Some(recommended)) = (quirks, info.quirks) {
+        *quirks = recommended;
+    }
+}
+
+fn detect_quirks_for(options: &Options) -> Quirks {
+    let (quirks, scores) = quirks::detect(
+        &options.cartridge.rom,
+        options.cartridge.variant,
+        QUIRK_DETECTION_FRAMES,
+        options.cycles_per_frame(),
+    );
+    for score in &scores {
+        eprintln!(
+            "Quirks {}: {} faults, {} draws, penalty {}",
+            score.quirks,
+            score.faults,
+            score.draw_calls,
+            score.penalty(QUIRK_DETECTION_FRAMES)
+        );
+    }
+    eprintln!("Detected quirks: {}", quirks);
+    quirks
+}
+
+// Read a ROM from disk, or download it when given a URL.
+fn read_rom(path: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    if path.starts_with("http://") || path.starts_with("https://") {
+        #[cfg(feature = "net")]
+        return Ok(fetch::fetch_rom(path)?);
+        #[cfg(not(feature = "net"))]
+        return Err("built without the `net` feature".into());
+    }
+    Ok(fs::read(path)?)
+}
+
+// Parse a per-check override such as `stack=halt`.
+fn parse_check(value: &str) -> Result<(Check, FaultPolicy), String> {
+    let (check, policy) = value.split_once('=').ok_or("expected <check>=<policy>")?;
+    Ok((check.parse()?, policy.parse()?))
+}
+
+// Parse a freeze cheat given on the command line, named after its code.
+fn parse_cheat(value: &str) -> Result<Cheat, String> {
+    Ok(Cheat::new(value, CheatKind::Freeze, vec![value.parse()?]))
+}
+
+// Parse a hexadecimal address, with or without 0x prefix.
+fn parse_address(value: &str) -> Result<usize, String> {
+    let digits = value.trim_start_matches("0x").trim_start_matches("0X");
+    usize::from_str_radix(digits, 16).map_err(|_| format!("invalid address `{}`", value))
+}
+
+// Report an argument clap could not check, with the usage, and exit with 2.
+fn usage_error(message: &str) -> ! {
+    Cli::command()
+        .error(ErrorKind::ValueValidation, message)
+        .exit()
+}
+
+// Report a failure that is not the arguments' fault, and exit with 1.
+fn fail(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(1)
 }
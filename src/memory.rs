@@ -1,6 +1,11 @@
+use core::fmt;
+
+#[cfg(feature = "std")]
+use crate::font::FontSet;
+
 /// # Memory Map:
 ///
-/// ```
+/// ```text
 /// +---------------+= 0xFFF (4095) End of Chip-8 RAM
 /// |               |
 /// |               |
@@ -22,49 +27,166 @@
 /// | 0x000 to 0x1FF|
 /// | Reserved for  |
 /// |  interpreter  |
+/// +- - - - - - - -+= 0x0F0 (240) End of the fonts
+/// |   8x10 font   |
+/// +- - - - - - - -+= 0x050 (80) Start of the SUPER-CHIP font
+/// |    8x5 font   |
 /// +---------------+= 0x000 (0) Start of Chip-8 RAM
 /// ```
+///
+/// Programs may read the whole address space, including the interpreter
+/// area with the fonts. Writing to the interpreter area fails while it is
+/// write-protected, which it is by default; some interpreters let programs
+/// overwrite it, see `set_write_protect`. The host loads programs and
+/// pokes memory through `load`, which ignores the protection. Which digits
+/// the fonts hold depends on the `FontSet`.
+///
+/// XO-CHIP extends the address space to 64KB, up to 0xFFFF, with the same
+/// layout below 0x1000.
+#[cfg(feature = "std")]
 #[derive(Debug)]
-struct Memory {
-    data: [u8; 4096],
+pub struct Memory {
+    data: Vec<u8>,
+    write_protect: bool,
+    font: FontSet,
 }
 
+/// Why an access failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryError {
+    // The address is past the end of memory
+    OutOfBounds(usize),
+
+    // The address is in the write-protected interpreter area
+    Protected(usize),
+}
+
+pub const SIZE: usize = 0x1000;
+pub const XO_CHIP_SIZE: usize = 0x10000;
+
+pub const FONT_ADDRESS: usize = 0x000;
+pub const BIG_FONT_ADDRESS: usize = 0x050;
+
+// One past the last byte of the interpreter area.
+pub const INTERPRETER_END: usize = 0x200;
+
+#[cfg(feature = "std")]
 impl Memory {
-    fn new() -> Self {
-        let mut data = [0; 4096];
-        data[0..5] = [0xF0, 0x90, 0x90, 0x90, 0xF0]; // "0"
-        data[5..10] = [0x20, 0x60, 0x20, 0x20, 0x70]; // "1"
-        data[10..15] = [0xF0, 0x10, 0xF0, 0x80, 0xF0]; // "2"
-        data[15..20] = [0xF0, 0x10, 0xF0, 0x10, 0xF0]; // "3"
-        data[20..25] = [0x90, 0x90, 0xF0, 0x10, 0x10]; // "4"
-        data[25..30] = [0xF0, 0x80, 0xF0, 0x10, 0xF0]; // "5"
-        data[30..35] = [0xF0, 0x80, 0xF0, 0x90, 0xF0]; // "6"
-        data[35..40] = [0xF0, 0x10, 0x20, 0x40, 0x40]; // "7"
-        data[40..45] = [0xF0, 0x90, 0xF0, 0x90, 0xF0]; // "8"
-        data[45..50] = [0xF0, 0x90, 0xF0, 0x10, 0xF0]; // "9"
-        data[50..55] = [0xF0, 0x90, 0xF0, 0x90, 0x90]; // "A"
-        data[55..60] = [0xE0, 0x90, 0xE0, 0x90, 0xE0]; // "B"
-        data[60..65] = [0xF0, 0x80, 0x80, 0x80, 0xF0]; // "C"
-        data[65..70] = [0xE0, 0x90, 0x90, 0x90, 0xE0]; // "D"
-        data[70..75] = [0xF0, 0x80, 0xF0, 0x80, 0xF0]; // "E"
-        data[75..80] = [0xF0, 0x80, 0xF0, 0x80, 0x80]; // "F"
-        Self { data };
-    }
-
-    fn address(&self, addr: usize) -> Option<&u8> {
-        if addr >= 0x200 && addr <= 0x1000 {
-            Some(&self.data[addr])
-        } else {
-            None
+    pub fn new() -> Self {
+        Self::with_size(SIZE)
+    }
+
+    // Memory of `size` bytes, `SIZE` or `XO_CHIP_SIZE`, with the fonts in
+    // place.
+    pub fn with_size(size: usize) -> Self {
+        let mut memory = Self {
+            data: vec![0; size.max(SIZE)],
+            write_protect: true,
+            font: FontSet::default(),
+        };
+        memory.set_font(FontSet::default());
+        memory
+    }
+
+    // Replace the digits in the interpreter area with those of `font`.
+    pub fn set_font(&mut self, font: FontSet) {
+        let fonts = [
+            (FONT_ADDRESS, font.small().as_flattened()),
+            (BIG_FONT_ADDRESS, font.big().as_flattened()),
+        ];
+        for (address, digits) in fonts {
+            self.load(address, digits)
+                .expect("the fonts fit in the interpreter area");
+        }
+        self.font = font;
+    }
+
+    pub fn font(&self) -> FontSet {
+        self.font
+    }
+
+    // Whether programs may write to the interpreter area, 0x000 to 0x1FF.
+    pub fn set_write_protect(&mut self, write_protect: bool) {
+        self.write_protect = write_protect;
+    }
+
+    pub fn is_write_protected(&self) -> bool {
+        self.write_protect
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    // Size of the address space in bytes.
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    // Grow or shrink the address space, keeping the contents which still
+    // fit.
+    pub fn resize(&mut self, size: usize) {
+        self.data.resize(size.max(SIZE), 0);
+    }
+
+    // Overwrite the whole address space, e.g. when restoring a save state.
+    pub fn restore(&mut self, data: &[u8]) {
+        let len = data.len().min(self.data.len());
+        self.data[..len].copy_from_slice(&data[..len]);
+    }
+
+    pub fn read(&self, addr: usize) -> Result<u8, MemoryError> {
+        self.data
+            .get(addr)
+            .copied()
+            .ok_or(MemoryError::OutOfBounds(addr))
+    }
+
+    // The big-endian word at `addr` and the byte after it.
+    pub fn read_word(&self, addr: usize) -> Result<u16, MemoryError> {
+        let high = self.read(addr)?;
+        let low = self.read(addr + 1)?;
+        Ok(u16::from_be_bytes([high, low]))
+    }
+
+    pub fn write(&mut self, addr: usize, value: u8) -> Result<(), MemoryError> {
+        if self.write_protect && addr < INTERPRETER_END {
+            return Err(MemoryError::Protected(addr));
         }
+        let byte = self
+            .data
+            .get_mut(addr)
+            .ok_or(MemoryError::OutOfBounds(addr))?;
+        *byte = value;
+        Ok(())
     }
 
-    fn assign(&mut self, addr: usize, value: u8) -> Result<(), String> {
-        if addr >= 0x200 && addr <= 0x1000 {
-            self.data[addr] = value;
-            Ok(())
-        } else {
-            Err(format!("Invalid memory address: 0x{:X}", addr))
+    // Copy `bytes` into memory from `start` on, whatever the protection.
+    // Nothing is copied unless all of them fit.
+    pub fn load(&mut self, start: usize, bytes: &[u8]) -> Result<(), MemoryError> {
+        let end = start + bytes.len();
+        if end > self.data.len() {
+            return Err(MemoryError::OutOfBounds(self.data.len().max(start)));
         }
+        self.data[start..end].copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+impl fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfBounds(addr) => write!(f, "address 0x{:X} is out of bounds", addr),
+            Self::Protected(addr) => write!(f, "address 0x{:03X} is write-protected", addr),
+        }
+    }
+}
+
+impl core::error::Error for MemoryError {}
+
+#[cfg(feature = "std")]
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
     }
 }
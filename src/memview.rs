@@ -0,0 +1,260 @@
+use std::fmt::Write;
+
+use crate::cpu::Chip8;
+use crate::registers::Register;
+
+/// # Memory View
+///
+/// Navigation state for a paged hex dump of memory, as shown by a debugger's
+/// memory pane. A flat dump works for the 4KB of a plain CHIP-8, but XO-CHIP
+/// programs address 64KB (and MegaChip more), so memory is split into 4KB
+/// banks made of pages of `page_size` bytes. The view keeps track of the
+/// page shown and a cursor, can step through pages and banks, and jump
+/// straight to the location of I or PC.
+///
+/// `render` produces the text of the current page, 16 bytes per row,
+/// prefixed with the bank and the address. The byte PC points at is marked
+/// with `>` and the one I points at with `*`.
+#[derive(Debug, Clone)]
+pub struct MemoryView {
+    size: usize,
+    page_size: usize,
+    page: usize,
+    cursor: usize,
+}
+
+pub const BANK_SIZE: usize = 0x1000;
+
+const ROW_SIZE: usize = 16;
+
+impl MemoryView {
+    // A view over `size` bytes of memory, showing `page_size` bytes at a
+    // time. The page size is rounded to whole rows and capped to a bank.
+    pub fn new(size: usize, page_size: usize) -> MemoryView {
+        let page_size = page_size.div_ceil(ROW_SIZE).clamp(1, BANK_SIZE / ROW_SIZE) * ROW_SIZE;
+        MemoryView {
+            size: size.max(1),
+            page_size,
+            page: 0,
+            cursor: 0,
+        }
+    }
+
+    pub fn page(&self) -> usize {
+        self.page
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.size.div_ceil(self.page_size)
+    }
+
+    pub fn bank(&self) -> usize {
+        self.page * self.page_size / BANK_SIZE
+    }
+
+    pub fn bank_count(&self) -> usize {
+        self.size.div_ceil(BANK_SIZE)
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    // Range of addresses on the current page.
+    pub fn range(&self) -> std::ops::Range<usize> {
+        let start = self.page * self.page_size;
+        start..(start + self.page_size).min(self.size)
+    }
+
+    pub fn next_page(&mut self) {
+        self.show_page(self.page + 1);
+    }
+
+    pub fn previous_page(&mut self) {
+        self.show_page(self.page.saturating_sub(1));
+    }
+
+    // Show the same offset in the next bank.
+    pub fn next_bank(&mut self) {
+        self.jump_to(self.cursor + BANK_SIZE);
+    }
+
+    pub fn previous_bank(&mut self) {
+        self.jump_to(self.cursor.saturating_sub(BANK_SIZE));
+    }
+
+    // Show the page containing `address` and put the cursor on it, e.g. to
+    // follow I or PC.
+    pub fn jump_to(&mut self, address: usize) {
+        let address = address.min(self.size - 1);
+        self.page = address / self.page_size;
+        self.cursor = address;
+    }
+
+    // Move the cursor by `delta` bytes, turning the page when it leaves it.
+    pub fn move_cursor(&mut self, delta: isize) {
+        self.jump_to(self.cursor.saturating_add_signed(delta));
+    }
+
+    fn show_page(&mut self, page: usize) {
+        self.page = page.min(self.page_count() - 1);
+        let range = self.range();
+        self.cursor = self.cursor.clamp(range.start, range.end - 1);
+    }
+
+    // Hex dump of the current page of `memory`.
+    pub fn render(&self, memory: &[u8], pc: usize, i: usize) -> String {
+        let mut text = String::new();
+        let range = self.range();
+        for row in range.clone().step_by(ROW_SIZE) {
+            let _ = write!(text, "{:X}:{:03X} ", row / BANK_SIZE, row % BANK_SIZE);
+            for address in row..(row + ROW_SIZE).min(range.end) {
+                let marker = if address == pc {
+                    '>'
+                } else if address == i {
+                    '*'
+                } else {
+                    ' '
+                };
+                match memory.get(address) {
+                    Some(byte) => {
+                        let _ = write!(text, "{}{:02X}", marker, byte);
+                    }
+                    None => {
+                        let _ = write!(text, "{}--", marker);
+                    }
+                }
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// An editor on a `MemoryView`, as the memory panel of the frontends
+/// shows it next to the running game. Host keys, named as for `KeyMap`,
+/// move the cursor and change the byte under it:
+///
+/// - Up, Down, Left and Right move by a row or a byte, PageUp and PageDown
+///   by a page
+/// - I and P jump to where I and PC point
+/// - two hexadecimal digits overwrite the byte under the cursor, see
+///   `Chip8::poke`, and move on to the next one
+///
+/// F8 shows and hides the panel; while it is shown, the keys it uses do not
+/// reach the keypad.
+#[derive(Debug, Clone)]
+pub struct MemoryEditor {
+    view: MemoryView,
+    shown: bool,
+
+    // The high nibble typed for the byte under the cursor
+    pending: Option<u8>,
+}
+
+pub const TOGGLE_KEY: &str = "F8";
+
+impl MemoryEditor {
+    pub fn new(size: usize, page_size: usize) -> MemoryEditor {
+        MemoryEditor {
+            view: MemoryView::new(size, page_size),
+            shown: false,
+            pending: None,
+        }
+    }
+
+    pub fn view(&self) -> &MemoryView {
+        &self.view
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.shown
+    }
+
+    // The digit typed so far for the byte under the cursor.
+    pub fn pending(&self) -> Option<u8> {
+        self.pending
+    }
+
+    // Show or hide the panel, showing it at PC.
+    pub fn toggle(&mut self, chip8: &Chip8) {
+        self.shown = !self.shown;
+        self.pending = None;
+        if self.shown {
+            self.view.jump_to(chip8.register(Register::PC) as usize);
+        }
+    }
+
+    // Act on a host key pressed while the panel is shown. Returns whether
+    // the editor used it.
+    pub fn handle(&mut self, chip8: &mut Chip8, key: &str) -> bool {
+        if key == TOGGLE_KEY {
+            self.toggle(chip8);
+            return true;
+        }
+        if !self.shown {
+            return false;
+        }
+        let row = ROW_SIZE as isize;
+        let page = self.view.range().len() as isize;
+        match key.to_ascii_lowercase().as_str() {
+            "up" => self.view.move_cursor(-row),
+            "down" => self.view.move_cursor(row),
+            "left" => self.view.move_cursor(-1),
+            "right" => self.view.move_cursor(1),
+            "pageup" => self.view.move_cursor(-page),
+            "pagedown" => self.view.move_cursor(page),
+            "i" => self.view.jump_to(chip8.register(Register::I) as usize),
+            "p" => self.view.jump_to(chip8.register(Register::PC) as usize),
+            digit => {
+                let Some(nibble) = digit.parse::<char>().ok().and_then(|c| c.to_digit(16)) else {
+                    return false;
+                };
+                let nibble = nibble as u8;
+                match self.pending.take() {
+                    None => self.pending = Some(nibble),
+                    Some(high) => {
+                        chip8.poke(self.view.cursor(), high << 4 | nibble);
+                        self.view.move_cursor(1);
+                    }
+                }
+                return true;
+            }
+        }
+        self.pending = None;
+        true
+    }
+
+    // Text lines of the panel: the current page of `chip8`'s memory as by
+    // `MemoryView::render`, followed by the location and the typed digit.
+    pub fn render(&self, chip8: &Chip8) -> Vec<String> {
+        let pc = chip8.register(Register::PC) as usize;
+        let i = chip8.register(Register::I) as usize;
+        let mut lines: Vec<String> = self
+            .view
+            .render(chip8.memory(), pc, i)
+            .lines()
+            .map(str::to_string)
+            .collect();
+        let cursor = self.view.cursor();
+        let mut status = format!(
+            "{:X}:{:03X} = {:02X}",
+            cursor / BANK_SIZE,
+            cursor % BANK_SIZE,
+            chip8.peek(cursor).unwrap_or_default()
+        );
+        if let Some(high) = self.pending {
+            let _ = write!(status, " <- {:X}_", high);
+        }
+        lines.push(status);
+        lines
+    }
+
+    // Column of the byte under the cursor in the lines of `render`, and its
+    // line.
+    pub fn cursor_position(&self) -> (usize, usize) {
+        let offset = self.view.cursor() - self.view.range().start;
+        // After the "B:AAA " prefix, each byte takes a marker and two digits.
+        (6 + (offset % ROW_SIZE) * 3 + 1, offset / ROW_SIZE)
+    }
+}
@@ -0,0 +1,208 @@
+use std::fmt::Write as _;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+
+use crate::telemetry::Telemetry;
+
+/// # Metrics
+///
+/// Counters and gauges for monitoring long-running deployments, such as a
+/// netplay host or a streaming server, in the Prometheus text format. The
+/// telemetry counters are exported as they are, next to gauges the host
+/// sets itself: frame time, queue depths and the like.
+///
+/// `MetricsServer` answers `GET /metrics` over plain HTTP. Like the vote
+/// server it never blocks, the host calls `poll` once per frame with the
+/// current values.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    metrics: Vec<Metric>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Counter,
+    Gauge,
+}
+
+#[derive(Debug, Clone)]
+struct Metric {
+    name: String,
+    help: String,
+    kind: Kind,
+    value: f64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Set a metric, adding it on first use. Counters are set to their
+    // running total rather than incremented, since the host keeps those.
+    pub fn set(&mut self, name: &str, kind: Kind, help: &str, value: f64) {
+        match self.metrics.iter_mut().find(|m| m.name == name) {
+            Some(metric) => metric.value = value,
+            None => self.metrics.push(Metric {
+                name: name.to_string(),
+                help: help.to_string(),
+                kind,
+                value,
+            }),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.metrics
+            .iter()
+            .find(|m| m.name == name)
+            .map(|m| m.value)
+    }
+
+    pub fn record_telemetry(&mut self, telemetry: &Telemetry) {
+        let counters = [
+            ("chip8_frames_total", "Timer ticks run", telemetry.frames),
+            (
+                "chip8_instructions_total",
+                "Instructions executed",
+                telemetry.instructions,
+            ),
+            (
+                "chip8_draw_calls_total",
+                "DRW instructions executed",
+                telemetry.draw_calls,
+            ),
+            (
+                "chip8_collisions_total",
+                "Draws which set VF on a collision",
+                telemetry.collisions,
+            ),
+            (
+                "chip8_sound_activations_total",
+                "Times the buzzer was switched on",
+                telemetry.sound_activations,
+            ),
+            ("chip8_errors_total", "Faults raised", telemetry.errors),
+        ];
+        for (name, help, value) in counters {
+            self.set(name, Kind::Counter, help, value as f64);
+        }
+        self.set(
+            "chip8_instructions_per_second",
+            Kind::Gauge,
+            "Achieved speed since the start",
+            telemetry.ips(),
+        );
+    }
+
+    // Render every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        for metric in &self.metrics {
+            let kind = match metric.kind {
+                Kind::Counter => "counter",
+                Kind::Gauge => "gauge",
+            };
+            let _ = writeln!(text, "# HELP {} {}", metric.name, metric.help);
+            let _ = writeln!(text, "# TYPE {} {}", metric.name, kind);
+            let _ = writeln!(text, "{} {}", metric.name, metric.value);
+        }
+        text
+    }
+}
+
+pub struct MetricsServer {
+    listener: TcpListener,
+    clients: Vec<Client>,
+}
+
+struct Client {
+    stream: TcpStream,
+
+    // Request received so far
+    request: Vec<u8>,
+}
+
+// Longest request head accepted, anything longer is dropped.
+const MAX_REQUEST: usize = 8 * 1024;
+
+impl MetricsServer {
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            clients: Vec::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // Accept new clients and answer every complete request.
+    pub fn poll(&mut self, metrics: &Metrics) -> io::Result<()> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    self.clients.push(Client {
+                        stream,
+                        request: Vec::new(),
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.clients.retain_mut(|client| {
+            let mut buffer = [0; 1024];
+            loop {
+                match client.stream.read(&mut buffer) {
+                    // Connection closed before the request was complete
+                    Ok(0) => return false,
+                    Ok(n) => client.request.extend_from_slice(&buffer[..n]),
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                    Err(_) => return false,
+                }
+            }
+            if !client.request.windows(4).any(|w| w == b"\r\n\r\n") {
+                return client.request.len() < MAX_REQUEST;
+            }
+            // One request per connection, the response is small enough to
+            // send in one go.
+            let _ = client
+                .stream
+                .set_nonblocking(false)
+                .and_then(|()| client.stream.write_all(&response(&client.request, metrics)));
+            false
+        });
+        Ok(())
+    }
+}
+
+fn response(request: &[u8], metrics: &Metrics) -> Vec<u8> {
+    let line = request.split(|&b| b == b'\r').next().unwrap_or_default();
+    let mut parts = line.split(|&b| b == b' ');
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some(b"GET"), Some(b"/metrics")) => ("200 OK", metrics.render()),
+        (Some(b"GET"), _) => ("404 Not Found", "not found\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "only GET is supported\n".to_string(),
+        ),
+    };
+    format!(
+        concat!(
+            "HTTP/1.1 {}\r\n",
+            "Content-Type: text/plain; version=0.0.4\r\n",
+            "Content-Length: {}\r\n",
+            "Connection: close\r\n\r\n{}"
+        ),
+        status,
+        body.len(),
+        body
+    )
+    .into_bytes()
+}
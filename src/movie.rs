@@ -0,0 +1,207 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::cpu::Chip8;
+use crate::recording::InputRecording;
+
+/// # Movies
+///
+/// A run recorded for exact replay, as tool-assisted runs and regression
+/// tests need: the RNG seed and instructions per frame the run was made
+/// with, a hash of the ROM it belongs to, and the keypad state of every
+/// frame as an `InputRecording`. Replaying the movie against the same ROM
+/// with the same quirks goes through the same states frame by frame.
+///
+/// Movie files (`.c8m`) are text, a header followed by the recording:
+///
+/// ```text
+/// # chip8 movie
+/// seed 42
+/// cycles 12
+/// rom 9B2C4F1E6A0D7753
+/// 0000
+/// 0010
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    pub seed: u64,
+    pub cycles_per_frame: usize,
+
+    // FNV-1a hash of the ROM
+    pub rom_hash: u64,
+
+    pub recording: InputRecording,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MovieError {
+    // A header line is missing or malformed
+    Header(&'static str),
+    Recording(usize),
+
+    // The movie was recorded with another ROM
+    WrongRom { expected: u64, found: u64 },
+}
+
+impl Movie {
+    pub fn new(rom: &[u8], seed: u64, cycles_per_frame: usize) -> Movie {
+        Movie {
+            seed,
+            cycles_per_frame,
+            rom_hash: rom_hash(rom),
+            recording: InputRecording::new(),
+        }
+    }
+
+    pub fn frames(&self) -> usize {
+        self.recording.len()
+    }
+
+    // Check that the movie was recorded with `rom`.
+    pub fn check_rom(&self, rom: &[u8]) -> Result<(), MovieError> {
+        let found = rom_hash(rom);
+        if found != self.rom_hash {
+            return Err(MovieError::WrongRom {
+                expected: self.rom_hash,
+                found,
+            });
+        }
+        Ok(())
+    }
+
+    // Replay the whole movie on a machine with the ROM loaded, headlessly.
+    pub fn play(&self, chip8: &mut Chip8) {
+        chip8.set_seed(self.seed);
+        for frame in 0..self.frames() {
+            chip8.set_keys(self.recording.keys(frame));
+            chip8.run_frame(self.cycles_per_frame);
+        }
+    }
+}
+
+/// A movie being recorded or replayed by a frontend, which calls
+/// `before_frame` before running each frame.
+#[derive(Debug, Clone)]
+pub struct MovieSession {
+    movie: Movie,
+    replaying: bool,
+    frame: usize,
+}
+
+impl MovieSession {
+    pub fn record(movie: Movie) -> MovieSession {
+        MovieSession {
+            movie,
+            replaying: false,
+            frame: 0,
+        }
+    }
+
+    // Replay a movie, seeding the machine as it was when recording.
+    pub fn replay(movie: Movie, chip8: &mut Chip8) -> MovieSession {
+        chip8.set_seed(movie.seed);
+        MovieSession {
+            movie,
+            replaying: true,
+            frame: 0,
+        }
+    }
+
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replaying
+    }
+
+    // Whether a replay has run all of its frames.
+    pub fn is_finished(&self) -> bool {
+        self.replaying && self.frame >= self.movie.frames()
+    }
+
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    // Replays set the keypad to the recorded state of the frame, recordings
+    // take down the state the user left it in.
+    pub fn before_frame(&mut self, chip8: &mut Chip8) {
+        if self.replaying {
+            chip8.set_keys(self.movie.recording.keys(self.frame));
+        } else {
+            let keys = chip8.keys();
+            let mask = (0..16).fold(0u16, |mask, k| mask | (keys[k] as u16) << k);
+            self.movie.recording.push(mask);
+        }
+        self.frame += 1;
+    }
+
+    pub fn into_movie(self) -> Movie {
+        self.movie
+    }
+}
+
+// FNV-1a hash identifying a ROM.
+pub fn rom_hash(rom: &[u8]) -> u64 {
+    rom.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+impl FromStr for Movie {
+    type Err = MovieError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim().starts_with('#'));
+        let mut field = |name: &'static str, radix: u32| -> Result<u64, MovieError> {
+            let (_, line) = lines.next().ok_or(MovieError::Header(name))?;
+            line.trim()
+                .strip_prefix(name)
+                .and_then(|value| u64::from_str_radix(value.trim(), radix).ok())
+                .ok_or(MovieError::Header(name))
+        };
+        let seed = field("seed", 10)?;
+        let cycles_per_frame = field("cycles", 10)? as usize;
+        let rom_hash = field("rom", 16)?;
+        let mut recording = InputRecording::new();
+        for (index, line) in lines {
+            let keys = u16::from_str_radix(line.trim(), 16)
+                .map_err(|_| MovieError::Recording(index + 1))?;
+            recording.push(keys);
+        }
+        Ok(Movie {
+            seed,
+            cycles_per_frame,
+            rom_hash,
+            recording,
+        })
+    }
+}
+
+impl fmt::Display for Movie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# chip8 movie")?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "cycles {}", self.cycles_per_frame)?;
+        writeln!(f, "rom {:016X}", self.rom_hash)?;
+        write!(f, "{}", self.recording)
+    }
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Header(name) => write!(f, "missing or invalid `{}` line", name),
+            Self::Recording(line) => write!(f, "invalid key mask on line {}", line),
+            Self::WrongRom { expected, found } => {
+                write!(f, "recorded with ROM {:016X}, not {:016X}", expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MovieError {}
@@ -0,0 +1,191 @@
+use std::fmt;
+use std::time::Duration;
+
+/// # Frame Pacing
+///
+/// The machine has to advance at exactly its timer rate (60Hz, or 50Hz for
+/// PAL timing), but hosts present at whatever rate their display refreshes,
+/// e.g. 144Hz. `FramePacer` decouples the two: on every presented frame the
+/// host reports the wall time that passed, runs as many emulated frames as
+/// are due, and presents the latest completed one. On a fast display most
+/// calls return zero frames and the same picture is shown again; on a slow
+/// one several frames are run at once.
+///
+/// Time is accounted in whole nanoseconds scaled by the rate, so there is no
+/// rounding drift however long the host runs. If the host stalls (a dragged
+/// window, a debugger pause), the backlog is capped at `max_catch_up` frames
+/// and the rest dropped, instead of running the machine in a burst.
+///
+/// The pacer also runs the machine at another `Speed` than real time, as
+/// the frontends' speed hotkeys set it: F1 pauses and resumes, F2 and F3
+/// step down to slow motion and up to fast-forward.
+#[derive(Debug, Clone)]
+pub struct FramePacer {
+    rate: u32,
+
+    // Elapsed time not yet turned into frames, in nanoseconds times `rate`
+    pending: u128,
+
+    max_catch_up: u32,
+
+    speed: Speed,
+
+    // Speed to go back to when unpausing
+    resume: Speed,
+}
+
+/// How fast emulated time passes compared to wall time. `Uncapped` runs as
+/// many frames as the catch-up limit allows every time the host presents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Speed {
+    Paused,
+    Quarter,
+    Half,
+    Normal,
+    Double,
+    Quadruple,
+    Uncapped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeedHotkey {
+    Pause,
+    Slower,
+    Faster,
+}
+
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+
+impl FramePacer {
+    // Pace frames at `rate` Hz, catching up at most a quarter second.
+    pub fn new(rate: u32) -> FramePacer {
+        let rate = rate.max(1);
+        FramePacer {
+            rate,
+            pending: 0,
+            max_catch_up: rate.div_ceil(4),
+            speed: Speed::Normal,
+            resume: Speed::Normal,
+        }
+    }
+
+    pub fn speed(&self) -> Speed {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: Speed) {
+        if speed != Speed::Paused {
+            self.resume = speed;
+        }
+        self.speed = speed;
+    }
+
+    // Act on a speed hotkey, returning a message for the status line.
+    pub fn handle(&mut self, hotkey: SpeedHotkey) -> String {
+        let speed = match (hotkey, self.speed) {
+            (SpeedHotkey::Pause, Speed::Paused) => self.resume,
+            (SpeedHotkey::Pause, _) => Speed::Paused,
+            // Leaving the pause through the other keys starts from the
+            // speed it was entered at
+            (SpeedHotkey::Slower, Speed::Paused) => self.resume.slower(),
+            (SpeedHotkey::Faster, Speed::Paused) => self.resume.faster(),
+            (SpeedHotkey::Slower, speed) => speed.slower(),
+            (SpeedHotkey::Faster, speed) => speed.faster(),
+        };
+        self.set_speed(speed);
+        match speed {
+            Speed::Paused => "Paused".to_string(),
+            speed => format!("Speed {}", speed),
+        }
+    }
+
+    pub fn set_max_catch_up(&mut self, frames: u32) {
+        self.max_catch_up = frames.max(1);
+    }
+
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    // Account for `elapsed` wall time and return the number of emulated
+    // frames to run before presenting.
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        let (numerator, denominator) = match self.speed {
+            Speed::Paused => return 0,
+            Speed::Uncapped => return self.max_catch_up,
+            speed => speed.ratio(),
+        };
+        self.pending += elapsed.as_nanos() * self.rate as u128 * numerator / denominator;
+        let due = self.pending / NANOS_PER_SECOND;
+        self.pending %= NANOS_PER_SECOND;
+        due.min(self.max_catch_up as u128) as u32
+    }
+
+    // Progress towards the next emulated frame, from 0.0 to 1.0, for hosts
+    // blending between the last two frames.
+    pub fn fraction(&self) -> f32 {
+        self.pending as f32 / NANOS_PER_SECOND as f32
+    }
+}
+
+impl Speed {
+    // The next speed down, stopping at a quarter.
+    pub fn slower(self) -> Speed {
+        match self {
+            Speed::Paused | Speed::Quarter | Speed::Half => Speed::Quarter,
+            Speed::Normal => Speed::Half,
+            Speed::Double => Speed::Normal,
+            Speed::Quadruple => Speed::Double,
+            Speed::Uncapped => Speed::Quadruple,
+        }
+    }
+
+    // The next speed up, stopping at uncapped.
+    pub fn faster(self) -> Speed {
+        match self {
+            Speed::Paused | Speed::Normal => Speed::Double,
+            Speed::Quarter => Speed::Half,
+            Speed::Half => Speed::Normal,
+            Speed::Double => Speed::Quadruple,
+            Speed::Quadruple | Speed::Uncapped => Speed::Uncapped,
+        }
+    }
+
+    // Emulated time per wall time, as a fraction.
+    fn ratio(self) -> (u128, u128) {
+        match self {
+            Speed::Paused => (0, 1),
+            Speed::Quarter => (1, 4),
+            Speed::Half => (1, 2),
+            Speed::Normal | Speed::Uncapped => (1, 1),
+            Speed::Double => (2, 1),
+            Speed::Quadruple => (4, 1),
+        }
+    }
+}
+
+impl SpeedHotkey {
+    // The hotkey bound to a host key, named as for `KeyMap`.
+    pub fn from_name(name: &str) -> Option<SpeedHotkey> {
+        match name {
+            "F1" => Some(SpeedHotkey::Pause),
+            "F2" => Some(SpeedHotkey::Slower),
+            "F3" => Some(SpeedHotkey::Faster),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Speed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Speed::Paused => f.write_str("paused"),
+            Speed::Quarter => f.write_str("0.25x"),
+            Speed::Half => f.write_str("0.5x"),
+            Speed::Normal => f.write_str("1x"),
+            Speed::Double => f.write_str("2x"),
+            Speed::Quadruple => f.write_str("4x"),
+            Speed::Uncapped => f.write_str("uncapped"),
+        }
+    }
+}
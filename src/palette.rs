@@ -0,0 +1,159 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::display::Display;
+
+/// # Palettes
+///
+/// The colors a frontend shows the screen in. The display has four colors
+/// (see `Display::colors`): the background, pixels lit on the first plane,
+/// on the second plane, and on both. Only XO-CHIP programs draw on the
+/// second plane, so for everything else a palette is its background and
+/// foreground.
+///
+/// Palettes are named presets or custom colors, written `#RRGGBB`:
+///
+/// - `classic`: white on black, the default
+/// - `amber`: an amber monochrome monitor
+/// - `paper-white`: dark ink on off-white paper
+/// - `gameboy`: the four greens of the original Game Boy
+/// - `#102010,#80FF80`: background and foreground, with the plane colors
+///   blended between them
+/// - `#102010,#80FF80,#FF8040,#FFFFFF`: all four colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    // Colors 0 to 3: the background, the first plane, the second plane and
+    // both planes
+    pub colors: [[u8; 3]; 4],
+}
+
+impl Palette {
+    pub const CLASSIC: Palette = Palette::new([0x00, 0x00, 0x00], [0xFF, 0xFF, 0xFF]);
+    pub const AMBER: Palette = Palette::new([0x1E, 0x12, 0x00], [0xFF, 0xB0, 0x00]);
+    pub const PAPER_WHITE: Palette = Palette::new([0xF4, 0xF1, 0xE8], [0x20, 0x20, 0x20]);
+    pub const GAMEBOY: Palette = Palette {
+        colors: [
+            [0x9B, 0xBC, 0x0F],
+            [0x0F, 0x38, 0x0F],
+            [0x8B, 0xAC, 0x0F],
+            [0x30, 0x62, 0x30],
+        ],
+    };
+
+    pub const PRESETS: [(&'static str, Palette); 4] = [
+        ("classic", Palette::CLASSIC),
+        ("amber", Palette::AMBER),
+        ("paper-white", Palette::PAPER_WHITE),
+        ("gameboy", Palette::GAMEBOY),
+    ];
+
+    // Two colors, with the second plane a third of the way from the
+    // background to the foreground and both planes two thirds.
+    pub const fn new(background: [u8; 3], foreground: [u8; 3]) -> Palette {
+        Palette {
+            colors: [
+                background,
+                foreground,
+                blend(background, foreground, 1),
+                blend(background, foreground, 2),
+            ],
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Palette> {
+        Palette::PRESETS
+            .into_iter()
+            .find(|&(preset, _)| preset == name)
+            .map(|(_, palette)| palette)
+    }
+
+    pub fn background(&self) -> [u8; 3] {
+        self.colors[0]
+    }
+
+    pub fn foreground(&self) -> [u8; 3] {
+        self.colors[1]
+    }
+
+    // The color for a value of `Display::colors`.
+    pub fn color(&self, color: u8) -> [u8; 3] {
+        self.colors[color as usize & 3]
+    }
+
+    // The screen as RGBA bytes in row-major order, for canvases and image
+    // encoders.
+    pub fn rgba(&self, display: &Display) -> Vec<u8> {
+        display
+            .colors()
+            .iter()
+            .flat_map(|&color| {
+                let [r, g, b] = self.color(color);
+                [r, g, b, 0xFF]
+            })
+            .collect()
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::CLASSIC
+    }
+}
+
+// The color `thirds` thirds of the way from `from` to `to`.
+const fn blend(from: [u8; 3], to: [u8; 3], thirds: u16) -> [u8; 3] {
+    let mut color = [0; 3];
+    let mut i = 0;
+    while i < 3 {
+        let (from, to) = (from[i] as u16, to[i] as u16);
+        color[i] = ((from * (3 - thirds) + to * thirds) / 3) as u8;
+        i += 1;
+    }
+    color
+}
+
+// An RGB color written `#RRGGBB`.
+pub fn parse_color(value: &str) -> Result<[u8; 3], String> {
+    let error = || format!("expected a color as #RRGGBB, got `{}`", value);
+    let digits = value
+        .strip_prefix('#')
+        .filter(|digits| digits.len() == 6 && digits.chars().all(|c| c.is_ascii_hexdigit()))
+        .ok_or_else(error)?;
+    let rgb = u32::from_str_radix(digits, 16).map_err(|_| error())?;
+    let [_, r, g, b] = rgb.to_be_bytes();
+    Ok([r, g, b])
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(palette) = Palette::preset(s) {
+            return Ok(palette);
+        }
+        if !s.starts_with('#') {
+            return Err(format!("unknown palette `{}`", s));
+        }
+        let colors = s
+            .split(',')
+            .map(|color| parse_color(color.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        match colors[..] {
+            [background, foreground] => Ok(Palette::new(background, foreground)),
+            [background, first, second, both] => Ok(Palette {
+                colors: [background, first, second, both],
+            }),
+            _ => Err(format!("expected 2 or 4 colors, got {}", colors.len())),
+        }
+    }
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, [r, g, b]) in self.colors.into_iter().enumerate() {
+            let separator = if index == 0 { "" } else { "," };
+            write!(f, "{}#{:02X}{:02X}{:02X}", separator, r, g, b)?;
+        }
+        Ok(())
+    }
+}
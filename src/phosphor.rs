@@ -0,0 +1,178 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::display::Display;
+use crate::palette::Palette;
+
+/// # Phosphor
+///
+/// A presentation stage giving the screen the afterglow of a CRT. CHIP-8
+/// programs erase a sprite by drawing it again, so a moving sprite is off
+/// for part of every frame and flickers. The phosphor keeps the last
+/// `frames` frames and shows a pixel switched off in the current one in the
+/// color it last had, faded towards the background by `decay` for every
+/// frame since:
+///
+/// ```text
+/// color = background + (lit - background) * decay^frames_since_lit
+/// ```
+///
+/// The result is an RGBA presentation buffer in the colors of a `Palette`,
+/// which any frontend can upload as is. Frontends presenting once per frame
+/// call `present`; those drawing more or less often call `push` once per
+/// frame of the machine, before running it, and `rgba` when they draw. A
+/// switch of resolution starts over.
+///
+/// Settings are written `FRAMES[,DECAY]`, e.g. `4` or `3,0.4`.
+#[derive(Debug, Clone, Default)]
+pub struct Phosphor {
+    config: PhosphorConfig,
+
+    // Colors of the previous frames, most recent first
+    history: VecDeque<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhosphorConfig {
+    // Frames blended, the current one included; 1 shows the screen as is
+    pub frames: u8,
+
+    // Brightness a pixel keeps for every frame since it was switched off,
+    // from 0 to 1
+    pub decay: f32,
+}
+
+impl Default for PhosphorConfig {
+    fn default() -> Self {
+        PhosphorConfig {
+            frames: 4,
+            decay: 0.5,
+        }
+    }
+}
+
+impl FromStr for PhosphorConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (frames, decay) = match s.split_once(',') {
+            Some((frames, decay)) => (frames, Some(decay)),
+            None => (s, None),
+        };
+        let frames = frames
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&frames| frames > 0)
+            .ok_or_else(|| format!("expected a number of frames, got `{}`", frames))?;
+        let decay = match decay {
+            Some(decay) => decay
+                .trim()
+                .parse()
+                .ok()
+                .filter(|decay| (0.0..=1.0).contains(decay))
+                .ok_or_else(|| format!("expected a decay from 0 to 1, got `{}`", decay))?,
+            None => PhosphorConfig::default().decay,
+        };
+        Ok(PhosphorConfig { frames, decay })
+    }
+}
+
+impl fmt::Display for PhosphorConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.frames, self.decay)
+    }
+}
+
+impl Phosphor {
+    pub fn new(config: PhosphorConfig) -> Phosphor {
+        Phosphor {
+            config,
+            history: VecDeque::new(),
+        }
+    }
+
+    pub fn config(&self) -> PhosphorConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: PhosphorConfig) {
+        self.config = config;
+        self.history.truncate(self.previous_frames());
+    }
+
+    // Forget the previous frames, e.g. when another program is loaded.
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+
+    // Keep the frame on the screen before the machine replaces it.
+    pub fn push(&mut self, display: &Display) {
+        let colors = display.colors();
+        if self
+            .history
+            .front()
+            .is_some_and(|previous| previous.len() != colors.len())
+        {
+            self.history.clear();
+        }
+        if self.previous_frames() == 0 {
+            return;
+        }
+        if self.history.len() == self.previous_frames() {
+            self.history.pop_back();
+        }
+        self.history.push_front(colors.to_vec());
+    }
+
+    // The screen with the previous frames fading out, as RGBA bytes in
+    // row-major order.
+    pub fn rgba(&self, display: &Display, palette: &Palette) -> Vec<u8> {
+        let background = palette.background();
+        let colors = display.colors();
+        let history: Vec<&Vec<u8>> = self
+            .history
+            .iter()
+            .take_while(|frame| frame.len() == colors.len())
+            .collect();
+        let mut rgba = Vec::with_capacity(colors.len() * 4);
+        for (i, &color) in colors.iter().enumerate() {
+            let [r, g, b] = if color != 0 {
+                palette.color(color)
+            } else {
+                match history.iter().position(|frame| frame[i] != 0) {
+                    Some(index) => {
+                        let brightness = self.config.decay.powi(index as i32 + 1);
+                        fade(background, palette.color(history[index][i]), brightness)
+                    }
+                    None => background,
+                }
+            };
+            rgba.extend([r, g, b, 0xFF]);
+        }
+        rgba
+    }
+
+    // The RGBA bytes of the screen, then keep it for the frames after; for
+    // frontends presenting every frame once.
+    pub fn present(&mut self, display: &Display, palette: &Palette) -> Vec<u8> {
+        let rgba = self.rgba(display, palette);
+        self.push(display);
+        rgba
+    }
+
+    fn previous_frames(&self) -> usize {
+        self.config.frames.saturating_sub(1) as usize
+    }
+}
+
+// The color `brightness` of the way from `background` to `lit`.
+fn fade(background: [u8; 3], lit: [u8; 3], brightness: f32) -> [u8; 3] {
+    let mut color = background;
+    for (channel, lit) in color.iter_mut().zip(lit) {
+        let from = *channel as f32;
+        *channel = (from + (lit as f32 - from) * brightness).round() as u8;
+    }
+    color
+}
@@ -0,0 +1,277 @@
+/// # PNG Encoder
+///
+/// A minimal PNG writer, enough for exporting screenshots, recordings and
+/// visualizations without pulling in an image library: 8-bit RGB images,
+/// indexed images of up to 256 colors, and animated PNGs (APNG) made of
+/// indexed frames. The pixel data is compressed with the fixed Huffman codes
+/// of deflate, finding repeats through the last place each three bytes were
+/// seen. That catches the runs of pixels and the repeated rows which scaled
+/// up emulator screens are made of, while keeping the encoder tiny.
+pub fn encode_rgb(width: u32, height: u32, rgb: &[u8]) -> Vec<u8> {
+    assert_eq!(rgb.len(), (width * height * 3) as usize);
+
+    let mut png = signature(width, height, COLOR_TYPE_RGB);
+    chunk(
+        &mut png,
+        b"IDAT",
+        &zlib(&scanlines(rgb, width as usize * 3)),
+    );
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+// An image of one byte per pixel, indexing into `palette`.
+pub fn encode_indexed(width: u32, height: u32, palette: &[[u8; 3]], indices: &[u8]) -> Vec<u8> {
+    encode_apng(
+        width,
+        height,
+        palette,
+        &[Frame {
+            indices: indices.to_vec(),
+            delay: (0, 0),
+        }],
+    )
+}
+
+/// One frame of an animation in palette indices, shown for `delay.0` /
+/// `delay.1` seconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub indices: Vec<u8>,
+    pub delay: (u16, u16),
+}
+
+// An animated PNG of indexed frames, looping forever. Viewers without APNG
+// support show the first frame, which makes a single frame a plain indexed
+// image.
+pub fn encode_apng(width: u32, height: u32, palette: &[[u8; 3]], frames: &[Frame]) -> Vec<u8> {
+    assert!(!frames.is_empty() && palette.len() <= 256);
+
+    let mut png = signature(width, height, COLOR_TYPE_INDEXED);
+    chunk(&mut png, b"PLTE", palette.as_flattened());
+    if frames.len() > 1 {
+        let mut control = (frames.len() as u32).to_be_bytes().to_vec();
+        control.extend(0u32.to_be_bytes());
+        chunk(&mut png, b"acTL", &control);
+    }
+    // Frame control and frame data chunks share one sequence.
+    let mut sequence = 0u32;
+    for (index, frame) in frames.iter().enumerate() {
+        assert_eq!(frame.indices.len(), (width * height) as usize);
+        if frames.len() > 1 {
+            let mut control = sequence.to_be_bytes().to_vec();
+            for value in [width, height, 0, 0] {
+                control.extend(value.to_be_bytes());
+            }
+            control.extend(frame.delay.0.to_be_bytes());
+            control.extend(frame.delay.1.to_be_bytes());
+            // Dispose op none, blend op source.
+            control.extend([0, 0]);
+            chunk(&mut png, b"fcTL", &control);
+            sequence += 1;
+        }
+        let data = zlib(&scanlines(&frame.indices, width as usize));
+        if index == 0 {
+            chunk(&mut png, b"IDAT", &data);
+        } else {
+            let mut fdat = sequence.to_be_bytes().to_vec();
+            fdat.extend(data);
+            chunk(&mut png, b"fdAT", &fdat);
+            sequence += 1;
+        }
+    }
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+const COLOR_TYPE_RGB: u8 = 2;
+const COLOR_TYPE_INDEXED: u8 = 3;
+
+// The PNG signature and header of an 8-bit image.
+fn signature(width: u32, height: u32, color_type: u8) -> Vec<u8> {
+    let mut header = Vec::with_capacity(13);
+    header.extend(width.to_be_bytes());
+    header.extend(height.to_be_bytes());
+    // Bit depth 8, default compression, filter and interlace methods.
+    header.extend([8, color_type, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    chunk(&mut png, b"IHDR", &header);
+    png
+}
+
+// Every scanline starts with filter type 0 (none).
+fn scanlines(pixels: &[u8], row_size: usize) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(pixels.len() + pixels.len() / row_size.max(1));
+    for row in pixels.chunks(row_size.max(1)) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    raw
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+// Deflate looks back this far for repeats, and repeats are at most this long.
+const WINDOW: usize = 32768;
+const MAX_MATCH: usize = 258;
+
+// Lengths and distances of repeats: the smallest value of each code, and
+// the number of extra bits following it.
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+// Wrap the data in a zlib stream of a single deflate block with the fixed
+// Huffman codes.
+fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut out = BitWriter {
+        bytes: vec![0x78, 0x01],
+        used: 0,
+    };
+    // Final block, fixed codes.
+    out.bits(1, 1);
+    out.bits(1, 2);
+
+    let hash = |i: usize| {
+        ((data[i] as usize) << 10 ^ (data[i + 1] as usize) << 5 ^ data[i + 2] as usize) & 0x7FFF
+    };
+    let mut last_seen = vec![usize::MAX; 0x8000];
+    let mut i = 0;
+    while i < data.len() {
+        if i + 3 <= data.len() {
+            let candidate = std::mem::replace(&mut last_seen[hash(i)], i);
+            if candidate != usize::MAX && i - candidate <= WINDOW {
+                let length = (0..MAX_MATCH.min(data.len() - i))
+                    .take_while(|&k| data[candidate + k] == data[i + k])
+                    .count();
+                if length >= 3 {
+                    out.repeat(length, i - candidate);
+                    for j in i + 1..(i + length).min(data.len() - 2) {
+                        last_seen[hash(j)] = j;
+                    }
+                    i += length;
+                    continue;
+                }
+            }
+        }
+        out.symbol(data[i] as u16);
+        i += 1;
+    }
+    out.symbol(256);
+
+    let mut zlib = out.bytes;
+    zlib.extend(adler32(data).to_be_bytes());
+    zlib
+}
+
+// Writes deflate's bit stream, starting with the least significant bit of
+// each byte.
+struct BitWriter {
+    bytes: Vec<u8>,
+
+    // Bits used in the last byte
+    used: u32,
+}
+
+impl BitWriter {
+    fn bits(&mut self, value: u32, count: u32) {
+        for n in 0..count {
+            if self.used == 0 {
+                self.bytes.push(0);
+            }
+            let bit = (value >> n) & 1;
+            *self.bytes.last_mut().expect("a byte was pushed") |= (bit as u8) << self.used;
+            self.used = (self.used + 1) % 8;
+        }
+    }
+
+    // Huffman codes go most significant bit first.
+    fn code(&mut self, code: u32, length: u32) {
+        for n in (0..length).rev() {
+            self.bits(code >> n, 1);
+        }
+    }
+
+    // A literal byte, the end of the block (256) or a length code.
+    fn symbol(&mut self, symbol: u16) {
+        let symbol = symbol as u32;
+        match symbol {
+            0..=143 => self.code(0x30 + symbol, 8),
+            144..=255 => self.code(0x190 + symbol - 144, 9),
+            256..=279 => self.code(symbol - 256, 7),
+            _ => self.code(0xC0 + symbol - 280, 8),
+        }
+    }
+
+    fn repeat(&mut self, length: usize, distance: usize) {
+        let code = LENGTH_BASES.partition_point(|&base| base as usize <= length) - 1;
+        self.symbol(257 + code as u16);
+        self.bits(
+            (length - LENGTH_BASES[code] as usize) as u32,
+            LENGTH_EXTRA[code] as u32,
+        );
+        let code = DISTANCE_BASES.partition_point(|&base| base as usize <= distance) - 1;
+        self.code(code as u32, 5);
+        self.bits(
+            (distance - DISTANCE_BASES[code] as usize) as u32,
+            DISTANCE_EXTRA[code] as u32,
+        );
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+// Enlarge an RGB image by an integer factor using nearest neighbour sampling.
+pub fn scale_rgb(width: u32, height: u32, rgb: &[u8], factor: u32) -> Vec<u8> {
+    let (width, height, factor) = (width as usize, height as usize, factor as usize);
+    let mut out = Vec::with_capacity(rgb.len() * factor * factor);
+    for y in 0..height * factor {
+        for x in 0..width * factor {
+            let i = ((y / factor) * width + x / factor) * 3;
+            out.extend_from_slice(&rgb[i..i + 3]);
+        }
+    }
+    out
+}
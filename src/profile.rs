@@ -0,0 +1,215 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::time::Duration;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::Instant;
+
+use crate::disasm;
+use crate::instruction::{decode, Instruction};
+
+/// # Profile
+///
+/// Counts how often each instruction executes, attributed to the chain of
+/// subroutines it was reached through. The chain is tracked on a shadow
+/// stack of subroutine entry addresses, pushed on CALL (2nnn) and popped on
+/// RET (00EE), since the machine's own stack only holds return addresses.
+///
+/// `to_folded` exports the counts in the folded stacks format understood by
+/// flamegraph tools such as inferno and speedscope, one line per distinct
+/// stack:
+///
+/// ```text
+/// main;sub_0x2A0;0x2A6 1520
+/// ```
+///
+/// Every frame below `main` is a subroutine named after its entry address,
+/// and the leaf is the address of the executed instruction.
+///
+/// Executions are also counted per opcode pattern (`Dxyn`, `8xy4`, ...),
+/// and the time spent executing is split between DRW and every other
+/// instruction, which is where a frame's budget usually goes. `to_report`
+/// sums it all up for `chip8 run --profile`. Browsers have no clock for
+/// the machine to read, there the times stay zero.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    // Executions per instruction address
+    counts: HashMap<u16, u64>,
+
+    // The opcode last executed at each address, for the report
+    opcodes: HashMap<u16, u16>,
+
+    // Executions per opcode pattern, `invalid` for opcodes which decode to
+    // no instruction
+    patterns: HashMap<&'static str, u64>,
+
+    // Time spent executing DRW, and everything else
+    draw_time: Duration,
+    other_time: Duration,
+
+    // Executions per call stack, the instruction address being the last
+    // element
+    stacks: HashMap<Vec<u16>, u64>,
+
+    // Entry addresses of the active subroutines
+    calls: Vec<u16>,
+}
+
+// Deepest call chain tracked, the size of the CHIP-8 stack.
+const MAX_DEPTH: usize = 16;
+
+impl Profile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Record the execution of `opcode` at `pc`.
+    pub fn record(&mut self, pc: u16, opcode: u16) {
+        *self.counts.entry(pc).or_insert(0) += 1;
+        self.opcodes.insert(pc, opcode);
+        let pattern = decode(opcode).map_or("invalid", Instruction::pattern);
+        *self.patterns.entry(pattern).or_insert(0) += 1;
+
+        let mut stack = self.calls.clone();
+        stack.push(pc);
+        *self.stacks.entry(stack).or_insert(0) += 1;
+
+        match decode(opcode) {
+            Ok(Instruction::Call { addr }) => {
+                if self.calls.len() == MAX_DEPTH {
+                    self.calls.remove(0);
+                }
+                self.calls.push(addr);
+            }
+            Ok(Instruction::Return) => {
+                self.calls.pop();
+            }
+            _ => {}
+        }
+    }
+
+    // Add the time `opcode` took to execute.
+    pub fn record_time(&mut self, opcode: u16, elapsed: Duration) {
+        match decode(opcode) {
+            Ok(Instruction::Draw { .. }) => self.draw_time += elapsed,
+            _ => self.other_time += elapsed,
+        }
+    }
+
+    // Number of instructions executed.
+    pub fn instructions(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    // Number of executions of the instruction at `pc`.
+    pub fn count(&self, pc: u16) -> u64 {
+        self.counts.get(&pc).copied().unwrap_or(0)
+    }
+
+    // Instruction addresses and their execution counts, busiest first.
+    pub fn hot_spots(&self) -> Vec<(u16, u64)> {
+        let mut spots: Vec<_> = self.counts.iter().map(|(&pc, &n)| (pc, n)).collect();
+        spots.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        spots
+    }
+
+    // Opcode patterns and their execution counts, busiest first.
+    pub fn opcode_counts(&self) -> Vec<(&'static str, u64)> {
+        let mut counts: Vec<_> = self.patterns.iter().map(|(&p, &n)| (p, n)).collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        counts
+    }
+
+    // Time spent executing DRW instructions.
+    pub fn draw_time(&self) -> Duration {
+        self.draw_time
+    }
+
+    // Time spent executing all other instructions.
+    pub fn other_time(&self) -> Duration {
+        self.other_time
+    }
+
+    // A report of the `top` busiest opcode patterns and addresses, with
+    // the share of executions each takes, and the time spent drawing.
+    pub fn to_report(&self, top: usize) -> String {
+        let total = self.instructions();
+        let share = |n: u64| 100.0 * n as f64 / total.max(1) as f64;
+        let mut report = String::new();
+        let _ = writeln!(report, "Instructions: {}", total);
+        let time = self.draw_time + self.other_time;
+        let _ = writeln!(
+            report,
+            "Time: {:.3}ms in DRW ({:.1}%), {:.3}ms in other instructions",
+            self.draw_time.as_secs_f64() * 1000.0,
+            100.0 * self.draw_time.as_secs_f64() / time.as_secs_f64().max(f64::MIN_POSITIVE),
+            self.other_time.as_secs_f64() * 1000.0
+        );
+        let _ = writeln!(report, "\nOpcodes:");
+        for (pattern, count) in self.opcode_counts().into_iter().take(top) {
+            let _ = writeln!(
+                report,
+                "  {:<8} {:>12} {:>6.1}%",
+                pattern,
+                count,
+                share(count)
+            );
+        }
+        let _ = writeln!(report, "\nHot spots:");
+        let symbols = BTreeMap::new();
+        for (pc, count) in self.hot_spots().into_iter().take(top) {
+            let opcode = self.opcodes.get(&pc).copied().unwrap_or_default();
+            let instruction = match decode(opcode) {
+                Ok(instruction) => disasm::mnemonic(instruction, &symbols),
+                Err(_) => format!("0x{:04X}", opcode),
+            };
+            let _ = writeln!(
+                report,
+                "  0x{:03X}  {:<20} {:>12} {:>6.1}%",
+                pc,
+                instruction,
+                count,
+                share(count)
+            );
+        }
+        report
+    }
+
+    // Export the stacks in the folded format, sorted for stable output.
+    pub fn to_folded(&self) -> String {
+        let mut stacks: Vec<_> = self.stacks.iter().collect();
+        stacks.sort();
+        let mut folded = String::new();
+        for (stack, count) in stacks {
+            let (leaf, calls) = stack.split_last().expect("stacks are never empty");
+            folded.push_str("main");
+            for entry in calls {
+                let _ = write!(folded, ";sub_0x{:03X}", entry);
+            }
+            let _ = writeln!(folded, ";0x{:03X} {}", leaf, count);
+        }
+        folded
+    }
+}
+
+// Times the execution of an instruction for the profile.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Stopwatch {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    started_at: Instant,
+}
+
+impl Stopwatch {
+    pub(crate) fn start() -> Stopwatch {
+        Stopwatch {
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            started_at: Instant::now(),
+        }
+    }
+
+    pub(crate) fn elapsed(self) -> Duration {
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        return self.started_at.elapsed();
+        #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+        return Duration::ZERO;
+    }
+}
@@ -0,0 +1,125 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cpu::Chip8;
+use crate::savestate::{MachineState, Thumbnail};
+
+/// # Quick Saves
+///
+/// Save state slots behind the frontends' hotkeys: F5 saves the machine
+/// into the selected slot, F9 loads it back, F6 and F7 select the previous
+/// and next slot. Given the path of the ROM, slot n is kept in the file
+/// `<rom>.state<n>` next to it, so a frozen game can be picked up again
+/// after a restart; otherwise the slots only last as long as the frontend.
+/// The frontends preview the thumbnail saved with the selected slot, see
+/// `thumbnail`, whenever one of the hotkeys is pressed.
+#[derive(Debug, Clone)]
+pub struct QuickSaves {
+    slots: [Option<Vec<u8>>; SLOTS],
+    selected: usize,
+    rom_path: Option<PathBuf>,
+}
+
+/// The number of slots.
+pub const SLOTS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hotkey {
+    Save,
+    Load,
+    PreviousSlot,
+    NextSlot,
+}
+
+impl Hotkey {
+    // The hotkey bound to a host key, named as for `KeyMap`.
+    pub fn from_name(name: &str) -> Option<Hotkey> {
+        match name {
+            "F5" => Some(Hotkey::Save),
+            "F9" => Some(Hotkey::Load),
+            "F6" => Some(Hotkey::PreviousSlot),
+            "F7" => Some(Hotkey::NextSlot),
+            _ => None,
+        }
+    }
+}
+
+impl QuickSaves {
+    pub fn new(rom_path: Option<&Path>) -> QuickSaves {
+        QuickSaves {
+            slots: Default::default(),
+            selected: 0,
+            rom_path: rom_path.map(Path::to_path_buf),
+        }
+    }
+
+    // The selected slot, numbered from 1.
+    pub fn selected(&self) -> usize {
+        self.selected + 1
+    }
+
+    // The file slot n (from 1) is kept in, if the slots are kept in files.
+    pub fn path(&self, slot: usize) -> Option<PathBuf> {
+        let rom_path = self.rom_path.as_ref()?;
+        let mut name = rom_path.file_name()?.to_os_string();
+        name.push(format!(".state{}", slot));
+        Some(rom_path.with_file_name(name))
+    }
+
+    // Act on a hotkey, returning a message for the status line.
+    pub fn handle(&mut self, chip8: &mut Chip8, hotkey: Hotkey) -> String {
+        let slot = self.selected();
+        match hotkey {
+            Hotkey::Save => match self.save(chip8) {
+                Ok(()) => format!("Saved slot {}", slot),
+                Err(e) => format!("Failed to save slot {}: {}", slot, e),
+            },
+            Hotkey::Load => match self.load(chip8) {
+                Ok(()) => format!("Loaded slot {}", slot),
+                Err(e) => format!("Failed to load slot {}: {}", slot, e),
+            },
+            Hotkey::PreviousSlot => {
+                self.selected = (self.selected + SLOTS - 1) % SLOTS;
+                format!("Slot {}", self.selected())
+            }
+            Hotkey::NextSlot => {
+                self.selected = (self.selected + 1) % SLOTS;
+                format!("Slot {}", self.selected())
+            }
+        }
+    }
+
+    // Save the machine into the selected slot.
+    pub fn save(&mut self, chip8: &Chip8) -> Result<(), String> {
+        let state = chip8.save_state();
+        if let Some(path) = self.path(self.selected()) {
+            fs::write(&path, &state).map_err(|e| e.to_string())?;
+        }
+        self.slots[self.selected] = Some(state);
+        Ok(())
+    }
+
+    // Restore the machine from the selected slot.
+    pub fn load(&mut self, chip8: &mut Chip8) -> Result<(), String> {
+        let state = self.state()?.ok_or("the slot is empty")?;
+        chip8.load_state(state).map_err(|e| e.to_string())
+    }
+
+    // The thumbnail of the screen saved in the selected slot, none if the
+    // slot is empty, cannot be read, or holds a state without one.
+    pub fn thumbnail(&mut self) -> Option<Thumbnail> {
+        let state = self.state().ok()??;
+        MachineState::decode(state).ok()?.thumbnail
+    }
+
+    // The state in the selected slot, reading it from its file if it was
+    // not saved since the frontend started.
+    fn state(&mut self) -> Result<Option<&[u8]>, String> {
+        if self.slots[self.selected].is_none() {
+            if let Some(path) = self.path(self.selected()).filter(|path| path.exists()) {
+                self.slots[self.selected] = Some(fs::read(path).map_err(|e| e.to_string())?);
+            }
+        }
+        Ok(self.slots[self.selected].as_deref())
+    }
+}
@@ -0,0 +1,280 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::str::FromStr;
+
+#[cfg(feature = "std")]
+use crate::cartridge::Variant;
+#[cfg(feature = "std")]
+use crate::cpu::Chip8;
+use crate::instruction::{decode, Instruction};
+
+/// # Quirks
+///
+/// The CHIP-8 interpreters that followed the original COSMAC VIP one did
+/// not agree on every instruction, and games were written against the one
+/// their authors had. The differences which matter for this machine can be
+/// switched on and off individually:
+///
+/// - `vf_reset`: 8xy1, 8xy2 and 8xy3 reset VF to 0 (COSMAC)
+/// - `memory`: Fx55 and Fx65 leave I past the last register (COSMAC)
+/// - `shifting`: 8xy6 and 8xyE shift Vx in place instead of shifting Vy
+///   into Vx (SUPER-CHIP)
+/// - `jumping`: Bxnn jumps to xnn + Vx instead of xnn + V0 (SUPER-CHIP)
+///
+/// Quirks are given as a preset (`chip8`, `schip`, `xochip` or `default`),
+/// optionally followed by overrides, e.g. `schip,memory=on`. The default is
+/// what this emulator always did: SUPER-CHIP shifts, everything else COSMAC
+/// apart from the VF reset and I increment.
+///
+/// For ROMs of unknown origin, `detect` picks a preset by running the ROM
+/// briefly under each one, on the variant it is going to run as, and
+/// looking for signs of malfunction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    pub vf_reset: bool,
+    pub memory: bool,
+    pub shifting: bool,
+    pub jumping: bool,
+}
+
+impl Quirks {
+    // The original COSMAC VIP interpreter.
+    pub const CHIP8: Quirks = Quirks {
+        vf_reset: true,
+        memory: true,
+        shifting: false,
+        jumping: false,
+    };
+
+    // SUPER-CHIP 1.1 on the HP-48.
+    pub const SCHIP: Quirks = Quirks {
+        vf_reset: false,
+        memory: false,
+        shifting: true,
+        jumping: true,
+    };
+
+    // XO-CHIP, as implemented by Octo.
+    pub const XOCHIP: Quirks = Quirks {
+        vf_reset: false,
+        memory: true,
+        shifting: false,
+        jumping: false,
+    };
+
+    pub const NAMES: [&'static str; 4] = ["vf_reset", "memory", "shifting", "jumping"];
+
+    pub fn get(&self, name: &str) -> Option<bool> {
+        match name {
+            "vf_reset" => Some(self.vf_reset),
+            "memory" => Some(self.memory),
+            "shifting" => Some(self.shifting),
+            "jumping" => Some(self.jumping),
+            _ => None,
+        }
+    }
+
+    // Override a single quirk, `None` if there is no quirk called `name`.
+    pub fn set(&mut self, name: &str, on: bool) -> Option<()> {
+        match name {
+            "vf_reset" => self.vf_reset = on,
+            "memory" => self.memory = on,
+            "shifting" => self.shifting = on,
+            "jumping" => self.jumping = on,
+            _ => return None,
+        }
+        Some(())
+    }
+
+    // Names of the quirks set differently in `other`.
+    #[cfg(feature = "std")]
+    pub fn differences(&self, other: &Quirks) -> Vec<&'static str> {
+        Quirks::NAMES
+            .into_iter()
+            .filter(|name| self.get(name) != other.get(name))
+            .collect()
+    }
+
+    // Name of the quirk changing the behavior of `opcode`, if any.
+    pub fn affecting(opcode: u16) -> Option<&'static str> {
+        match decode(opcode).ok()? {
+            Instruction::Or { .. } | Instruction::And { .. } | Instruction::Xor { .. } => {
+                Some("vf_reset")
+            }
+            Instruction::ShiftRight { .. } | Instruction::ShiftLeft { .. } => Some("shifting"),
+            Instruction::JumpWithOffset { .. } => Some("jumping"),
+            Instruction::StoreRegisters { .. } | Instruction::LoadRegisters { .. } => {
+                Some("memory")
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The interpreters the presets are modelled on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    CosmacVip,
+    SuperChip,
+    XoChip,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::CosmacVip, Preset::SuperChip, Preset::XoChip];
+
+    // Name of the preset in quirk specs.
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::CosmacVip => "chip8",
+            Preset::SuperChip => "schip",
+            Preset::XoChip => "xochip",
+        }
+    }
+
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Preset::CosmacVip => Quirks::CHIP8,
+            Preset::SuperChip => Quirks::SCHIP,
+            Preset::XoChip => Quirks::XOCHIP,
+        }
+    }
+}
+
+impl From<Preset> for Quirks {
+    fn from(preset: Preset) -> Quirks {
+        preset.quirks()
+    }
+}
+
+/// How badly a ROM behaved under a set of quirks during detection, lower
+/// is better.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuirkScore {
+    pub quirks: Quirks,
+
+    // Faults raised: invalid memory accesses, unknown opcodes, stack overflows
+    pub faults: u64,
+
+    // Frame of the first fault, if any
+    pub first_fault: Option<u64>,
+
+    pub draw_calls: u64,
+}
+
+// Presets tried by `detect`, the first one winning ties.
+const CANDIDATES: [Quirks; 4] = [
+    Quirks {
+        vf_reset: false,
+        memory: false,
+        shifting: true,
+        jumping: false,
+    },
+    Quirks::CHIP8,
+    Quirks::SCHIP,
+    Quirks::XOCHIP,
+];
+
+impl QuirkScore {
+    // Faults are bad, faults right away worse, since a wrong quirk usually
+    // derails a program within its first frames. A program which never draws
+    // is likely stuck.
+    pub fn penalty(&self, frames: u64) -> u64 {
+        let early = self
+            .first_fault
+            .map_or(0, |frame| frames - frame.min(frames));
+        let blank = if self.draw_calls == 0 { frames } else { 0 };
+        self.faults * 4 + early + blank
+    }
+}
+
+// Run the ROM as `variant` for `frames` frames under every candidate preset
+// and return the one that malfunctioned the least, along with all the
+// scores. ROMs without any sign of trouble keep the default.
+#[cfg(feature = "std")]
+pub fn detect(
+    rom: &[u8],
+    variant: Variant,
+    frames: u64,
+    cycles_per_frame: usize,
+) -> (Quirks, Vec<QuirkScore>) {
+    let scores: Vec<QuirkScore> = CANDIDATES
+        .iter()
+        .map(|&quirks| {
+            let mut chip8 = Chip8::new();
+            chip8.set_variant(variant);
+            chip8.set_seed(0);
+            chip8.set_quirks(quirks);
+            // A ROM which does not fit runs nothing under any preset, which
+            // keeps the default.
+            let _ = chip8.load_rom(rom);
+            let mut first_fault = None;
+            for frame in 0..frames {
+                chip8.run_frame(cycles_per_frame);
+                if first_fault.is_none() && chip8.telemetry().errors > 0 {
+                    first_fault = Some(frame);
+                }
+            }
+            QuirkScore {
+                quirks,
+                faults: chip8.telemetry().errors,
+                first_fault,
+                draw_calls: chip8.telemetry().draw_calls,
+            }
+        })
+        .collect();
+    let best = scores
+        .iter()
+        .min_by_key(|score| score.penalty(frames))
+        .map_or_else(Quirks::default, |score| score.quirks);
+    (best, scores)
+}
+
+impl Default for Quirks {
+    fn default() -> Quirks {
+        CANDIDATES[0]
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for Quirks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',').map(str::trim);
+        let mut quirks = match parts.next().unwrap_or_default() {
+            "default" => Quirks::default(),
+            name => Preset::ALL
+                .into_iter()
+                .find(|preset| preset.name() == name)
+                .ok_or_else(|| format!("unknown quirk preset `{}`", name))?
+                .quirks(),
+        };
+        for part in parts {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected <quirk>=on|off, got `{}`", part))?;
+            let on = match value {
+                "on" => true,
+                "off" => false,
+                _ => return Err(format!("expected on or off for `{}`", name)),
+            };
+            quirks
+                .set(name, on)
+                .ok_or_else(|| format!("unknown quirk `{}`", name))?;
+        }
+        Ok(quirks)
+    }
+}
+
+impl fmt::Display for Quirks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut on = Quirks::NAMES
+            .into_iter()
+            .filter(|name| self.get(name) == Some(true));
+        let Some(first) = on.next() else {
+            return write!(f, "none");
+        };
+        f.write_str(first)?;
+        on.try_for_each(|name| write!(f, ", {}", name))
+    }
+}
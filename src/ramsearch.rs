@@ -0,0 +1,191 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// # RAM Search
+///
+/// The classic cheat finder: to locate e.g. the lives counter of a game,
+/// start a search, lose a life, search for "decreased", play on without
+/// dying, search for "unchanged", and so on. Every search compares memory
+/// against the snapshot taken by the previous one and keeps only the
+/// addresses matching the condition, until a handful of candidates remain
+/// which can be turned into cheats or poked to check.
+///
+/// Values are single bytes, or 16-bit words stored most significant byte
+/// first at the candidate address and the one after, for counters such as
+/// scores which do not fit a byte.
+///
+/// Conditions can be parsed from short strings, as typed in a debugger:
+///
+/// - `= 5`, `!= 5` - the value equals (or differs from) a number
+/// - `changed`, `unchanged` - the value changed since the last search
+/// - `increased`, `decreased` - the value grew (or shrank)
+/// - `+1`, `-1` - the value grew (or shrank) by exactly that much
+///
+/// Numbers are decimal, or hexadecimal with a `0x` prefix.
+#[derive(Debug, Clone)]
+pub struct RamSearch {
+    width: Width,
+    candidates: Vec<usize>,
+    snapshot: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Width {
+    #[default]
+    Byte,
+    Word,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Equal(u16),
+    NotEqual(u16),
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+    IncreasedBy(u16),
+    DecreasedBy(u16),
+}
+
+impl RamSearch {
+    // Start a search with every address of `memory` as a candidate.
+    pub fn new(memory: &[u8]) -> RamSearch {
+        RamSearch::with_width(memory, Width::Byte)
+    }
+
+    // Start a search for values of `width`, with every address holding a
+    // whole one as a candidate.
+    pub fn with_width(memory: &[u8], width: Width) -> RamSearch {
+        let end = (memory.len() + 1).saturating_sub(width.bytes());
+        RamSearch {
+            width,
+            candidates: (0..end).collect(),
+            snapshot: memory.to_vec(),
+        }
+    }
+
+    pub fn width(&self) -> Width {
+        self.width
+    }
+
+    // Keep the candidates matching `condition` against the previous
+    // snapshot, then take a new snapshot. Returns the number left.
+    pub fn filter(&mut self, memory: &[u8], condition: Condition) -> usize {
+        let (snapshot, width) = (&self.snapshot, self.width);
+        self.candidates.retain(|&address| {
+            match (width.read(snapshot, address), width.read(memory, address)) {
+                (Some(old), Some(new)) => condition.matches(width, old, new),
+                _ => false,
+            }
+        });
+        self.snapshot = memory.to_vec();
+        self.candidates.len()
+    }
+
+    pub fn candidates(&self) -> &[usize] {
+        &self.candidates
+    }
+
+    // The candidates with their value in the last snapshot.
+    pub fn results(&self) -> Vec<(usize, u16)> {
+        self.candidates
+            .iter()
+            .filter_map(|&address| Some((address, self.width.read(&self.snapshot, address)?)))
+            .collect()
+    }
+}
+
+impl Width {
+    pub fn bytes(self) -> usize {
+        match self {
+            Width::Byte => 1,
+            Width::Word => 2,
+        }
+    }
+
+    // Largest value of this width, values wrap around past it.
+    pub fn max(self) -> u16 {
+        match self {
+            Width::Byte => 0xFF,
+            Width::Word => 0xFFFF,
+        }
+    }
+
+    // The value at `address`, none if it does not fit in `memory`.
+    pub fn read(self, memory: &[u8], address: usize) -> Option<u16> {
+        match self {
+            Width::Byte => memory.get(address).map(|&byte| byte as u16),
+            Width::Word => match memory.get(address..address + 2)? {
+                &[high, low] => Some(u16::from_be_bytes([high, low])),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl Condition {
+    // Whether a value of `width` going from `old` to `new` matches.
+    pub fn matches(&self, width: Width, old: u16, new: u16) -> bool {
+        match *self {
+            Condition::Equal(value) => new == value,
+            Condition::NotEqual(value) => new != value,
+            Condition::Changed => new != old,
+            Condition::Unchanged => new == old,
+            Condition::Increased => new > old,
+            Condition::Decreased => new < old,
+            Condition::IncreasedBy(delta) => new == old.wrapping_add(delta) & width.max(),
+            Condition::DecreasedBy(delta) => new == old.wrapping_sub(delta) & width.max(),
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let value = |text: &str| -> Result<u16, String> {
+            let text = text.trim();
+            match text.strip_prefix("0x") {
+                Some(hex) => u16::from_str_radix(hex, 16),
+                None => text.parse(),
+            }
+            .map_err(|_| format!("invalid value `{}`", text))
+        };
+        match s {
+            "changed" => Ok(Condition::Changed),
+            "unchanged" => Ok(Condition::Unchanged),
+            "increased" => Ok(Condition::Increased),
+            "decreased" => Ok(Condition::Decreased),
+            _ => {
+                if let Some(rest) = s.strip_prefix("!=") {
+                    Ok(Condition::NotEqual(value(rest)?))
+                } else if let Some(rest) = s.strip_prefix('=') {
+                    Ok(Condition::Equal(value(rest)?))
+                } else if let Some(rest) = s.strip_prefix('+') {
+                    Ok(Condition::IncreasedBy(value(rest)?))
+                } else if let Some(rest) = s.strip_prefix('-') {
+                    Ok(Condition::DecreasedBy(value(rest)?))
+                } else {
+                    Err(format!("unknown search condition `{}`", s))
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Equal(value) => write!(f, "= {}", value),
+            Condition::NotEqual(value) => write!(f, "!= {}", value),
+            Condition::Changed => write!(f, "changed"),
+            Condition::Unchanged => write!(f, "unchanged"),
+            Condition::Increased => write!(f, "increased"),
+            Condition::Decreased => write!(f, "decreased"),
+            Condition::IncreasedBy(delta) => write!(f, "+{}", delta),
+            Condition::DecreasedBy(delta) => write!(f, "-{}", delta),
+        }
+    }
+}
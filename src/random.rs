@@ -0,0 +1,91 @@
+use core::fmt;
+
+/// # Random Numbers
+///
+/// Cxkk draws its bytes from a `RandomSource`. Machines use `SplitMix64`
+/// by default, a small generator whose whole state is one 64-bit word:
+/// seeded through `Chip8::set_seed` a run becomes reproducible, and since
+/// the word is part of save states, loading one (or rewinding, or rolling
+/// back) draws the same numbers again. Tests can install a `FixedRandom`
+/// with `Chip8::set_random_source` to control exactly what Cxkk sees.
+pub trait RandomSource: fmt::Debug {
+    fn next_byte(&mut self) -> u8;
+
+    // The state to keep in save states, none if the source cannot be
+    // restored.
+    fn state(&self) -> Option<u64> {
+        None
+    }
+
+    fn restore(&mut self, _state: u64) {}
+}
+
+/// SplitMix64, as used to seed the xoshiro generators: a counter advanced
+/// by the golden ratio, scrambled by two multiply-xorshift rounds. Every
+/// state is valid, including 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> SplitMix64 {
+        SplitMix64 { state: seed }
+    }
+
+    // Seeded from the operating system, or the Web Crypto API in browsers.
+    #[cfg(feature = "std")]
+    pub fn from_entropy() -> SplitMix64 {
+        SplitMix64::new(rand::random())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+impl RandomSource for SplitMix64 {
+    fn next_byte(&mut self) -> u8 {
+        (self.next_u64() >> 56) as u8
+    }
+
+    fn state(&self) -> Option<u64> {
+        Some(self.state)
+    }
+
+    fn restore(&mut self, state: u64) {
+        self.state = state;
+    }
+}
+
+/// Returns the given bytes in order, starting over after the last one.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedRandom {
+    bytes: Vec<u8>,
+    next: usize,
+}
+
+#[cfg(feature = "std")]
+impl FixedRandom {
+    pub fn new(bytes: &[u8]) -> FixedRandom {
+        assert!(!bytes.is_empty(), "FixedRandom needs at least one byte");
+        FixedRandom {
+            bytes: bytes.to_vec(),
+            next: 0,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl RandomSource for FixedRandom {
+    fn next_byte(&mut self) -> u8 {
+        let byte = self.bytes[self.next];
+        self.next = (self.next + 1) % self.bytes.len();
+        byte
+    }
+}
@@ -0,0 +1,79 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// # Input Recording
+///
+/// The keypad state of every frame of a run, stored as a 16-bit mask per
+/// frame where bit k set means key k is held down. Together with the ROM and
+/// the RNG seed this is enough to reproduce a run exactly.
+///
+/// Recordings are saved as text, one mask of four hexadecimal digits per
+/// line. Blank lines and lines starting with `#` are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputRecording {
+    pub frames: Vec<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordingError {
+    pub line: usize,
+}
+
+impl InputRecording {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Append the keypad state of the next frame.
+    pub fn push(&mut self, keys: u16) {
+        self.frames.push(keys);
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    // Keypad state for a frame, with every key released past the end.
+    pub fn keys(&self, frame: usize) -> u16 {
+        self.frames.get(frame).copied().unwrap_or(0)
+    }
+}
+
+impl FromStr for InputRecording {
+    type Err = RecordingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut recording = Self::new();
+        for (index, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let keys =
+                u16::from_str_radix(line, 16).map_err(|_| RecordingError { line: index + 1 })?;
+            recording.push(keys);
+        }
+        Ok(recording)
+    }
+}
+
+impl fmt::Display for InputRecording {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for keys in &self.frames {
+            writeln!(f, "{:04X}", keys)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid key mask on line {}", self.line)
+    }
+}
+
+impl std::error::Error for RecordingError {}
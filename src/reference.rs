@@ -0,0 +1,345 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::cpu::Chip8;
+use crate::disasm;
+use crate::instruction::decode;
+use crate::json::{self, Json, JsonError};
+use crate::memory;
+use crate::registers::Register;
+
+/// # Reference Traces
+///
+/// The machine state before every instruction of a run, as recorded by
+/// another emulator or by an earlier run of this one, for checking an
+/// implementation (a quirk, a SUPER-CHIP or XO-CHIP instruction) against
+/// one known to be right. `compare` runs a machine in lockstep with the
+/// trace and reports the first instruction after which they disagree.
+///
+/// A trace is a JSON array of objects, or JSON Lines with one object per
+/// line, each holding registers by their `Register` names in any case,
+/// with the V registers also accepted as an array `v`:
+///
+/// ```json
+/// [
+/// {"pc":512,"i":0,"sp":0,"dt":0,"st":0,"v":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]},
+/// {"PC":"0x202","V0":"0x2A"}
+/// ]
+/// ```
+///
+/// Values are integers or strings of hexadecimal ones. Registers an entry
+/// leaves out are not compared, and other keys, such as an opcode or a
+/// cycle count, are ignored, so traces of emulators logging less or more
+/// than this one work as they are.
+///
+/// Each entry is the state before an instruction runs, the first one that
+/// of the freshly loaded program. The timers tick after every
+/// `cycles_per_frame` instructions, so the reference has to run at the same
+/// speed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReferenceTrace {
+    pub steps: Vec<ReferenceStep>,
+}
+
+/// The registers known at one step of a trace.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReferenceStep {
+    pub registers: Vec<(Register, u16)>,
+}
+
+/// Where a run first disagreed with its trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceDivergence {
+    // Index of the entry in the trace
+    pub step: usize,
+    pub frame: u64,
+
+    // The instruction run last before the entry, which is likely the one
+    // implemented differently, or none if the initial states differ
+    pub instruction: Option<(u16, u16)>,
+    pub mismatches: Vec<Mismatch>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mismatch {
+    pub register: Register,
+    pub expected: u16,
+    pub actual: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceError {
+    pub line: usize,
+    pub message: String,
+}
+
+// Registers recorded for every step, in the order they are written.
+const REGISTERS: [Register; 21] = [
+    Register::PC,
+    Register::I,
+    Register::SP,
+    Register::DT,
+    Register::ST,
+    Register::V(0x0),
+    Register::V(0x1),
+    Register::V(0x2),
+    Register::V(0x3),
+    Register::V(0x4),
+    Register::V(0x5),
+    Register::V(0x6),
+    Register::V(0x7),
+    Register::V(0x8),
+    Register::V(0x9),
+    Register::V(0xA),
+    Register::V(0xB),
+    Register::V(0xC),
+    Register::V(0xD),
+    Register::V(0xE),
+    Register::V(0xF),
+];
+
+impl ReferenceTrace {
+    // Record `frames` frames of a run, to compare other runs against.
+    pub fn record(chip8: &mut Chip8, frames: u64, cycles_per_frame: usize) -> ReferenceTrace {
+        let mut steps = Vec::new();
+        for _ in 0..frames {
+            for _ in 0..cycles_per_frame {
+                steps.push(ReferenceStep::of(chip8));
+                chip8.step();
+            }
+            chip8.tick_timers();
+        }
+        ReferenceTrace { steps }
+    }
+
+    // Run the machine along the trace, returning the number of steps which
+    // matched, or where they stopped matching.
+    pub fn compare(
+        &self,
+        chip8: &mut Chip8,
+        cycles_per_frame: usize,
+    ) -> Result<usize, ReferenceDivergence> {
+        let cycles_per_frame = cycles_per_frame.max(1);
+        let mut instruction = None;
+        for (step, expected) in self.steps.iter().enumerate() {
+            if step > 0 && step % cycles_per_frame == 0 {
+                chip8.tick_timers();
+            }
+            let mismatches = expected.mismatches(chip8);
+            if !mismatches.is_empty() {
+                return Err(ReferenceDivergence {
+                    step,
+                    frame: (step / cycles_per_frame) as u64,
+                    instruction,
+                    mismatches,
+                });
+            }
+            let pc = chip8.register(Register::PC);
+            let opcode = u16::from_be_bytes([
+                chip8.peek(pc as usize).unwrap_or(0),
+                chip8.peek(pc as usize + 1).unwrap_or(0),
+            ]);
+            instruction = Some((pc, opcode));
+            chip8.step();
+        }
+        Ok(self.steps.len())
+    }
+
+    // The trace as a JSON array, one step per line.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("[\n");
+        for (index, step) in self.steps.iter().enumerate() {
+            json.push_str(&step.to_json());
+            json.push_str(if index + 1 < self.steps.len() {
+                ",\n"
+            } else {
+                "\n"
+            });
+        }
+        json.push_str("]\n");
+        json
+    }
+}
+
+impl ReferenceStep {
+    // Every register of the machine.
+    pub fn of(chip8: &Chip8) -> ReferenceStep {
+        ReferenceStep {
+            registers: REGISTERS
+                .iter()
+                .map(|&register| (register, chip8.register(register)))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, register: Register) -> Option<u16> {
+        self.registers
+            .iter()
+            .find(|(known, _)| *known == register)
+            .map(|&(_, value)| value)
+    }
+
+    // The registers the machine disagrees on, in the order of the step.
+    pub fn mismatches(&self, chip8: &Chip8) -> Vec<Mismatch> {
+        self.registers
+            .iter()
+            .map(|&(register, expected)| Mismatch {
+                register,
+                expected,
+                actual: chip8.register(register),
+            })
+            .filter(|mismatch| mismatch.expected != mismatch.actual)
+            .collect()
+    }
+
+    // A single-line JSON object, the V registers as an array `v` when all
+    // of them are known.
+    pub fn to_json(&self) -> String {
+        let v: Vec<String> = (0..16)
+            .map_while(|x| self.get(Register::V(x)))
+            .map(|value| value.to_string())
+            .collect();
+        let mut fields: Vec<String> = self
+            .registers
+            .iter()
+            .filter(|(register, _)| v.len() < 16 || !matches!(register, Register::V(_)))
+            .map(|(register, value)| {
+                format!("\"{}\":{}", register.to_string().to_lowercase(), value)
+            })
+            .collect();
+        if v.len() == 16 {
+            fields.push(format!("\"v\":[{}]", v.join(",")));
+        }
+        format!("{{{}}}", fields.join(","))
+    }
+
+    fn set(&mut self, register: Register, value: u16) {
+        match self
+            .registers
+            .iter_mut()
+            .find(|(known, _)| *known == register)
+        {
+            Some((_, known)) => *known = value,
+            None => self.registers.push((register, value)),
+        }
+    }
+}
+
+impl FromStr for ReferenceTrace {
+    type Err = ReferenceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut steps = Vec::new();
+        if s.trim_start().starts_with('[') {
+            let Json::Array(items) = json::parse(s)? else {
+                unreachable!("`[` starts an array");
+            };
+            for (index, item) in items.into_iter().enumerate() {
+                steps.push(step(item).map_err(|message| ReferenceError {
+                    line: 0,
+                    message: format!("step {}: {}", index, message),
+                })?);
+            }
+        } else {
+            for (index, line) in s.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let error = |message: String| ReferenceError {
+                    line: index + 1,
+                    message,
+                };
+                let value = json::parse(line).map_err(|e| error(e.message))?;
+                steps.push(step(value).map_err(error)?);
+            }
+        }
+        Ok(ReferenceTrace { steps })
+    }
+}
+
+// A step of a trace from its JSON object.
+fn step(value: Json) -> Result<ReferenceStep, String> {
+    let Json::Object(fields) = value else {
+        return Err("expected an object".to_string());
+    };
+    let mut step = ReferenceStep::default();
+    for (key, value) in fields {
+        if key.eq_ignore_ascii_case("v") {
+            let Json::Array(values) = value else {
+                return Err("`v` is not an array".to_string());
+            };
+            if values.len() > 16 {
+                return Err("`v` has more than 16 registers".to_string());
+            }
+            for (x, value) in values.into_iter().enumerate() {
+                register_value(&mut step, Register::V(x as u8), value)?;
+            }
+        } else if let Ok(register) = key.parse::<Register>() {
+            register_value(&mut step, register, value)?;
+        }
+    }
+    Ok(step)
+}
+
+fn register_value(step: &mut ReferenceStep, register: Register, value: Json) -> Result<(), String> {
+    let number = match &value {
+        Json::Number(text) => text.parse::<u16>().ok(),
+        Json::String(text) => text
+            .strip_prefix("0x")
+            .or_else(|| text.strip_prefix("0X"))
+            .and_then(|hex| u16::from_str_radix(hex, 16).ok()),
+        _ => None,
+    };
+    let value = number.ok_or_else(|| format!("{}: expected a number", register))?;
+    // The machine is not known yet, any address of XO-CHIP memory will do.
+    register
+        .validate(value, memory::XO_CHIP_SIZE)
+        .map_err(|e| e.to_string())?;
+    step.set(register, value);
+    Ok(())
+}
+
+impl fmt::Display for ReferenceDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Diverged at step {} (frame {})", self.step, self.frame)?;
+        match self.instruction {
+            Some((pc, opcode)) => {
+                let text = decode(opcode)
+                    .map(|instruction| disasm::mnemonic(instruction, &BTreeMap::new()))
+                    .unwrap_or_else(|_| "???".to_string());
+                write!(f, ", after 0x{:03X}: {:04X}  {}", pc, opcode, text)?
+            }
+            None => write!(f, ", in the initial state")?,
+        }
+        for mismatch in &self.mismatches {
+            write!(
+                f,
+                "\n  {}: expected 0x{:X}, got 0x{:X}",
+                mismatch.register, mismatch.expected, mismatch.actual
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ReferenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "line {}: {}", self.line, self.message)
+        }
+    }
+}
+
+impl std::error::Error for ReferenceError {}
+
+impl From<JsonError> for ReferenceError {
+    fn from(error: JsonError) -> Self {
+        ReferenceError {
+            line: error.line,
+            message: error.message,
+        }
+    }
+}
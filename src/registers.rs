@@ -0,0 +1,108 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// # Registers
+///
+/// Names for every register visible to a debugger, so that tools can inspect
+/// and edit the machine state by name (`V3`, `I`, `PC`, `SP`, `DT`, `ST`).
+/// Edits are validated against the width of the register: Vx, DT and ST are
+/// 8-bit, SP counts the addresses on the 16-level stack, and I and PC hold
+/// addresses of the machine's memory: 12-bit ones on 4K machines, 16-bit
+/// ones on XO-CHIP with its 64K.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Register {
+    V(u8),
+    I,
+    PC,
+    SP,
+    DT,
+    ST,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegisterError {
+    UnknownRegister(String),
+    OutOfRange {
+        register: Register,
+        value: u16,
+        max: u16,
+    },
+}
+
+impl Register {
+    // Largest value the register accepts on a machine with `memory_size`
+    // bytes of memory.
+    pub fn max(self, memory_size: usize) -> u16 {
+        match self {
+            Self::V(_) | Self::DT | Self::ST => 0xFF,
+            Self::I | Self::PC => (memory_size.clamp(1, 0x10000) - 1) as u16,
+            Self::SP => 0x10,
+        }
+    }
+
+    pub fn validate(self, value: u16, memory_size: usize) -> Result<(), RegisterError> {
+        let max = self.max(memory_size);
+        if value > max {
+            Err(RegisterError::OutOfRange {
+                register: self,
+                value,
+                max,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl FromStr for Register {
+    type Err = RegisterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_uppercase();
+        match name.as_str() {
+            "I" => Ok(Self::I),
+            "PC" => Ok(Self::PC),
+            "SP" => Ok(Self::SP),
+            "DT" => Ok(Self::DT),
+            "ST" => Ok(Self::ST),
+            _ => name
+                .strip_prefix('V')
+                .filter(|x| x.len() == 1)
+                .and_then(|x| u8::from_str_radix(x, 16).ok())
+                .map(Self::V)
+                .ok_or_else(|| RegisterError::UnknownRegister(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::V(x) => write!(f, "V{:X}", x),
+            Self::I => write!(f, "I"),
+            Self::PC => write!(f, "PC"),
+            Self::SP => write!(f, "SP"),
+            Self::DT => write!(f, "DT"),
+            Self::ST => write!(f, "ST"),
+        }
+    }
+}
+
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownRegister(name) => write!(f, "unknown register: {}", name),
+            Self::OutOfRange {
+                register,
+                value,
+                max,
+            } => write!(
+                f,
+                "0x{:X} does not fit in {} (max 0x{:X})",
+                value, register, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RegisterError {}
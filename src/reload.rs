@@ -0,0 +1,160 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::asm;
+use crate::cartridge::Cartridge;
+use crate::cpu::Chip8;
+use crate::rom::LoadError;
+use crate::savestate::MachineState;
+
+/// # Live Reload
+///
+/// Reloads a ROM while it is being worked on: whenever the file changes,
+/// e.g. because the assembler wrote it again, the new program replaces the
+/// old one in the running machine. The machine keeps its settings (quirks,
+/// variant, font, checks and sinks) and restarts as after a hard reset, or,
+/// with a mark set, goes back to the machine state at the mark with the new
+/// program in memory, so the part of the game being worked on does not have
+/// to be played to again. Cartridges are reloaded as their ROM; their
+/// settings are not applied again.
+///
+/// ROMs linked from assembler sources are watched through their sources
+/// instead, see `with_sources`: a change to one of them, or to a file they
+/// include, reassembles the ROM, writes it and reloads it.
+///
+/// Changes are noticed by the files' modification times, checked on every
+/// `poll`. Frontends poll once per frame and set the mark with `MARK_KEY`.
+#[derive(Debug, Clone)]
+pub struct LiveReload {
+    path: PathBuf,
+
+    // What the ROM is linked from, see `asm::link_files`, empty for ROMs
+    // reloaded from their file
+    sources: Vec<PathBuf>,
+
+    // The files watched: the ROM, or the sources and what they include
+    files: Vec<PathBuf>,
+
+    // Modification times as of the last poll, none for missing files
+    modified: Vec<Option<SystemTime>>,
+
+    mark: Option<MachineState>,
+}
+
+// The host key setting the mark, named as for `KeyMap`.
+pub const MARK_KEY: &str = "End";
+
+impl LiveReload {
+    // Watch the file at `path`, which counts as unchanged until it is
+    // written again.
+    pub fn new(path: impl Into<PathBuf>) -> LiveReload {
+        LiveReload::with_sources(path, Vec::new())
+    }
+
+    // Watch the sources the ROM at `path` is linked from, and the files they
+    // include, rebuilding it when one of them changes. Without sources the
+    // ROM itself is watched, as by `new`.
+    pub fn with_sources(path: impl Into<PathBuf>, sources: Vec<PathBuf>) -> LiveReload {
+        let path = path.into();
+        let files = if sources.is_empty() {
+            vec![path.clone()]
+        } else {
+            linked_files(&sources)
+        };
+        LiveReload {
+            modified: modification_times(&files),
+            path,
+            sources,
+            files,
+            mark: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Remember the state of `chip8` for reloads to go back to.
+    pub fn set_mark(&mut self, chip8: &Chip8) {
+        self.mark = Some(chip8.snapshot());
+    }
+
+    pub fn clear_mark(&mut self) {
+        self.mark = None;
+    }
+
+    pub fn mark(&self) -> Option<&MachineState> {
+        self.mark.as_ref()
+    }
+
+    // Reload the file into `chip8`, or rebuild it from its sources, if one
+    // of them changed since the last poll. Returns none while none did,
+    // otherwise the size of the new ROM, or why it could not be built or
+    // loaded, in which case the machine runs on as it was.
+    pub fn poll(&mut self, chip8: &mut Chip8) -> Option<Result<usize, String>> {
+        let modified = modification_times(&self.files);
+        if modified == self.modified || modified.contains(&None) {
+            return None;
+        }
+        self.modified = modified;
+        let result = self.build().and_then(|rom| {
+            self.reload(chip8, &rom)
+                .map(|()| rom.len())
+                .map_err(|e| format!("Failed to load {}: {}", self.path.display(), e))
+        });
+        Some(result)
+    }
+
+    // Put `rom` in place of the program of `chip8`, then restart it or go
+    // back to the mark. ROMs which do not fit leave the machine untouched.
+    pub fn reload(&self, chip8: &mut Chip8, rom: &[u8]) -> Result<(), LoadError> {
+        chip8.replace_rom(rom)?;
+        match &self.mark {
+            Some(state) => {
+                // The state holds the old program, the new one goes over it.
+                chip8.restore(state);
+                chip8.replace_rom(rom).expect("the ROM fit a moment ago");
+            }
+            None => chip8.reset(true),
+        }
+        Ok(())
+    }
+
+    // Read the ROM, or link it from the sources and write it.
+    fn build(&mut self) -> Result<Vec<u8>, String> {
+        let path = self.path.display();
+        if self.sources.is_empty() {
+            let bytes =
+                fs::read(&self.path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            let cartridge =
+                Cartridge::load(&bytes).map_err(|e| format!("Failed to load {}: {}", path, e))?;
+            return Ok(cartridge.rom);
+        }
+        let sources: Vec<&Path> = self.sources.iter().map(PathBuf::as_path).collect();
+        let assembly = asm::link_files(&sources).map_err(|e| e.to_string())?;
+        // Includes may have come or gone with the change.
+        self.files = assembly.files.iter().map(PathBuf::from).collect();
+        self.modified = modification_times(&self.files);
+        fs::write(&self.path, &assembly.rom)
+            .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        Ok(assembly.rom)
+    }
+}
+
+// The sources with the files they include, or only the sources while they
+// do not assemble.
+fn linked_files(sources: &[PathBuf]) -> Vec<PathBuf> {
+    let paths: Vec<&Path> = sources.iter().map(PathBuf::as_path).collect();
+    match asm::link_files(&paths) {
+        Ok(assembly) => assembly.files.iter().map(PathBuf::from).collect(),
+        Err(_) => sources.to_vec(),
+    }
+}
+
+fn modification_times(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| fs::metadata(file).and_then(|m| m.modified()).ok())
+        .collect()
+}
@@ -0,0 +1,242 @@
+use std::collections::VecDeque;
+
+/// # Rewind Buffer
+///
+/// History of encoded save states for stepping back in time, within a fixed
+/// memory budget. From one frame to the next only a few bytes of a state
+/// change, so only the newest state is stored whole, every older one as the
+/// XOR of itself and the state after it. Those deltas are almost entirely
+/// zero and are stored run-length encoded, making each frame of history
+/// cost tens of bytes instead of the 4KB of a full state.
+///
+/// Deltas point backwards, from a state to the one before, so when the
+/// budget is exceeded the oldest history is simply dropped.
+///
+/// The history can also be limited to a number of states with `set_depth`.
+///
+/// A delta is the length of the older state as a varint followed by
+/// `(zeros, literals)` pairs: a varint count of unchanged bytes, a varint
+/// count of changed bytes, then the XOR of those changed bytes.
+#[derive(Debug, Clone)]
+pub struct RewindBuffer {
+    budget: usize,
+    depth: usize,
+    newest: Option<Vec<u8>>,
+    deltas: VecDeque<Vec<u8>>,
+    used: usize,
+}
+
+impl RewindBuffer {
+    // A buffer holding at most `budget` bytes of history, not counting the
+    // newest state.
+    pub fn new(budget: usize) -> RewindBuffer {
+        RewindBuffer {
+            budget,
+            depth: usize::MAX,
+            newest: None,
+            deltas: VecDeque::new(),
+            used: 0,
+        }
+    }
+
+    // Record the state of the next frame, e.g. `Chip8::save_state`.
+    pub fn push(&mut self, state: &[u8]) {
+        if let Some(newest) = self.newest.replace(state.to_vec()) {
+            let delta = encode_delta(state, &newest);
+            self.used += delta.len();
+            self.deltas.push_back(delta);
+        }
+        while self.used > self.budget || self.len() > self.depth {
+            match self.deltas.pop_front() {
+                Some(oldest) => self.used -= oldest.len(),
+                None => break,
+            }
+        }
+    }
+
+    // Keep at most `depth` states, the newest included.
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth.max(1);
+        while self.len() > self.depth {
+            match self.deltas.pop_front() {
+                Some(oldest) => self.used -= oldest.len(),
+                None => break,
+            }
+        }
+    }
+
+    // Take the newest state out of the history, stepping back one frame.
+    pub fn pop(&mut self) -> Option<Vec<u8>> {
+        let newest = self.newest.take()?;
+        if let Some(delta) = self.deltas.pop_back() {
+            self.used -= delta.len();
+            self.newest = Some(decode_delta(&newest, &delta));
+        }
+        Some(newest)
+    }
+
+    // Number of states held.
+    pub fn len(&self) -> usize {
+        self.deltas.len() + self.newest.is_some() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.newest.is_none()
+    }
+
+    // Bytes used by the history, not counting the newest state.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    pub fn clear(&mut self) {
+        self.newest = None;
+        self.deltas.clear();
+        self.used = 0;
+    }
+}
+
+/// How `Chip8::enable_rewind` records history: a save state every
+/// `interval` frames, at most `depth` of them within `budget` bytes of
+/// deltas. The default keeps ten seconds at 60Hz.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RewindConfig {
+    pub depth: usize,
+    pub interval: u32,
+    pub budget: usize,
+}
+
+impl Default for RewindConfig {
+    fn default() -> Self {
+        RewindConfig {
+            depth: 600,
+            interval: 1,
+            budget: 1 << 20,
+        }
+    }
+}
+
+/// A rewind buffer recording every `interval` frames.
+#[derive(Debug, Clone)]
+pub struct Rewind {
+    interval: u32,
+    buffer: RewindBuffer,
+
+    // Frames run since the newest state was recorded
+    since: u32,
+}
+
+impl Rewind {
+    pub fn new(config: RewindConfig) -> Rewind {
+        let mut buffer = RewindBuffer::new(config.budget);
+        buffer.set_depth(config.depth);
+        Rewind {
+            interval: config.interval.max(1),
+            buffer,
+            since: 0,
+        }
+    }
+
+    // Count `frames` that ran, returning whether a state is due now.
+    pub fn advance(&mut self, frames: u32) -> bool {
+        self.since += frames;
+        let due = self.buffer.is_empty() || self.since >= self.interval;
+        if due {
+            self.since = 0;
+        }
+        due
+    }
+
+    pub fn record(&mut self, state: &[u8]) {
+        self.buffer.push(state);
+    }
+
+    // Step back at least `frames` frames, or as far as the history goes.
+    // Returns the state to restore and how many frames back it lies; the
+    // state stays in the history, which records on from there.
+    pub fn rewind(&mut self, frames: u32) -> Option<(Vec<u8>, u32)> {
+        let mut back = self.since;
+        let mut state = self.buffer.pop()?;
+        while back < frames && !self.buffer.is_empty() {
+            state = self.buffer.pop()?;
+            back += self.interval;
+        }
+        self.buffer.push(&state);
+        self.since = 0;
+        Some((state, back))
+    }
+
+    // Frames of history held.
+    pub fn frames(&self) -> u32 {
+        (self.buffer.len().saturating_sub(1) as u32) * self.interval + self.since
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.since = 0;
+    }
+}
+
+// Delta turning `newer` back into `older`.
+fn encode_delta(newer: &[u8], older: &[u8]) -> Vec<u8> {
+    let byte = |bytes: &[u8], i: usize| bytes.get(i).copied().unwrap_or(0);
+    let mut delta = Vec::new();
+    push_varint(&mut delta, older.len());
+    let len = newer.len().max(older.len());
+    let mut i = 0;
+    while i < len {
+        let start = i;
+        while i < len && byte(newer, i) == byte(older, i) {
+            i += 1;
+        }
+        push_varint(&mut delta, i - start);
+        let start = i;
+        while i < len && byte(newer, i) != byte(older, i) {
+            i += 1;
+        }
+        push_varint(&mut delta, i - start);
+        delta.extend((start..i).map(|j| byte(newer, j) ^ byte(older, j)));
+    }
+    delta
+}
+
+fn decode_delta(newer: &[u8], delta: &[u8]) -> Vec<u8> {
+    let mut at = 0;
+    let len = read_varint(delta, &mut at);
+    let mut older = newer.to_vec();
+    older.resize(older.len().max(len), 0);
+    let mut i = 0;
+    while at < delta.len() {
+        i += read_varint(delta, &mut at);
+        let literals = read_varint(delta, &mut at);
+        for &x in &delta[at..at + literals] {
+            older[i] ^= x;
+            i += 1;
+        }
+        at += literals;
+    }
+    older.truncate(len);
+    older
+}
+
+fn push_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], at: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = bytes[*at];
+        *at += 1;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
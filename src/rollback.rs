@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::cpu::Chip8;
+use crate::rewind::RewindBuffer;
+
+/// # Rollback Netplay
+///
+/// Two players share the keypad of one machine, each running their own
+/// copy of it. Waiting for the other player's input before every frame
+/// makes the game as sluggish as the connection, so instead a session runs
+/// ahead on a prediction (the remote player keeps holding the keys they
+/// last sent) and, when the real input turns out different, rolls back to
+/// the save state of the mispredicted frame and simulates forward again.
+///
+/// Local input can also be delayed by a few frames. Both players then see
+/// it at the same frame as long as the connection is faster than the
+/// delay, so rollbacks only happen past that, trading a little input lag
+/// for fewer visible corrections.
+///
+/// The session does not deal with the connection: the frame and mask
+/// returned by `local_input` are to be sent to the peer, which passes them
+/// to its own `remote_input`. The machine's keypad is the union of both
+/// players' masks.
+#[derive(Debug)]
+pub struct RollbackSession {
+    local: usize,
+    delay: u32,
+    max_rollback: u32,
+
+    // Next frame to simulate
+    frame: u32,
+
+    // Confirmed inputs of both players, by frame
+    inputs: [BTreeMap<u32, u16>; 2],
+
+    // Remote inputs guessed for frames simulated before they arrived
+    predictions: BTreeMap<u32, u16>,
+
+    // State before each simulated frame, newest last
+    states: RewindBuffer,
+
+    // Earliest frame simulated with a wrong prediction
+    rollback_to: Option<u32>,
+
+    rollbacks: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollbackError {
+    // The remote input is older than the rollback window, the machines
+    // have desynchronized
+    TooLate { frame: u32, current: u32 },
+}
+
+impl RollbackSession {
+    // A session for `player` (0 or 1) delaying local input by `delay`
+    // frames and rolling back at most `max_rollback` frames.
+    pub fn new(player: usize, delay: u32, max_rollback: u32) -> RollbackSession {
+        RollbackSession {
+            local: player & 1,
+            delay,
+            max_rollback,
+            frame: 0,
+            inputs: Default::default(),
+            predictions: BTreeMap::new(),
+            states: RollbackSession::history(max_rollback),
+            rollback_to: None,
+            rollbacks: 0,
+        }
+    }
+
+    // The states of the rollback window, limited by their number alone:
+    // whatever the size of a state, 4KB for CHIP-8 or 64KB for XO-CHIP, and
+    // however much of it changes, every frame of the window stays held.
+    fn history(max_rollback: u32) -> RewindBuffer {
+        let mut states = RewindBuffer::new(usize::MAX);
+        states.set_depth(max_rollback as usize + 1);
+        states
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    // Number of rollbacks performed so far.
+    pub fn rollbacks(&self) -> u64 {
+        self.rollbacks
+    }
+
+    // Set the local keypad mask, returning the frame it applies to. The
+    // frame and mask are to be sent to the peer.
+    pub fn local_input(&mut self, mask: u16) -> u32 {
+        let frame = self.frame + self.delay;
+        self.inputs[self.local].insert(frame, mask);
+        frame
+    }
+
+    // Record the peer's keypad mask for `frame`, scheduling a rollback if
+    // that frame already ran with a different prediction.
+    pub fn remote_input(&mut self, frame: u32, mask: u16) -> Result<(), RollbackError> {
+        if frame + self.max_rollback < self.frame {
+            return Err(RollbackError::TooLate {
+                frame,
+                current: self.frame,
+            });
+        }
+        self.inputs[1 - self.local].insert(frame, mask);
+        if let Some(predicted) = self.predictions.remove(&frame) {
+            if predicted != mask {
+                self.rollback_to = Some(self.rollback_to.map_or(frame, |f| f.min(frame)));
+            }
+        }
+        Ok(())
+    }
+
+    // Run the next frame, first rolling back and replaying the frames
+    // simulated with a wrong prediction. Fails without running anything if
+    // the state to roll back to is no longer held, leaving the machines
+    // desynchronized.
+    pub fn advance(&mut self, chip8: &mut Chip8, cycles: usize) -> Result<(), RollbackError> {
+        if let Some(target) = self.rollback_to.take() {
+            let current = self.frame;
+            if (current - target) as usize > self.states.len() {
+                return Err(RollbackError::TooLate {
+                    frame: target,
+                    current,
+                });
+            }
+            let mut state = None;
+            while self.frame > target {
+                state = self.states.pop();
+                self.frame -= 1;
+            }
+            if let Some(state) = state {
+                chip8
+                    .load_state(&state)
+                    .expect("rollback states are saved by this session");
+            }
+            self.rollbacks += 1;
+            while self.frame < current {
+                self.simulate(chip8, cycles);
+            }
+        }
+        self.simulate(chip8, cycles);
+        self.forget();
+        Ok(())
+    }
+
+    fn simulate(&mut self, chip8: &mut Chip8, cycles: usize) {
+        let frame = self.frame;
+        let remote = 1 - self.local;
+        let local_keys = self.inputs[self.local].get(&frame).copied().unwrap_or(0);
+        let remote_keys = match self.inputs[remote].get(&frame) {
+            Some(&mask) => mask,
+            None => {
+                let guess = self.inputs[remote]
+                    .range(..frame)
+                    .next_back()
+                    .map_or(0, |(_, &mask)| mask);
+                self.predictions.insert(frame, guess);
+                guess
+            }
+        };
+
+        self.states.push(&chip8.save_state());
+        chip8.set_keys(local_keys | remote_keys);
+        chip8.run_frame(cycles);
+        self.frame += 1;
+    }
+
+    // Drop what is older than the rollback window.
+    fn forget(&mut self) {
+        let oldest = self.frame.saturating_sub(self.max_rollback);
+        self.predictions = self.predictions.split_off(&oldest);
+        for inputs in &mut self.inputs {
+            // Keep the last input before the window for predictions.
+            let keep = inputs.range(..oldest).next_back().map(|(&f, _)| f);
+            *inputs = inputs.split_off(&keep.unwrap_or(oldest));
+        }
+    }
+}
+
+impl fmt::Display for RollbackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RollbackError::TooLate { frame, current } => write!(
+                f,
+                "input for frame {} arrived at frame {}, past the rollback window",
+                frame, current
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RollbackError {}
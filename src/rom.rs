@@ -0,0 +1,70 @@
+use std::{fmt, io};
+
+/// # Loading ROMs
+///
+/// Programs are copied into memory at their load address, and execution
+/// starts there. Most programs are written for the COSMAC VIP layout and
+/// load at 0x200; programs for the ETI 660 load at 0x600 (see `memory`).
+/// Either way a program must end by 0xFFF (0xFFFF on XO-CHIP), larger ROMs
+/// are rejected instead of being cut off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoadAddress {
+    #[default]
+    Standard,
+    Eti660,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    // The ROM file could not be read
+    Io(io::Error),
+
+    // The ROM does not fit between its load address and the end of memory
+    TooLarge { size: usize, capacity: usize },
+}
+
+impl LoadAddress {
+    pub fn address(self) -> u16 {
+        match self {
+            LoadAddress::Standard => 0x200,
+            LoadAddress::Eti660 => 0x600,
+        }
+    }
+
+    // Largest ROM which fits in `memory_size` bytes of memory from this
+    // address on.
+    pub fn capacity(self, memory_size: usize) -> usize {
+        memory_size.saturating_sub(self.address() as usize)
+    }
+
+    // Fail if `rom` does not fit in memory from this address on.
+    pub fn check(self, rom: &[u8], memory_size: usize) -> Result<(), LoadError> {
+        let capacity = self.capacity(memory_size);
+        if rom.len() > capacity {
+            return Err(LoadError::TooLarge {
+                size: rom.len(),
+                capacity,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::TooLarge { size, capacity } => {
+                write!(f, "ROM is {} bytes, only {} fit in memory", size, capacity)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
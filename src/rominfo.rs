@@ -0,0 +1,383 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::cartridge::Variant;
+use crate::json::{self, Json, JsonError};
+use crate::palette;
+use crate::quirks::Quirks;
+
+/// # ROM Database
+///
+/// What is known about published ROMs, looked up by the SHA-1 of their
+/// bytes: title, authors, the platform they were written for and how to run
+/// them there. Plain `.ch8` files carry none of this, and the wrong quirks
+/// or speed are the usual reason a game misbehaves.
+///
+/// Databases use the format of the CHIP-8 community database's
+/// `programs.json`, an array of programs each listing its ROMs by hash:
+///
+/// ```json
+/// [{
+///   "title": "Octojam 1 Title", "authors": ["John Earnest"], "release": "2014",
+///   "roms": {
+///     "<sha1>": {
+///       "file": "octojam1title.ch8",
+///       "platforms": ["xochip", "superchip"],
+///       "tickrate": 1000,
+///       "quirkyPlatforms": {"superchip": {"shift": false}},
+///       "colors": {"pixels": ["#000000", "#FFFFFF"]}
+///     }
+///   }
+/// }]
+/// ```
+///
+/// Platforms are listed in order of preference, the first one this
+/// emulator runs decides the variant and quirks:
+///
+/// - `originalChip8` and `hybridVIP`: CHIP-8 with the COSMAC quirks
+/// - `modernChip8`: CHIP-8 with the default quirks
+/// - `chip48`, `superchip1` and `superchip`: SUPER-CHIP
+/// - `xochip`: XO-CHIP
+///
+/// The quirks of that platform may be overridden per ROM: `shift`, `logic`
+/// (the VF reset), `jump`, `memoryLeaveIUnchanged` and
+/// `memoryIncrementByX`, which is approximated by the `memory` quirk.
+/// Other keys and platforms are ignored.
+///
+/// The bundled database knows the ROMs which come with the crate, the
+/// built-in splash screen and the test ROMs of the `test-roms` corpus.
+/// The community database is not bundled, it is maintained and licensed
+/// apart from this crate at <https://github.com/chip-8/chip8-database>.
+/// `load` reads it from a download of its `programs.json` or a clone of the
+/// repository, and `extend` adds it to the bundled one.
+///
+/// `chip8` reads the database of the `rom_db` setting or `--rom-db`, which
+/// with the `net` feature may also be a URL, or `community` for
+/// `COMMUNITY_URL`. Downloads are cached like ROMs, see `fetch`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RomDatabase {
+    // Entries by lowercase hexadecimal SHA-1
+    entries: BTreeMap<String, RomInfo>,
+}
+
+/// A ROM found in a database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomInfo {
+    pub sha1: String,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub release: Option<String>,
+    pub description: Option<String>,
+
+    // The file name the ROM is usually found under
+    pub file: Option<String>,
+
+    // Platforms the ROM runs on, most suitable first
+    pub platforms: Vec<String>,
+
+    // The first of `platforms` this emulator runs
+    pub platform: Option<String>,
+    pub variant: Option<Variant>,
+    pub quirks: Option<Quirks>,
+
+    // Instructions executed per frame
+    pub tickrate: Option<u16>,
+
+    // Background and foreground colors
+    pub palette: Option<[[u8; 3]; 2]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomDatabaseError {
+    // Line of the JSON error, 0 for entries which are not what they should
+    pub line: usize,
+    pub message: String,
+}
+
+const BUNDLED: &str = include_str!("rominfo.json");
+
+// Where the community database publishes its `programs.json`.
+pub const COMMUNITY_URL: &str =
+    "https://raw.githubusercontent.com/chip-8/chip8-database/master/database/programs.json";
+
+impl RomDatabase {
+    pub fn bundled() -> RomDatabase {
+        BUNDLED.parse().expect("the bundled ROM database is valid")
+    }
+
+    // Read a database from a `programs.json`, or from a clone of the
+    // community database, which keeps it under `database/`.
+    pub fn load(path: &Path) -> Result<RomDatabase, RomDatabaseError> {
+        let file = if path.is_dir() {
+            path.join("database").join("programs.json")
+        } else {
+            path.to_path_buf()
+        };
+        let text = fs::read_to_string(&file).map_err(|e| RomDatabaseError {
+            line: 0,
+            message: format!("cannot read {}: {}", file.display(), e),
+        })?;
+        text.parse()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Add the entries of `other`, which replace those for the same ROMs.
+    pub fn extend(&mut self, other: RomDatabase) {
+        self.entries.extend(other.entries);
+    }
+
+    pub fn lookup(&self, rom: &[u8]) -> Option<&RomInfo> {
+        self.lookup_hash(&sha1_hex(rom))
+    }
+
+    // Look up a ROM by its SHA-1 in hexadecimal, in either case.
+    pub fn lookup_hash(&self, sha1: &str) -> Option<&RomInfo> {
+        self.entries.get(&sha1.to_ascii_lowercase())
+    }
+}
+
+impl FromStr for RomDatabase {
+    type Err = RomDatabaseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Json::Array(programs) = json::parse(s)? else {
+            return Err(RomDatabaseError::entry(
+                None,
+                "expected an array of programs",
+            ));
+        };
+        let mut database = RomDatabase::default();
+        for (index, program) in programs.iter().enumerate() {
+            let error = |message: &str| RomDatabaseError::entry(Some(index), message);
+            let Json::Object(_) = program else {
+                return Err(error("expected an object"));
+            };
+            let title = optional_string(program, "title").map_err(error)?;
+            let release = optional_string(program, "release").map_err(error)?;
+            let description = optional_string(program, "description").map_err(error)?;
+            let authors = strings(program.get("authors")).map_err(error)?;
+            let Some(Json::Object(roms)) = program.get("roms") else {
+                return Err(error("`roms` is not an object"));
+            };
+            for (sha1, rom) in roms {
+                let error = |message: &str| error(&format!("ROM {}: {}", sha1, message));
+                if sha1.len() != 40 || !sha1.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(error("expected a SHA-1 in hexadecimal"));
+                }
+                let mut info = rom_info(rom).map_err(error)?;
+                info.sha1 = sha1.to_ascii_lowercase();
+                info.title = title.clone().unwrap_or_default();
+                info.authors = authors.clone();
+                info.release = release.clone();
+                info.description = description.clone();
+                database.entries.insert(info.sha1.clone(), info);
+            }
+        }
+        Ok(database)
+    }
+}
+
+// The settings of a ROM from its entry in `roms`, without the program's.
+fn rom_info(rom: &Json) -> Result<RomInfo, &'static str> {
+    let Json::Object(_) = rom else {
+        return Err("expected an object");
+    };
+    let platforms = strings(rom.get("platforms"))?;
+    let supported = platforms
+        .iter()
+        .find_map(|id| platform(id).map(|settings| (id, settings)));
+    let quirks = match supported {
+        Some((id, (_, quirks))) => {
+            let overrides = rom.get("quirkyPlatforms").and_then(|quirky| quirky.get(id));
+            Some(quirk_overrides(quirks, overrides)?)
+        }
+        None => None,
+    };
+    let tickrate = match rom.get("tickrate") {
+        Some(rate) => Some(
+            rate.as_u64()
+                .and_then(|rate| u16::try_from(rate).ok())
+                .filter(|&rate| rate > 0)
+                .ok_or("`tickrate` is not a number of instructions")?,
+        ),
+        None => None,
+    };
+    let palette = match rom.get("colors").and_then(|colors| colors.get("pixels")) {
+        Some(pixels) => match &strings(Some(pixels))?[..] {
+            [background, foreground, ..] => Some([
+                palette::parse_color(background).map_err(|_| "`pixels` is not colors")?,
+                palette::parse_color(foreground).map_err(|_| "`pixels` is not colors")?,
+            ]),
+            _ => None,
+        },
+        None => None,
+    };
+    Ok(RomInfo {
+        sha1: String::new(),
+        title: String::new(),
+        authors: Vec::new(),
+        release: None,
+        description: None,
+        file: optional_string(rom, "file")?,
+        platform: supported.map(|(id, _)| id.clone()),
+        variant: supported.map(|(_, (variant, _))| variant),
+        platforms,
+        quirks,
+        tickrate,
+        palette,
+    })
+}
+
+// The variant and quirks of a platform of the database, if this emulator
+// runs it.
+fn platform(id: &str) -> Option<(Variant, Quirks)> {
+    match id {
+        "originalChip8" | "hybridVIP" => Some((Variant::Chip8, Quirks::CHIP8)),
+        "modernChip8" => Some((Variant::Chip8, Quirks::default())),
+        "chip48" | "superchip1" | "superchip" => Some((Variant::SuperChip, Quirks::SCHIP)),
+        "xochip" => Some((Variant::XoChip, Quirks::XOCHIP)),
+        _ => None,
+    }
+}
+
+fn quirk_overrides(mut quirks: Quirks, overrides: Option<&Json>) -> Result<Quirks, &'static str> {
+    let Some(Json::Object(fields)) = overrides else {
+        return Ok(quirks);
+    };
+    for (key, value) in fields {
+        let Json::Bool(on) = *value else {
+            return Err("quirks are not true or false");
+        };
+        match key.as_str() {
+            "shift" => quirks.shifting = on,
+            "logic" => quirks.vf_reset = on,
+            "jump" => quirks.jumping = on,
+            "memoryLeaveIUnchanged" if on => quirks.memory = false,
+            "memoryIncrementByX" if on => quirks.memory = true,
+            _ => {}
+        }
+    }
+    Ok(quirks)
+}
+
+fn optional_string(object: &Json, key: &str) -> Result<Option<String>, &'static str> {
+    match object.get(key) {
+        None | Some(Json::Null) => Ok(None),
+        Some(Json::String(text)) => Ok(Some(text.clone())),
+        Some(_) => Err("expected a string"),
+    }
+}
+
+fn strings(value: Option<&Json>) -> Result<Vec<String>, &'static str> {
+    let Some(value) = value else {
+        return Ok(Vec::new());
+    };
+    value
+        .as_array()
+        .ok_or("expected an array of strings")?
+        .iter()
+        .map(|item| item.as_str().map(str::to_string))
+        .collect::<Option<_>>()
+        .ok_or("expected an array of strings")
+}
+
+impl RomDatabaseError {
+    fn entry(index: Option<usize>, message: &str) -> RomDatabaseError {
+        RomDatabaseError {
+            line: 0,
+            message: match index {
+                Some(index) => format!("program {}: {}", index, message),
+                None => message.to_string(),
+            },
+        }
+    }
+}
+
+impl From<JsonError> for RomDatabaseError {
+    fn from(error: JsonError) -> Self {
+        RomDatabaseError {
+            line: error.line,
+            message: error.message,
+        }
+    }
+}
+
+impl fmt::Display for RomDatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "line {}: {}", self.line, self.message)
+        }
+    }
+}
+
+impl std::error::Error for RomDatabaseError {}
+
+// The SHA-1 of `bytes` (FIPS 180-4), which ROM databases identify ROMs by.
+pub fn sha1(bytes: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = bytes.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((bytes.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (t, word) in block.chunks_exact(4).enumerate() {
+            w[t] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for t in 16..80 {
+            w[t] = (w[t - 3] ^ w[t - 8] ^ w[t - 14] ^ w[t - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (t, &word) in w.iter().enumerate() {
+            let (f, k) = match t {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (value, added) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(added);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, value) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+// The SHA-1 of `bytes` in lowercase hexadecimal, as databases list it.
+pub fn sha1_hex(bytes: &[u8]) -> String {
+    sha1(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
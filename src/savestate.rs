@@ -0,0 +1,471 @@
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::cartridge::Variant;
+use crate::display::{self, Resolution};
+use crate::memory;
+
+/// # Save States
+///
+/// A snapshot of everything that determines how the machine continues:
+/// registers, timers, stack, keypad, variant, memory, RPL flags, screen,
+/// audio pattern and random number generator, plus an optional thumbnail
+/// of the screen for slot pickers to show. Snapshots are encoded in a
+/// small versioned binary format, all multi-byte values most-significant-byte
+/// first.
+///
+/// ```text
+/// +--------+-----------------------------------------+
+/// | Offset | Content (version 8)                     |
+/// +--------+-----------------------------------------+
+/// | 0      | Magic "C8S"                             |
+/// | 3      | Format version                          |
+/// | 4      | V0 to VF                                |
+/// | 20     | I (2 bytes)                             |
+/// | 22     | Delay timer, sound timer                |
+/// | 24     | PC (2 bytes), SP (entries on the stack) |
+/// | 27     | Stack, 16 entries of 2 bytes, bottom    |
+/// |        | first                                   |
+/// | 59     | Keypad mask (2 bytes), bit k for key k  |
+/// | 61     | Variant, 0 CHIP-8, 1 SUPER-CHIP,        |
+/// |        | 2 XO-CHIP, 3 Hires CHIP-8               |
+/// | 62     | Memory (4096 bytes, 65536 on XO-CHIP)   |
+/// | +0     | RPL flags (16 bytes)                    |
+/// | +16    | Resolution, 0 for 64x32, 1 for 128x64,  |
+/// |        | 2 for 64x48, 3 for 64x64                |
+/// | +17    | Selected planes, pitch                  |
+/// | +19    | Audio pattern present, pattern (16)     |
+/// | +36    | RNG state present, state (8 bytes)      |
+/// | +45    | Screen, first then second plane, 8      |
+/// |        | pixels per byte (256 bytes each in      |
+/// |        | 64x32, up to 1024 in 128x64)            |
+/// | +0     | Thumbnail width, height (0x0 if none)   |
+/// | +2     | Thumbnail pixels, one gray byte each    |
+/// +--------+-----------------------------------------+
+/// ```
+///
+/// Whenever the format changes, the version is bumped and a migration from
+/// the previous version is appended to `MIGRATIONS`. Loading an older state
+/// runs it through every migration up to the current version, so long-lived
+/// quick-saves keep working after upgrades. States written by a newer version
+/// of the emulator are rejected.
+///
+/// With the `serde` feature, the state also implements `Serialize` and
+/// `Deserialize`, for storing it in other formats such as JSON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MachineState {
+    pub v_registers: [u8; 16],
+    pub i_register: u16,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub program_counter: u16,
+    pub stack_pointer: u8,
+    pub stack: [u16; 16],
+    pub rpl_flags: [u8; 16],
+    pub keys: [bool; 16],
+    pub variant: Variant,
+    pub memory: Vec<u8>,
+
+    // Pixels of the first and second plane in row-major order, in the
+    // resolution of the screen
+    pub resolution: Resolution,
+    pub planes: u8,
+    pub framebuffer: Vec<bool>,
+    pub second_plane: Vec<bool>,
+
+    // XO-CHIP audio pattern and its pitch
+    pub audio_pattern: Option<[u8; 16]>,
+    pub pitch: u8,
+
+    // State of the random number generator, none if it cannot be saved
+    pub rng: Option<u64>,
+
+    pub thumbnail: Option<Thumbnail>,
+}
+
+/// A small grayscale picture of the screen, one byte per pixel from 0
+/// (off) to 255 (on). Downscaling averages blocks of pixels, so thin lines
+/// stay visible as gray instead of disappearing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Thumbnail {
+    pub width: u8,
+    pub height: u8,
+    pub pixels: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SaveStateError {
+    // Missing magic, not a save state at all
+    NotASaveState,
+
+    // Written by a newer version of the emulator
+    TooNew(u8),
+
+    // Older than the oldest version that can be migrated
+    Unsupported(u8),
+
+    // The data does not match its version's layout
+    Corrupt,
+}
+
+pub const VERSION: u8 = 8;
+
+const MAGIC: &[u8; 3] = b"C8S";
+
+// Memory size of versions 1 to 4, which predate XO-CHIP.
+const MEMORY_SIZE: usize = 4096;
+
+const FRAMEBUFFER_SIZE: usize = display::WIDTH * display::HEIGHT / 8;
+const HIRES_FRAMEBUFFER_SIZE: usize = display::HIRES_WIDTH * display::HIRES_HEIGHT / 8;
+
+type Migration = fn(&[u8]) -> Result<Vec<u8>, SaveStateError>;
+
+// `MIGRATIONS[n]` upgrades the payload (everything after the version byte)
+// of version n + 1 to version n + 2.
+const MIGRATIONS: &[Migration] = &[
+    add_thumbnail,
+    add_framebuffer,
+    add_schip,
+    add_xochip,
+    add_rng,
+    count_stack_entries,
+    add_resolutions,
+];
+
+// Version 2 appended the thumbnail, version 1 states have none.
+fn add_thumbnail(payload: &[u8]) -> Result<Vec<u8>, SaveStateError> {
+    if payload.len() != 57 + MEMORY_SIZE {
+        return Err(SaveStateError::Corrupt);
+    }
+    let mut payload = payload.to_vec();
+    payload.extend([0, 0]);
+    Ok(payload)
+}
+
+// Version 3 inserted the screen before the thumbnail, older states restore
+// to a blank one.
+fn add_framebuffer(payload: &[u8]) -> Result<Vec<u8>, SaveStateError> {
+    let end = 57 + MEMORY_SIZE;
+    if payload.len() < end {
+        return Err(SaveStateError::Corrupt);
+    }
+    let mut migrated = payload[..end].to_vec();
+    migrated.extend([0; FRAMEBUFFER_SIZE]);
+    migrated.extend(&payload[end..]);
+    Ok(migrated)
+}
+
+// Version 4 inserted the RPL flags and the resolution before the screen,
+// older states restore to cleared flags in low resolution.
+fn add_schip(payload: &[u8]) -> Result<Vec<u8>, SaveStateError> {
+    let end = 57 + MEMORY_SIZE;
+    if payload.len() < end {
+        return Err(SaveStateError::Corrupt);
+    }
+    let mut migrated = payload[..end].to_vec();
+    migrated.extend([0; 17]);
+    migrated.extend(&payload[end..]);
+    Ok(migrated)
+}
+
+// Version 5 inserted the variant before memory, the planes, pitch and audio
+// pattern before the screen and the second plane after it. Older states
+// restore to CHIP-8 with the first plane selected and no pattern.
+fn add_xochip(payload: &[u8]) -> Result<Vec<u8>, SaveStateError> {
+    let screen = 57 + MEMORY_SIZE + 17;
+    if payload.len() < screen {
+        return Err(SaveStateError::Corrupt);
+    }
+    let size = match payload[screen - 1] {
+        0 => FRAMEBUFFER_SIZE,
+        1 => HIRES_FRAMEBUFFER_SIZE,
+        _ => return Err(SaveStateError::Corrupt),
+    };
+    let end = screen + size;
+    if payload.len() < end {
+        return Err(SaveStateError::Corrupt);
+    }
+    let mut migrated = payload[..57].to_vec();
+    migrated.push(0);
+    migrated.extend(&payload[57..screen]);
+    migrated.extend([1, DEFAULT_PITCH, 0]);
+    migrated.extend([0; 16]);
+    migrated.extend(&payload[screen..end]);
+    migrated.extend(vec![0; size]);
+    migrated.extend(&payload[end..]);
+    Ok(migrated)
+}
+
+// Version 6 inserted the random number generator before the screen. Older
+// states leave the generator as it is.
+fn add_rng(payload: &[u8]) -> Result<Vec<u8>, SaveStateError> {
+    let screen = match payload.get(57) {
+        Some(0 | 1) => 58 + memory::SIZE + 36,
+        Some(2) => 58 + memory::XO_CHIP_SIZE + 36,
+        _ => return Err(SaveStateError::Corrupt),
+    };
+    if payload.len() < screen {
+        return Err(SaveStateError::Corrupt);
+    }
+    let mut migrated = payload[..screen].to_vec();
+    migrated.extend([0; 9]);
+    migrated.extend(&payload[screen..]);
+    Ok(migrated)
+}
+
+// Up to version 6, SP pointed at the top entry, the bottom one being the
+// second; version 7 counts the entries from the first. Stacks which wrapped
+// around are left as they are.
+fn count_stack_entries(payload: &[u8]) -> Result<Vec<u8>, SaveStateError> {
+    if payload.len() < 55 {
+        return Err(SaveStateError::Corrupt);
+    }
+    let mut migrated = payload.to_vec();
+    migrated.copy_within(25..55, 23);
+    migrated[53..55].fill(0);
+    Ok(migrated)
+}
+
+// Version 8 added Hires CHIP-8 and the 64x48 and 64x64 resolutions, which
+// older states do not use.
+fn add_resolutions(payload: &[u8]) -> Result<Vec<u8>, SaveStateError> {
+    Ok(payload.to_vec())
+}
+
+// Pitch of states without an audio pattern, as set on reset.
+const DEFAULT_PITCH: u8 = 64;
+
+// Oldest version that can still be loaded.
+const OLDEST: u8 = VERSION - MIGRATIONS.len() as u8;
+
+impl MachineState {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend(self.v_registers);
+        bytes.extend(self.i_register.to_be_bytes());
+        bytes.extend([self.delay_timer, self.sound_timer]);
+        bytes.extend(self.program_counter.to_be_bytes());
+        bytes.push(self.stack_pointer);
+        for entry in self.stack {
+            bytes.extend(entry.to_be_bytes());
+        }
+        let keys = (0..16).fold(0u16, |mask, k| mask | (self.keys[k] as u16) << k);
+        bytes.extend(keys.to_be_bytes());
+        bytes.push(variant_byte(self.variant));
+        let mut memory = self.memory.clone();
+        memory.resize(memory_size(self.variant), 0);
+        bytes.extend(memory);
+        bytes.extend(self.rpl_flags);
+        bytes.push(self.resolution.id());
+        bytes.extend([self.planes, self.pitch, self.audio_pattern.is_some() as u8]);
+        bytes.extend(self.audio_pattern.unwrap_or_default());
+        bytes.push(self.rng.is_some() as u8);
+        bytes.extend(self.rng.unwrap_or_default().to_be_bytes());
+        for plane in [&self.framebuffer, &self.second_plane] {
+            let mut plane = plane.clone();
+            plane.resize(framebuffer_size(self.resolution) * 8, false);
+            bytes.extend(plane.chunks(8).map(display::pack));
+        }
+        match &self.thumbnail {
+            Some(thumbnail) => {
+                bytes.extend([thumbnail.width, thumbnail.height]);
+                bytes.extend(&thumbnail.pixels);
+            }
+            None => bytes.extend([0, 0]),
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, SaveStateError> {
+        let rest = bytes
+            .strip_prefix(MAGIC)
+            .ok_or(SaveStateError::NotASaveState)?;
+        let (&version, payload) = rest.split_first().ok_or(SaveStateError::Corrupt)?;
+        if version > VERSION {
+            return Err(SaveStateError::TooNew(version));
+        }
+        if version < OLDEST {
+            return Err(SaveStateError::Unsupported(version));
+        }
+
+        let mut payload = payload.to_vec();
+        for migration in &MIGRATIONS[(version - OLDEST) as usize..] {
+            payload = migration(&payload)?;
+        }
+        Self::decode_current(&payload)
+    }
+
+    fn decode_current(payload: &[u8]) -> Result<Self, SaveStateError> {
+        let variant = match payload.get(57) {
+            Some(0) => Variant::Chip8,
+            Some(1) => Variant::SuperChip,
+            Some(2) => Variant::XoChip,
+            Some(3) => Variant::HiresChip8,
+            _ => return Err(SaveStateError::Corrupt),
+        };
+        let memory_end = 58 + memory_size(variant);
+        let screen = memory_end + 45;
+        if payload.len() < screen {
+            return Err(SaveStateError::Corrupt);
+        }
+        let resolution =
+            Resolution::from_id(payload[memory_end + 16]).ok_or(SaveStateError::Corrupt)?;
+        let size = framebuffer_size(resolution);
+        let end = screen + size * 2;
+        if payload.len() < end + 2 {
+            return Err(SaveStateError::Corrupt);
+        }
+        let unpack = |bytes: &[u8]| -> Vec<bool> {
+            bytes
+                .iter()
+                .flat_map(|&byte| (0..8).map(move |bit| byte & (0x80 >> bit) != 0))
+                .collect()
+        };
+        let framebuffer = unpack(&payload[screen..screen + size]);
+        let second_plane = unpack(&payload[screen + size..end]);
+        let [planes, pitch, has_pattern] = [17, 18, 19].map(|at| payload[memory_end + at]);
+        let mut pattern = [0; 16];
+        pattern.copy_from_slice(&payload[memory_end + 20..memory_end + 36]);
+        let audio_pattern = match has_pattern {
+            0 => None,
+            1 => Some(pattern),
+            _ => return Err(SaveStateError::Corrupt),
+        };
+        let mut rng = [0; 8];
+        rng.copy_from_slice(&payload[memory_end + 37..screen]);
+        let rng = match payload[memory_end + 36] {
+            0 => None,
+            1 => Some(u64::from_be_bytes(rng)),
+            _ => return Err(SaveStateError::Corrupt),
+        };
+        let (width, height) = (payload[end], payload[end + 1]);
+        let pixels = &payload[end + 2..];
+        if pixels.len() != width as usize * height as usize {
+            return Err(SaveStateError::Corrupt);
+        }
+        let thumbnail = (!pixels.is_empty()).then(|| Thumbnail {
+            width,
+            height,
+            pixels: pixels.to_vec(),
+        });
+        let word = |at: usize| u16::from_be_bytes([payload[at], payload[at + 1]]);
+
+        let mut v_registers = [0; 16];
+        v_registers.copy_from_slice(&payload[0..16]);
+        let mut rpl_flags = [0; 16];
+        rpl_flags.copy_from_slice(&payload[memory_end..memory_end + 16]);
+        if payload[22] as usize > 16 {
+            return Err(SaveStateError::Corrupt);
+        }
+        let mut stack = [0; 16];
+        for (i, entry) in stack.iter_mut().enumerate() {
+            *entry = word(23 + i * 2);
+        }
+        let mask = word(55);
+        let keys = std::array::from_fn(|k| mask & (1 << k) != 0);
+
+        Ok(Self {
+            v_registers,
+            i_register: word(16),
+            delay_timer: payload[18],
+            sound_timer: payload[19],
+            program_counter: word(20),
+            stack_pointer: payload[22],
+            stack,
+            rpl_flags,
+            keys,
+            variant,
+            memory: payload[58..memory_end].to_vec(),
+            resolution,
+            planes,
+            framebuffer,
+            second_plane,
+            audio_pattern,
+            pitch,
+            rng,
+            thumbnail,
+        })
+    }
+}
+
+fn variant_byte(variant: Variant) -> u8 {
+    match variant {
+        Variant::Chip8 => 0,
+        Variant::SuperChip => 1,
+        Variant::XoChip => 2,
+        Variant::HiresChip8 => 3,
+    }
+}
+
+fn memory_size(variant: Variant) -> usize {
+    match variant {
+        Variant::XoChip => memory::XO_CHIP_SIZE,
+        _ => memory::SIZE,
+    }
+}
+
+// Bytes taken by one plane of the screen.
+fn framebuffer_size(resolution: Resolution) -> usize {
+    resolution.width() * resolution.height() / 8
+}
+
+impl Thumbnail {
+    // Shrink a `width` x `height` monochrome screen by `factor` in both
+    // directions, e.g. 64x32 by 4 to 16x8. Partial blocks at the edges
+    // are averaged over the pixels they cover.
+    pub fn downscale(screen: &[bool], width: usize, height: usize, factor: usize) -> Thumbnail {
+        assert_eq!(screen.len(), width * height);
+        let factor = factor.max(1);
+        let (w, h) = (width.div_ceil(factor), height.div_ceil(factor));
+        assert!(w <= 255 && h <= 255, "thumbnail larger than 255x255");
+        let mut pixels = Vec::with_capacity(w * h);
+        for ty in 0..h {
+            for tx in 0..w {
+                let (mut lit, mut total) = (0, 0);
+                for y in ty * factor..((ty + 1) * factor).min(height) {
+                    for x in tx * factor..((tx + 1) * factor).min(width) {
+                        lit += screen[y * width + x] as usize;
+                        total += 1;
+                    }
+                }
+                pixels.push((lit * 255 / total) as u8);
+            }
+        }
+        Thumbnail {
+            width: w as u8,
+            height: h as u8,
+            pixels,
+        }
+    }
+
+    // RGB pixels for displaying the thumbnail, e.g. with `png::encode_rgb`.
+    pub fn to_rgb(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(|&gray| [gray; 3]).collect()
+    }
+}
+
+impl fmt::Display for SaveStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotASaveState => write!(f, "not a save state"),
+            Self::TooNew(v) => write!(
+                f,
+                "save state version {} is newer than this emulator (version {})",
+                v, VERSION
+            ),
+            Self::Unsupported(v) => write!(
+                f,
+                "save state version {} is too old to migrate (oldest supported is {})",
+                v, OLDEST
+            ),
+            Self::Corrupt => write!(f, "save state is corrupt"),
+        }
+    }
+}
+
+impl std::error::Error for SaveStateError {}
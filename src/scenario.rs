@@ -0,0 +1,312 @@
+use std::path::Path;
+use std::{fmt, fs};
+
+use crate::asm;
+use crate::cartridge::Variant;
+use crate::cpu::Chip8;
+use crate::quirks::Quirks;
+use crate::snapshot;
+use crate::watch::Expr;
+
+/// # Scenarios
+///
+/// A scenario pins down the behaviour of a ROM in a small declarative text
+/// file: which ROM to load, which keys to press at which frame, and which
+/// assertions must hold at which frame. Blank lines and lines starting with
+/// `#` are ignored.
+///
+/// ```text
+/// rom counter.ch8          # path relative to the scenario file, .s sources
+///                          # are assembled and several files linked
+/// seed 42                  # RNG seed, defaults to 0
+/// tickrate 12              # instructions per frame, defaults to 12
+/// quirks schip             # see `Quirks`, defaults to `default`
+/// variant xochip           # chip8, schip, xochip or hires, defaults to chip8
+/// at 10 press 5            # key 5 goes down before frame 10 runs
+/// at 20 release 5
+/// at 600 assert V5 == 3    # checked after frame 600 ran
+/// at 600 screen 0x1F2E3D4C5B6A7988   # hash of the screen, see `snapshot`
+/// at 600 screen logo.txt   # snapshot file, relative to the scenario file
+/// ```
+///
+/// Assertions are watch expressions (see `watch`) which must evaluate to a
+/// non-zero value. Screen assertions compare the framebuffer against a
+/// snapshot or its hash. The scenario runs until the last frame mentioned.
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub rom: Vec<u8>,
+    pub seed: u64,
+    pub tickrate: usize,
+    pub quirks: Quirks,
+    pub variant: Variant,
+    events: Vec<Event>,
+}
+
+#[derive(Debug, Clone)]
+struct Event {
+    frame: u64,
+    action: Action,
+}
+
+#[derive(Debug, Clone)]
+enum Action {
+    Press(u8),
+    Release(u8),
+    Assert(String, Expr),
+    Screen(Screen),
+}
+
+// The picture expected by a screen assertion.
+#[derive(Debug, Clone)]
+enum Screen {
+    Hash(u64),
+    Snapshot(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScenarioError {
+    // The scenario file could not be read or parsed
+    Invalid {
+        line: usize,
+        message: String,
+    },
+
+    // An assertion did not hold
+    Failed {
+        frame: u64,
+        assertion: String,
+        value: Option<u16>,
+    },
+
+    // The screen did not match, shown as it was
+    Screen {
+        frame: u64,
+        expected: u64,
+        found: u64,
+        snapshot: String,
+    },
+}
+
+const DEFAULT_TICKRATE: usize = 12;
+
+impl Scenario {
+    // Read a scenario file, resolving the ROM relative to its directory.
+    // ROMs given as `.s` assembly sources are assembled first, and several
+    // files are linked together.
+    pub fn load(path: &Path) -> Result<Self, ScenarioError> {
+        let text = fs::read_to_string(path).map_err(|e| invalid(0, e.to_string()))?;
+        let base = path.parent().unwrap_or(Path::new("."));
+        Self::parse(&text, |rom| {
+            let files: Vec<_> = rom.split_whitespace().map(|f| base.join(f)).collect();
+            let assemble = files.len() > 1
+                || files
+                    .iter()
+                    .any(|f| f.extension().is_some_and(|ext| ext == "s"));
+            if assemble {
+                let files: Vec<&Path> = files.iter().map(|f| f.as_path()).collect();
+                return asm::link_files(&files)
+                    .map(|assembly| assembly.rom)
+                    .map_err(|e| e.to_string());
+            }
+            fs::read(base.join(rom)).map_err(|e| e.to_string())
+        })
+    }
+
+    // Parse a scenario, using `read_file` to resolve the `rom` line and the
+    // snapshot files of screen assertions.
+    pub fn parse(
+        text: &str,
+        mut read_file: impl FnMut(&str) -> Result<Vec<u8>, String>,
+    ) -> Result<Self, ScenarioError> {
+        let mut rom = None;
+        let mut seed = 0;
+        let mut tickrate = DEFAULT_TICKRATE;
+        let mut quirks = Quirks::default();
+        let mut variant = Variant::default();
+        let mut events = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let line = match line.find('#') {
+                Some(comment) => &line[..comment],
+                None => line,
+            }
+            .trim();
+            let Some((keyword, rest)) = split_word(line) else {
+                continue;
+            };
+
+            match keyword {
+                "rom" => rom = Some(read_file(rest).map_err(|e| invalid(number, e))?),
+                "seed" => seed = number_arg(number, rest)?,
+                "tickrate" => tickrate = number_arg(number, rest)? as usize,
+                "quirks" => quirks = rest.parse().map_err(|e| invalid(number, e))?,
+                "variant" => variant = rest.parse().map_err(|e| invalid(number, e))?,
+                "at" => events.push(parse_event(number, rest, &mut read_file)?),
+                _ => return Err(invalid(number, format!("unknown keyword `{}`", keyword))),
+            }
+        }
+
+        let rom = rom.ok_or_else(|| invalid(0, "missing `rom` line".to_string()))?;
+        events.sort_by_key(|e| e.frame);
+        Ok(Self {
+            rom,
+            seed,
+            tickrate,
+            quirks,
+            variant,
+            events,
+        })
+    }
+
+    // Run the scenario, stopping at the first failed assertion.
+    pub fn run(&self) -> Result<(), ScenarioError> {
+        let mut chip8 = Chip8::new();
+        chip8.set_seed(self.seed);
+        chip8.set_quirks(self.quirks);
+        chip8.set_variant(self.variant);
+        chip8
+            .load_rom(&self.rom)
+            .map_err(|e| invalid(0, e.to_string()))?;
+
+        let last = self.events.last().map_or(0, |e| e.frame);
+        let mut events = self.events.iter().peekable();
+        for frame in 1..=last {
+            // Input for a frame is applied before it runs.
+            while let Some(event) = events.next_if(|e| e.frame == frame && e.is_input()) {
+                match event.action {
+                    Action::Press(key) => chip8.set_key(key, true),
+                    Action::Release(key) => chip8.set_key(key, false),
+                    Action::Assert(..) | Action::Screen(_) => {}
+                }
+            }
+            chip8.run_frame(self.tickrate);
+
+            while let Some(event) = events.next_if(|e| e.frame == frame) {
+                match &event.action {
+                    Action::Assert(source, expr) => {
+                        let value = expr.eval(&chip8);
+                        if value.unwrap_or(0) == 0 {
+                            return Err(ScenarioError::Failed {
+                                frame,
+                                assertion: source.clone(),
+                                value,
+                            });
+                        }
+                    }
+                    Action::Screen(screen) => {
+                        let (matches, expected) = match screen {
+                            Screen::Hash(hash) => (snapshot::hash(chip8.display()) == *hash, *hash),
+                            Screen::Snapshot(text) => (
+                                snapshot::matches(chip8.display(), text),
+                                snapshot::hash_text(text),
+                            ),
+                        };
+                        if !matches {
+                            return Err(ScenarioError::Screen {
+                                frame,
+                                expected,
+                                found: snapshot::hash(chip8.display()),
+                                snapshot: snapshot::to_text(chip8.display()),
+                            });
+                        }
+                    }
+                    Action::Press(_) | Action::Release(_) => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Event {
+    fn is_input(&self) -> bool {
+        matches!(self.action, Action::Press(_) | Action::Release(_))
+    }
+}
+
+// `<frame> press <key>`, `<frame> release <key>`, `<frame> assert <expr>`
+// or `<frame> screen <hash or file>`
+fn parse_event(
+    line: usize,
+    text: &str,
+    read_file: impl FnOnce(&str) -> Result<Vec<u8>, String>,
+) -> Result<Event, ScenarioError> {
+    let (frame, rest) = split_word(text).ok_or_else(|| invalid(line, "missing frame".into()))?;
+    let frame = number_arg(line, frame)?;
+    let (verb, arg) = split_word(rest).ok_or_else(|| invalid(line, "missing action".into()))?;
+    let action = match verb {
+        "press" => Action::Press(key_arg(line, arg)?),
+        "release" => Action::Release(key_arg(line, arg)?),
+        "assert" => Action::Assert(
+            arg.to_string(),
+            Expr::parse(arg).map_err(|e| invalid(line, e.to_string()))?,
+        ),
+        "screen" if arg.starts_with("0x") => Action::Screen(Screen::Hash(number_arg(line, arg)?)),
+        "screen" => {
+            let bytes = read_file(arg).map_err(|e| invalid(line, e))?;
+            let text = String::from_utf8(bytes)
+                .map_err(|_| invalid(line, format!("`{}` is not a text file", arg)))?;
+            Action::Screen(Screen::Snapshot(text))
+        }
+        _ => return Err(invalid(line, format!("unknown action `{}`", verb))),
+    };
+    Ok(Event { frame, action })
+}
+
+fn split_word(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    Some((&text[..end], text[end..].trim()))
+}
+
+fn number_arg(line: usize, text: &str) -> Result<u64, ScenarioError> {
+    let parsed = match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| invalid(line, format!("invalid number `{}`", text)))
+}
+
+fn key_arg(line: usize, text: &str) -> Result<u8, ScenarioError> {
+    u8::from_str_radix(text, 16)
+        .ok()
+        .filter(|&key| key < 16)
+        .ok_or_else(|| invalid(line, format!("invalid key `{}`", text)))
+}
+
+fn invalid(line: usize, message: String) -> ScenarioError {
+    ScenarioError::Invalid { line, message }
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid { line: 0, message } => write!(f, "{}", message),
+            Self::Invalid { line, message } => write!(f, "line {}: {}", line, message),
+            Self::Failed {
+                frame,
+                assertion,
+                value: Some(value),
+            } => write!(f, "frame {}: `{}` failed (0x{:X})", frame, assertion, value),
+            Self::Failed {
+                frame, assertion, ..
+            } => write!(f, "frame {}: `{}` has no value", frame, assertion),
+            Self::Screen {
+                frame,
+                expected,
+                found,
+                snapshot,
+            } => write!(
+                f,
+                "frame {}: screen 0x{:016X} does not match 0x{:016X}\n{}",
+                frame, found, expected, snapshot
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
@@ -0,0 +1,97 @@
+use std::collections::VecDeque;
+
+/// # Oscilloscope
+///
+/// Keeps the most recent audio samples coming out of the mixer and extracts
+/// a stable window from them for display, which helps when composing XO-CHIP
+/// patterns. Like a hardware scope, the window starts at a rising zero
+/// crossing (the trigger), so a periodic wave stays still from one frame to
+/// the next instead of scrolling. Without a trigger, e.g. during silence or
+/// noise, the latest samples are shown.
+///
+/// Frontends can draw `trace` themselves, or export `to_rgb` as an image.
+#[derive(Debug, Clone)]
+pub struct Oscilloscope {
+    samples: VecDeque<f32>,
+    window: usize,
+}
+
+// Trace color, phosphor green.
+const TRACE: [u8; 3] = [0x33, 0xFF, 0x66];
+
+// Center line color.
+const AXIS: [u8; 3] = [0x30, 0x30, 0x30];
+
+impl Oscilloscope {
+    // Create a scope showing `window` samples at a time.
+    pub fn new(window: usize) -> Oscilloscope {
+        Oscilloscope {
+            samples: VecDeque::with_capacity(window * 2),
+            window,
+        }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    // Add samples, keeping enough history to find a trigger.
+    pub fn feed(&mut self, samples: &[f32]) {
+        self.samples.extend(samples);
+        let excess = self.samples.len().saturating_sub(self.window * 2);
+        self.samples.drain(..excess);
+    }
+
+    // The samples to display, `window` long once enough have been fed.
+    pub fn trace(&self) -> Vec<f32> {
+        let len = self.samples.len();
+        let latest = len.saturating_sub(self.window);
+        let start = (1..=latest)
+            .rev()
+            .find(|&i| self.samples[i - 1] <= 0.0 && self.samples[i] > 0.0)
+            .unwrap_or(latest);
+        self.samples
+            .range(start..)
+            .take(self.window)
+            .copied()
+            .collect()
+    }
+
+    // Render the trace as 8-bit RGB pixels, in the range -1.0 (bottom) to
+    // 1.0 (top).
+    pub fn to_rgb(&self, width: u32, height: u32) -> Vec<u8> {
+        let (w, h) = (width as usize, height as usize);
+        let mut rgb = vec![0; w * h * 3];
+        if w == 0 || h == 0 {
+            return rgb;
+        }
+        let mut plot = |x: usize, y: usize, color: [u8; 3]| {
+            let i = (y * w + x) * 3;
+            rgb[i..i + 3].copy_from_slice(&color);
+        };
+        for x in 0..w {
+            plot(x, h / 2, AXIS);
+        }
+
+        let trace = self.trace();
+        if trace.is_empty() {
+            return rgb;
+        }
+        let row = |sample: f32| {
+            let y = (1.0 - sample.clamp(-1.0, 1.0)) / 2.0 * (h - 1) as f32;
+            y.round() as usize
+        };
+        let mut previous = None;
+        for x in 0..w {
+            let y = row(trace[x * trace.len() / w]);
+            // Join consecutive points with a vertical line, so square waves
+            // show their edges.
+            let from = previous.unwrap_or(y);
+            for y in from.min(y)..=from.max(y) {
+                plot(x, y, TRACE);
+            }
+            previous = Some(y);
+        }
+        rgb
+    }
+}
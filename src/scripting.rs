@@ -0,0 +1,384 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+
+use crate::audit::AuditEvent;
+use crate::cpu::{Chip8, StepResult};
+use crate::registers::Register;
+
+/// # Scripting
+///
+/// Rhai scripts which watch and steer a running machine, for cheats and
+/// trainers, automated gameplay tests and live visualizations without
+/// rebuilding the emulator. A script registers callbacks by defining
+/// functions with their names:
+///
+/// - `on_frame(frame)` after every frame, with the number of frames run
+/// - `on_instruction(pc, opcode)` after every instruction
+/// - `on_write(address, before, after)` after every write to memory
+///
+/// Inside them, `peek(address)` and `poke(address, value)` read and write
+/// memory, `reg("V3")` and `set_reg("V3", value)` the registers by their
+/// `Register` names, and `key(k)` and `set_key(k, pressed)` the keypad.
+/// `stop()` ends the run, `throw` fails it and `print` writes to the log
+/// the host shows. Functions cannot see the script's variables, so they
+/// keep their state in `this`, an object map that lives as long as the
+/// script:
+///
+/// ```text
+/// fn on_frame(frame) {
+///     if peek(0x3F0) < 3 { poke(0x3F0, 3); }
+///     this.lowest = min(this.lowest ?? 255, reg("V4"));
+/// }
+/// ```
+///
+/// Callbacks see the machine as it was when they were called, plus their
+/// own changes, which are applied when they return. Running `on_instruction`
+/// or `on_write` steps the machine one instruction at a time and takes over
+/// its audit sink, which makes frames a lot slower than with `on_frame`
+/// alone. Runaway loops fail after `MAX_OPERATIONS` operations per call.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    this: Dynamic,
+    state: Rc<RefCell<State>>,
+    on_frame: bool,
+    on_instruction: bool,
+    on_write: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError {
+    pub message: String,
+}
+
+// Operations one call may take before it fails, about a tenth of a second.
+pub const MAX_OPERATIONS: u64 = 1_000_000;
+
+// Registers scripts read and write.
+const REGISTERS: [Register; 21] = [
+    Register::V(0x0),
+    Register::V(0x1),
+    Register::V(0x2),
+    Register::V(0x3),
+    Register::V(0x4),
+    Register::V(0x5),
+    Register::V(0x6),
+    Register::V(0x7),
+    Register::V(0x8),
+    Register::V(0x9),
+    Register::V(0xA),
+    Register::V(0xB),
+    Register::V(0xC),
+    Register::V(0xD),
+    Register::V(0xE),
+    Register::V(0xF),
+    Register::I,
+    Register::PC,
+    Register::SP,
+    Register::DT,
+    Register::ST,
+];
+
+// The machine as the script sees it during a callback, and what it asked
+// for.
+#[derive(Debug, Default)]
+struct State {
+    memory: Vec<u8>,
+    registers: [u16; REGISTERS.len()],
+    keys: [bool; 16],
+    changes: Vec<Change>,
+    log: Vec<String>,
+    stopped: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Change {
+    Poke(usize, u8),
+    Register(Register, u16),
+    Key(u8, bool),
+}
+
+impl Script {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Script, ScriptError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|e| ScriptError {
+            message: format!("Failed to read {}: {}", path.display(), e),
+        })?;
+        Script::new(&source)
+    }
+
+    // Compile a script and run its top level statements.
+    pub fn new(source: &str) -> Result<Script, ScriptError> {
+        let state = Rc::new(RefCell::new(State::default()));
+        let engine = engine(&state);
+        let ast = engine.compile(source).map_err(ScriptError::from)?;
+        let defines = |name: &str, params: usize| {
+            ast.iter_functions()
+                .any(|f| f.name == name && f.params.len() == params)
+        };
+        let (on_frame, on_instruction, on_write) = (
+            defines("on_frame", 1),
+            defines("on_instruction", 2),
+            defines("on_write", 3),
+        );
+        let mut scope = Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(ScriptError::from)?;
+        Ok(Script {
+            engine,
+            ast,
+            scope,
+            this: Dynamic::from_map(Map::new()),
+            state,
+            on_frame,
+            on_instruction,
+            on_write,
+        })
+    }
+
+    // Run a frame of `cycles` instructions and tick the timers, calling
+    // the script's callbacks along the way, as `Chip8::run_frame` would.
+    pub fn run_frame(&mut self, chip8: &mut Chip8, cycles: usize) -> Result<(), ScriptError> {
+        if chip8.is_halted() || chip8.is_paused() {
+            // As `Chip8::run_frame`, which neither runs nor ticks then.
+        } else if self.on_instruction || self.on_write {
+            let events = Rc::new(RefCell::new(Vec::new()));
+            let sink = Rc::clone(&events);
+            chip8.set_audit_sink(Box::new(move |event: &AuditEvent| {
+                if let AuditEvent::Write { .. } = event {
+                    sink.borrow_mut().push(*event);
+                }
+            }));
+            for _ in 0..cycles {
+                if chip8.is_halted() || self.is_stopped() {
+                    break;
+                }
+                let pc = chip8.register(Register::PC);
+                let opcode = chip8
+                    .peek(pc as usize)
+                    .zip(chip8.peek(pc as usize + 1))
+                    .map_or(0, |(high, low)| u16::from_be_bytes([high, low]));
+                let result = chip8.step();
+                let writes: Vec<AuditEvent> = events.borrow_mut().drain(..).collect();
+                for event in writes {
+                    if let (
+                        true,
+                        AuditEvent::Write {
+                            address, old, new, ..
+                        },
+                    ) = (self.on_write, event)
+                    {
+                        let args = (address as i64, old as i64, new as i64);
+                        self.call(chip8, "on_write", args)?;
+                    }
+                }
+                if self.on_instruction && result != StepResult::Paused {
+                    self.call(chip8, "on_instruction", (pc as i64, opcode as i64))?;
+                }
+            }
+            chip8.clear_audit_sink();
+            chip8.tick_timers();
+        } else {
+            chip8.run_frame(cycles);
+        }
+        if self.on_frame {
+            let frame = chip8.telemetry().frames as i64;
+            self.call(chip8, "on_frame", (frame,))?;
+        }
+        Ok(())
+    }
+
+    // Whether the script called `stop()`.
+    pub fn is_stopped(&self) -> bool {
+        self.state.borrow().stopped
+    }
+
+    // Lines printed since the last call.
+    pub fn take_log(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.state.borrow_mut().log)
+    }
+
+    fn call(
+        &mut self,
+        chip8: &mut Chip8,
+        name: &str,
+        args: impl rhai::FuncArgs,
+    ) -> Result<(), ScriptError> {
+        self.state.borrow_mut().refresh(chip8);
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.this);
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            options,
+            &mut self.scope,
+            &self.ast,
+            name,
+            args,
+        );
+        // Apply what the script did before it failed, too.
+        let changes = std::mem::take(&mut self.state.borrow_mut().changes);
+        for change in changes {
+            match change {
+                Change::Poke(address, value) => {
+                    chip8.poke(address, value);
+                }
+                Change::Register(register, value) => {
+                    let _ = chip8.set_register(register, value);
+                }
+                Change::Key(key, pressed) => chip8.set_key(key, pressed),
+            }
+        }
+        result.map(|_| ()).map_err(ScriptError::from)
+    }
+}
+
+impl State {
+    fn refresh(&mut self, chip8: &Chip8) {
+        self.memory.clear();
+        self.memory.extend_from_slice(chip8.memory());
+        self.registers = REGISTERS.map(|register| chip8.register(register));
+        self.keys = chip8.keys();
+    }
+
+    fn register(&self, register: Register) -> usize {
+        REGISTERS
+            .iter()
+            .position(|&r| r == register)
+            .expect("every register is listed")
+    }
+}
+
+// An engine with the machine API registered, reading and recording through
+// `state`.
+fn engine(state: &Rc<RefCell<State>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    let log = Rc::clone(state);
+    engine.on_print(move |text| log.borrow_mut().log.push(text.to_string()));
+    let log = Rc::clone(state);
+    engine.on_debug(move |text, _, _| log.borrow_mut().log.push(text.to_string()));
+
+    let view = Rc::clone(state);
+    engine.register_fn(
+        "peek",
+        move |address: i64| -> Result<i64, Box<EvalAltResult>> {
+            let state = view.borrow();
+            let byte = usize::try_from(address)
+                .ok()
+                .and_then(|address| state.memory.get(address));
+            match byte {
+                Some(&byte) => Ok(byte as i64),
+                None => Err(format!("peek: address {} is outside of memory", address).into()),
+            }
+        },
+    );
+    let view = Rc::clone(state);
+    engine.register_fn(
+        "poke",
+        move |address: i64, value: i64| -> Result<(), Box<EvalAltResult>> {
+            let mut state = view.borrow_mut();
+            let address = usize::try_from(address)
+                .ok()
+                .filter(|&address| address < state.memory.len())
+                .ok_or_else(|| format!("poke: address {} is outside of memory", address))?;
+            let value =
+                u8::try_from(value).map_err(|_| format!("poke: {} is not a byte", value))?;
+            state.memory[address] = value;
+            state.changes.push(Change::Poke(address, value));
+            Ok(())
+        },
+    );
+    let view = Rc::clone(state);
+    engine.register_fn(
+        "reg",
+        move |name: &str| -> Result<i64, Box<EvalAltResult>> {
+            let register: Register = name.parse().map_err(|e| format!("reg: {}", e))?;
+            let state = view.borrow();
+            Ok(state.registers[state.register(register)] as i64)
+        },
+    );
+    let view = Rc::clone(state);
+    engine.register_fn(
+        "set_reg",
+        move |name: &str, value: i64| -> Result<(), Box<EvalAltResult>> {
+            let register: Register = name.parse().map_err(|e| format!("set_reg: {}", e))?;
+            let mut state = view.borrow_mut();
+            let memory_size = state.memory.len();
+            let value = u16::try_from(value)
+                .ok()
+                .filter(|&value| register.validate(value, memory_size).is_ok())
+                .ok_or_else(|| format!("set_reg: {} does not fit in {}", value, register))?;
+            let index = state.register(register);
+            state.registers[index] = value;
+            state.changes.push(Change::Register(register, value));
+            Ok(())
+        },
+    );
+    let view = Rc::clone(state);
+    engine.register_fn("key", move |key: i64| -> Result<bool, Box<EvalAltResult>> {
+        let key = keypad_key("key", key)?;
+        Ok(view.borrow().keys[key as usize])
+    });
+    let view = Rc::clone(state);
+    engine.register_fn(
+        "set_key",
+        move |key: i64, pressed: bool| -> Result<(), Box<EvalAltResult>> {
+            let key = keypad_key("set_key", key)?;
+            let mut state = view.borrow_mut();
+            state.keys[key as usize] = pressed;
+            state.changes.push(Change::Key(key, pressed));
+            Ok(())
+        },
+    );
+    let view = Rc::clone(state);
+    engine.register_fn("stop", move || view.borrow_mut().stopped = true);
+    engine
+}
+
+fn keypad_key(function: &str, key: i64) -> Result<u8, Box<EvalAltResult>> {
+    u8::try_from(key)
+        .ok()
+        .filter(|&key| key < 16)
+        .ok_or_else(|| format!("{}: {} is not a key from 0 to 15", function, key).into())
+}
+
+impl From<Box<EvalAltResult>> for ScriptError {
+    fn from(error: Box<EvalAltResult>) -> Self {
+        ScriptError {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<rhai::ParseError> for ScriptError {
+    fn from(error: rhai::ParseError) -> Self {
+        ScriptError {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Debug for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Script")
+            .field("on_frame", &self.on_frame)
+            .field("on_instruction", &self.on_instruction)
+            .field("on_write", &self.on_write)
+            .finish()
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
@@ -0,0 +1,863 @@
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::VideoSubsystem;
+
+use crate::attract::{AttractAction, AttractMode};
+use crate::audio::{AudioConfig, Buzzer, Mixer, Speaker};
+use crate::browser::{BrowserAction, RomBrowser};
+use crate::capture::{CaptureHotkey, Captures};
+use crate::cpu::{Chip8, ResetHotkey};
+use crate::display::{Display, Resolution};
+use crate::flicker::{FlickerConfig, FlickerLimiter};
+use crate::host::{AudioBackend, DisplayBackend};
+use crate::keyboard::KeyMap;
+use crate::memview::MemoryEditor;
+use crate::movie::MovieSession;
+use crate::pacing::{FramePacer, SpeedHotkey};
+use crate::palette::Palette;
+use crate::phosphor::{Phosphor, PhosphorConfig};
+use crate::quicksave::{Hotkey, QuickSaves};
+use crate::registers::Register;
+use crate::reload::{LiveReload, MARK_KEY};
+use crate::rewind::RewindConfig;
+use crate::savestate::Thumbnail;
+
+/// # SDL Frontend
+///
+/// A desktop window for playing ROMs, built on SDL2. The host keys are
+/// mapped onto the keypad by a `KeyMap`, 1234/QWER/ASDF/ZXCV by default,
+/// and Escape closes the window. F5 and F9 save and load quick save slots,
+/// F6 and F7 select the slot, see `QuickSaves`, showing the thumbnail
+/// saved in the slot in a corner of the screen for a while. Holding
+/// Backspace plays
+/// the game backwards through the rewind history. While a movie is recorded
+/// or replayed, both are disabled, and a replay closes the window once it
+/// has run all of its frames. F1 pauses, F2 and F3 slow the game down and
+/// speed it up, see `FramePacer`. F11 and Home reset the machine, see
+/// `ResetHotkey`, except during movies. F12 saves a screenshot and F10 starts and
+/// stops recording an animated PNG, see `Captures`. F8 opens a second
+/// window with a live hex dump of memory, drawn in the digits of the font,
+/// where the byte under the cursor can be edited, see `MemoryEditor`.
+/// Dropping a ROM file onto the window, or picking one in the ROM browser
+/// F4 opens in another window, loads it into a fresh machine, see
+/// `RomBrowser` and `RomLoader`; both are disabled during movies too. When
+/// watching, the ROM is reloaded whenever its file, or one of the sources
+/// it is linked from, changes, and End marks the point reloads go back to,
+/// see `LiveReload`. With an attract mode and no movie, a demo plays after
+/// the keys were left alone for a while, until any key is pressed, see
+/// `AttractMode`; loading another ROM ends it. The screen is drawn `scale`
+/// times its size through the flicker limiter, or through a `Phosphor` if
+/// one is configured, high resolution at the same window size, by a
+/// `DisplayBackend`, and the buzzer is played by a `Speaker` into an SDL
+/// audio queue.
+///
+/// The machine advances at its timer rate whatever the refresh rate of the
+/// display, see `FramePacer`.
+#[derive(Debug, Clone)]
+pub struct SdlConfig {
+    // Window pixels per CHIP-8 pixel
+    pub scale: u32,
+
+    pub cycles_per_frame: usize,
+
+    // Timer and frame rate, in Hz
+    pub frame_rate: u32,
+
+    pub palette: Palette,
+
+    pub keymap: KeyMap,
+    pub audio: AudioConfig,
+    pub flicker: FlickerConfig,
+
+    // Afterglow replacing the flicker limiter's persistence, none to show
+    // the screen through the limiter
+    pub phosphor: Option<PhosphorConfig>,
+
+    // Where the quick save slots are kept, see `QuickSaves`
+    pub rom_path: Option<PathBuf>,
+
+    // Where the ROM browser lists ROMs, by default the directory of
+    // `rom_path`, or the working directory
+    pub rom_dir: Option<PathBuf>,
+
+    // History recorded for rewinding, none to disable it
+    pub rewind: Option<RewindConfig>,
+
+    // Whether to reload the ROM at `rom_path` whenever it changes, see
+    // `LiveReload`
+    pub watch: bool,
+
+    // The sources `rom_path` is linked from, which watching reassembles it
+    // from instead
+    pub sources: Vec<PathBuf>,
+
+    // Demo played while the keys are left alone
+    pub attract: Option<AttractMode>,
+}
+
+impl Default for SdlConfig {
+    fn default() -> Self {
+        SdlConfig {
+            scale: 10,
+            cycles_per_frame: 12,
+            frame_rate: 60,
+            palette: Palette::default(),
+            keymap: KeyMap::standard(),
+            audio: AudioConfig::default(),
+            flicker: FlickerConfig::default(),
+            phosphor: None,
+            rom_path: None,
+            rom_dir: None,
+            rewind: Some(RewindConfig::default()),
+            watch: false,
+            sources: Vec::new(),
+            attract: None,
+        }
+    }
+}
+
+/// Boots a fresh machine for a ROM file dropped onto the window or picked in
+/// the ROM browser, set up like the one the window started with. Errors are
+/// shown in the title.
+pub type RomLoader<'a> = dyn Fn(&Path) -> Result<LoadedRom, String> + 'a;
+
+/// A machine booted by a `RomLoader`, and the settings of its cartridge.
+pub struct LoadedRom {
+    pub chip8: Chip8,
+    pub title: String,
+    pub cycles_per_frame: usize,
+    pub frame_rate: u32,
+}
+
+/// A `Buzzer` playing through an SDL audio queue. Samples which would queue
+/// up more than the configured latency are dropped, so sound never lags
+/// behind the picture.
+pub struct SdlBuzzer {
+    device: AudioQueue<f32>,
+    latency: usize,
+}
+
+impl SdlBuzzer {
+    pub fn open(audio: &sdl2::AudioSubsystem, config: &AudioConfig) -> Result<SdlBuzzer, String> {
+        let spec = AudioSpecDesired {
+            freq: Some(config.sample_rate as i32),
+            channels: Some(1),
+            samples: Some(config.buffer_size as u16),
+        };
+        let device = audio.open_queue(None, &spec)?;
+        device.resume();
+        Ok(SdlBuzzer {
+            device,
+            latency: config.latency_samples(),
+        })
+    }
+}
+
+impl Buzzer for SdlBuzzer {
+    fn name(&self) -> &'static str {
+        "sdl"
+    }
+
+    fn write(&mut self, samples: &[f32]) {
+        let queued = self.device.size() as usize / mem::size_of::<f32>();
+        if queued + samples.len() <= self.latency {
+            let _ = self.device.queue_audio(samples);
+        }
+    }
+}
+
+// The window as a `DisplayBackend`, blending the background into the color
+// of each pixel by the intensity the flicker limiter gives it, or drawing
+// the colors of the phosphor.
+struct Screen {
+    canvas: Canvas<Window>,
+    limiter: FlickerLimiter,
+    phosphor: Option<Phosphor>,
+    palette: Palette,
+
+    // Resolution the canvas is laid out for
+    resolution: Resolution,
+
+    // Thumbnail of the selected quick save slot, and since when it is shown
+    preview: Option<(Thumbnail, Instant)>,
+}
+
+// How long the thumbnail of the selected quick save slot stays up.
+const PREVIEW_TIME: Duration = Duration::from_secs(3);
+
+impl Screen {
+    // Draw the thumbnail of the selected quick save slot into the top right
+    // corner, framed in the foreground color, until it expires.
+    fn draw_preview(&mut self) {
+        if self
+            .preview
+            .as_ref()
+            .is_some_and(|(_, shown)| shown.elapsed() >= PREVIEW_TIME)
+        {
+            self.preview = None;
+        }
+        let Some((thumbnail, _)) = &self.preview else {
+            return;
+        };
+        let (width, height) = (thumbnail.width as i32, thumbnail.height as i32);
+        let left = self.resolution.width() as i32 - width - 2;
+        let [r, g, b] = self.palette.foreground();
+        self.canvas.set_draw_color(Color::RGB(r, g, b));
+        let frame = Rect::new(left - 1, 0, width as u32 + 2, height as u32 + 2);
+        let _ = self.canvas.fill_rect(frame);
+        let (background, foreground) = (self.palette.background(), self.palette.foreground());
+        for (i, &gray) in thumbnail.pixels.iter().enumerate() {
+            let (x, y) = (i as i32 % width, i as i32 / width);
+            self.canvas
+                .set_draw_color(color(background, foreground, gray));
+            let _ = self.canvas.fill_rect(Rect::new(left + x, 1 + y, 1, 1));
+        }
+    }
+}
+
+impl DisplayBackend for Screen {
+    fn present(&mut self, display: &Display) {
+        if display.resolution() != self.resolution {
+            let (width, height) = (display.width() as u32, display.height() as u32);
+            // Screens of another shape reshape the window, keeping its width.
+            let (from_width, from_height) = (self.resolution.width(), self.resolution.height());
+            if width as usize * from_height != height as usize * from_width {
+                let (window_width, _) = self.canvas.window().size();
+                let _ = self
+                    .canvas
+                    .window_mut()
+                    .set_size(window_width, window_width * height / width);
+            }
+            self.resolution = display.resolution();
+            let _ = self.canvas.set_logical_size(width, height);
+        }
+        let background = self.palette.background();
+        let [r, g, b] = background;
+        self.canvas.set_draw_color(Color::RGB(r, g, b));
+        self.canvas.clear();
+        if let Some(phosphor) = &mut self.phosphor {
+            let rgba = phosphor.present(display, &self.palette);
+            for (i, pixel) in rgba.chunks_exact(4).enumerate() {
+                if pixel[..3] == background {
+                    continue;
+                }
+                let (x, y) = (i % display.width(), i / display.width());
+                self.canvas
+                    .set_draw_color(Color::RGB(pixel[0], pixel[1], pixel[2]));
+                let _ = self.canvas.fill_rect(Rect::new(x as i32, y as i32, 1, 1));
+            }
+            self.draw_preview();
+            self.canvas.present();
+            return;
+        }
+        let colors = display.colors();
+        for (i, &intensity) in self.limiter.present(display.pixels()).iter().enumerate() {
+            if intensity == 0 {
+                continue;
+            }
+            // Pixels fading out after being switched off keep the foreground.
+            let foreground = match colors[i] {
+                0 => self.palette.foreground(),
+                lit => self.palette.color(lit),
+            };
+            let (x, y) = (i % display.width(), i / display.width());
+            self.canvas
+                .set_draw_color(color(background, foreground, intensity));
+            let _ = self.canvas.fill_rect(Rect::new(x as i32, y as i32, 1, 1));
+        }
+        self.draw_preview();
+        self.canvas.present();
+    }
+}
+
+// Bytes shown by the memory window at a time.
+const MEMORY_PAGE_SIZE: usize = 0x100;
+
+// Window pixels per pixel of the memory window's digits.
+const MEMORY_SCALE: u32 = 3;
+
+// Sizes in the memory window, in pixels of the digits: a digit with the
+// space after it, a byte, a row, and the address in front of each row.
+const DIGIT_WIDTH: i32 = 5;
+const BYTE_WIDTH: i32 = 2 * DIGIT_WIDTH + 2;
+const ROW_HEIGHT: i32 = 7;
+const ADDRESS_WIDTH: i32 = 4 * DIGIT_WIDTH + 3;
+
+// The memory panel in a window of its own. Bytes are drawn with the
+// hexadecimal digits of the machine's font, the byte under the cursor in
+// reverse, the ones PC and I point at on the colors of the second plane and
+// both planes. The title shows the cursor's address and value.
+struct MemoryWindow {
+    canvas: Canvas<Window>,
+    palette: Palette,
+}
+
+impl MemoryWindow {
+    fn open(video: &VideoSubsystem, palette: Palette) -> Result<MemoryWindow, String> {
+        let width = ADDRESS_WIDTH + 16 * BYTE_WIDTH;
+        let height = (MEMORY_PAGE_SIZE / 16) as i32 * ROW_HEIGHT + 1;
+        let window = video
+            .window(
+                "Memory",
+                width as u32 * MEMORY_SCALE,
+                height as u32 * MEMORY_SCALE,
+            )
+            .resizable()
+            .build()
+            .map_err(|e| e.to_string())?;
+        let mut canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+        canvas
+            .set_logical_size(width as u32, height as u32)
+            .map_err(|e| e.to_string())?;
+        Ok(MemoryWindow { canvas, palette })
+    }
+
+    fn present(&mut self, editor: &MemoryEditor, chip8: &Chip8) {
+        let rgb = |[r, g, b]: [u8; 3]| Color::RGB(r, g, b);
+        let (background, foreground) = (self.palette.background(), self.palette.foreground());
+        self.canvas.set_draw_color(rgb(background));
+        self.canvas.clear();
+        let digits = chip8.font().small();
+        let pc = chip8.register(Register::PC) as usize;
+        let i = chip8.register(Register::I) as usize;
+        let range = editor.view().range();
+        for (offset, &byte) in chip8.memory_slice(range.clone()).iter().enumerate() {
+            let address = range.start + offset;
+            let x = ADDRESS_WIDTH + (offset % 16) as i32 * BYTE_WIDTH;
+            let y = 1 + (offset / 16) as i32 * ROW_HEIGHT;
+            if offset % 16 == 0 {
+                for (n, shift) in [12, 8, 4, 0].into_iter().enumerate() {
+                    let digit = &digits[(address >> shift) & 0xF];
+                    self.draw_digit(1 + n as i32 * DIGIT_WIDTH, y, digit, foreground);
+                }
+            }
+            let (highlight, ink) = if address == editor.view().cursor() {
+                (Some(foreground), background)
+            } else if address == pc {
+                (Some(self.palette.color(2)), foreground)
+            } else if address == i {
+                (Some(self.palette.color(3)), foreground)
+            } else {
+                (None, foreground)
+            };
+            if let Some(highlight) = highlight {
+                self.canvas.set_draw_color(rgb(highlight));
+                let _ = self.canvas.fill_rect(Rect::new(
+                    x - 1,
+                    y - 1,
+                    2 * DIGIT_WIDTH as u32 + 1,
+                    ROW_HEIGHT as u32,
+                ));
+            }
+            self.draw_digit(x, y, &digits[byte as usize >> 4], ink);
+            self.draw_digit(x + DIGIT_WIDTH, y, &digits[byte as usize & 0xF], ink);
+        }
+        if let Some(status) = editor.render(chip8).pop() {
+            let _ = self.canvas.window_mut().set_title(&status);
+        }
+        self.canvas.present();
+    }
+
+    fn draw_digit(&mut self, x: i32, y: i32, digit: &[u8; 5], color: [u8; 3]) {
+        draw_rows(&mut self.canvas, x, y, digit, 4, color);
+    }
+}
+
+// Lines of the ROM browser window, and the characters shown of each.
+const BROWSER_PAGE_SIZE: usize = 16;
+const BROWSER_COLUMNS: usize = 48;
+
+// Window pixels per pixel of the ROM browser's letters.
+const BROWSER_SCALE: u32 = 3;
+
+// Sizes in the ROM browser window, in pixels of the letters: a letter with
+// the space after it, and a line.
+const LETTER_WIDTH: i32 = 4;
+const LINE_HEIGHT: i32 = 7;
+
+// The ROM browser in a window of its own, its lines written in a 3x5 font,
+// in upper case, the selected ROM in reverse.
+struct BrowserWindow {
+    canvas: Canvas<Window>,
+    palette: Palette,
+}
+
+impl BrowserWindow {
+    fn open(video: &VideoSubsystem, palette: Palette) -> Result<BrowserWindow, String> {
+        let width = BROWSER_COLUMNS as i32 * LETTER_WIDTH + 1;
+        let height = (BROWSER_PAGE_SIZE + 1) as i32 * LINE_HEIGHT + 1;
+        let window = video
+            .window(
+                "ROMs",
+                width as u32 * BROWSER_SCALE,
+                height as u32 * BROWSER_SCALE,
+            )
+            .resizable()
+            .build()
+            .map_err(|e| e.to_string())?;
+        let mut canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+        canvas
+            .set_logical_size(width as u32, height as u32)
+            .map_err(|e| e.to_string())?;
+        Ok(BrowserWindow { canvas, palette })
+    }
+
+    fn present(&mut self, browser: &RomBrowser) {
+        let (background, foreground) = (self.palette.background(), self.palette.foreground());
+        let [r, g, b] = background;
+        self.canvas.set_draw_color(Color::RGB(r, g, b));
+        self.canvas.clear();
+        for (line, text) in browser.render().iter().enumerate() {
+            let y = 1 + line as i32 * LINE_HEIGHT;
+            let ink = if line > 0 && text.starts_with('>') {
+                let [r, g, b] = foreground;
+                self.canvas.set_draw_color(Color::RGB(r, g, b));
+                let width = BROWSER_COLUMNS as u32 * LETTER_WIDTH as u32 + 1;
+                let _ = self
+                    .canvas
+                    .fill_rect(Rect::new(0, y - 1, width, LINE_HEIGHT as u32));
+                background
+            } else {
+                foreground
+            };
+            for (column, c) in text.chars().take(BROWSER_COLUMNS).enumerate() {
+                let x = 1 + column as i32 * LETTER_WIDTH;
+                draw_rows(&mut self.canvas, x, y, &letter(c), 3, ink);
+            }
+        }
+        self.canvas.present();
+    }
+}
+
+// Draw rows of up to 8 pixels, the leftmost in the highest bit.
+fn draw_rows(canvas: &mut Canvas<Window>, x: i32, y: i32, rows: &[u8], width: i32, color: [u8; 3]) {
+    let [r, g, b] = color;
+    canvas.set_draw_color(Color::RGB(r, g, b));
+    for (row, bits) in rows.iter().enumerate() {
+        for column in 0..width {
+            if bits & (0x80 >> column) != 0 {
+                let _ = canvas.fill_rect(Rect::new(x + column, y + row as i32, 1, 1));
+            }
+        }
+    }
+}
+
+// The 3x5 letter for a character, ignoring case. Characters without one are
+// drawn as `?`.
+fn letter(c: char) -> [u8; 5] {
+    let rows = match c.to_ascii_uppercase() {
+        'A' => [2, 5, 7, 5, 5],
+        'B' => [6, 5, 6, 5, 6],
+        'C' => [3, 4, 4, 4, 3],
+        'D' => [6, 5, 5, 5, 6],
+        'E' => [7, 4, 6, 4, 7],
+        'F' => [7, 4, 6, 4, 4],
+        'G' => [3, 4, 5, 5, 3],
+        'H' => [5, 5, 7, 5, 5],
+        'I' => [7, 2, 2, 2, 7],
+        'J' => [1, 1, 1, 5, 2],
+        'K' => [5, 5, 6, 5, 5],
+        'L' => [4, 4, 4, 4, 7],
+        'M' => [5, 7, 7, 5, 5],
+        'N' => [6, 5, 5, 5, 5],
+        'O' => [2, 5, 5, 5, 2],
+        'P' => [6, 5, 6, 4, 4],
+        'Q' => [2, 5, 5, 6, 3],
+        'R' => [6, 5, 6, 5, 5],
+        'S' => [3, 4, 2, 1, 6],
+        'T' => [7, 2, 2, 2, 2],
+        'U' => [5, 5, 5, 5, 7],
+        'V' => [5, 5, 5, 5, 2],
+        'W' => [5, 5, 7, 7, 5],
+        'X' => [5, 5, 2, 5, 5],
+        'Y' => [5, 5, 2, 2, 2],
+        'Z' => [7, 1, 2, 4, 7],
+        '0' => [7, 5, 5, 5, 7],
+        '1' => [2, 6, 2, 2, 7],
+        '2' => [6, 1, 2, 4, 7],
+        '3' => [6, 1, 2, 1, 6],
+        '4' => [5, 5, 7, 1, 1],
+        '5' => [7, 4, 6, 1, 6],
+        '6' => [3, 4, 6, 5, 2],
+        '7' => [7, 1, 2, 2, 2],
+        '8' => [2, 5, 2, 5, 2],
+        '9' => [2, 5, 3, 1, 6],
+        ' ' => [0, 0, 0, 0, 0],
+        '.' => [0, 0, 0, 0, 2],
+        ',' => [0, 0, 0, 2, 4],
+        ':' => [0, 2, 0, 2, 0],
+        '-' => [0, 0, 7, 0, 0],
+        '_' => [0, 0, 0, 0, 7],
+        '+' => [0, 2, 7, 2, 0],
+        '/' => [1, 1, 2, 4, 4],
+        '\\' => [4, 4, 2, 1, 1],
+        '(' | '[' => [1, 2, 2, 2, 1],
+        ')' | ']' => [4, 2, 2, 2, 4],
+        '>' => [4, 2, 1, 2, 4],
+        '\'' => [2, 2, 0, 0, 0],
+        '!' => [2, 2, 2, 0, 2],
+        '&' => [2, 5, 2, 5, 3],
+        _ => [6, 1, 2, 0, 2],
+    };
+    rows.map(|bits| bits << 5)
+}
+
+// Open a window and run the machine in it until it is closed, recording or
+// replaying `movie` if given, booting dropped and picked ROMs with `load`.
+pub fn run(
+    chip8: &mut Chip8,
+    title: &str,
+    config: &SdlConfig,
+    mut movie: Option<&mut MovieSession>,
+    load: &RomLoader,
+) -> Result<(), String> {
+    let sdl = sdl2::init()?;
+    let video = sdl.video()?;
+    let scale = config.scale.max(1);
+    let resolution = chip8.display().resolution();
+    let window = video
+        .window(
+            title,
+            resolution.width() as u32 * scale,
+            resolution.height() as u32 * scale,
+        )
+        .position_centered()
+        .resizable()
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut canvas = window
+        .into_canvas()
+        .present_vsync()
+        .build()
+        .map_err(|e| e.to_string())?;
+    canvas
+        .set_logical_size(resolution.width() as u32, resolution.height() as u32)
+        .map_err(|e| e.to_string())?;
+
+    let buzzer = SdlBuzzer::open(&sdl.audio()?, &config.audio)?;
+    let mut mixer = Mixer::new(config.audio);
+    mixer.set_frame_rate(config.frame_rate);
+    let mut speaker = Speaker::new(mixer, Box::new(buzzer));
+
+    let mut screen = Screen {
+        canvas,
+        limiter: FlickerLimiter::new(config.flicker),
+        phosphor: config.phosphor.map(Phosphor::new),
+        palette: config.palette,
+        resolution,
+        preview: None,
+    };
+    let mut title = title.to_string();
+    let mut cycles_per_frame = config.cycles_per_frame;
+    let mut frame_rate = config.frame_rate;
+    let mut rom_path = config.rom_path.clone();
+    let mut pacer = FramePacer::new(config.frame_rate);
+    let mut saves = QuickSaves::new(config.rom_path.as_deref());
+    let mut watcher = rom_path
+        .as_ref()
+        .filter(|_| config.watch)
+        .map(|path| LiveReload::with_sources(path, config.sources.clone()));
+    let mut captures = Captures::new(
+        config.rom_path.as_deref(),
+        config.palette,
+        scale,
+        config.frame_rate,
+    );
+    if let (Some(rewind), None) = (config.rewind, &movie) {
+        chip8.enable_rewind(rewind);
+    }
+    let mut editor = MemoryEditor::new(chip8.memory().len(), MEMORY_PAGE_SIZE);
+    let mut memory_window: Option<MemoryWindow> = None;
+    // Whether the memory window has to be drawn even without a new frame
+    let mut memory_changed = false;
+    let rom_dir = match (
+        &config.rom_dir,
+        config.rom_path.as_deref().and_then(Path::parent),
+    ) {
+        (Some(dir), _) => dir.clone(),
+        (None, Some(dir)) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let mut browser = RomBrowser::new(&rom_dir, BROWSER_PAGE_SIZE);
+    let mut browser_window: Option<BrowserWindow> = None;
+    let mut browser_changed = false;
+    let main_window = screen.canvas.window().id();
+    let mut rewinding = false;
+    // Whether the screen has to be drawn even without a new frame
+    let mut repaint = false;
+    let mut attract = config.attract.clone().filter(|_| movie.is_none());
+    // Whether a key was pressed since the last frame, for the attract mode
+    let mut touched = false;
+    let mut events = sdl.event_pump()?;
+    let mut last = Instant::now();
+    'running: loop {
+        // A ROM dropped onto the window or picked in the browser
+        let mut pending: Option<PathBuf> = None;
+        for event in events.poll_iter() {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => break 'running,
+                // With the memory window or the browser open, closing a
+                // window does not quit by itself.
+                Event::Window {
+                    window_id,
+                    win_event: WindowEvent::Close,
+                    ..
+                } => {
+                    if window_id == main_window {
+                        break 'running;
+                    }
+                    let is_browser = browser_window
+                        .as_ref()
+                        .is_some_and(|window| window.canvas.window().id() == window_id);
+                    if is_browser {
+                        browser.toggle();
+                    } else if editor.is_shown() {
+                        editor.toggle(chip8);
+                    }
+                }
+                Event::DropFile { filename, .. } => pending = Some(PathBuf::from(filename)),
+                Event::KeyDown {
+                    keycode: Some(Keycode::Backspace),
+                    ..
+                } => rewinding = true,
+                Event::KeyUp {
+                    keycode: Some(Keycode::Backspace),
+                    ..
+                } => rewinding = false,
+                Event::KeyDown {
+                    keycode: Some(key),
+                    repeat: false,
+                    ..
+                } => {
+                    touched = true;
+                    let name = key.name();
+                    if movie.is_none() {
+                        match browser.handle(&name) {
+                            BrowserAction::Ignored => {}
+                            BrowserAction::Handled => {
+                                if let Some(path) =
+                                    rom_path.as_deref().filter(|_| browser.is_shown())
+                                {
+                                    browser.select(path);
+                                }
+                                browser_changed = true;
+                                continue;
+                            }
+                            BrowserAction::Load(path) => {
+                                pending = Some(path);
+                                continue;
+                            }
+                        }
+                    }
+                    if let (Some(watcher), true, None) = (&mut watcher, name == MARK_KEY, &movie) {
+                        watcher.set_mark(chip8);
+                        let _ = screen
+                            .canvas
+                            .window_mut()
+                            .set_title(&format!("{} (Marked for reloads)", title));
+                        continue;
+                    }
+                    let message = match (
+                        SpeedHotkey::from_name(&name),
+                        CaptureHotkey::from_name(&name),
+                        Hotkey::from_name(&name),
+                        ResetHotkey::from_name(&name),
+                    ) {
+                        (Some(hotkey), _, _, _) => Some(pacer.handle(hotkey)),
+                        (None, Some(hotkey), _, _) => {
+                            Some(captures.handle(chip8.display(), hotkey))
+                        }
+                        (None, None, Some(hotkey), _) if movie.is_none() => {
+                            let message = saves.handle(chip8, hotkey);
+                            screen.preview = saves.thumbnail().map(|t| (t, Instant::now()));
+                            repaint = true;
+                            Some(message)
+                        }
+                        (None, None, None, Some(hotkey)) if movie.is_none() => {
+                            Some(hotkey.apply(chip8))
+                        }
+                        (None, None, Some(_), _) | (None, None, None, Some(_)) => None,
+                        (None, None, None, None) => {
+                            if editor.handle(chip8, &name) {
+                                memory_changed = true;
+                            } else {
+                                config.keymap.apply(chip8, &name, true);
+                            }
+                            None
+                        }
+                    };
+                    if let Some(message) = message {
+                        let _ = screen
+                            .canvas
+                            .window_mut()
+                            .set_title(&format!("{} ({})", title, message));
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(key), ..
+                } => {
+                    config.keymap.apply(chip8, &key.name(), false);
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(path) = pending {
+            let loaded = match movie {
+                Some(_) => Err("Cannot load ROMs during a movie".to_string()),
+                None => load(&path),
+            };
+            let message = match loaded {
+                Ok(loaded) => {
+                    *chip8 = loaded.chip8;
+                    if let Some(rewind) = config.rewind {
+                        chip8.enable_rewind(rewind);
+                    }
+                    title = loaded.title;
+                    cycles_per_frame = loaded.cycles_per_frame;
+                    if loaded.frame_rate != frame_rate {
+                        frame_rate = loaded.frame_rate;
+                        let speed = pacer.speed();
+                        pacer = FramePacer::new(frame_rate);
+                        pacer.set_speed(speed);
+                        speaker.mixer_mut().set_frame_rate(frame_rate);
+                    }
+                    captures.finish();
+                    captures = Captures::new(Some(&path), config.palette, scale, frame_rate);
+                    saves = QuickSaves::new(Some(&path));
+                    let shown = editor.is_shown();
+                    editor = MemoryEditor::new(chip8.memory().len(), MEMORY_PAGE_SIZE);
+                    if shown {
+                        editor.toggle(chip8);
+                    }
+                    memory_changed = true;
+                    if let Some(watcher) = &mut watcher {
+                        *watcher = LiveReload::new(&path);
+                    }
+                    rom_path = Some(path);
+                    // The demo belongs to the ROM on the command line.
+                    attract = None;
+                    None
+                }
+                Err(message) => Some(message),
+            };
+            let window_title = match message {
+                Some(message) => format!("{} ({})", title, message),
+                None => title.clone(),
+            };
+            let _ = screen.canvas.window_mut().set_title(&window_title);
+            browser_changed = true;
+        }
+
+        if let Some(watcher) = watcher.as_mut().filter(|_| movie.is_none()) {
+            if let Some(result) = watcher.poll(chip8) {
+                let message = match result {
+                    Ok(size) => format!("Reloaded, {} bytes", size),
+                    Err(message) => message,
+                };
+                let _ = screen
+                    .canvas
+                    .window_mut()
+                    .set_title(&format!("{} ({})", title, message));
+                memory_changed = true;
+            }
+        }
+
+        let now = Instant::now();
+        let frames = pacer.advance(now - last);
+        last = now;
+        for _ in 0..frames {
+            if rewinding && movie.is_none() {
+                chip8.rewind(1);
+            } else {
+                if let Some(movie) = movie.as_deref_mut() {
+                    if movie.is_finished() {
+                        break 'running;
+                    }
+                    movie.before_frame(chip8);
+                }
+                if let Some(attract) = &mut attract {
+                    let window_title = match attract.before_frame(chip8, mem::take(&mut touched)) {
+                        AttractAction::Restart(_) => {
+                            Some(format!("{} (Demo, press any key)", title))
+                        }
+                        AttractAction::Stop => Some(title.clone()),
+                        AttractAction::Idle | AttractAction::Play(_) => None,
+                    };
+                    if let Some(window_title) = window_title {
+                        let _ = screen.canvas.window_mut().set_title(&window_title);
+                    }
+                }
+                chip8.run_frame(cycles_per_frame);
+                speaker.play(chip8.sound_active(), chip8.voice());
+            }
+            captures.after_frame(chip8.display());
+        }
+        if !editor.is_shown() {
+            memory_window = None;
+        } else if memory_window.is_none() {
+            memory_window = Some(MemoryWindow::open(&video, config.palette)?);
+        }
+        if let Some(window) = memory_window
+            .as_mut()
+            .filter(|_| frames > 0 || memory_changed)
+        {
+            window.present(&editor, chip8);
+            memory_changed = false;
+        }
+        if !browser.is_shown() {
+            browser_window = None;
+        } else if browser_window.is_none() {
+            browser_window = Some(BrowserWindow::open(&video, config.palette)?);
+            browser_changed = true;
+        }
+        if let Some(window) = browser_window.as_mut().filter(|_| browser_changed) {
+            window.present(&browser);
+        }
+        browser_changed = false;
+        if chip8.is_halted() && chip8.fault().is_none() {
+            // The program exited with 00FD
+            break;
+        }
+        // A preview running out takes a frame to disappear while paused.
+        repaint |= screen
+            .preview
+            .as_ref()
+            .is_some_and(|(_, shown)| shown.elapsed() >= PREVIEW_TIME);
+        if frames == 0 && !repaint {
+            // Without vsync, do not spin while no frame is due.
+            thread::sleep(Duration::from_millis(1));
+            continue;
+        }
+        repaint = false;
+
+        screen.present(chip8.display());
+    }
+    // Keep a recording still running.
+    captures.finish();
+    Ok(())
+}
+
+// Blend the palette by the intensity of a pixel, from 0 (background) to 255
+// (foreground).
+fn color(background: [u8; 3], foreground: [u8; 3], intensity: u8) -> Color {
+    let blend = |i: usize| {
+        let (b, f, t) = (background[i] as u32, foreground[i] as u32, intensity as u32);
+        ((b * (255 - t) + f * t) / 255) as u8
+    };
+    Color::RGB(blend(0), blend(1), blend(2))
+}
@@ -0,0 +1,658 @@
+use std::fmt;
+
+use crate::cpu::Chip8;
+use crate::quirks::Quirks;
+use crate::registers::Register;
+use Expect::{Memory as M, Register as R};
+use Register::{I, PC, SP, V};
+
+/// # Self Test
+///
+/// A built-in battery of opcode micro-tests, to check a build of the
+/// emulator on a new platform or backend without downloading test ROMs.
+/// Every case is a few instructions setting up operands, usually edge cases
+/// such as carries, borrows or VF as an operand, followed by the
+/// instruction under test. The case then checks registers and memory
+/// against what the instruction should have done.
+///
+/// Every case runs under all 16 combinations of quirks. Its expectations
+/// depend on the quirks where they matter, so the results read as a matrix
+/// of cases by quirk combinations. A case also fails if it raises a fault.
+///
+/// The expectations follow the original COSMAC VIP behavior where the
+/// quirks do not say otherwise, e.g. VF holds the flag even when it is an
+/// operand, and 8xy5 sets VF when nothing is borrowed, equal operands
+/// included.
+#[derive(Debug, Clone, Copy)]
+pub struct Case {
+    // Instruction under test, e.g. `8xy4`
+    pub instruction: &'static str,
+    pub name: &'static str,
+    program: &'static [u16],
+
+    // Instructions executed, which differs from the program length when
+    // it jumps
+    steps: usize,
+
+    // Keypad mask held for the first `steps` instructions
+    keys: u16,
+
+    // Instructions executed after releasing the keys, for Fx0A which waits
+    // for a whole key stroke
+    steps_after_release: usize,
+
+    expect: fn(Quirks) -> Vec<Expect>,
+}
+
+/// A value a case expects to find after running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expect {
+    Register(Register, u16),
+    Memory(usize, u8),
+}
+
+/// Result of one case under one set of quirks.
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub case: Case,
+    pub quirks: Quirks,
+
+    // What went wrong, `None` if the case passed
+    pub failure: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    // Every case under every combination of quirks, cases first
+    pub outcomes: Vec<Outcome>,
+}
+
+// Where cases keep data, clear of the program.
+const DATA: usize = 0x300;
+
+pub const CASES: &[Case] = &[
+    Case {
+        instruction: "00EE",
+        name: "return after call",
+        // CALL 0x206; LD V0, 1; JP 0x204; RET
+        program: &[0x2206, 0x6001, 0x1204, 0x00EE],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0), 1), R(PC, 0x204), R(SP, 0)],
+    },
+    Case {
+        instruction: "1nnn",
+        name: "jump",
+        program: &[0x1206, 0x6001, 0x6001, 0x6102],
+        steps: 2,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0), 0), R(V(1), 2)],
+    },
+    Case {
+        instruction: "2nnn",
+        name: "call",
+        program: &[0x2206, 0x0000, 0x0000, 0x6102],
+        steps: 2,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(1), 2), R(PC, 0x208), R(SP, 1)],
+    },
+    Case {
+        instruction: "3xkk",
+        name: "skip if equal",
+        program: &[0x6005, 0x3005, 0x6101, 0x6202],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(1), 0), R(V(2), 2)],
+    },
+    Case {
+        instruction: "3xkk",
+        name: "no skip if different",
+        program: &[0x6005, 0x3006, 0x6101],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(1), 1)],
+    },
+    Case {
+        instruction: "4xkk",
+        name: "skip if different",
+        program: &[0x6005, 0x4006, 0x6101, 0x6202],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(1), 0), R(V(2), 2)],
+    },
+    Case {
+        instruction: "4xkk",
+        name: "no skip if equal",
+        program: &[0x6005, 0x4005, 0x6101],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(1), 1)],
+    },
+    Case {
+        instruction: "5xy0",
+        name: "skip if registers equal",
+        program: &[0x6007, 0x6107, 0x5010, 0x6201],
+        steps: 4,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(2), 0), R(PC, 0x20A)],
+    },
+    Case {
+        instruction: "6xkk",
+        name: "load byte",
+        program: &[0x6AFF],
+        steps: 1,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0xA), 0xFF)],
+    },
+    Case {
+        instruction: "7xkk",
+        name: "add wraps without carry",
+        program: &[0x6F05, 0x60FF, 0x7002],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0), 1), R(V(0xF), 5)],
+    },
+    Case {
+        instruction: "8xy0",
+        name: "copy register",
+        program: &[0x6142, 0x8010],
+        steps: 2,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0), 0x42), R(V(1), 0x42)],
+    },
+    Case {
+        instruction: "8xy1",
+        name: "or",
+        program: &[0x6F07, 0x60F0, 0x610F, 0x8011],
+        steps: 4,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |q| vec![R(V(0), 0xFF), R(V(0xF), if q.vf_reset { 0 } else { 7 })],
+    },
+    Case {
+        instruction: "8xy2",
+        name: "and",
+        program: &[0x6F07, 0x603C, 0x610F, 0x8012],
+        steps: 4,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |q| vec![R(V(0), 0x0C), R(V(0xF), if q.vf_reset { 0 } else { 7 })],
+    },
+    Case {
+        instruction: "8xy3",
+        name: "xor",
+        program: &[0x6F07, 0x603C, 0x610F, 0x8013],
+        steps: 4,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |q| vec![R(V(0), 0x33), R(V(0xF), if q.vf_reset { 0 } else { 7 })],
+    },
+    Case {
+        instruction: "8xy4",
+        name: "add without carry",
+        program: &[0x6001, 0x6102, 0x8014],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0), 3), R(V(0xF), 0)],
+    },
+    Case {
+        instruction: "8xy4",
+        name: "add with carry",
+        program: &[0x60FF, 0x6102, 0x8014],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0), 1), R(V(0xF), 1)],
+    },
+    Case {
+        instruction: "8xy4",
+        name: "flag wins over VF result",
+        program: &[0x6FFF, 0x6101, 0x8F14],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0xF), 1)],
+    },
+    Case {
+        instruction: "8xy5",
+        name: "subtract without borrow",
+        program: &[0x6005, 0x6103, 0x8015],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0), 2), R(V(0xF), 1)],
+    },
+    Case {
+        instruction: "8xy5",
+        name: "subtract with borrow",
+        program: &[0x6003, 0x6105, 0x8015],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0), 0xFE), R(V(0xF), 0)],
+    },
+    Case {
+        instruction: "8xy5",
+        name: "subtract equal values",
+        program: &[0x6005, 0x6105, 0x8015],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0), 0), R(V(0xF), 1)],
+    },
+    Case {
+        instruction: "8xy6",
+        name: "shift right",
+        program: &[0x6004, 0x6103, 0x8016],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |q| {
+            if q.shifting {
+                vec![R(V(0), 2), R(V(0xF), 0)]
+            } else {
+                vec![R(V(0), 1), R(V(0xF), 1)]
+            }
+        },
+    },
+    Case {
+        instruction: "8xy6",
+        name: "flag wins over VF result",
+        program: &[0x6F03, 0x6103, 0x8F16],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0xF), 1)],
+    },
+    Case {
+        instruction: "8xy7",
+        name: "reverse subtract",
+        program: &[0x6003, 0x6105, 0x8017],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0), 2), R(V(0xF), 1)],
+    },
+    Case {
+        instruction: "8xy7",
+        name: "reverse subtract with borrow",
+        program: &[0x6005, 0x6103, 0x8017],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0), 0xFE), R(V(0xF), 0)],
+    },
+    Case {
+        instruction: "8xyE",
+        name: "shift left",
+        program: &[0x6081, 0x6140, 0x801E],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |q| {
+            if q.shifting {
+                vec![R(V(0), 0x02), R(V(0xF), 1)]
+            } else {
+                vec![R(V(0), 0x80), R(V(0xF), 0)]
+            }
+        },
+    },
+    Case {
+        instruction: "9xy0",
+        name: "skip if registers differ",
+        program: &[0x6007, 0x6108, 0x9010, 0x6201],
+        steps: 4,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(2), 0), R(PC, 0x20A)],
+    },
+    Case {
+        instruction: "Annn",
+        name: "load I",
+        program: &[0xA123],
+        steps: 1,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(I, 0x123)],
+    },
+    Case {
+        instruction: "Bnnn",
+        name: "jump with offset",
+        program: &[0x6004, 0x6202, 0xB208],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |q| vec![R(PC, if q.jumping { 0x20A } else { 0x20C })],
+    },
+    Case {
+        instruction: "Cxkk",
+        name: "random masked to zero",
+        program: &[0x60FF, 0xC000],
+        steps: 2,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0), 0)],
+    },
+    Case {
+        instruction: "Dxyn",
+        name: "no collision on blank screen",
+        program: &[0x6F05, 0xA300, 0xD011],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0xF), 0)],
+    },
+    Case {
+        instruction: "Dxyn",
+        name: "collision when drawn twice",
+        // LD V0, 0xFF; LD I, 0x300; LD [I], V0; LD I, 0x300; LD V0, 0; DRW V0, V0, 1 twice
+        program: &[0x60FF, 0xA300, 0xF055, 0xA300, 0x6000, 0xD001, 0xD001],
+        steps: 7,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0xF), 1)],
+    },
+    Case {
+        instruction: "Ex9E",
+        name: "skip if key pressed",
+        program: &[0x6005, 0xE09E, 0x6101, 0x6202],
+        steps: 3,
+        keys: 1 << 5,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(1), 0), R(V(2), 2)],
+    },
+    Case {
+        instruction: "Ex9E",
+        name: "no skip if key released",
+        program: &[0x6005, 0xE09E, 0x6101],
+        steps: 3,
+        keys: 1 << 4,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(1), 1)],
+    },
+    Case {
+        instruction: "ExA1",
+        name: "skip if key released",
+        program: &[0x6005, 0xE0A1, 0x6101, 0x6202],
+        steps: 3,
+        keys: 1 << 4,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(1), 0), R(V(2), 2)],
+    },
+    Case {
+        instruction: "ExA1",
+        name: "no skip if key pressed",
+        program: &[0x6005, 0xE0A1, 0x6101],
+        steps: 3,
+        keys: 1 << 5,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(1), 1)],
+    },
+    Case {
+        instruction: "Fx07",
+        name: "read delay timer",
+        program: &[0x6030, 0xF015, 0xF107],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(1), 0x30), R(Register::DT, 0x30)],
+    },
+    Case {
+        instruction: "Fx0A",
+        name: "wait while key held",
+        program: &[0xF30A],
+        steps: 2,
+        keys: 1 << 7,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(3), 0), R(Register::PC, 0x200)],
+    },
+    Case {
+        instruction: "Fx0A",
+        name: "wait for key release",
+        program: &[0xF30A],
+        steps: 2,
+        keys: 1 << 7,
+        steps_after_release: 1,
+        expect: |_| vec![R(V(3), 7), R(Register::PC, 0x202)],
+    },
+    Case {
+        instruction: "Fx18",
+        name: "set sound timer",
+        program: &[0x6030, 0xF018],
+        steps: 2,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(Register::ST, 0x30)],
+    },
+    Case {
+        instruction: "Fx1E",
+        name: "add to I",
+        program: &[0xA300, 0x6005, 0xF01E],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(I, 0x305), M(DATA, 0)],
+    },
+    Case {
+        instruction: "Fx29",
+        name: "font character",
+        program: &[0x600A, 0xF029],
+        steps: 2,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(I, 0xA * 5)],
+    },
+    Case {
+        instruction: "Fx30",
+        name: "large font character",
+        program: &[0x600A, 0xF030],
+        steps: 2,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(I, 0x50 + 0xA * 10)],
+    },
+    Case {
+        instruction: "Fx33",
+        name: "binary-coded decimal",
+        program: &[0x609C, 0xA300, 0xF033],
+        steps: 3,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![M(DATA, 1), M(DATA + 1, 5), M(DATA + 2, 6)],
+    },
+    Case {
+        instruction: "Fx55",
+        name: "store registers",
+        program: &[0x6011, 0x6122, 0x6233, 0xA300, 0xF155],
+        steps: 5,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |q| {
+            vec![
+                M(DATA, 0x11),
+                M(DATA + 1, 0x22),
+                M(DATA + 2, 0),
+                R(I, if q.memory { 0x302 } else { 0x300 }),
+            ]
+        },
+    },
+    Case {
+        instruction: "Fx65",
+        name: "load registers",
+        program: &[
+            0x6011, 0x6122, 0xA300, 0xF155, 0x6000, 0x6100, 0x6233, 0xA300, 0xF165,
+        ],
+        steps: 9,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |q| {
+            vec![
+                R(V(0), 0x11),
+                R(V(1), 0x22),
+                R(V(2), 0x33),
+                R(I, if q.memory { 0x302 } else { 0x300 }),
+            ]
+        },
+    },
+    Case {
+        instruction: "Fx75",
+        name: "save and restore RPL flags",
+        program: &[
+            0x6011, 0x6122, 0x6233, 0xF175, 0x6000, 0x6100, 0x6200, 0xF285,
+        ],
+        steps: 8,
+        keys: 0,
+        steps_after_release: 0,
+        expect: |_| vec![R(V(0), 0x11), R(V(1), 0x22), R(V(2), 0)],
+    },
+];
+
+impl Case {
+    // Run the case on a fresh machine, returning what went wrong if it
+    // failed.
+    pub fn run(&self, mut chip8: Chip8, quirks: Quirks) -> Option<String> {
+        let rom: Vec<u8> = self
+            .program
+            .iter()
+            .flat_map(|op| op.to_be_bytes())
+            .collect();
+        chip8.set_seed(0);
+        chip8.set_quirks(quirks);
+        if let Err(e) = chip8.load_rom(&rom) {
+            return Some(e.to_string());
+        }
+        chip8.set_keys(self.keys);
+        for step in 0..self.steps + self.steps_after_release {
+            if step == self.steps {
+                chip8.set_keys(0);
+            }
+            chip8.step();
+            if let Some(fault) = chip8.fault() {
+                return Some(fault.to_string());
+            }
+        }
+        let mismatches: Vec<String> = (self.expect)(quirks)
+            .into_iter()
+            .filter_map(|expect| {
+                let (actual, expected) = match expect {
+                    Expect::Register(register, value) => (chip8.register(register), value),
+                    Expect::Memory(address, value) => {
+                        (chip8.peek(address).unwrap_or(0) as u16, value as u16)
+                    }
+                };
+                (actual != expected).then(|| {
+                    format!(
+                        "{} = 0x{:X}, expected 0x{:X}",
+                        expect.target(),
+                        actual,
+                        expected
+                    )
+                })
+            })
+            .collect();
+        (!mismatches.is_empty()).then(|| mismatches.join(", "))
+    }
+}
+
+impl Expect {
+    fn target(&self) -> String {
+        match self {
+            Expect::Register(register, _) => register.to_string(),
+            Expect::Memory(address, _) => format!("[0x{:03X}]", address),
+        }
+    }
+}
+
+// Every combination of quirks, bit n of the index switching on the nth of
+// `Quirks::NAMES`.
+pub fn combinations() -> Vec<Quirks> {
+    (0..16)
+        .map(|bits| Quirks {
+            vf_reset: bits & 1 != 0,
+            memory: bits & 2 != 0,
+            shifting: bits & 4 != 0,
+            jumping: bits & 8 != 0,
+        })
+        .collect()
+}
+
+// Run every case under every combination of quirks, on machines created by
+// `new_machine`, e.g. to pick the execution backend.
+pub fn run(new_machine: impl Fn() -> Chip8) -> Report {
+    let outcomes = CASES
+        .iter()
+        .flat_map(|case| {
+            combinations().into_iter().map(|quirks| Outcome {
+                case: *case,
+                quirks,
+                failure: case.run(new_machine(), quirks),
+            })
+        })
+        .collect();
+    Report { outcomes }
+}
+
+impl Report {
+    pub fn passed(&self) -> usize {
+        self.outcomes.iter().filter(|o| o.failure.is_none()).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.outcomes.len() - self.passed()
+    }
+}
+
+// The pass/fail matrix, one row per case and one column per combination of
+// quirks, followed by the first failure of every failing case.
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let columns = combinations().len();
+        let width = CASES.iter().map(|c| c.name.len()).max().unwrap_or(0);
+        for (bit, name) in Quirks::NAMES.iter().enumerate().rev() {
+            write!(f, "{:w$}", "", w = width + 7)?;
+            for column in 0..columns {
+                let on = column & (1 << bit) != 0;
+                write!(f, "{}", if on { '+' } else { '-' })?;
+            }
+            writeln!(f, " {}", name)?;
+        }
+        for row in self.outcomes.chunks(columns) {
+            write!(
+                f,
+                "{} {:w$}  ",
+                row[0].case.instruction,
+                row[0].case.name,
+                w = width
+            )?;
+            for outcome in row {
+                write!(f, "{}", if outcome.failure.is_none() { '.' } else { 'X' })?;
+            }
+            writeln!(f)?;
+        }
+        for row in self.outcomes.chunks(columns) {
+            if let Some(outcome) = row.iter().find(|o| o.failure.is_some()) {
+                writeln!(
+                    f,
+                    "FAIL {} {} (quirks: {}): {}",
+                    outcome.case.instruction,
+                    outcome.case.name,
+                    outcome.quirks,
+                    outcome.failure.as_deref().unwrap_or_default()
+                )?;
+            }
+        }
+        write!(f, "{} passed, {} failed", self.passed(), self.failed())
+    }
+}
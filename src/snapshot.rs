@@ -0,0 +1,54 @@
+use crate::display::Display;
+
+/// # Framebuffer Snapshots
+///
+/// Test ROMs report their results on screen, so checking one means comparing
+/// the screen after a number of frames against a known good picture. A
+/// snapshot is that picture as text, one line per row and one character per
+/// pixel: `.` for an unlit pixel, `#` for the first plane, `o` for the
+/// second and `@` for both. Snapshots are kept in files next to the tests,
+/// where a failing one shows what changed in a plain diff.
+///
+/// When a whole picture is too bulky, the FNV-1a hash of the snapshot text
+/// stands in for it; `hash` of a screen and `hash_text` of its snapshot file
+/// agree.
+pub fn to_text(display: &Display) -> String {
+    let mut text = String::with_capacity((display.width() + 1) * display.height());
+    for row in display.colors().chunks(display.width()) {
+        text.extend(row.iter().map(|&color| PIXELS[color as usize & 3]));
+        text.push('\n');
+    }
+    text
+}
+
+// Characters for the colors 0 to 3.
+const PIXELS: [char; 4] = ['.', '#', 'o', '@'];
+
+pub fn hash(display: &Display) -> u64 {
+    fnv1a(to_text(display).as_bytes())
+}
+
+// Hash a snapshot as read from a file, ignoring blank lines and whitespace
+// around the rows.
+pub fn hash_text(text: &str) -> u64 {
+    fnv1a(normalize(text).as_bytes())
+}
+
+// Whether the screen matches a snapshot read from a file.
+pub fn matches(display: &Display, text: &str) -> bool {
+    to_text(display) == normalize(text)
+}
+
+fn normalize(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .flat_map(|line| [line, "\n"])
+        .collect()
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
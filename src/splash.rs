@@ -0,0 +1,91 @@
+/// # Splash ROM
+///
+/// A tiny program shown when the emulator is started without a ROM, so that
+/// there is something on screen instead of a black window. It writes
+/// "CHIP-8", "DROP A ROM" and "TO PLAY" with its own 3x5 glyphs and then
+/// loops forever. Written for this project and in the public domain.
+#[rustfmt::skip]
+pub const ROM: &[u8] = &[
+    // "CHIP-8"
+    0x61, 0x05, // 200: LD V1, 5
+    0xA2, 0x80, // 202: LD I, 0x280 ('C')
+    0x60, 0x14, // 204: LD V0, 20
+    0xD0, 0x15, // 206: DRW V0, V1, 5
+    0xA2, 0x85, // 208: LD I, 0x285 ('H')
+    0x60, 0x18, // 20A: LD V0, 24
+    0xD0, 0x15, // 20C: DRW V0, V1, 5
+    0xA2, 0x8A, // 20E: LD I, 0x28A ('I')
+    0x60, 0x1C, // 210: LD V0, 28
+    0xD0, 0x15, // 212: DRW V0, V1, 5
+    0xA2, 0x8F, // 214: LD I, 0x28F ('P')
+    0x60, 0x20, // 216: LD V0, 32
+    0xD0, 0x15, // 218: DRW V0, V1, 5
+    0xA2, 0x94, // 21A: LD I, 0x294 ('-')
+    0x60, 0x24, // 21C: LD V0, 36
+    0xD0, 0x15, // 21E: DRW V0, V1, 5
+    0xA2, 0x99, // 220: LD I, 0x299 ('8')
+    0x60, 0x28, // 222: LD V0, 40
+    0xD0, 0x15, // 224: DRW V0, V1, 5
+    // "DROP A ROM"
+    0x61, 0x0F, // 226: LD V1, 15
+    0xA2, 0x9E, // 228: LD I, 0x29E ('D')
+    0x60, 0x0C, // 22A: LD V0, 12
+    0xD0, 0x15, // 22C: DRW V0, V1, 5
+    0xA2, 0xA3, // 22E: LD I, 0x2A3 ('R')
+    0x60, 0x10, // 230: LD V0, 16
+    0xD0, 0x15, // 232: DRW V0, V1, 5
+    0xA2, 0xA8, // 234: LD I, 0x2A8 ('O')
+    0x60, 0x14, // 236: LD V0, 20
+    0xD0, 0x15, // 238: DRW V0, V1, 5
+    0xA2, 0x8F, // 23A: LD I, 0x28F ('P')
+    0x60, 0x18, // 23C: LD V0, 24
+    0xD0, 0x15, // 23E: DRW V0, V1, 5
+    0xA2, 0xAD, // 240: LD I, 0x2AD ('A')
+    0x60, 0x20, // 242: LD V0, 32
+    0xD0, 0x15, // 244: DRW V0, V1, 5
+    0xA2, 0xA3, // 246: LD I, 0x2A3 ('R')
+    0x60, 0x28, // 248: LD V0, 40
+    0xD0, 0x15, // 24A: DRW V0, V1, 5
+    0xA2, 0xA8, // 24C: LD I, 0x2A8 ('O')
+    0x60, 0x2C, // 24E: LD V0, 44
+    0xD0, 0x15, // 250: DRW V0, V1, 5
+    0xA2, 0xB2, // 252: LD I, 0x2B2 ('M')
+    0x60, 0x30, // 254: LD V0, 48
+    0xD0, 0x15, // 256: DRW V0, V1, 5
+    // "TO PLAY"
+    0x61, 0x16, // 258: LD V1, 22
+    0xA2, 0xB7, // 25A: LD I, 0x2B7 ('T')
+    0x60, 0x12, // 25C: LD V0, 18
+    0xD0, 0x15, // 25E: DRW V0, V1, 5
+    0xA2, 0xA8, // 260: LD I, 0x2A8 ('O')
+    0x60, 0x16, // 262: LD V0, 22
+    0xD0, 0x15, // 264: DRW V0, V1, 5
+    0xA2, 0x8F, // 266: LD I, 0x28F ('P')
+    0x60, 0x1E, // 268: LD V0, 30
+    0xD0, 0x15, // 26A: DRW V0, V1, 5
+    0xA2, 0xBC, // 26C: LD I, 0x2BC ('L')
+    0x60, 0x22, // 26E: LD V0, 34
+    0xD0, 0x15, // 270: DRW V0, V1, 5
+    0xA2, 0xAD, // 272: LD I, 0x2AD ('A')
+    0x60, 0x26, // 274: LD V0, 38
+    0xD0, 0x15, // 276: DRW V0, V1, 5
+    0xA2, 0xC1, // 278: LD I, 0x2C1 ('Y')
+    0x60, 0x2A, // 27A: LD V0, 42
+    0xD0, 0x15, // 27C: DRW V0, V1, 5
+    0x12, 0x7E, // 27E: JP 0x27E
+    // Glyphs, 3x5 pixels
+    0x60, 0x80, 0x80, 0x80, 0x60, // 280: 'C'
+    0xA0, 0xA0, 0xE0, 0xA0, 0xA0, // 285: 'H'
+    0xE0, 0x40, 0x40, 0x40, 0xE0, // 28A: 'I'
+    0xC0, 0xA0, 0xC0, 0x80, 0x80, // 28F: 'P'
+    0x00, 0x00, 0xE0, 0x00, 0x00, // 294: '-'
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 299: '8'
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // 29E: 'D'
+    0xC0, 0xA0, 0xC0, 0xA0, 0xA0, // 2A3: 'R'
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 2A8: 'O'
+    0x40, 0xA0, 0xE0, 0xA0, 0xA0, // 2AD: 'A'
+    0xA0, 0xE0, 0xE0, 0xA0, 0xA0, // 2B2: 'M'
+    0xE0, 0x40, 0x40, 0x40, 0x40, // 2B7: 'T'
+    0x80, 0x80, 0x80, 0x80, 0xE0, // 2BC: 'L'
+    0xA0, 0xA0, 0x40, 0x40, 0x40, // 2C1: 'Y'
+];
@@ -0,0 +1,188 @@
+use crate::cpu::Chip8;
+use crate::instruction::{decode, Instruction};
+
+/// # Sprite Scanner
+///
+/// Finds likely sprite data in a ROM without running it. Programs point I at
+/// a sprite with `Annn - LD I, addr` shortly before drawing it with
+/// `Dxyn - DRW Vx, Vy, nibble`, so every `LD I` that is followed by a `DRW`
+/// within a few instructions marks `nnn` as the start of an n-byte sprite
+/// (a 16x16 SUPER-CHIP sprite when n is 0).
+///
+/// Code and data are interleaved freely, so both even and odd alignments are
+/// scanned. The search gives up at the next `LD I`, jump or return, which
+/// keeps obviously unrelated pairs apart.
+///
+/// Sprites can also be edited pixel by pixel, as in a sprite editor panel,
+/// then exported as assembler `db` lines or written straight into a running
+/// machine's memory for a live preview.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sprite {
+    // Address of the first byte in memory
+    pub address: u16,
+
+    // Width in pixels, 8 or 16
+    pub width: usize,
+
+    // Sprite rows, one byte per row (two for 16 pixel wide sprites)
+    pub data: Vec<u8>,
+}
+
+// How many instructions after an `LD I` to look for a `DRW`.
+const LOOKAHEAD: usize = 8;
+
+pub fn scan(rom: &[u8], load_address: u16) -> Vec<Sprite> {
+    let opcode = |offset: usize| -> Option<u16> {
+        Some(u16::from_be_bytes([
+            *rom.get(offset)?,
+            *rom.get(offset + 1)?,
+        ]))
+    };
+
+    let mut sprites: Vec<Sprite> = Vec::new();
+    for offset in 0..rom.len() {
+        let Some(Ok(Instruction::LoadI { addr: address })) = opcode(offset).map(decode) else {
+            continue;
+        };
+
+        for step in 1..=LOOKAHEAD {
+            let Some(op) = opcode(offset + step * 2) else {
+                break;
+            };
+            match decode(op) {
+                Ok(Instruction::Draw { n, .. }) => {
+                    let (width, len) = if n == 0 { (16, 32) } else { (8, n as usize) };
+                    let start = (address as usize).wrapping_sub(load_address as usize);
+                    if let Some(data) = rom.get(start..start + len) {
+                        add(&mut sprites, address, width, data);
+                    }
+                    break;
+                }
+                Ok(
+                    Instruction::LoadI { .. }
+                    | Instruction::Jump { .. }
+                    | Instruction::JumpWithOffset { .. }
+                    | Instruction::Return,
+                ) => break,
+                _ => {}
+            }
+        }
+    }
+    sprites.sort_by_key(|s| s.address);
+    sprites
+}
+
+// Keep one candidate per address, preferring the largest draw seen.
+fn add(sprites: &mut Vec<Sprite>, address: u16, width: usize, data: &[u8]) {
+    match sprites.iter_mut().find(|s| s.address == address) {
+        Some(s) if s.data.len() >= data.len() => {}
+        Some(s) => {
+            s.width = width;
+            s.data = data.to_vec();
+        }
+        None => sprites.push(Sprite {
+            address,
+            width,
+            data: data.to_vec(),
+        }),
+    }
+}
+
+impl Sprite {
+    // An empty sprite to edit, `width` 8 or 16 pixels wide.
+    pub fn blank(address: u16, width: usize, height: usize) -> Sprite {
+        let width = if width > 8 { 16 } else { 8 };
+        Sprite {
+            address,
+            width,
+            data: vec![0; height * width / 8],
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.data.len() / (self.width / 8)
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        let bytes_per_row = self.width / 8;
+        let byte = self.data[y * bytes_per_row + x / 8];
+        byte & (0x80 >> (x % 8)) != 0
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, on: bool) {
+        let bytes_per_row = self.width / 8;
+        let byte = &mut self.data[y * bytes_per_row + x / 8];
+        if on {
+            *byte |= 0x80 >> (x % 8);
+        } else {
+            *byte &= !(0x80 >> (x % 8));
+        }
+    }
+
+    pub fn toggle_pixel(&mut self, x: usize, y: usize) {
+        self.set_pixel(x, y, !self.pixel(x, y));
+    }
+
+    // Copy the sprite into the machine's memory at its address, e.g. to
+    // preview edits in a running program. Fails if it does not fit.
+    pub fn write_to(&self, chip8: &mut Chip8) -> bool {
+        let address = self.address as usize;
+        if (address..address + self.data.len()).any(|a| chip8.peek(a).is_none()) {
+            return false;
+        }
+        for (i, &byte) in self.data.iter().enumerate() {
+            chip8.poke(address + i, byte);
+        }
+        true
+    }
+
+    // Assembler source for the sprite, one `db` line per row with the
+    // pixels spelled out in binary.
+    pub fn to_db(&self, label: &str) -> String {
+        let mut text = format!("{}:\n", label);
+        for row in self.data.chunks(self.width / 8) {
+            let bytes: Vec<String> = row.iter().map(|b| format!("0b{:08b}", b)).collect();
+            text.push_str(&format!("    db {}\n", bytes.join(", ")));
+        }
+        text
+    }
+
+    // Render the sprite as text, `#` for set pixels and `.` for clear ones.
+    pub fn to_ascii(&self) -> String {
+        let mut text = String::new();
+        for y in 0..self.height() {
+            for x in 0..self.width {
+                text.push(if self.pixel(x, y) { '#' } else { '.' });
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+// Lay the sprites out on a grid of 16x16 cells with a one pixel gutter and
+// encode the sheet as a plain PBM image.
+pub fn sheet_pbm(sprites: &[Sprite], columns: usize) -> String {
+    const CELL: usize = 17;
+    let columns = columns.clamp(1, sprites.len().max(1));
+    let rows = sprites.len().div_ceil(columns);
+    let (width, height) = (columns * CELL, rows.max(1) * CELL);
+
+    let mut pixels = vec![false; width * height];
+    for (i, sprite) in sprites.iter().enumerate() {
+        let (left, top) = ((i % columns) * CELL, (i / columns) * CELL);
+        for y in 0..sprite.height().min(16) {
+            for x in 0..sprite.width {
+                pixels[(top + y) * width + left + x] = sprite.pixel(x, y);
+            }
+        }
+    }
+
+    let mut pbm = format!("P1\n{} {}\n", width, height);
+    for row in pixels.chunks(width) {
+        let line: Vec<&str> = row.iter().map(|&p| if p { "1" } else { "0" }).collect();
+        pbm.push_str(&line.join(" "));
+        pbm.push('\n');
+    }
+    pbm
+}
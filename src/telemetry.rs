@@ -0,0 +1,104 @@
+use std::time::Duration;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::Instant;
+
+/// # Telemetry
+///
+/// Runtime statistics collected by the interpreter while a program runs. The
+/// counters are cheap to maintain and can be dumped as a JSON report at the
+/// end of a run (or at any point through the API), which makes it easy to
+/// compare the performance and behaviour of different versions of the
+/// emulator on the same ROM.
+///
+/// - frames - Number of timer ticks (60Hz, or 50Hz for PAL timing)
+/// - instructions - Number of executed instructions
+/// - draw_calls - Number of executed Dxyn instructions
+/// - collisions - Number of draws which set VF because of a collision
+/// - sound_activations - Number of times the buzzer was switched on
+/// - errors - Number of faults raised, e.g. invalid memory writes, whether
+///   the program carried on after them or halted
+#[derive(Debug, Clone)]
+pub struct Telemetry {
+    // Start of the collection, used to compute the achieved speed. Browsers
+    // have no `Instant`, there the speed is not measured.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    started_at: Instant,
+
+    pub frames: u64,
+    pub instructions: u64,
+    pub draw_calls: u64,
+    pub collisions: u64,
+    pub sound_activations: u64,
+    pub errors: u64,
+}
+
+impl Telemetry {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            started_at: Instant::now(),
+            frames: 0,
+            instructions: 0,
+            draw_calls: 0,
+            collisions: 0,
+            sound_activations: 0,
+            errors: 0,
+        }
+    }
+
+    // Wall time elapsed since the collection started, always zero in
+    // browsers.
+    pub fn elapsed(&self) -> Duration {
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        return self.started_at.elapsed();
+        #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+        return Duration::ZERO;
+    }
+
+    // Achieved instructions per second.
+    pub fn ips(&self) -> f64 {
+        self.ips_over(self.elapsed())
+    }
+
+    // Instructions per second, had the collection run for `elapsed`.
+    fn ips_over(&self, elapsed: Duration) -> f64 {
+        let secs = elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.instructions as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    // Render the statistics as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        self.to_json_at(self.elapsed())
+    }
+
+    // Render the statistics as if `elapsed` had passed since the collection
+    // started, for reports which have to be reproducible.
+    pub fn to_json_at(&self, elapsed: Duration) -> String {
+        format!(
+            concat!(
+                "{{\"version\":\"{}\",\"frames\":{},\"instructions\":{},",
+                "\"elapsed_secs\":{:.3},\"ips\":{:.1},\"draw_calls\":{},",
+                "\"collisions\":{},\"sound_activations\":{},\"errors\":{}}}"
+            ),
+            env!("CARGO_PKG_VERSION"),
+            self.frames,
+            self.instructions,
+            elapsed.as_secs_f64(),
+            self.ips_over(elapsed),
+            self.draw_calls,
+            self.collisions,
+            self.sound_activations,
+            self.errors,
+        )
+    }
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
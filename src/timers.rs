@@ -1,3 +1,5 @@
+use core::time::Duration;
+
 /// # Timers & Sound
 ///
 /// Chip-8 provides 2 timers, a delay timer and a sound timer.
@@ -13,3 +15,75 @@
 ///
 /// The sound produced by the Chip-8 interpreter has only one tone. The frequency
 /// of this tone is decided by the author of the interpreter.
+///
+/// `Timers` holds both timers and counts them down independently of how fast
+/// instructions run. Hosts either inject one `tick` per emulated frame, which
+/// is what `Chip8::run_frame` does and keeps headless runs reproducible, or
+/// report the wall time that passed to `advance`, which ticks at exactly the
+/// timer rate however often it is called.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timers {
+    pub delay: u8,
+    pub sound: u8,
+
+    // Ticks per second, 60Hz or 50Hz with PAL timing
+    rate: u32,
+
+    // Elapsed time not yet turned into ticks, in nanoseconds times `rate`
+    pending: u128,
+}
+
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+
+impl Timers {
+    pub fn new() -> Timers {
+        Timers::with_rate(60)
+    }
+
+    pub fn with_rate(rate: u32) -> Timers {
+        Timers {
+            delay: 0,
+            sound: 0,
+            rate: rate.max(1),
+            pending: 0,
+        }
+    }
+
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    pub fn set_rate(&mut self, rate: u32) {
+        self.rate = rate.max(1);
+    }
+
+    // Count both timers down by one.
+    pub fn tick(&mut self) {
+        self.delay = self.delay.saturating_sub(1);
+        self.sound = self.sound.saturating_sub(1);
+    }
+
+    // Account for `elapsed` wall time, ticking as often as is due, and
+    // return the number of ticks.
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        self.pending += elapsed.as_nanos() * self.rate as u128;
+        let due = self.pending / NANOS_PER_SECOND;
+        self.pending %= NANOS_PER_SECOND;
+        // Past 255 ticks both timers have run out anyway.
+        for _ in 0..due.min(u8::MAX as u128) {
+            self.tick();
+        }
+        due.min(u32::MAX as u128) as u32
+    }
+
+    // Whether the buzzer sounds, i.e. the sound timer is running.
+    pub fn is_beeping(&self) -> bool {
+        self.sound > 0
+    }
+}
+
+impl Default for Timers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+use crate::audit::AuditEvent;
+use crate::disasm;
+use crate::instruction::decode;
+use crate::registers::Register;
+
+/// # Execution Tracing
+///
+/// Tracing follows a misbehaving ROM instruction by instruction. With a
+/// `TraceSink` installed through `Chip8::set_trace_sink`, every executed
+/// instruction is reported as a `TraceEntry`: its address, opcode and the
+/// memory accesses and register changes it made, in the form of audit
+/// events. Any `FnMut(&TraceEntry)` closure is a sink, and `TraceEntry`'s
+/// `Display` gives a disassembly line such as
+///
+/// ```text
+/// 0x204: F055  ld [i], v0  write 0x300 00->01
+/// ```
+///
+/// Independently of the sink, `Chip8::enable_history` keeps the last
+/// instructions run in a `History`, to be dumped when the machine crashes.
+/// The history only records the instructions themselves, plus their events
+/// while a sink is installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub pc: u16,
+    pub opcode: u16,
+    pub events: Vec<AuditEvent>,
+}
+
+pub trait TraceSink {
+    fn record(&mut self, entry: &TraceEntry);
+}
+
+impl<F: FnMut(&TraceEntry)> TraceSink for F {
+    fn record(&mut self, entry: &TraceEntry) {
+        self(entry)
+    }
+}
+
+/// The number of instructions the CLI keeps to dump on a crash.
+pub const HISTORY_LEN: usize = 256;
+
+/// The last instructions run, oldest first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct History {
+    entries: VecDeque<TraceEntry>,
+    capacity: usize,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> History {
+        History {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, entry: TraceEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &TraceEntry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl fmt::Display for History {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+// State of tracing in a machine: the sink, the history and the events of
+// the instruction being executed.
+pub(crate) struct Tracer {
+    pub(crate) sink: Option<Box<dyn TraceSink>>,
+    pub(crate) history: Option<History>,
+    pub(crate) events: Vec<AuditEvent>,
+}
+
+impl Tracer {
+    pub(crate) fn new() -> Tracer {
+        Tracer {
+            sink: None,
+            history: None,
+            events: Vec::new(),
+        }
+    }
+
+    pub(crate) fn is_idle(&self) -> bool {
+        self.sink.is_none() && self.history.is_none()
+    }
+
+    // Report an executed instruction, with the events collected for it.
+    pub(crate) fn finish(&mut self, pc: u16, opcode: u16) {
+        let entry = TraceEntry {
+            pc,
+            opcode,
+            events: std::mem::take(&mut self.events),
+        };
+        if let Some(sink) = &mut self.sink {
+            sink.record(&entry);
+        }
+        if let Some(history) = &mut self.history {
+            history.push(entry);
+        }
+    }
+}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tracer")
+            .field("history", &self.history)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = decode(self.opcode)
+            .map(|instruction| disasm::mnemonic(instruction, &BTreeMap::new()))
+            .unwrap_or_else(|_| "???".to_string());
+        write!(f, "0x{:03X}: {:04X}  {}", self.pc, self.opcode, text)?;
+        for (index, event) in self.events.iter().enumerate() {
+            f.write_str(if index == 0 { "  " } else { ", " })?;
+            match *event {
+                AuditEvent::Read { address, value, .. } => {
+                    write!(f, "read 0x{:03X}={:02X}", address, value)?
+                }
+                AuditEvent::Write {
+                    address, old, new, ..
+                } => write!(f, "write 0x{:03X} {:02X}->{:02X}", address, old, new)?,
+                AuditEvent::Register {
+                    register: Register::I,
+                    old,
+                    new,
+                    ..
+                } => write!(f, "I {:03X}->{:03X}", old, new)?,
+                AuditEvent::Register {
+                    register, old, new, ..
+                } => write!(f, "{} {:02X}->{:02X}", register, old, new)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,509 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::mem;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{
+    Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue, Command};
+
+use crate::attract::{AttractAction, AttractMode};
+use crate::audio::{AudioConfig, Buzzer, Mixer, Speaker};
+use crate::cpu::{Chip8, ResetHotkey};
+use crate::display::Display;
+use crate::host::AudioBackend;
+use crate::keyboard::{KeyMap, KeypadView};
+use crate::memview::MemoryEditor;
+use crate::movie::MovieSession;
+use crate::pacing::{FramePacer, SpeedHotkey};
+use crate::palette::Palette;
+use crate::quicksave::{Hotkey, QuickSaves};
+use crate::reload::{LiveReload, MARK_KEY};
+use crate::savestate::Thumbnail;
+
+/// # Terminal Frontend
+///
+/// Plays ROMs in a terminal, for when no graphical environment is around.
+/// Each character cell shows two pixels stacked on top of each other with
+/// the Unicode half blocks, so the 64x32 screen takes 64x16 cells (128x32
+/// in high resolution), followed by a status bar with the ROM's title and
+/// the achieved frame rate. Where the terminal is wide enough, the keypad
+/// is shown to the right of the screen, with held keys in brackets and a
+/// highlighted note while the program waits for a key in Fx0A, see
+/// `KeypadView`. With a palette, each cell is drawn in the colors of its
+/// two pixels on terminals supporting 24-bit color; without, lit pixels
+/// take the terminal's text color. The buzzer plays through a `Speaker` on
+/// the `Buzzer` given to `run`, or rings the terminal bell when it starts
+/// if there is none.
+///
+/// Host keys are mapped onto the keypad by a `KeyMap`, and Escape or Ctrl-C
+/// quits. F5 and F9 save and load quick save slots, F6 and F7 select the
+/// slot, see `QuickSaves`, except while a movie is recorded or replayed;
+/// the slot's thumbnail then takes the place of the keypad for a while. A
+/// replay quits once it has run all of its frames. F1 pauses, F2 and F3 slow
+/// the game down and speed it up, see `FramePacer`, and F11 and Home reset
+/// the machine, see `ResetHotkey`. F8 shows a live hex dump of memory under
+/// the status bar, with the byte under the cursor highlighted and editable,
+/// see `MemoryEditor`. When watching, the ROM is reloaded whenever its file,
+/// or one of the sources it is linked from, changes, and End marks the point
+/// reloads go back to, see `LiveReload`.
+/// With an attract mode and no movie, a demo plays after the keys were left
+/// alone for a while, until any key is pressed, see `AttractMode`.
+///
+/// Most terminals only report key presses, plus repeats while a key is
+/// held; there, a key counts as released once it has not been reported for
+/// `HOLD_TIME`. Terminals supporting the kitty keyboard protocol report
+/// releases, which are used instead.
+#[derive(Debug, Clone)]
+pub struct TuiConfig {
+    pub title: String,
+    pub cycles_per_frame: usize,
+
+    // Timer and frame rate, in Hz
+    pub frame_rate: u32,
+
+    pub keymap: KeyMap,
+    pub audio: AudioConfig,
+    pub palette: Option<Palette>,
+
+    // Where the quick save slots are kept, see `QuickSaves`
+    pub rom_path: Option<PathBuf>,
+
+    // Whether to reload the ROM at `rom_path` whenever it changes
+    pub watch: bool,
+
+    // The sources `rom_path` is linked from, which watching reassembles it
+    // from instead
+    pub sources: Vec<PathBuf>,
+
+    // Demo played while the keys are left alone
+    pub attract: Option<AttractMode>,
+}
+
+impl Default for TuiConfig {
+    fn default() -> Self {
+        TuiConfig {
+            title: "CHIP-8".to_string(),
+            cycles_per_frame: 12,
+            frame_rate: 60,
+            keymap: KeyMap::standard(),
+            audio: AudioConfig::default(),
+            palette: None,
+            rom_path: None,
+            watch: false,
+            sources: Vec::new(),
+            attract: None,
+        }
+    }
+}
+
+// How long a key stays down after its last press or repeat, on terminals
+// without release events. Long enough to bridge the initial repeat delay.
+const HOLD_TIME: Duration = Duration::from_millis(500);
+
+// Bytes shown by the memory panel at a time.
+const MEMORY_PAGE_SIZE: usize = 0x80;
+
+// Lines of the keypad grid drawn by `KeypadView::render`, columns taken by
+// its widest line, the note on Fx0A, and columns between it and the screen.
+const KEYPAD_ROWS: usize = 9;
+const KEYPAD_WIDTH: u16 = 24;
+const KEYPAD_GAP: u16 = 2;
+
+// How long the thumbnail of the selected quick save slot stays up.
+const PREVIEW_TIME: Duration = Duration::from_secs(3);
+
+// Characters for thumbnail pixels, from off to on.
+const SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+
+// Restores the terminal however `run` exits.
+struct Session {
+    enhanced: bool,
+}
+
+impl Session {
+    fn start(out: &mut impl Write) -> io::Result<Session> {
+        terminal::enable_raw_mode()?;
+        let enhanced = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        execute!(out, EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+        if enhanced {
+            execute!(
+                out,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+        Ok(Session { enhanced })
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let mut out = io::stdout();
+        if self.enhanced {
+            let _ = execute!(out, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(out, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// Run the machine in the terminal until the user quits, recording or
+// replaying `movie` if given.
+pub fn run(
+    chip8: &mut Chip8,
+    config: &TuiConfig,
+    buzzer: Option<Box<dyn Buzzer>>,
+    mut movie: Option<&mut MovieSession>,
+) -> io::Result<()> {
+    let mut out = io::stdout();
+    let session = Session::start(&mut out)?;
+
+    let mut speaker = buzzer.map(|buzzer| {
+        let mut mixer = Mixer::new(config.audio);
+        mixer.set_frame_rate(config.frame_rate);
+        Speaker::new(mixer, buzzer)
+    });
+
+    // Host keys held down, and when they were last reported
+    let mut held: BTreeMap<String, Instant> = BTreeMap::new();
+    let mut pacer = FramePacer::new(config.frame_rate);
+    let mut last = Instant::now();
+    let (mut second, mut frames_this_second, mut fps) = (last, 0, 0);
+    let mut sounding = false;
+    let mut saves = QuickSaves::new(config.rom_path.as_deref());
+    let mut watcher = config
+        .rom_path
+        .as_ref()
+        .filter(|_| config.watch)
+        .map(|path| LiveReload::with_sources(path, config.sources.clone()));
+    let mut message = String::new();
+    // Whether the screen has to be drawn even without a new frame
+    let mut redraw = false;
+    let mut editor = MemoryEditor::new(chip8.memory().len(), MEMORY_PAGE_SIZE);
+    let mut resolution = chip8.display().resolution();
+    let mut panel = editor.is_shown();
+    // The selected slot as shown in place of the keypad, and since when
+    let mut preview: Option<(Vec<String>, Instant)> = None;
+    let mut previewing = false;
+    let mut attract = config.attract.clone().filter(|_| movie.is_none());
+    // Whether a key was pressed since the last frame, for the attract mode
+    let mut touched = false;
+    loop {
+        while event::poll(Duration::ZERO)? {
+            let Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind,
+                ..
+            }) = event::read()?
+            else {
+                continue;
+            };
+            let ctrl_c = code == KeyCode::Char('c') && modifiers.contains(KeyModifiers::CONTROL);
+            if code == KeyCode::Esc || ctrl_c {
+                return Ok(());
+            }
+            touched |= kind == KeyEventKind::Press;
+            let Some(host) = host_key(code) else {
+                continue;
+            };
+            if let Some(hotkey) = SpeedHotkey::from_name(&host) {
+                if kind == KeyEventKind::Press {
+                    message = pacer.handle(hotkey);
+                    redraw = true;
+                }
+                continue;
+            }
+            if let Some(hotkey) = Hotkey::from_name(&host) {
+                if kind == KeyEventKind::Press && movie.is_none() {
+                    message = saves.handle(chip8, hotkey);
+                    preview = Some((slot_preview(&mut saves), Instant::now()));
+                    redraw = true;
+                }
+                continue;
+            }
+            if let (Some(watcher), true) = (&mut watcher, host == MARK_KEY) {
+                if kind == KeyEventKind::Press && movie.is_none() {
+                    watcher.set_mark(chip8);
+                    message = "Marked for reloads".to_string();
+                    redraw = true;
+                }
+                continue;
+            }
+            if let Some(hotkey) = ResetHotkey::from_name(&host) {
+                if kind == KeyEventKind::Press && movie.is_none() {
+                    message = hotkey.apply(chip8);
+                    redraw = true;
+                }
+                continue;
+            }
+            if kind != KeyEventKind::Release && editor.handle(chip8, &host) {
+                redraw = true;
+                continue;
+            }
+            if kind == KeyEventKind::Release {
+                held.remove(&host);
+                config.keymap.apply(chip8, &host, false);
+            } else {
+                held.insert(host.clone(), Instant::now());
+                config.keymap.apply(chip8, &host, true);
+            }
+        }
+
+        if let Some(watcher) = watcher.as_mut().filter(|_| movie.is_none()) {
+            if let Some(result) = watcher.poll(chip8) {
+                message = match result {
+                    Ok(size) => format!("Reloaded, {} bytes", size),
+                    Err(message) => message,
+                };
+                redraw = true;
+            }
+        }
+
+        let now = Instant::now();
+        if preview
+            .as_ref()
+            .is_some_and(|(_, shown)| now - *shown >= PREVIEW_TIME)
+        {
+            preview = None;
+            redraw = true;
+        }
+        if !session.enhanced {
+            held.retain(|host, &mut pressed| {
+                let down = now - pressed < HOLD_TIME;
+                if !down {
+                    config.keymap.apply(chip8, host, false);
+                }
+                down
+            });
+        }
+
+        let frames = pacer.advance(now - last);
+        last = now;
+        for _ in 0..frames {
+            if let Some(movie) = movie.as_deref_mut() {
+                if movie.is_finished() {
+                    return Ok(());
+                }
+                movie.before_frame(chip8);
+            }
+            if let Some(attract) = &mut attract {
+                match attract.before_frame(chip8, mem::take(&mut touched)) {
+                    AttractAction::Restart(_) => message = "Demo, press any key".to_string(),
+                    AttractAction::Stop => message.clear(),
+                    AttractAction::Idle | AttractAction::Play(_) => {}
+                }
+            }
+            chip8.run_frame(config.cycles_per_frame);
+            if let Some(speaker) = &mut speaker {
+                speaker.play(chip8.sound_active(), chip8.voice());
+            }
+        }
+        if chip8.is_halted() && chip8.fault().is_none() {
+            // The program exited with 00FD
+            return Ok(());
+        }
+        if frames == 0 && !redraw {
+            thread::sleep(Duration::from_millis(1));
+            continue;
+        }
+        redraw = false;
+        frames_this_second += frames;
+        if now - second >= Duration::from_secs(1) {
+            (second, fps, frames_this_second) = (now, frames_this_second, 0);
+        }
+
+        let display = chip8.display();
+        // Screens of another size lay the terminal out anew.
+        if display.resolution() != resolution
+            || editor.is_shown() != panel
+            || preview.is_some() != previewing
+        {
+            (resolution, panel) = (display.resolution(), editor.is_shown());
+            previewing = preview.is_some();
+            queue!(out, Clear(ClearType::All))?;
+        }
+        let lines = match &config.palette {
+            Some(palette) => render_colors(display, palette),
+            None => render(display.pixels(), display.width()),
+        };
+        for (row, line) in lines.iter().enumerate() {
+            queue!(out, MoveTo(0, row as u16), Print(line))?;
+        }
+        let mut status = format!("{} | {} fps | Esc quits", config.title, fps);
+        if !message.is_empty() {
+            status = format!("{} | {}", status, message);
+        }
+        let side_column = display.width() as u16 + KEYPAD_GAP;
+        let columns = terminal::size()?.0;
+        match &preview {
+            Some((lines, _)) => {
+                let width = lines.iter().map(|line| line.chars().count()).max();
+                if columns >= side_column + width.unwrap_or(0) as u16 {
+                    for (row, line) in lines.iter().enumerate() {
+                        queue!(out, MoveTo(side_column, row as u16), Print(line))?;
+                    }
+                }
+            }
+            None if columns >= side_column + KEYPAD_WIDTH => {
+                draw_keypad(&mut out, &chip8.keypad().view(), side_column)?;
+            }
+            None => {}
+        }
+        let status_row = (display.height() / 2) as u16;
+        queue!(
+            out,
+            MoveTo(0, status_row),
+            Clear(ClearType::CurrentLine),
+            Print(status)
+        )?;
+        if editor.is_shown() {
+            let lines = editor.render(chip8);
+            for (row, line) in lines.iter().enumerate() {
+                queue!(
+                    out,
+                    MoveTo(0, status_row + 1 + row as u16),
+                    Clear(ClearType::CurrentLine),
+                    Print(line)
+                )?;
+            }
+            let (column, row) = editor.cursor_position();
+            let (line, row) = (&lines[row], status_row + 1 + row as u16);
+            queue!(
+                out,
+                MoveTo(column as u16, row),
+                SetAttribute(Attribute::Reverse),
+                Print(&line[column..column + 2]),
+                SetAttribute(Attribute::Reset)
+            )?;
+        }
+        if chip8.sound_active() && !sounding && speaker.is_none() {
+            queue!(out, Print('\x07'))?;
+        }
+        sounding = chip8.sound_active();
+        out.flush()?;
+    }
+}
+
+// Draw the keypad from `column` on, with the note of a program waiting in
+// Fx0A in reverse video under it, or blanks over the last one.
+fn draw_keypad(out: &mut impl Write, view: &KeypadView, column: u16) -> io::Result<()> {
+    let text = view.render();
+    let mut lines = text.lines();
+    for (row, line) in lines.by_ref().take(KEYPAD_ROWS).enumerate() {
+        queue!(out, MoveTo(column, row as u16), Print(line))?;
+    }
+    queue!(out, MoveTo(column, KEYPAD_ROWS as u16))?;
+    match lines.next() {
+        Some(note) => queue!(
+            out,
+            SetAttribute(Attribute::Reverse),
+            Print(note),
+            SetAttribute(Attribute::Reset)
+        ),
+        None => queue!(out, Print(" ".repeat(KEYPAD_WIDTH as usize))),
+    }
+}
+
+// The selected quick save slot as drawn in place of the keypad: its number
+// over the thumbnail saved in it.
+fn slot_preview(saves: &mut QuickSaves) -> Vec<String> {
+    let mut lines = vec![format!("Slot {}", saves.selected())];
+    match saves.thumbnail() {
+        Some(thumbnail) => lines.extend(render_thumbnail(&thumbnail)),
+        None => lines.push("(empty)".to_string()),
+    }
+    lines
+}
+
+// Draw a thumbnail as text in shades, one pixel row per line and two
+// characters per pixel to keep its shape in cells twice as tall as wide.
+pub fn render_thumbnail(thumbnail: &Thumbnail) -> Vec<String> {
+    thumbnail
+        .pixels
+        .chunks(thumbnail.width.max(1) as usize)
+        .map(|row| {
+            row.iter()
+                .flat_map(|&gray| [SHADES[gray as usize * SHADES.len() / 256]; 2])
+                .collect()
+        })
+        .collect()
+}
+
+// Draw a screen `width` pixels wide as text, two pixel rows per line.
+pub fn render(pixels: &[bool], width: usize) -> Vec<String> {
+    pixels
+        .chunks(width * 2)
+        .map(|rows| {
+            let (top, bottom) = rows.split_at(width.min(rows.len()));
+            (0..width)
+                .map(|x| {
+                    let lit = |row: &[bool]| row.get(x).copied().unwrap_or(false);
+                    match (lit(top), lit(bottom)) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Draw a screen as text in the colors of a palette, two pixel rows per
+// line: the upper half block takes the color of the upper pixel, the cell
+// background that of the lower one.
+pub fn render_colors(display: &Display, palette: &Palette) -> Vec<String> {
+    let width = display.width();
+    let rgb = |color: u8| {
+        let [r, g, b] = palette.color(color);
+        Color::Rgb { r, g, b }
+    };
+    display
+        .colors()
+        .chunks(width * 2)
+        .map(|rows| {
+            let (top, bottom) = rows.split_at(width.min(rows.len()));
+            let mut line = String::new();
+            let mut last = None;
+            for (x, &upper) in top.iter().enumerate() {
+                let colors = (upper, bottom.get(x).copied().unwrap_or(0));
+                if last != Some(colors) {
+                    let _ = SetForegroundColor(rgb(colors.0)).write_ansi(&mut line);
+                    let _ = SetBackgroundColor(rgb(colors.1)).write_ansi(&mut line);
+                    last = Some(colors);
+                }
+                line.push('▀');
+            }
+            let _ = ResetColor.write_ansi(&mut line);
+            line
+        })
+        .collect()
+}
+
+// The name of a host key as used by `KeyMap`.
+fn host_key(code: KeyCode) -> Option<String> {
+    match code {
+        KeyCode::Char(c) => Some(c.to_string()),
+        KeyCode::Up => Some("Up".to_string()),
+        KeyCode::Down => Some("Down".to_string()),
+        KeyCode::Left => Some("Left".to_string()),
+        KeyCode::Right => Some("Right".to_string()),
+        KeyCode::PageUp => Some("PageUp".to_string()),
+        KeyCode::PageDown => Some("PageDown".to_string()),
+        KeyCode::Home => Some("Home".to_string()),
+        KeyCode::End => Some("End".to_string()),
+        KeyCode::F(n) => Some(format!("F{}", n)),
+        _ => None,
+    }
+}
@@ -0,0 +1,286 @@
+use js_sys::Function;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::audio::Voice;
+use crate::cartridge::Variant;
+use crate::cpu::Chip8;
+use crate::host::AudioBackend;
+use crate::palette::Palette;
+use crate::phosphor::{Phosphor, PhosphorConfig};
+use crate::quirks::Quirks;
+
+/// # JavaScript API
+///
+/// The surface exported to JavaScript with the `web` feature, packaged by
+/// `wasm-pack build web` from the `cdylib` in `web/` and published to npm
+/// as `chip-8-rs-web`. It wraps one `Chip8` in an `Emulator` class with
+/// camelCase methods, keys as a `Key` enum, memory and save states as
+/// `Uint8Array`s, and callbacks for the events a page reacts to: the buzzer
+/// switching on or off, and faults.
+/// wasm-bindgen generates the TypeScript declarations from these bindings.
+///
+/// The host drives the emulator from `requestAnimationFrame`, calling
+/// `runFrame` once per 60Hz tick (see `FramePacer` for displays running at
+/// other rates), then draws `framebuffer` onto a canvas.
+///
+/// The core itself builds for `wasm32-unknown-unknown` without the feature
+/// too: the RNG is seeded through the Web Crypto API, and as browsers have
+/// no `Instant`, telemetry does not measure the achieved speed there.
+#[wasm_bindgen]
+pub struct Emulator {
+    chip8: Chip8,
+    cycles_per_frame: usize,
+    palette: Palette,
+    phosphor: Phosphor,
+    sound: SoundEvents,
+    on_fault: Option<Function>,
+}
+
+// The `AudioBackend` of the page, calling back when the buzzer switches on
+// or off and leaving the sound itself to JavaScript.
+struct SoundEvents {
+    sounding: bool,
+    callback: Option<Function>,
+}
+
+impl AudioBackend for SoundEvents {
+    fn play(&mut self, sounding: bool, _voice: Voice) {
+        if sounding != self.sounding {
+            self.sounding = sounding;
+            if let Some(callback) = &self.callback {
+                let _ = callback.call1(&JsValue::NULL, &JsValue::from_bool(sounding));
+            }
+        }
+    }
+}
+
+/// The 16 keys of the hexadecimal keypad.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Key0 = 0x0,
+    Key1 = 0x1,
+    Key2 = 0x2,
+    Key3 = 0x3,
+    Key4 = 0x4,
+    Key5 = 0x5,
+    Key6 = 0x6,
+    Key7 = 0x7,
+    Key8 = 0x8,
+    Key9 = 0x9,
+    KeyA = 0xA,
+    KeyB = 0xB,
+    KeyC = 0xC,
+    KeyD = 0xD,
+    KeyE = 0xE,
+    KeyF = 0xF,
+}
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "(active: boolean) => void")]
+    pub type SoundCallback;
+
+    #[wasm_bindgen(typescript_type = "(message: string) => void")]
+    pub type FaultCallback;
+}
+
+// Instructions per frame unless set otherwise, 720 per second at 60Hz.
+const DEFAULT_CYCLES_PER_FRAME: usize = 12;
+
+#[wasm_bindgen]
+impl Emulator {
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: Option<u32>) -> Emulator {
+        let mut chip8 = Chip8::new();
+        if let Some(seed) = seed {
+            chip8.set_seed(seed as u64);
+        }
+        Emulator {
+            chip8,
+            cycles_per_frame: DEFAULT_CYCLES_PER_FRAME,
+            palette: Palette::default(),
+            phosphor: Phosphor::new(PhosphorConfig {
+                frames: 1,
+                ..PhosphorConfig::default()
+            }),
+            sound: SoundEvents {
+                sounding: false,
+                callback: None,
+            },
+            on_fault: None,
+        }
+    }
+
+    #[wasm_bindgen(js_name = loadRom)]
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), JsError> {
+        self.phosphor.clear();
+        self.chip8
+            .load_rom(rom)
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    // Restart the loaded program on a cleared machine, see `Chip8::reset`.
+    pub fn reset(&mut self) {
+        self.chip8.reset(true);
+    }
+
+    // Restart the program where it stands in memory.
+    #[wasm_bindgen(js_name = softReset)]
+    pub fn soft_reset(&mut self) {
+        self.chip8.soft_reset();
+    }
+
+    // While paused, `runFrame` leaves the machine as it is.
+    pub fn pause(&mut self) {
+        self.chip8.pause();
+    }
+
+    pub fn resume(&mut self) {
+        self.chip8.resume();
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn paused(&self) -> bool {
+        self.chip8.is_paused()
+    }
+
+    // Run one 60Hz frame, then report the events it caused.
+    #[wasm_bindgen(js_name = runFrame)]
+    pub fn run_frame(&mut self) {
+        let errors = self.chip8.telemetry().errors;
+        self.phosphor.push(self.chip8.display());
+        self.chip8.run_frame(self.cycles_per_frame);
+
+        self.sound
+            .play(self.chip8.sound_active(), self.chip8.voice());
+        if self.chip8.telemetry().errors > errors {
+            if let (Some(callback), Some(fault)) = (&self.on_fault, self.chip8.fault()) {
+                let _ = callback.call1(&JsValue::NULL, &JsValue::from_str(&fault.to_string()));
+            }
+        }
+    }
+
+    #[wasm_bindgen(js_name = setKey)]
+    pub fn set_key(&mut self, key: Key, pressed: bool) {
+        self.chip8.set_key(key as u8, pressed);
+    }
+
+    // Set the quirks from a spec such as `schip,memory=on`.
+    #[wasm_bindgen(js_name = setQuirks)]
+    pub fn set_quirks(&mut self, spec: &str) -> Result<(), JsError> {
+        let quirks: Quirks = spec.parse().map_err(|e: String| JsError::new(&e))?;
+        self.chip8.set_quirks(quirks);
+        Ok(())
+    }
+
+    // Select the machine the program targets, `chip8`, `schip` or `xochip`,
+    // before loading it.
+    #[wasm_bindgen(js_name = setVariant)]
+    pub fn set_variant(&mut self, name: &str) -> Result<(), JsError> {
+        let variant: Variant = name.parse().map_err(|e: String| JsError::new(&e))?;
+        self.chip8.set_variant(variant);
+        Ok(())
+    }
+
+    // Set the colors of `rgba` from a preset name such as `amber`, or from
+    // colors such as `#102010,#80FF80`.
+    #[wasm_bindgen(js_name = setPalette)]
+    pub fn set_palette(&mut self, spec: &str) -> Result<(), JsError> {
+        self.palette = spec.parse().map_err(|e: String| JsError::new(&e))?;
+        Ok(())
+    }
+
+    // Let pixels of `rgba` fade out over frames, e.g. `4,0.5` for four
+    // frames losing half their brightness each; `1` turns it off. See
+    // `Phosphor`.
+    #[wasm_bindgen(js_name = setPhosphor)]
+    pub fn set_phosphor(&mut self, spec: &str) -> Result<(), JsError> {
+        let config: PhosphorConfig = spec.parse().map_err(|e: String| JsError::new(&e))?;
+        self.phosphor.set_config(config);
+        Ok(())
+    }
+
+    #[wasm_bindgen(getter, js_name = cyclesPerFrame)]
+    pub fn cycles_per_frame(&self) -> usize {
+        self.cycles_per_frame
+    }
+
+    #[wasm_bindgen(setter, js_name = cyclesPerFrame)]
+    pub fn set_cycles_per_frame(&mut self, cycles: usize) {
+        self.cycles_per_frame = cycles;
+    }
+
+    #[wasm_bindgen(getter, js_name = soundActive)]
+    pub fn sound_active(&self) -> bool {
+        self.chip8.sound_active()
+    }
+
+    // The screen, `width` by `height` bytes in row-major order, 1 for a lit
+    // pixel and 0 for an unlit one.
+    #[wasm_bindgen(getter)]
+    pub fn framebuffer(&self) -> Vec<u8> {
+        self.chip8
+            .framebuffer()
+            .iter()
+            .map(|&on| on as u8)
+            .collect()
+    }
+
+    // The screen in the colors of the palette, `width` by `height` RGBA
+    // pixels ready for an `ImageData`, with the afterglow of `setPhosphor`.
+    #[wasm_bindgen(getter)]
+    pub fn rgba(&self) -> Vec<u8> {
+        self.phosphor.rgba(self.chip8.display(), &self.palette)
+    }
+
+    // Size of the screen, which changes when the program switches to or
+    // from high resolution.
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> usize {
+        self.chip8.display().width()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> usize {
+        self.chip8.display().height()
+    }
+
+    // A copy of the 4KB address space.
+    #[wasm_bindgen(getter)]
+    pub fn memory(&self) -> Vec<u8> {
+        self.chip8.memory().to_vec()
+    }
+
+    #[wasm_bindgen(js_name = saveState)]
+    pub fn save_state(&self) -> Vec<u8> {
+        self.chip8.save_state()
+    }
+
+    #[wasm_bindgen(js_name = loadState)]
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), JsError> {
+        self.chip8
+            .load_state(state)
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    // The telemetry counters as a JSON string.
+    #[wasm_bindgen(js_name = telemetryJson)]
+    pub fn telemetry_json(&self) -> String {
+        self.chip8.telemetry().to_json()
+    }
+
+    // Call `callback` whenever the buzzer switches on or off, or stop
+    // calling any when given `undefined`.
+    #[wasm_bindgen(js_name = onSound)]
+    pub fn on_sound(&mut self, callback: Option<SoundCallback>) {
+        self.sound.callback = callback.map(JsCast::unchecked_into);
+    }
+
+    // Call `callback` with a description of every frame's last fault.
+    #[wasm_bindgen(js_name = onFault)]
+    pub fn on_fault(&mut self, callback: Option<FaultCallback>) {
+        self.on_fault = callback.map(JsCast::unchecked_into);
+    }
+}
@@ -0,0 +1,354 @@
+use std::fmt;
+
+use crate::cpu::Chip8;
+use crate::registers::Register;
+
+/// # Watch Expressions
+///
+/// Small expressions over the machine state which debuggers re-evaluate after
+/// every step, like the watch windows of conventional debuggers.
+///
+/// - Registers - `V0` to `VF`, `I`, `PC`, `SP`, `DT` and `ST`
+/// - Numbers - decimal (`12`) or hexadecimal (`0x1F`)
+/// - Memory - `[addr]` reads the byte at an address, e.g. `[I+2]`
+/// - Operators - `* / %`, `+ -`, `&`, `^`, `|` and the comparisons
+///   `== != < <= > >=`, from highest to lowest precedence, with parentheses
+///   for grouping
+///
+/// Arithmetic wraps at 16 bits and comparisons evaluate to 1 or 0. An
+/// expression has no value when it divides by zero or reads memory outside
+/// of RAM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Number(u16),
+    Register(Register),
+    Memory(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Mul,
+    Div,
+    Rem,
+    Add,
+    Sub,
+    And,
+    Xor,
+    Or,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+        };
+        let expr = parser.binary(0)?;
+        match parser.tokens.get(parser.position) {
+            None => Ok(expr),
+            Some(token) => Err(ParseError::new(token.offset, "unexpected trailing input")),
+        }
+    }
+
+    pub fn eval(&self, chip8: &Chip8) -> Option<u16> {
+        match self {
+            Self::Number(n) => Some(*n),
+            Self::Register(r) => Some(chip8.register(*r)),
+            Self::Memory(addr) => chip8.peek(addr.eval(chip8)? as usize).map(u16::from),
+            Self::Binary(op, lhs, rhs) => op.apply(lhs.eval(chip8)?, rhs.eval(chip8)?),
+        }
+    }
+}
+
+impl Op {
+    // Binding strength, higher binds tighter.
+    fn precedence(self) -> u8 {
+        match self {
+            Self::Mul | Self::Div | Self::Rem => 6,
+            Self::Add | Self::Sub => 5,
+            Self::And => 4,
+            Self::Xor => 3,
+            Self::Or => 2,
+            Self::Eq | Self::Ne | Self::Lt | Self::Le | Self::Gt | Self::Ge => 1,
+        }
+    }
+
+    fn apply(self, a: u16, b: u16) -> Option<u16> {
+        Some(match self {
+            Self::Mul => a.wrapping_mul(b),
+            Self::Div => a.checked_div(b)?,
+            Self::Rem => a.checked_rem(b)?,
+            Self::Add => a.wrapping_add(b),
+            Self::Sub => a.wrapping_sub(b),
+            Self::And => a & b,
+            Self::Xor => a ^ b,
+            Self::Or => a | b,
+            Self::Eq => (a == b) as u16,
+            Self::Ne => (a != b) as u16,
+            Self::Lt => (a < b) as u16,
+            Self::Le => (a <= b) as u16,
+            Self::Gt => (a > b) as u16,
+            Self::Ge => (a >= b) as u16,
+        })
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Rem => "%",
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::And => "&",
+            Self::Xor => "^",
+            Self::Or => "|",
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+        }
+    }
+}
+
+/// A list of named expressions shown side by side, e.g. in a watch panel.
+#[derive(Debug, Clone, Default)]
+pub struct WatchList {
+    entries: Vec<(String, Expr)>,
+}
+
+impl WatchList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, source: &str) -> Result<(), ParseError> {
+        let expr = Expr::parse(source)?;
+        self.entries.push((source.trim().to_string(), expr));
+        Ok(())
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.entries.len() {
+            self.entries.remove(index);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Evaluate every expression against the current machine state.
+    pub fn evaluate(&self, chip8: &Chip8) -> Vec<(&str, Option<u16>)> {
+        self.entries
+            .iter()
+            .map(|(source, expr)| (source.as_str(), expr.eval(chip8)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TokenKind {
+    Number(u16),
+    Register(Register),
+    Op(Op),
+    Open,
+    Close,
+    OpenBracket,
+    CloseBracket,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    offset: usize,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ParseError> {
+    const OPERATORS: [Op; 14] = [
+        Op::Eq,
+        Op::Ne,
+        Op::Le,
+        Op::Ge,
+        Op::Lt,
+        Op::Gt,
+        Op::Mul,
+        Op::Div,
+        Op::Rem,
+        Op::Add,
+        Op::Sub,
+        Op::And,
+        Op::Xor,
+        Op::Or,
+    ];
+
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+    while offset < source.len() {
+        let rest = &source[offset..];
+        let c = rest.chars().next().unwrap_or_default();
+        if c.is_whitespace() {
+            offset += c.len_utf8();
+            continue;
+        }
+
+        let punctuation = match c {
+            '(' => Some(TokenKind::Open),
+            ')' => Some(TokenKind::Close),
+            '[' => Some(TokenKind::OpenBracket),
+            ']' => Some(TokenKind::CloseBracket),
+            _ => None,
+        };
+        if let Some(kind) = punctuation {
+            tokens.push(Token { kind, offset });
+            offset += 1;
+            continue;
+        }
+        if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(op.symbol())) {
+            tokens.push(Token {
+                kind: TokenKind::Op(*op),
+                offset,
+            });
+            offset += op.symbol().len();
+            continue;
+        }
+
+        let len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(ParseError::new(offset, "unexpected character"));
+        }
+        let word = &rest[..len];
+        let kind = if c.is_ascii_digit() {
+            let value = match word.strip_prefix("0x").or(word.strip_prefix("0X")) {
+                Some(hex) => u16::from_str_radix(hex, 16),
+                None => word.parse(),
+            };
+            TokenKind::Number(value.map_err(|_| ParseError::new(offset, "invalid number"))?)
+        } else {
+            TokenKind::Register(
+                word.parse()
+                    .map_err(|_| ParseError::new(offset, "unknown register"))?,
+            )
+        };
+        tokens.push(Token { kind, offset });
+        offset += len;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    // Precedence climbing over the binary operators.
+    fn binary(&mut self, min_precedence: u8) -> Result<Expr, ParseError> {
+        let mut lhs = self.atom()?;
+        while let Some(TokenKind::Op(op)) = self.peek() {
+            let op = *op;
+            if op.precedence() <= min_precedence {
+                break;
+            }
+            self.position += 1;
+            let rhs = self.binary(op.precedence())?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn atom(&mut self) -> Result<Expr, ParseError> {
+        let offset = self.offset();
+        let token = self
+            .next()
+            .ok_or_else(|| ParseError::new(offset, "expected a value"))?;
+        match token {
+            TokenKind::Number(n) => Ok(Expr::Number(n)),
+            TokenKind::Register(r) => Ok(Expr::Register(r)),
+            TokenKind::Open => {
+                let expr = self.binary(0)?;
+                self.expect(TokenKind::Close, "expected `)`")?;
+                Ok(expr)
+            }
+            TokenKind::OpenBracket => {
+                let expr = self.binary(0)?;
+                self.expect(TokenKind::CloseBracket, "expected `]`")?;
+                Ok(Expr::Memory(Box::new(expr)))
+            }
+            _ => Err(ParseError::new(offset, "expected a value")),
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind, message: &str) -> Result<(), ParseError> {
+        let offset = self.offset();
+        match self.next() {
+            Some(token) if token == kind => Ok(()),
+            _ => Err(ParseError::new(offset, message)),
+        }
+    }
+
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.position).map(|t| &t.kind)
+    }
+
+    fn next(&mut self) -> Option<TokenKind> {
+        let kind = self.peek().cloned();
+        self.position += 1;
+        kind
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or(self.tokens.last())
+            .map_or(0, |t| t.offset)
+    }
+}
+
+impl ParseError {
+    fn new(position: usize, message: &str) -> Self {
+        Self {
+            position,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "0x{:X}", n),
+            Self::Register(r) => write!(f, "{}", r),
+            Self::Memory(addr) => write!(f, "[{}]", addr),
+            Self::Binary(op, lhs, rhs) => write!(f, "({} {} {})", lhs, op.symbol(), rhs),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.position + 1)
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -0,0 +1,131 @@
+use js_sys::{Array, Float32Array, Object, Reflect};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    AudioContext, AudioContextOptions, AudioWorkletNode, AudioWorkletNodeOptions, Blob,
+    BlobPropertyBag, Url,
+};
+
+use crate::audio::{AudioConfig, Buzzer};
+
+/// # Web Audio
+///
+/// A `Buzzer` for browser builds, playing the samples rendered by the shared
+/// `Mixer` through an `AudioWorkletNode`. The worklet processor runs on the
+/// audio rendering thread and keeps its own queue of sample buffers posted
+/// from the emulation loop, bounded by the configured latency like
+/// `SampleQueue`, padding with silence when it runs dry.
+///
+/// Since the worklet only ever sees rendered samples, anything the mixer
+/// produces (the plain beep, XO-CHIP audio patterns) plays the same way as
+/// on desktop.
+///
+/// Browsers only allow audio to start after a user gesture, so frontends
+/// should call `resume` from an input handler.
+#[derive(Debug)]
+pub struct WebAudioBuzzer {
+    context: AudioContext,
+    node: AudioWorkletNode,
+}
+
+// Name the processor is registered under in the worklet scope.
+const PROCESSOR_NAME: &str = "chip8-buzzer";
+
+// Source of the worklet processor, loaded through a blob URL so that no
+// separate file has to be served alongside the WASM module.
+const PROCESSOR_SOURCE: &str = r#"
+class Chip8Buzzer extends AudioWorkletProcessor {
+  constructor(options) {
+    super();
+    this.capacity = options.processorOptions.capacity;
+    this.buffers = [];
+    this.offset = 0;
+    this.queued = 0;
+    this.port.onmessage = (event) => {
+      this.buffers.push(event.data);
+      this.queued += event.data.length;
+      while (this.queued - this.offset > this.capacity && this.buffers.length > 1) {
+        this.queued -= this.buffers.shift().length;
+        this.offset = 0;
+      }
+    };
+  }
+
+  process(inputs, outputs) {
+    const out = outputs[0][0];
+    let written = 0;
+    while (written < out.length && this.buffers.length > 0) {
+      const buffer = this.buffers[0];
+      const count = Math.min(out.length - written, buffer.length - this.offset);
+      out.set(buffer.subarray(this.offset, this.offset + count), written);
+      written += count;
+      this.offset += count;
+      if (this.offset === buffer.length) {
+        this.buffers.shift();
+        this.queued -= buffer.length;
+        this.offset = 0;
+      }
+    }
+    out.fill(0, written);
+    return true;
+  }
+}
+
+registerProcessor("chip8-buzzer", Chip8Buzzer);
+"#;
+
+impl WebAudioBuzzer {
+    // Create the audio context and load the worklet processor.
+    pub async fn new(config: &AudioConfig) -> Result<WebAudioBuzzer, JsValue> {
+        let options = AudioContextOptions::new();
+        options.set_sample_rate(config.sample_rate as f32);
+        options.set_latency_hint_f64(config.latency_ms as f64 / 1000.0);
+        let context = AudioContext::new_with_context_options(&options)?;
+
+        let blob_options = BlobPropertyBag::new();
+        blob_options.set_type("application/javascript");
+        let blob = Blob::new_with_str_sequence_and_options(
+            &Array::of1(&JsValue::from_str(PROCESSOR_SOURCE)),
+            &blob_options,
+        )?;
+        let url = Url::create_object_url_with_blob(&blob)?;
+        let loaded = JsFuture::from(context.audio_worklet()?.add_module(&url)?).await;
+        Url::revoke_object_url(&url)?;
+        loaded?;
+
+        let processor_options = Object::new();
+        Reflect::set(
+            &processor_options,
+            &JsValue::from_str("capacity"),
+            &JsValue::from(config.latency_samples() as u32),
+        )?;
+        let node_options = AudioWorkletNodeOptions::new();
+        node_options.set_number_of_inputs(0);
+        node_options.set_output_channel_count(&Array::of1(&JsValue::from(1)));
+        node_options.set_processor_options(Some(&processor_options));
+        let node = AudioWorkletNode::new_with_options(&context, PROCESSOR_NAME, &node_options)?;
+        node.connect_with_audio_node(&context.destination())?;
+
+        Ok(WebAudioBuzzer { context, node })
+    }
+
+    // Start or continue playback, must be called from a user gesture.
+    pub async fn resume(&self) -> Result<(), JsValue> {
+        JsFuture::from(self.context.resume()?).await?;
+        Ok(())
+    }
+}
+
+impl Buzzer for WebAudioBuzzer {
+    fn name(&self) -> &'static str {
+        "web-audio"
+    }
+
+    fn write(&mut self, samples: &[f32]) {
+        // A closed context or detached port only means the page is going
+        // away, there is nothing sensible to do about it here.
+        if let Ok(port) = self.node.port() {
+            let _ = port.post_message(&Float32Array::from(samples));
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::{env, fs};
+
+use chip_8_rs::attract::{AttractAction, AttractMode};
+use chip_8_rs::movie::Movie;
+use chip_8_rs::recording::InputRecording;
+use chip_8_rs::{Chip8, Register};
+
+// Keys 1, 2 and 3 held in turn.
+fn demo() -> InputRecording {
+    InputRecording {
+        frames: vec![1 << 1, 1 << 2, 1 << 3],
+    }
+}
+
+// Run `frames` frames without user input, returning the last action.
+fn idle(attract: &mut AttractMode, frames: u64) -> AttractAction {
+    (0..frames)
+        .map(|_| attract.update(false))
+        .last()
+        .unwrap_or(AttractAction::Idle)
+}
+
+#[test]
+fn the_demo_starts_after_the_idle_timeout() {
+    let mut attract = AttractMode::new(demo(), 2);
+    assert_eq!(idle(&mut attract, 119), AttractAction::Idle);
+    assert!(!attract.is_playing());
+    assert_eq!(attract.update(false), AttractAction::Restart(1 << 1));
+    assert!(attract.is_playing());
+    assert_eq!(attract.update(false), AttractAction::Play(1 << 2));
+    assert_eq!(attract.update(false), AttractAction::Play(1 << 3));
+
+    // The recording loops, from a reset machine.
+    assert_eq!(attract.update(false), AttractAction::Restart(1 << 1));
+    assert_eq!(attract.update(false), AttractAction::Play(1 << 2));
+}
+
+#[test]
+fn input_restarts_the_idle_timeout() {
+    let mut attract = AttractMode::new(demo(), 1);
+    assert_eq!(idle(&mut attract, 59), AttractAction::Idle);
+    assert_eq!(attract.update(true), AttractAction::Idle);
+    assert_eq!(idle(&mut attract, 59), AttractAction::Idle);
+    assert!(!attract.is_playing());
+    assert_eq!(attract.update(false), AttractAction::Restart(1 << 1));
+}
+
+#[test]
+fn input_stops_the_demo() {
+    let mut attract = AttractMode::new(demo(), 1);
+    assert_eq!(idle(&mut attract, 61), AttractAction::Play(1 << 2));
+    assert_eq!(attract.update(true), AttractAction::Stop);
+    assert!(!attract.is_playing());
+
+    // The user is in control again, until the next timeout.
+    assert_eq!(attract.update(true), AttractAction::Idle);
+    assert_eq!(idle(&mut attract, 59), AttractAction::Idle);
+    assert_eq!(attract.update(false), AttractAction::Restart(1 << 1));
+}
+
+#[test]
+fn empty_recordings_never_play() {
+    let mut attract = AttractMode::new(InputRecording::new(), 1);
+    assert_eq!(idle(&mut attract, 600), AttractAction::Idle);
+    assert!(!attract.is_playing());
+}
+
+#[test]
+fn demos_are_attached_next_to_the_rom() {
+    let dir = env::temp_dir().join(format!("chip8-attract-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let rom = dir.join("pong.ch8");
+    assert!(AttractMode::attached(&rom, 1).is_none());
+
+    fs::write(dir.join("pong.demo"), demo().to_string()).unwrap();
+    let mut attract = AttractMode::attached(&rom, 1).unwrap();
+    assert_eq!(idle(&mut attract, 60), AttractAction::Restart(1 << 1));
+
+    // Unreadable recordings are ignored.
+    fs::write(dir.join("pong.demo"), "not a recording").unwrap();
+    assert!(AttractMode::attached(&rom, 1).is_none());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn movie_demos_restart_the_machine_from_their_seed() {
+    // RND V0, 0xFF; ADD V1, 1; JP 0x202
+    let rom = [0xC0, 0xFF, 0x71, 0x01, 0x12, 0x02];
+    let mut movie = Movie::new(&rom, 7, 2);
+    movie.recording = demo();
+    let mut attract = AttractMode::from_movie(movie, 1);
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&rom).unwrap();
+    for _ in 0..59 {
+        assert_eq!(attract.before_frame(&mut chip8, false), AttractAction::Idle);
+        chip8.run_frame(2);
+    }
+    assert_eq!(chip8.register(Register::V(1)), 59);
+
+    assert_eq!(
+        attract.before_frame(&mut chip8, false),
+        AttractAction::Restart(1 << 1)
+    );
+    assert_eq!(chip8.register(Register::V(1)), 0);
+    assert!(chip8.keys()[1]);
+    chip8.run_frame(2);
+    let mut seeded = Chip8::new();
+    seeded.set_seed(7);
+    seeded.load_rom(&rom).unwrap();
+    seeded.run_frame(2);
+    assert_eq!(
+        chip8.register(Register::V(0)),
+        seeded.register(Register::V(0))
+    );
+
+    assert_eq!(
+        attract.before_frame(&mut chip8, false),
+        AttractAction::Play(1 << 2)
+    );
+    assert_eq!(chip8.keys(), {
+        let mut keys = [false; 16];
+        keys[2] = true;
+        keys
+    });
+
+    // A key pressed hands a reset machine back, with the demo's keys up.
+    assert_eq!(attract.before_frame(&mut chip8, true), AttractAction::Stop);
+    assert_eq!(chip8.register(Register::PC), 0x200);
+    assert_eq!(chip8.keys(), [false; 16]);
+}
@@ -0,0 +1,45 @@
+use chip_8_rs::audio::{AudioConfig, Mixer, Waveform};
+
+// The first 60Hz frame of a 1kHz beep at full volume, without an attack.
+fn frame(waveform: Waveform) -> Vec<f32> {
+    let config = AudioConfig {
+        sample_rate: 48000,
+        pitch: 1000.0,
+        volume: 1.0,
+        attack_ms: 0,
+        waveform,
+        ..AudioConfig::default()
+    };
+    let mut mixer = Mixer::new(config);
+    let mut samples = Vec::new();
+    mixer.render_frame(true, &mut samples);
+    samples
+}
+
+#[test]
+fn waveforms() {
+    // 48 samples per period, starting at phase 0.
+    let close = |a: f32, b: f32| (a - b).abs() < 1e-2;
+    let square = frame(Waveform::Square);
+    assert_eq!(square.len(), 800);
+    assert!(square[1..23].iter().all(|&s| s == 1.0));
+    assert!(square[25..47].iter().all(|&s| s == -1.0));
+
+    let sine = frame(Waveform::Sine);
+    assert!(close(sine[0], 0.0));
+    assert!(close(sine[12], 1.0));
+    assert!(close(sine[36], -1.0));
+
+    let triangle = frame(Waveform::Triangle);
+    assert!(close(triangle[0], -1.0));
+    assert!(close(triangle[12], 0.0));
+    assert!(close(triangle[24], 1.0));
+}
+
+#[test]
+fn parse_waveforms() {
+    assert_eq!("sine".parse(), Ok(Waveform::Sine));
+    assert_eq!("triangle".parse(), Ok(Waveform::Triangle));
+    assert!("sawtooth".parse::<Waveform>().is_err());
+    assert_eq!(AudioConfig::default().waveform, Waveform::Square);
+}
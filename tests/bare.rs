@@ -0,0 +1,300 @@
+use std::time::Duration;
+
+use proptest::prelude::*;
+
+use chip_8_rs::bare::Machine;
+use chip_8_rs::cartridge::Variant;
+use chip_8_rs::random::{FixedRandom, SplitMix64};
+use chip_8_rs::{Chip8, Chip8Error, EmulationMode, Quirks, Register, StepResult};
+
+fn run(rom: &[u8], frames: usize) -> Machine<SplitMix64> {
+    let mut machine = Machine::new(SplitMix64::new(0));
+    machine.load_rom(rom).unwrap();
+    for _ in 0..frames {
+        machine.run_frame(10).unwrap();
+    }
+    machine
+}
+
+#[test]
+fn digits_are_drawn_from_the_font() {
+    // LD V0, 5; LD F, V0; DRW V1, V1, 5; EXIT
+    let machine = run(&[0x60, 0x05, 0xF0, 0x29, 0xD1, 0x15, 0x00, 0xFD], 1);
+    assert!(machine.has_exited());
+    let rows: Vec<u8> = machine.screen().rows()[..6]
+        .iter()
+        .map(|&row| (row >> 120) as u8)
+        .collect();
+    assert_eq!(rows, [0xF0, 0x80, 0xF0, 0x10, 0xF0, 0x00]);
+    assert!(machine.screen().pixel(0, 1));
+    assert!(!machine.screen().pixel(1, 1));
+    assert_eq!(machine.screen().rows().len(), 32);
+}
+
+#[test]
+fn subroutines_return() {
+    // CALL 0x206; LD V1, 1; EXIT; LD V0, 7; RET
+    let machine = run(
+        &[0x22, 0x06, 0x61, 0x01, 0x00, 0xFD, 0x60, 0x07, 0x00, 0xEE],
+        1,
+    );
+    assert!(machine.has_exited());
+    assert_eq!(machine.v_registers()[..2], [7, 1]);
+}
+
+#[test]
+fn faults_halt_the_machine() {
+    let mut machine = Machine::new(SplitMix64::new(0));
+    machine.load_rom(&[0x00, 0xEE, 0x60, 0x01]).unwrap();
+    let fault = machine.step().unwrap_err();
+    assert_eq!(fault.kind, Chip8Error::StackUnderflow);
+    assert_eq!(fault.program_counter, 0x202);
+    assert_eq!(machine.run_frame(10), Err(fault));
+    assert_eq!(machine.v_registers()[0], 0);
+
+    // XO-CHIP instructions do not exist on this machine.
+    let mut machine = Machine::new(SplitMix64::new(0));
+    machine.load_rom(&[0xF0, 0x00, 0x12, 0x34]).unwrap();
+    assert_eq!(
+        machine.step().unwrap_err().kind,
+        Chip8Error::InvalidOpcode(0xF000)
+    );
+
+    // LD V0, 0; LD [I], V0 with I = 0 writes to the interpreter area.
+    let mut machine = Machine::new(SplitMix64::new(0));
+    machine.load_rom(&[0x60, 0x00, 0xF0, 0x55]).unwrap();
+    assert_eq!(
+        machine.run_frame(2).unwrap_err().kind,
+        Chip8Error::WriteProtected(0)
+    );
+    assert!(Machine::new(SplitMix64::new(0))
+        .load_rom(&[0; 0xE01])
+        .is_err());
+}
+
+#[test]
+fn keys_are_taken_once_released() {
+    // LD V3, K; EXIT
+    let mut machine = run(&[0xF3, 0x0A, 0x00, 0xFD], 1);
+    assert!(machine.is_waiting_for_key());
+    machine.set_key(0x7, true);
+    machine.run_frame(10).unwrap();
+    assert!(machine.is_waiting_for_key());
+    machine.set_key(0x7, false);
+    machine.run_frame(10).unwrap();
+    assert!(!machine.is_waiting_for_key());
+    assert!(machine.has_exited());
+    assert_eq!(machine.v_registers()[3], 0x7);
+}
+
+#[test]
+fn sprites_wrap_and_scroll_in_high_resolution() {
+    // HIGH; LD V0, 124; LD I, 0x20C; DRW V0, V1, 1; SCR; EXIT; 0xFF
+    let rom = [
+        0x00, 0xFF, 0x60, 124, 0xA2, 0x0C, 0xD0, 0x11, 0x00, 0xFB, 0x00, 0xFD, 0xFF,
+    ];
+    let machine = run(&rom, 1);
+    let screen = machine.screen();
+    assert!(screen.is_hires());
+    assert_eq!((screen.width(), screen.height()), (128, 64));
+    // Pixels 124 to 127 and 0 to 3, moved 4 to the right: the ones at the
+    // right edge are scrolled out.
+    assert_eq!(screen.rows()[0], 0x0F << 120);
+}
+
+#[test]
+fn timers_run_on_host_ticks() {
+    // LD V0, 10; LD DT, V0; LD ST, V0; then loop
+    let mut machine = run(&[0x60, 0x0A, 0xF0, 0x15, 0xF0, 0x18, 0x12, 0x06], 1);
+    assert_eq!(machine.timers().delay, 9);
+    assert!(machine.is_beeping());
+    assert_eq!(machine.advance_timers(Duration::from_millis(50)), 3);
+    machine.tick_timers();
+    assert_eq!(machine.timers().delay, 5);
+}
+
+#[test]
+fn random_bytes_come_from_the_host() {
+    // RND V0, 0x0F; RND V1, 0xFF
+    let mut machine = Machine::new(FixedRandom::new(&[0xAB, 0xCD]));
+    machine.load_rom(&[0xC0, 0x0F, 0xC1, 0xFF]).unwrap();
+    machine.run_frame(2).unwrap();
+    assert_eq!(machine.v_registers()[..2], [0x0B, 0xCD]);
+}
+
+#[test]
+fn quirks_apply() {
+    // LD V1, 0x81; SHR V0, V1; LD V2, 1; OR V2, V2
+    let rom = [0x61, 0x81, 0x80, 0x16, 0x62, 0x01, 0x82, 0x21];
+    let mut machine = Machine::new(SplitMix64::new(0));
+    machine.set_quirks(Quirks::CHIP8);
+    machine.load_rom(&rom).unwrap();
+    machine.run_frame(4).unwrap();
+    assert_eq!(machine.v_registers()[0], 0x40);
+    assert_eq!(machine.v_registers()[0xF], 0);
+
+    let mut machine = Machine::new(SplitMix64::new(0));
+    machine.set_quirks(Quirks::SCHIP);
+    machine.load_rom(&rom).unwrap();
+    machine.run_frame(4).unwrap();
+    assert_eq!(machine.v_registers()[0], 0);
+    assert_eq!(machine.v_registers()[0xF], 0);
+    assert_eq!(machine.quirks(), Quirks::SCHIP);
+}
+
+// The machine `Machine` is checked against: SUPER-CHIP, so that the same
+// instructions exist on both, halting on every fault as `Machine` does.
+fn reference(rom: &[u8], quirks: Quirks, seed: u64) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.set_variant(Variant::SuperChip);
+    chip8.set_quirks(quirks);
+    chip8.set_mode(EmulationMode::Strict);
+    chip8.set_seed(seed);
+    chip8.load_rom(rom).unwrap();
+    chip8
+}
+
+// Step both machines `steps` times, checking after every instruction that
+// they agree on the registers, memory, screen and faults.
+fn run_both(rom: &[u8], quirks: Quirks, seed: u64, steps: usize) -> Result<(), TestCaseError> {
+    let mut chip8 = reference(rom, quirks, seed);
+    let mut machine = Machine::new(SplitMix64::new(seed));
+    machine.set_quirks(quirks);
+    machine.load_rom(rom).unwrap();
+    for step in 0..steps {
+        let fault = match chip8.step() {
+            StepResult::Error(fault) => Some(fault),
+            _ => None,
+        };
+        prop_assert_eq!(machine.step().err(), fault, "step {}", step);
+        for x in 0..16 {
+            prop_assert_eq!(
+                machine.v_registers()[x] as u16,
+                chip8.register(Register::V(x as u8)),
+                "V{:X} at step {}",
+                x,
+                step
+            );
+        }
+        prop_assert_eq!(machine.i_register(), chip8.register(Register::I));
+        prop_assert_eq!(machine.program_counter(), chip8.register(Register::PC));
+        prop_assert_eq!(machine.timers().delay, chip8.timers().delay);
+        prop_assert_eq!(machine.timers().sound, chip8.timers().sound);
+        prop_assert!(
+            machine.memory() == chip8.memory(),
+            "memory at step {}",
+            step
+        );
+        let screen = machine.screen();
+        prop_assert_eq!(screen.width(), chip8.display().width());
+        prop_assert_eq!(screen.height(), chip8.display().height());
+        for y in 0..screen.height() {
+            for x in 0..screen.width() {
+                prop_assert_eq!(
+                    screen.pixel(x, y),
+                    chip8.display().pixel(x, y),
+                    "pixel ({}, {}) at step {}",
+                    x,
+                    y,
+                    step
+                );
+            }
+        }
+        if fault.is_some() || machine.has_exited() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn quirks() -> impl Strategy<Value = Quirks> {
+    any::<[bool; 4]>().prop_map(|[vf_reset, memory, shifting, jumping]| Quirks {
+        vf_reset,
+        memory,
+        shifting,
+        jumping,
+    })
+}
+
+// Opcodes which do something more often than not: random high nibbles and
+// low bytes, with plausible addresses for the instructions which take one.
+fn opcode() -> impl Strategy<Value = u16> {
+    prop_oneof![
+        any::<u16>(),
+        (0u16..16, 0u16..0x100).prop_map(|(high, low)| (high << 12) | 0x200 | low),
+        (0u16..16, 0u16..16, 0u16..16, 0u16..16)
+            .prop_map(|(a, b, c, d)| (a << 12) | (b << 8) | (c << 4) | d),
+    ]
+}
+
+#[test]
+fn programs_run_as_on_chip8() {
+    // Every quirk combination on a program exercising them all: logic,
+    // shifts, memory and BB0 jumps.
+    // LD V0, 0xF3; LD V1, 0x81; OR V0, V1; SHR V2, V1; SHL V3, V0;
+    // LD I, 0x300; LD [I], V3; LD V4, [I]; LD V2, 2; LD V0, 2;
+    // JP V0, 0x216; RET; DRW V0, V1, 0; EXIT
+    let rom = [
+        0x60, 0xF3, 0x61, 0x81, 0x80, 0x11, 0x82, 0x16, 0x83, 0x0E, 0xA3, 0x00, 0xF3, 0x55, 0xF4,
+        0x65, 0x62, 0x02, 0x60, 0x02, 0xB2, 0x16, 0x00, 0xEE, 0xD0, 0x10, 0x00, 0xFD,
+    ];
+    for index in 0..16u8 {
+        let [vf_reset, memory, shifting, jumping] = [0, 1, 2, 3].map(|bit| index >> bit & 1 == 1);
+        let quirks = Quirks {
+            vf_reset,
+            memory,
+            shifting,
+            jumping,
+        };
+        run_both(&rom, quirks, 0, 64).unwrap();
+        let mut machine = Machine::new(SplitMix64::new(0));
+        machine.set_quirks(quirks);
+        machine.load_rom(&rom).unwrap();
+        machine.run_frame(64).unwrap();
+        assert!(machine.has_exited());
+    }
+}
+
+#[test]
+fn edge_cases_run_as_on_chip8() {
+    // LD VF, 0xDB; JP VF, 0xF25 with the jumping quirk: the target wraps
+    // past the end of memory.
+    let jumping = Quirks {
+        jumping: true,
+        ..Quirks::SCHIP
+    };
+    run_both(&[0x6F, 0xDB, 0xBF, 0x25], jumping, 0, 8).unwrap();
+    // LD B, V0 and LD [I], V1 with I = 0: both stop at the first protected
+    // byte, leaving I as it was.
+    for quirks in [Quirks::CHIP8, Quirks::SCHIP] {
+        run_both(&[0xF0, 0x33], quirks, 0, 2).unwrap();
+        run_both(&[0xF1, 0x55], quirks, 0, 2).unwrap();
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        failure_persistence: None,
+        ..ProptestConfig::default()
+    })]
+
+    // Random programs leave both machines in the same state, fault for
+    // fault: the bare core is a second implementation of the instructions,
+    // which must not drift from the first.
+    #[test]
+    fn random_programs_run_as_on_chip8(
+        v in any::<[u8; 16]>(),
+        program in prop::collection::vec(opcode(), 1..64),
+        quirks in quirks(),
+        seed in any::<u64>(),
+    ) {
+        // LD Vx, kk for every register first, so that arithmetic carries
+        // and borrows as often as not.
+        let setup = v.iter().enumerate().map(|(x, &kk)| 0x6000 | (x as u16) << 8 | kk as u16);
+        let rom: Vec<u8> = setup
+            .chain(program)
+            .flat_map(|op| op.to_be_bytes())
+            .collect();
+        run_both(&rom, quirks, seed, 256)?;
+    }
+}
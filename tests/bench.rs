@@ -0,0 +1,38 @@
+use chip_8_rs::bench::{self, CYCLES_PER_FRAME, WORKLOADS};
+use chip_8_rs::cached::CachedInterpreter;
+use chip_8_rs::Chip8;
+
+#[test]
+fn workloads_run_without_faults() {
+    for workload in WORKLOADS {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&workload.rom()).unwrap();
+        chip8.run_frame(CYCLES_PER_FRAME);
+        assert_eq!(chip8.fault(), None, "{}", workload.name);
+        assert_eq!(
+            chip8.telemetry().instructions,
+            CYCLES_PER_FRAME as u64,
+            "{}",
+            workload.name
+        );
+    }
+}
+
+#[test]
+fn reports_list_every_workload_under_every_backend() {
+    let report = bench::run(&["interpreter", "cached"], 2, |name| match name {
+        "cached" => Chip8::with_backend(Box::new(CachedInterpreter::new())),
+        _ => Chip8::new(),
+    });
+    assert_eq!(report.measurements.len(), 2 * WORKLOADS.len());
+    for measurement in &report.measurements {
+        assert_eq!(measurement.instructions, 2 * CYCLES_PER_FRAME as u64);
+    }
+    assert_eq!(report.measurements[1].backend, "cached");
+
+    let table = report.to_string();
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 1 + WORKLOADS.len());
+    assert!(lines[0].contains("interpreter") && lines[0].contains("cached"));
+    assert!(lines[1].starts_with("draw") && lines[1].ends_with("MIPS"));
+}
@@ -0,0 +1,63 @@
+use chip_8_rs::blocks::BlockTranslator;
+use chip_8_rs::cartridge::Variant;
+use chip_8_rs::{Chip8, Register};
+
+fn translated(rom: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::with_backend(Box::new(BlockTranslator::new()));
+    chip8.load_rom(rom).unwrap();
+    chip8
+}
+
+#[test]
+fn hot_loops_run_as_blocks() {
+    // ADD V0, 1; ADD V1, 2; JP 0x200
+    let mut chip8 = translated(&[0x70, 0x01, 0x71, 0x02, 0x12, 0x00]);
+    chip8.run_frame(300);
+    assert_eq!(chip8.register(Register::V(0)), 100);
+    assert_eq!(chip8.register(Register::V(1)), 200);
+    assert_eq!(chip8.telemetry().instructions, 300);
+    assert_eq!(chip8.register(Register::PC), 0x200);
+}
+
+#[test]
+fn written_blocks_are_translated_again() {
+    // ADD V0, 1; JP 0x200
+    let mut chip8 = translated(&[0x70, 0x01, 0x12, 0x00]);
+    chip8.run_frame(100);
+    assert_eq!(chip8.register(Register::V(0)), 50);
+
+    // ADD V0, 3
+    chip8.poke(0x201, 0x03);
+    chip8.run_frame(100);
+    assert_eq!(chip8.register(Register::V(0)), 200);
+
+    // A loop writing ADD V2, 0 over its own first instruction:
+    // ADD V2, 1; LD V0, 0; LD I, 0x201; LD [I], V0; JP 0x200
+    let mut chip8 = translated(&[0x72, 0x01, 0x60, 0x00, 0xA2, 0x01, 0xF0, 0x55, 0x12, 0x00]);
+    chip8.set_write_protect(false);
+    chip8.run_frame(500);
+    assert_eq!(chip8.register(Register::V(2)), 1);
+}
+
+#[test]
+fn blocks_may_end_at_the_end_of_memory() {
+    let mut chip8 = Chip8::with_backend(Box::new(BlockTranslator::new()));
+    chip8.set_variant(Variant::XoChip);
+    chip8.load_rom(&[]).unwrap();
+    // LD V0, 0x60; LD V1, 1; LD V2, 2; LD [I], V0 at 0xFFF8, writing the
+    // block's first byte over again.
+    for (offset, byte) in [0x60, 0x60, 0x61, 0x01, 0x62, 0x02, 0xF0, 0x55]
+        .into_iter()
+        .enumerate()
+    {
+        chip8.poke(0xFFF8 + offset, byte);
+    }
+    chip8.set_register(Register::I, 0xFFF8).unwrap();
+    for _ in 0..40 {
+        chip8.set_register(Register::PC, 0xFFF8).unwrap();
+        chip8.run_frame(4);
+    }
+    assert_eq!(chip8.register(Register::V(2)), 2);
+    assert_eq!(chip8.peek(0xFFF8), Some(0x60));
+    assert_eq!(chip8.fault(), None);
+}
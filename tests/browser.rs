@@ -0,0 +1,101 @@
+use std::path::PathBuf;
+use std::{env, fs};
+
+use chip_8_rs::browser::{is_rom, BrowserAction, RomBrowser};
+
+// A directory with three ROMs, a text file and a subdirectory.
+fn rom_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("chip8-browser-{}-{}", name, std::process::id()));
+    fs::create_dir_all(dir.join("more.ch8")).unwrap();
+    for file in ["pong.ch8", "Breakout.c8x", "blitz.CH8", "notes.txt"] {
+        fs::write(dir.join(file), [0x00, 0xE0]).unwrap();
+    }
+    dir
+}
+
+fn names(browser: &RomBrowser) -> Vec<String> {
+    browser
+        .roms()
+        .iter()
+        .map(|rom| rom.file_name().unwrap().to_string_lossy().into_owned())
+        .collect()
+}
+
+#[test]
+fn roms_are_listed_by_name() {
+    let dir = rom_dir("list");
+    let mut browser = RomBrowser::new(&dir, 2);
+    assert!(!browser.is_shown());
+    assert!(browser.roms().is_empty());
+    assert_eq!(browser.handle("F4"), BrowserAction::Handled);
+    assert!(browser.is_shown());
+    assert_eq!(names(&browser), ["blitz.CH8", "Breakout.c8x", "pong.ch8"]);
+    assert!(is_rom(&dir.join("game.XO8")));
+    assert!(!is_rom(&dir.join("notes.txt")));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn keys_move_the_selection() {
+    let dir = rom_dir("keys");
+    let mut browser = RomBrowser::new(&dir, 2);
+    // Keys only reach the browser while it is shown.
+    assert_eq!(browser.handle("Down"), BrowserAction::Ignored);
+    browser.toggle();
+    assert_eq!(browser.handle("Down"), BrowserAction::Handled);
+    assert_eq!(browser.selected(), Some(dir.join("Breakout.c8x").as_path()));
+    browser.handle("End");
+    browser.handle("Down");
+    assert_eq!(browser.selected(), Some(dir.join("pong.ch8").as_path()));
+    browser.handle("PageUp");
+    assert_eq!(browser.selected(), Some(dir.join("blitz.CH8").as_path()));
+
+    // A letter jumps to the next ROM starting with it, wrapping around.
+    browser.handle("B");
+    assert_eq!(browser.selected(), Some(dir.join("Breakout.c8x").as_path()));
+    browser.handle("B");
+    assert_eq!(browser.selected(), Some(dir.join("blitz.CH8").as_path()));
+    assert_eq!(browser.handle("Space"), BrowserAction::Ignored);
+
+    browser.select(&dir.join("pong.ch8"));
+    assert_eq!(
+        browser.handle("Return"),
+        BrowserAction::Load(dir.join("pong.ch8"))
+    );
+    assert!(!browser.is_shown());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn pages_hold_the_selection() {
+    let dir = rom_dir("render");
+    let mut browser = RomBrowser::new(&dir, 2);
+    browser.toggle();
+    browser.handle("End");
+    let lines = browser.render();
+    assert_eq!(lines[0], format!("{} (3/3)", dir.display()));
+    assert_eq!(lines[1..], ["> pong.ch8"]);
+    browser.handle("Up");
+    assert_eq!(browser.render()[1..], ["  blitz.CH8", "> Breakout.c8x"]);
+
+    // The selection stays on its ROM when the directory is read again.
+    fs::write(dir.join("alien.ch8"), [0x00, 0xE0]).unwrap();
+    browser.toggle();
+    browser.toggle();
+    assert_eq!(browser.selected(), Some(dir.join("Breakout.c8x").as_path()));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn missing_and_empty_directories_are_reported() {
+    let dir = env::temp_dir().join(format!("chip8-browser-empty-{}", std::process::id()));
+    let mut browser = RomBrowser::new(&dir, 8);
+    browser.toggle();
+    assert!(browser.render()[0].starts_with("Cannot read"));
+    assert_eq!(browser.handle("Return"), BrowserAction::Handled);
+
+    fs::create_dir_all(&dir).unwrap();
+    browser.refresh();
+    assert_eq!(browser.render(), [format!("No ROMs in {}", dir.display())]);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,143 @@
+use chip_8_rs::c8b;
+use chip_8_rs::cartridge::{Cartridge, CartridgeError, TimerRate, Variant};
+
+const CHIP8: u8 = 0x01;
+const SUPER_CHIP: u8 = 0x02;
+const XO_CHIP: u8 = 0x03;
+
+// Encode a `.c8b` binary with a code segment per `(platform, code)` and a
+// properties table if there are any, which comes before the code.
+fn c8b(segments: &[(u8, &[u8])], properties: &[(u8, &[u8])]) -> Vec<u8> {
+    let table = 6 + segments.len() * 5 + 1;
+    let mut props = Vec::new();
+    for (key, data) in properties {
+        props.push(*key);
+        props.push(data.len() as u8);
+        props.extend(*data);
+    }
+    if !properties.is_empty() {
+        props.push(0xFF);
+    }
+
+    let mut bytes = b"CBF\0".to_vec();
+    let offset = if properties.is_empty() { 0 } else { table };
+    bytes.extend((offset as u16).to_be_bytes());
+    let mut code = table + props.len();
+    for (platform, data) in segments {
+        bytes.push(*platform);
+        bytes.extend((code as u16).to_be_bytes());
+        bytes.extend((data.len() as u16).to_be_bytes());
+        code += data.len();
+    }
+    bytes.push(0);
+    bytes.extend(props);
+    for (_, data) in segments {
+        bytes.extend(*data);
+    }
+    bytes
+}
+
+fn pong() -> Vec<u8> {
+    c8b(
+        &[
+            (CHIP8, &[0x12, 0x00]),
+            (SUPER_CHIP, &[0x00, 0xFF, 0x12, 0x02]),
+        ],
+        &[
+            (0x00, b"Ann"),
+            (0x01, b"Bats and a ball"),
+            (0x02, b"Pong"),
+            (0x04, &[0x00, 0x14]),
+            (0x05, &[0x10, 0x20, 0x30, 0xF0, 0xE0, 0xD0]),
+            // Unknown
+            (0x40, &[1, 2, 3]),
+        ],
+    )
+}
+
+#[test]
+fn binaries_load_as_cartridges() {
+    let bytes = pong();
+    assert!(c8b::is_c8b(&bytes));
+    let cartridge = Cartridge::load(&bytes).unwrap();
+    assert_eq!(
+        cartridge,
+        Cartridge {
+            variant: Variant::SuperChip,
+            tickrate: Some(20),
+            palette: Some([[0x10, 0x20, 0x30], [0xF0, 0xE0, 0xD0]]),
+            timer_rate: TimerRate::Ntsc,
+            title: "Pong".to_string(),
+            author: "Ann".to_string(),
+            rom: vec![0x00, 0xFF, 0x12, 0x02],
+        }
+    );
+
+    // The settings survive saving the cartridge as a `.c8x`.
+    assert_eq!(Cartridge::load(&cartridge.to_bytes()).unwrap(), cartridge);
+}
+
+#[test]
+fn the_most_capable_supported_segment_is_chosen() {
+    let bytes = c8b(
+        &[
+            (CHIP8, &[1]),
+            (XO_CHIP, &[3]),
+            (SUPER_CHIP, &[2]),
+            (0x04, &[4]),
+        ],
+        &[],
+    );
+    let cartridge = c8b::parse(&bytes).unwrap();
+    assert_eq!(
+        (cartridge.variant, cartridge.rom),
+        (Variant::XoChip, vec![3])
+    );
+
+    // Without properties, nothing is set but the code.
+    let cartridge = c8b::parse(&c8b(&[(CHIP8, &[1])], &[])).unwrap();
+    assert_eq!(cartridge, Cartridge::from_rom(&[1]));
+
+    // Unknown platforms only
+    assert_eq!(
+        c8b::parse(&c8b(&[(0x04, &[4])], &[])),
+        Err(CartridgeError::NoSupportedSegment)
+    );
+}
+
+#[test]
+fn truncated_binaries_are_rejected() {
+    let bytes = pong();
+    for len in 3..bytes.len() {
+        assert_eq!(
+            c8b::parse(&bytes[..len]),
+            Err(CartridgeError::Truncated),
+            "cut to {} bytes",
+            len
+        );
+    }
+
+    // A segment reaching past the end
+    let mut bytes = c8b(&[(CHIP8, &[1, 2])], &[]);
+    bytes.pop();
+    assert_eq!(c8b::parse(&bytes), Err(CartridgeError::Truncated));
+}
+
+#[test]
+fn bad_headers_are_rejected() {
+    let mut bytes = pong();
+    bytes[2] = b'X';
+    assert_eq!(c8b::parse(&bytes), Err(CartridgeError::BadMagic));
+    // Which makes it a plain ROM to the loader.
+    assert_eq!(Cartridge::load(&bytes).unwrap().rom, bytes);
+
+    let mut bytes = pong();
+    bytes[3] = 1;
+    assert_eq!(
+        c8b::parse(&bytes),
+        Err(CartridgeError::UnsupportedVersion(1))
+    );
+
+    let bytes = c8b(&[(CHIP8, &[1])], &[(0x02, &[0xFF, 0xFE])]);
+    assert_eq!(c8b::parse(&bytes), Err(CartridgeError::InvalidText));
+}
@@ -0,0 +1,89 @@
+use chip_8_rs::backend::{ExecutionBackend, Interpreter};
+use chip_8_rs::cached::CachedInterpreter;
+use chip_8_rs::cartridge::Variant;
+use chip_8_rs::{Chip8, Register};
+
+// Runs the instruction at 0x214 (LD V2, 1), overwrites it with LD V2, 7
+// and runs it again:
+//
+// 0x200  LD I, 0x214; LD V0, 0x62; LD V1, 0x07
+// 0x206  ADD V3, 1; JP 0x214
+// 0x20A  SE V3, 2; LD [I], V1      (skipped on the second pass)
+// 0x20E  SE V3, 2; JP 0x206; JP 0x212
+// 0x214  LD V2, 1; JP 0x20A
+const SELF_MODIFYING: &[u8] = &[
+    0xA2, 0x14, 0x60, 0x62, 0x61, 0x07, 0x73, 0x01, 0x12, 0x14, 0x33, 0x02, 0xF1, 0x55, 0x33, 0x02,
+    0x12, 0x06, 0x12, 0x12, 0x62, 0x01, 0x12, 0x0A,
+];
+
+fn run(mut chip8: Chip8, rom: &[u8]) -> Chip8 {
+    chip8.load_rom(rom).unwrap();
+    chip8.run_frame(100);
+    chip8
+}
+
+#[test]
+fn self_modifying_code_is_decoded_again() {
+    let cached = run(
+        Chip8::with_backend(Box::new(CachedInterpreter::new())),
+        SELF_MODIFYING,
+    );
+    let interpreter = run(Chip8::with_backend(Box::new(Interpreter)), SELF_MODIFYING);
+    assert_eq!(cached.register(Register::V(2)), 7);
+    assert_eq!(cached.state_hash(), interpreter.state_hash());
+    assert_eq!(cached.backend_name(), "cached");
+}
+
+#[test]
+fn instrumented_machines_are_interpreted() {
+    let mut cached = Chip8::with_backend(Box::new(CachedInterpreter::new()));
+    cached.enable_profile();
+    let cached = run(cached, SELF_MODIFYING);
+    let mut interpreter = Chip8::new();
+    interpreter.enable_profile();
+    let interpreter = run(interpreter, SELF_MODIFYING);
+    assert_eq!(
+        cached.profile().unwrap().hot_spots(),
+        interpreter.profile().unwrap().hot_spots()
+    );
+}
+
+#[test]
+fn addresses_are_decoded_once() {
+    let mut backend = CachedInterpreter::new();
+    let mut chip8 = Chip8::new();
+    // LD V0, 1; JP 0x200
+    chip8.load_rom(&[0x60, 0x01, 0x12, 0x00]).unwrap();
+    for _ in 0..10 {
+        backend.step(&mut chip8);
+    }
+    assert_eq!(backend.cached_count(), 2);
+    assert_eq!(chip8.telemetry().instructions, 10);
+
+    // Loading a ROM writes over the cached instructions.
+    chip8.load_rom(&[0x12, 0x00]).unwrap();
+    backend.step(&mut chip8);
+    assert_eq!(chip8.register(Register::PC), 0x200);
+}
+
+#[test]
+fn resizing_memory_drops_the_cache() {
+    let mut backend = CachedInterpreter::new();
+    let mut chip8 = Chip8::new();
+    chip8.set_variant(Variant::XoChip);
+    // JP 0xFFE; then LD V0, 1 at 0xFFE and JP 0x200 past 4KB
+    chip8.load_rom(&[0x1F, 0xFE]).unwrap();
+    chip8.poke(0xFFE, 0x60);
+    chip8.poke(0xFFF, 0x01);
+    chip8.poke(0x1000, 0x12);
+    chip8.poke(0x1001, 0x00);
+    for _ in 0..4 {
+        backend.step(&mut chip8);
+    }
+    assert_eq!(backend.cached_count(), 3);
+    assert_eq!(chip8.register(Register::PC), 0xFFE);
+
+    chip8.set_variant(Variant::Chip8);
+    backend.step(&mut chip8);
+    assert_eq!(backend.cached_count(), 1);
+}
@@ -0,0 +1,104 @@
+use std::env;
+use std::fs;
+
+use chip_8_rs::capture::{self, CaptureHotkey, Captures, Recorder};
+use chip_8_rs::display::Display;
+use chip_8_rs::palette::Palette;
+use chip_8_rs::Chip8;
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+// The width and height from the IHDR chunk.
+fn size(png: &[u8]) -> (u32, u32) {
+    let word = |at: usize| u32::from_be_bytes(png[at..at + 4].try_into().unwrap());
+    (word(16), word(20))
+}
+
+fn count_chunks(png: &[u8], name: &[u8]) -> usize {
+    png.windows(4).filter(|window| window == &name).count()
+}
+
+#[test]
+fn screenshots_are_scaled() {
+    let mut display = Display::new();
+    display.draw_sprite(0, 0, &[0xF0]);
+    let png = capture::screenshot(&display, &Palette::AMBER, 3);
+    assert!(png.starts_with(SIGNATURE));
+    assert_eq!(size(&png), (64 * 3, 32 * 3));
+    assert_eq!(count_chunks(&png, b"acTL"), 0);
+
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&[0x00, 0xE0]).unwrap();
+    let png = chip8.screenshot();
+    assert!(png.starts_with(SIGNATURE));
+    assert_eq!(size(&png), (64, 32));
+}
+
+#[test]
+fn recordings_merge_still_frames() {
+    let mut recorder = Recorder::new(Palette::CLASSIC, 2, 60);
+    assert!(recorder.is_empty());
+    assert_eq!(recorder.encode(), None);
+
+    let mut display = Display::new();
+    for _ in 0..3 {
+        recorder.capture(&display);
+    }
+    display.draw_sprite(8, 8, &[0xFF]);
+    recorder.capture(&display);
+    assert_eq!(recorder.len(), 4);
+
+    let apng = recorder.encode().unwrap();
+    assert!(apng.starts_with(SIGNATURE));
+    assert_eq!(size(&apng), (128, 64));
+    assert_eq!(count_chunks(&apng, b"acTL"), 1);
+    assert_eq!(count_chunks(&apng, b"fcTL"), 2);
+}
+
+#[test]
+fn recordings_scale_to_the_largest_resolution() {
+    let mut recorder = Recorder::new(Palette::CLASSIC, 1, 60);
+    let mut display = Display::new();
+    recorder.capture(&display);
+    display.set_hires(true);
+    recorder.capture(&display);
+    assert_eq!(size(&recorder.encode().unwrap()), (128, 64));
+}
+
+#[test]
+fn hotkeys_save_next_to_the_rom() {
+    assert_eq!(
+        CaptureHotkey::from_name("F12"),
+        Some(CaptureHotkey::Screenshot)
+    );
+    assert_eq!(CaptureHotkey::from_name("F10"), Some(CaptureHotkey::Record));
+    assert_eq!(CaptureHotkey::from_name("F11"), None);
+
+    let dir = env::temp_dir().join(format!("chip8-capture-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let rom = dir.join("game.ch8");
+    let display = Display::new();
+    let mut captures = Captures::new(Some(&rom), Palette::CLASSIC, 1, 60);
+
+    captures.handle(&display, CaptureHotkey::Screenshot);
+    captures.handle(&display, CaptureHotkey::Screenshot);
+    assert!(dir.join("game.ch8.shot1.png").exists());
+    assert!(dir.join("game.ch8.shot2.png").exists());
+
+    assert_eq!(
+        captures.handle(&display, CaptureHotkey::Record),
+        "Recording"
+    );
+    assert!(captures.is_recording());
+    captures.after_frame(&display);
+    captures.after_frame(&display);
+    let message = captures.finish().unwrap();
+    assert!(
+        message.ends_with("game.ch8.rec1.png (2 frames)"),
+        "{}",
+        message
+    );
+    assert!(!captures.is_recording());
+    assert_eq!(captures.finish(), None);
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,133 @@
+use chip_8_rs::cartridge::{Cartridge, CartridgeError, TimerRate, Variant};
+
+fn cartridge() -> Cartridge {
+    Cartridge {
+        variant: Variant::XoChip,
+        tickrate: Some(1000),
+        palette: Some([[0x11, 0x22, 0x33], [0xAA, 0xBB, 0xCC]]),
+        timer_rate: TimerRate::Pal,
+        title: "Octo".to_string(),
+        author: "Ann".to_string(),
+        rom: vec![0x12, 0x00],
+    }
+}
+
+// A version 1 cartridge, without the timer rate byte.
+fn version_1() -> Vec<u8> {
+    let mut bytes = b"C8X\x01\x01\x00\x0A".to_vec();
+    bytes.extend([0; 6]);
+    bytes.extend(b"\x01T\x01A");
+    bytes.extend([0x12, 0x00]);
+    bytes
+}
+
+#[test]
+fn cartridges_round_trip() {
+    for variant in Variant::ALL {
+        for timer_rate in [TimerRate::Ntsc, TimerRate::Pal] {
+            let cartridge = Cartridge {
+                variant,
+                timer_rate,
+                ..cartridge()
+            };
+            assert_eq!(Cartridge::load(&cartridge.to_bytes()), Ok(cartridge));
+        }
+    }
+
+    // Default settings are stored as zeros.
+    let cartridge = Cartridge::from_rom(&[0x00, 0xE0]);
+    let bytes = cartridge.to_bytes();
+    assert_eq!(&bytes[5..13], &[0; 8]);
+    assert_eq!(Cartridge::load(&bytes), Ok(cartridge));
+}
+
+#[test]
+fn plain_roms_are_wrapped() {
+    let rom = [0x00, 0xE0, 0x12, 0x00];
+    assert_eq!(Cartridge::load(&rom), Ok(Cartridge::from_rom(&rom)));
+}
+
+#[test]
+fn version_1_runs_at_60hz() {
+    let cartridge = Cartridge::load(&version_1()).unwrap();
+    assert_eq!(cartridge.variant, Variant::SuperChip);
+    assert_eq!(cartridge.tickrate, Some(10));
+    assert_eq!(cartridge.timer_rate, TimerRate::Ntsc);
+    assert_eq!(
+        (cartridge.title.as_str(), cartridge.author.as_str()),
+        ("T", "A")
+    );
+    assert_eq!(cartridge.rom, [0x12, 0x00]);
+}
+
+#[test]
+fn unknown_versions_are_rejected() {
+    for version in [0, 3, 0xFF] {
+        let mut bytes = cartridge().to_bytes();
+        bytes[3] = version;
+        assert_eq!(
+            Cartridge::load(&bytes),
+            Err(CartridgeError::UnsupportedVersion(version))
+        );
+    }
+}
+
+#[test]
+fn unknown_fields_are_rejected() {
+    let mut bytes = cartridge().to_bytes();
+    bytes[4] = 4;
+    assert_eq!(
+        Cartridge::load(&bytes),
+        Err(CartridgeError::UnknownVariant(4))
+    );
+
+    let mut bytes = cartridge().to_bytes();
+    bytes[13] = 2;
+    assert_eq!(
+        Cartridge::load(&bytes),
+        Err(CartridgeError::UnknownTimerRate(2))
+    );
+
+    let mut bytes = cartridge().to_bytes();
+    // The first byte of the title
+    bytes[15] = 0xFF;
+    assert_eq!(Cartridge::load(&bytes), Err(CartridgeError::InvalidText));
+}
+
+#[test]
+fn truncated_headers_are_rejected() {
+    let cartridge = cartridge();
+    let bytes = cartridge.to_bytes();
+    let header = bytes.len() - cartridge.rom.len();
+    for len in 3..header {
+        assert_eq!(
+            Cartridge::load(&bytes[..len]),
+            Err(CartridgeError::Truncated),
+            "cut to {} bytes",
+            len
+        );
+    }
+    // Without the timer rate, version 1 headers are a byte shorter.
+    let bytes = version_1();
+    for len in 3..bytes.len() - 2 {
+        assert_eq!(
+            Cartridge::load(&bytes[..len]),
+            Err(CartridgeError::Truncated)
+        );
+    }
+
+    // Only the ROM is missing.
+    assert_eq!(Cartridge::load(&bytes[..bytes.len() - 2]).unwrap().rom, []);
+}
+
+#[test]
+fn long_texts_are_cut_between_characters() {
+    let cartridge = Cartridge {
+        title: "é".repeat(200),
+        ..cartridge()
+    };
+    let loaded = Cartridge::load(&cartridge.to_bytes()).unwrap();
+    assert_eq!(loaded.title, "é".repeat(127));
+    assert_eq!(loaded.author, "Ann");
+    assert_eq!(loaded.rom, cartridge.rom);
+}
@@ -0,0 +1,122 @@
+use chip_8_rs::cheats::{Cheat, CheatCode, CheatKind, Cheats};
+use chip_8_rs::debugger::Debugger;
+use chip_8_rs::Chip8;
+
+// LD V0, 9; LD I, 0x300; LD [I], V0; ADD V0, 1; JP 0x204
+const COUNTER: [u8; 10] = [0x60, 0x09, 0xA3, 0x00, 0xF0, 0x55, 0x70, 0x01, 0x12, 0x04];
+
+const CHEATS: &str = r#"
+# Count from 5 instead of 9
+[patch]
+"Count from 5" = "0x201:05?09"
+"Other version" = "201:07?08"
+
+[freeze]
+"Frozen count" = ["300:2A", "301:FF"]
+"#;
+
+fn code(address: u16, value: u8, compare: Option<u8>) -> CheatCode {
+    CheatCode {
+        address,
+        value,
+        compare,
+    }
+}
+
+#[test]
+fn cheat_files() {
+    let cheats: Cheats = CHEATS.parse().unwrap();
+    assert_eq!(cheats.len(), 3);
+    let count = cheats.get("Count from 5").unwrap();
+    assert_eq!(count.kind, CheatKind::Patch);
+    assert_eq!(count.codes, [code(0x201, 0x05, Some(0x09))]);
+    let frozen = cheats.get("Frozen count").unwrap();
+    assert_eq!(frozen.kind, CheatKind::Freeze);
+    assert_eq!(
+        frozen.codes,
+        [code(0x300, 0x2A, None), code(0x301, 0xFF, None)]
+    );
+    assert!(frozen.enabled);
+    assert_eq!(frozen.to_string(), "on  freeze Frozen count: 300:2A 301:FF");
+    assert_eq!(code(0x201, 5, Some(9)).to_string(), "201:05?09");
+
+    let error = "[freeze]\nlives = \"300\"".parse::<Cheats>().unwrap_err();
+    assert_eq!(error.line, 2);
+    assert!(error.message.contains("ADDRESS:VALUE"), "{}", error);
+    assert_eq!("lives = \"300:01\"".parse::<Cheats>().unwrap_err().line, 1);
+    assert!("[freeze]\nlives = \"300:100\"".parse::<Cheats>().is_err());
+    assert!("[cheats]".parse::<Cheats>().is_err());
+}
+
+#[test]
+fn patches_are_written_on_loads_and_resets() {
+    let mut chip8 = Chip8::new();
+    chip8.set_cheats(CHEATS.parse().unwrap());
+    chip8.enable_cheat("Frozen count", false);
+    chip8.load_rom(&COUNTER).unwrap();
+    // The compare value keeps the code for another version out.
+    assert_eq!(chip8.memory()[0x201], 0x05);
+    chip8.step();
+    chip8.step();
+    chip8.step();
+    assert_eq!(chip8.memory()[0x300], 0x05);
+
+    chip8.poke(0x201, 0x09);
+    chip8.reset(true);
+    assert_eq!(chip8.memory()[0x201], 0x05);
+
+    // Turning a patch off only stops it from being written again.
+    assert!(chip8.enable_cheat("Count from 5", false));
+    chip8.reset(true);
+    assert_eq!(chip8.memory()[0x201], 0x09);
+    assert!(!chip8.enable_cheat("Count from 6", true));
+}
+
+#[test]
+fn freezes_are_written_after_every_frame() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&COUNTER).unwrap();
+    chip8.add_cheat(Cheat::new(
+        "Frozen count",
+        CheatKind::Freeze,
+        vec![code(0x300, 0x2A, None)],
+    ));
+    assert_eq!(chip8.memory()[0x300], 0x2A);
+    chip8.run_frame(12);
+    assert_eq!(chip8.memory()[0x300], 0x2A);
+    assert_eq!(chip8.memory()[0x301], 0);
+
+    chip8.enable_cheat("Frozen count", false);
+    chip8.run_frame(12);
+    assert_ne!(chip8.memory()[0x300], 0x2A);
+    chip8.enable_cheat("Frozen count", true);
+    assert_eq!(chip8.memory()[0x300], 0x2A);
+}
+
+#[test]
+fn debugger_commands() {
+    let mut chip8 = Chip8::new();
+    chip8.set_cheats(CHEATS.parse().unwrap());
+    chip8.load_rom(&COUNTER).unwrap();
+    let mut debugger = Debugger::new(chip8, 12);
+    assert_eq!(
+        debugger.command("cheats").unwrap(),
+        "on  patch  Count from 5: 201:05?09\n\
+         on  patch  Other version: 201:07?08\n\
+         on  freeze Frozen count: 300:2A 301:FF"
+    );
+    assert_eq!(
+        debugger.command("cheat off Frozen count").unwrap(),
+        "off freeze Frozen count: 300:2A 301:FF"
+    );
+    assert_eq!(
+        debugger.command("cheat 302:01 303:02").unwrap(),
+        "on  freeze 302:01 303:02: 302:01 303:02"
+    );
+    assert_eq!(debugger.chip8().memory()[0x302..0x304], [1, 2]);
+    debugger.command("c 1").unwrap();
+    assert_ne!(debugger.chip8().memory()[0x300], 0x2A);
+    assert!(debugger.command("cheat on Infinite lives").is_err());
+    assert!(debugger.command("cheat 302").is_err());
+    assert!(debugger.command("cheat").is_err());
+}
@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+
+use chip_8_rs::config::Config;
+use chip_8_rs::keyboard::{KeyBinding, KeyMap};
+use chip_8_rs::palette::Palette;
+use chip_8_rs::Quirks;
+
+const CONFIG: &str = r##"
+# Faster than the default
+quirks = "schip,shifting=off"
+ips = 1_000
+scale = 12
+rom_dir = "/home/me/roms"
+rom_db = "/home/me/roms/programs.json"
+
+[colors]
+foreground = "#80FF80" # green
+
+[keys]
+5 = "Up"
+a = ["Down", "S"]
+"##;
+
+#[test]
+fn parse() {
+    let config: Config = CONFIG.parse().unwrap();
+    assert_eq!(
+        config.quirks,
+        Some("schip,shifting=off".parse::<Quirks>().unwrap())
+    );
+    assert_eq!(config.ips, Some(1000));
+    assert_eq!(config.scale, Some(12));
+    assert_eq!(config.rom_dir, Some(PathBuf::from("/home/me/roms")));
+    assert_eq!(
+        config.rom_db,
+        Some(PathBuf::from("/home/me/roms/programs.json"))
+    );
+    let palette = config.palette.unwrap();
+    assert_eq!(palette.background(), [0, 0, 0]);
+    assert_eq!(palette.foreground(), [0x80, 0xFF, 0x80]);
+    assert_eq!(config.keys[&0x5], ["Up"]);
+    assert_eq!(config.keys[&0xA], ["Down", "S"]);
+
+    assert_eq!("".parse::<Config>(), Ok(Config::default()));
+
+    // Single colors change the preset, wherever it is given.
+    let config: Config = "[colors]\nplane2 = \"#FF0000\"\npalette = \"amber\""
+        .parse()
+        .unwrap();
+    let mut amber = Palette::AMBER;
+    amber.colors[2] = [0xFF, 0, 0];
+    assert_eq!(config.palette, Some(amber));
+}
+
+#[test]
+fn errors() {
+    let error = |text: &str| text.parse::<Config>().unwrap_err().to_string();
+    assert_eq!(
+        error("ips = \"fast\""),
+        "line 1: invalid type: string \"fast\", expected u32"
+    );
+    assert!(error("ips = fast").starts_with("line 1: invalid string"));
+    assert_eq!(
+        error("\n[colors]\nforeground = \"#80FF8\""),
+        "line 3: expected a color as #RRGGBB, got `#80FF8`"
+    );
+    assert_eq!(
+        error("[keys]\n10 = \"X\""),
+        "line 2: unknown keypad key `10`"
+    );
+    assert_eq!(
+        error("[keys]\n5 = 3"),
+        "line 2: a string or an array of strings"
+    );
+    assert!(error("[sound]").starts_with("line 1: unknown field `sound`"));
+    assert!(error("speed = 2").starts_with("line 1: unknown field `speed`"));
+    assert_eq!(
+        error("quirks = \"fast\""),
+        "line 1: unknown quirk preset `fast`"
+    );
+}
+
+#[test]
+fn remapped_keys() {
+    let config: Config = CONFIG.parse().unwrap();
+    let mut keymap = KeyMap::standard();
+    config.apply_keys(&mut keymap);
+
+    let binding = |key| Some(KeyBinding { pad: 0, key });
+    assert_eq!(keymap.lookup("up"), binding(0x5));
+    assert_eq!(keymap.lookup("S"), binding(0xA));
+    assert_eq!(keymap.lookup("down"), binding(0xA));
+
+    // The standard keys of remapped keys are gone, the others stay.
+    assert_eq!(keymap.lookup("W"), None);
+    assert_eq!(keymap.lookup("Z"), None);
+    assert_eq!(keymap.lookup("Q"), binding(0x4));
+}
@@ -0,0 +1,131 @@
+use std::fs;
+use std::path::Path;
+
+use chip_8_rs::backend::Interpreter;
+use chip_8_rs::blocks::BlockTranslator;
+use chip_8_rs::cached::CachedInterpreter;
+use chip_8_rs::corpus;
+use chip_8_rs::cpu::Chip8;
+use chip_8_rs::snapshot;
+use sha2::{Digest, Sha256};
+
+const FRAMES: u64 = 120;
+const CYCLES_PER_FRAME: usize = 12;
+
+// A public test ROM, which is downloaded into `tests/roms/public` by hand,
+// see the README there.
+struct PublicRom {
+    file: &'static str,
+
+    // Archive the pinned copy was taken from, and its path in there
+    url: &'static str,
+    member: &'static str,
+
+    // SHA-256 of the file, and the hash of the screen after `frames`, see
+    // `snapshot::hash`
+    sha256: &'static str,
+    screen: u64,
+    frames: u64,
+}
+
+const PUBLIC_ROMS: &[PublicRom] = &[
+    PublicRom {
+        file: "ibm-logo.ch8",
+        url: "https://static.crates.io/crates/c8/c8-1.0.1.crate",
+        member: "c8-1.0.1/roms/c8/ibm_logo.ch8",
+        sha256: "8bf3b46d8a64c2074e7538200f684a2eaced258404d3c7d3bd7a917c3d0143e5",
+        screen: 0xCCAA5440418EC389,
+        frames: 60,
+    },
+    PublicRom {
+        file: "test_opcode.ch8",
+        url: "https://static.crates.io/crates/deca/deca-0.0.10.crate",
+        member: "deca-0.0.10/tests/test_roms/test_opcode.ch8",
+        sha256: "67759cf9f5b27db66f0769ea8fd0b30ba220f46d6f19f8ba4fd4108d986ce0ab",
+        screen: 0xD6D0930DABC62709,
+        frames: 120,
+    },
+];
+
+// Run every bundled ROM under each backend; none may fault, and all
+// backends must end in the same state.
+#[test]
+fn corpus() {
+    let mut failures = Vec::new();
+    for test_rom in corpus::ROMS {
+        let mut machines = [
+            Chip8::with_backend(Box::new(Interpreter)),
+            Chip8::with_backend(Box::new(BlockTranslator::new())),
+            Chip8::with_backend(Box::new(CachedInterpreter::new())),
+        ];
+        for chip8 in &mut machines {
+            chip8.set_seed(0);
+            chip8
+                .load_rom(test_rom.rom)
+                .expect("test ROMs fit in memory");
+            for _ in 0..FRAMES {
+                chip8.run_frame(CYCLES_PER_FRAME);
+            }
+            if chip8.telemetry().errors > 0 {
+                failures.push(format!(
+                    "{} ({}): {} errors",
+                    test_rom.name,
+                    chip8.backend_name(),
+                    chip8.telemetry().errors
+                ));
+            }
+        }
+        if machines[1..]
+            .iter()
+            .any(|chip8| chip8.state_hash() != machines[0].state_hash())
+        {
+            failures.push(format!("{}: backends diverge", test_rom.name));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+// Run the public test ROMs which were downloaded, checking each against the
+// SHA-256 and the screen pinned for it.
+#[test]
+fn public_roms() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/roms/public");
+    let mut failures = Vec::new();
+    for public in PUBLIC_ROMS {
+        let Ok(rom) = fs::read(dir.join(public.file)) else {
+            eprintln!(
+                "Skipping {}, which is not in tests/roms/public; extract {} from {}",
+                public.file, public.member, public.url
+            );
+            continue;
+        };
+        let sha256: String = Sha256::digest(&rom)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        if sha256 != public.sha256 {
+            failures.push(format!(
+                "{}: SHA-256 {} is not the one pinned",
+                public.file, sha256
+            ));
+            continue;
+        }
+
+        let mut chip8 = Chip8::new();
+        chip8.set_seed(0);
+        chip8.load_rom(&rom).expect("test ROMs fit in memory");
+        for _ in 0..public.frames {
+            chip8.run_frame(CYCLES_PER_FRAME);
+        }
+        let screen = snapshot::hash(chip8.display());
+        if screen != public.screen {
+            failures.push(format!(
+                "{}: screen 0x{:016X} is not the one pinned\n{}",
+                public.file,
+                screen,
+                snapshot::to_text(chip8.display())
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
@@ -0,0 +1,101 @@
+use chip_8_rs::crowd::CrowdInput;
+
+#[test]
+fn the_key_with_the_most_votes_is_held() {
+    let mut crowd = CrowdInput::new();
+    crowd.vote(1, 0x5);
+    crowd.vote(2, 0xA);
+    crowd.vote(3, 0x5);
+    assert_eq!(crowd.vote_count(), 3);
+    assert_eq!(crowd.tally(), 1 << 0x5);
+}
+
+#[test]
+fn later_votes_replace_earlier_ones() {
+    let mut crowd = CrowdInput::new();
+    crowd.vote(1, 0x5);
+    crowd.vote(2, 0x5);
+    crowd.vote(3, 0xA);
+    crowd.vote(1, 0xA);
+    assert_eq!(crowd.vote_count(), 3);
+    assert_eq!(crowd.tally(), 1 << 0xA);
+
+    // Keys past F are taken modulo 16.
+    crowd.vote(1, 0x13);
+    assert_eq!(crowd.tally(), 1 << 0x3);
+}
+
+#[test]
+fn ties_go_to_the_lowest_key() {
+    for order in [[1, 2, 3, 4], [4, 3, 2, 1], [2, 4, 1, 3]] {
+        let mut crowd = CrowdInput::new();
+        for client in order {
+            let key = if client % 2 == 0 { 0xC } else { 0x7 };
+            crowd.vote(client, key);
+        }
+        assert_eq!(crowd.tally(), 1 << 0x7, "{:?}", order);
+    }
+    let mut crowd = CrowdInput::new();
+    crowd.vote(9, 0xF);
+    crowd.vote(8, 0x0);
+    assert_eq!(crowd.tally(), 1 << 0x0);
+}
+
+#[test]
+fn votes_expire_with_their_frame() {
+    let mut crowd = CrowdInput::new();
+    crowd.vote(1, 0x2);
+    crowd.vote(2, 0x2);
+    assert_eq!(crowd.tally(), 1 << 0x2);
+    assert_eq!(crowd.vote_count(), 0);
+
+    // Nobody voted this frame, so no key is held.
+    assert_eq!(crowd.tally(), 0);
+
+    // The two earlier votes do not outweigh a single new one.
+    crowd.vote(3, 0x8);
+    assert_eq!(crowd.tally(), 1 << 0x8);
+}
+
+#[cfg(feature = "net")]
+#[test]
+fn votes_arrive_over_tcp() {
+    use std::io::Write;
+    use std::net::TcpStream;
+    use std::thread;
+    use std::time::Duration;
+
+    use chip_8_rs::crowd::VoteServer;
+
+    let mut server = VoteServer::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    let mut clients: Vec<TcpStream> = (0..3).map(|_| TcpStream::connect(addr).unwrap()).collect();
+    // Two votes for 4, one for B, and a partial line which only counts once
+    // it is complete.
+    clients[0].write_all(b"4\n").unwrap();
+    clients[1].write_all(b"b\n").unwrap();
+    clients[2].write_all(b"4").unwrap();
+
+    let mut crowd = CrowdInput::new();
+    for _ in 0..100 {
+        server.poll(&mut crowd).unwrap();
+        if crowd.vote_count() == 2 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(server.client_count(), 3);
+    assert_eq!(crowd.tally(), 1 << 0x4);
+
+    // The rest of the line, and a vote which is not a key: a tie.
+    clients[2].write_all(b"\n").unwrap();
+    clients[1].write_all(b"b\nzz\n").unwrap();
+    for _ in 0..100 {
+        server.poll(&mut crowd).unwrap();
+        if crowd.vote_count() == 2 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(crowd.tally(), 1 << 0x4);
+}
@@ -0,0 +1,251 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use chip_8_rs::audit::AuditEvent;
+use chip_8_rs::debugger::{Debugger, Stop};
+use chip_8_rs::watch::Expr;
+use chip_8_rs::{asm, Chip8, Register};
+
+fn debugger(source: &str) -> Debugger {
+    let assembly = asm::assemble(source, "test.s", |name| Err(name.to_string())).unwrap();
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&assembly.rom).unwrap();
+    Debugger::new(chip8, 10)
+}
+
+const COUNTER: &str = "
+loop:
+    add v0, 1
+    ld i, 0x300
+    ld [i], v0
+    jp loop";
+
+#[test]
+fn breakpoints_stop_before_the_instruction() {
+    let mut debugger = debugger(COUNTER);
+    debugger.add_breakpoint(0x204);
+    assert_eq!(debugger.resume(1), Some(Stop::Breakpoint(0x204)));
+    assert_eq!(debugger.chip8().register(Register::PC), 0x204);
+    assert_eq!(debugger.chip8().memory()[0x300], 0);
+
+    // Continuing runs the instruction under the breakpoint.
+    assert_eq!(debugger.resume(1), Some(Stop::Breakpoint(0x204)));
+    assert_eq!(debugger.chip8().register(Register::V(0)), 2);
+
+    assert!(debugger.remove_breakpoint(0x204));
+    assert_eq!(debugger.resume(1), None);
+}
+
+#[test]
+fn conditional_breakpoints_wait_for_their_condition() {
+    let mut debugger = debugger(COUNTER);
+    debugger.add_conditional_breakpoint(0x204, Expr::parse("V0 == 3").unwrap());
+    assert_eq!(debugger.resume(10), Some(Stop::Breakpoint(0x204)));
+    assert_eq!(debugger.chip8().register(Register::V(0)), 3);
+    assert_eq!(debugger.chip8().memory()[0x300], 2);
+    assert_eq!(
+        debugger.condition(0x204),
+        Some(&Expr::parse("V0 == 3").unwrap())
+    );
+
+    // Conditions without a value never break.
+    debugger.add_conditional_breakpoint(0x204, Expr::parse("[0xFFFF]").unwrap());
+    assert_eq!(debugger.resume(1), None);
+    assert_eq!(debugger.breakpoints().collect::<Vec<_>>(), [0x204]);
+}
+
+#[test]
+fn step_hooks_see_every_instruction() {
+    let mut debugger = debugger(COUNTER);
+    let steps = Rc::new(Cell::new(0));
+    let counted = Rc::clone(&steps);
+    debugger.set_step_hook(Box::new(move |chip8: &Chip8| {
+        counted.set(counted.get() + 1);
+        chip8.memory()[0x300] == 2
+    }));
+    assert_eq!(debugger.resume(10), Some(Stop::Hook(0x206)));
+    assert_eq!(steps.get(), 7);
+    assert_eq!(debugger.chip8().register(Register::V(0)), 2);
+    assert_eq!(debugger.step(), Some(Stop::Hook(0x200)));
+
+    debugger.clear_step_hook();
+    assert_eq!(debugger.resume(1), None);
+}
+
+#[test]
+fn watchpoints_stop_after_the_change() {
+    let mut debugger = debugger(COUNTER);
+    debugger.watch_memory(0x300);
+    assert_eq!(
+        debugger.resume(1),
+        Some(Stop::Watchpoint(AuditEvent::Write {
+            pc: 0x204,
+            address: 0x300,
+            old: 0,
+            new: 1,
+        }))
+    );
+    assert_eq!(debugger.chip8().register(Register::PC), 0x206);
+
+    let mut debugger = self::debugger(COUNTER);
+    debugger.watch_register(Register::V(0));
+    let stop = debugger.resume(1).unwrap();
+    assert_eq!(stop.to_string(), "V0 changed from 0x00 to 0x01 at 0x200");
+}
+
+#[test]
+fn stepping_over_calls() {
+    let mut debugger = debugger(
+        "call sub
+         ld v1, 1
+     sub:
+         ld v0, 5
+     spin:
+         jp spin",
+    );
+    // Plain instructions are single-stepped.
+    debugger
+        .chip8_mut()
+        .set_register(Register::PC, 0x202)
+        .unwrap();
+    assert_eq!(debugger.step_over(1), None);
+    assert_eq!(debugger.chip8().register(Register::PC), 0x204);
+
+    // A call runs until a breakpoint inside the subroutine, or for at most
+    // the given frames when it never returns.
+    debugger
+        .chip8_mut()
+        .set_register(Register::PC, 0x200)
+        .unwrap();
+    debugger.add_breakpoint(0x206);
+    assert_eq!(debugger.step_over(1), Some(Stop::Breakpoint(0x206)));
+    assert_eq!(debugger.chip8().register(Register::V(0)), 5);
+    assert_eq!(debugger.chip8().register(Register::SP), 1);
+}
+
+#[test]
+fn snapshots_go_back_in_time() {
+    let mut debugger = debugger(COUNTER);
+    debugger.step();
+    let state = debugger.snapshot();
+    debugger.resume(2);
+    assert_ne!(debugger.chip8().register(Register::V(0)), 1);
+    debugger.restore(&state);
+    assert_eq!(debugger.chip8().register(Register::V(0)), 1);
+    assert_eq!(debugger.chip8().register(Register::PC), 0x202);
+}
+
+#[test]
+fn commands() {
+    let mut debugger = debugger(COUNTER);
+    assert_eq!(debugger.command("b 0x206").unwrap(), "breakpoint at 0x206");
+    assert_eq!(
+        debugger.command("c").unwrap(),
+        "breakpoint at 0x206\n0x206: 1200  jp 0x200"
+    );
+    assert_eq!(debugger.command("s").unwrap(), "0x200: 7001  add v0, 0x01");
+    assert_eq!(debugger.command("x/4 I").unwrap(), "0x300: 01 00 00 00");
+    assert_eq!(
+        debugger.command("regs").unwrap(),
+        "V0=01 V1=00 V2=00 V3=00 V4=00 V5=00 V6=00 V7=00\n\
+         V8=00 V9=00 VA=00 VB=00 VC=00 VD=00 VE=00 VF=00\n\
+         I=300 PC=200 SP=0 DT=00 ST=00"
+    );
+    assert_eq!(debugger.command("snap").unwrap(), "saved");
+    debugger.command("s 3").unwrap();
+    assert_eq!(debugger.chip8().memory()[0x300], 2);
+    assert_eq!(
+        debugger.command("restore").unwrap(),
+        "0x200: 7001  add v0, 0x01"
+    );
+    assert_eq!(debugger.chip8().register(Register::V(0)), 1);
+
+    assert_eq!(
+        debugger.command("d 0x208").unwrap_err(),
+        "no breakpoint at 0x208"
+    );
+    assert_eq!(
+        debugger.command("jump").unwrap_err(),
+        "unknown command `jump`"
+    );
+    assert!(debugger.command("x/zz 0").is_err());
+}
+
+#[test]
+fn expression_commands() {
+    let mut debugger = debugger(COUNTER);
+    assert_eq!(
+        debugger.command("b 0x206 if [0x300] == 2").unwrap(),
+        "breakpoint at 0x206 if [0x300] == 2"
+    );
+    assert_eq!(debugger.command("display V0").unwrap(), "1: V0 = 0x0");
+    // I starts out at the font.
+    assert_eq!(
+        debugger.command("display [I] * 2").unwrap(),
+        "1: V0 = 0x0\n2: [I] * 2 = 0x1E0"
+    );
+    assert_eq!(
+        debugger.command("c").unwrap(),
+        "breakpoint at 0x206\n0x206: 1200  jp 0x200\n1: V0 = 0x2\n2: [I] * 2 = 0x4"
+    );
+    assert_eq!(debugger.command("p I + 2").unwrap(), "I + 2 = 0x302");
+    debugger.command("undisplay 1").unwrap();
+    assert_eq!(
+        debugger.command("s").unwrap(),
+        "0x200: 7001  add v0, 0x01\n1: [I] * 2 = 0x4"
+    );
+    assert_eq!(
+        debugger.command("undisplay 2").unwrap_err(),
+        "no expression 2"
+    );
+    assert!(debugger.command("b 0x200 if V0 ==").is_err());
+    assert!(debugger.command("p [0xFFFF]").is_err());
+}
+
+#[test]
+fn ram_search_commands() {
+    let mut debugger = debugger(COUNTER);
+    assert!(debugger.command("search = 1").is_err());
+    debugger.command("s 3").unwrap();
+    assert!(debugger
+        .command("search 8")
+        .unwrap()
+        .starts_with("4096 candidates"));
+    assert!(debugger
+        .command("search = 1")
+        .unwrap()
+        .starts_with("2 candidates"));
+    debugger.command("s 4").unwrap();
+    // Listed as codes for `cheat`.
+    assert_eq!(
+        debugger.command("search increased").unwrap(),
+        "1 candidate\n300:02"
+    );
+    assert_eq!(debugger.command("search").unwrap(), "1 candidate\n300:02");
+
+    // Words list both of their bytes.
+    debugger.command("search 16").unwrap();
+    assert_eq!(
+        debugger.command("search = 0x0200").unwrap(),
+        "1 candidate\n300:02 301:00"
+    );
+    assert!(debugger.command("search bigger").is_err());
+}
+
+#[test]
+fn heatmaps_show_the_accesses_since_the_start() {
+    let mut debugger = debugger(COUNTER);
+    debugger.command("s 4").unwrap();
+    let map = debugger.command("heatmap").unwrap();
+    let lines: Vec<&str> = map.lines().collect();
+    assert_eq!(lines.len(), 64);
+    assert!(lines.iter().all(|line| line.len() == 6 + 64));
+    // The loop's eight bytes at 0x200, and the counter at 0x300.
+    assert_eq!(&lines[8][..16], "0x200 xxxxxxxx..");
+    assert_eq!(&lines[12][..8], "0x300 w.");
+    let touched: usize = lines
+        .iter()
+        .map(|line| line[6..].matches(['x', 'w']).count())
+        .sum();
+    assert_eq!(touched, 9);
+}
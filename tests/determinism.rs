@@ -0,0 +1,136 @@
+use chip_8_rs::backend::{ExecutionBackend, Interpreter};
+use chip_8_rs::blocks::BlockTranslator;
+use chip_8_rs::cached::CachedInterpreter;
+use chip_8_rs::determinism::{self, Divergence};
+use chip_8_rs::{Chip8, Quirks};
+
+// Draws random digits, storing them and setting the delay timer, while
+// stepping through the keys held.
+const PROGRAM: [u8; 22] = [
+    0x63, 0x0F, // LD V3, 0x0F
+    0xC0, 0xFF, // RND V0, 0xFF
+    0xA3, 0x00, // LD I, 0x300
+    0xF0, 0x55, // LD [I], V0
+    0xF0, 0x29, // LD F, V0
+    0xD1, 0x25, // DRW V1, V2, 5
+    0xE1, 0x9E, // SKP V1
+    0x71, 0x01, // ADD V1, 1
+    0x81, 0x32, // AND V1, V3
+    0xF0, 0x15, // LD DT, V0
+    0x12, 0x02, // JP 0x202
+];
+
+const FRAMES: u64 = 120;
+
+// A different set of keys held every frame.
+fn inputs() -> Vec<u16> {
+    (0..FRAMES as u16)
+        .map(|frame| frame.wrapping_mul(0x9E37).rotate_left(frame as u32 % 16))
+        .collect()
+}
+
+fn machine(backend: Box<dyn ExecutionBackend>, seed: u64) -> Chip8 {
+    let mut chip8 = Chip8::with_backend(backend);
+    chip8.set_seed(seed);
+    chip8.load_rom(&PROGRAM).unwrap();
+    chip8
+}
+
+#[test]
+fn runs_repeat_exactly() {
+    assert_eq!(
+        determinism::compare_runs(
+            machine(Box::new(Interpreter), 42),
+            machine(Box::new(Interpreter), 42),
+            &inputs(),
+            FRAMES,
+            20
+        ),
+        Ok(())
+    );
+}
+
+#[test]
+fn backends_agree() {
+    let backends: [fn() -> Box<dyn ExecutionBackend>; 2] = [
+        || Box::new(CachedInterpreter::new()),
+        || Box::new(BlockTranslator::new()),
+    ];
+    for backend in backends {
+        let result = determinism::compare_runs(
+            machine(Box::new(Interpreter), 42),
+            machine(backend(), 42),
+            &inputs(),
+            FRAMES,
+            20,
+        );
+        assert_eq!(result, Ok(()));
+    }
+}
+
+#[test]
+fn divergences_are_reported_at_the_first_frame() {
+    // The first instruction draws a different random number.
+    let result = determinism::compare_runs(
+        machine(Box::new(Interpreter), 1),
+        machine(Box::new(Interpreter), 2),
+        &inputs(),
+        FRAMES,
+        20,
+    );
+    let Err(Divergence {
+        frame,
+        first,
+        second,
+    }) = result
+    else {
+        panic!("expected a divergence");
+    };
+    assert_eq!(frame, 0);
+    assert_ne!(first, second);
+
+    // LD V0, 10; LD DT, V0; LD V0, DT; SE V0, 0; JP 0x204; RND V1, 0xFF;
+    // JP 0x20C
+    let delayed = [
+        0x60, 0x0A, 0xF0, 0x15, 0xF0, 0x07, 0x30, 0x00, 0x12, 0x04, 0xC1, 0xFF, 0x12, 0x0C,
+    ];
+    let run = |frames| {
+        let [first, second] = [1, 2].map(|seed| {
+            let mut chip8 = machine(Box::new(Interpreter), seed);
+            chip8.load_rom(&delayed).unwrap();
+            chip8
+        });
+        determinism::compare_runs(first, second, &[], frames, 20)
+    };
+    // The delay timer runs out after ten frames.
+    assert_eq!(run(10), Ok(()));
+    assert!(matches!(run(FRAMES), Err(Divergence { frame: 10, .. })));
+}
+
+#[test]
+fn quirk_divergences_need_the_inputs_reaching_them() {
+    // LD V0, 5; SKNP V0; JP 0x208; JP 0x202; LD V1, 0x81; LD V2, 2;
+    // SHR V1, V2; JP 0x20E
+    let rom = [
+        0x60, 0x05, 0xE0, 0xA1, 0x12, 0x08, 0x12, 0x02, 0x61, 0x81, 0x62, 0x02, 0x81, 0x26, 0x12,
+        0x0E,
+    ];
+    let run = |inputs: &[u16]| {
+        let [first, second] = [Quirks::CHIP8, Quirks::SCHIP].map(|quirks| {
+            let mut chip8 = Chip8::new();
+            chip8.set_quirks(quirks);
+            chip8.load_rom(&rom).unwrap();
+            chip8
+        });
+        determinism::find_quirk_divergence(first, second, inputs, 10, 20)
+    };
+    // Without key 5 the shift is never reached.
+    assert_eq!(run(&[]), None);
+    let divergence = run(&[0, 0, 1 << 5]).expect("the shift runs on frame 2");
+    assert_eq!(divergence.frame, 2);
+    assert_eq!(
+        (divergence.program_counter, divergence.opcode),
+        (0x20C, 0x8126)
+    );
+    assert_eq!(Quirks::affecting(divergence.opcode), Some("shifting"));
+}
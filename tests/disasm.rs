@@ -0,0 +1,81 @@
+use std::fs;
+
+use chip_8_rs::asm;
+use chip_8_rs::cpu::Chip8;
+use chip_8_rs::disasm::{self, Trace};
+
+// Listings of the bundled ROMs, as `build.rs` assembles them, annotated or
+// not, must reassemble into the same bytes.
+#[test]
+fn listings_reassemble() {
+    let mut failures = Vec::new();
+    for entry in fs::read_dir(concat!(env!("OUT_DIR"), "/roms")).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "ch8") {
+            continue;
+        }
+        let rom = fs::read(&path).unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.set_seed(0);
+        chip8.load_rom(&rom).expect("test ROMs fit in memory");
+        let trace = Trace::record(&mut chip8, 10, 12);
+        for listing in [
+            disasm::listing(&rom, None),
+            disasm::listing(&rom, Some(&trace)),
+        ] {
+            match asm::assemble(&listing, "listing", |name| Err(name.to_string())) {
+                Ok(assembly) if assembly.rom == rom => {}
+                Ok(_) => failures.push(format!("{}: different bytes", path.display())),
+                Err(e) => failures.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+// Bytes no instruction leads to are data, even where they would decode.
+#[test]
+fn unreachable_bytes_are_data() {
+    let rom = [0x12, 0x04, 0x60, 0x01, 0x3A, 0x00, 0x00, 0xE0, 0x12, 0x08];
+    let listing = disasm::listing(&rom, None);
+    let lines: Vec<&str> = listing
+        .lines()
+        .map(|line| line.split(';').next().unwrap().trim())
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            "jp loc_204",
+            "db 0x60, 0x01",
+            "loc_204:",
+            "se va, 0x00",
+            "cls",
+            "loc_208:",
+            "jp loc_208"
+        ]
+    );
+    let assembly = asm::assemble(&listing, "listing", |name| Err(name.to_string())).unwrap();
+    assert_eq!(assembly.rom, rom);
+}
+
+#[test]
+fn xo_chip_instructions_reassemble() {
+    let rom = [
+        0xF0, 0x00, 0x02, 0x0A, 0x51, 0x32, 0xF3, 0x01, 0x00, 0xD2, 0xF0, 0x02, 0xF4, 0x3A, 0x00,
+        0xFD,
+    ];
+    let listing = disasm::listing(&rom, None);
+    for text in [
+        "ld i, long data_20A",
+        "ld [i], v1-v3",
+        "plane 3",
+        "scu 2",
+        "audio",
+        "pitch v4",
+    ] {
+        assert!(listing.contains(text), "no `{}` in\n{}", text, listing);
+    }
+    assert!(listing.contains("200: F000 020A"));
+    let assembly = asm::assemble(&listing, "listing", |name| Err(name.to_string())).unwrap();
+    assert_eq!(assembly.rom, rom);
+}
@@ -0,0 +1,45 @@
+use chip_8_rs::{Chip8, Register};
+
+// LD V0, 60; LD DT, V0; LD V1, DT; SE V1, 0; JP 0x204; DRW V0, V0, 5;
+// JP 0x20C: a title delay of a second, then the first sprite.
+const DELAYED: [u8; 14] = [
+    0x60, 0x3C, 0xF0, 0x15, 0xF1, 0x07, 0x31, 0x00, 0x12, 0x04, 0xD0, 0x05, 0x12, 0x0C,
+];
+
+fn delayed() -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&DELAYED).unwrap();
+    chip8
+}
+
+#[test]
+fn fast_boots_stop_at_the_frame_of_the_first_draw() {
+    let mut chip8 = delayed();
+    // The delay timer runs out after frame 60, the loop sees it in 61.
+    assert_eq!(chip8.run_until_first_draw(10, 600), 61);
+    assert_eq!(chip8.telemetry().frames, 61);
+    assert_eq!(chip8.telemetry().draw_calls, 1);
+    // The 0 digit, at V0 = 60 wrapped onto the 64x32 screen.
+    assert!(chip8.display().pixel(60, 28));
+    // The frame was run to its end, past the draw.
+    assert_eq!(chip8.register(Register::PC), 0x20C);
+
+    // Later calls wait for the next draw.
+    assert_eq!(chip8.run_until_first_draw(10, 5), 5);
+    assert_eq!(chip8.telemetry().draw_calls, 1);
+}
+
+#[test]
+fn fast_boots_stop_at_their_frame_budget() {
+    let mut chip8 = delayed();
+    assert_eq!(chip8.run_until_first_draw(10, 30), 30);
+    assert_eq!(chip8.telemetry().frames, 30);
+    assert_eq!(chip8.telemetry().draw_calls, 0);
+    assert_eq!(chip8.timers().delay, 30);
+
+    // Programs drawing at once take a single frame.
+    let mut chip8 = Chip8::new();
+    // DRW V0, V0, 5; JP 0x202
+    chip8.load_rom(&[0xD0, 0x05, 0x12, 0x02]).unwrap();
+    assert_eq!(chip8.run_until_first_draw(10, 30), 1);
+}
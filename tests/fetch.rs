@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+use std::{env, fs};
+
+use chip_8_rs::fetch::{self, FetchError, RomCache, MAX_ROM_SIZE};
+
+const URL: &str = "https://example.com/jam/game.ch8";
+
+fn cache_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("chip8-fetch-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn only_http_urls_are_fetched() {
+    for url in [
+        "http://example.com/game.ch8",
+        "https://example.com",
+        "https://example.com:8080/a?b#c",
+    ] {
+        assert!(fetch::check_url(url).is_ok(), "{}", url);
+    }
+    for url in [
+        "ftp://example.com/game.ch8",
+        "https://",
+        "http:///game.ch8",
+        "game.ch8",
+    ] {
+        assert!(
+            matches!(fetch::check_url(url), Err(FetchError::InvalidUrl(u)) if u == url),
+            "{}",
+            url
+        );
+    }
+}
+
+#[test]
+fn downloads_must_fit_a_rom() {
+    assert!(matches!(fetch::check_download(&[]), Err(FetchError::Empty)));
+    assert!(fetch::check_download(&[0x12, 0x00]).is_ok());
+    assert!(fetch::check_download(&vec![0; MAX_ROM_SIZE as usize]).is_ok());
+    assert!(matches!(
+        fetch::check_download(&vec![0; MAX_ROM_SIZE as usize + 1]),
+        Err(FetchError::TooLarge(MAX_ROM_SIZE))
+    ));
+}
+
+#[test]
+fn roms_are_cached_by_url() {
+    let dir = cache_dir("url");
+    let cache = RomCache::new(&dir);
+    assert_eq!(cache.get(URL), None);
+
+    cache.put(URL, &[0x12, 0x00]).unwrap();
+    assert_eq!(cache.get(URL), Some(vec![0x12, 0x00]));
+    assert!(cache.path(URL).starts_with(&dir));
+    assert_ne!(cache.path(URL), cache.path("https://example.com/other.ch8"));
+    assert_eq!(cache.get("https://example.com/other.ch8"), None);
+
+    // Downloading again replaces the ROM.
+    cache.put(URL, &[0x00, 0xE0]).unwrap();
+    assert_eq!(cache.get(URL), Some(vec![0x00, 0xE0]));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn damaged_cache_entries_are_misses() {
+    let dir = cache_dir("damaged");
+    let cache = RomCache::new(&dir);
+    cache.put(URL, &[0x12, 0x00]).unwrap();
+    let path = cache.path(URL);
+    assert_eq!(
+        fs::read_to_string(path.with_extension("sha1")).unwrap(),
+        "92a5652d382a18e89c4881ec57041fc7d885ca80"
+    );
+
+    // Cut short, or changed
+    fs::write(&path, [0x12]).unwrap();
+    assert_eq!(cache.get(URL), None);
+    fs::write(&path, [0x12, 0x02]).unwrap();
+    assert_eq!(cache.get(URL), None);
+
+    // Without its hash
+    cache.put(URL, &[0x12, 0x00]).unwrap();
+    fs::remove_file(path.with_extension("sha1")).unwrap();
+    assert_eq!(cache.get(URL), None);
+    fs::remove_dir_all(dir).unwrap();
+}
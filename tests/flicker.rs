@@ -0,0 +1,42 @@
+use chip_8_rs::flicker::{FlickerConfig, FlickerLimiter};
+
+const PIXELS: usize = 64 * 32;
+
+// Inverting the whole screen every frame for a second must not show more
+// than three flashes.
+#[test]
+fn inversions_are_limited() {
+    let mut limiter = FlickerLimiter::new(FlickerConfig::default());
+    let mut flashes = 0;
+    let mut previous = 0;
+    for frame in 0..60 {
+        let lit = limiter
+            .present(&[frame % 2 == 0; PIXELS])
+            .iter()
+            .filter(|&&v| v > 0)
+            .count();
+        if lit.abs_diff(previous) * 10 >= PIXELS {
+            flashes += 1;
+        }
+        previous = lit;
+    }
+    assert!(flashes <= 3, "{} flashes", flashes);
+}
+
+// A sprite erased and redrawn on alternate frames stays lit.
+#[test]
+fn erase_redraw_persists() {
+    let mut limiter = FlickerLimiter::new(FlickerConfig::default());
+    let mut frame = [false; PIXELS];
+    for step in 0..10 {
+        frame[0] = step % 2 == 0;
+        assert_eq!(limiter.present(&frame)[0], 255, "step {}", step);
+    }
+
+    let mut unlimited = FlickerLimiter::new(FlickerConfig {
+        enabled: false,
+        ..FlickerConfig::default()
+    });
+    unlimited.present(&[true; PIXELS]);
+    assert_eq!(unlimited.present(&[false; PIXELS])[0], 0);
+}
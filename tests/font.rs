@@ -0,0 +1,84 @@
+use chip_8_rs::memory;
+use chip_8_rs::{Chip8, FontSet, Register};
+
+// Draw digit 4 at the top left and return the rows of the sprite, `width`
+// pixels wide, as text.
+fn draw_four(chip8: &mut Chip8, opcode: u16, width: usize, height: u8) -> Vec<String> {
+    // LD V0, 4; <opcode with x = 0>; DRW V1, V1, height
+    let [high, low] = opcode.to_be_bytes();
+    chip8
+        .load_rom(&[0x60, 0x04, high, low, 0xD1, 0x10 | height])
+        .unwrap();
+    chip8.run_frame(3);
+    let display = chip8.display();
+    (0..height as usize)
+        .map(|y| {
+            (0..width)
+                .map(|x| match display.pixels()[y * display.width() + x] {
+                    true => '#',
+                    false => '.',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn names() {
+    for font in FontSet::ALL {
+        assert_eq!(font.to_string().parse(), Ok(font));
+    }
+    assert_eq!("vip".parse(), Ok(FontSet::CosmacVip));
+    assert!("cosmac".parse::<FontSet>().is_err());
+    assert_eq!(FontSet::default(), FontSet::Modern);
+}
+
+#[test]
+fn small_digits() {
+    let mut chip8 = Chip8::new();
+    assert_eq!(
+        draw_four(&mut chip8, 0xF029, 4, 5),
+        ["#..#", "#..#", "####", "...#", "...#"]
+    );
+
+    let mut chip8 = Chip8::new();
+    chip8.set_font(FontSet::CosmacVip);
+    assert_eq!(chip8.font(), FontSet::CosmacVip);
+    assert_eq!(
+        draw_four(&mut chip8, 0xF029, 4, 5),
+        ["#.#.", "#.#.", "####", "..#.", "..#."]
+    );
+    assert_eq!(
+        chip8.register(Register::I) as usize,
+        memory::FONT_ADDRESS + 20
+    );
+
+    let mut chip8 = Chip8::new();
+    chip8.set_font(FontSet::Dream6800);
+    assert_eq!(
+        draw_four(&mut chip8, 0xF029, 4, 5),
+        ["#...", "#.#.", "#.#.", "###.", "..#."]
+    );
+}
+
+#[test]
+fn big_digits() {
+    // Only the SUPER-CHIP set changes the 8x10 digits.
+    let mut chip8 = Chip8::new();
+    chip8.set_font(FontSet::CosmacVip);
+    assert_eq!(
+        draw_four(&mut chip8, 0xF030, 8, 10)[..2],
+        ["##....##", "##....##"]
+    );
+
+    let mut chip8 = Chip8::new();
+    chip8.set_font(FontSet::SuperChip);
+    assert_eq!(
+        draw_four(&mut chip8, 0xF030, 8, 10)[..2],
+        [".....##.", "....###."]
+    );
+    assert_eq!(
+        chip8.register(Register::I) as usize,
+        memory::BIG_FONT_ADDRESS + 40
+    );
+}
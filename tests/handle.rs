@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use chip_8_rs::audio::Voice;
+use chip_8_rs::cartridge::Variant;
+use chip_8_rs::handle::{Command, EmulatorHandle, Event, HandleConfig};
+use chip_8_rs::keyboard::KeypadView;
+use chip_8_rs::pacing::Speed;
+use chip_8_rs::Chip8;
+
+// Long enough for any event of these tests, short enough to fail fast.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn handle() -> EmulatorHandle {
+    let handle = EmulatorHandle::spawn(HandleConfig::default(), || {
+        let mut chip8 = Chip8::new();
+        chip8.set_variant(Variant::SuperChip);
+        // JP 0x200, so that a blank machine does not run into the font and
+        // fault before the test loads its ROM.
+        chip8.load_rom(&[0x12, 0x00]).unwrap();
+        chip8
+    });
+    handle.send(Command::SetSpeed(Speed::Uncapped));
+    handle
+}
+
+// The next event other than a frame or keypad.
+fn next_event(handle: &EmulatorHandle) -> Event {
+    loop {
+        match handle.wait_event(TIMEOUT).expect("an event") {
+            Event::Frame { .. } | Event::Keypad(_) => continue,
+            event => return event,
+        }
+    }
+}
+
+// Whether no event other than frames or keypads comes for a while.
+fn quiet(handle: &EmulatorHandle) -> bool {
+    while let Some(event) = handle.wait_event(Duration::from_millis(100)) {
+        if !matches!(event, Event::Frame { .. } | Event::Keypad(_)) {
+            return false;
+        }
+    }
+    true
+}
+
+#[test]
+fn frames_are_sent_when_the_screen_changes() {
+    let handle = handle();
+    // LD F, V0; DRW V0, V0, 5; JP 0x204
+    handle.send(Command::Load(vec![0xF0, 0x29, 0xD0, 0x05, 0x12, 0x04]));
+    // The top row of the 0 digit, after blank frames from before the load.
+    loop {
+        let Some(Event::Frame { frame, display }) = handle.wait_event(TIMEOUT) else {
+            panic!("expected a frame");
+        };
+        assert!(frame > 0);
+        if (0..4).all(|x| display.pixel(x, 0)) {
+            break;
+        }
+    }
+    // Nothing changes after that.
+    assert!(handle.wait_event(Duration::from_millis(100)).is_none());
+
+    handle.send(Command::Reset);
+    assert!(matches!(
+        handle.wait_event(TIMEOUT),
+        Some(Event::Frame { .. })
+    ));
+}
+
+#[test]
+fn key_waits_resume_on_key_events() {
+    let handle = handle();
+    // LD V0, K; LD V1, 2; LD ST, V1; JP 0x206
+    handle.send(Command::Load(vec![
+        0xF0, 0x0A, 0x61, 0x02, 0xF1, 0x18, 0x12, 0x06,
+    ]));
+    assert!(quiet(&handle));
+
+    // Fx0A waits for the key to be released.
+    handle.send(Command::Key(5, true));
+    assert!(quiet(&handle));
+    handle.send(Command::Key(5, false));
+    assert_eq!(next_event(&handle), Event::Beep(true));
+    assert_eq!(next_event(&handle), Event::Beep(false));
+}
+
+#[test]
+fn keypad_changes_are_reported() {
+    let handle = handle();
+    // LD V0, K; JP 0x202
+    handle.send(Command::Load(vec![0xF0, 0x0A, 0x12, 0x02]));
+    let keypad = |handle: &EmulatorHandle| loop {
+        match handle.wait_event(TIMEOUT).expect("an event") {
+            Event::Keypad(view) => return view,
+            _ => continue,
+        }
+    };
+    assert_eq!(keypad(&handle), KeypadView::new([false; 16], true));
+
+    handle.send(Command::Key(5, true));
+    let mut keys = [false; 16];
+    keys[5] = true;
+    assert_eq!(keypad(&handle), KeypadView::new(keys, true));
+    handle.send(Command::Key(5, false));
+    assert_eq!(keypad(&handle), KeypadView::new([false; 16], false));
+}
+
+#[test]
+fn pokes_write_into_memory() {
+    let handle = handle();
+    // CLS; LD I, 0x20A; DRW V0, V0, 1; JP 0x200; and an empty sprite
+    handle.send(Command::Load(vec![
+        0x00, 0xE0, 0xA2, 0x0A, 0xD0, 0x01, 0x12, 0x00, 0x00, 0x00, 0x00,
+    ]));
+    assert!(quiet(&handle));
+
+    handle.send(Command::Poke(0x20A, vec![0xC0]));
+    loop {
+        let Some(Event::Frame { display, .. }) = handle.wait_event(TIMEOUT) else {
+            panic!("expected a frame");
+        };
+        if display.pixel(0, 0) && display.pixel(1, 0) && !display.pixel(2, 0) {
+            break;
+        }
+    }
+    // Past the end of memory
+    handle.send(Command::Poke(0xFFFF, vec![0xFF; 4]));
+    assert!(quiet(&handle));
+}
+
+#[test]
+fn voice_changes_are_reported() {
+    let handle = EmulatorHandle::spawn(HandleConfig::default(), || {
+        let mut chip8 = Chip8::new();
+        chip8.set_variant(Variant::XoChip);
+        // JP 0x200
+        chip8.load_rom(&[0x12, 0x00]).unwrap();
+        chip8
+    });
+    handle.send(Command::SetSpeed(Speed::Uncapped));
+    let bits = [0xF0; 16];
+    // LD I, 0x20A; LD AUDIO; LD V0, 0x80; LD PITCH, V0; JP 0x208
+    let mut rom = vec![0xA2, 0x0A, 0xF0, 0x02, 0x60, 0x80, 0xF0, 0x3A, 0x12, 0x08];
+    rom.extend(bits);
+    handle.send(Command::Load(rom));
+    assert_eq!(
+        next_event(&handle),
+        Event::Voice(Voice::Pattern { bits, pitch: 0x80 })
+    );
+    assert!(quiet(&handle));
+
+    // Resets drop the pattern: a program which never loads one beeps.
+    // JP 0x200
+    handle.send(Command::Load(vec![0x12, 0x00]));
+    assert_eq!(next_event(&handle), Event::Voice(Voice::Beep));
+}
+
+#[test]
+fn errors_and_exits_are_reported() {
+    let handle = handle();
+    handle.send(Command::Load(vec![0; 0x1000]));
+    assert!(matches!(next_event(&handle), Event::Error(_)));
+
+    // EXIT
+    handle.send(Command::Load(vec![0x00, 0xFD]));
+    assert_eq!(next_event(&handle), Event::Exited);
+    // Unknown opcode
+    handle.send(Command::Load(vec![0xFF, 0xFF]));
+    assert!(matches!(next_event(&handle), Event::Error(_)));
+}
@@ -0,0 +1,100 @@
+use chip_8_rs::heatmap::{AccessMap, GRID_SIZE};
+use chip_8_rs::Chip8;
+
+// LD I, 0x300; LD V1, [I]; LD I, 0x300; LD [I], V0; JP 0x200
+const PROGRAM: [u8; 10] = [0xA3, 0x00, 0xF1, 0x65, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x00];
+
+// Run the program's loop `loops` times with the map enabled.
+fn run(loops: usize) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&PROGRAM).unwrap();
+    chip8.enable_access_map();
+    for _ in 0..loops * 5 {
+        chip8.step();
+    }
+    chip8
+}
+
+#[test]
+fn accesses_are_counted_per_byte() {
+    let chip8 = run(3);
+    let map = chip8.access_map().unwrap();
+    for addr in 0x200..0x20A {
+        assert_eq!(map.executes(addr), 3, "executes of {:#X}", addr);
+    }
+    assert_eq!(map.executes(0x20A), 0);
+
+    // LD V1, [I] reads two bytes, LD [I], V0 writes one.
+    assert_eq!((map.reads(0x300), map.reads(0x301)), (3, 3));
+    assert_eq!((map.writes(0x300), map.writes(0x301)), (3, 0));
+    assert_eq!(map.reads(0x302), 0);
+
+    // Loading the ROM came before the map.
+    assert_eq!(map.writes(0x200), 0);
+    assert_eq!(map.reads(0x200), 0);
+}
+
+#[test]
+fn maps_are_off_until_enabled() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&PROGRAM).unwrap();
+    chip8.step();
+    assert!(chip8.access_map().is_none());
+}
+
+#[test]
+fn addresses_past_the_grid_are_ignored() {
+    let mut map = AccessMap::new();
+    let end = GRID_SIZE * GRID_SIZE;
+    map.record_read(end);
+    map.record_write(end);
+    map.record_execute(end);
+    assert_eq!(map.reads(end), 0);
+    assert_eq!(map.writes(end), 0);
+    assert_eq!(map.executes(end), 0);
+    assert!(map.to_rgb().iter().all(|&channel| channel == 0));
+}
+
+#[test]
+fn maps_render_one_pixel_per_byte() {
+    let chip8 = run(3);
+    let rgb = chip8.access_map().unwrap().to_rgb();
+    assert_eq!(rgb.len(), GRID_SIZE * GRID_SIZE * 3);
+    let pixel = |addr: usize| [rgb[addr * 3], rgb[addr * 3 + 1], rgb[addr * 3 + 2]];
+
+    // Writes are red, reads green and executes blue, the busiest at full
+    // intensity.
+    assert_eq!(pixel(0x200), [0, 0, 255]);
+    assert_eq!(pixel(0x300), [255, 255, 0]);
+    assert_eq!(pixel(0x301), [0, 255, 0]);
+    assert_eq!(pixel(0x000), [0, 0, 0]);
+
+    // Fewer accesses are dimmer, but still visible.
+    let mut map = AccessMap::new();
+    for _ in 0..100 {
+        map.record_read(0x300);
+    }
+    map.record_read(0x301);
+    let rgb = map.to_rgb();
+    let green = |addr: usize| rgb[addr * 3 + 1];
+    assert_eq!(green(0x300), 255);
+    assert!((64..255).contains(&green(0x301)));
+}
+
+#[test]
+fn maps_render_one_character_per_byte() {
+    let mut map = AccessMap::new();
+    map.record_read(0x000);
+    map.record_write(0x001);
+    map.record_read(0x001);
+    map.record_execute(0x040);
+    map.record_execute(0xFFF);
+    map.record_write(0xFFF);
+
+    let text = map.to_text();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), GRID_SIZE);
+    assert_eq!(lines[0], format!("0x000 rw{}", ".".repeat(62)));
+    assert_eq!(lines[1], format!("0x040 x{}", ".".repeat(63)));
+    assert_eq!(lines[63], format!("0xFC0 {}X", ".".repeat(63)));
+}
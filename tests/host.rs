@@ -0,0 +1,70 @@
+use chip_8_rs::host::{self, BeepEvent, Headless};
+use chip_8_rs::recording::InputRecording;
+use chip_8_rs::Chip8;
+
+// LD V0, 10; LD ST, V0; LD I, sprite; DRW V0, V0, 1; JP 0x208; sprite: 0xFF
+const ROM: [u8; 11] = [
+    0x60, 0x0A, 0xF0, 0x18, 0xA2, 0x0A, 0xD0, 0x01, 0x12, 0x08, 0xFF,
+];
+
+#[test]
+fn headless_captures_the_screen_and_the_beeps() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&ROM).unwrap();
+    let mut headless = Headless::new();
+    headless.run(&mut chip8, 20, 12);
+
+    assert_eq!(headless.frames(), 20);
+    assert_eq!((headless.width(), headless.height()), (64, 32));
+    assert_eq!(headless.framebuffer(), chip8.framebuffer());
+    assert!((10..18).all(|x| headless.pixel(x, 10)));
+    assert!(!headless.pixel(18, 10));
+
+    assert_eq!(
+        headless.beeps(),
+        [
+            BeepEvent {
+                frame: 0,
+                sounding: true,
+            },
+            BeepEvent {
+                frame: 9,
+                sounding: false,
+            },
+        ]
+    );
+    assert!(!headless.is_sounding());
+}
+
+#[test]
+fn headless_input_follows_the_script_then_the_keys() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&ROM).unwrap();
+    let mut script = InputRecording::new();
+    script.push(0x0001);
+    script.push(0x0002);
+    let mut headless = Headless::with_input(script);
+    headless.set_keys(0x8000);
+
+    let mut pressed = Vec::new();
+    for _ in 0..3 {
+        headless.run(&mut chip8, 1, 12);
+        pressed.push(chip8.keys().iter().position(|&down| down));
+    }
+    assert_eq!(pressed, [Some(0x0), Some(0x1), Some(0xF)]);
+}
+
+#[test]
+fn run_frame_takes_separate_backends() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&ROM).unwrap();
+    let (mut display, mut audio, mut input) = (Headless::new(), Headless::new(), Headless::new());
+    input.set_keys(0x0010);
+    host::run_frame(&mut chip8, 12, &mut display, &mut audio, &mut input);
+
+    assert_eq!(display.frames(), 1);
+    assert_eq!(display.beeps(), []);
+    assert_eq!(audio.frames(), 0);
+    assert!(audio.is_sounding());
+    assert!(chip8.keys()[4]);
+}
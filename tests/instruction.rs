@@ -0,0 +1,43 @@
+use chip_8_rs::instruction::{decode, DecodeError, Instruction};
+
+#[test]
+fn every_decoded_opcode_encodes_back() {
+    for opcode in 0..=0xFFFF {
+        if let Ok(instruction) = decode(opcode) {
+            assert_eq!(instruction.encode(), opcode, "{:?}", instruction);
+        }
+    }
+}
+
+#[test]
+fn operands() {
+    assert_eq!(decode(0x1228), Ok(Instruction::Jump { addr: 0x228 }));
+    assert_eq!(decode(0x631F), Ok(Instruction::Load { x: 3, byte: 0x1F }));
+    assert_eq!(decode(0x8AB4), Ok(Instruction::Add { x: 0xA, y: 0xB }));
+    assert_eq!(decode(0xD015), Ok(Instruction::Draw { x: 0, y: 1, n: 5 }));
+    assert_eq!(
+        decode(0xE7A1),
+        Ok(Instruction::SkipIfKeyNotPressed { x: 7 })
+    );
+    assert_eq!(decode(0xF265), Ok(Instruction::LoadRegisters { x: 2 }));
+    assert_eq!(decode(0x00C4), Ok(Instruction::ScrollDown { n: 4 }));
+    assert_eq!(decode(0xF301), Ok(Instruction::SelectPlanes { n: 3 }));
+    assert_eq!(decode(0x0123), Ok(Instruction::Sys { addr: 0x123 }));
+}
+
+#[test]
+fn unknown_opcodes() {
+    for opcode in [0x5121, 0x800F, 0x9AB1, 0xE09F, 0xF0FF, 0xF100, 0xF102] {
+        assert_eq!(decode(opcode), Err(DecodeError(opcode)));
+    }
+    assert_eq!(DecodeError(0xF0FF).to_string(), "unknown opcode 0xF0FF");
+}
+
+#[test]
+fn extensions_and_branches() {
+    assert!(decode(0xF000).unwrap().is_xo_chip());
+    assert!(!decode(0xF030).unwrap().is_xo_chip());
+    assert!(decode(0x3A00).unwrap().is_branch());
+    assert!(decode(0xF00A).unwrap().is_branch());
+    assert!(!decode(0xA200).unwrap().is_branch());
+}
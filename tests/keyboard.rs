@@ -0,0 +1,67 @@
+use chip_8_rs::keyboard::{KeyCell, KeypadView};
+use chip_8_rs::Chip8;
+
+#[test]
+fn held_keys_are_shown_pressed() {
+    let mut chip8 = Chip8::new();
+    chip8.set_key(0x5, true);
+    chip8.set_key(0xF, true);
+    let view = chip8.keypad().view();
+    assert!(!view.waiting);
+
+    let pressed: Vec<KeyCell> = view.cells().filter(|cell| cell.pressed).collect();
+    assert_eq!(
+        pressed,
+        [
+            KeyCell {
+                row: 1,
+                column: 1,
+                key: 0x5,
+                pressed: true
+            },
+            KeyCell {
+                row: 3,
+                column: 3,
+                key: 0xF,
+                pressed: true
+            },
+        ]
+    );
+    assert_eq!(view.cells().count(), 16);
+    assert_eq!(
+        view.render(),
+        "\
++---+---+---+---+
+| 1 | 2 | 3 | C |
++---+---+---+---+
+| 4 |[5]| 6 | D |
++---+---+---+---+
+| 7 | 8 | 9 | E |
++---+---+---+---+
+| A | 0 | B |[F]|
++---+---+---+---+
+"
+    );
+}
+
+#[test]
+fn key_waits_are_shown_until_the_stroke_completes() {
+    let mut chip8 = Chip8::new();
+    // LD V0, K; JP 0x202
+    chip8.load_rom(&[0xF0, 0x0A, 0x12, 0x02]).unwrap();
+    chip8.run_frame(4);
+    let view = chip8.keypad().view();
+    assert_eq!(view, KeypadView::new([false; 16], true));
+    assert!(view.render().ends_with("waiting for a key (Fx0A)\n"));
+
+    // Held, the key is shown pressed while the program keeps waiting.
+    chip8.set_key(0x7, true);
+    chip8.run_frame(4);
+    let view = chip8.keypad().view();
+    assert!(view.waiting && view.keys[0x7]);
+    chip8.set_key(0x7, false);
+    chip8.run_frame(4);
+    let view = chip8.keypad().view();
+    assert_eq!(view, KeypadView::new([false; 16], false));
+    assert!(!view.render().contains("waiting"));
+}
@@ -0,0 +1,103 @@
+use std::ffi::{c_uint, c_void, CStr};
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use chip_8_rs::libretro::*;
+
+static VIDEO_SIZE: AtomicU32 = AtomicU32::new(0);
+static FIRST_PIXEL: AtomicU32 = AtomicU32::new(0);
+static AUDIO_FRAMES: AtomicUsize = AtomicUsize::new(0);
+static PIXEL_FORMAT: AtomicU32 = AtomicU32::new(u32::MAX);
+
+unsafe extern "C" fn environment(cmd: c_uint, data: *mut c_void) -> bool {
+    if cmd == 10 {
+        PIXEL_FORMAT.store(unsafe { *data.cast::<c_uint>() }, Ordering::SeqCst);
+    }
+    true
+}
+
+unsafe extern "C" fn video_refresh(data: *const c_void, width: c_uint, height: c_uint, _: usize) {
+    VIDEO_SIZE.store(width << 16 | height, Ordering::SeqCst);
+    FIRST_PIXEL.store(unsafe { *data.cast::<u32>() }, Ordering::SeqCst);
+}
+
+unsafe extern "C" fn audio_sample_batch(_: *const i16, frames: usize) -> usize {
+    AUDIO_FRAMES.store(frames, Ordering::SeqCst);
+    frames
+}
+
+unsafe extern "C" fn input_poll() {}
+
+// Holds A, which is mapped onto key 5.
+unsafe extern "C" fn input_state(port: c_uint, device: c_uint, _: c_uint, id: c_uint) -> i16 {
+    (port == 0 && device == 1 && id == 8) as i16
+}
+
+// LD V5, 5; SKP V5; JP 0x202; LD F, V5; DRW V0, V0, 5; JP 0x20A
+const ROM: &[u8] = &[
+    0x65, 0x05, 0xE5, 0x9E, 0x12, 0x02, 0xF5, 0x29, 0xD0, 0x05, 0x12, 0x0A,
+];
+
+fn load(rom: &[u8]) -> bool {
+    let game = GameInfo {
+        path: ptr::null(),
+        data: rom.as_ptr().cast(),
+        size: rom.len(),
+        meta: ptr::null(),
+    };
+    unsafe { retro_load_game(&game) }
+}
+
+#[test]
+fn core_runs_a_game() {
+    assert_eq!(retro_api_version(), 1);
+    let mut info = MaybeUninit::<SystemInfo>::uninit();
+    let info = unsafe {
+        retro_get_system_info(info.as_mut_ptr());
+        info.assume_init()
+    };
+    let name = unsafe { CStr::from_ptr(info.library_name) };
+    assert_eq!(name.to_str(), Ok("chip-8-rs"));
+    assert!(!info.need_fullpath);
+
+    retro_set_environment(environment);
+    retro_set_video_refresh(video_refresh);
+    retro_set_audio_sample_batch(audio_sample_batch);
+    retro_set_input_poll(input_poll);
+    retro_set_input_state(input_state);
+    retro_init();
+    assert!(load(ROM));
+    assert_eq!(PIXEL_FORMAT.load(Ordering::SeqCst), 1);
+
+    let mut av = MaybeUninit::<SystemAvInfo>::uninit();
+    let av = unsafe {
+        retro_get_system_av_info(av.as_mut_ptr());
+        av.assume_init()
+    };
+    assert_eq!(av.timing.fps, 60.0);
+    assert_eq!((av.geometry.max_width, av.geometry.max_height), (128, 64));
+    assert_eq!(retro_get_region(), 0);
+
+    retro_run();
+    // The digit 5 is drawn once key 5 is down, white on black.
+    assert_eq!(VIDEO_SIZE.load(Ordering::SeqCst), 64 << 16 | 32);
+    assert_eq!(FIRST_PIXEL.load(Ordering::SeqCst), 0x00FF_FFFF);
+    assert_eq!(
+        AUDIO_FRAMES.load(Ordering::SeqCst),
+        av.timing.sample_rate as usize / 60
+    );
+
+    let size = retro_serialize_size();
+    assert!(size > 0);
+    let mut state = vec![0u8; size];
+    assert!(unsafe { retro_serialize(state.as_mut_ptr().cast(), size) });
+    assert!(!unsafe { retro_serialize(state.as_mut_ptr().cast(), size - 1) });
+    retro_reset();
+    assert!(unsafe { retro_unserialize(state.as_ptr().cast(), size) });
+
+    retro_unload_game();
+    assert_eq!(retro_serialize_size(), 0);
+    assert!(!load(&[0; 0x10000]));
+    retro_deinit();
+}
@@ -0,0 +1,96 @@
+use chip_8_rs::fault::Chip8Error;
+use chip_8_rs::memory::{self, Memory, MemoryError};
+use chip_8_rs::{Chip8, Register};
+
+#[test]
+fn bounds() {
+    let mut memory = Memory::new();
+    assert_eq!(memory.size(), memory::SIZE);
+    assert_eq!(memory.write(0xFFF, 0x12), Ok(()));
+    assert_eq!(memory.read(0xFFF), Ok(0x12));
+    assert_eq!(memory.read(0x1000), Err(MemoryError::OutOfBounds(0x1000)));
+    assert_eq!(
+        memory.write(0x1000, 0),
+        Err(MemoryError::OutOfBounds(0x1000))
+    );
+
+    // Words need both bytes in memory.
+    memory.write(0xFFE, 0x34).unwrap();
+    assert_eq!(memory.read_word(0xFFE), Ok(0x3412));
+    assert_eq!(
+        memory.read_word(0xFFF),
+        Err(MemoryError::OutOfBounds(0x1000))
+    );
+
+    // Loads copy everything or nothing.
+    assert_eq!(memory.load(0xFFE, &[1, 2]), Ok(()));
+    assert_eq!(
+        memory.load(0xFFF, &[3, 4]),
+        Err(MemoryError::OutOfBounds(0x1000))
+    );
+    assert_eq!(memory.read(0xFFF), Ok(2));
+}
+
+#[test]
+fn write_protection() {
+    let mut memory = Memory::new();
+    assert!(memory.is_write_protected());
+    assert_eq!(memory.read(0x000), Ok(0xF0));
+    assert_eq!(memory.write(0x1FF, 1), Err(MemoryError::Protected(0x1FF)));
+    assert_eq!(memory.write(0x200, 1), Ok(()));
+
+    // The host still loads into the interpreter area.
+    assert_eq!(memory.load(0x1FF, &[1]), Ok(()));
+
+    memory.set_write_protect(false);
+    assert_eq!(memory.write(0x000, 0), Ok(()));
+    assert_eq!(memory.read(0x000), Ok(0));
+}
+
+#[test]
+fn protected_writes_fault() {
+    // LD I, 0x100; LD [I], V0
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&[0xA1, 0x00, 0xF0, 0x55]).unwrap();
+    chip8.set_register(Register::V(0), 0x2A).unwrap();
+    chip8.step();
+    let fault = chip8.execute(0xF055).unwrap_err();
+    assert_eq!(fault.kind, Chip8Error::WriteProtected(0x100));
+    assert_eq!(chip8.peek(0x100), Some(0));
+
+    chip8.set_write_protect(false);
+    assert_eq!(chip8.execute(0xF055), Ok(()));
+    assert_eq!(chip8.peek(0x100), Some(0x2A));
+
+    // Pokes ignore the protection.
+    chip8.set_write_protect(true);
+    assert!(chip8.poke(0x101, 0x17));
+    assert_eq!(chip8.peek(0x101), Some(0x17));
+}
+
+#[test]
+fn hex_digit_sprites() {
+    // LD V0, 0x1A; LD F, V0; DRW V1, V1, 5
+    let mut chip8 = Chip8::new();
+    chip8
+        .load_rom(&[0x60, 0x1A, 0xF0, 0x29, 0xD1, 0x15])
+        .unwrap();
+    chip8.run_frame(3);
+    assert_eq!(
+        chip8.register(Register::I) as usize,
+        memory::FONT_ADDRESS + 0xA * 5
+    );
+
+    let display = chip8.display();
+    let rows: Vec<String> = (0..5)
+        .map(|y| {
+            (0..4)
+                .map(|x| match display.pixels()[y * display.width() + x] {
+                    true => '#',
+                    false => '.',
+                })
+                .collect()
+        })
+        .collect();
+    assert_eq!(rows, ["####", "#..#", "####", "#..#", "#..#"]);
+}
@@ -0,0 +1,89 @@
+use chip_8_rs::memview::{MemoryEditor, MemoryView};
+use chip_8_rs::{Chip8, Register};
+
+fn editor() -> (Chip8, MemoryEditor) {
+    let mut chip8 = Chip8::new();
+    // LD I, 0x300; JP 0x202
+    chip8.load_rom(&[0xA3, 0x00, 0x12, 0x02]).unwrap();
+    chip8.run_frame(1);
+    let editor = MemoryEditor::new(chip8.memory().len(), 0x80);
+    (chip8, editor)
+}
+
+#[test]
+fn memory_slices_are_cut_off() {
+    let chip8 = Chip8::new();
+    let size = chip8.memory().len();
+    assert_eq!(chip8.memory_slice(0x200..0x204).len(), 4);
+    assert_eq!(chip8.memory_slice(size - 2..size + 2).len(), 2);
+    assert!(chip8.memory_slice(size + 1..size + 8).is_empty());
+}
+
+#[test]
+fn cursor_turns_pages() {
+    let mut view = MemoryView::new(0x1000, 0x80);
+    view.jump_to(0x27F);
+    view.move_cursor(1);
+    assert_eq!((view.cursor(), view.range()), (0x280, 0x280..0x300));
+    view.move_cursor(-0x1000);
+    assert_eq!((view.cursor(), view.page()), (0, 0));
+    view.move_cursor(0x2000);
+    assert_eq!(view.cursor(), 0xFFF);
+}
+
+#[test]
+fn keys_move_the_cursor() {
+    let (mut chip8, mut editor) = editor();
+    assert!(!editor.handle(&mut chip8, "Down"));
+    assert!(editor.handle(&mut chip8, "F8"));
+    assert!(editor.is_shown());
+    assert_eq!(editor.view().cursor(), 0x202);
+
+    editor.handle(&mut chip8, "Down");
+    editor.handle(&mut chip8, "Right");
+    assert_eq!(editor.view().cursor(), 0x213);
+    editor.handle(&mut chip8, "PageDown");
+    assert_eq!(editor.view().cursor(), 0x293);
+    editor.handle(&mut chip8, "I");
+    assert_eq!(editor.view().cursor(), 0x300);
+    editor.handle(&mut chip8, "p");
+    assert_eq!(editor.view().cursor(), 0x202);
+    // Keys the editor does not use reach the keypad.
+    assert!(!editor.handle(&mut chip8, "Q"));
+}
+
+#[test]
+fn digits_poke_bytes() {
+    let (mut chip8, mut editor) = editor();
+    editor.toggle(&chip8);
+    editor.handle(&mut chip8, "I");
+    editor.handle(&mut chip8, "A");
+    assert_eq!(editor.pending(), Some(0xA));
+    assert_eq!(editor.render(&chip8).last().unwrap(), "0:300 = 00 <- A_");
+    editor.handle(&mut chip8, "5");
+    assert_eq!(chip8.peek(0x300), Some(0xA5));
+    assert_eq!(editor.view().cursor(), 0x301);
+
+    // Moving drops a half typed byte.
+    editor.handle(&mut chip8, "F");
+    editor.handle(&mut chip8, "Left");
+    assert_eq!(editor.pending(), None);
+    assert_eq!(editor.render(&chip8).last().unwrap(), "0:300 = A5");
+
+    // The interpreter area can be edited despite its write protection.
+    editor.handle(&mut chip8, "PageUp");
+    editor.handle(&mut chip8, "0");
+    editor.handle(&mut chip8, "0");
+    assert_eq!(chip8.peek(0x280), Some(0));
+    assert_eq!(chip8.register(Register::I), 0x300);
+}
+
+#[test]
+fn rendered_pages_mark_the_cursor() {
+    let (chip8, mut editor) = editor();
+    editor.toggle(&chip8);
+    let lines = editor.render(&chip8);
+    assert_eq!(lines.len(), 9);
+    let (column, row) = editor.cursor_position();
+    assert_eq!(&lines[row][column - 1..column + 2], ">12");
+}
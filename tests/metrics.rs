@@ -0,0 +1,45 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+use chip_8_rs::cpu::Chip8;
+use chip_8_rs::metrics::{Metrics, MetricsServer};
+
+// A scrape over HTTP returns the telemetry counters.
+#[test]
+fn scrape() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&[0x12, 0x00]).unwrap();
+    chip8.run_frame(10);
+    let mut metrics = Metrics::new();
+    metrics.record_telemetry(chip8.telemetry());
+
+    let mut server = MetricsServer::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+    client
+        .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let reader = thread::spawn(move || {
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        response
+    });
+    while !reader.is_finished() {
+        server.poll(&metrics).unwrap();
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    let response = reader.join().unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(
+        response.contains("\nchip8_instructions_total 10\n"),
+        "{}",
+        response
+    );
+    assert!(
+        response.contains("# TYPE chip8_frames_total counter\n"),
+        "{}",
+        response
+    );
+}
@@ -0,0 +1,75 @@
+use chip_8_rs::movie::{Movie, MovieError, MovieSession};
+use chip_8_rs::Chip8;
+
+// Waits for a key (LD V0, K), then draws a random number of rows of the
+// font glyph at a position depending on the key, forever.
+const ROM: [u8; 12] = [
+    0xF0, 0x0A, 0xC1, 0x0F, 0xF0, 0x29, 0xD0, 0x05, 0x71, 0x01, 0x12, 0x00,
+];
+
+fn machine(seed: u64) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.set_seed(seed);
+    chip8.load_rom(&ROM).unwrap();
+    chip8
+}
+
+// Play a run by hand, pressing keys in some frames.
+fn record() -> (Movie, u64) {
+    let mut chip8 = machine(5);
+    let mut session = MovieSession::record(Movie::new(&ROM, 5, 8));
+    for frame in 0..120 {
+        chip8.set_key(0x7, frame % 30 < 4);
+        chip8.set_key(0xC, (60..70).contains(&frame));
+        session.before_frame(&mut chip8);
+        chip8.run_frame(8);
+    }
+    (session.into_movie(), chip8.state_hash())
+}
+
+#[test]
+fn replays_reproduce_the_run() {
+    let (movie, expected) = record();
+    assert_eq!(movie.frames(), 120);
+
+    // Whatever the machine was seeded with, the movie's seed is used.
+    let mut chip8 = machine(77);
+    let text = movie.to_string();
+    assert!(text.starts_with("# chip8 movie\nseed 5\ncycles 8\nrom "));
+    let parsed: Movie = text.parse().unwrap();
+    assert_eq!(parsed, movie);
+    parsed.play(&mut chip8);
+    assert_eq!(chip8.state_hash(), expected);
+
+    let mut chip8 = machine(77);
+    let mut session = MovieSession::replay(parsed, &mut chip8);
+    while !session.is_finished() {
+        session.before_frame(&mut chip8);
+        chip8.run_frame(8);
+    }
+    assert_eq!(chip8.state_hash(), expected);
+}
+
+#[test]
+fn movies_belong_to_their_rom() {
+    let (movie, _) = record();
+    assert_eq!(movie.check_rom(&ROM), Ok(()));
+    let error = movie.check_rom(&ROM[..10]).unwrap_err();
+    assert!(matches!(error, MovieError::WrongRom { .. }));
+}
+
+#[test]
+fn malformed_movies() {
+    assert_eq!(
+        "seed 1\nrom 00".parse::<Movie>(),
+        Err(MovieError::Header("cycles"))
+    );
+    assert_eq!(
+        "seed 1\ncycles 8\nrom 00\n0000\nzz".parse::<Movie>(),
+        Err(MovieError::Recording(5))
+    );
+    assert_eq!(
+        MovieError::Header("seed").to_string(),
+        "missing or invalid `seed` line"
+    );
+}
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use chip_8_rs::pacing::{FramePacer, Speed, SpeedHotkey};
+
+const SECOND: Duration = Duration::from_secs(1);
+
+#[test]
+fn speeds_scale_emulated_time() {
+    let mut pacer = FramePacer::new(60);
+    pacer.set_max_catch_up(1000);
+    let mut frames = |speed| {
+        pacer.set_speed(speed);
+        pacer.advance(SECOND)
+    };
+    assert_eq!(frames(Speed::Normal), 60);
+    assert_eq!(frames(Speed::Half), 30);
+    assert_eq!(frames(Speed::Quarter), 15);
+    assert_eq!(frames(Speed::Double), 120);
+    assert_eq!(frames(Speed::Quadruple), 240);
+    assert_eq!(frames(Speed::Paused), 0);
+
+    // Uncapped runs the catch-up limit whatever the time passed.
+    pacer.set_max_catch_up(15);
+    pacer.set_speed(Speed::Uncapped);
+    assert_eq!(pacer.advance(Duration::ZERO), 15);
+}
+
+#[test]
+fn slow_motion_carries_fractions_over() {
+    let mut pacer = FramePacer::new(60);
+    pacer.set_speed(Speed::Quarter);
+    let frames: u32 = (0..240)
+        .map(|_| pacer.advance(Duration::from_nanos(16_666_667)))
+        .sum();
+    assert_eq!(frames, 60);
+}
+
+#[test]
+fn hotkeys() {
+    let mut pacer = FramePacer::new(60);
+    assert_eq!(SpeedHotkey::from_name("F1"), Some(SpeedHotkey::Pause));
+    assert_eq!(SpeedHotkey::from_name("F4"), None);
+
+    assert_eq!(pacer.handle(SpeedHotkey::Faster), "Speed 2x");
+    assert_eq!(pacer.handle(SpeedHotkey::Pause), "Paused");
+    assert_eq!(pacer.advance(SECOND), 0);
+    assert_eq!(pacer.handle(SpeedHotkey::Pause), "Speed 2x");
+    for _ in 0..3 {
+        pacer.handle(SpeedHotkey::Faster);
+    }
+    assert_eq!(pacer.speed(), Speed::Uncapped);
+    for _ in 0..9 {
+        pacer.handle(SpeedHotkey::Slower);
+    }
+    assert_eq!(pacer.speed(), Speed::Quarter);
+
+    // Stepping out of the pause goes from the speed before it.
+    pacer.set_speed(Speed::Normal);
+    pacer.handle(SpeedHotkey::Pause);
+    assert_eq!(pacer.handle(SpeedHotkey::Slower), "Speed 0.5x");
+}
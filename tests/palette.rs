@@ -0,0 +1,54 @@
+use chip_8_rs::display::Display;
+use chip_8_rs::palette::{self, Palette};
+
+#[test]
+fn presets() {
+    assert_eq!(Palette::default(), Palette::CLASSIC);
+    for (name, palette) in Palette::PRESETS {
+        assert_eq!(name.parse(), Ok(palette));
+    }
+    assert_eq!(
+        "gameboy".parse::<Palette>().unwrap().background(),
+        [0x9B, 0xBC, 0x0F]
+    );
+    assert_eq!(
+        "sepia".parse::<Palette>(),
+        Err("unknown palette `sepia`".to_string())
+    );
+}
+
+#[test]
+fn custom_colors() {
+    // Two colors blend the planes between them.
+    let palette: Palette = "#000000, #FFFFFF".parse().unwrap();
+    assert_eq!(palette, Palette::CLASSIC);
+    assert_eq!(palette.color(2), [0x55, 0x55, 0x55]);
+    assert_eq!(palette.color(3), [0xAA, 0xAA, 0xAA]);
+
+    let spec = "#102010,#80FF80,#FF4040,#FFD040";
+    let palette: Palette = spec.parse().unwrap();
+    assert_eq!(palette.color(2), [0xFF, 0x40, 0x40]);
+    assert_eq!(palette.to_string(), spec);
+
+    assert!("#000000".parse::<Palette>().is_err());
+    assert_eq!(
+        palette::parse_color("#12345G"),
+        Err("expected a color as #RRGGBB, got `#12345G`".to_string())
+    );
+}
+
+#[test]
+fn rgba_per_plane() {
+    let mut display = Display::new();
+    display.select_planes(0b11);
+    // First plane then second plane: both, first only, second only.
+    display.draw_sprite(0, 0, &[0b1100_0000, 0b1010_0000]);
+
+    let rgba = Palette::GAMEBOY.rgba(&display);
+    assert_eq!(rgba.len(), display.width() * display.height() * 4);
+    let pixel = |x: usize| &rgba[x * 4..x * 4 + 4];
+    assert_eq!(pixel(0), [0x30, 0x62, 0x30, 0xFF]);
+    assert_eq!(pixel(1), [0x0F, 0x38, 0x0F, 0xFF]);
+    assert_eq!(pixel(2), [0x8B, 0xAC, 0x0F, 0xFF]);
+    assert_eq!(pixel(3), [0x9B, 0xBC, 0x0F, 0xFF]);
+}
@@ -0,0 +1,104 @@
+use chip_8_rs::display::{Display, Resolution};
+use chip_8_rs::palette::Palette;
+use chip_8_rs::phosphor::{Phosphor, PhosphorConfig};
+
+// The RGB of pixel `i` in an RGBA buffer.
+fn rgb(rgba: &[u8], i: usize) -> [u8; 3] {
+    [rgba[i * 4], rgba[i * 4 + 1], rgba[i * 4 + 2]]
+}
+
+// A pixel switched off fades towards the background, then goes out once it
+// leaves the frames kept.
+#[test]
+fn switched_off_pixels_fade_out() {
+    let mut phosphor = Phosphor::new(PhosphorConfig {
+        frames: 3,
+        decay: 0.5,
+    });
+    let mut display = Display::new();
+    display.draw_sprite(0, 0, &[0x80]);
+    let rgba = phosphor.present(&display, &Palette::CLASSIC);
+    assert_eq!(rgba.len(), 64 * 32 * 4);
+    assert_eq!(rgb(&rgba, 0), [0xFF; 3]);
+    assert_eq!(rgba[3], 0xFF);
+
+    display.draw_sprite(0, 0, &[0x80]);
+    assert_eq!(
+        rgb(&phosphor.present(&display, &Palette::CLASSIC), 0),
+        [128; 3]
+    );
+    assert_eq!(
+        rgb(&phosphor.present(&display, &Palette::CLASSIC), 0),
+        [64; 3]
+    );
+    assert_eq!(
+        rgb(&phosphor.present(&display, &Palette::CLASSIC), 0),
+        [0; 3]
+    );
+
+    // Fading goes towards the background of light palettes too.
+    let mut phosphor = Phosphor::new(PhosphorConfig::default());
+    display.draw_sprite(0, 0, &[0x80]);
+    phosphor.push(&display);
+    display.clear();
+    // Halfway from #F4F1E8 to #202020.
+    let faded = rgb(&phosphor.rgba(&display, &Palette::PAPER_WHITE), 0);
+    assert_eq!(faded, [0x8A, 0x89, 0x84]);
+}
+
+// Erasing and redrawing a sprite every other frame keeps it visible, and a
+// single frame shows the screen as is.
+#[test]
+fn erase_redraw_flicker_is_smoothed() {
+    let mut phosphor = Phosphor::new(PhosphorConfig::default());
+    let mut display = Display::new();
+    for frame in 0..10 {
+        if frame % 2 == 0 {
+            display.draw_sprite(0, 0, &[0x80]);
+        } else {
+            display.clear();
+        }
+        let shown = rgb(&phosphor.present(&display, &Palette::CLASSIC), 0);
+        assert!(shown[0] >= 128, "frame {}: {:?}", frame, shown);
+    }
+
+    let mut off = Phosphor::new(PhosphorConfig {
+        frames: 1,
+        ..PhosphorConfig::default()
+    });
+    display.draw_sprite(0, 0, &[0x80]);
+    off.present(&display, &Palette::AMBER);
+    display.clear();
+    assert_eq!(
+        off.present(&display, &Palette::AMBER),
+        Palette::AMBER.rgba(&display)
+    );
+}
+
+#[test]
+fn resolution_switches_start_over() {
+    let mut phosphor = Phosphor::new(PhosphorConfig::default());
+    let mut display = Display::new();
+    display.draw_sprite(0, 0, &[0xFF]);
+    phosphor.push(&display);
+    display.set_resolution(Resolution::R128x64);
+    let rgba = phosphor.rgba(&display, &Palette::CLASSIC);
+    assert_eq!(rgba, Palette::CLASSIC.rgba(&display));
+    assert_eq!(rgba.len(), 128 * 64 * 4);
+}
+
+#[test]
+fn settings_are_parsed() {
+    assert_eq!(
+        "3,0.25".parse(),
+        Ok(PhosphorConfig {
+            frames: 3,
+            decay: 0.25
+        })
+    );
+    assert_eq!("6".parse::<PhosphorConfig>().unwrap().frames, 6);
+    assert_eq!("6".parse::<PhosphorConfig>().unwrap().to_string(), "6,0.5");
+    assert!("0".parse::<PhosphorConfig>().is_err());
+    assert!("4,1.5".parse::<PhosphorConfig>().is_err());
+    assert!("fast".parse::<PhosphorConfig>().is_err());
+}
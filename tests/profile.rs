@@ -0,0 +1,82 @@
+use chip_8_rs::instruction::decode;
+use chip_8_rs::profile::Profile;
+use chip_8_rs::Chip8;
+
+fn profiled() -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.enable_profile();
+    // LD I, 0x050; DRW V0, V0, 5; JP 0x204
+    chip8
+        .load_rom(&[0xA0, 0x50, 0xD0, 0x05, 0x12, 0x04])
+        .unwrap();
+    chip8.run_frame(10);
+    chip8
+}
+
+#[test]
+fn counts_per_address_and_opcode() {
+    let chip8 = profiled();
+    let profile = chip8.profile().unwrap();
+    assert_eq!(profile.instructions(), 10);
+    assert_eq!(profile.count(0x200), 1);
+    assert_eq!(profile.count(0x204), 8);
+    assert_eq!(profile.hot_spots()[0], (0x204, 8));
+    assert_eq!(
+        profile.opcode_counts(),
+        [("1nnn", 8), ("Annn", 1), ("Dxyn", 1)]
+    );
+}
+
+#[test]
+fn draw_time_is_kept_apart() {
+    let mut profile = Profile::new();
+    profile.record_time(0xD015, std::time::Duration::from_millis(3));
+    profile.record_time(0x6001, std::time::Duration::from_millis(1));
+    profile.record_time(0xD123, std::time::Duration::from_millis(2));
+    assert_eq!(profile.draw_time().as_millis(), 5);
+    assert_eq!(profile.other_time().as_millis(), 1);
+}
+
+#[test]
+fn report_lists_the_busiest_opcodes_and_addresses() {
+    let chip8 = profiled();
+    let report = chip8.profile().unwrap().to_report(2);
+    assert!(report.starts_with("Instructions: 10\n"), "{}", report);
+    assert!(report.contains("\nOpcodes:\n  1nnn"), "{}", report);
+    assert!(report.contains("0x204  jp 0x204"), "{}", report);
+    // Only the busiest two of each.
+    assert!(!report.contains("Dxyn"), "{}", report);
+}
+
+#[test]
+fn patterns_name_opcodes() {
+    let pattern = |opcode| decode(opcode).unwrap().pattern();
+    assert_eq!(pattern(0xD015), "Dxyn");
+    assert_eq!(pattern(0x8AB4), "8xy4");
+    assert_eq!(pattern(0xF21E), "Fx1E");
+    assert_eq!(pattern(0x00E0), "00E0");
+}
+
+#[test]
+fn folded_stacks_follow_the_calls() {
+    let mut chip8 = Chip8::new();
+    chip8.enable_profile();
+    // CALL 0x206; JP 0x202; 0x0000; CALL 0x20A; RET; LD V0, 1; RET
+    chip8
+        .load_rom(&[
+            0x22, 0x06, 0x12, 0x02, 0x00, 0x00, 0x22, 0x0A, 0x00, 0xEE, 0x60, 0x01, 0x00, 0xEE,
+        ])
+        .unwrap();
+    chip8.run_frame(10);
+    assert_eq!(
+        chip8.profile().unwrap().to_folded(),
+        "\
+main;0x200 1
+main;0x202 5
+main;sub_0x206;0x206 1
+main;sub_0x206;0x208 1
+main;sub_0x206;sub_0x20A;0x20A 1
+main;sub_0x206;sub_0x20A;0x20C 1
+"
+    );
+}
@@ -0,0 +1,201 @@
+use proptest::prelude::*;
+
+use chip_8_rs::backend::{ExecutionBackend, Interpreter};
+use chip_8_rs::blocks::BlockTranslator;
+use chip_8_rs::cached::CachedInterpreter;
+use chip_8_rs::cartridge::Variant;
+use chip_8_rs::instruction::decode;
+use chip_8_rs::{Chip8, EmulationMode, Quirks, Register};
+
+fn backend(index: usize) -> Box<dyn ExecutionBackend> {
+    match index {
+        0 => Box::new(Interpreter),
+        1 => Box::new(CachedInterpreter::new()),
+        _ => Box::new(BlockTranslator::new()),
+    }
+}
+
+fn quirks() -> impl Strategy<Value = Quirks> {
+    any::<[bool; 4]>().prop_map(|[vf_reset, memory, shifting, jumping]| Quirks {
+        vf_reset,
+        memory,
+        shifting,
+        jumping,
+    })
+}
+
+fn variant() -> impl Strategy<Value = Variant> {
+    prop_oneof![
+        Just(Variant::Chip8),
+        Just(Variant::SuperChip),
+        Just(Variant::XoChip),
+    ]
+}
+
+// A machine with V0 to VF set from `v`, ready to execute an instruction.
+fn machine(quirks: Quirks, v: [u8; 16]) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.set_quirks(quirks);
+    chip8.load_rom(&[0x00, 0xE0]).unwrap();
+    for (x, value) in v.into_iter().enumerate() {
+        chip8
+            .set_register(Register::V(x as u8), value as u16)
+            .unwrap();
+    }
+    chip8
+}
+
+#[test]
+fn every_opcode_decodes_without_panicking() {
+    for opcode in 0..=u16::MAX {
+        if let Ok(instruction) = decode(opcode) {
+            assert_eq!(decode(instruction.encode()), Ok(instruction));
+        }
+    }
+}
+
+proptest! {
+    // Failures are kept as regular tests instead.
+    #![proptest_config(ProptestConfig {
+        failure_persistence: None,
+        ..ProptestConfig::default()
+    })]
+
+    // Random programs, anything from garbage to plausible code, never take
+    // the machine outside of its memory or stack, under any backend,
+    // variant, quirks or emulation mode.
+    #[test]
+    fn random_programs_keep_the_machine_consistent(
+        rom in prop::collection::vec(any::<u8>(), 2..256),
+        backend_index in 0..3usize,
+        variant in variant(),
+        quirks in quirks(),
+        strict in any::<bool>(),
+        keys in any::<u16>(),
+        seed in any::<u64>(),
+    ) {
+        let mut chip8 = Chip8::with_backend(backend(backend_index));
+        chip8.set_variant(variant);
+        chip8.set_quirks(quirks);
+        chip8.set_seed(seed);
+        if strict {
+            chip8.set_mode(EmulationMode::Strict);
+        }
+        chip8.load_rom(&rom).unwrap();
+        for frame in 0..20 {
+            chip8.set_keys(keys.rotate_left(frame));
+            chip8.run_frame(16);
+            let pc = chip8.register(Register::PC) as usize;
+            prop_assert!(pc <= chip8.memory().len(), "PC {:#X} left memory", pc);
+            prop_assert!(chip8.register(Register::SP) <= 16);
+            prop_assert!(chip8.register(Register::I) as usize <= 0xFFFF);
+            if strict && chip8.fault().is_some() {
+                prop_assert!(chip8.is_halted());
+            }
+        }
+    }
+
+    // Single steps agree with whole frames.
+    #[test]
+    fn stepping_matches_running_frames(
+        rom in prop::collection::vec(any::<u8>(), 2..128),
+        seed in any::<u64>(),
+    ) {
+        let mut framed = Chip8::new();
+        let mut stepped = Chip8::new();
+        for chip8 in [&mut framed, &mut stepped] {
+            chip8.set_seed(seed);
+            chip8.load_rom(&rom).unwrap();
+        }
+        framed.run_frame(64);
+        for _ in 0..64 {
+            stepped.step();
+        }
+        stepped.tick_timers();
+        prop_assert_eq!(framed.state_hash(), stepped.state_hash());
+    }
+
+    // 8xy4 - ADD Vx, Vy sets VF to the carry, which wins over the sum when
+    // x is F.
+    #[test]
+    fn add_sets_the_carry(x in 0..16u8, y in 0..16u8, v in any::<[u8; 16]>()) {
+        let mut chip8 = machine(Quirks::default(), v);
+        chip8.execute(0x8004 | (x as u16) << 8 | (y as u16) << 4).unwrap();
+        let (sum, carry) = v[x as usize].overflowing_add(v[y as usize]);
+        prop_assert_eq!(chip8.register(Register::V(0xF)), carry as u16);
+        if x != 0xF {
+            prop_assert_eq!(chip8.register(Register::V(x)), sum as u16);
+        }
+    }
+
+    // 8xy5 - SUB Vx, Vy and 8xy7 - SUBN Vx, Vy set VF to NOT borrow, so to
+    // 1 when both operands are equal.
+    #[test]
+    fn subtraction_sets_not_borrow(
+        x in 0..16u8,
+        y in 0..16u8,
+        v in any::<[u8; 16]>(),
+        reversed in any::<bool>(),
+    ) {
+        let mut chip8 = machine(Quirks::default(), v);
+        let kind = if reversed { 0x7 } else { 0x5 };
+        chip8.execute(0x8000 | (x as u16) << 8 | (y as u16) << 4 | kind).unwrap();
+        let (vx, vy) = (v[x as usize], v[y as usize]);
+        let (from, by) = if reversed { (vy, vx) } else { (vx, vy) };
+        let (difference, borrow) = from.overflowing_sub(by);
+        prop_assert_eq!(chip8.register(Register::V(0xF)), !borrow as u16);
+        if x != 0xF {
+            prop_assert_eq!(chip8.register(Register::V(x)), difference as u16);
+        }
+    }
+
+    // 8xy6 - SHR and 8xyE - SHL set VF to the bit shifted out, of Vx with
+    // the shifting quirk and of Vy without.
+    #[test]
+    fn shifts_set_the_bit_shifted_out(
+        x in 0..16u8,
+        y in 0..16u8,
+        v in any::<[u8; 16]>(),
+        quirks in quirks(),
+        left in any::<bool>(),
+    ) {
+        let mut chip8 = machine(quirks, v);
+        let kind = if left { 0xE } else { 0x6 };
+        chip8.execute(0x8000 | (x as u16) << 8 | (y as u16) << 4 | kind).unwrap();
+        let value = v[if quirks.shifting { x } else { y } as usize];
+        let (shifted, flag) = if left {
+            (value << 1, value >> 7)
+        } else {
+            (value >> 1, value & 0x1)
+        };
+        prop_assert_eq!(chip8.register(Register::V(0xF)), flag as u16);
+        if x != 0xF {
+            prop_assert_eq!(chip8.register(Register::V(x)), shifted as u16);
+        }
+    }
+}
+
+// 0000 is SYS 0, which does nothing, so an empty XO-CHIP program runs
+// through all 64KB of memory and wraps around to 0x0000.
+#[test]
+fn programs_wrap_around_the_end_of_memory() {
+    for index in 0..3 {
+        let mut chip8 = Chip8::with_backend(backend(index));
+        chip8.set_variant(Variant::XoChip);
+        chip8.load_rom(&[0x00, 0x00]).unwrap();
+        for _ in 0..(0x10000 - 0x200) / 2 {
+            chip8.step();
+        }
+        assert_eq!(chip8.register(Register::PC), 0, "{}", chip8.backend_name());
+    }
+}
+
+// Bnnn wraps around the end of memory instead of leaving it.
+#[test]
+fn offset_jumps_wrap_around_the_end_of_memory() {
+    // LD V0, 0x10; JP V0, 0xFF8
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&[0x60, 0x10, 0xBF, 0xF8]).unwrap();
+    chip8.run_frame(2);
+    assert_eq!(chip8.register(Register::PC), 0x008);
+}
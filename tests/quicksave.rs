@@ -0,0 +1,79 @@
+use std::env;
+use std::fs;
+
+use chip_8_rs::quicksave::{Hotkey, QuickSaves};
+use chip_8_rs::{Chip8, Register};
+
+fn counter() -> Chip8 {
+    let mut chip8 = Chip8::new();
+    // ADD V0, 1; JP 0x200
+    chip8.load_rom(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+    chip8
+}
+
+#[test]
+fn slots_save_and_load() {
+    let mut chip8 = counter();
+    let mut saves = QuickSaves::new(None);
+    assert_eq!(
+        saves.handle(&mut chip8, Hotkey::Load),
+        "Failed to load slot 1: the slot is empty"
+    );
+
+    chip8.run_frame(10);
+    assert_eq!(saves.handle(&mut chip8, Hotkey::Save), "Saved slot 1");
+    assert_eq!(saves.handle(&mut chip8, Hotkey::PreviousSlot), "Slot 4");
+    assert_eq!(saves.handle(&mut chip8, Hotkey::NextSlot), "Slot 1");
+
+    chip8.run_frame(10);
+    assert_eq!(saves.handle(&mut chip8, Hotkey::Load), "Loaded slot 1");
+    assert_eq!(chip8.register(Register::V(0)), 5);
+}
+
+#[test]
+fn slots_are_kept_next_to_the_rom() {
+    let dir = env::temp_dir().join(format!("chip8-quicksave-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let rom = dir.join("game.ch8");
+
+    let mut chip8 = counter();
+    chip8.run_frame(6);
+    let mut saves = QuickSaves::new(Some(&rom));
+    saves.handle(&mut chip8, Hotkey::NextSlot);
+    saves.save(&chip8).unwrap();
+    assert!(dir.join("game.ch8.state2").exists());
+
+    // A later session finds the slot again.
+    let mut chip8 = counter();
+    let mut saves = QuickSaves::new(Some(&rom));
+    saves.handle(&mut chip8, Hotkey::NextSlot);
+    saves.load(&mut chip8).unwrap();
+    assert_eq!(chip8.register(Register::V(0)), 3);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn slots_preview_their_thumbnail() {
+    let dir = env::temp_dir().join(format!("chip8-thumbnail-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let rom = dir.join("game.ch8");
+
+    let mut chip8 = Chip8::new();
+    // LD F, V0; DRW V0, V0, 5; JP 0x204
+    chip8
+        .load_rom(&[0xF0, 0x29, 0xD0, 0x05, 0x12, 0x04])
+        .unwrap();
+    let mut saves = QuickSaves::new(Some(&rom));
+    assert_eq!(saves.thumbnail(), None);
+    chip8.run_frame(3);
+    saves.save(&chip8).unwrap();
+    let thumbnail = saves.thumbnail().unwrap();
+    assert!(thumbnail.pixels[0] > 0);
+
+    // Read from the file by a later session, other slots being empty.
+    let mut saves = QuickSaves::new(Some(&rom));
+    assert_eq!(saves.thumbnail(), Some(thumbnail));
+    saves.handle(&mut chip8, Hotkey::NextSlot);
+    assert_eq!(saves.thumbnail(), None);
+    fs::remove_dir_all(dir).unwrap();
+}
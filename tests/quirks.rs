@@ -0,0 +1,76 @@
+use chip_8_rs::cartridge::Variant;
+use chip_8_rs::quirks::{self, Quirks};
+
+// Frames and instructions per frame detection runs for in these tests.
+const FRAMES: u64 = 30;
+const CYCLES: usize = 10;
+
+#[test]
+fn plain_chip8_roms_keep_the_default() {
+    // LD I, 0x050; DRW V0, V0, 5; JP 0x204
+    let rom = [0xA0, 0x50, 0xD0, 0x05, 0x12, 0x04];
+    let (quirks, scores) = quirks::detect(&rom, Variant::Chip8, FRAMES, CYCLES);
+    assert_eq!(quirks, Quirks::default());
+    assert_eq!(scores.len(), 4);
+    for score in scores {
+        assert_eq!((score.faults, score.first_fault), (0, None));
+        assert_eq!(score.penalty(FRAMES), 0);
+    }
+}
+
+#[test]
+fn roms_resetting_vf_are_cosmac_vip_roms() {
+    // LD VF, 8; OR V0, V1; LD V0, VF; LD V2, V0; JP V2, 0x20C: only with
+    // VF reset to 0 does the jump land on DRW V0, V0, 5; JP 0x20E, and not
+    // on 0xFFFF at 0x214.
+    let rom = [
+        0x6F, 0x08, 0x80, 0x11, 0x80, 0xF0, 0x82, 0x00, 0xB2, 0x0C, 0x00, 0x00, 0xD0, 0x05, 0x12,
+        0x0E, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF,
+    ];
+    let (quirks, scores) = quirks::detect(&rom, Variant::Chip8, FRAMES, CYCLES);
+    assert_eq!(quirks, Quirks::CHIP8);
+    let faulty = scores.iter().filter(|score| score.faults > 0).count();
+    assert_eq!(faulty, 3);
+}
+
+#[test]
+fn schip_roms_jump_with_vx() {
+    // HIGH; LD V0, 0x0E; LD V2, 0x0A; JP V2, 0x200: to DRW V0, V0, 5;
+    // JP 0x20C at 0x20A with SUPER-CHIP jumps, to 0xFFFF at 0x20E otherwise.
+    let rom = [
+        0x00, 0xFF, 0x60, 0x0E, 0x62, 0x0A, 0xB2, 0x00, 0x00, 0x00, 0xD0, 0x05, 0x12, 0x0C, 0xFF,
+        0xFF,
+    ];
+    let (quirks, scores) = quirks::detect(&rom, Variant::SuperChip, FRAMES, CYCLES);
+    assert_eq!(quirks, Quirks::SCHIP);
+    let schip = scores.iter().find(|score| score.quirks == Quirks::SCHIP);
+    assert_eq!(schip.map(|score| score.faults), Some(0));
+    assert!(scores
+        .iter()
+        .filter(|score| score.quirks != Quirks::SCHIP)
+        .all(|score| score.first_fault == Some(0)));
+}
+
+#[test]
+fn xo_chip_roms_are_probed_as_xo_chip() {
+    // LD I, long 0x230; LD V0, [I] twice, which loads the second byte of
+    // the table with I incremented; JP V0, 0x200 to 0x20C, or 0xFFFF at
+    // 0x22E without the increment. There, LD VF, 8; OR V0, V1; LD V0, VF;
+    // JP V0, 0x210 to DRW V0, V0, 5; JP 0x21A at 0x218 unless VF was reset,
+    // which loops without drawing.
+    let mut rom = vec![
+        0xF0, 0x00, 0x02, 0x30, 0xF0, 0x65, 0xF0, 0x65, 0xB2, 0x00, 0x00, 0x00, 0x6F, 0x08, 0x80,
+        0x11, 0x80, 0xF0, 0xB2, 0x10, 0x00, 0x00, 0x00, 0x00, 0xD0, 0x05, 0x12, 0x1A,
+    ];
+    rom.resize(0x2E, 0);
+    rom.extend([0xFF, 0xFF, 0x2E, 0x0C]);
+    let (quirks, scores) = quirks::detect(&rom, Variant::XoChip, FRAMES, CYCLES);
+    assert_eq!(quirks, Quirks::XOCHIP);
+    let best = scores.iter().find(|score| score.quirks == Quirks::XOCHIP);
+    assert_eq!(best.map(|score| score.penalty(FRAMES)), Some(0));
+
+    // As plain CHIP-8, the long load is no instruction under any preset.
+    let (quirks, scores) = quirks::detect(&rom, Variant::Chip8, FRAMES, CYCLES);
+    assert_eq!(quirks, Quirks::default());
+    assert!(scores.iter().all(|score| score.first_fault == Some(0)));
+}
@@ -0,0 +1,132 @@
+use chip_8_rs::ramsearch::{Condition, RamSearch, Width};
+use chip_8_rs::Chip8;
+
+#[test]
+fn searches_narrow_down_across_snapshots() {
+    let mut memory = [0u8; 8];
+    memory[2] = 3;
+    memory[5] = 3;
+    let mut search = RamSearch::new(&memory);
+    assert_eq!(search.candidates().len(), 8);
+
+    assert_eq!(search.filter(&memory, Condition::Equal(3)), 2);
+    assert_eq!(search.candidates(), [2, 5]);
+
+    // One of the two changes, the other one does not.
+    memory[2] = 2;
+    assert_eq!(search.filter(&memory, Condition::Changed), 1);
+    assert_eq!(search.results(), [(2, 2)]);
+
+    // Compared against the snapshot of the last search, not the first.
+    memory[5] = 9;
+    assert_eq!(search.filter(&memory, Condition::Unchanged), 1);
+    assert_eq!(search.results(), [(2, 2)]);
+    memory[2] = 1;
+    assert_eq!(search.filter(&memory, Condition::Unchanged), 0);
+}
+
+#[test]
+fn unchanged_and_changed_split_the_candidates() {
+    let before = [1, 2, 3, 4];
+    let after = [1, 7, 3, 0];
+    let mut unchanged = RamSearch::new(&before);
+    unchanged.filter(&after, Condition::Unchanged);
+    assert_eq!(unchanged.candidates(), [0, 2]);
+    let mut changed = RamSearch::new(&before);
+    changed.filter(&after, Condition::Changed);
+    assert_eq!(changed.candidates(), [1, 3]);
+
+    let mut increased = RamSearch::new(&before);
+    increased.filter(&after, Condition::Increased);
+    assert_eq!(increased.candidates(), [1]);
+    let mut decreased = RamSearch::new(&before);
+    decreased.filter(&after, Condition::Decreased);
+    assert_eq!(decreased.candidates(), [3]);
+}
+
+#[test]
+fn bytes_wrap_around() {
+    let mut search = RamSearch::new(&[0xFF, 0x00, 0x10]);
+    search.filter(&[0x00, 0xFF, 0x11], Condition::IncreasedBy(1));
+    assert_eq!(search.candidates(), [0, 2]);
+    let mut search = RamSearch::new(&[0xFF, 0x00, 0x10]);
+    search.filter(&[0x00, 0xFF, 0x11], Condition::DecreasedBy(1));
+    assert_eq!(search.candidates(), [1]);
+
+    // No byte holds a value past 0xFF.
+    let mut search = RamSearch::new(&[0x01]);
+    assert_eq!(search.filter(&[0x01], Condition::Equal(0x101)), 0);
+}
+
+#[test]
+fn words_are_read_big_endian() {
+    // A score of 0x01FF at 1, which goes up by one to 0x0200.
+    let mut memory = [0x00, 0x01, 0xFF, 0x00];
+    let mut search = RamSearch::with_width(&memory, Width::Word);
+    assert_eq!(search.width(), Width::Word);
+    // Words start anywhere but at the last byte.
+    assert_eq!(search.candidates(), [0, 1, 2]);
+    assert_eq!(search.filter(&memory, Condition::Equal(0x01FF)), 1);
+    memory[1..3].copy_from_slice(&[0x02, 0x00]);
+    assert_eq!(search.filter(&memory, Condition::IncreasedBy(1)), 1);
+    assert_eq!(search.results(), [(1, 0x0200)]);
+
+    // Byte searches see each byte on its own: the high byte went up by one
+    // and the low one wrapped around.
+    let mut search = RamSearch::new(&[0x00, 0x01, 0xFF, 0x00]);
+    search.filter(&memory, Condition::IncreasedBy(1));
+    assert_eq!(search.candidates(), [1, 2]);
+
+    // Words wrap around at 0xFFFF.
+    let mut search = RamSearch::with_width(&[0xFF, 0xFF], Width::Word);
+    assert_eq!(search.filter(&[0x00, 0x00], Condition::IncreasedBy(1)), 1);
+    assert_eq!(RamSearch::with_width(&[0], Width::Word).candidates(), []);
+}
+
+#[test]
+fn searches_find_a_counter_in_a_running_program() {
+    // LD I, 0x300; LD V0, 3; LD [I], V0; LD V5, 5; then a loop waiting
+    // for key 5, which decrements V0 and stores it again:
+    // SKP V5; JP 0x208; ADD V0, 0xFF; LD [I], V0; JP 0x208
+    let mut chip8 = Chip8::new();
+    chip8
+        .load_rom(&[
+            0xA3, 0x00, 0x60, 0x03, 0xF0, 0x55, 0x65, 0x05, 0xE5, 0x9E, 0x12, 0x08, 0x70, 0xFF,
+            0xF0, 0x55, 0x12, 0x08,
+        ])
+        .unwrap();
+    chip8.run_frame(10);
+    let mut search = RamSearch::new(chip8.memory());
+    search.filter(chip8.memory(), Condition::Equal(3));
+    for _ in 0..2 {
+        chip8.set_key(5, true);
+        chip8.run_frame(4);
+        chip8.set_key(5, false);
+        chip8.run_frame(10);
+        search.filter(chip8.memory(), Condition::Decreased);
+        chip8.run_frame(10);
+        search.filter(chip8.memory(), Condition::Unchanged);
+    }
+    assert_eq!(search.results(), [(0x300, 1)]);
+}
+
+#[test]
+fn conditions_parse() {
+    let cases = [
+        ("= 5", Condition::Equal(5)),
+        ("!= 0x1F", Condition::NotEqual(0x1F)),
+        ("=0x1234", Condition::Equal(0x1234)),
+        ("changed", Condition::Changed),
+        ("unchanged", Condition::Unchanged),
+        ("increased", Condition::Increased),
+        ("decreased", Condition::Decreased),
+        ("+1", Condition::IncreasedBy(1)),
+        ("-10", Condition::DecreasedBy(10)),
+    ];
+    for (text, condition) in cases {
+        assert_eq!(text.parse(), Ok(condition), "{}", text);
+    }
+    assert_eq!(Condition::Equal(5).to_string(), "= 5");
+    assert!("= 70000".parse::<Condition>().is_err());
+    assert!("bigger".parse::<Condition>().is_err());
+}
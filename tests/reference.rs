@@ -0,0 +1,113 @@
+use chip_8_rs::reference::{Mismatch, ReferenceStep, ReferenceTrace};
+use chip_8_rs::{asm, Chip8, Quirks, Register};
+
+fn chip8(source: &str) -> Chip8 {
+    let assembly = asm::assemble(source, "test.s", |name| Err(name.to_string())).unwrap();
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&assembly.rom).unwrap();
+    chip8
+}
+
+const SHIFTS: &str = "
+    ld v0, 3
+    ld dt, v0
+loop:
+    ld v1, 0x81
+    shr v2, v1
+    add v3, 1
+    jp loop";
+
+#[test]
+fn recorded_traces_match_their_own_run() {
+    let trace = ReferenceTrace::record(&mut chip8(SHIFTS), 5, 10);
+    assert_eq!(trace.steps.len(), 50);
+    assert_eq!(trace.steps[0], ReferenceStep::of(&chip8(SHIFTS)));
+    // Timers ticked after every 10 steps.
+    assert_eq!(trace.steps[10].get(Register::DT), Some(2));
+
+    let parsed: ReferenceTrace = trace.to_json().parse().unwrap();
+    assert_eq!(parsed, trace);
+    assert_eq!(parsed.compare(&mut chip8(SHIFTS), 10), Ok(50));
+}
+
+#[test]
+fn the_first_divergence_is_reported() {
+    let trace = ReferenceTrace::record(&mut chip8(SHIFTS), 2, 10);
+    let mut schip = chip8(SHIFTS);
+    schip.set_quirks(Quirks::SCHIP);
+    let mut cosmac = chip8(SHIFTS);
+    cosmac.set_quirks(Quirks::CHIP8);
+    let divergence = trace.compare(&mut cosmac, 10).unwrap_err();
+    assert_eq!(divergence.step, 4);
+    assert_eq!(divergence.frame, 0);
+    // Without the shifting quirk, SHR V2, V1 shifts V1 into V2.
+    assert_eq!(divergence.instruction, Some((0x206, 0x8216)));
+    assert_eq!(
+        divergence.mismatches,
+        [
+            Mismatch {
+                register: Register::V(2),
+                expected: 0,
+                actual: 0x40,
+            },
+            Mismatch {
+                register: Register::V(0xF),
+                expected: 0,
+                actual: 1,
+            }
+        ]
+    );
+    assert!(divergence
+        .to_string()
+        .starts_with("Diverged at step 4 (frame 0), after 0x206: 8216"));
+    assert_eq!(trace.compare(&mut schip, 10), Ok(20));
+
+    // Runs differing from the start blame no instruction.
+    let mut other = chip8("ld v0, 1");
+    other.set_register(Register::I, 0x300).unwrap();
+    let divergence = trace.compare(&mut other, 10).unwrap_err();
+    assert_eq!((divergence.step, divergence.instruction), (0, None));
+}
+
+#[test]
+fn traces_of_other_emulators_are_understood() {
+    // JSON Lines, registers in any case, hexadecimal strings, and keys
+    // which are not registers.
+    let trace: ReferenceTrace = "\
+        {\"PC\": \"0x200\", \"cycle\": 0, \"opcode\": \"6003\"}\n\
+        \n\
+        {\"pc\": 514, \"V0\": \"0x03\", \"meta\": {\"a\": [1.5, true, null, \"\\\"\"]}}\n"
+        .parse()
+        .unwrap();
+    assert_eq!(trace.steps.len(), 2);
+    assert_eq!(trace.steps[0].registers, [(Register::PC, 0x200)]);
+    assert_eq!(trace.steps[1].get(Register::V(0)), Some(3));
+    assert_eq!(trace.compare(&mut chip8(SHIFTS), 10), Ok(2));
+
+    let trace: ReferenceTrace = "[{\"v\": [1, 2]}, {\"i\": 16}]".parse().unwrap();
+    assert_eq!(
+        trace.steps[0].registers,
+        [(Register::V(0), 1), (Register::V(1), 2)]
+    );
+    assert_eq!(trace.steps[1].get(Register::I), Some(16));
+}
+
+#[test]
+fn malformed_traces_are_rejected() {
+    for (text, line, message) in [
+        ("[{\"pc\": 512},\n {\"pc\": 51x}]", 2, "unexpected `51x`"),
+        ("[{\"pc\": 512}", 1, "expected `]`"),
+        (
+            "{\"pc\": 512}\n{\"pc\": \"512\"}",
+            2,
+            "PC: expected a number",
+        ),
+        ("{\"v0\": 256}", 1, "does not fit in V0"),
+        ("[1]", 0, "step 0: expected an object"),
+        ("[{\"pc\": 512}] x", 1, "expected the end"),
+    ] {
+        let error = text.parse::<ReferenceTrace>().unwrap_err();
+        assert_eq!(error.line, line, "{}", text);
+        assert!(error.message.contains(message), "{}", error);
+    }
+}
@@ -0,0 +1,138 @@
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use chip_8_rs::reload::LiveReload;
+use chip_8_rs::{Chip8, Quirks, Register};
+
+// ADD V0, 1; JP 0x200
+const COUNT_BY_ONE: [u8; 4] = [0x70, 0x01, 0x12, 0x00];
+// ADD V0, 2; JP 0x200
+const COUNT_BY_TWO: [u8; 4] = [0x70, 0x02, 0x12, 0x00];
+
+fn rom_file(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("chip8-reload-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("game.ch8");
+    write(&path, &COUNT_BY_ONE, 0);
+    path
+}
+
+// Write the file with a modification time `version` seconds later, so the
+// change shows however coarse the file system's timestamps are.
+fn write(path: &Path, bytes: &[u8], version: u64) {
+    fs::write(path, bytes).unwrap();
+    File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000 + version))
+        .unwrap();
+}
+
+fn step(chip8: &mut Chip8, steps: usize) {
+    for _ in 0..steps {
+        chip8.step();
+    }
+}
+
+#[test]
+fn changed_roms_restart_with_the_same_settings() {
+    let path = rom_file("restart");
+    let mut chip8 = Chip8::new();
+    chip8.set_quirks(Quirks::SCHIP);
+    chip8.load_rom(&COUNT_BY_ONE).unwrap();
+    let mut watcher = LiveReload::new(&path);
+    assert_eq!(watcher.poll(&mut chip8), None);
+    step(&mut chip8, 5);
+
+    write(&path, &COUNT_BY_TWO, 1);
+    assert_eq!(watcher.poll(&mut chip8), Some(Ok(4)));
+    assert_eq!(watcher.poll(&mut chip8), None);
+    assert_eq!(chip8.register(Register::PC), 0x200);
+    assert_eq!(chip8.register(Register::V(0)), 0);
+    assert_eq!(chip8.quirks(), Quirks::SCHIP);
+    step(&mut chip8, 3);
+    assert_eq!(chip8.register(Register::V(0)), 4);
+
+    // Resets load the new program too.
+    chip8.reset(true);
+    assert_eq!(chip8.memory()[0x200..0x204], COUNT_BY_TWO);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn reloads_go_back_to_the_mark() {
+    let path = rom_file("mark");
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&COUNT_BY_ONE).unwrap();
+    let mut watcher = LiveReload::new(&path);
+    step(&mut chip8, 6);
+    watcher.set_mark(&chip8);
+    assert!(watcher.mark().is_some());
+    step(&mut chip8, 10);
+
+    write(&path, &COUNT_BY_TWO, 1);
+    assert_eq!(watcher.poll(&mut chip8), Some(Ok(4)));
+    assert_eq!(chip8.register(Register::V(0)), 3);
+    assert_eq!(chip8.register(Register::PC), 0x200);
+    assert_eq!(chip8.memory()[0x200..0x204], COUNT_BY_TWO);
+    step(&mut chip8, 1);
+    assert_eq!(chip8.register(Register::V(0)), 5);
+
+    watcher.clear_mark();
+    write(&path, &COUNT_BY_ONE, 2);
+    assert_eq!(watcher.poll(&mut chip8), Some(Ok(4)));
+    assert_eq!(chip8.register(Register::V(0)), 0);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn broken_builds_leave_the_machine_running() {
+    let path = rom_file("broken");
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&COUNT_BY_ONE).unwrap();
+    let mut watcher = LiveReload::new(&path);
+    step(&mut chip8, 3);
+    let before = chip8.snapshot();
+
+    write(&path, &vec![0; 0x1000], 1);
+    let error = watcher.poll(&mut chip8).unwrap().unwrap_err();
+    assert!(error.starts_with("Failed to load"), "{}", error);
+    assert_eq!(chip8.snapshot(), before);
+
+    // A missing file is waited for, then reloaded once it is back.
+    fs::remove_file(&path).unwrap();
+    assert_eq!(watcher.poll(&mut chip8), None);
+    write(&path, &COUNT_BY_TWO, 2);
+    assert_eq!(watcher.poll(&mut chip8), Some(Ok(4)));
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn changed_sources_are_reassembled() {
+    let path = rom_file("sources");
+    let source = path.with_extension("s");
+    write(&source, b"start:\n  ADD V0, 1\n  JP start\n", 0);
+    let mut chip8 = Chip8::new();
+    chip8.set_quirks(Quirks::SCHIP);
+    chip8.load_rom(&COUNT_BY_ONE).unwrap();
+    let mut watcher = LiveReload::with_sources(&path, vec![source.clone()]);
+    assert_eq!(watcher.poll(&mut chip8), None);
+    step(&mut chip8, 5);
+
+    write(&source, b"start:\n  ADD V0, 2\n  JP start\n", 1);
+    assert_eq!(watcher.poll(&mut chip8), Some(Ok(4)));
+    assert_eq!(fs::read(&path).unwrap(), COUNT_BY_TWO);
+    assert_eq!(chip8.memory()[0x200..0x204], COUNT_BY_TWO);
+    assert_eq!(chip8.register(Register::V(0)), 0);
+    assert_eq!(chip8.quirks(), Quirks::SCHIP);
+
+    // Errors leave the last good build running.
+    write(&source, b"start:\n  ADD V0\n", 2);
+    assert!(watcher.poll(&mut chip8).unwrap().is_err());
+    assert_eq!(fs::read(&path).unwrap(), COUNT_BY_TWO);
+    assert_eq!(chip8.memory()[0x200..0x204], COUNT_BY_TWO);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,137 @@
+use chip_8_rs::cartridge::Variant;
+use chip_8_rs::fault::Access;
+use chip_8_rs::{Chip8, Chip8Error, LoadAddress, Register, ResetHotkey, StepResult};
+
+// LD V0, 0x55; LD I, 0x20A; LD [I], V0; LD DT, V0; JP 0x208; then the byte
+// the program writes over
+const ROM: [u8; 11] = [
+    0x60, 0x55, 0xA2, 0x0A, 0xF0, 0x55, 0xF0, 0x15, 0x12, 0x08, 0x00,
+];
+
+fn ran() -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&ROM).unwrap();
+    chip8.run_frame(10);
+    assert_eq!(chip8.peek(0x20A), Some(0x55));
+    chip8
+}
+
+#[test]
+fn soft_resets_keep_memory() {
+    let mut chip8 = ran();
+    chip8.poke(0x000, 0x00);
+    chip8.set_key(0x4, true);
+    chip8.soft_reset();
+    assert_eq!(chip8.register(Register::PC), 0x200);
+    assert_eq!(chip8.register(Register::V(0)), 0);
+    assert_eq!(chip8.register(Register::I), 0);
+    assert_eq!(chip8.timers().delay, 0);
+    assert_eq!(chip8.peek(0x20A), Some(0x55));
+    // The font is put back, held keys stay held.
+    assert_eq!(chip8.peek(0x000), Some(0xF0));
+    assert!(chip8.keys()[0x4]);
+}
+
+#[test]
+fn resets_reload_the_rom() {
+    let mut chip8 = ran();
+    chip8.reset(true);
+    assert_eq!(chip8.register(Register::PC), 0x200);
+    assert_eq!(chip8.memory_slice(0x200..0x20B), ROM);
+    chip8.run_frame(10);
+    assert_eq!(chip8.register(Register::V(0)), 0x55);
+
+    assert_eq!(ResetHotkey::from_name("F11"), Some(ResetHotkey::Reset));
+    assert_eq!(ResetHotkey::from_name("Home"), Some(ResetHotkey::SoftReset));
+    let mut chip8 = ran();
+    assert_eq!(ResetHotkey::Reset.apply(&mut chip8), "Reset");
+    assert_eq!(chip8.peek(0x20A), Some(0x00));
+
+    // Without the ROM, memory is empty for the next one.
+    chip8.reset(false);
+    assert!(chip8
+        .memory_slice(0x200..0x20B)
+        .iter()
+        .all(|&byte| byte == 0));
+    chip8.reset(true);
+    assert!(chip8
+        .memory_slice(0x200..0x20B)
+        .iter()
+        .all(|&byte| byte == 0));
+    chip8.load_rom(&[0x61, 0x07]).unwrap();
+    chip8.run_frame(1);
+    assert_eq!(chip8.register(Register::V(1)), 7);
+}
+
+#[test]
+fn resets_leave_faults_behind() {
+    // 00FD exits
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&[0x00, 0xFD]).unwrap();
+    chip8.run_frame(1);
+    assert!(chip8.is_halted());
+    chip8.reset(true);
+    assert!(!chip8.is_halted());
+    assert_eq!(chip8.step(), StepResult::Exited);
+}
+
+#[test]
+fn paused_machines_stand_still() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&ROM).unwrap();
+    chip8.pause();
+    assert!(chip8.is_paused());
+    chip8.run_frame(10);
+    assert_eq!(chip8.step(), StepResult::Paused);
+    assert_eq!(chip8.register(Register::PC), 0x200);
+    assert_eq!(chip8.telemetry().frames, 0);
+
+    chip8.resume();
+    chip8.run_frame(10);
+    assert_eq!(chip8.timers().delay, 0x54);
+    chip8.pause();
+    chip8.tick_timers();
+    assert_eq!(chip8.timers().delay, 0x54);
+
+    // Pauses outlast soft resets.
+    chip8.soft_reset();
+    assert!(chip8.is_paused());
+}
+
+// The variant or load address can change after a ROM is loaded, leaving it
+// too large to be loaded again.
+fn assert_dropped(chip8: &Chip8) {
+    assert_eq!(
+        chip8.fault().map(|fault| fault.kind),
+        Some(Chip8Error::MemoryOutOfBounds {
+            access: Access::Write,
+            address: chip8.memory().len(),
+        })
+    );
+    let start = chip8.load_address().address() as usize;
+    assert!(chip8.memory()[start..].iter().all(|&byte| byte == 0));
+    assert_eq!(chip8.register(Register::PC), start as u16);
+}
+
+#[test]
+fn resets_drop_roms_which_no_longer_fit_the_memory() {
+    let mut chip8 = Chip8::new();
+    chip8.set_variant(Variant::XoChip);
+    chip8.load_rom(&[0x12; 0x2000]).unwrap();
+    chip8.set_variant(Variant::Chip8);
+    chip8.reset(true);
+    assert_dropped(&chip8);
+
+    // The ROM stays dropped.
+    chip8.reset(true);
+    assert_eq!(chip8.fault(), None);
+}
+
+#[test]
+fn resets_drop_roms_which_no_longer_fit_after_the_load_address() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&[0x12; 0xD00]).unwrap();
+    chip8.set_load_address(LoadAddress::Eti660);
+    chip8.reset(true);
+    assert_dropped(&chip8);
+}
@@ -0,0 +1,124 @@
+use chip_8_rs::cartridge::{Cartridge, Variant};
+use chip_8_rs::display::Resolution;
+use chip_8_rs::{Chip8, Register};
+
+// A Hires CHIP-8 ROM: the jump into the interpreter patch, the patch, then
+// the program at 0x2C0, which draws a dot on the last row of the 64x64
+// screen and clears it again.
+fn hires_rom() -> Vec<u8> {
+    let mut rom = vec![0x12, 0x60];
+    rom.resize(0xC0, 0xFF);
+    rom.extend([
+        0xA2, 0xCC, // 2C0: LD I, dot
+        0x61, 0x3F, // 2C2: LD V1, 63
+        0xD0, 0x11, // 2C4: DRW V0, V1, 1
+        0x02, 0x30, // 2C6: CLS (0230)
+        0x12, 0xC8, // 2C8: JP 2C8
+        0x00, 0x00, // 2CA
+        0x80, // 2CC: dot
+    ]);
+    rom
+}
+
+fn step(chip8: &mut Chip8, steps: usize) {
+    for _ in 0..steps {
+        chip8.step();
+    }
+}
+
+#[test]
+fn hires_chip8_programs_boot_into_64x64() {
+    let mut chip8 = Chip8::new();
+    chip8.set_variant(Variant::HiresChip8);
+    chip8.load_rom(&hires_rom()).unwrap();
+    assert_eq!(chip8.display().resolution(), Resolution::R64x64);
+    // The jump into the patch now jumps past it.
+    assert_eq!(chip8.memory()[0x200..0x202], [0x12, 0xC0]);
+    step(&mut chip8, 1);
+    assert_eq!(chip8.register(Register::PC), 0x2C0);
+
+    step(&mut chip8, 3);
+    assert!(chip8.display().pixel(0, 63));
+    assert_eq!(chip8.framebuffer().len(), 64 * 64);
+    step(&mut chip8, 1);
+    assert!(chip8.framebuffer().iter().all(|&on| !on));
+
+    // Resets keep the resolution and the redirected jump.
+    chip8.reset(true);
+    assert_eq!(chip8.display().resolution(), Resolution::R64x64);
+    assert_eq!(chip8.memory()[0x200..0x202], [0x12, 0xC0]);
+}
+
+#[test]
+fn other_variants_leave_the_boot_jump_and_0230_alone() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&hires_rom()).unwrap();
+    assert_eq!(chip8.display().resolution(), Resolution::R64x32);
+    step(&mut chip8, 1);
+    assert_eq!(chip8.register(Register::PC), 0x260);
+
+    // 0230 is a machine code routine of the COSMAC VIP, which is skipped.
+    chip8.set_register(Register::PC, 0x2C0).unwrap();
+    step(&mut chip8, 4);
+    assert!(chip8.display().pixel(0, 31));
+}
+
+#[test]
+fn low_resolutions_are_returned_to_from_128x64() {
+    let mut chip8 = Chip8::new();
+    chip8.set_resolution(Resolution::R64x48);
+    // HIGH; LOW; LD V1, 47; LD I, dot; DRW V0, V1, 2; dot: 80 80
+    chip8
+        .load_rom(&[
+            0x00, 0xFF, 0x00, 0xFE, 0x61, 0x2F, 0xA2, 0x0A, 0xD0, 0x12, 0x80, 0x80,
+        ])
+        .unwrap();
+    step(&mut chip8, 1);
+    assert!(chip8.display().is_hires());
+    step(&mut chip8, 1);
+    assert_eq!(chip8.display().resolution(), Resolution::R64x48);
+    assert_eq!(chip8.display().lores(), Resolution::R64x48);
+    step(&mut chip8, 3);
+    // The second row wraps around to the top.
+    assert!(chip8.display().pixel(0, 47) && chip8.display().pixel(0, 0));
+
+    chip8.soft_reset();
+    assert_eq!(chip8.display().resolution(), Resolution::R64x48);
+    // Variants start in their own resolution.
+    chip8.set_variant(Variant::SuperChip);
+    assert_eq!(chip8.display().resolution(), Resolution::R64x32);
+}
+
+#[test]
+fn save_states_keep_every_resolution() {
+    for resolution in Resolution::ALL {
+        let mut chip8 = Chip8::new();
+        chip8.set_variant(Variant::HiresChip8);
+        chip8.set_resolution(resolution);
+        // LD I, dot; DRW V0, V0, 1; dot: 80
+        chip8.load_rom(&[0xA2, 0x04, 0xD0, 0x01, 0x80]).unwrap();
+        step(&mut chip8, 2);
+        let state = chip8.save_state();
+
+        let mut restored = Chip8::new();
+        restored.load_state(&state).unwrap();
+        assert_eq!(restored.display().resolution(), resolution);
+        assert_eq!(restored.variant(), Variant::HiresChip8);
+        assert_eq!(restored.framebuffer(), chip8.framebuffer());
+        assert_eq!(restored.state_hash(), chip8.state_hash());
+    }
+}
+
+#[test]
+fn resolutions_and_the_variant_are_named() {
+    for (name, resolution) in [("64x32", Resolution::R64x32), ("64x48", Resolution::R64x48)] {
+        assert_eq!(name.parse(), Ok(resolution));
+        assert_eq!(resolution.to_string(), name);
+    }
+    assert!("32x64".parse::<Resolution>().is_err());
+
+    assert_eq!("hires".parse(), Ok(Variant::HiresChip8));
+    let mut cartridge = Cartridge::from_rom(&hires_rom());
+    cartridge.variant = Variant::HiresChip8;
+    assert_eq!(Cartridge::load(&cartridge.to_bytes()), Ok(cartridge));
+}
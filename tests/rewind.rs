@@ -0,0 +1,78 @@
+use chip_8_rs::rewind::{RewindBuffer, RewindConfig};
+use chip_8_rs::{Chip8, Register};
+
+// ADD V0, 1; JP 0x200, so V0 counts instructions.
+fn counter() -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+    chip8
+}
+
+#[test]
+fn buffers_keep_at_most_depth_states() {
+    let mut buffer = RewindBuffer::new(usize::MAX);
+    buffer.set_depth(3);
+    for i in 0..5u8 {
+        buffer.push(&[i; 8]);
+    }
+    assert_eq!(buffer.len(), 3);
+    assert_eq!(buffer.pop(), Some(vec![4; 8]));
+    assert_eq!(buffer.pop(), Some(vec![3; 8]));
+    assert_eq!(buffer.pop(), Some(vec![2; 8]));
+    assert_eq!(buffer.pop(), None);
+}
+
+#[test]
+fn rewinding_goes_back_frames() {
+    let mut chip8 = counter();
+    chip8.enable_rewind(RewindConfig::default());
+    for _ in 0..10 {
+        chip8.run_frame(2);
+    }
+    assert_eq!(chip8.register(Register::V(0)), 10);
+    assert_eq!(chip8.rewind_frames(), 9);
+
+    assert_eq!(chip8.rewind(3), 3);
+    assert_eq!(chip8.register(Register::V(0)), 7);
+
+    // The history records on from the restored frame.
+    chip8.run_frame(2);
+    assert_eq!(chip8.rewind(1), 1);
+    assert_eq!(chip8.register(Register::V(0)), 7);
+
+    // Going back further than the history stops at its start.
+    assert_eq!(chip8.rewind(100), 6);
+    assert_eq!(chip8.register(Register::V(0)), 1);
+}
+
+#[test]
+fn intervals_and_depth_bound_the_history() {
+    let mut chip8 = counter();
+    chip8.enable_rewind(RewindConfig {
+        depth: 4,
+        interval: 5,
+        ..RewindConfig::default()
+    });
+    for _ in 0..32 {
+        chip8.run_frame(2);
+    }
+    // The first frame is recorded right away, then every fifth: frames 16,
+    // 21, 26 and 31 are left, one frame before the current one.
+    assert_eq!(chip8.rewind_frames(), 16);
+    assert_eq!(chip8.rewind(7), 11);
+    assert_eq!(chip8.register(Register::V(0)), 21);
+
+    chip8.run_frame(2);
+    chip8.run_frame(2);
+    // Two frames after the newest state, rewinding one frame goes to it.
+    assert_eq!(chip8.rewind(1), 2);
+    assert_eq!(chip8.register(Register::V(0)), 21);
+}
+
+#[test]
+fn no_history_without_enabling_it() {
+    let mut chip8 = counter();
+    chip8.run_frame(2);
+    assert_eq!(chip8.rewind(1), 0);
+    assert_eq!(chip8.register(Register::V(0)), 1);
+}
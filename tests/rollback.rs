@@ -0,0 +1,90 @@
+use chip_8_rs::cartridge::Variant;
+use chip_8_rs::rollback::{RollbackError, RollbackSession};
+use chip_8_rs::{Chip8, Quirks};
+
+const CYCLES: usize = 40_000;
+
+// An XO-CHIP program rewriting 12KB of memory with random bytes over and
+// over, so that most of its 64KB state changes every frame, and counting
+// in VD the loops run with key 5 held.
+fn machine() -> Chip8 {
+    // LD I, 0x1000; LD VE, 5; SKNP VE; ADD VD, 1; RND V0, 0xFF;
+    // RND V1, 0xFF; RND V2, 0xFF; LD [I], V2; ADD VC, 1; SE VC, 0;
+    // JP 0x206; ADD VB, 1; SE VB, 16; JP 0x206; LD VB, 0; JP 0x200
+    let program: [u16; 17] = [
+        0xF000, 0x1000, 0x6E05, 0xEEA1, 0x7D01, 0xC0FF, 0xC1FF, 0xC2FF, 0xF255, 0x7C01, 0x3C00,
+        0x1206, 0x7B01, 0x3B10, 0x1206, 0x6B00, 0x1200,
+    ];
+    let rom: Vec<u8> = program.iter().flat_map(|op| op.to_be_bytes()).collect();
+    let mut chip8 = Chip8::new();
+    chip8.set_variant(Variant::XoChip);
+    chip8.set_quirks(Quirks::XOCHIP);
+    chip8.set_seed(7);
+    chip8.load_rom(&rom).unwrap();
+    chip8
+}
+
+// The remote player holds key 5 from frame 3 to 6.
+fn remote_mask(frame: u32) -> u16 {
+    if (3..=6).contains(&frame) {
+        1 << 5
+    } else {
+        0
+    }
+}
+
+#[test]
+fn late_input_rolls_back_to_the_on_time_state() {
+    let mut on_time = machine();
+    let mut session = RollbackSession::new(0, 0, 8);
+    for frame in 0..16 {
+        session.local_input(0);
+        session.remote_input(frame, remote_mask(frame)).unwrap();
+        session.advance(&mut on_time, CYCLES).unwrap();
+    }
+    assert_eq!(session.rollbacks(), 0);
+
+    // The inputs for frames 3 to 9 only arrive at frame 10, seven frames
+    // late: frames 3 to 6 ran on a wrong prediction.
+    let mut late = machine();
+    let mut session = RollbackSession::new(0, 0, 8);
+    for frame in 0..16 {
+        session.local_input(0);
+        match frame {
+            0..=2 | 11.. => session.remote_input(frame, remote_mask(frame)).unwrap(),
+            10 => {
+                for frame in 3..=10 {
+                    session.remote_input(frame, remote_mask(frame)).unwrap();
+                }
+            }
+            _ => {}
+        }
+        session.advance(&mut late, CYCLES).unwrap();
+    }
+    assert_eq!(session.rollbacks(), 1);
+    assert_eq!(session.frame(), 16);
+    assert_eq!(late.state_hash(), on_time.state_hash());
+    assert_eq!(late.memory(), on_time.memory());
+}
+
+#[test]
+fn input_past_the_window_is_too_late() {
+    let mut chip8 = machine();
+    let mut session = RollbackSession::new(1, 2, 4);
+    for _ in 0..8 {
+        session.local_input(0);
+        session.advance(&mut chip8, 100).unwrap();
+    }
+    assert_eq!(
+        session.remote_input(3, 1),
+        Err(RollbackError::TooLate {
+            frame: 3,
+            current: 8
+        })
+    );
+    // Within the window, the mispredicted frames are simulated again.
+    session.remote_input(4, 1).unwrap();
+    session.advance(&mut chip8, 100).unwrap();
+    assert_eq!(session.rollbacks(), 1);
+    assert_eq!(session.frame(), 9);
+}
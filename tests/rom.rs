@@ -0,0 +1,54 @@
+use std::path::Path;
+
+use chip_8_rs::{Chip8, LoadAddress, LoadError, Register};
+
+#[test]
+fn load_addresses() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&[0x60, 0x2A]).unwrap();
+    assert_eq!(chip8.register(Register::PC), 0x200);
+    assert_eq!(chip8.peek(0x201), Some(0x2A));
+
+    let mut chip8 = Chip8::new();
+    chip8.set_load_address(LoadAddress::Eti660);
+    chip8.load_rom(&[0x60, 0x2A]).unwrap();
+    assert_eq!(chip8.register(Register::PC), 0x600);
+    assert_eq!(chip8.peek(0x601), Some(0x2A));
+    chip8.step();
+    assert_eq!(chip8.register(Register::V(0)), 0x2A);
+}
+
+#[test]
+fn oversized_roms_are_rejected() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&[0xAA; 0xE00]).unwrap();
+
+    let mut chip8 = Chip8::new();
+    let err = chip8.load_rom(&[0xAA; 0xE01]).unwrap_err();
+    assert!(matches!(
+        err,
+        LoadError::TooLarge {
+            size: 0xE01,
+            capacity: 0xE00
+        }
+    ));
+    assert_eq!(chip8.peek(0x200), Some(0));
+
+    chip8.set_load_address(LoadAddress::Eti660);
+    assert!(chip8.load_rom(&[0xAA; 0xA01]).is_err());
+}
+
+#[test]
+fn from_file() {
+    let path = Path::new(env!("OUT_DIR")).join("roms/counter.ch8");
+    let chip8 = Chip8::from_file(&path).unwrap();
+    assert_eq!(
+        chip8.peek(0x200),
+        std::fs::read(&path).unwrap().first().copied()
+    );
+
+    assert!(matches!(
+        Chip8::from_file("tests/roms/missing.ch8"),
+        Err(LoadError::Io(_))
+    ));
+}
@@ -0,0 +1,205 @@
+use std::path::Path;
+use std::{env, fs};
+
+use chip_8_rs::cartridge::Variant;
+use chip_8_rs::rominfo::{sha1_hex, RomDatabase};
+use chip_8_rs::{splash, Quirks};
+
+const DATABASE: &str = r##"[
+  {
+    "title": "Plane Game",
+    "description": "Fly the \u00e9lan \"plane\" \ud83d\udee9\ufe0f\nover the sea.",
+    "release": "2021",
+    "authors": ["A. Author", "B. Author"],
+    "images": [],
+    "roms": {
+      "A9993E364706816ABA3E25717850C26C9CD0D89D": {
+        "file": "plane.ch8",
+        "platforms": ["megachip8", "superchip", "xochip"],
+        "tickrate": 30,
+        "quirkyPlatforms": {
+          "superchip": {"shift": false, "memoryLeaveIUnchanged": false, "wrap": true},
+          "xochip": {"jump": true}
+        },
+        "colors": {"pixels": ["#000000", "#ffcc00"], "buzzer": "#990000"}
+      },
+      "84983e441c3bd26ebaae4aa1f95129e5e54670f1": {
+        "platforms": ["originalChip8"],
+        "quirkyPlatforms": {"originalChip8": {"logic": false, "memoryLeaveIUnchanged": true}}
+      }
+    }
+  },
+  {"title": "Unsupported", "roms": {"da39a3ee5e6b4b0d3255bfef95601890afd80709": {"platforms": ["chip8x"]}}}
+]"##;
+
+#[test]
+fn sha1_matches_the_standard_vectors() {
+    assert_eq!(sha1_hex(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+    assert_eq!(sha1_hex(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+    // Padding spills into a second block.
+    assert_eq!(
+        sha1_hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+        "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+    );
+    assert_eq!(
+        sha1_hex(&vec![b'a'; 1_000_000]),
+        "34aa973cd4c4daa4f61eeb2bdbad27316534016f"
+    );
+}
+
+#[test]
+fn roms_are_found_by_their_hash() {
+    let database: RomDatabase = DATABASE.parse().unwrap();
+    assert_eq!(database.len(), 3);
+
+    let plane = database.lookup(b"abc").unwrap();
+    assert_eq!(plane.sha1, "a9993e364706816aba3e25717850c26c9cd0d89d");
+    assert_eq!(plane.title, "Plane Game");
+    assert_eq!(plane.authors, ["A. Author", "B. Author"]);
+    assert_eq!(plane.release.as_deref(), Some("2021"));
+    assert_eq!(
+        plane.description.as_deref(),
+        Some("Fly the \u{e9}lan \"plane\" \u{1F6E9}\u{FE0F}\nover the sea.")
+    );
+    assert_eq!(plane.file.as_deref(), Some("plane.ch8"));
+    // MEGA-CHIP is skipped for the first platform which is supported, and
+    // only its quirks apply.
+    assert_eq!(plane.platform.as_deref(), Some("superchip"));
+    assert_eq!(plane.variant, Some(Variant::SuperChip));
+    assert_eq!(
+        plane.quirks,
+        Some(Quirks {
+            shifting: false,
+            ..Quirks::SCHIP
+        })
+    );
+    assert_eq!(plane.tickrate, Some(30));
+    assert_eq!(plane.palette, Some([[0, 0, 0], [0xFF, 0xCC, 0x00]]));
+
+    let cosmac = database
+        .lookup_hash("84983E441C3BD26EBAAE4AA1F95129E5E54670F1")
+        .unwrap();
+    assert_eq!(cosmac.variant, Some(Variant::Chip8));
+    assert_eq!(
+        cosmac.quirks,
+        Some(Quirks {
+            vf_reset: false,
+            memory: false,
+            ..Quirks::CHIP8
+        })
+    );
+    assert_eq!((cosmac.tickrate, cosmac.palette), (None, None));
+
+    let unsupported = database.lookup(b"").unwrap();
+    assert_eq!(unsupported.platforms, ["chip8x"]);
+    assert_eq!((unsupported.variant, unsupported.quirks), (None, None));
+
+    assert_eq!(database.lookup(b"abd"), None);
+}
+
+#[test]
+fn databases_extend_the_bundled_one() {
+    let mut database = RomDatabase::bundled();
+    let splash = database.lookup(splash::ROM).unwrap().clone();
+    assert_eq!(splash.variant, Some(Variant::Chip8));
+
+    database.extend(DATABASE.parse().unwrap());
+    assert_eq!(database.len(), RomDatabase::bundled().len() + 3);
+    assert_eq!(database.lookup(splash::ROM), Some(&splash));
+
+    // Later entries replace earlier ones for the same ROM.
+    let renamed = format!(
+        "[{{\"title\": \"Renamed\", \"roms\": {{\"{}\": {{}}}}}}]",
+        sha1_hex(splash::ROM)
+    );
+    database.extend(renamed.parse().unwrap());
+    let renamed = database.lookup(splash::ROM).unwrap();
+    assert_eq!((renamed.title.as_str(), renamed.variant), ("Renamed", None));
+}
+
+#[test]
+fn databases_load_from_files_and_clones() {
+    let dir = env::temp_dir().join(format!("chip8-rominfo-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    // A clone of the community database keeps it under `database/`.
+    fs::create_dir_all(dir.join("database")).unwrap();
+    let file = dir.join("database/programs.json");
+    fs::write(&file, DATABASE).unwrap();
+
+    let expected: RomDatabase = DATABASE.parse().unwrap();
+    assert_eq!(RomDatabase::load(&file).unwrap(), expected);
+    assert_eq!(RomDatabase::load(&dir).unwrap(), expected);
+
+    let missing = RomDatabase::load(&dir.join("database")).unwrap_err();
+    assert_eq!(missing.line, 0);
+    assert!(missing.message.starts_with(&format!(
+        "cannot read {}",
+        dir.join("database/database").display()
+    )));
+    fs::write(&file, "{}").unwrap();
+    assert_eq!(
+        RomDatabase::load(&dir).unwrap_err().to_string(),
+        "expected an array of programs"
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn malformed_databases_are_rejected() {
+    let error = |text: &str| text.parse::<RomDatabase>().unwrap_err().to_string();
+    assert_eq!(error("{}"), "expected an array of programs");
+    assert_eq!(error("[\n{\"title\": 1,}]"), "line 2: expected a key");
+    assert_eq!(error("[{\"title\": 1}]"), "program 0: expected a string");
+    assert_eq!(error("[{}]"), "program 0: `roms` is not an object");
+    assert_eq!(
+        error("[{\"roms\": {\"abc\": {}}}]"),
+        "program 0: ROM abc: expected a SHA-1 in hexadecimal"
+    );
+    let rom = |fields: &str| {
+        error(&format!(
+            "[{{\"roms\": {{\"{}\": {{{}}}}}}}]",
+            sha1_hex(b""),
+            fields
+        ))
+    };
+    assert!(rom("\"tickrate\": -1").ends_with("`tickrate` is not a number of instructions"));
+    assert!(
+        rom("\"colors\": {\"pixels\": [\"red\", \"blue\"]}").ends_with("`pixels` is not colors")
+    );
+    assert!(
+        rom("\"platforms\": [\"xochip\"], \"quirkyPlatforms\": {\"xochip\": {\"shift\": 1}}")
+            .ends_with("quirks are not true or false")
+    );
+}
+
+#[test]
+fn the_bundled_test_roms_are_known() {
+    let database = RomDatabase::bundled();
+    assert_eq!(database.len(), 6);
+    let path = Path::new(env!("OUT_DIR")).join("roms/counter.ch8");
+    let counter = database.lookup(&fs::read(path).unwrap()).unwrap();
+    assert_eq!(counter.sha1, "b425489fda2619c3ee04f6c765e2fa8bc62f78f1");
+    assert_eq!(counter.title, "Counter test");
+    assert_eq!(counter.file.as_deref(), Some("counter.ch8"));
+    assert_eq!(counter.variant, Some(Variant::Chip8));
+    assert_eq!(counter.tickrate, Some(12));
+    assert_eq!(
+        database.lookup_hash("B425489FDA2619C3EE04F6C765E2FA8BC62F78F1"),
+        Some(counter)
+    );
+
+    let quirks = database
+        .lookup_hash("e31102770297b95cef8359b4a8f6cd8ee14d88d2")
+        .unwrap();
+    assert_eq!(quirks.platform.as_deref(), Some("originalChip8"));
+    assert_eq!(quirks.quirks, Some(Quirks::CHIP8));
+
+    for name in ["bcd", "quirks", "selfmod", "timer"] {
+        let path = Path::new(env!("OUT_DIR"))
+            .join("roms")
+            .join(format!("{}.ch8", name));
+        let rom = fs::read(path).unwrap();
+        let info = database.lookup(&rom).unwrap();
+        assert_eq!(info.file, Some(format!("{}.ch8", name)));
+    }
+}
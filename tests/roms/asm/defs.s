@@ -0,0 +1,3 @@
+%define STEPS 3
+%define MASK 0x0F
+%define TABLE_VALUE STEPS * 7           ; 21
@@ -0,0 +1,25 @@
+; Exercises the assembler's preprocessor: includes, macros with local
+; labels, constants and expressions.
+%include "defs.s"
+
+%macro countdown reg, from
+    ld reg, from
+%%loop:
+    add reg, -1
+    se reg, 0
+    jp %%loop
+%endmacro
+
+start:
+    countdown v0, STEPS
+    countdown v1, STEPS * 2
+    ld v2, (MASK << 2) | 1          ; 0x3D
+    ld v3, ~MASK & 0xFF             ; 0xF0
+    ld v4, end - start              ; size of the program
+    ld i, table + 1
+    ld v0, [i]                      ; 21
+end:
+    jp end
+
+table:
+    db 1, TABLE_VALUE, 3
@@ -0,0 +1,7 @@
+; Stores 156 as BCD at 0x300 and reads the digits back into V0 to V2.
+    ld va, 156
+    ld i, 0x300
+    ld b, va
+    ld v2, [i]
+halt:
+    jp halt
@@ -0,0 +1,4 @@
+; Increments V0 with every other instruction.
+start:
+    add v0, 1
+    jp start
@@ -0,0 +1,5 @@
+; Counts the key strokes read by Fx0A in V1, with the last key in V0.
+start:
+    ld v0, k
+    add v1, 1
+    jp start
@@ -0,0 +1,6 @@
+%define BALL_HEIGHT ball_end - ball
+
+; Load the first rows of the ball into V0 to V2.
+load_ball:
+    ld v2, [i]
+    ret
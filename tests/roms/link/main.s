@@ -0,0 +1,7 @@
+; Linked with draw.s and ball.bin, see tests/scenarios/link.scenario.
+start:
+    ld i, ball
+    ld v0, BALL_HEIGHT
+    call load_ball
+hang:
+    jp hang
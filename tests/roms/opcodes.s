@@ -0,0 +1,96 @@
+; An opcode test in the style of the public test ROMs: every test draws a
+; check mark when the instruction behaved and a cross when it did not, eight
+; to a row. V8 and V9 hold the position of the next mark, VA the result.
+
+%macro expect reg, value
+    ld va, 0
+    sne reg, value
+    ld va, 1
+    ld i, cross
+    se va, 0
+    ld i, check
+    drw v8, v9, 5
+    add v8, 8
+    se v8, 64
+    jp %%done
+    ld v8, 0
+    add v9, 6
+%%done:
+%endmacro
+
+start:
+    ld v8, 0
+    ld v9, 0
+
+    ; 7xkk, with and without wrapping
+    ld v0, 5
+    add v0, 3
+    expect v0, 8
+    ld v0, 0xFF
+    add v0, 2
+    expect v0, 1
+
+    ; 8xy1, 8xy2, 8xy3
+    ld v0, 0x0C
+    ld v1, 0x0A
+    or v0, v1
+    expect v0, 0x0E
+    ld v0, 0x0C
+    and v0, v1
+    expect v0, 0x08
+    ld v0, 0x0C
+    xor v0, v1
+    expect v0, 0x06
+
+    ; 8xy4 with a carry
+    ld v0, 0xF0
+    ld v1, 0x20
+    add v0, v1
+    ld v3, vf                       ; drawing the mark overwrites VF
+    expect v0, 0x10
+    expect v3, 1
+
+    ; 8xy5 without a borrow
+    ld v0, 5
+    ld v1, 3
+    sub v0, v1
+    ld v3, vf
+    expect v0, 2
+    expect v3, 1
+
+    ; 5xy0 and 9xy0
+    ld v0, 7
+    ld v1, 7
+    ld v2, 0
+    se v0, v1
+    ld v2, 1
+    expect v2, 0
+    ld v2, 0
+    sne v0, v1
+    ld v2, 1
+    expect v2, 1
+
+    ; Fx33, Fx55 and Fx65
+    ld v0, 123
+    ld i, buffer
+    ld b, v0
+    ld v2, [i]
+    expect v0, 1
+    expect v1, 2
+    expect v2, 3
+
+    ; Fx07 reads what Fx15 set
+    ld v0, 10
+    ld dt, v0
+    ld v1, dt
+    expect v1, 10
+
+halt:
+    jp halt
+
+check:
+    db 0x01, 0x02, 0x84, 0x48, 0x30
+cross:
+    db 0x88, 0x50, 0x20, 0x50, 0x88
+buffer:
+    db 0, 0, 0
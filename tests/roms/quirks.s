@@ -0,0 +1,24 @@
+; Runs one instruction per quirk, leaving the results in registers and
+; memory: 8xy1 resets VF or keeps it (VC), 8xy6 shifts Vy or Vx in place
+; (V3), Fx55 advances I or leaves it alone, and Bnnn jumps relative to V0
+; (VA set) or to V2 (VB set).
+    ld v0, 5
+    ld v1, 3
+    ld v2, 0x81
+    ld vf, 7
+    or v0, v1
+    ld vc, vf
+    shr v3, v2
+    ld i, 0x300
+    ld [i], v1
+    ld v0, 2
+    ld v2, 4
+    jp v0, targets
+
+    org 0x220
+targets:
+    dw 0                ; + 0, never jumped to
+    ld va, 1            ; + V0
+    ld vb, 1            ; + V2
+halt:
+    jp halt
@@ -0,0 +1,19 @@
+; A hot loop rewrites its own ADD V3, 1 into ADD V3, 2 after 32 iterations,
+; then runs 32 more, so V3 ends up at 32 * 1 + 32 * 2.
+    ld v0, 0x73         ; ADD V3, 2
+    ld v1, 2
+    ld i, loop
+loop:
+    add v3, 1
+    add v2, 1
+    se v2, 32
+    jp loop
+    se v4, 1
+    jp rewrite
+halt:
+    jp halt
+rewrite:
+    add v4, 1
+    ld [i], v1
+    ld v2, 0
+    jp loop
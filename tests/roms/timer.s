@@ -0,0 +1,6 @@
+; Loads the delay timer with 60 and reads it into V1 as it counts down.
+    ld v0, 60
+    ld dt, v0
+wait:
+    ld v1, dt
+    jp wait
@@ -0,0 +1,13 @@
+; Stores a byte past the 4KB of CHIP-8 memory and reads it back, through
+; XO-CHIP long I loads.
+start:
+    ld i, long 0x8000
+    ld v0, 0x2A
+    ld [i], v0
+    ld i, long 0x8000
+    ld v0, 0
+    ld v0, [i]
+    se v0, 0x2A
+    ld i, long 0                    ; skipped whole
+halt:
+    jp halt
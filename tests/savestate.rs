@@ -0,0 +1,216 @@
+use std::path::Path;
+
+use chip_8_rs::savestate::{MachineState, SaveStateError, Thumbnail, VERSION};
+use chip_8_rs::{Chip8, Register};
+
+#[cfg(feature = "serde")]
+#[test]
+fn states_serialize_with_serde() {
+    let mut chip8 = Chip8::new();
+    chip8.set_seed(1);
+    // LD V3, 0x2A; LD I, 0x300; LD [I], V3; DRW V0, V0, 5; CALL 0x20A
+    chip8
+        .load_rom(&[0x63, 0x2A, 0xA3, 0x00, 0xF3, 0x55, 0xD0, 0x05, 0x22, 0x0A])
+        .unwrap();
+    chip8.set_key(0xB, true);
+    chip8.run_frame(5);
+    let state = chip8.snapshot();
+
+    let json = serde_json::to_string(&state).unwrap();
+    let decoded: MachineState = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, state);
+
+    let mut restored = Chip8::new();
+    restored.restore(&decoded);
+    assert_eq!(restored.register(Register::V(3)), 0x2A);
+    assert_eq!(restored.register(Register::SP), 1);
+    assert!(restored.keys()[0xB]);
+    assert_eq!(restored.state_hash(), chip8.state_hash());
+}
+
+// The program the fixtures in `tests/savestates` were saved from, by the
+// emulator as it was at each format version, after one frame of 20
+// instructions with key B held:
+// LD V3, 0x2A; LD V4, 5; LD DT, V4; LD I, 0x300; LD B, V3; CALL 0x20E;
+// JP 0x20C; DRW V0, V0, 3; JP 0x210
+const FIXTURE_ROM: [u8; 18] = [
+    0x63, 0x2A, 0x64, 0x05, 0xF4, 0x15, 0xA3, 0x00, 0xF3, 0x33, 0x22, 0x0E, 0x12, 0x0C, 0xD0, 0x03,
+    0x12, 0x10,
+];
+
+fn fixture(version: u8) -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/savestates")
+        .join(format!("v{}.c8s", version));
+    std::fs::read(path).unwrap()
+}
+
+// The state the fixture program is in today, less what version `version`
+// did not save yet.
+fn expected(version: u8) -> MachineState {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&FIXTURE_ROM).unwrap();
+    chip8.set_key(0xB, true);
+    chip8.run_frame(20);
+    let mut state = chip8.snapshot();
+    if version < 3 {
+        state.framebuffer.fill(false);
+        state.thumbnail = None;
+    }
+    if version < 6 {
+        state.rng = None;
+    }
+    state
+}
+
+#[test]
+fn every_older_version_loads_to_the_same_state() {
+    for version in 1..VERSION {
+        let state = MachineState::decode(&fixture(version))
+            .unwrap_or_else(|e| panic!("version {}: {}", version, e));
+        let mut expected = expected(version);
+        // The font moved within the interpreter area over the versions.
+        expected.memory[..0x200].copy_from_slice(&state.memory[..0x200]);
+        // The generator was seeded from the operating system.
+        if version >= 6 {
+            assert!(state.rng.is_some(), "version {}", version);
+            expected.rng = state.rng;
+        }
+        assert_eq!(state, expected, "version {}", version);
+        assert_eq!(state.stack_pointer, 1, "version {}", version);
+        assert_eq!(state.stack[0], 0x20C, "version {}", version);
+        assert_eq!(state.memory[0x300..0x303], [0, 4, 2], "version {}", version);
+    }
+    // From version 3 on, the sprite drawn is in the state.
+    let state = MachineState::decode(&fixture(3)).unwrap();
+    assert!(state.framebuffer[64 + 5] && state.framebuffer[2 * 64 + 6]);
+
+    let mut chip8 = Chip8::new();
+    chip8.load_state(&fixture(1)).unwrap();
+    assert_eq!(chip8.register(Register::PC), 0x210);
+    assert_eq!(chip8.register(Register::V(3)), 0x2A);
+    assert_eq!(chip8.timers().delay, 4);
+    assert!(chip8.keys()[0xB]);
+}
+
+#[test]
+fn current_states_round_trip() {
+    let state = expected(VERSION);
+    assert_eq!(MachineState::decode(&state.encode()), Ok(state));
+}
+
+#[test]
+fn newer_versions_are_rejected() {
+    let mut bytes = expected(VERSION).encode();
+    bytes[3] = VERSION + 1;
+    assert_eq!(
+        MachineState::decode(&bytes),
+        Err(SaveStateError::TooNew(VERSION + 1))
+    );
+}
+
+#[test]
+fn versions_before_the_first_are_unsupported() {
+    let mut bytes = fixture(1);
+    bytes[3] = 0;
+    assert_eq!(
+        MachineState::decode(&bytes),
+        Err(SaveStateError::Unsupported(0))
+    );
+}
+
+#[test]
+fn damaged_states_are_corrupt() {
+    for version in 1..=VERSION {
+        let bytes = match version {
+            VERSION => expected(VERSION).encode(),
+            _ => fixture(version),
+        };
+        // Cut short in the middle of memory, or with trailing bytes which
+        // are no thumbnail.
+        let truncated = &bytes[..bytes.len() / 2];
+        assert_eq!(
+            MachineState::decode(truncated),
+            Err(SaveStateError::Corrupt),
+            "version {}",
+            version
+        );
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert_eq!(
+            MachineState::decode(&extended),
+            Err(SaveStateError::Corrupt),
+            "version {}",
+            version
+        );
+    }
+    assert_eq!(MachineState::decode(b"C8S"), Err(SaveStateError::Corrupt));
+    assert_eq!(
+        MachineState::decode(b"PNG"),
+        Err(SaveStateError::NotASaveState)
+    );
+
+    // An unknown variant byte.
+    let mut bytes = expected(VERSION).encode();
+    bytes[4 + 57] = 9;
+    assert_eq!(MachineState::decode(&bytes), Err(SaveStateError::Corrupt));
+
+    // A failed load leaves the machine alone.
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&FIXTURE_ROM).unwrap();
+    let hash = chip8.state_hash();
+    assert!(chip8.load_state(&bytes).is_err());
+    assert_eq!(chip8.state_hash(), hash);
+}
+
+#[test]
+fn states_round_trip_their_thumbnail() {
+    let mut chip8 = Chip8::new();
+    // LD F, V0; DRW V0, V0, 5; JP 0x204
+    chip8
+        .load_rom(&[0xF0, 0x29, 0xD0, 0x05, 0x12, 0x04])
+        .unwrap();
+    chip8.run_frame(3);
+    let thumbnail = MachineState::decode(&chip8.save_state())
+        .unwrap()
+        .thumbnail
+        .unwrap();
+    assert_eq!((thumbnail.width, thumbnail.height), (16, 8));
+    // The 0 digit: 10 of the 16 pixels of the top left block, the bottom
+    // row of the next block down.
+    assert_eq!(thumbnail.pixels[0], (10 * 255 / 16) as u8);
+    assert_eq!(thumbnail.pixels[16], (4 * 255 / 16) as u8);
+    assert!(thumbnail.pixels[1..16].iter().all(|&gray| gray == 0));
+
+    // Restored and saved again, the state keeps the same thumbnail.
+    let mut restored = Chip8::new();
+    restored.load_state(&chip8.save_state()).unwrap();
+    let state = MachineState::decode(&restored.save_state()).unwrap();
+    assert_eq!(state.thumbnail, Some(thumbnail));
+}
+
+#[test]
+fn thumbnails_average_blocks_of_pixels() {
+    let screen = [
+        true, true, false, true, false, //
+        true, true, false, false, false, //
+        false, false, false, false, true,
+    ];
+    // 2x2 blocks, with partial ones at the right and bottom edges.
+    let thumbnail = Thumbnail::downscale(&screen, 5, 3, 2);
+    assert_eq!(
+        thumbnail,
+        Thumbnail {
+            width: 3,
+            height: 2,
+            pixels: vec![255, 255 / 4, 0, 0, 0, 255],
+        }
+    );
+    assert_eq!(thumbnail.to_rgb()[3..6], [63, 63, 63]);
+
+    // Factor 1 keeps the screen as it is.
+    let thumbnail = Thumbnail::downscale(&screen, 5, 3, 1);
+    assert_eq!((thumbnail.width, thumbnail.height), (5, 3));
+    let lit: Vec<bool> = thumbnail.pixels.iter().map(|&gray| gray == 255).collect();
+    assert_eq!(lit, screen);
+}
@@ -0,0 +1,46 @@
+use std::fs;
+use std::path::Path;
+
+use chip_8_rs::scenario::Scenario;
+
+// Run every scenario in tests/scenarios and report all failures at once.
+#[test]
+fn scenarios() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios");
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .expect("tests/scenarios exists")
+        .map(|entry| entry.expect("readable entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "scenario"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no scenarios found in {}", dir.display());
+
+    let failures: Vec<String> = paths
+        .iter()
+        .filter_map(|path| {
+            Scenario::load(path)
+                .and_then(|scenario| scenario.run())
+                .err()
+                .map(|e| format!("{}: {}", path.display(), e))
+        })
+        .collect();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn screen_mismatches_show_the_screen() {
+    // LD I, 0x206; DRW V0, V0, 1; JP 0x204; 0x80
+    let rom = [0xA2, 0x06, 0xD0, 0x01, 0x12, 0x04, 0x80];
+    let scenario = Scenario::parse("rom test.ch8\nat 1 screen blank.txt", |name| {
+        Ok(match name {
+            "test.ch8" => rom.to_vec(),
+            _ => format!("{}\n", ".".repeat(64)).repeat(32).into_bytes(),
+        })
+    })
+    .unwrap();
+    let error = scenario.run().unwrap_err().to_string();
+    let mut lines = error.lines();
+    assert!(lines.next().unwrap().starts_with("frame 1: screen 0x"));
+    assert_eq!(lines.next().unwrap(), format!("#{}", ".".repeat(63)));
+    assert_eq!(lines.count(), 31);
+}
@@ -0,0 +1,112 @@
+use chip_8_rs::display::{HIRES_HEIGHT, HIRES_WIDTH, WIDTH};
+use chip_8_rs::{asm, Chip8, Register, StepResult};
+
+fn assemble(source: &str) -> Chip8 {
+    let assembly = asm::assemble(source, "test.s", |name| Err(name.to_string())).unwrap();
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&assembly.rom).unwrap();
+    chip8
+}
+
+fn run(chip8: &mut Chip8, steps: usize) {
+    for _ in 0..steps {
+        chip8.step();
+    }
+}
+
+#[test]
+fn high_resolution_and_large_sprites() {
+    let mut chip8 = assemble(
+        "high
+         ld v0, 120
+         ld v1, 60
+         ld i, square
+         drw v0, v1, 0
+         drw v0, v1, 0
+         low
+    square:
+         dw 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF
+         dw 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF, 0xFFFF",
+    );
+    run(&mut chip8, 5);
+    let display = chip8.display();
+    assert!(display.is_hires());
+    assert_eq!(chip8.framebuffer().len(), HIRES_WIDTH * HIRES_HEIGHT);
+    assert_eq!(chip8.framebuffer().iter().filter(|&&on| on).count(), 256);
+    // The sprite wraps around both edges.
+    assert!(display.pixel(127, 63) && display.pixel(0, 0) && display.pixel(7, 11));
+    assert!(!display.pixel(8, 12));
+    assert_eq!(chip8.register(Register::V(0xF)), 0);
+
+    run(&mut chip8, 1);
+    assert_eq!(chip8.register(Register::V(0xF)), 1);
+    run(&mut chip8, 1);
+    assert!(!chip8.display().is_hires());
+    assert_eq!(chip8.framebuffer().len(), 64 * 32);
+}
+
+#[test]
+fn scrolling() {
+    let mut chip8 = assemble(
+        "ld i, dot
+         drw v0, v0, 1
+         scd 3
+         scr
+         scr
+         scl
+    dot: db 0x80",
+    );
+    run(&mut chip8, 3);
+    assert!(chip8.display().pixel(0, 3));
+    run(&mut chip8, 2);
+    assert!(chip8.display().pixel(8, 3));
+    run(&mut chip8, 1);
+    assert_eq!(
+        chip8.framebuffer().iter().position(|&on| on),
+        Some(3 * WIDTH + 4)
+    );
+}
+
+#[test]
+fn large_digits() {
+    let mut chip8 = assemble("ld v0, 8\nld hf, v0\ndrw v1, v1, 10");
+    run(&mut chip8, 3);
+    let rows: Vec<u8> = (0..10)
+        .map(|y| (0..8).fold(0, |row, x| row << 1 | chip8.display().pixel(x, y) as u8))
+        .collect();
+    assert_eq!(
+        rows,
+        [0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF]
+    );
+}
+
+#[test]
+fn exit_halts_without_a_fault() {
+    let mut chip8 = assemble("exit\nld v0, 1");
+    assert_eq!(chip8.step(), StepResult::Exited);
+    assert!(chip8.is_halted());
+    assert_eq!(chip8.fault(), None);
+    assert_eq!(chip8.step(), StepResult::Exited);
+    assert_eq!(chip8.register(Register::V(0)), 0);
+}
+
+#[test]
+fn save_states_keep_the_resolution_and_flags() {
+    let mut chip8 = assemble(
+        "high
+         ld v0, 0x42
+         ld r, v0
+         ld i, dot
+         drw v1, v1, 1
+    dot: db 0x80",
+    );
+    run(&mut chip8, 5);
+    let state = chip8.save_state();
+
+    let mut restored = Chip8::new();
+    restored.load_state(&state).unwrap();
+    assert!(restored.display().is_hires());
+    assert_eq!(restored.framebuffer(), chip8.framebuffer());
+    assert_eq!(restored.state_hash(), chip8.state_hash());
+    assert_eq!(restored.snapshot().rpl_flags[0], 0x42);
+}
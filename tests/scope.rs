@@ -0,0 +1,97 @@
+use chip_8_rs::audio::{AudioConfig, Mixer, Voice};
+use chip_8_rs::scope::Oscilloscope;
+
+// A square wave of `period` samples, starting `phase` samples into it.
+fn square(period: usize, phase: usize, len: usize) -> Vec<f32> {
+    (phase..phase + len)
+        .map(|i| if i % period < period / 2 { 0.5 } else { -0.5 })
+        .collect()
+}
+
+#[test]
+fn periodic_waves_stand_still() {
+    let mut scope = Oscilloscope::new(64);
+    scope.feed(&square(20, 7, 200));
+    let first = scope.trace();
+    assert_eq!(first.len(), 64);
+    // The window starts on the rising edge.
+    assert_eq!(first[0], 0.5);
+
+    // Feeding a part of a period more moves the wave along, but the
+    // trigger brings the window back to the same phase.
+    scope.feed(&square(20, 207, 13));
+    assert_eq!(scope.trace(), first);
+}
+
+#[test]
+fn latest_samples_show_without_a_trigger() {
+    let mut scope = Oscilloscope::new(4);
+    scope.feed(&[0.0; 6]);
+    scope.feed(&[-0.1, -0.2, -0.3, -0.4]);
+    assert_eq!(scope.trace(), [-0.1, -0.2, -0.3, -0.4]);
+
+    // Fewer samples than the window are shown as they are.
+    let mut scope = Oscilloscope::new(8);
+    scope.feed(&[0.25, 0.5]);
+    assert_eq!(scope.trace(), [0.25, 0.5]);
+}
+
+#[test]
+fn history_is_kept_to_two_windows() {
+    let mut scope = Oscilloscope::new(4);
+    // A rising edge, pushed out by the samples which follow it.
+    scope.feed(&[-1.0, 1.0]);
+    scope.feed(&[-0.5; 8]);
+    assert_eq!(scope.trace(), [-0.5; 4]);
+}
+
+#[test]
+fn mixer_output_triggers() {
+    let mut mixer = Mixer::new(AudioConfig::default());
+    mixer.set_voice(Voice::Pattern {
+        bits: [0xF0; 16],
+        pitch: 64,
+    });
+    let mut scope = Oscilloscope::new(256);
+    let mut samples = Vec::new();
+    for _ in 0..4 {
+        samples.clear();
+        mixer.render_frame(true, &mut samples);
+        scope.feed(&samples);
+    }
+    let trace = scope.trace();
+    assert_eq!(trace.len(), 256);
+    assert!(trace[0] > 0.0);
+    assert!(trace.iter().any(|&sample| sample < 0.0));
+}
+
+#[test]
+fn traces_render_as_images() {
+    let (width, height) = (16, 9);
+    let pixel = |rgb: &[u8], x: usize, y: usize| {
+        let i = (y * width + x) * 3;
+        [rgb[i], rgb[i + 1], rgb[i + 2]]
+    };
+
+    // Only the center line without samples.
+    let scope = Oscilloscope::new(16);
+    let rgb = scope.to_rgb(width as u32, height as u32);
+    assert_eq!(rgb.len(), width * height * 3);
+    assert_ne!(pixel(&rgb, 0, 4), [0; 3]);
+    assert_eq!(pixel(&rgb, 0, 0), [0; 3]);
+
+    // Full scale reaches the top and bottom rows, joined by an edge.
+    let mut scope = Oscilloscope::new(16);
+    scope.feed(&[-1.0; 4]);
+    scope.feed(&[1.0; 8]);
+    scope.feed(&[-1.0; 8]);
+    let rgb = scope.to_rgb(width as u32, height as u32);
+    let trace = pixel(&rgb, 0, 0);
+    assert_ne!(trace, pixel(&rgb, 0, 4));
+    assert_eq!(pixel(&rgb, 7, 0), trace);
+    assert_eq!(pixel(&rgb, 8, 8), trace);
+    assert!((0..height).all(|y| pixel(&rgb, 8, y) == trace));
+    assert_eq!(pixel(&rgb, 7, 8), [0; 3]);
+
+    assert!(scope.to_rgb(0, 0).is_empty());
+}
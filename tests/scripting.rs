@@ -0,0 +1,115 @@
+use chip_8_rs::scripting::Script;
+use chip_8_rs::{asm, Chip8, Register};
+
+fn chip8(source: &str) -> Chip8 {
+    let assembly = asm::assemble(source, "test.s", |name| Err(name.to_string())).unwrap();
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&assembly.rom).unwrap();
+    chip8
+}
+
+// Counts V0 up and stores it at 0x300, forever.
+const COUNTER: &str = "
+loop:
+    add v0, 1
+    ld i, 0x300
+    ld [i], v0
+    jp loop";
+
+#[test]
+fn frame_hooks_read_and_write_the_machine() {
+    let mut chip8 = chip8(COUNTER);
+    let mut script = Script::new(
+        r#"
+        fn on_frame(frame) {
+            if frame == 2 {
+                print(`V0 = ${reg("V0")}, [0x300] = ${peek(0x300)}`);
+                set_reg("V0", 100);
+                poke(0x301, 0xAB);
+                set_key(5, true);
+            }
+        }"#,
+    )
+    .unwrap();
+    script.run_frame(&mut chip8, 8).unwrap();
+    assert!(script.take_log().is_empty());
+    script.run_frame(&mut chip8, 8).unwrap();
+    assert_eq!(script.take_log(), ["V0 = 4, [0x300] = 4"]);
+    assert_eq!(chip8.register(Register::V(0)), 100);
+    assert_eq!(chip8.memory()[0x301], 0xAB);
+    assert!(chip8.keys()[5]);
+    assert!(script.take_log().is_empty());
+}
+
+#[test]
+fn instruction_and_write_hooks_see_every_step() {
+    let mut chip8 = chip8(COUNTER);
+    let mut script = Script::new(
+        r#"
+        fn on_instruction(pc, opcode) {
+            this.steps = (this.steps ?? 0) + 1;
+            if opcode == 0xF055 { this.stores = (this.stores ?? 0) + 1; }
+        }
+        fn on_write(address, before, after) {
+            print(`${address} ${before} -> ${after}`);
+        }
+        fn on_frame(frame) {
+            print(`${this.steps} steps, ${this.stores} stores`);
+        }"#,
+    )
+    .unwrap();
+    script.run_frame(&mut chip8, 8).unwrap();
+    assert_eq!(
+        script.take_log(),
+        ["768 0 -> 1", "768 1 -> 2", "8 steps, 2 stores"]
+    );
+    assert_eq!(chip8.telemetry().frames, 1);
+}
+
+#[test]
+fn scripts_stop_the_run() {
+    let mut chip8 = chip8(COUNTER);
+    let mut script = Script::new(
+        r#"
+        fn on_instruction(pc, opcode) {
+            if reg("V0") == 3 { stop(); }
+        }"#,
+    )
+    .unwrap();
+    assert!(!script.is_stopped());
+    script.run_frame(&mut chip8, 100).unwrap();
+    assert!(script.is_stopped());
+    assert_eq!(chip8.register(Register::V(0)), 3);
+    assert_eq!(chip8.register(Register::PC), 0x202);
+}
+
+#[test]
+fn errors_are_reported() {
+    assert!(Script::new("fn on_frame(frame) {").is_err());
+    assert!(Script::new("throw \"at load\";").is_err());
+
+    let mut chip8 = chip8(COUNTER);
+    for (source, message) in [
+        (
+            "fn on_frame(frame) { poke(0x10000, 1); }",
+            "outside of memory",
+        ),
+        ("fn on_frame(frame) { poke(0x300, 256); }", "not a byte"),
+        (
+            "fn on_frame(frame) { set_reg(\"I\", 0x1000); }",
+            "does not fit",
+        ),
+        ("fn on_frame(frame) { reg(\"VG\"); }", "reg:"),
+        ("fn on_frame(frame) { set_key(16, true); }", "not a key"),
+        ("fn on_frame(frame) { loop {} }", "operations"),
+    ] {
+        let mut script = Script::new(source).unwrap();
+        let error = script.run_frame(&mut chip8, 1).unwrap_err();
+        assert!(error.message.contains(message), "{}", error);
+    }
+
+    // Changes made before the error still apply.
+    let mut script = Script::new("fn on_frame(frame) { poke(0x302, 7); throw \"oops\"; }").unwrap();
+    assert!(script.run_frame(&mut chip8, 1).is_err());
+    assert_eq!(chip8.memory()[0x302], 7);
+}
@@ -0,0 +1,33 @@
+use chip_8_rs::backend::Interpreter;
+use chip_8_rs::blocks::BlockTranslator;
+use chip_8_rs::cached::CachedInterpreter;
+use chip_8_rs::cpu::Chip8;
+use chip_8_rs::selftest;
+
+// Every case passes under every combination of quirks.
+#[test]
+fn interpreter_passes_every_case() {
+    let report = selftest::run(|| Chip8::with_backend(Box::new(Interpreter)));
+    assert_eq!(report.failed(), 0, "\n{}", report);
+}
+
+// Every backend must pass and fail the same self-test cases, with the same
+// symptoms.
+#[test]
+fn backends_agree() {
+    let interpreter = selftest::run(|| Chip8::with_backend(Box::new(Interpreter)));
+    let blocks = selftest::run(|| Chip8::with_backend(Box::new(BlockTranslator::new())));
+    let cached = selftest::run(|| Chip8::with_backend(Box::new(CachedInterpreter::new())));
+    let failures: Vec<String> = [blocks, cached]
+        .iter()
+        .flat_map(|report| interpreter.outcomes.iter().zip(&report.outcomes))
+        .filter(|(a, b)| a.failure != b.failure)
+        .map(|(a, b)| {
+            format!(
+                "{} {} (quirks: {}): {:?} vs {:?}",
+                a.case.instruction, a.case.name, a.quirks, a.failure, b.failure
+            )
+        })
+        .collect();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
@@ -0,0 +1,156 @@
+use chip_8_rs::sprites::{self, Sprite};
+use chip_8_rs::Chip8;
+
+// The digit 0, and a 16x16 SUPER-CHIP box.
+const DIGIT: [u8; 5] = [0xF0, 0x90, 0x90, 0x90, 0xF0];
+const BOX: [u8; 32] = [
+    0xFF, 0xFF, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01,
+    0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0x80, 0x01, 0xFF, 0xFF,
+];
+
+// Draws the digit at 0x20E and the box at 0x213, with a pair of LD I and
+// DRW which a jump keeps apart.
+fn rom() -> Vec<u8> {
+    let mut rom = vec![
+        0xA2, 0x0E, // LD I, 0x20E
+        0xD0, 0x15, // DRW V0, V1, 5
+        0xA2, 0x13, // LD I, 0x213
+        0x60, 0x05, // LD V0, 5
+        0xD0, 0x10, // DRW V0, V1, 0
+        0xA2, 0x0E, // LD I, 0x20E
+        0xD0, 0x13, // DRW V0, V1, 3
+    ];
+    rom.extend(DIGIT);
+    rom.extend(BOX);
+    rom.extend([
+        0xA2, 0x1F, // LD I, 0x21F
+        0x12, 0x00, // JP 0x200
+        0xD0, 0x11, // DRW V0, V1, 1
+    ]);
+    rom
+}
+
+#[test]
+fn sprites_are_found_where_they_are_drawn() {
+    assert_eq!(
+        sprites::scan(&rom(), 0x200),
+        [
+            // The larger of the two draws of the digit
+            Sprite {
+                address: 0x20E,
+                width: 8,
+                data: DIGIT.to_vec(),
+            },
+            Sprite {
+                address: 0x213,
+                width: 16,
+                data: BOX.to_vec(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn sprites_outside_the_rom_are_skipped() {
+    // Loaded elsewhere, the addresses point past the ROM's data.
+    assert!(sprites::scan(&rom(), 0x600).is_empty());
+
+    // LD I, 0x300; DRW V0, V1, 5
+    assert!(sprites::scan(&[0xA3, 0x00, 0xD0, 0x15], 0x200).is_empty());
+}
+
+#[test]
+fn draws_too_far_from_ld_i_are_not_paired() {
+    // LD I, 0x214; eight times LD V0, 0; DRW V0, V1, 1; and a sprite
+    let mut rom = vec![0xA2, 0x14];
+    rom.extend([0x60, 0x00].repeat(8));
+    rom.extend([0xD0, 0x11, 0xFF]);
+    assert!(sprites::scan(&rom, 0x200).is_empty());
+
+    // One instruction less and the pair is close enough.
+    let mut rom = vec![0xA2, 0x12];
+    rom.extend([0x60, 0x00].repeat(7));
+    rom.extend([0xD0, 0x11, 0xFF]);
+    assert_eq!(sprites::scan(&rom, 0x200)[0].data, [0xFF]);
+}
+
+#[test]
+fn sheets_lay_sprites_out_on_a_grid() {
+    let found = sprites::scan(&rom(), 0x200);
+    let pbm = sprites::sheet_pbm(&found, 2);
+    let mut lines = pbm.lines();
+    assert_eq!(lines.next(), Some("P1"));
+    assert_eq!(lines.next(), Some("34 17"));
+    let rows: Vec<Vec<&str>> = lines.map(|line| line.split(' ').collect()).collect();
+    assert_eq!(rows.len(), 17);
+    // The top rows of the digit and of the box, one cell to the right.
+    assert_eq!(rows[0][0..8].join(""), "11110000");
+    assert_eq!(rows[0][17..33].join(""), "1".repeat(16));
+    assert_eq!(rows[1][17..33].join(""), "1000000000000001");
+    // The gutter stays clear.
+    assert!(rows.iter().all(|row| row[16] == "0" && row[33] == "0"));
+}
+
+#[test]
+fn blank_sprites_are_8_or_16_pixels_wide() {
+    let sprite = Sprite::blank(0x300, 8, 5);
+    assert_eq!((sprite.width, sprite.height()), (8, 5));
+    assert_eq!(sprite.data, [0; 5]);
+
+    // Anything wider is a SUPER-CHIP sprite, two bytes per row.
+    let sprite = Sprite::blank(0x300, 12, 16);
+    assert_eq!((sprite.width, sprite.height()), (16, 16));
+    assert_eq!(sprite.data.len(), 32);
+}
+
+#[test]
+fn pixels_are_edited_one_at_a_time() {
+    let mut sprite = Sprite::blank(0x300, 16, 2);
+    sprite.set_pixel(0, 0, true);
+    sprite.set_pixel(9, 1, true);
+    sprite.toggle_pixel(15, 0);
+    assert_eq!(sprite.data, [0x80, 0x01, 0x00, 0x40]);
+    assert!(sprite.pixel(9, 1));
+    assert!(!sprite.pixel(8, 1));
+
+    sprite.toggle_pixel(0, 0);
+    sprite.set_pixel(9, 1, false);
+    assert_eq!(sprite.data, [0x00, 0x01, 0x00, 0x00]);
+    assert_eq!(sprite.to_ascii(), "...............#\n................\n");
+}
+
+#[test]
+fn sprites_export_as_db_lines() {
+    let sprite = Sprite {
+        address: 0x300,
+        width: 8,
+        data: vec![0x3C, 0x42],
+    };
+    assert_eq!(
+        sprite.to_db("ball"),
+        "ball:\n    db 0b00111100\n    db 0b01000010\n"
+    );
+
+    let mut wide = Sprite::blank(0x300, 16, 1);
+    wide.set_pixel(8, 0, true);
+    assert_eq!(wide.to_db("wide"), "wide:\n    db 0b00000000, 0b10000000\n");
+}
+
+#[test]
+fn sprites_write_into_memory() {
+    let mut chip8 = Chip8::new();
+    let mut sprite = Sprite::blank(0x300, 8, 2);
+    sprite.set_pixel(7, 1, true);
+    assert!(sprite.write_to(&mut chip8));
+    assert_eq!(&chip8.memory()[0x300..0x302], &[0x00, 0x01]);
+
+    // Nothing is written unless the whole sprite fits.
+    let end = chip8.memory().len() as u16;
+    let sprite = Sprite {
+        address: end - 1,
+        width: 8,
+        data: vec![0xFF, 0xFF],
+    };
+    assert!(!sprite.write_to(&mut chip8));
+    assert_eq!(chip8.memory()[end as usize - 1], 0);
+}
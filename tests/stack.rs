@@ -0,0 +1,92 @@
+use chip_8_rs::fault::{Check, FaultPolicy};
+use chip_8_rs::{Chip8, Chip8Error, EmulationMode, Register, StepResult};
+
+fn machine(rom: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(rom).unwrap();
+    chip8
+}
+
+#[test]
+fn subroutines_return_after_the_call() {
+    // CALL 0x208; LD V1, 1; CLS; EXIT; LD V0, 7; CALL 0x20E; RET; RET
+    let mut chip8 = machine(&[
+        0x22, 0x08, 0x61, 0x01, 0x00, 0xE0, 0x00, 0xFD, 0x60, 0x07, 0x22, 0x0E, 0x00, 0xEE, 0x00,
+        0xEE,
+    ]);
+    chip8.step();
+    assert_eq!(chip8.register(Register::SP), 1);
+    assert_eq!(chip8.register(Register::PC), 0x208);
+    chip8.run_frame(10);
+    assert!(chip8.is_halted());
+    assert_eq!(chip8.fault(), None);
+    assert_eq!(chip8.register(Register::V(0)), 7);
+    assert_eq!(chip8.register(Register::V(1)), 1);
+    assert_eq!(chip8.register(Register::SP), 0);
+}
+
+#[test]
+fn the_stack_holds_sixteen_calls() {
+    // LD V0, 1; ADD V0, 1; CALL 0x202
+    let mut chip8 = machine(&[0x60, 0x00, 0x70, 0x01, 0x22, 0x02]);
+    chip8.set_mode(EmulationMode::Strict);
+    chip8.run_frame(1 + 16 * 2);
+    assert_eq!(chip8.register(Register::SP), 16);
+    assert_eq!(chip8.fault(), None);
+    chip8.run_frame(2);
+    let fault = chip8.fault().unwrap();
+    assert_eq!(fault.kind, Chip8Error::StackOverflow);
+    assert_eq!(fault.program_counter, 0x206);
+    assert!(chip8.is_halted());
+    assert_eq!(chip8.register(Register::V(0)), 17);
+    assert_eq!(chip8.register(Register::SP), 16);
+
+    // Continuing skips the call, leaving the stack as it is.
+    let mut chip8 = machine(&[0x60, 0x00, 0x70, 0x01, 0x22, 0x02]);
+    chip8.set_check(Check::Stack, FaultPolicy::Continue);
+    chip8.run_frame(1 + 17 * 2);
+    assert_eq!(chip8.fault().unwrap().kind, Chip8Error::StackOverflow);
+    assert!(!chip8.is_halted());
+    assert_eq!(chip8.register(Register::SP), 16);
+    assert_eq!(chip8.register(Register::PC), 0x206);
+}
+
+#[test]
+fn returning_without_a_call_underflows() {
+    // RET; LD V0, 1
+    let mut chip8 = machine(&[0x00, 0xEE, 0x60, 0x01]);
+    let StepResult::Error(fault) = chip8.step() else {
+        panic!("RET with an empty stack");
+    };
+    assert_eq!(fault.kind, Chip8Error::StackUnderflow);
+    assert_eq!(chip8.register(Register::SP), 0);
+    // Permissive machines carry on after the RET.
+    assert_eq!(chip8.step(), StepResult::Normal);
+    assert_eq!(chip8.register(Register::V(0)), 1);
+}
+
+#[test]
+fn old_save_states_move_their_stack() {
+    // CALL 0x204; LD V0, 1; RET
+    let mut chip8 = machine(&[0x22, 0x04, 0x60, 0x01, 0x00, 0xEE]);
+    chip8.step();
+    let state = chip8.save_state();
+
+    // Version 6 kept the first address in the second entry.
+    let mut old = state.clone();
+    old[3] = 6;
+    let stack = 4 + 23;
+    assert_eq!(old[stack..stack + 2], [0x02, 0x02]);
+    old.copy_within(stack..stack + 30, stack + 2);
+    old[stack..stack + 2].fill(0);
+    let mut migrated = Chip8::new();
+    migrated.load_state(&old).unwrap();
+    assert_eq!(migrated.save_state(), state);
+    migrated.run_frame(1);
+    assert_eq!(migrated.register(Register::PC), 0x202);
+    assert_eq!(migrated.register(Register::SP), 0);
+
+    let mut corrupt = state;
+    corrupt[4 + 22] = 17;
+    assert!(Chip8::new().load_state(&corrupt).is_err());
+}
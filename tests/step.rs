@@ -0,0 +1,68 @@
+use chip_8_rs::fault::Chip8Error;
+use chip_8_rs::{Chip8, EmulationMode, Register, StepResult};
+
+#[test]
+fn step_results() {
+    // LD V0, 0x2A; LD V1, K; 0xFFFF
+    let mut chip8 = Chip8::new();
+    chip8
+        .load_rom(&[0x60, 0x2A, 0xF1, 0x0A, 0xFF, 0xFF])
+        .unwrap();
+    assert_eq!(chip8.step(), StepResult::Normal);
+    assert_eq!(chip8.register(Register::PC), 0x202);
+
+    assert_eq!(chip8.step(), StepResult::WaitingForKey);
+    chip8.set_key(0x3, true);
+    assert_eq!(chip8.step(), StepResult::WaitingForKey);
+    chip8.set_key(0x3, false);
+    assert_eq!(chip8.step(), StepResult::Normal);
+    assert_eq!(chip8.register(Register::V(1)), 0x3);
+
+    let StepResult::Error(fault) = chip8.step() else {
+        panic!("0xFFFF is not an instruction");
+    };
+    assert_eq!(fault.kind, Chip8Error::InvalidOpcode(0xFFFF));
+    assert!(!chip8.is_halted());
+}
+
+#[test]
+fn halted_machines_report_their_fault() {
+    let mut chip8 = Chip8::new();
+    chip8.set_mode(EmulationMode::Strict);
+    chip8.load_rom(&[0xFF, 0xFF]).unwrap();
+    let first = chip8.step();
+    assert!(matches!(first, StepResult::Error(_)));
+    assert!(chip8.is_halted());
+    assert_eq!(chip8.step(), first);
+    assert_eq!(chip8.register(Register::PC), 0x202);
+}
+
+#[test]
+fn execute_returns_the_fault() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&[0x00, 0xE0]).unwrap();
+    assert_eq!(chip8.execute(0x6005), Ok(()));
+    let fault = chip8.execute(0xE0FF).unwrap_err();
+    assert_eq!(fault.kind, Chip8Error::InvalidOpcode(0xE0FF));
+    assert_eq!(fault.to_string(), "unknown opcode 0xE0FF (PC 0x200)");
+
+    // Reads past the end of memory fail instead of panicking.
+    chip8.set_register(Register::I, 0xFFF).unwrap();
+    let fault = chip8.execute(0xF165).unwrap_err();
+    assert!(matches!(fault.kind, Chip8Error::MemoryOutOfBounds { .. }));
+}
+
+#[test]
+fn add_to_i_adds_vx_to_i() {
+    let mut chip8 = Chip8::new();
+    // LD I, 0x300; LD V0, 5; ADD I, V0
+    chip8
+        .load_rom(&[0xA3, 0x00, 0x60, 0x05, 0xF0, 0x1E])
+        .unwrap();
+    for _ in 0..3 {
+        chip8.step();
+    }
+    assert_eq!(chip8.register(Register::I), 0x305);
+    assert_eq!(chip8.memory()[0x300], 0);
+    assert_eq!(chip8.fault(), None);
+}
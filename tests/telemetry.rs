@@ -0,0 +1,70 @@
+use std::thread;
+use std::time::Duration;
+
+use chip_8_rs::{Chip8, EmulationMode};
+
+// LD I, 0x050; DRW V0, V0, 5 twice, the second colliding with the first;
+// LD V0, 2; LD ST, V0; 0xFFFF; JP 0x20C
+const PROGRAM: [u8; 14] = [
+    0xA0, 0x50, 0xD0, 0x05, 0xD0, 0x05, 0x60, 0x02, 0xF0, 0x18, 0xFF, 0xFF, 0x12, 0x0C,
+];
+
+#[test]
+fn runs_are_counted() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&PROGRAM).unwrap();
+    for _ in 0..3 {
+        chip8.run_frame(10);
+    }
+    let telemetry = chip8.telemetry();
+    assert_eq!(telemetry.frames, 3);
+    assert_eq!(telemetry.instructions, 30);
+    assert_eq!(telemetry.draw_calls, 2);
+    assert_eq!(telemetry.collisions, 1);
+    assert_eq!(telemetry.sound_activations, 1);
+    // The invalid opcode, which the program carried on after.
+    assert_eq!(telemetry.errors, 1);
+    assert!(!chip8.is_halted());
+
+    assert_eq!(
+        telemetry.to_json_at(Duration::from_millis(1500)),
+        format!(
+            concat!(
+                "{{\"version\":\"{}\",\"frames\":3,\"instructions\":30,",
+                "\"elapsed_secs\":1.500,\"ips\":20.0,\"draw_calls\":2,",
+                "\"collisions\":1,\"sound_activations\":1,\"errors\":1}}"
+            ),
+            env!("CARGO_PKG_VERSION")
+        )
+    );
+    assert!(telemetry
+        .to_json_at(Duration::ZERO)
+        .contains("\"ips\":0.0,"));
+}
+
+#[test]
+fn speed_is_measured_over_wall_time() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&PROGRAM).unwrap();
+    chip8.run_frame(10);
+    thread::sleep(Duration::from_millis(20));
+
+    let telemetry = chip8.telemetry();
+    let elapsed = telemetry.elapsed();
+    assert!(elapsed >= Duration::from_millis(20));
+    // Measured after `elapsed`, over a little more time.
+    let ips = telemetry.ips();
+    assert!(ips > 0.0 && ips <= 10.0 / elapsed.as_secs_f64());
+    assert!(telemetry.elapsed() >= elapsed);
+}
+
+#[test]
+fn halting_faults_are_counted_too() {
+    let mut chip8 = Chip8::new();
+    chip8.set_mode(EmulationMode::Strict);
+    chip8.load_rom(&PROGRAM).unwrap();
+    chip8.run_frame(10);
+    assert!(chip8.is_halted());
+    assert_eq!(chip8.telemetry().errors, 1);
+    assert_eq!(chip8.telemetry().instructions, 6);
+}
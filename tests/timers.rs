@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use chip_8_rs::timers::Timers;
+use chip_8_rs::{Chip8, Register};
+
+#[test]
+fn wall_time_ticks_at_the_timer_rate() {
+    let mut timers = Timers::new();
+    timers.delay = 200;
+    timers.sound = 2;
+    assert!(timers.is_beeping());
+
+    // One second in uneven steps, e.g. a 144Hz display with jitter.
+    let steps = [7, 6, 8, 7, 6];
+    let (mut ticks, mut elapsed) = (0, 0);
+    for step in steps.iter().cycle() {
+        let step = (*step).min(1000 - elapsed);
+        ticks += timers.advance(Duration::from_millis(step));
+        elapsed += step;
+        if elapsed == 1000 {
+            break;
+        }
+    }
+    assert_eq!(ticks, 60);
+    assert_eq!(timers.delay, 140);
+    assert!(!timers.is_beeping());
+
+    let mut pal = Timers::with_rate(50);
+    pal.delay = 100;
+    assert_eq!(pal.advance(Duration::from_secs(1)), 50);
+    assert_eq!(pal.delay, 50);
+}
+
+#[test]
+fn instructions_do_not_tick_the_timers() {
+    // LD V0, 30; LD DT, V0; JP 0x204
+    let mut chip8 = Chip8::new();
+    chip8
+        .load_rom(&[0x60, 0x1E, 0xF0, 0x15, 0x12, 0x04])
+        .unwrap();
+    for _ in 0..1000 {
+        chip8.step();
+    }
+    assert_eq!(chip8.register(Register::DT), 30);
+
+    assert_eq!(chip8.advance_timers(Duration::from_millis(250)), 15);
+    assert_eq!(chip8.register(Register::DT), 15);
+    assert_eq!(chip8.telemetry().frames, 15);
+}
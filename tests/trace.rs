@@ -0,0 +1,66 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use chip_8_rs::blocks::BlockTranslator;
+use chip_8_rs::trace::{TraceEntry, HISTORY_LEN};
+use chip_8_rs::{Chip8, EmulationMode};
+
+// LD V0, 5; LD I, 0x300; LD [I], V0; JP 0x200
+const ROM: [u8; 8] = [0x60, 0x05, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x00];
+
+#[test]
+fn sinks_see_every_instruction_and_what_it_did() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&ROM).unwrap();
+    let entries = Rc::new(RefCell::new(Vec::new()));
+    let sink = Rc::clone(&entries);
+    chip8.set_trace_sink(Box::new(move |entry: &TraceEntry| {
+        sink.borrow_mut().push(entry.to_string())
+    }));
+    chip8.run_frame(5);
+    assert_eq!(
+        *entries.borrow(),
+        [
+            "0x200: 6005  ld v0, 0x05  V0 00->05",
+            "0x202: A300  ld i, 0x300  I 000->300",
+            "0x204: F055  ld [i], v0  write 0x300 00->05",
+            "0x206: 1200  jp 0x200",
+            "0x200: 6005  ld v0, 0x05",
+        ]
+    );
+
+    chip8.clear_trace_sink();
+    chip8.run_frame(1);
+    assert_eq!(entries.borrow().len(), 5);
+}
+
+#[test]
+fn the_history_keeps_the_last_instructions() {
+    let mut chip8 = Chip8::with_backend(Box::new(BlockTranslator::new()));
+    chip8.enable_history(3);
+    chip8.load_rom(&ROM).unwrap();
+    chip8.run_frame(101);
+    let history = chip8.history().unwrap();
+    assert_eq!(history.len(), 3);
+    let pcs: Vec<u16> = history.entries().map(|entry| entry.pc).collect();
+    assert_eq!(pcs, [0x204, 0x206, 0x200]);
+    assert!(history.entries().all(|entry| entry.events.is_empty()));
+
+    chip8.disable_history();
+    assert!(chip8.history().is_none());
+}
+
+#[test]
+fn crashes_leave_the_faulting_instruction_last() {
+    // LD I, 0xFFF; LD V1, [I]
+    let mut chip8 = Chip8::new();
+    chip8.set_mode(EmulationMode::Strict);
+    chip8.enable_history(HISTORY_LEN);
+    chip8.load_rom(&[0xAF, 0xFF, 0xF1, 0x65]).unwrap();
+    chip8.run_frame(10);
+    assert!(chip8.is_halted());
+    assert_eq!(
+        chip8.history().unwrap().to_string(),
+        "0x200: AFFF  ld i, 0xFFF\n0x202: F165  ld v1, [i]\n"
+    );
+}
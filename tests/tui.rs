@@ -0,0 +1,42 @@
+use chip_8_rs::display::{Display, HEIGHT, WIDTH};
+use chip_8_rs::palette::Palette;
+use chip_8_rs::savestate::Thumbnail;
+use chip_8_rs::tui::{render, render_colors, render_thumbnail};
+
+#[test]
+fn half_blocks() {
+    let mut display = Display::new();
+    // Rows 0 and 1 fully lit at the left, only row 0 and only row 1 next.
+    display.draw_sprite(0, 0, &[0b1100_0000, 0b1010_0000]);
+    display.draw_sprite(WIDTH - 1, HEIGHT - 1, &[0x80]);
+
+    let lines = render(display.pixels(), WIDTH);
+    assert_eq!(lines.len(), HEIGHT / 2);
+    assert!(lines.iter().all(|line| line.chars().count() == WIDTH));
+    assert!(lines[0].starts_with("█▀▄ "));
+    assert!(lines[HEIGHT / 2 - 1].ends_with('▄'));
+}
+
+#[test]
+fn colored_cells() {
+    let mut display = Display::new();
+    display.draw_sprite(0, 1, &[0x80]);
+    let palette: Palette = "#000000,#FFFFFF".parse().unwrap();
+
+    let lines = render_colors(&display, &palette);
+    assert_eq!(lines.len(), HEIGHT / 2);
+    // A black upper and white lower pixel, then black on black to the end.
+    assert!(lines[0].starts_with("\x1b[38;2;0;0;0m\x1b[48;2;255;255;255m▀\x1b[38;2;0;0;0m"));
+    assert_eq!(lines[0].matches('▀').count(), WIDTH);
+    assert!(lines[0].ends_with("▀\x1b[0m"));
+}
+
+#[test]
+fn thumbnail_shades() {
+    let thumbnail = Thumbnail {
+        width: 3,
+        height: 2,
+        pixels: vec![0, 63, 255, 127, 191, 0],
+    };
+    assert_eq!(render_thumbnail(&thumbnail), ["  ░░██", "▒▒▓▓  "]);
+}
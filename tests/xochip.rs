@@ -0,0 +1,185 @@
+use chip_8_rs::audio::Voice;
+use chip_8_rs::cartridge::Variant;
+use chip_8_rs::fault::Chip8Error;
+use chip_8_rs::memory::{SIZE, XO_CHIP_SIZE};
+use chip_8_rs::{asm, Chip8, Register, StepResult};
+
+fn assemble(source: &str) -> Chip8 {
+    let assembly = asm::assemble(source, "test.s", |name| Err(name.to_string())).unwrap();
+    let mut chip8 = Chip8::new();
+    chip8.set_variant(Variant::XoChip);
+    chip8.load_rom(&assembly.rom).unwrap();
+    chip8
+}
+
+fn run(chip8: &mut Chip8, steps: usize) {
+    for _ in 0..steps {
+        chip8.step();
+    }
+}
+
+#[test]
+fn variant_sets_the_memory_size() {
+    let mut chip8 = Chip8::new();
+    assert_eq!(chip8.memory().len(), SIZE);
+    chip8.set_variant(Variant::XoChip);
+    assert_eq!(chip8.memory().len(), XO_CHIP_SIZE);
+    chip8.set_variant(Variant::SuperChip);
+    assert_eq!(chip8.memory().len(), SIZE);
+}
+
+#[test]
+fn drawing_on_planes() {
+    let mut chip8 = assemble(
+        "ld i, 0x20C
+         plane 2
+         drw v0, v0, 1
+         plane 3
+         drw v0, v0, 1
+         exit
+         db 0x80, 0x80",
+    );
+    run(&mut chip8, 3);
+    assert_eq!(chip8.display().colors()[0], 2);
+    run(&mut chip8, 2);
+    // Both planes take one row each: the first plane lights up, the second
+    // switches off again.
+    assert_eq!(chip8.display().colors()[0], 1);
+    assert_eq!(chip8.register(Register::V(0xF)), 1);
+    assert!(chip8.display().pixel(0, 0));
+}
+
+#[test]
+fn long_loads_and_skips_over_them() {
+    let mut chip8 = assemble(
+        "ld i, long 0x8000
+         ld v0, 0x42
+         ld [i], v0
+         se v0, 0x42
+         ld i, long 0x1234
+         ld v1, 1",
+    );
+    run(&mut chip8, 1);
+    assert_eq!(chip8.register(Register::I), 0x8000);
+    assert_eq!(chip8.register(Register::PC), 0x204);
+    run(&mut chip8, 3);
+    assert_eq!(chip8.memory()[0x8000], 0x42);
+    assert_eq!(chip8.register(Register::PC), 0x20E);
+    run(&mut chip8, 1);
+    assert_eq!(chip8.register(Register::V(1)), 1);
+}
+
+#[test]
+fn scrolling_up() {
+    let mut chip8 = assemble(
+        "ld v0, 5
+         ld i, 0x208
+         drw v0, v0, 1
+         scu 3
+         db 0x80",
+    );
+    run(&mut chip8, 4);
+    assert!(chip8.display().pixel(5, 2));
+    assert_eq!(chip8.framebuffer().iter().filter(|&&on| on).count(), 1);
+}
+
+#[test]
+fn register_ranges() {
+    let mut chip8 = assemble(
+        "ld i, 0x300
+         ld v1, 1
+         ld v2, 2
+         ld v3, 3
+         ld [i], v1-v3
+         ld v6-v3, [i]",
+    );
+    run(&mut chip8, 6);
+    assert_eq!(&chip8.memory()[0x300..0x303], &[1, 2, 3]);
+    // Loading V6 down to V3 reads the bytes into V6 first.
+    let registers: Vec<u16> = (3..=6).map(|v| chip8.register(Register::V(v))).collect();
+    assert_eq!(registers, [0, 3, 2, 1]);
+    assert_eq!(chip8.register(Register::I), 0x300);
+}
+
+#[test]
+fn audio_patterns() {
+    let mut chip8 = assemble(
+        "ld i, 0x208
+         audio
+         ld v0, 100
+         pitch v0
+         dw 0xFF00, 0xFF00, 0xFF00, 0xFF00, 0xFF00, 0xFF00, 0xFF00, 0xFF00",
+    );
+    assert_eq!(chip8.voice(), Voice::Beep);
+    run(&mut chip8, 4);
+    assert_eq!(
+        chip8.voice(),
+        Voice::Pattern {
+            bits: [0xFF, 0x00].repeat(8).try_into().unwrap(),
+            pitch: 100,
+        }
+    );
+}
+
+#[test]
+fn other_variants_reject_the_extensions() {
+    let mut chip8 = Chip8::new();
+    chip8
+        .load_rom(&[0x51, 0x22, 0xF0, 0x00, 0x12, 0x34])
+        .unwrap();
+    for opcode in [0x5122, 0xF000] {
+        let StepResult::Error(fault) = chip8.step() else {
+            panic!("0x{:04X} did not fault", opcode);
+        };
+        assert_eq!(fault.kind, Chip8Error::InvalidOpcode(opcode));
+    }
+    // F000 did not take the next word as its address.
+    assert_eq!(chip8.register(Register::PC), 0x204);
+    assert_eq!(chip8.register(Register::I), 0);
+}
+
+#[test]
+fn save_states_keep_the_extensions() {
+    let mut chip8 = assemble(
+        "ld i, long 0x9000
+         ld v0, 7
+         ld [i], v0
+         plane 3
+         ld i, 0x212
+         drw v0, v0, 1
+         audio
+         db 0xC0, 0x80",
+    );
+    run(&mut chip8, 7);
+    let state = chip8.save_state();
+
+    let mut restored = Chip8::new();
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.variant(), Variant::XoChip);
+    assert_eq!(restored.memory()[0x9000], 7);
+    assert_eq!(restored.display().colors(), chip8.display().colors());
+    assert_eq!(restored.display().planes(), 3);
+    assert_eq!(restored.voice(), chip8.voice());
+    assert_eq!(restored.state_hash(), chip8.state_hash());
+}
+
+#[test]
+fn registers_hold_any_address_of_the_memory() {
+    // LD I, 0x1234 (F000 1234)
+    let mut chip8 = Chip8::new();
+    chip8.set_variant(Variant::XoChip);
+    chip8.load_rom(&[0xF0, 0x00, 0x12, 0x34]).unwrap();
+    chip8.step();
+    assert_eq!(chip8.register(Register::I), 0x1234);
+    let state = chip8.register(Register::I);
+    chip8.set_register(Register::I, 0).unwrap();
+    chip8.set_register(Register::I, state).unwrap();
+    chip8.set_register(Register::PC, 0xFFFE).unwrap();
+    assert_eq!(chip8.register(Register::PC), 0xFFFE);
+
+    // 4K machines take 12-bit addresses only.
+    chip8.set_variant(Variant::Chip8);
+    assert!(chip8.set_register(Register::I, 0x1234).is_err());
+    assert!(chip8.set_register(Register::PC, 0x1000).is_err());
+    chip8.set_register(Register::I, 0xFFF).unwrap();
+}
@@ -0,0 +1,4 @@
+//! The npm package of `chip-8-rs`: its JavaScript API, see
+//! `chip_8_rs::wasm`, built as the `cdylib` wasm-pack packages.
+
+pub use chip_8_rs::wasm::*;